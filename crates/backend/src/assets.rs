@@ -1,36 +1,288 @@
-use foxhole_shared::models::{GameMap, Weapon};
-use std::path::Path;
+use arc_swap::ArcSwap;
+use foxhole_shared::models::{GameMap, Weapon, WeaponProfile, WeaponsFile};
+use foxhole_shared::terrain::TerrainMask;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::embedded::{self, Bundle};
+
+/// The text of a read of `name` in `assets_dir`.
+fn asset_text(
+    assets_dir: &Path,
+    name: &str,
+    read: std::io::Result<Cow<'static, [u8]>>,
+) -> Result<String, String> {
+    let failed = |e: &dyn std::fmt::Display| {
+        format!("Failed to read {}: {}", assets_dir.join(name).display(), e)
+    };
+    let data = read.map_err(|e| failed(&e))?;
+    String::from_utf8(data.into_owned()).map_err(|e| failed(&e))
+}
+
+/// Read a file from `assets_dir`, falling back to the copy embedded in the binary.
+fn read_asset(assets_dir: &Path, name: &str) -> Result<String, String> {
+    asset_text(
+        assets_dir,
+        name,
+        embedded::read(assets_dir, Bundle::GameAssets, name),
+    )
+}
+
+/// Like [`read_asset`], for a file the server can do without: `None` when it
+/// is neither on disk nor embedded.
+fn read_optional_asset(assets_dir: &Path, name: &str) -> Result<Option<String>, String> {
+    match embedded::read(assets_dir, Bundle::GameAssets, name) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        read => asset_text(assets_dir, name, read).map(Some),
+    }
+}
+
+fn parse_weapons(name: &str, data: &str) -> Result<WeaponsFile, String> {
+    serde_json::from_str(data).map_err(|e| format!("Failed to parse {}: {}", name, e))
+}
 
 pub struct Assets {
     pub maps: Vec<GameMap>,
     pub weapons: Vec<Weapon>,
+    /// Game patch the weapon figures were taken from, from `weapons.json`.
+    pub game_version: Option<String>,
+    /// Upcoming figures from `weapons-devbranch.json`, if the server has them.
+    pub devbranch: Option<WeaponsFile>,
+    /// Water and impassable ground per map file name, from `masks/<fileName>.txt`.
+    /// Maps without a mask are only checked against the hex outline.
+    pub terrain_masks: HashMap<String, TerrainMask>,
 }
 
 impl Assets {
     pub fn load(assets_dir: &Path) -> Result<Self, String> {
-        let maps_path = assets_dir.join("maps.json");
-        let weapons_path = assets_dir.join("weapons.json");
-
-        let maps_data = std::fs::read_to_string(&maps_path)
-            .map_err(|e| format!("Failed to read {}: {}", maps_path.display(), e))?;
-        let weapons_data = std::fs::read_to_string(&weapons_path)
-            .map_err(|e| format!("Failed to read {}: {}", weapons_path.display(), e))?;
+        let maps_data = read_asset(assets_dir, "maps.json")?;
+        let live = WeaponProfile::Live.file_name();
+        let weapons_data = read_asset(assets_dir, live)?;
+        let devbranch_name = WeaponProfile::Devbranch.file_name();
+        let devbranch_data = read_optional_asset(assets_dir, devbranch_name)?;
 
         let maps: Vec<GameMap> = serde_json::from_str(&maps_data)
             .map_err(|e| format!("Failed to parse maps.json: {}", e))?;
-        let weapons: Vec<Weapon> = serde_json::from_str(&weapons_data)
-            .map_err(|e| format!("Failed to parse weapons.json: {}", e))?;
+        let WeaponsFile {
+            game_version,
+            weapons,
+        } = parse_weapons(live, &weapons_data)?;
+        let devbranch = devbranch_data
+            .map(|data| parse_weapons(devbranch_name, &data))
+            .transpose()?;
+        let mut terrain_masks = HashMap::new();
+        for map in &maps {
+            let name = format!("masks/{}.txt", map.file_name);
+            if let Some(data) = read_optional_asset(assets_dir, &name)? {
+                let mask = TerrainMask::parse(&data)
+                    .map_err(|e| format!("Failed to parse {}: {}", name, e))?;
+                terrain_masks.insert(map.file_name.clone(), mask);
+            }
+        }
+
+        tracing::info!(
+            maps = maps.len(),
+            weapons = weapons.len(),
+            game_version = game_version.as_deref().unwrap_or("unversioned"),
+            devbranch_weapons = devbranch.as_ref().map(|d| d.weapons.len()),
+            terrain_masks = terrain_masks.len(),
+            "Loaded game assets"
+        );
+
+        Ok(Assets {
+            maps,
+            weapons,
+            game_version,
+            devbranch,
+            terrain_masks,
+        })
+    }
 
-        tracing::info!(maps = maps.len(), weapons = weapons.len(), "Loaded game assets");
+    /// The weapons of `profile`, or `None` when the server has no figures for it.
+    pub fn weapons_of(&self, profile: WeaponProfile) -> Option<&[Weapon]> {
+        match profile {
+            WeaponProfile::Live => Some(&self.weapons),
+            WeaponProfile::Devbranch => self.devbranch.as_ref().map(|d| d.weapons.as_slice()),
+        }
+    }
 
-        Ok(Assets { maps, weapons })
+    /// Game patch of `profile`'s figures, if the file says.
+    pub fn game_version_of(&self, profile: WeaponProfile) -> Option<&str> {
+        match profile {
+            WeaponProfile::Live => self.game_version.as_deref(),
+            WeaponProfile::Devbranch => self.devbranch.as_ref()?.game_version.as_deref(),
+        }
     }
 
     pub fn find_weapon_by_slug(&self, slug: &str) -> Option<&Weapon> {
         self.weapons.iter().find(|w| w.slug() == slug)
     }
 
+    /// The weapon `slug` among `profile`'s weapons.
+    pub fn find_weapon_of(&self, profile: WeaponProfile, slug: &str) -> Option<&Weapon> {
+        self.weapons_of(profile)?.iter().find(|w| w.slug() == slug)
+    }
+
     pub fn find_map_by_file_name(&self, file_name: &str) -> Option<&GameMap> {
         self.maps.iter().find(|m| m.file_name == file_name)
     }
+
+    pub fn terrain_mask(&self, file_name: &str) -> Option<&TerrainMask> {
+        self.terrain_masks.get(file_name)
+    }
+}
+
+/// The game data the server is currently using, replaceable while it runs so
+/// new weapon stats can go live after a balance patch without a restart.
+///
+/// Readers take a snapshot with [`SharedAssets::current`] and keep it for the
+/// rest of the request, so a reload never shows them a mix of old and new data.
+pub struct SharedAssets {
+    current: ArcSwap<Assets>,
+    dir: PathBuf,
+}
+
+impl SharedAssets {
+    pub fn new(assets: Assets, dir: PathBuf) -> Self {
+        SharedAssets {
+            current: ArcSwap::from_pointee(assets),
+            dir,
+        }
+    }
+
+    pub fn current(&self) -> Arc<Assets> {
+        self.current.load_full()
+    }
+
+    /// Re-read `maps.json` and the weapon files and swap them in. On any error,
+    /// or if any list comes back empty, the data in use stays as it was.
+    pub fn reload(&self) -> Result<Arc<Assets>, String> {
+        let fresh = Assets::load(&self.dir)?;
+        if fresh.maps.is_empty() || fresh.weapons.is_empty() {
+            return Err("Refusing to load game data with no maps or no weapons".to_string());
+        }
+        if fresh
+            .devbranch
+            .as_ref()
+            .is_some_and(|d| d.weapons.is_empty())
+        {
+            return Err("Refusing to load Devbranch weapon data with no weapons".to_string());
+        }
+        let fresh = Arc::new(fresh);
+        self.current.store(fresh.clone());
+        Ok(fresh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_assets(dir: &Path, weapons: &str) {
+        let maps =
+            r#"[{"type":"webp","displayName":"Test Map","fileName":"test-map","active":true}]"#;
+        std::fs::write(dir.join("maps.json"), maps).unwrap();
+        std::fs::write(dir.join("weapons.json"), weapons).unwrap();
+    }
+
+    fn weapon_json(name: &str) -> String {
+        format!(
+            r#"[{{"faction":"Colonial","displayName":"{name}","minRange":75,"maxRange":300,"accRadius":[20,35],"windDrift":[5,15],"flightTime":[3,6]}}]"#
+        )
+    }
+
+    #[test]
+    fn test_load_reads_game_version() {
+        let dir = tempfile::tempdir().unwrap();
+        write_assets(dir.path(), &weapon_json("Mortar"));
+        assert_eq!(Assets::load(dir.path()).unwrap().game_version, None);
+
+        let versioned = format!(
+            r#"{{"gameVersion":"2026-10-18","weapons":{}}}"#,
+            weapon_json("Mortar")
+        );
+        write_assets(dir.path(), &versioned);
+        let assets = Assets::load(dir.path()).unwrap();
+        assert_eq!(assets.game_version.as_deref(), Some("2026-10-18"));
+        assert_eq!(assets.weapons[0].display_name, "Mortar");
+    }
+
+    #[test]
+    fn test_load_reads_the_devbranch_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        write_assets(dir.path(), &weapon_json("Mortar"));
+        let assets = Assets::load(dir.path()).unwrap();
+        assert!(assets.devbranch.is_none());
+        assert!(assets.weapons_of(WeaponProfile::Devbranch).is_none());
+        assert_eq!(assets.weapons_of(WeaponProfile::Live).unwrap().len(), 1);
+
+        let devbranch = format!(
+            r#"{{"gameVersion":"devbranch-63","weapons":{}}}"#,
+            weapon_json("Mortar Mk2")
+        );
+        std::fs::write(dir.path().join("weapons-devbranch.json"), devbranch).unwrap();
+        let assets = Assets::load(dir.path()).unwrap();
+        let weapons = assets.weapons_of(WeaponProfile::Devbranch).unwrap();
+        assert_eq!(weapons[0].display_name, "Mortar Mk2");
+        assert_eq!(
+            assets.game_version_of(WeaponProfile::Devbranch),
+            Some("devbranch-63")
+        );
+        assert_eq!(assets.weapons[0].display_name, "Mortar");
+
+        // A broken Devbranch file fails the load instead of going unnoticed
+        std::fs::write(dir.path().join("weapons-devbranch.json"), "{").unwrap();
+        assert!(Assets::load(dir.path()).is_err_and(|e| e.contains("weapons-devbranch.json")));
+    }
+
+    #[test]
+    fn test_load_reads_terrain_masks() {
+        let dir = tempfile::tempdir().unwrap();
+        write_assets(dir.path(), &weapon_json("Mortar"));
+        assert!(Assets::load(dir.path())
+            .unwrap()
+            .terrain_mask("test-map")
+            .is_none());
+
+        std::fs::create_dir(dir.path().join("masks")).unwrap();
+        std::fs::write(dir.path().join("masks/test-map.txt"), "..~\n..~\n").unwrap();
+        let assets = Assets::load(dir.path()).unwrap();
+        assert_eq!(
+            assets.terrain_mask("test-map").unwrap().rows(),
+            vec!["..~", "..~"]
+        );
+
+        std::fs::write(dir.path().join("masks/test-map.txt"), "..~\n.\n").unwrap();
+        assert!(Assets::load(dir.path()).is_err_and(|e| e.contains("masks/test-map.txt")));
+    }
+
+    #[test]
+    fn test_reload_swaps_in_new_data() {
+        let dir = tempfile::tempdir().unwrap();
+        write_assets(dir.path(), &weapon_json("Old Mortar"));
+        let shared = SharedAssets::new(Assets::load(dir.path()).unwrap(), dir.path().into());
+        let before = shared.current();
+
+        write_assets(dir.path(), &weapon_json("New Mortar"));
+        let reloaded = shared.reload().unwrap();
+        assert_eq!(reloaded.weapons[0].display_name, "New Mortar");
+        assert_eq!(shared.current().weapons[0].display_name, "New Mortar");
+        // Snapshots taken before the reload are unaffected
+        assert_eq!(before.weapons[0].display_name, "Old Mortar");
+    }
+
+    #[test]
+    fn test_reload_keeps_old_data_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        write_assets(dir.path(), &weapon_json("Old Mortar"));
+        let shared = SharedAssets::new(Assets::load(dir.path()).unwrap(), dir.path().into());
+
+        write_assets(dir.path(), "[{not json");
+        assert!(shared.reload().is_err_and(|e| e.contains("weapons.json")));
+        write_assets(dir.path(), "[]");
+        assert!(shared.reload().is_err());
+        assert_eq!(shared.current().weapons[0].display_name, "Old Mortar");
+    }
 }
@@ -0,0 +1,77 @@
+//! Command-line flags. Without any the server starts as usual; the maintenance
+//! commands work on the configured database and exit. redb locks the database
+//! file while it's open, so stop the server before running them.
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: foxhole-backend [--backup | --restore <snapshot>]
+
+With no flags, start the server. Settings come from config.toml and the
+environment as usual.
+
+  --backup               Write a snapshot of the database to the backup
+                         directory, prune old ones and exit
+  --restore <snapshot>   Replace the database with <snapshot> and exit. The
+                         current database is saved to the backup directory first
+  -h, --help             Show this message";
+
+/// What the process was started to do.
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Serve,
+    Backup,
+    Restore(PathBuf),
+    Help,
+}
+
+/// Parse the arguments after the program name.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+    let command = match args.next().as_deref() {
+        None => return Ok(Command::Serve),
+        Some("-h" | "--help") => Command::Help,
+        Some("--backup") => Command::Backup,
+        Some("--restore") => match args.next() {
+            Some(snapshot) if !snapshot.starts_with('-') => {
+                Command::Restore(PathBuf::from(snapshot))
+            }
+            _ => return Err("--restore needs the path of a snapshot".to_string()),
+        },
+        Some(other) => return Err(format!("Unknown argument {:?}", other)),
+    };
+    match args.next() {
+        Some(extra) => Err(format!("Unexpected argument {:?}", extra)),
+        None => Ok(command),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse_args(&[]), Ok(Command::Serve));
+        assert_eq!(parse_args(&["--help"]), Ok(Command::Help));
+        assert_eq!(parse_args(&["--backup"]), Ok(Command::Backup));
+        assert_eq!(
+            parse_args(&["--restore", "backups/plans-20261018T000000Z.redb"]),
+            Ok(Command::Restore(PathBuf::from(
+                "backups/plans-20261018T000000Z.redb"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_arguments() {
+        assert!(parse_args(&["--restore"]).is_err());
+        assert!(parse_args(&["--restore", "--backup"]).is_err());
+        assert!(parse_args(&["--backup", "now"]).is_err());
+        assert!(parse_args(&["--serve"]).is_err());
+    }
+}
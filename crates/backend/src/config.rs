@@ -0,0 +1,804 @@
+//! Server configuration: an optional `config.toml` overlaid with environment
+//! variables, validated once at startup.
+//!
+//! Every setting can come from either place; an environment variable wins over
+//! the file. See `config.example.toml` for the file layout.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use axum::http::HeaderValue;
+use serde::Deserialize;
+
+use crate::graphql::{AdminSettings, AdminToken};
+use crate::rate_limit::RateLimits;
+use crate::storage::{BackupPolicy, RetentionPolicy};
+use crate::war::WarSource;
+use crate::war_map::MapPollSettings;
+use crate::war_report::WarReportSettings;
+
+/// Read when `CONFIG_PATH` isn't set; a missing default file is fine.
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+const DEFAULT_PORT: u16 = 3000;
+const DEFAULT_DB_PATH: &str = "data/plans.redb";
+const DEFAULT_ASSETS_DIR: &str = "assets";
+/// Cross-origin callers allowed by default in debug builds: the dev servers.
+/// Release builds default to same-origin only.
+const DEV_CORS_ORIGINS: [&str; 2] = ["http://localhost:8080", "http://localhost:3000"];
+const DEFAULT_STATIC_MAX_AGE_SECS: u64 = 86_400;
+const DEFAULT_IMMUTABLE_MAX_AGE_SECS: u64 = 31_536_000;
+const DEFAULT_GRAPHQL_MAX_CONCURRENT: usize = 16;
+const DEFAULT_GRAPHQL_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_RATE_LIMIT_PLANS_PER_MIN: u32 = 10;
+const DEFAULT_RATE_LIMIT_TRACKING_PER_MIN: u32 = 120;
+const DEFAULT_CLEANUP_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_READY_MIN_FREE_DISK_MB: u64 = 100;
+const DEFAULT_BACKUP_INTERVAL_HOURS: u64 = 24;
+const DEFAULT_BACKUP_KEEP: usize = 7;
+const DEFAULT_WAR_API_URL: &str = "https://war-service-live.foxholeservices.com/api";
+const DEFAULT_WAR_POLL_INTERVAL_SECS: u64 = 600;
+const DEFAULT_WAR_MAP_POLL_INTERVAL_SECS: u64 = 120;
+
+// Layout of config.toml. Every key is optional.
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: ServerFile,
+    cache: CacheFile,
+    graphql: GraphqlFile,
+    rate_limits: RateLimitsFile,
+    retention: RetentionFile,
+    readiness: ReadinessFile,
+    admin: AdminFile,
+    backup: BackupFile,
+    war: WarFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ServerFile {
+    bind_address: Option<IpAddr>,
+    port: Option<u16>,
+    db_path: Option<PathBuf>,
+    assets_dir: Option<PathBuf>,
+    cors_origins: Option<Vec<String>>,
+    public_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct CacheFile {
+    static_max_age_secs: Option<u64>,
+    immutable_max_age_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct GraphqlFile {
+    max_concurrent: Option<usize>,
+    timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RateLimitsFile {
+    plans_per_min: Option<u32>,
+    tracking_per_min: Option<u32>,
+    trust_forwarded: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RetentionFile {
+    plan_days: Option<i64>,
+    cleanup_interval_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ReadinessFile {
+    min_free_disk_mb: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct AdminFile {
+    token: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BackupFile {
+    dir: Option<PathBuf>,
+    interval_hours: Option<u64>,
+    keep: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct WarFile {
+    number: Option<u32>,
+    api_url: Option<String>,
+    poll_interval_secs: Option<u64>,
+    map_poll_interval_secs: Option<u64>,
+    report_webhook: Option<String>,
+}
+
+/// Bounds on GraphQL execution so a burst of expensive queries waits its turn
+/// instead of piling onto the database writer and memory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphqlLimits {
+    /// Requests executing at once; the rest queue.
+    pub max_concurrent: usize,
+    /// Longest a request may take, queueing included.
+    pub timeout: Duration,
+}
+
+impl Default for GraphqlLimits {
+    fn default() -> Self {
+        GraphqlLimits {
+            max_concurrent: DEFAULT_GRAPHQL_MAX_CONCURRENT,
+            timeout: Duration::from_secs(DEFAULT_GRAPHQL_TIMEOUT_SECS),
+        }
+    }
+}
+
+/// `Cache-Control` values for the static file routes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheHeaders {
+    /// Game data and map images, which change between game updates.
+    pub static_files: HeaderValue,
+    /// Hashed frontend bundles, which never change under the same name.
+    pub immutable: HeaderValue,
+}
+
+impl CacheHeaders {
+    fn new(static_max_age_secs: u64, immutable_max_age_secs: u64) -> Self {
+        let header = |value: String| HeaderValue::try_from(value).expect("ASCII header value");
+        CacheHeaders {
+            static_files: header(format!(
+                "public, max-age={}, must-revalidate",
+                static_max_age_secs
+            )),
+            immutable: header(format!(
+                "public, max-age={}, immutable",
+                immutable_max_age_secs
+            )),
+        }
+    }
+}
+
+impl Default for CacheHeaders {
+    fn default() -> Self {
+        CacheHeaders::new(DEFAULT_STATIC_MAX_AGE_SECS, DEFAULT_IMMUTABLE_MAX_AGE_SECS)
+    }
+}
+
+/// Everything the server reads at startup.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bind_addr: SocketAddr,
+    pub db_path: PathBuf,
+    pub assets_dir: PathBuf,
+    pub cors_origins: Vec<HeaderValue>,
+    /// Scheme and host the site is served on, without a trailing slash. Link
+    /// previews use it for absolute URLs; without it they stay relative.
+    pub public_url: Option<String>,
+    pub cache: CacheHeaders,
+    pub graphql: GraphqlLimits,
+    pub rate_limits: RateLimits,
+    pub retention: RetentionPolicy,
+    /// How often expired plans are deleted and the database compacted.
+    pub cleanup_interval: Duration,
+    /// Below this much free disk space `/readyz` reports not ready.
+    pub min_free_disk_bytes: u64,
+    pub admin: AdminSettings,
+    /// Which war new plans and placement counts are filed under.
+    pub war: WarSource,
+    /// Where live structures per map come from, if anywhere.
+    pub war_maps: Option<MapPollSettings>,
+    /// Where end-of-war reports are posted, if anywhere.
+    pub war_report: Option<WarReportSettings>,
+}
+
+/// A resolved setting and where it came from, for error messages.
+struct Setting<T> {
+    value: T,
+    source: String,
+}
+
+impl<T: std::fmt::Debug> Setting<T> {
+    fn invalid(&self, expected: &str) -> String {
+        format!(
+            "Invalid {}: {:?} (expected {})",
+            self.source, self.value, expected
+        )
+    }
+}
+
+/// Look a setting up in the environment (`var`), then the file (`key`).
+fn setting<T>(
+    env: &dyn Fn(&str) -> Option<String>,
+    var: &str,
+    key: &str,
+    file_value: Option<T>,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Option<Setting<T>>, String> {
+    if let Some(raw) = env(var) {
+        let value = parse(raw.trim())
+            .ok_or_else(|| format!("Invalid {} environment variable: {:?}", var, raw))?;
+        return Ok(Some(Setting {
+            value,
+            source: var.to_string(),
+        }));
+    }
+    Ok(file_value.map(|value| Setting {
+        value,
+        source: format!("`{}` in the config file", key),
+    }))
+}
+
+fn parse_num<T: std::str::FromStr>(raw: &str) -> Option<T> {
+    raw.parse().ok()
+}
+
+fn parse_flag(raw: &str) -> Option<bool> {
+    match raw {
+        "" | "0" | "false" => Some(false),
+        "1" | "true" => Some(true),
+        _ => None,
+    }
+}
+
+/// A setting that must be greater than zero, or `default` when unset.
+fn positive<T: Copy + PartialOrd + Default + std::fmt::Debug>(
+    setting: Option<Setting<T>>,
+    default: T,
+) -> Result<T, String> {
+    match setting {
+        Some(s) if s.value > T::default() => Ok(s.value),
+        Some(s) => Err(s.invalid("a number above 0")),
+        None => Ok(default),
+    }
+}
+
+impl Config {
+    /// Load `CONFIG_PATH` (or `config.toml` if present) and apply environment overrides.
+    pub fn load() -> Result<Self, String> {
+        let explicit_path = std::env::var("CONFIG_PATH").ok();
+        let path = Path::new(explicit_path.as_deref().unwrap_or(DEFAULT_CONFIG_PATH));
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => {
+                tracing::info!(path = %path.display(), "Loaded config file");
+                Some(text)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && explicit_path.is_none() => None,
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        let result = Self::from_sources(text.as_deref(), &|var| std::env::var(var).ok());
+        match text {
+            Some(_) => result.map_err(|e| format!("{} (config file: {})", e, path.display())),
+            None => result,
+        }
+    }
+
+    /// Build the configuration from config file contents and an environment lookup.
+    fn from_sources(
+        file_text: Option<&str>,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self, String> {
+        let file: FileConfig = match file_text {
+            Some(text) => {
+                toml::from_str(text).map_err(|e| format!("Invalid config file: {}", e))?
+            }
+            None => FileConfig::default(),
+        };
+
+        let bind_ip = setting(
+            env,
+            "BIND_ADDRESS",
+            "server.bind_address",
+            file.server.bind_address,
+            parse_num,
+        )?
+        .map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |s| s.value);
+        let port = setting(env, "PORT", "server.port", file.server.port, parse_num)?
+            .map_or(DEFAULT_PORT, |s| s.value);
+        let db_path = setting(env, "DB_PATH", "server.db_path", file.server.db_path, |v| {
+            Some(PathBuf::from(v))
+        })?
+        .map_or_else(|| PathBuf::from(DEFAULT_DB_PATH), |s| s.value);
+        let assets_dir = setting(
+            env,
+            "ASSETS_DIR",
+            "server.assets_dir",
+            file.server.assets_dir,
+            |v| Some(PathBuf::from(v)),
+        )?
+        .map_or_else(|| PathBuf::from(DEFAULT_ASSETS_DIR), |s| s.value);
+
+        // CORS_ORIGIN takes a comma-separated list
+        let cors_origins = setting(
+            env,
+            "CORS_ORIGIN",
+            "server.cors_origins",
+            file.server.cors_origins,
+            |v| Some(v.split(',').map(|o| o.trim().to_string()).collect()),
+        )?;
+        let cors_origins = match cors_origins {
+            Some(s) => s
+                .value
+                .iter()
+                .filter(|o| !o.is_empty())
+                .map(|o| {
+                    o.parse::<HeaderValue>()
+                        .map_err(|_| format!("Invalid origin {:?} in {}", o, s.source))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None if cfg!(debug_assertions) => DEV_CORS_ORIGINS
+                .iter()
+                .map(|o| HeaderValue::from_static(o))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let public_url = match setting(
+            env,
+            "PUBLIC_URL",
+            "server.public_url",
+            file.server.public_url,
+            |v| Some(v.to_string()),
+        )? {
+            Some(s) if s.value.trim().is_empty() => None,
+            Some(s) => {
+                let url = s.value.trim().trim_end_matches('/');
+                if !(url.starts_with("https://") || url.starts_with("http://")) {
+                    return Err(s.invalid("an http:// or https:// URL"));
+                }
+                Some(url.to_string())
+            }
+            None => None,
+        };
+
+        let cache = CacheHeaders::new(
+            setting(
+                env,
+                "CACHE_STATIC_MAX_AGE_SECS",
+                "cache.static_max_age_secs",
+                file.cache.static_max_age_secs,
+                parse_num,
+            )?
+            .map_or(DEFAULT_STATIC_MAX_AGE_SECS, |s| s.value),
+            setting(
+                env,
+                "CACHE_IMMUTABLE_MAX_AGE_SECS",
+                "cache.immutable_max_age_secs",
+                file.cache.immutable_max_age_secs,
+                parse_num,
+            )?
+            .map_or(DEFAULT_IMMUTABLE_MAX_AGE_SECS, |s| s.value),
+        );
+
+        let graphql = GraphqlLimits {
+            max_concurrent: positive(
+                setting(
+                    env,
+                    "GRAPHQL_MAX_CONCURRENT",
+                    "graphql.max_concurrent",
+                    file.graphql.max_concurrent,
+                    parse_num,
+                )?,
+                DEFAULT_GRAPHQL_MAX_CONCURRENT,
+            )?,
+            timeout: Duration::from_secs(positive(
+                setting(
+                    env,
+                    "GRAPHQL_TIMEOUT_SECS",
+                    "graphql.timeout_secs",
+                    file.graphql.timeout_secs,
+                    parse_num,
+                )?,
+                DEFAULT_GRAPHQL_TIMEOUT_SECS,
+            )?),
+        };
+
+        let rate_limits = RateLimits {
+            plan_creations_per_min: setting(
+                env,
+                "RATE_LIMIT_PLANS_PER_MIN",
+                "rate_limits.plans_per_min",
+                file.rate_limits.plans_per_min,
+                parse_num,
+            )?
+            .map_or(DEFAULT_RATE_LIMIT_PLANS_PER_MIN, |s| s.value),
+            tracking_per_min: setting(
+                env,
+                "RATE_LIMIT_TRACKING_PER_MIN",
+                "rate_limits.tracking_per_min",
+                file.rate_limits.tracking_per_min,
+                parse_num,
+            )?
+            .map_or(DEFAULT_RATE_LIMIT_TRACKING_PER_MIN, |s| s.value),
+            trust_forwarded: setting(
+                env,
+                "RATE_LIMIT_TRUST_FORWARDED",
+                "rate_limits.trust_forwarded",
+                file.rate_limits.trust_forwarded,
+                parse_flag,
+            )?
+            .is_some_and(|s| s.value),
+        };
+
+        // Unset or 0 keeps plans forever
+        let retention_days = match setting(
+            env,
+            "PLAN_RETENTION_DAYS",
+            "retention.plan_days",
+            file.retention.plan_days,
+            parse_num,
+        )? {
+            Some(s) if s.value < 0 => return Err(s.invalid("0 or more days")),
+            Some(s) => s.value,
+            None => 0,
+        };
+        let retention = RetentionPolicy {
+            plan_ttl_secs: (retention_days > 0).then(|| retention_days * 86_400),
+        };
+        let cleanup_interval = Duration::from_secs(positive(
+            setting(
+                env,
+                "PLAN_CLEANUP_INTERVAL_SECS",
+                "retention.cleanup_interval_secs",
+                file.retention.cleanup_interval_secs,
+                parse_num,
+            )?,
+            DEFAULT_CLEANUP_INTERVAL_SECS,
+        )?);
+
+        // 0 skips the disk space check
+        let min_free_disk_mb = setting(
+            env,
+            "READY_MIN_FREE_DISK_MB",
+            "readiness.min_free_disk_mb",
+            file.readiness.min_free_disk_mb,
+            parse_num,
+        )?
+        .map_or(DEFAULT_READY_MIN_FREE_DISK_MB, |s| s.value);
+
+        let admin_token = setting(env, "ADMIN_TOKEN", "admin.token", file.admin.token, |v| {
+            Some(v.to_string())
+        })?;
+        // Backups sit next to the database unless told otherwise
+        let backup_dir = setting(env, "BACKUP_DIR", "backup.dir", file.backup.dir, |v| {
+            Some(PathBuf::from(v))
+        })?
+        .map_or_else(
+            || db_path.parent().unwrap_or(Path::new(".")).join("backups"),
+            |s| s.value,
+        );
+        // 0 turns scheduled backups off
+        let backup_interval_hours = setting(
+            env,
+            "BACKUP_INTERVAL_HOURS",
+            "backup.interval_hours",
+            file.backup.interval_hours,
+            parse_num,
+        )?
+        .map_or(DEFAULT_BACKUP_INTERVAL_HOURS, |s| s.value);
+        // 0 keeps every snapshot
+        let backup_keep = setting(
+            env,
+            "BACKUP_KEEP",
+            "backup.keep",
+            file.backup.keep,
+            parse_num,
+        )?
+        .map_or(DEFAULT_BACKUP_KEEP, |s| s.value);
+        let admin = AdminSettings {
+            token: AdminToken::new(admin_token.as_ref().map(|s| s.value.as_str())),
+            backup: BackupPolicy {
+                dir: backup_dir,
+                interval: (backup_interval_hours > 0)
+                    .then(|| Duration::from_secs(backup_interval_hours.saturating_mul(3600))),
+                keep: backup_keep,
+            },
+        };
+
+        // A pinned war number wins; an empty API URL turns polling off
+        let war_number = setting(env, "WAR_NUMBER", "war.number", file.war.number, parse_num)?;
+        let war_api_url = setting(env, "WAR_API_URL", "war.api_url", file.war.api_url, |v| {
+            Some(v.to_string())
+        })?
+        .map_or_else(|| DEFAULT_WAR_API_URL.to_string(), |s| s.value);
+        let war_poll_interval = Duration::from_secs(positive(
+            setting(
+                env,
+                "WAR_POLL_INTERVAL_SECS",
+                "war.poll_interval_secs",
+                file.war.poll_interval_secs,
+                parse_num,
+            )?,
+            DEFAULT_WAR_POLL_INTERVAL_SECS,
+        )?);
+        // 0 turns live map data off
+        let war_map_poll_interval_secs = setting(
+            env,
+            "WAR_MAP_POLL_INTERVAL_SECS",
+            "war.map_poll_interval_secs",
+            file.war.map_poll_interval_secs,
+            parse_num,
+        )?
+        .map_or(DEFAULT_WAR_MAP_POLL_INTERVAL_SECS, |s| s.value);
+        let war_maps =
+            (!war_api_url.is_empty() && war_map_poll_interval_secs > 0).then(|| MapPollSettings {
+                url: war_api_url.clone(),
+                every: Duration::from_secs(war_map_poll_interval_secs),
+            });
+        // Unset or empty turns end-of-war reports off
+        let war_report = match setting(
+            env,
+            "WAR_REPORT_WEBHOOK",
+            "war.report_webhook",
+            file.war.report_webhook,
+            |v| Some(v.to_string()),
+        )? {
+            Some(s) if !s.value.is_empty() && war_api_url.is_empty() => {
+                return Err(format!(
+                    "{} needs the War API, but its URL is empty",
+                    s.source
+                ))
+            }
+            Some(s) if !s.value.is_empty() => Some(WarReportSettings {
+                url: war_api_url.clone(),
+                webhook: s.value,
+                every: war_poll_interval,
+            }),
+            _ => None,
+        };
+        let war = match war_number {
+            Some(s) if s.value == 0 => return Err(s.invalid("a war number above 0")),
+            Some(s) => WarSource::Fixed(s.value),
+            None if war_api_url.is_empty() => WarSource::Unknown,
+            None => WarSource::Api {
+                url: war_api_url,
+                every: war_poll_interval,
+            },
+        };
+
+        Ok(Config {
+            bind_addr: SocketAddr::new(bind_ip, port),
+            db_path,
+            assets_dir,
+            cors_origins,
+            public_url,
+            cache,
+            graphql,
+            rate_limits,
+            retention,
+            cleanup_interval,
+            min_free_disk_bytes: min_free_disk_mb.saturating_mul(1024 * 1024),
+            admin,
+            war,
+            war_maps,
+            war_report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn load(file: Option<&str>, env: &[(&str, &str)]) -> Result<Config, String> {
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Config::from_sources(file, &|var| env.get(var).cloned())
+    }
+
+    #[test]
+    fn test_defaults_without_file_or_env() {
+        let config = load(None, &[]).unwrap();
+        assert_eq!(config.bind_addr, "0.0.0.0:3000".parse().unwrap());
+        assert_eq!(config.db_path, PathBuf::from("data/plans.redb"));
+        assert_eq!(config.assets_dir, PathBuf::from("assets"));
+        // Tests build with debug assertions, so the dev origins are allowed
+        assert_eq!(config.cors_origins, DEV_CORS_ORIGINS);
+        assert_eq!(config.public_url, None);
+        assert_eq!(
+            config.cache.static_files,
+            "public, max-age=86400, must-revalidate"
+        );
+        assert_eq!(
+            config.cache.immutable,
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(config.graphql, GraphqlLimits::default());
+        assert_eq!(
+            config.rate_limits.plan_creations_per_min,
+            DEFAULT_RATE_LIMIT_PLANS_PER_MIN
+        );
+        assert!(!config.rate_limits.trust_forwarded);
+        assert_eq!(config.retention.plan_ttl_secs, None);
+        assert_eq!(
+            config.cleanup_interval,
+            Duration::from_secs(DEFAULT_CLEANUP_INTERVAL_SECS)
+        );
+        assert_eq!(config.min_free_disk_bytes, 100 * 1024 * 1024);
+        assert_eq!(
+            config.admin.backup,
+            BackupPolicy {
+                dir: PathBuf::from("data/backups"),
+                interval: Some(Duration::from_secs(24 * 3600)),
+                keep: 7,
+            }
+        );
+        assert_eq!(
+            config.war,
+            WarSource::Api {
+                url: DEFAULT_WAR_API_URL.to_string(),
+                every: Duration::from_secs(DEFAULT_WAR_POLL_INTERVAL_SECS),
+            }
+        );
+        assert_eq!(
+            config.war_maps,
+            Some(MapPollSettings {
+                url: DEFAULT_WAR_API_URL.to_string(),
+                every: Duration::from_secs(DEFAULT_WAR_MAP_POLL_INTERVAL_SECS),
+            })
+        );
+        assert_eq!(config.war_report, None);
+    }
+
+    const FILE: &str = r#"
+[server]
+bind_address = "127.0.0.1"
+port = 8000
+cors_origins = ["https://arty.example.com", "https://other.example.com"]
+public_url = "https://arty.example.com/"
+
+[cache]
+static_max_age_secs = 60
+
+[graphql]
+max_concurrent = 4
+
+[rate_limits]
+plans_per_min = 0
+trust_forwarded = true
+
+[retention]
+plan_days = 0
+
+[backup]
+dir = "/srv/backups"
+interval_hours = 0
+keep = 30
+
+[war]
+api_url = ""
+"#;
+
+    #[test]
+    fn test_file_values_apply() {
+        let config = load(Some(FILE), &[]).unwrap();
+        assert_eq!(config.bind_addr, "127.0.0.1:8000".parse().unwrap());
+        assert_eq!(config.cors_origins[1], "https://other.example.com");
+        assert_eq!(
+            config.public_url.as_deref(),
+            Some("https://arty.example.com")
+        );
+        assert_eq!(
+            config.cache.static_files,
+            "public, max-age=60, must-revalidate"
+        );
+        assert_eq!(config.graphql.max_concurrent, 4);
+        assert_eq!(config.rate_limits.plan_creations_per_min, 0);
+        assert!(config.rate_limits.trust_forwarded);
+        assert_eq!(config.retention.plan_ttl_secs, None);
+        assert_eq!(config.admin.backup.dir, PathBuf::from("/srv/backups"));
+        assert_eq!(config.admin.backup.interval, None);
+        assert_eq!(config.admin.backup.keep, 30);
+        assert_eq!(config.war, WarSource::Unknown);
+        assert_eq!(config.war_maps, None);
+    }
+
+    #[test]
+    fn test_env_overrides_file() {
+        let config = load(
+            Some(FILE),
+            &[
+                ("PORT", "9000"),
+                (
+                    "CORS_ORIGIN",
+                    "https://a.example.com, https://b.example.com",
+                ),
+                ("GRAPHQL_MAX_CONCURRENT", " 8 "),
+                ("PLAN_RETENTION_DAYS", "7"),
+                ("RATE_LIMIT_TRUST_FORWARDED", "0"),
+                ("BACKUP_INTERVAL_HOURS", "6"),
+                ("WAR_NUMBER", "118"),
+                ("WAR_API_URL", "http://localhost:8900/api"),
+                ("WAR_MAP_POLL_INTERVAL_SECS", "30"),
+                ("WAR_REPORT_WEBHOOK", "https://example.com/hook"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(config.bind_addr, "127.0.0.1:9000".parse().unwrap());
+        assert_eq!(
+            config.cors_origins,
+            vec!["https://a.example.com", "https://b.example.com"]
+        );
+        assert_eq!(config.graphql.max_concurrent, 8);
+        assert_eq!(config.retention.plan_ttl_secs, Some(7 * 86_400));
+        assert!(!config.rate_limits.trust_forwarded);
+        assert_eq!(
+            config.admin.backup.interval,
+            Some(Duration::from_secs(6 * 3600))
+        );
+        // A pinned war number still leaves live map data on
+        assert_eq!(config.war, WarSource::Fixed(118));
+        assert_eq!(
+            config.war_maps,
+            Some(MapPollSettings {
+                url: "http://localhost:8900/api".to_string(),
+                every: Duration::from_secs(30),
+            })
+        );
+        assert_eq!(
+            config.war_report,
+            Some(WarReportSettings {
+                url: "http://localhost:8900/api".to_string(),
+                webhook: "https://example.com/hook".to_string(),
+                every: Duration::from_secs(DEFAULT_WAR_POLL_INTERVAL_SECS),
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_env_values_name_the_variable() {
+        for (var, value) in [
+            ("PLAN_RETENTION_DAYS", "forever"),
+            ("PLAN_RETENTION_DAYS", "-1"),
+            ("GRAPHQL_MAX_CONCURRENT", "0"),
+            ("GRAPHQL_TIMEOUT_SECS", "0"),
+            ("PLAN_CLEANUP_INTERVAL_SECS", "0"),
+            ("RATE_LIMIT_PLANS_PER_MIN", "-1"),
+            ("RATE_LIMIT_TRUST_FORWARDED", "yes"),
+            ("READY_MIN_FREE_DISK_MB", "lots"),
+            ("BACKUP_KEEP", "-1"),
+            ("WAR_NUMBER", "0"),
+            ("WAR_POLL_INTERVAL_SECS", "0"),
+            ("PORT", "70000"),
+            ("BIND_ADDRESS", "localhost"),
+            ("PUBLIC_URL", "arty.example.com"),
+        ] {
+            let err = load(None, &[(var, value)]).unwrap_err();
+            assert!(err.contains(var), "{var}={value}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_invalid_file_values_name_the_key() {
+        let err = load(Some("[graphql]\ntimeout_secs = 0\n"), &[]).unwrap_err();
+        assert!(err.contains("`graphql.timeout_secs`"), "{err}");
+
+        let err = load(Some("[retention]\nplan_days = \"forever\"\n"), &[]).unwrap_err();
+        assert!(err.contains("plan_days"), "{err}");
+
+        let err = load(Some("[server]\nprot = 3000\n"), &[]).unwrap_err();
+        assert!(err.contains("prot"), "{err}");
+
+        // Reports need the War API to know when a war ends
+        let err = load(Some(FILE), &[("WAR_REPORT_WEBHOOK", "https://example.com")]).unwrap_err();
+        assert!(err.contains("WAR_REPORT_WEBHOOK"), "{err}");
+
+        let err = load(Some("[server]\ncors_origins = [\"bad\\norigin\"]\n"), &[]).unwrap_err();
+        assert!(err.contains("server.cors_origins"), "{err}");
+    }
+}
@@ -0,0 +1,124 @@
+//! Files compiled into the binary for single-executable deployments.
+//!
+//! With the default `embed-game-data` feature, the game data (`assets/*.json`
+//! and the terrain masks) and map images are embedded at compile time, so the server starts even
+//! without an `assets/` directory. `embed-assets` adds the built frontend
+//! (`dist/`) for a fully self-contained binary. Lookups in a bundle that isn't
+//! compiled in miss. Files on disk are always tried first, so a self-hoster
+//! can still override any of them.
+
+use std::borrow::Cow;
+use std::path::Path;
+
+/// Which embedded directory to look in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bundle {
+    /// `assets/`: maps.json, weapons.json, the terrain masks and the map images.
+    GameAssets,
+    /// `dist/`: the built frontend.
+    Frontend,
+    /// `dist/assets/`: hashed frontend bundles, served at `/assets`.
+    FrontendAssets,
+}
+
+#[cfg_attr(not(feature = "embed-game-data"), allow(dead_code))]
+pub struct EmbeddedFile {
+    pub data: Cow<'static, [u8]>,
+    pub mime: String,
+    /// Strong `ETag` from the content hash computed at build time.
+    pub etag: String,
+}
+
+#[cfg(feature = "embed-game-data")]
+mod bundled {
+    use rust_embed::RustEmbed;
+
+    #[derive(RustEmbed)]
+    #[folder = "../../assets/"]
+    #[include = "*.json"]
+    #[include = "masks/*"]
+    #[include = "images/maps/*"]
+    pub struct GameAssets;
+
+    #[cfg(feature = "embed-assets")]
+    #[derive(RustEmbed)]
+    #[folder = "../../dist/"]
+    pub struct Frontend;
+}
+
+/// Look up `path` (relative to the bundle's directory) in the binary.
+#[cfg(feature = "embed-game-data")]
+pub fn get(bundle: Bundle, path: &str) -> Option<EmbeddedFile> {
+    let path = path.trim_start_matches('/');
+    let file = match bundle {
+        Bundle::GameAssets => bundled::GameAssets::get(path),
+        #[cfg(feature = "embed-assets")]
+        Bundle::Frontend => bundled::Frontend::get(path),
+        #[cfg(feature = "embed-assets")]
+        Bundle::FrontendAssets => bundled::Frontend::get(&format!("assets/{path}")),
+        #[cfg(not(feature = "embed-assets"))]
+        Bundle::Frontend | Bundle::FrontendAssets => None,
+    }?;
+    let hash: String = file.metadata.sha256_hash()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    Some(EmbeddedFile {
+        mime: file.metadata.mimetype().to_string(),
+        etag: format!("\"{hash}\""),
+        data: file.data,
+    })
+}
+
+/// Look up `path` in the binary. Nothing is embedded without `embed-game-data`.
+#[cfg(not(feature = "embed-game-data"))]
+pub fn get(_bundle: Bundle, _path: &str) -> Option<EmbeddedFile> {
+    None
+}
+
+/// Read `path` from `dir` on disk, falling back to `bundle`'s embedded copy.
+/// The error is the disk one, for files that are in neither.
+pub fn read(dir: &Path, bundle: Bundle, path: &str) -> std::io::Result<Cow<'static, [u8]>> {
+    match std::fs::read(dir.join(path)) {
+        Ok(bytes) => Ok(Cow::Owned(bytes)),
+        Err(e) => get(bundle, path).map(|file| file.data).ok_or(e),
+    }
+}
+
+#[cfg(all(test, feature = "embed-game-data"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_game_data_is_present() {
+        let maps = get(Bundle::GameAssets, "maps.json").unwrap();
+        assert_eq!(maps.mime, "application/json");
+        assert_eq!(maps.etag.len(), 18);
+        assert!(maps.data.starts_with(b"["));
+        assert!(get(Bundle::GameAssets, "/weapons.json").is_some());
+        assert!(get(Bundle::GameAssets, "missing.json").is_none());
+    }
+
+    #[test]
+    fn test_files_on_disk_override_embedded_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("maps.json"), "[]").unwrap();
+        let maps = read(dir.path(), Bundle::GameAssets, "maps.json").unwrap();
+        assert_eq!(&maps[..], b"[]");
+        assert!(read(dir.path(), Bundle::GameAssets, "weapons.json").is_ok());
+        assert!(read(dir.path(), Bundle::GameAssets, "missing.json").is_err());
+    }
+
+    #[test]
+    fn test_embedded_map_images_are_present() {
+        let image = get(Bundle::GameAssets, "images/maps/deadlands.webp").unwrap();
+        assert_eq!(image.mime, "image/webp");
+        assert!(!image.data.is_empty());
+    }
+
+    #[cfg(feature = "embed-assets")]
+    #[test]
+    fn test_embedded_frontend_has_index() {
+        assert!(get(Bundle::Frontend, "index.html").is_some());
+    }
+}
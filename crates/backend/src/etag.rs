@@ -0,0 +1,126 @@
+//! `ETag` / `If-None-Match` handling for the static file routers.
+//!
+//! `ServeDir` answers `If-Modified-Since` but sends no `ETag`, so browsers
+//! holding a multi-megabyte map image had to rely on the date alone. This
+//! middleware tags every successful static response and turns a matching
+//! `If-None-Match` into a bodiless 304.
+
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+
+/// A weak tag for a file served from disk, built from the size, modification
+/// time and encoding `ServeDir` reports. The encoding is part of it because a
+/// precompressed `.br` copy has different bytes from the plain file.
+fn derived_etag(headers: &HeaderMap) -> Option<HeaderValue> {
+    let len = headers.get(header::CONTENT_LENGTH)?;
+    let modified = headers.get(header::LAST_MODIFIED)?;
+    let mut hasher = Sha256::new();
+    hasher.update(len.as_bytes());
+    hasher.update(b"|");
+    hasher.update(modified.as_bytes());
+    hasher.update(b"|");
+    if let Some(encoding) = headers.get(header::CONTENT_ENCODING) {
+        hasher.update(encoding.as_bytes());
+    }
+    let digest = hasher.finalize();
+    let hex: String = digest[..8].iter().map(|b| format!("{b:02x}")).collect();
+    HeaderValue::from_str(&format!("W/\"{hex}\"")).ok()
+}
+
+/// The tag with any `W/` prefix removed, for weak comparison.
+fn opaque_tag(tag: &str) -> &str {
+    tag.trim().trim_start_matches("W/")
+}
+
+/// Whether an `If-None-Match` header value matches `etag` (RFC 9110 weak comparison).
+fn etag_matches(if_none_match: &HeaderValue, etag: &HeaderValue) -> bool {
+    let (Ok(candidates), Ok(etag)) = (if_none_match.to_str(), etag.to_str()) else {
+        return false;
+    };
+    candidates.trim() == "*"
+        || candidates
+            .split(',')
+            .any(|candidate| opaque_tag(candidate) == opaque_tag(etag))
+}
+
+/// Add an `ETag` to successful `GET`/`HEAD` responses that lack one and answer
+/// 304 Not Modified when the client already holds that version.
+pub async fn conditional_get(req: Request, next: Next) -> Response {
+    let cacheable = matches!(*req.method(), Method::GET | Method::HEAD);
+    let if_none_match = req.headers().get(header::IF_NONE_MATCH).cloned();
+    let mut resp = next.run(req).await;
+    if !cacheable || resp.status() != StatusCode::OK {
+        return resp;
+    }
+
+    if !resp.headers().contains_key(header::ETAG) {
+        match derived_etag(resp.headers()) {
+            Some(etag) => {
+                resp.headers_mut().insert(header::ETAG, etag);
+            }
+            None => return resp,
+        }
+    }
+    let etag = resp.headers()[header::ETAG].clone();
+    match if_none_match {
+        Some(candidates) if etag_matches(&candidates, &etag) => {
+            let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+            let headers = not_modified.headers_mut();
+            headers.insert(header::ETAG, etag);
+            for name in [header::CACHE_CONTROL, header::LAST_MODIFIED, header::VARY] {
+                if let Some(value) = resp.headers().get(&name) {
+                    headers.insert(name, value.clone());
+                }
+            }
+            not_modified
+        }
+        _ => resp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_headers(len: &str, modified: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from_str(len).unwrap());
+        headers.insert(
+            header::LAST_MODIFIED,
+            HeaderValue::from_str(modified).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn test_derived_etag_tracks_file_changes() {
+        let base = file_headers("1024", "Sat, 17 Oct 2026 10:00:00 GMT");
+        let etag = derived_etag(&base).unwrap();
+        assert!(etag.to_str().unwrap().starts_with("W/\""));
+        assert_eq!(derived_etag(&base).unwrap(), etag);
+
+        let touched = file_headers("1024", "Sun, 18 Oct 2026 10:00:00 GMT");
+        assert_ne!(derived_etag(&touched).unwrap(), etag);
+
+        let mut brotli = base.clone();
+        brotli.insert(header::CONTENT_ENCODING, HeaderValue::from_static("br"));
+        assert_ne!(derived_etag(&brotli).unwrap(), etag);
+
+        assert!(derived_etag(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        let etag = HeaderValue::from_static("W/\"abc\"");
+        let matches = |value: &'static str| etag_matches(&HeaderValue::from_static(value), &etag);
+        assert!(matches("W/\"abc\""));
+        assert!(matches("\"abc\""));
+        assert!(matches("\"xyz\", W/\"abc\""));
+        assert!(matches("*"));
+        assert!(!matches("\"xyz\""));
+        assert!(!matches("\"abcd\""));
+    }
+}
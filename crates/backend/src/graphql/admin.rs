@@ -0,0 +1,293 @@
+//! The `admin` query and mutation groups for instance operators.
+//!
+//! Operators authenticate with `Authorization: Bearer <ADMIN_TOKEN>`. The HTTP
+//! handler extracts that header as an [`AdminCredential`] and attaches it to
+//! the GraphQL request; the `admin` fields on the query and mutation roots
+//! check it before any of the fields below them can run.
+
+use std::sync::Arc;
+
+use async_graphql::{Context, Object, SimpleObject, ID};
+use axum::extract::FromRequestParts;
+use axum::http::header;
+use axum::http::request::Parts;
+use sha2::{Digest, Sha256};
+
+use super::{cleanup_stats, ctx_data, internal_err, GqlCleanupStats, GqlPlan};
+use crate::assets::SharedAssets;
+use crate::storage::{BackupPolicy, RetentionPolicy, Storage};
+
+const DEFAULT_PLANS_PAGE: i32 = 50;
+const MAX_PLANS_PAGE: i32 = 100;
+
+/// Token guarding the admin API, from `ADMIN_TOKEN`. Only its hash is kept;
+/// without one the admin API is disabled.
+#[derive(Clone, Default)]
+pub struct AdminToken(Option<[u8; 32]>);
+
+impl AdminToken {
+    pub fn new(token: Option<&str>) -> Self {
+        Self(
+            token
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(|t| Sha256::digest(t.as_bytes()).into()),
+        )
+    }
+
+    fn check(&self, token: Option<&str>) -> async_graphql::Result<()> {
+        match (&self.0, token) {
+            (None, _) => Err(async_graphql::Error::new("Admin API is disabled")),
+            (Some(_), None) => Err(async_graphql::Error::new(
+                "Admin token required: send it as `Authorization: Bearer <token>`",
+            )),
+            (Some(hash), Some(token))
+                if *hash == <[u8; 32]>::from(Sha256::digest(token.as_bytes())) =>
+            {
+                Ok(())
+            }
+            (Some(_), Some(_)) => {
+                tracing::warn!("Rejected admin token");
+                Err(async_graphql::Error::new("Invalid admin token"))
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for AdminToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = if self.0.is_some() { "set" } else { "unset" };
+        f.debug_tuple("AdminToken").field(&state).finish()
+    }
+}
+
+/// Operator settings the admin resolvers need.
+#[derive(Debug, Clone)]
+pub struct AdminSettings {
+    pub token: AdminToken,
+    /// Where `admin { backup }` writes snapshots and how many it keeps.
+    pub backup: BackupPolicy,
+}
+
+/// The bearer token a request presented, if any.
+#[derive(Clone, Default)]
+pub struct AdminCredential(Option<String>);
+
+impl<S: Send + Sync> FromRequestParts<S> for AdminCredential {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|t| t.trim().to_string());
+        Ok(AdminCredential(token))
+    }
+}
+
+#[cfg(test)]
+impl AdminCredential {
+    pub fn bearer(token: &str) -> Self {
+        AdminCredential(Some(token.to_string()))
+    }
+}
+
+/// Check the request's bearer token against `ADMIN_TOKEN`.
+pub(super) fn authorize(ctx: &Context<'_>) -> async_graphql::Result<()> {
+    let token = ctx
+        .data_opt::<AdminCredential>()
+        .and_then(|c| c.0.as_deref());
+    ctx_data::<AdminSettings>(ctx)?.token.check(token)
+}
+
+/// Instance overview shown on the admin page.
+#[derive(SimpleObject)]
+pub struct GqlAdminStatus {
+    pub total_plans: u64,
+    pub db_size_bytes: u64,
+    /// Storage schema version the database has been migrated to.
+    pub schema_version: u64,
+    pub cleanup: GqlCleanupStats,
+}
+
+/// One page of `admin { plans }`.
+#[derive(SimpleObject)]
+pub struct GqlPlanPage {
+    /// Plans stored in total, across all pages.
+    pub total: u64,
+    pub plans: Vec<GqlPlan>,
+}
+
+/// Outcome of an admin-triggered cleanup run.
+#[derive(SimpleObject)]
+pub struct GqlCleanupReport {
+    pub plans_deleted: u64,
+    pub bytes_reclaimed: u64,
+}
+
+/// What an admin-triggered asset reload loaded.
+#[derive(SimpleObject)]
+pub struct GqlAssetsReport {
+    pub maps: usize,
+    pub weapons: usize,
+    /// Weapons in `weapons-devbranch.json`; `null` when there is no such file.
+    pub devbranch_weapons: Option<usize>,
+}
+
+/// A database copy written by `admin { backup }`.
+#[derive(SimpleObject)]
+pub struct GqlBackupReport {
+    /// Path of the copy on the server.
+    pub path: String,
+    pub size_bytes: u64,
+    /// Older copies deleted to stay within `BACKUP_KEEP`.
+    pub pruned: usize,
+}
+
+/// Operator queries, reached through `Query.admin`.
+pub struct AdminQuery;
+
+#[Object]
+impl AdminQuery {
+    async fn status(&self, ctx: &Context<'_>) -> async_graphql::Result<GqlAdminStatus> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let retention = ctx_data::<RetentionPolicy>(ctx)?;
+        Ok(GqlAdminStatus {
+            total_plans: storage
+                .count_plans()
+                .map_err(internal_err("Failed to count plans"))?,
+            db_size_bytes: storage
+                .db_size_bytes()
+                .map_err(internal_err("Failed to get database size"))?,
+            schema_version: storage
+                .schema_version()
+                .map_err(internal_err("Failed to read schema version"))?,
+            cleanup: cleanup_stats(storage, retention)?,
+        })
+    }
+
+    /// Every stored plan in id order, `limit` (at most 100) at a time.
+    async fn plans(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 0)] offset: i32,
+        #[graphql(default = 50)] limit: i32,
+    ) -> async_graphql::Result<GqlPlanPage> {
+        if offset < 0 || !(1..=MAX_PLANS_PAGE).contains(&limit) {
+            return Err(async_graphql::Error::new(format!(
+                "offset must be 0 or more and limit between 1 and {MAX_PLANS_PAGE} (default {DEFAULT_PLANS_PAGE})"
+            )));
+        }
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let total = storage
+            .count_plans()
+            .map_err(internal_err("Failed to count plans"))?;
+        let plans = storage
+            .list_plans(offset as usize, limit as usize)
+            .map_err(internal_err("Failed to list plans"))?;
+        Ok(GqlPlanPage {
+            total,
+            plans: plans.into_iter().map(GqlPlan::from).collect(),
+        })
+    }
+}
+
+/// Operator mutations, reached through `Mutation.admin`.
+pub struct AdminMutation;
+
+#[Object]
+impl AdminMutation {
+    /// Delete expired plans and compact the database now instead of waiting for
+    /// the next scheduled run.
+    async fn purge_expired_plans(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<GqlCleanupReport> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let retention = ctx_data::<RetentionPolicy>(ctx)?;
+        let report = storage
+            .run_cleanup(chrono::Utc::now().timestamp(), retention)
+            .map_err(internal_err("Failed to purge expired plans"))?;
+        tracing::info!(
+            plans_deleted = report.plans_deleted,
+            bytes_reclaimed = report.bytes_reclaimed,
+            "Admin purge of expired plans"
+        );
+        Ok(GqlCleanupReport {
+            plans_deleted: report.plans_deleted,
+            bytes_reclaimed: report.bytes_reclaimed,
+        })
+    }
+
+    /// Re-read `maps.json` and the weapon files from the assets directory and start
+    /// serving them, e.g. after a balance patch. On failure the current data
+    /// stays in use.
+    async fn reload_assets(&self, ctx: &Context<'_>) -> async_graphql::Result<GqlAssetsReport> {
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?;
+        let reloaded = assets.reload().map_err(|e| {
+            tracing::error!(error = %e, "Failed to reload game assets");
+            async_graphql::Error::new(format!("Failed to reload game assets: {e}"))
+        })?;
+        let devbranch_weapons = reloaded.devbranch.as_ref().map(|d| d.weapons.len());
+        tracing::info!(
+            maps = reloaded.maps.len(),
+            weapons = reloaded.weapons.len(),
+            devbranch_weapons,
+            "Admin reload of game assets"
+        );
+        Ok(GqlAssetsReport {
+            maps: reloaded.maps.len(),
+            weapons: reloaded.weapons.len(),
+            devbranch_weapons,
+        })
+    }
+
+    /// Delete any plan, without its edit token. Returns whether it existed.
+    async fn delete_plan(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<bool> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let existed = storage
+            .delete_plan(&id)
+            .map_err(internal_err("Failed to delete plan"))?;
+        tracing::info!(plan_id = %id.as_str(), existed, "Admin deleted plan");
+        Ok(existed)
+    }
+
+    /// Zero the gun, target and spotter placement statistics. Returns how many
+    /// counters were cleared.
+    async fn reset_stats(&self, ctx: &Context<'_>) -> async_graphql::Result<u64> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let cleared = storage
+            .reset_placement_stats()
+            .map_err(internal_err("Failed to reset placement stats"))?;
+        tracing::info!(cleared, "Admin reset of placement stats");
+        Ok(cleared)
+    }
+
+    /// Copy the database into the configured backup directory, e.g. before an
+    /// upgrade, and drop the oldest copies past the retention count.
+    async fn backup(&self, ctx: &Context<'_>) -> async_graphql::Result<GqlBackupReport> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?.clone();
+        let policy = ctx_data::<AdminSettings>(ctx)?.backup.clone();
+        // Copying a large file shouldn't hold up an async worker
+        let report = tokio::task::spawn_blocking(move || {
+            storage.backup(&policy, chrono::Utc::now().timestamp())
+        })
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map_err(internal_err("Failed to back up database"))?;
+        tracing::info!(
+            path = %report.path.display(),
+            size_bytes = report.size_bytes,
+            pruned = report.pruned,
+            "Admin database backup"
+        );
+        Ok(GqlBackupReport {
+            path: report.path.display().to_string(),
+            size_bytes: report.size_bytes,
+            pruned: report.pruned,
+        })
+    }
+}
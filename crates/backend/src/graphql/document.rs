@@ -0,0 +1,313 @@
+//! Plans as self-contained JSON documents, for archiving, editing offline or
+//! moving between server instances.
+//!
+//! The document's `plan` is a `CreatePlanInput` in METERS, so importing one
+//! goes through the same checks as `createPlan`. The map and weapon names and
+//! the weapon data version beside it are only there for people reading the
+//! file.
+
+use async_graphql::{InputType, Value};
+use foxhole_shared::models::Plan;
+
+use super::{
+    CalibrationInput, CalibrationPointInput, CoordinateSpace, CreatePlanInput, CustomRingInput,
+    GqlRingAnchor, GqlTargetStatus, PositionInput, RegistrationPointInput, ShotRecordInput,
+};
+use crate::assets::Assets;
+
+/// Value of the document's `format` field.
+pub const PLAN_DOCUMENT_FORMAT: &str = "foxhole-artillery-plan";
+
+/// Layout of the document; documents in any other layout are refused.
+pub const PLAN_DOCUMENT_VERSION: u64 = 1;
+
+fn position(p: &foxhole_shared::models::Position) -> PositionInput {
+    PositionInput {
+        x: Some(p.x),
+        y: Some(p.y),
+        grid: None,
+    }
+}
+
+/// The `createPlan` input that recreates `plan`.
+fn plan_input(plan: &Plan) -> CreatePlanInput {
+    CreatePlanInput {
+        coordinate_space: CoordinateSpace::Meters,
+        name: plan.name.clone(),
+        map_id: plan.map_id.clone(),
+        weapon_ids: plan.weapon_ids.clone(),
+        gun_ammo: Some(plan.gun_ammo.clone()),
+        gun_positions: Some(plan.gun_positions.iter().map(position).collect()),
+        target_positions: Some(plan.target_positions.iter().map(position).collect()),
+        spotter_positions: Some(plan.spotter_positions.iter().map(position).collect()),
+        enemy_gun_positions: Some(plan.intel.enemy_guns.iter().map(position).collect()),
+        fob_positions: Some(plan.intel.fobs.iter().map(position).collect()),
+        bunker_positions: Some(plan.intel.bunkers.iter().map(position).collect()),
+        no_fire_zone_positions: Some(plan.intel.no_fire_zones.iter().map(position).collect()),
+        no_fire_zone_radii: Some(plan.intel.no_fire_radii.clone()),
+        gun_target_indices: Some(
+            plan.gun_target_indices
+                .iter()
+                .map(|o| o.map(|i| i as i32))
+                .collect(),
+        ),
+        gun_labels: Some(plan.gun_labels.clone()),
+        target_labels: Some(plan.target_labels.clone()),
+        spotter_labels: Some(plan.spotter_labels.clone()),
+        target_statuses: Some(
+            plan.target_statuses
+                .iter()
+                .map(|&s| GqlTargetStatus::from(s))
+                .collect(),
+        ),
+        wind_direction: plan.wind_direction,
+        wind_strength: Some(plan.wind_strength as u32),
+        calibration: plan.calibration.as_ref().map(|c| CalibrationInput {
+            image_url: c.image_url.clone(),
+            width: c.width,
+            height: c.height,
+            points: c
+                .points
+                .iter()
+                .map(|p| CalibrationPointInput {
+                    image_x: p.px.x,
+                    image_y: p.px.y,
+                    position: position(&p.meters),
+                })
+                .collect(),
+        }),
+        shot_log: Some(
+            plan.shot_log
+                .iter()
+                .map(|s| ShotRecordInput {
+                    gun: s.gun as i32,
+                    target: s.target.map(|t| t as i32),
+                    azimuth: s.azimuth,
+                    distance: s.distance,
+                    result: s.result.clone(),
+                })
+                .collect(),
+        ),
+        registrations: Some(
+            plan.registrations
+                .iter()
+                .map(|r| RegistrationPointInput {
+                    gun: r.gun as i32,
+                    name: r.name.clone(),
+                    position: position(&r.position),
+                    azimuth: r.azimuth,
+                    distance: r.distance,
+                })
+                .collect(),
+        ),
+        gun_rings_hidden: Some(plan.range_rings.hidden.clone()),
+        custom_rings: Some(
+            plan.range_rings
+                .custom
+                .iter()
+                .map(|r| CustomRingInput {
+                    anchor: GqlRingAnchor::from(r.anchor),
+                    index: r.index as i32,
+                    radius: r.radius,
+                    label: r.label.clone(),
+                })
+                .collect(),
+        ),
+        profile: plan.weapon_profile.into(),
+    }
+}
+
+/// Drop `null` fields, which only stand for "not given" in an input.
+fn without_nulls(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(fields) => fields
+            .into_iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| (k, without_nulls(v)))
+            .collect(),
+        serde_json::Value::Array(items) => items.into_iter().map(without_nulls).collect(),
+        other => other,
+    }
+}
+
+/// `plan` as a pretty-printed document.
+pub fn plan_document(plan: &Plan, assets: &Assets) -> String {
+    let map_name = assets
+        .find_map_by_file_name(&plan.map_id)
+        .map(|m| m.display_name.clone());
+    let weapon_names: Vec<Option<String>> = plan
+        .weapon_ids
+        .iter()
+        .map(|id| {
+            assets
+                .find_weapon_of(plan.weapon_profile, id)
+                .map(|w| w.display_name.clone())
+        })
+        .collect();
+    let plan_json = plan_input(plan).to_value().into_json().unwrap_or_default();
+    let document = serde_json::json!({
+        "format": PLAN_DOCUMENT_FORMAT,
+        "version": PLAN_DOCUMENT_VERSION,
+        "exportedAt": chrono::Utc::now().to_rfc3339(),
+        "mapName": map_name,
+        "weaponNames": weapon_names,
+        "weaponDataVersion": plan.weapon_data_version,
+        "plan": without_nulls(plan_json),
+    });
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+/// The `createPlan` input in a document, or why it can't be read.
+pub fn plan_input_from_document(document: &str) -> Result<CreatePlanInput, String> {
+    let document: serde_json::Value =
+        serde_json::from_str(document).map_err(|e| format!("Not a JSON document: {}", e))?;
+    if document["format"] != PLAN_DOCUMENT_FORMAT {
+        return Err("Not a plan document".to_string());
+    }
+    if document["version"] != PLAN_DOCUMENT_VERSION {
+        return Err(format!(
+            "Unsupported plan document version {}",
+            document["version"]
+        ));
+    }
+    let plan = Value::from_json(document["plan"].clone())
+        .map_err(|e| format!("Invalid plan in document: {}", e))?;
+    CreatePlanInput::parse(Some(plan)).map_err(|e| {
+        format!(
+            "Invalid plan in document: {}",
+            e.into_server_error(Default::default()).message
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::models::{
+        CalibrationPoint, CustomRing, MapCalibration, Position, RangeRings, RegistrationPoint,
+        RingAnchor, ShotRecord, TargetStatus,
+    };
+
+    fn test_plan() -> Plan {
+        let at = |x: f64, y: f64| Position { x, y };
+        Plan {
+            id: uuid::Uuid::new_v4(),
+            name: "Archive".to_string(),
+            map_id: "test-map".to_string(),
+            weapon_ids: vec!["w".to_string()],
+            gun_ammo: vec!["Flare".to_string()],
+            gun_positions: vec![at(100.0, 200.0)],
+            target_positions: vec![at(300.0, 250.0)],
+            gun_target_indices: vec![Some(0)],
+            gun_labels: vec!["Left".to_string()],
+            target_statuses: vec![TargetStatus::FiredUpon],
+            wind_direction: Some(90.0),
+            wind_strength: 2,
+            version: 4,
+            war_number: Some(117),
+            calibration: Some(MapCalibration {
+                image_url: "https://example.com/map.png".to_string(),
+                width: 1000,
+                height: 900,
+                points: [
+                    CalibrationPoint {
+                        px: at(0.0, 0.0),
+                        meters: at(0.0, 0.0),
+                    },
+                    CalibrationPoint {
+                        px: at(1000.0, 900.0),
+                        meters: at(2000.0, 1800.0),
+                    },
+                ],
+            }),
+            shot_log: vec![ShotRecord {
+                gun: 0,
+                target: Some(0),
+                azimuth: 82.0,
+                distance: 205.0,
+                result: "hit".to_string(),
+            }],
+            registrations: vec![RegistrationPoint {
+                gun: 0,
+                name: "Crossroads".to_string(),
+                position: at(150.0, 150.0),
+                azimuth: 45.0,
+                distance: 70.0,
+            }],
+            range_rings: RangeRings {
+                hidden: vec![true],
+                custom: vec![CustomRing {
+                    anchor: RingAnchor::Target,
+                    index: 0,
+                    radius: 40.0,
+                    label: "Blast".to_string(),
+                }],
+            },
+            weapon_data_version: Some("2026-10-18".to_string()),
+            ..Plan::default()
+        }
+    }
+
+    fn test_assets() -> Assets {
+        Assets {
+            maps: vec![],
+            weapons: vec![],
+            game_version: None,
+            devbranch: None,
+            terrain_masks: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_document_round_trips_the_plan() {
+        let plan = test_plan();
+        let document = plan_document(&plan, &test_assets());
+        let json: serde_json::Value = serde_json::from_str(&document).unwrap();
+        assert_eq!(json["format"], PLAN_DOCUMENT_FORMAT);
+        assert_eq!(json["plan"]["coordinateSpace"], "METERS");
+        assert_eq!(json["weaponDataVersion"], "2026-10-18");
+        assert_eq!(json["plan"]["gunPositions"][0]["x"], 100.0);
+        // Unset input fields are left out rather than written as null
+        assert!(json["plan"]["gunPositions"][0].get("grid").is_none());
+
+        let input = plan_input_from_document(&document).unwrap();
+        assert_eq!(input.name, "Archive");
+        assert_eq!(input.map_id, "test-map");
+        assert_eq!(input.weapon_ids, vec!["w"]);
+        let guns = input.gun_positions.unwrap();
+        assert_eq!((guns[0].x, guns[0].y), (Some(100.0), Some(200.0)));
+        assert_eq!(input.gun_target_indices, Some(vec![Some(0)]));
+        assert!(input.target_statuses.unwrap() == vec![GqlTargetStatus::FiredUpon]);
+        assert_eq!(input.wind_direction, Some(90.0));
+        assert_eq!(input.wind_strength, Some(2));
+        assert_eq!(input.calibration.unwrap().points[1].image_x, 1000.0);
+        assert_eq!(input.shot_log.unwrap()[0].result, "hit");
+        assert_eq!(input.registrations.unwrap()[0].name, "Crossroads");
+        assert_eq!(input.gun_rings_hidden, Some(vec![true]));
+        let rings = input.custom_rings.unwrap();
+        assert!(rings[0].anchor == GqlRingAnchor::Target);
+        assert_eq!(rings[0].label, "Blast");
+    }
+
+    #[test]
+    fn test_bad_documents_are_refused() {
+        assert!(plan_input_from_document("not json").is_err());
+        assert_eq!(
+            plan_input_from_document(r#"{"format":"something-else","version":1}"#)
+                .err()
+                .unwrap(),
+            "Not a plan document"
+        );
+        assert_eq!(
+            plan_input_from_document(r#"{"format":"foxhole-artillery-plan","version":2}"#)
+                .err()
+                .unwrap(),
+            "Unsupported plan document version 2"
+        );
+        let missing_name = r#"{"format":"foxhole-artillery-plan","version":1,"plan":{"mapId":"m","weaponIds":[]}}"#;
+        assert!(plan_input_from_document(missing_name)
+            .err()
+            .unwrap()
+            .starts_with("Invalid plan in document"));
+    }
+}
@@ -1,14 +1,39 @@
+mod admin;
+mod document;
+mod tracking;
+
 use std::sync::Arc;
+use std::time::Instant;
 
-use async_graphql::{Context, Enum, InputObject, Object, SimpleObject, ID};
+use async_graphql::{
+    ComplexObject, Context, Enum, ErrorExtensions, InputObject, Object, SimpleObject, ID,
+};
 use foxhole_shared::{
     calc,
-    grid::{MAP_HEIGHT_M, MAP_WIDTH_M},
-    models::{self, Faction, Position, WindInput, UNASSIGNED_WEAPON},
+    grid::{self, format_grid_coord, MapScale, MAP_HEIGHT_M, MAP_WIDTH_M},
+    merge::{self, PlanMarkers},
+    models::{
+        self, CalibrationPoint, CustomRing, Faction, GameMap, IntelMarkers, MapCalibration,
+        NoFireZone, Position, RangeRings, RegistrationPoint, RingAnchor, ShotRecord, StructureKind,
+        TargetStatus, Weapon, WeaponMount, WeaponProfile, WindInput, MAX_CUSTOM_RINGS,
+        MAX_NO_FIRE_RADIUS, MAX_REGISTRATIONS, MAX_REGISTRATION_NAME_LEN, MAX_RING_RADIUS,
+        MAX_SHOT_LOG, MAX_SHOT_RESULT_LEN, UNASSIGNED_WEAPON,
+    },
+    overlay::{MarkerKind, MAX_LABEL_LEN},
+    research::{self, ImpactObservation, MAX_IMPACT_OFFSET},
+    template::{BatteryTemplate, TemplateGun, MAX_TEMPLATE_GUNS},
+    terrain::{self, TerrainIssue},
 };
 
-use crate::assets::Assets;
-use crate::storage::Storage;
+use crate::assets::{Assets, SharedAssets};
+use crate::rate_limit::{ClientIp, RateClass, RateLimiter, RATE_LIMITED_CODE};
+use crate::storage::{day_of, PlacementScope, RetentionPolicy, Storage, UpdateOutcome, WindReport};
+use crate::war::CurrentWar;
+use crate::war_map::{HexData, MapStructure, WarMaps};
+
+pub use admin::{AdminCredential, AdminSettings, AdminToken};
+use admin::{AdminMutation, AdminQuery};
+pub use tracking::TrackingSession;
 
 // Re-export Faction as a GraphQL enum
 #[derive(Enum, Copy, Clone, Eq, PartialEq)]
@@ -38,6 +63,119 @@ impl From<GqlFaction> for Faction {
     }
 }
 
+/// Which weapon figures to use.
+#[derive(Enum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GqlWeaponProfile {
+    /// The live game's figures, from `weapons.json`.
+    #[default]
+    Live,
+    /// Upcoming figures being tested on the Devbranch, from
+    /// `weapons-devbranch.json`. Not every server has them.
+    Devbranch,
+}
+
+impl From<WeaponProfile> for GqlWeaponProfile {
+    fn from(p: WeaponProfile) -> Self {
+        match p {
+            WeaponProfile::Live => GqlWeaponProfile::Live,
+            WeaponProfile::Devbranch => GqlWeaponProfile::Devbranch,
+        }
+    }
+}
+
+impl From<GqlWeaponProfile> for WeaponProfile {
+    fn from(p: GqlWeaponProfile) -> Self {
+        match p {
+            GqlWeaponProfile::Live => WeaponProfile::Live,
+            GqlWeaponProfile::Devbranch => WeaponProfile::Devbranch,
+        }
+    }
+}
+
+/// Progress of a target during an op.
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum GqlTargetStatus {
+    Active,
+    FiredUpon,
+    Destroyed,
+}
+
+impl From<TargetStatus> for GqlTargetStatus {
+    fn from(s: TargetStatus) -> Self {
+        match s {
+            TargetStatus::Active => GqlTargetStatus::Active,
+            TargetStatus::FiredUpon => GqlTargetStatus::FiredUpon,
+            TargetStatus::Destroyed => GqlTargetStatus::Destroyed,
+        }
+    }
+}
+
+impl From<GqlTargetStatus> for TargetStatus {
+    fn from(s: GqlTargetStatus) -> Self {
+        match s {
+            GqlTargetStatus::Active => TargetStatus::Active,
+            GqlTargetStatus::FiredUpon => TargetStatus::FiredUpon,
+            GqlTargetStatus::Destroyed => TargetStatus::Destroyed,
+        }
+    }
+}
+
+/// The kind of marker a custom range ring is drawn around.
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum GqlRingAnchor {
+    Gun,
+    Target,
+    Spotter,
+}
+
+impl From<RingAnchor> for GqlRingAnchor {
+    fn from(a: RingAnchor) -> Self {
+        match a {
+            RingAnchor::Gun => GqlRingAnchor::Gun,
+            RingAnchor::Target => GqlRingAnchor::Target,
+            RingAnchor::Spotter => GqlRingAnchor::Spotter,
+        }
+    }
+}
+
+impl From<GqlRingAnchor> for RingAnchor {
+    fn from(a: GqlRingAnchor) -> Self {
+        match a {
+            GqlRingAnchor::Gun => RingAnchor::Gun,
+            GqlRingAnchor::Target => RingAnchor::Target,
+            GqlRingAnchor::Spotter => RingAnchor::Spotter,
+        }
+    }
+}
+
+/// A kind of structure on the live War API map.
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum GqlStructureKind {
+    TownHall,
+    RelicBase,
+    Keep,
+    ForwardBase,
+    ObservationTower,
+    CoastalGun,
+    StormCannon,
+    IntelCenter,
+}
+
+impl From<StructureKind> for GqlStructureKind {
+    fn from(k: StructureKind) -> Self {
+        match k {
+            StructureKind::TownHall => GqlStructureKind::TownHall,
+            StructureKind::RelicBase => GqlStructureKind::RelicBase,
+            StructureKind::Keep => GqlStructureKind::Keep,
+            StructureKind::ForwardBase => GqlStructureKind::ForwardBase,
+            StructureKind::ObservationTower => GqlStructureKind::ObservationTower,
+            StructureKind::CoastalGun => GqlStructureKind::CoastalGun,
+            StructureKind::StormCannon => GqlStructureKind::StormCannon,
+            StructureKind::IntelCenter => GqlStructureKind::IntelCenter,
+        }
+    }
+}
+
 // GraphQL output types
 
 #[derive(SimpleObject)]
@@ -46,6 +184,148 @@ pub struct GqlGameMap {
     pub file_name: String,
     pub image_type: String,
     pub active: bool,
+    /// Map image size in pixels; `IMAGE_PX` positions on this map are in this space.
+    pub width: u32,
+    pub height: u32,
+    /// Initial viewport for the planner, if maps.json sets one.
+    pub default_view: Option<GqlMapViewPreset>,
+}
+
+#[derive(SimpleObject)]
+pub struct GqlMapViewPreset {
+    pub zoom: f64,
+    pub center: GqlPosition,
+}
+
+/// A structure on a map, from the Foxhole War API.
+#[derive(SimpleObject)]
+pub struct GqlMapStructure {
+    pub kind: GqlStructureKind,
+    /// The side holding it; `null` while unclaimed.
+    pub team: Option<GqlFaction>,
+    pub position: GqlPosition,
+    /// The nearest named place on the map, e.g. the town a town hall belongs to.
+    pub location: Option<String>,
+    /// Whether holding it counts towards winning the war.
+    pub victory_base: bool,
+    pub scorched: bool,
+}
+
+impl From<&MapStructure> for GqlMapStructure {
+    fn from(s: &MapStructure) -> Self {
+        GqlMapStructure {
+            kind: s.kind.into(),
+            team: s.team.map(GqlFaction::from),
+            position: GqlPosition::from(&s.position),
+            location: s.location.clone(),
+            victory_base: s.victory_base,
+            scorched: s.scorched,
+        }
+    }
+}
+
+/// The live structures on one map.
+#[derive(SimpleObject)]
+pub struct GqlMapStructures {
+    /// When the War API last reported a change on the map (RFC 3339).
+    pub updated_at: Option<String>,
+    pub structures: Vec<GqlMapStructure>,
+}
+
+impl From<&HexData> for GqlMapStructures {
+    fn from(hex: &HexData) -> Self {
+        GqlMapStructures {
+            updated_at: timestamp_to_rfc3339(hex.updated_at),
+            structures: hex.structures.iter().map(GqlMapStructure::from).collect(),
+        }
+    }
+}
+
+/// Wind someone observed in-game on a map.
+#[derive(SimpleObject)]
+pub struct GqlWindReport {
+    /// Bearing in degrees the wind blows towards.
+    pub direction: f64,
+    pub strength: u32,
+    pub reported_at: Option<String>,
+}
+
+impl From<WindReport> for GqlWindReport {
+    fn from(r: WindReport) -> Self {
+        GqlWindReport {
+            direction: r.direction,
+            strength: r.strength as u32,
+            reported_at: timestamp_to_rfc3339(r.reported_at),
+        }
+    }
+}
+
+/// One gun of a battery template, `offsetX`/`offsetY` meters from its centre.
+#[derive(SimpleObject)]
+pub struct GqlTemplateGun {
+    pub weapon_id: String,
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+/// A reusable gun layout shared by id.
+#[derive(SimpleObject)]
+pub struct GqlBatteryTemplate {
+    pub id: ID,
+    pub name: String,
+    pub guns: Vec<GqlTemplateGun>,
+}
+
+impl GqlBatteryTemplate {
+    fn new(id: &str, t: BatteryTemplate) -> Self {
+        GqlBatteryTemplate {
+            id: ID(id.to_string()),
+            name: t.name,
+            guns: t
+                .guns
+                .into_iter()
+                .map(|g| GqlTemplateGun {
+                    weapon_id: g.weapon_id,
+                    offset_x: g.offset.x,
+                    offset_y: g.offset.y,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// How a weapon gets to its firing position.
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum GqlWeaponMount {
+    /// Carried and set up by infantry.
+    Portable,
+    /// Pulled into place by a truck.
+    Towed,
+    /// Fires from a vehicle or ship.
+    Vehicle,
+    /// Built in place.
+    Emplaced,
+}
+
+impl From<WeaponMount> for GqlWeaponMount {
+    fn from(m: WeaponMount) -> Self {
+        match m {
+            WeaponMount::Portable => GqlWeaponMount::Portable,
+            WeaponMount::Towed => GqlWeaponMount::Towed,
+            WeaponMount::Vehicle => GqlWeaponMount::Vehicle,
+            WeaponMount::Emplaced => GqlWeaponMount::Emplaced,
+        }
+    }
+}
+
+/// A set of weapon figures the server has.
+#[derive(SimpleObject)]
+pub struct GqlWeaponProfileInfo {
+    pub profile: GqlWeaponProfile,
+    /// Game patch the figures are from, if their file says.
+    pub game_version: Option<String>,
+    /// Number of weapons in the profile.
+    pub weapons: usize,
 }
 
 #[derive(SimpleObject)]
@@ -59,6 +339,32 @@ pub struct GqlWeapon {
     pub acc_radius_max: f64,
     pub wind_drift_min: f64,
     pub wind_drift_max: f64,
+    pub flight_time_min: f64,
+    pub flight_time_max: f64,
+    /// Other shells it can fire, with how each one flies.
+    pub ammo: Vec<GqlAmmoType>,
+    /// Fewest soldiers needed to fire it, if known.
+    pub crew: Option<u32>,
+    /// Seconds from reaching a firing position to being ready to fire, if known.
+    pub deploy_time: Option<f64>,
+    pub mount: Option<GqlWeaponMount>,
+    /// Fires area salvos, like rocket artillery, rather than point shots.
+    pub area_fire: bool,
+}
+
+/// A shell a weapon can fire besides its standard one, with the weapon's
+/// figures when firing it.
+#[derive(SimpleObject)]
+pub struct GqlAmmoType {
+    pub name: String,
+    pub min_range: f64,
+    pub max_range: f64,
+    pub acc_radius_min: f64,
+    pub acc_radius_max: f64,
+    pub wind_drift_min: f64,
+    pub wind_drift_max: f64,
+    pub flight_time_min: f64,
+    pub flight_time_max: f64,
 }
 
 #[derive(SimpleObject)]
@@ -70,170 +376,948 @@ pub struct GqlFiringSolution {
     pub wind_adjusted_azimuth: Option<f64>,
     pub wind_adjusted_distance: Option<f64>,
     pub wind_offset_meters: Option<f64>,
+    /// Estimated shell flight time in seconds, if known for the weapon.
+    pub time_of_flight: Option<f64>,
+    /// Where an area-fire salvo lands; null for point-fire weapons.
+    pub impact_zone: Option<GqlImpactZone>,
+    /// Indexes of the no-fire zones the shells could land in (danger close).
+    pub danger_close: Vec<u32>,
+}
+
+/// Footprint of an area-fire salvo, centred on the target.
+#[derive(SimpleObject)]
+pub struct GqlImpactZone {
+    /// Meters along the line of fire.
+    pub length: f64,
+    /// Meters across the line of fire.
+    pub width: f64,
+    /// Compass bearing of the line of fire, in degrees.
+    pub azimuth: f64,
+}
+
+impl From<models::FiringSolution> for GqlFiringSolution {
+    fn from(sol: models::FiringSolution) -> Self {
+        GqlFiringSolution {
+            azimuth: sol.azimuth,
+            distance: sol.distance,
+            in_range: sol.in_range,
+            accuracy_radius: sol.accuracy_radius,
+            wind_adjusted_azimuth: sol.wind_adjusted_azimuth,
+            wind_adjusted_distance: sol.wind_adjusted_distance,
+            wind_offset_meters: sol.wind_offset_meters,
+            time_of_flight: sol.time_of_flight,
+            impact_zone: sol.impact_zone.map(|z| GqlImpactZone {
+                length: z.length,
+                width: z.width,
+                azimuth: z.azimuth,
+            }),
+            danger_close: Vec::new(),
+        }
+    }
+}
+
+impl GqlFiringSolution {
+    /// The solution for shells aimed at `target`, flagged for the `zones`
+    /// they could land in.
+    fn with_danger_close(
+        sol: models::FiringSolution,
+        target: Position,
+        zones: &[NoFireZone],
+    ) -> Self {
+        let danger_close = calc::danger_close(target, sol.accuracy_radius, zones)
+            .into_iter()
+            .map(|i| i as u32)
+            .collect();
+        GqlFiringSolution {
+            danger_close,
+            ..sol.into()
+        }
+    }
+}
+
+/// One row of a plan's barrage sheet: a gun, its paired target and the solution between them.
+#[derive(SimpleObject)]
+pub struct GqlFireMission {
+    /// 1-based gun number, matching the planner UI.
+    pub gun: u32,
+    pub weapon_id: String,
+    pub weapon_name: String,
+    /// Shell the gun fires; blank for the standard shell.
+    pub ammo: String,
+    pub gun_grid: String,
+    /// 1-based target number, matching the planner UI.
+    pub target: u32,
+    pub target_grid: String,
+    pub solution: GqlFiringSolution,
+}
+
+/// Result of trying to take a plan's edit lock.
+#[derive(SimpleObject)]
+pub struct GqlPlanLock {
+    /// Whether the caller now holds the lock. `false` means someone else is editing.
+    pub acquired: bool,
+    /// When the current lock lapses unless refreshed (RFC 3339).
+    pub expires_at: String,
 }
 
+/// A position in meters.
 #[derive(SimpleObject, Clone)]
 pub struct GqlPosition {
     pub x: f64,
     pub y: f64,
+    /// Grid reference of the position, e.g. "G9k3".
+    pub grid: String,
+}
+
+impl From<&Position> for GqlPosition {
+    fn from(p: &Position) -> Self {
+        GqlPosition {
+            x: p.x,
+            y: p.y,
+            grid: format_grid_coord(p.x, p.y),
+        }
+    }
 }
 
 #[derive(SimpleObject)]
+#[graphql(complex)]
 pub struct GqlPlan {
     pub id: ID,
     pub name: String,
     pub map_id: String,
     pub weapon_ids: Vec<String>,
+    /// Shell each gun fires, indexed like `weaponIds` (blank = standard shell).
+    pub gun_ammo: Vec<String>,
     pub gun_positions: Vec<GqlPosition>,
     pub target_positions: Vec<GqlPosition>,
     pub spotter_positions: Vec<GqlPosition>,
+    /// Intel: enemy guns, our FOBs and observation bunkers, and the centres
+    /// of no-fire zones.
+    pub enemy_gun_positions: Vec<GqlPosition>,
+    pub fob_positions: Vec<GqlPosition>,
+    pub bunker_positions: Vec<GqlPosition>,
+    pub no_fire_zone_positions: Vec<GqlPosition>,
+    /// Radius of each no-fire zone in meters, indexed like `noFireZonePositions`.
+    pub no_fire_zone_radii: Vec<f64>,
     pub gun_target_indices: Vec<Option<i32>>,
+    /// Marker names, indexed like the positions (blank = unnamed).
+    pub gun_labels: Vec<String>,
+    pub target_labels: Vec<String>,
+    pub spotter_labels: Vec<String>,
+    /// Progress per target, one entry per target position.
+    pub target_statuses: Vec<GqlTargetStatus>,
     pub wind_direction: Option<f64>,
     pub wind_strength: u32,
+    /// Pass back as `baseVersion` when updating the plan.
+    pub version: u32,
     pub created_at: String,
     pub updated_at: String,
+    /// When the plan becomes eligible for cleanup, if it has an explicit expiry.
+    pub expires_at: Option<String>,
+    /// The Foxhole war the plan was made in, if it was known.
+    pub war_number: Option<u32>,
+    /// Custom map image the plan was drawn on, if any.
+    pub calibration: Option<GqlMapCalibration>,
+    /// Game patch of the weapon data the plan was last saved with, see
+    /// `gameVersion`. Missing on plans saved before weapon data was versioned.
+    pub weapon_data_version: Option<String>,
+    /// Weapon figures the plan's guns are checked and calculated with.
+    pub profile: GqlWeaponProfile,
+    /// Rounds fired while adjusting, oldest first.
+    pub shot_log: Vec<GqlShotRecord>,
+    /// Named registration points per gun, with their stored solutions.
+    pub registrations: Vec<GqlRegistrationPoint>,
+    /// Guns whose min/max range rings are hidden, indexed like `weaponIds`.
+    pub gun_rings_hidden: Vec<bool>,
+    /// Rings drawn around markers, e.g. a spotter's intel range.
+    pub custom_rings: Vec<GqlCustomRing>,
+    /// Secret needed to update or delete the plan. Only returned by `createPlan`.
+    pub edit_token: Option<String>,
+}
+
+#[ComplexObject]
+impl GqlPlan {
+    /// Whether updates need an edit token. Plans saved before tokens existed are open.
+    async fn protected(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let matches = storage
+            .edit_token_matches(&self.id, None)
+            .map_err(internal_err("Failed to check edit token"))?;
+        Ok(matches.is_some())
+    }
+
+    /// Short link for reading out over voice comms, e.g. `brave-falcon-42`;
+    /// `plan` takes it in place of the id. Plans made before slugs have none.
+    async fn slug(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<String>> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        storage
+            .plan_slug(&self.id)
+            .map_err(internal_err("Failed to load plan slug"))
+    }
+
+    /// Guns and spotters standing outside the hex, in water or on impassable
+    /// ground, going by the map's terrain mask where it has one. Saving such a
+    /// plan still works; this is only a warning.
+    async fn terrain_warnings(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<GqlTerrainWarning>> {
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let meters = |positions: &[GqlPosition]| -> Vec<Position> {
+            positions
+                .iter()
+                .map(|p| Position { x: p.x, y: p.y })
+                .collect()
+        };
+        Ok(terrain::placement_warnings(
+            &meters(&self.gun_positions),
+            &meters(&self.spotter_positions),
+            assets.terrain_mask(&self.map_id),
+        )
+        .into_iter()
+        .map(Into::into)
+        .collect())
+    }
 }
 
 impl From<models::Plan> for GqlPlan {
     fn from(p: models::Plan) -> Self {
+        let target_statuses = (0..p.target_positions.len())
+            .map(|i| models::target_status(&p.target_statuses, i).into())
+            .collect();
+        let no_fire_zone_radii = p.intel.no_fire_circles().iter().map(|z| z.radius).collect();
+        let positions = |list: Vec<Position>| list.iter().map(GqlPosition::from).collect();
         GqlPlan {
             id: ID(p.id.to_string()),
             name: p.name,
             map_id: p.map_id,
             weapon_ids: p.weapon_ids,
+            gun_ammo: p.gun_ammo,
             gun_positions: p
                 .gun_positions
                 .into_iter()
-                .map(|pos| GqlPosition { x: pos.x, y: pos.y })
+                .map(|pos| GqlPosition::from(&pos))
                 .collect(),
             target_positions: p
                 .target_positions
                 .into_iter()
-                .map(|pos| GqlPosition { x: pos.x, y: pos.y })
+                .map(|pos| GqlPosition::from(&pos))
                 .collect(),
             spotter_positions: p
                 .spotter_positions
                 .into_iter()
-                .map(|pos| GqlPosition { x: pos.x, y: pos.y })
+                .map(|pos| GqlPosition::from(&pos))
                 .collect(),
+            enemy_gun_positions: positions(p.intel.enemy_guns),
+            fob_positions: positions(p.intel.fobs),
+            bunker_positions: positions(p.intel.bunkers),
+            no_fire_zone_radii,
+            no_fire_zone_positions: positions(p.intel.no_fire_zones),
             gun_target_indices: p
                 .gun_target_indices
                 .into_iter()
                 .map(|o| o.map(|v| v as i32))
                 .collect(),
+            gun_labels: p.gun_labels,
+            target_labels: p.target_labels,
+            spotter_labels: p.spotter_labels,
+            target_statuses,
             wind_direction: p.wind_direction,
             wind_strength: p.wind_strength as u32,
+            version: p.version,
             created_at: p.created_at,
             updated_at: p.updated_at,
+            expires_at: p.expires_at.and_then(timestamp_to_rfc3339),
+            war_number: p.war_number,
+            calibration: p.calibration.map(Into::into),
+            weapon_data_version: p.weapon_data_version,
+            profile: p.weapon_profile.into(),
+            shot_log: p.shot_log.into_iter().map(Into::into).collect(),
+            registrations: p.registrations.into_iter().map(Into::into).collect(),
+            gun_rings_hidden: p.range_rings.hidden,
+            custom_rings: p.range_rings.custom.into_iter().map(Into::into).collect(),
+            edit_token: None,
         }
     }
 }
 
-#[derive(SimpleObject)]
-pub struct GqlWeaponPlacementStat {
-    pub weapon_slug: String,
-    pub display_name: String,
-    pub faction: GqlFaction,
-    pub count: u64,
+/// Kind of marker on the map.
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum GqlMarkerKind {
+    Gun,
+    Target,
+    Spotter,
+    /// An enemy gun.
+    Enemy,
+    Fob,
+    /// An observation bunker.
+    Bunker,
+    /// The centre of a no-fire zone.
+    NoFire,
 }
 
-#[derive(SimpleObject)]
-pub struct GqlFactionPlacementStats {
-    pub colonial: u64,
-    pub warden: u64,
-    pub total: u64,
+impl From<MarkerKind> for GqlMarkerKind {
+    fn from(kind: MarkerKind) -> Self {
+        match kind {
+            MarkerKind::Gun => GqlMarkerKind::Gun,
+            MarkerKind::Target => GqlMarkerKind::Target,
+            MarkerKind::Spotter => GqlMarkerKind::Spotter,
+            MarkerKind::Enemy => GqlMarkerKind::Enemy,
+            MarkerKind::Fob => GqlMarkerKind::Fob,
+            MarkerKind::Bunker => GqlMarkerKind::Bunker,
+            MarkerKind::NoFire => GqlMarkerKind::NoFire,
+        }
+    }
+}
+
+/// Why a marker can't really stand where it was put.
+#[derive(Enum, Copy, Clone, Eq, PartialEq)]
+pub enum GqlTerrainIssue {
+    /// Outside the hex, in the blank corners of the map image.
+    OffMap,
+    Water,
+    Impassable,
+}
+
+impl From<TerrainIssue> for GqlTerrainIssue {
+    fn from(issue: TerrainIssue) -> Self {
+        match issue {
+            TerrainIssue::OffMap => GqlTerrainIssue::OffMap,
+            TerrainIssue::Water => GqlTerrainIssue::Water,
+            TerrainIssue::Impassable => GqlTerrainIssue::Impassable,
+        }
+    }
 }
 
+/// A gun or spotter standing somewhere it can't be.
 #[derive(SimpleObject)]
-pub struct GqlMarkerPlacementStats {
-    pub targets: u64,
-    pub spotters: u64,
+pub struct GqlTerrainWarning {
+    pub marker: GqlMarkerKind,
+    /// Index into the plan's gun or spotter positions.
+    pub index: u32,
+    pub issue: GqlTerrainIssue,
+    /// E.g. "Gun 2 is in water".
+    pub message: String,
+}
+
+impl From<terrain::TerrainWarning> for GqlTerrainWarning {
+    fn from(w: terrain::TerrainWarning) -> Self {
+        let kind = if w.marker == MarkerKind::Gun {
+            "Gun"
+        } else {
+            "Spotter"
+        };
+        GqlTerrainWarning {
+            marker: w.marker.into(),
+            index: w.index as u32,
+            issue: w.issue.into(),
+            message: w.message(&format!("{} {}", kind, w.index + 1)),
+        }
+    }
 }
 
+/// A pixel on a custom map image and the position it shows.
 #[derive(SimpleObject)]
-pub struct GqlStats {
-    pub total_plans: u64,
-    pub db_size_bytes: u64,
-    pub gun_placements: Vec<GqlWeaponPlacementStat>,
-    pub gun_placement_totals: GqlFactionPlacementStats,
-    pub marker_placements: GqlMarkerPlacementStats,
+pub struct GqlCalibrationPoint {
+    pub image_x: f64,
+    pub image_y: f64,
+    pub position: GqlPosition,
 }
 
-// Input types
+/// A custom map image lined up with the region by two reference points.
+#[derive(SimpleObject)]
+pub struct GqlMapCalibration {
+    pub image_url: String,
+    /// Image size in pixels.
+    pub width: u32,
+    pub height: u32,
+    pub points: Vec<GqlCalibrationPoint>,
+}
 
-#[derive(InputObject)]
-pub struct PositionInput {
-    pub x: f64,
-    pub y: f64,
+impl From<MapCalibration> for GqlMapCalibration {
+    fn from(c: MapCalibration) -> Self {
+        GqlMapCalibration {
+            image_url: c.image_url,
+            width: c.width,
+            height: c.height,
+            points: c
+                .points
+                .iter()
+                .map(|p| GqlCalibrationPoint {
+                    image_x: p.px.x,
+                    image_y: p.px.y,
+                    position: GqlPosition::from(&p.meters),
+                })
+                .collect(),
+        }
+    }
 }
 
-#[derive(InputObject)]
-pub struct GqlWindInput {
-    pub direction: f64,
-    pub strength: u32,
+/// One round fired while adjusting a gun.
+#[derive(SimpleObject)]
+pub struct GqlShotRecord {
+    /// Index into the plan's guns.
+    pub gun: i32,
+    /// Index into the plan's targets, if the gun had one.
+    pub target: Option<i32>,
+    pub azimuth: f64,
+    pub distance: f64,
+    /// What the spotter saw, e.g. "30 m long, 15 m right".
+    pub result: String,
 }
 
-#[derive(InputObject)]
-pub struct CalculateInput {
-    pub gun_position: PositionInput,
-    pub target_position: PositionInput,
-    pub weapon_id: String,
-    pub wind: Option<GqlWindInput>,
+impl From<ShotRecord> for GqlShotRecord {
+    fn from(s: ShotRecord) -> Self {
+        GqlShotRecord {
+            gun: s.gun as i32,
+            target: s.target.map(|t| t as i32),
+            azimuth: s.azimuth,
+            distance: s.distance,
+            result: s.result,
+        }
+    }
 }
 
-#[derive(InputObject)]
-pub struct CreatePlanInput {
+/// A named point a gun has registered on, with its stored firing data.
+#[derive(SimpleObject)]
+pub struct GqlRegistrationPoint {
+    /// Index into the plan's guns.
+    pub gun: i32,
     pub name: String,
-    pub map_id: String,
-    pub weapon_ids: Vec<String>,
-    pub gun_positions: Option<Vec<PositionInput>>,
-    pub target_positions: Option<Vec<PositionInput>>,
-    pub spotter_positions: Option<Vec<PositionInput>>,
-    pub gun_target_indices: Option<Vec<Option<i32>>>,
-    pub wind_direction: Option<f64>,
-    pub wind_strength: Option<u32>,
+    pub position: GqlPosition,
+    pub azimuth: f64,
+    pub distance: f64,
 }
 
-// Helpers
-
-fn ctx_data<'a, T: Send + Sync + 'static>(ctx: &'a Context<'a>) -> async_graphql::Result<&'a T> {
-    ctx.data::<T>().map_err(|_| {
-        tracing::error!(type_name = std::any::type_name::<T>(), "Missing context data");
-        async_graphql::Error::new("Internal server error: missing context data")
-    })
+/// A ring drawn around a marker.
+#[derive(SimpleObject)]
+pub struct GqlCustomRing {
+    pub anchor: GqlRingAnchor,
+    /// Index into the plan's positions of the anchor's kind.
+    pub index: i32,
+    /// Radius in meters.
+    pub radius: f64,
+    /// Blank when the ring shows its radius instead.
+    pub label: String,
 }
 
-/// Convert a storage error into a generic client-facing error, logging the details server-side.
-fn internal_err(context: &str) -> impl FnOnce(String) -> async_graphql::Error + '_ {
-    move |e| {
-        tracing::error!(error = %e, "{}", context);
-        async_graphql::Error::new("Internal server error")
+impl From<CustomRing> for GqlCustomRing {
+    fn from(r: CustomRing) -> Self {
+        GqlCustomRing {
+            anchor: r.anchor.into(),
+            index: r.index as i32,
+            radius: r.radius,
+            label: r.label,
+        }
     }
 }
 
-fn validate_name(name: &str) -> async_graphql::Result<()> {
-    if name.len() > 200 {
-        return Err(async_graphql::Error::new(
-            "Plan name must be 200 characters or fewer",
-        ));
+impl From<RegistrationPoint> for GqlRegistrationPoint {
+    fn from(r: RegistrationPoint) -> Self {
+        GqlRegistrationPoint {
+            gun: r.gun as i32,
+            name: r.name,
+            position: GqlPosition::from(&r.position),
+            azimuth: r.azimuth,
+            distance: r.distance,
+        }
     }
-    Ok(())
 }
 
-fn validate_map_id(map_id: &str, assets: &Assets) -> async_graphql::Result<()> {
-    if assets.find_map_by_file_name(map_id).is_none() {
-        return Err(async_graphql::Error::new(format!(
-            "Unknown map: {}",
-            map_id
-        )));
+/// Markers of one kind that only one side of a conflicting save has.
+#[derive(SimpleObject)]
+pub struct GqlMarkerDiff {
+    pub only_mine: Vec<GqlPosition>,
+    pub only_theirs: Vec<GqlPosition>,
+}
+
+impl From<merge::MarkerDiff> for GqlMarkerDiff {
+    fn from(d: merge::MarkerDiff) -> Self {
+        let to_gql = |v: Vec<Position>| v.iter().map(GqlPosition::from).collect();
+        GqlMarkerDiff {
+            only_mine: to_gql(d.only_mine),
+            only_theirs: to_gql(d.only_theirs),
+        }
     }
-    Ok(())
 }
 
-fn validate_weapon_ids(weapon_ids: &[String], assets: &Assets) -> async_graphql::Result<()> {
-    if weapon_ids.len() > MAX_POSITIONS {
-        return Err(async_graphql::Error::new(format!(
-            "weapon_ids: too many entries ({}, max {})",
+/// A save that lost the race: the stored plan ("theirs") and how it differs from the submitted one.
+#[derive(SimpleObject)]
+pub struct GqlPlanConflict {
+    pub theirs: GqlPlan,
+    pub guns: GqlMarkerDiff,
+    pub targets: GqlMarkerDiff,
+    pub spotters: GqlMarkerDiff,
+    pub name_changed: bool,
+    pub wind_changed: bool,
+}
+
+impl GqlPlanConflict {
+    fn new(mine: &models::Plan, theirs: models::Plan) -> Self {
+        let m = PlanMarkers::from(mine);
+        let t = PlanMarkers::from(&theirs);
+        GqlPlanConflict {
+            guns: merge::diff_positions(&m.gun_positions, &t.gun_positions).into(),
+            targets: merge::diff_positions(&m.target_positions, &t.target_positions).into(),
+            spotters: merge::diff_positions(&m.spotter_positions, &t.spotter_positions).into(),
+            name_changed: mine.name != theirs.name,
+            wind_changed: mine.wind_direction != theirs.wind_direction
+                || mine.wind_strength != theirs.wind_strength,
+            theirs: theirs.into(),
+        }
+    }
+}
+
+/// Exactly one of `plan` (saved) or `conflict` (not saved) is set.
+#[derive(SimpleObject)]
+pub struct GqlUpdatePlanResult {
+    pub plan: Option<GqlPlan>,
+    pub conflict: Option<GqlPlanConflict>,
+}
+
+#[derive(SimpleObject)]
+pub struct GqlWeaponPlacementStat {
+    pub weapon_slug: String,
+    pub display_name: String,
+    pub faction: GqlFaction,
+    pub count: u64,
+}
+
+#[derive(SimpleObject)]
+pub struct GqlFactionPlacementStats {
+    pub colonial: u64,
+    pub warden: u64,
+    pub total: u64,
+}
+
+#[derive(SimpleObject)]
+pub struct GqlMarkerPlacementStats {
+    pub targets: u64,
+    pub spotters: u64,
+}
+
+/// Placements counted at most once per anonymous session, weapon or marker
+/// kind and UTC day.
+#[derive(SimpleObject)]
+pub struct GqlDedupedPlacementStats {
+    pub guns: u64,
+    pub targets: u64,
+    pub spotters: u64,
+}
+
+/// Guns, targets and spotters placed on one map.
+#[derive(SimpleObject)]
+pub struct GqlMapPlacementStat {
+    pub map_id: String,
+    pub display_name: String,
+    pub guns: u64,
+    pub targets: u64,
+    pub spotters: u64,
+}
+
+/// Totals of the background task that deletes expired plans.
+#[derive(SimpleObject)]
+pub struct GqlCleanupStats {
+    pub plans_expired: u64,
+    /// Bytes the database file shrank by through compaction after deletes.
+    pub bytes_reclaimed: u64,
+    pub last_run_at: Option<String>,
+    /// Plan lifetime after its last save, in days; `null` when plans never expire.
+    pub retention_days: Option<f64>,
+}
+
+/// Requests turned away by the per-client rate limits since the server started.
+#[derive(SimpleObject)]
+pub struct GqlRateLimitStats {
+    /// Rejected `createPlan` / `forkPlan` calls.
+    pub plan_creations: u64,
+    /// Rejected `track*Placement` calls.
+    pub tracking: u64,
+}
+
+/// How many guns or targets created plans put in one grid cell.
+#[derive(SimpleObject)]
+pub struct GqlHeatmapCell {
+    /// 0-based column (A = 0).
+    pub col: u32,
+    /// 0-based row (1 = 0).
+    pub row: u32,
+    /// The cell as a grid reference, e.g. "G9".
+    pub grid: String,
+    pub count: u64,
+}
+
+impl GqlHeatmapCell {
+    fn from_counts(cells: Vec<(u32, u32, u64)>) -> Vec<Self> {
+        cells
+            .into_iter()
+            .map(|(col, row, count)| GqlHeatmapCell {
+                col,
+                row,
+                grid: format!("{}{}", grid::col_letter(col as usize), row + 1),
+                count,
+            })
+            .collect()
+    }
+}
+
+/// Where created plans put their guns and targets on one map.
+#[derive(SimpleObject)]
+pub struct GqlPlacementHeatmap {
+    pub guns: Vec<GqlHeatmapCell>,
+    pub targets: Vec<GqlHeatmapCell>,
+}
+
+/// Submitted impacts of one weapon against the figures the planner uses.
+#[derive(SimpleObject)]
+pub struct GqlImpactSummary {
+    pub weapon_slug: String,
+    pub display_name: String,
+    pub faction: GqlFaction,
+    pub samples: u64,
+    pub mean_distance: f64,
+    /// Mean straight-line miss, in meters.
+    pub mean_miss: f64,
+    /// Mean offset beyond the target (negative: short).
+    pub mean_long: f64,
+    /// Mean offset right of the target (negative: left).
+    pub mean_right: f64,
+    /// Accuracy radius the planner uses at `meanDistance`.
+    pub predicted_accuracy_radius: f64,
+    pub wind_samples: u64,
+    /// Mean wind drift scaled to strength 5; `null` without windy samples.
+    pub observed_wind_drift: Option<f64>,
+    /// Strength-5 wind drift the planner uses at `meanDistance`.
+    pub predicted_wind_drift: f64,
+}
+
+#[derive(SimpleObject)]
+pub struct GqlStats {
+    /// The war the placement figures cover; `null` for all wars.
+    pub war: Option<u32>,
+    /// First and last UTC day (`YYYY-MM-DD`) the placement figures cover when
+    /// limited to a date range; `null` otherwise.
+    pub from: Option<String>,
+    pub to: Option<String>,
+    /// The war being fought now, if known.
+    pub current_war: Option<u32>,
+    /// Wars with placements counted, oldest first; pass one as `stats(war:)`.
+    pub wars: Vec<u32>,
+    pub total_plans: u64,
+    /// Plans still stored in the old JSON encoding; each is converted to the
+    /// compact binary one the next time it's saved.
+    pub legacy_encoded_plans: u64,
+    pub db_size_bytes: u64,
+    pub gun_placements: Vec<GqlWeaponPlacementStat>,
+    pub gun_placement_totals: GqlFactionPlacementStats,
+    pub marker_placements: GqlMarkerPlacementStats,
+    /// The placement totals with repeats by one session on one day counted
+    /// once, for the same war or days. Only tracking calls that sent a session
+    /// id are counted.
+    pub deduped_placements: GqlDedupedPlacementStats,
+    /// Estimated number of planner sessions (browser tabs) that placed
+    /// anything, for the same war or days. A HyperLogLog estimate, within a
+    /// few percent; no session ids are kept for it.
+    pub unique_planners: u64,
+    /// Placements per map over all wars, most placements first. Not limited
+    /// by `war`, `from` or `to`; placements tracked without a `mapId` aren't
+    /// included.
+    pub placements_by_map: Vec<GqlMapPlacementStat>,
+    pub cleanup: GqlCleanupStats,
+    pub rate_limited: GqlRateLimitStats,
+}
+
+// Input types
+
+/// Unit of the positions in an input.
+#[derive(Enum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CoordinateSpace {
+    /// World meters (0–2184 × 0–1890). Plans are stored in this space.
+    #[default]
+    Meters,
+    /// Map-image pixels, 0 up to the map's image `width` × `height`
+    /// (0–2048 × 0–1776 for the stock images).
+    ImagePx,
+    /// Grid reference in `grid`, e.g. "G9k3" (keypad centre) or "G9" (cell centre).
+    GridRef,
+}
+
+/// File format of an exported plan.
+#[derive(Enum, Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PlanFormat {
+    /// A JSON document holding a `createPlan` input in meters, readable by `importPlan`.
+    #[default]
+    Json,
+}
+
+/// A position in the enclosing input's `coordinateSpace`: `x`/`y` for
+/// METERS and IMAGE_PX, `grid` for GRID_REF.
+#[derive(InputObject)]
+pub struct PositionInput {
+    pub x: Option<f64>,
+    pub y: Option<f64>,
+    pub grid: Option<String>,
+}
+
+/// A reference point for a custom map image: a pixel on it, and where that
+/// is on the map in meters (`x`/`y`) or as a grid reference (`grid`).
+#[derive(InputObject)]
+pub struct CalibrationPointInput {
+    pub image_x: f64,
+    pub image_y: f64,
+    pub position: PositionInput,
+}
+
+#[derive(InputObject)]
+pub struct ShotRecordInput {
+    /// Index into the plan's guns.
+    pub gun: i32,
+    /// Index into the plan's targets.
+    pub target: Option<i32>,
+    pub azimuth: f64,
+    pub distance: f64,
+    #[graphql(default)]
+    pub result: String,
+}
+
+#[derive(InputObject)]
+pub struct CustomRingInput {
+    pub anchor: GqlRingAnchor,
+    /// Index into the plan's positions of the anchor's kind.
+    pub index: i32,
+    /// Radius in meters, whatever the `coordinateSpace`.
+    pub radius: f64,
+    #[graphql(default)]
+    pub label: String,
+}
+
+#[derive(InputObject)]
+pub struct RegistrationPointInput {
+    /// Index into the plan's guns.
+    pub gun: i32,
+    pub name: String,
+    /// In the plan's `coordinateSpace`.
+    pub position: PositionInput,
+    pub azimuth: f64,
+    pub distance: f64,
+}
+
+#[derive(InputObject)]
+pub struct CalibrationInput {
+    /// `http(s)` URL of the image.
+    pub image_url: String,
+    /// Image size in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Exactly two points, apart in both x and y.
+    pub points: Vec<CalibrationPointInput>,
+}
+
+#[derive(InputObject)]
+pub struct TemplateGunInput {
+    pub weapon_id: String,
+    /// Meters east of the battery's centre.
+    pub offset_x: f64,
+    /// Meters south of the battery's centre.
+    pub offset_y: f64,
+}
+
+#[derive(InputObject)]
+pub struct BatteryTemplateInput {
+    pub name: String,
+    pub guns: Vec<TemplateGunInput>,
+}
+
+#[derive(InputObject)]
+pub struct GqlWindInput {
+    pub direction: f64,
+    pub strength: u32,
+}
+
+/// Where one round landed, measured from the target along the gun's line of fire.
+#[derive(InputObject)]
+pub struct ImpactObservationInput {
+    pub weapon_id: String,
+    /// Firing data the round was fired with.
+    pub azimuth: f64,
+    pub distance: f64,
+    pub wind_direction: Option<f64>,
+    #[graphql(default)]
+    pub wind_strength: u32,
+    /// Wind drift the firing data already allowed for, in meters.
+    #[graphql(default)]
+    pub compensated_drift: f64,
+    /// Meters beyond the target (negative: short).
+    pub long: f64,
+    /// Meters right of the target (negative: left).
+    pub right: f64,
+}
+
+#[derive(InputObject)]
+pub struct CalculateInput {
+    #[graphql(default)]
+    pub coordinate_space: CoordinateSpace,
+    pub gun_position: PositionInput,
+    pub target_position: PositionInput,
+    pub weapon_id: String,
+    /// Shell fired; the weapon's standard shell when omitted.
+    pub ammo: Option<String>,
+    pub wind: Option<GqlWindInput>,
+    /// Map whose image size `IMAGE_PX` positions refer to; the stock image
+    /// size when omitted.
+    pub map_id: Option<String>,
+    /// Weapon figures to calculate with.
+    #[graphql(default)]
+    pub profile: GqlWeaponProfile,
+    /// Areas the shells must stay out of; `dangerClose` lists those they could land in.
+    pub no_fire_zones: Option<Vec<NoFireZoneInput>>,
+}
+
+/// A circle friendly fire must stay out of.
+#[derive(InputObject)]
+pub struct NoFireZoneInput {
+    pub center: PositionInput,
+    /// Meters, whatever the coordinate space.
+    pub radius: f64,
+}
+
+#[derive(InputObject)]
+pub struct CreatePlanInput {
+    #[graphql(default)]
+    pub coordinate_space: CoordinateSpace,
+    pub name: String,
+    pub map_id: String,
+    pub weapon_ids: Vec<String>,
+    /// Shell each gun fires, indexed like `weaponIds`; missing or blank
+    /// entries are the standard shell.
+    pub gun_ammo: Option<Vec<String>>,
+    pub gun_positions: Option<Vec<PositionInput>>,
+    pub target_positions: Option<Vec<PositionInput>>,
+    pub spotter_positions: Option<Vec<PositionInput>>,
+    /// Enemy guns, our FOBs and observation bunkers, and no-fire zone centres.
+    pub enemy_gun_positions: Option<Vec<PositionInput>>,
+    pub fob_positions: Option<Vec<PositionInput>>,
+    pub bunker_positions: Option<Vec<PositionInput>>,
+    pub no_fire_zone_positions: Option<Vec<PositionInput>>,
+    /// Radius of each no-fire zone in meters, indexed like
+    /// `noFireZonePositions`; zones past the end reach 50 m.
+    pub no_fire_zone_radii: Option<Vec<f64>>,
+    pub gun_target_indices: Option<Vec<Option<i32>>>,
+    pub gun_labels: Option<Vec<String>>,
+    pub target_labels: Option<Vec<String>>,
+    pub spotter_labels: Option<Vec<String>>,
+    /// One per target; missing entries are active.
+    pub target_statuses: Option<Vec<GqlTargetStatus>>,
+    pub wind_direction: Option<f64>,
+    pub wind_strength: Option<u32>,
+    /// Custom map image the plan is drawn on. `IMAGE_PX` positions are pixels
+    /// of this image instead of the map's own.
+    pub calibration: Option<CalibrationInput>,
+    /// Rounds fired while adjusting, oldest first.
+    pub shot_log: Option<Vec<ShotRecordInput>>,
+    /// Named registration points per gun, with their stored solutions.
+    pub registrations: Option<Vec<RegistrationPointInput>>,
+    /// Guns whose min/max range rings are hidden, indexed like `weaponIds`;
+    /// missing entries are shown.
+    pub gun_rings_hidden: Option<Vec<bool>>,
+    /// Rings drawn around markers, e.g. a spotter's intel range.
+    pub custom_rings: Option<Vec<CustomRingInput>>,
+    /// Weapon figures the plan's guns are checked and calculated with.
+    #[graphql(default)]
+    pub profile: GqlWeaponProfile,
+}
+
+// Helpers
+
+fn ctx_data<'a, T: Send + Sync + 'static>(ctx: &'a Context<'a>) -> async_graphql::Result<&'a T> {
+    ctx.data::<T>().map_err(|_| {
+        tracing::error!(type_name = std::any::type_name::<T>(), "Missing context data");
+        async_graphql::Error::new("Internal server error: missing context data")
+    })
+}
+
+/// Convert a storage error into a generic client-facing error, logging the details server-side.
+fn internal_err(context: &str) -> impl FnOnce(String) -> async_graphql::Error + '_ {
+    move |e| {
+        tracing::error!(error = %e, "{}", context);
+        async_graphql::Error::new("Internal server error")
+    }
+}
+
+/// Take a token from the calling client's bucket for `class`. Requests with no
+/// known client address (executed in-process) aren't limited.
+fn check_rate_limit(ctx: &Context<'_>, class: RateClass) -> async_graphql::Result<()> {
+    let Some(ClientIp(ip)) = ctx.data_opt::<ClientIp>() else {
+        return Ok(());
+    };
+    if ctx_data::<Arc<RateLimiter>>(ctx)?.check(*ip, class, Instant::now()) {
+        return Ok(());
+    }
+    tracing::warn!(client = %ip, ?class, "Rate limit exceeded");
+    Err(
+        async_graphql::Error::new("Too many requests, please slow down")
+            .extend_with(|_, e| e.set("code", RATE_LIMITED_CODE)),
+    )
+}
+
+/// Count a new plan's guns and targets in the placement heatmap. Failures are
+/// only logged; the plan is saved either way.
+fn record_placement_cells(storage: &Storage, plan: &models::Plan) {
+    let cells = |positions: &[Position]| -> Vec<(usize, usize)> {
+        positions
+            .iter()
+            .map(|p| grid::grid_cell(p.x, p.y))
+            .collect()
+    };
+    for (kind, positions) in [
+        ("gun", &plan.gun_positions),
+        ("target", &plan.target_positions),
+    ] {
+        if let Err(e) = storage.add_placement_cells(&plan.map_id, kind, &cells(positions)) {
+            tracing::warn!(error = %e, plan_id = %plan.id, "Failed to record placement heatmap");
+        }
+    }
+}
+
+/// How long an edit lock lives without a refresh. Clients refresh well inside this window.
+const PLAN_LOCK_TTL_SECS: i64 = 60;
+
+/// Wind reports older than this are no longer offered; the in-game wind will
+/// likely have shifted.
+const WIND_REPORT_MAX_AGE_SECS: i64 = 30 * 60;
+
+fn validate_session_id(session_id: &str) -> async_graphql::Result<()> {
+    if session_id.is_empty() || session_id.len() > 64 {
+        return Err(async_graphql::Error::new(
+            "Session id must be 1-64 characters",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> async_graphql::Result<()> {
+    if name.len() > 200 {
+        return Err(async_graphql::Error::new(
+            "Plan name must be 200 characters or fewer",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_map_id(map_id: &str, assets: &Assets) -> async_graphql::Result<()> {
+    if assets.find_map_by_file_name(map_id).is_none() {
+        return Err(async_graphql::Error::new(format!(
+            "Unknown map: {}",
+            map_id
+        )));
+    }
+    Ok(())
+}
+
+fn validate_weapon_ids(weapon_ids: &[String], weapons: &[Weapon]) -> async_graphql::Result<()> {
+    if weapon_ids.len() > MAX_POSITIONS {
+        return Err(async_graphql::Error::new(format!(
+            "weapon_ids: too many entries ({}, max {})",
             weapon_ids.len(),
             MAX_POSITIONS
         )));
@@ -242,7 +1326,7 @@ fn validate_weapon_ids(weapon_ids: &[String], assets: &Assets) -> async_graphql:
         if wid.is_empty() || wid == UNASSIGNED_WEAPON {
             continue;
         }
-        if assets.find_weapon_by_slug(wid).is_none() {
+        if !weapons.iter().any(|w| w.slug() == *wid) {
             return Err(async_graphql::Error::new(format!(
                 "Unknown weapon: {}",
                 wid
@@ -252,7 +1336,89 @@ fn validate_weapon_ids(weapon_ids: &[String], assets: &Assets) -> async_graphql:
     Ok(())
 }
 
-fn validate_position(pos: &PositionInput, field_name: &str) -> async_graphql::Result<()> {
+/// The weapons of `profile`, or an error when the server has no figures for it.
+fn profile_weapons(assets: &Assets, profile: GqlWeaponProfile) -> async_graphql::Result<&[Weapon]> {
+    let profile = WeaponProfile::from(profile);
+    assets.weapons_of(profile).ok_or_else(|| {
+        async_graphql::Error::new(format!("No {} weapon data on this server", profile))
+    })
+}
+
+/// The weapon `slug` in `weapons` firing `ammo`, or why it can't.
+fn weapon_with_ammo(weapons: &[Weapon], slug: &str, ammo: &str) -> Result<Weapon, String> {
+    let weapon = weapons
+        .iter()
+        .find(|w| w.slug() == slug)
+        .ok_or_else(|| format!("Unknown weapon: {}", slug))?;
+    weapon
+        .with_ammo(ammo)
+        .ok_or_else(|| format!("{} doesn't fire {:?}", weapon.display_name, ammo.trim()))
+}
+
+/// Check each gun's shell against its weapon, returning them trimmed.
+fn validate_gun_ammo(
+    ammo: &[String],
+    weapon_ids: &[String],
+    weapons: &[Weapon],
+) -> async_graphql::Result<Vec<String>> {
+    if ammo.len() > MAX_POSITIONS {
+        return Err(async_graphql::Error::new(format!(
+            "gun_ammo: too many entries ({}, max {})",
+            ammo.len(),
+            MAX_POSITIONS
+        )));
+    }
+    ammo.iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let name = name.trim();
+            if name.is_empty() {
+                return Ok(String::new());
+            }
+            let slug = weapon_ids.get(i).map(String::as_str).unwrap_or_default();
+            weapon_with_ammo(weapons, slug, name)
+                .map(|_| name.to_string())
+                .map_err(|e| async_graphql::Error::new(format!("gun_ammo[{}]: {}", i, e)))
+        })
+        .collect()
+}
+
+/// Convert a position input to meters.
+fn resolve_position(
+    pos: &PositionInput,
+    space: CoordinateSpace,
+    scale: MapScale,
+    field_name: &str,
+) -> async_graphql::Result<Position> {
+    if space == CoordinateSpace::GridRef {
+        let grid_ref = pos.grid.as_deref().ok_or_else(|| {
+            async_graphql::Error::new(format!(
+                "{}: grid is required for GRID_REF positions",
+                field_name
+            ))
+        })?;
+        let (x, y) = grid::parse_grid_coord(grid_ref).ok_or_else(|| {
+            async_graphql::Error::new(format!(
+                "{}: invalid grid reference {:?}",
+                field_name, grid_ref
+            ))
+        })?;
+        return Ok(Position { x, y });
+    }
+    let (Some(x), Some(y)) = (pos.x, pos.y) else {
+        return Err(async_graphql::Error::new(format!(
+            "{}: x and y are required",
+            field_name
+        )));
+    };
+    let (x, y) = match space {
+        CoordinateSpace::ImagePx => scale.px_to_meters(x, y),
+        _ => (x, y),
+    };
+    Ok(Position { x, y })
+}
+
+fn validate_position(pos: &Position, field_name: &str) -> async_graphql::Result<()> {
     if !pos.x.is_finite() || !pos.y.is_finite() {
         return Err(async_graphql::Error::new(format!(
             "{}: coordinates must be finite numbers",
@@ -270,10 +1436,14 @@ fn validate_position(pos: &PositionInput, field_name: &str) -> async_graphql::Re
 
 const MAX_POSITIONS: usize = 50;
 
-fn validate_positions(
-    positions: &[PositionInput],
+/// Convert a list of position inputs to meters and check them.
+fn resolve_positions(
+    positions: Option<&[PositionInput]>,
+    space: CoordinateSpace,
+    scale: MapScale,
     field_name: &str,
-) -> async_graphql::Result<()> {
+) -> async_graphql::Result<Vec<Position>> {
+    let positions = positions.unwrap_or_default();
     if positions.len() > MAX_POSITIONS {
         return Err(async_graphql::Error::new(format!(
             "{}: too many entries ({}, max {})",
@@ -282,10 +1452,16 @@ fn validate_positions(
             MAX_POSITIONS
         )));
     }
-    for (i, pos) in positions.iter().enumerate() {
-        validate_position(pos, &format!("{}[{}]", field_name, i))?;
-    }
-    Ok(())
+    positions
+        .iter()
+        .enumerate()
+        .map(|(i, pos)| {
+            let field = format!("{}[{}]", field_name, i);
+            let pos = resolve_position(pos, space, scale, &field)?;
+            validate_position(&pos, &field)?;
+            Ok(pos)
+        })
+        .collect()
 }
 
 fn validate_gun_target_indices(
@@ -318,37 +1494,466 @@ fn validate_gun_target_indices(
     Ok(())
 }
 
-fn validate_wind_direction(dir: f64) -> async_graphql::Result<()> {
-    if !dir.is_finite() || !(0.0..360.0).contains(&dir) {
-        return Err(async_graphql::Error::new(
-            "wind_direction must be a finite number in range [0, 360)",
-        ));
+fn validate_labels(labels: &[String], field_name: &str) -> async_graphql::Result<()> {
+    if labels.len() > MAX_POSITIONS {
+        return Err(async_graphql::Error::new(format!(
+            "{}: too many entries ({}, max {})",
+            field_name,
+            labels.len(),
+            MAX_POSITIONS
+        )));
     }
-    Ok(())
-}
-
-fn validate_wind_strength(strength: u32) -> async_graphql::Result<()> {
-    if strength > 5 {
-        return Err(async_graphql::Error::new(
-            "wind_strength must be between 0 and 5",
-        ));
+    for (i, label) in labels.iter().enumerate() {
+        if label.trim().chars().count() > MAX_LABEL_LEN {
+            return Err(async_graphql::Error::new(format!(
+                "{}[{}]: label must be {} characters or fewer",
+                field_name, i, MAX_LABEL_LEN
+            )));
+        }
     }
     Ok(())
 }
 
-fn validate_create_plan(input: &CreatePlanInput, assets: &Assets) -> async_graphql::Result<()> {
-    validate_name(&input.name)?;
-    validate_map_id(&input.map_id, assets)?;
-    validate_weapon_ids(&input.weapon_ids, assets)?;
-    if let Some(positions) = &input.gun_positions {
-        validate_positions(positions, "gun_positions")?;
+/// Check a shot log against the plan's gun and target counts.
+fn validate_shot_log(
+    shots: &[ShotRecordInput],
+    guns: usize,
+    targets: usize,
+) -> async_graphql::Result<Vec<ShotRecord>> {
+    if shots.len() > MAX_SHOT_LOG {
+        return Err(async_graphql::Error::new(format!(
+            "shot_log: too many entries ({}, max {})",
+            shots.len(),
+            MAX_SHOT_LOG
+        )));
     }
-    if let Some(positions) = &input.target_positions {
-        validate_positions(positions, "target_positions")?;
+    let index = |v: i32, count: usize| usize::try_from(v).ok().filter(|&i| i < count);
+    shots
+        .iter()
+        .enumerate()
+        .map(|(i, shot)| {
+            let err = |msg: &str| async_graphql::Error::new(format!("shot_log[{}]: {}", i, msg));
+            let gun = index(shot.gun, guns).ok_or_else(|| err("gun index out of range"))?;
+            let target = match shot.target {
+                Some(t) => Some(index(t, targets).ok_or_else(|| err("target index out of range"))?),
+                None => None,
+            };
+            if !shot.azimuth.is_finite() || !(0.0..360.0).contains(&shot.azimuth) {
+                return Err(err("azimuth must be in [0, 360)"));
+            }
+            if !shot.distance.is_finite() || shot.distance < 0.0 {
+                return Err(err("distance must be a non-negative number"));
+            }
+            let result = shot.result.trim();
+            if result.chars().count() > MAX_SHOT_RESULT_LEN {
+                return Err(err(&format!(
+                    "result must be {} characters or fewer",
+                    MAX_SHOT_RESULT_LEN
+                )));
+            }
+            Ok(ShotRecord {
+                gun,
+                target,
+                azimuth: shot.azimuth,
+                distance: shot.distance,
+                result: result.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Check registration points against the plan's gun count, converting their
+/// positions to meters.
+fn validate_registrations(
+    points: &[RegistrationPointInput],
+    space: CoordinateSpace,
+    scale: MapScale,
+    guns: usize,
+) -> async_graphql::Result<Vec<RegistrationPoint>> {
+    if points.len() > MAX_REGISTRATIONS {
+        return Err(async_graphql::Error::new(format!(
+            "registrations: too many entries ({}, max {})",
+            points.len(),
+            MAX_REGISTRATIONS
+        )));
+    }
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, point)| {
+            let field = format!("registrations[{}]", i);
+            let err = |msg: &str| async_graphql::Error::new(format!("{}: {}", field, msg));
+            let gun = usize::try_from(point.gun)
+                .ok()
+                .filter(|&g| g < guns)
+                .ok_or_else(|| err("gun index out of range"))?;
+            let name = point.name.trim();
+            if name.is_empty() {
+                return Err(err("name must not be empty"));
+            }
+            if name.chars().count() > MAX_REGISTRATION_NAME_LEN {
+                return Err(err(&format!(
+                    "name must be {} characters or fewer",
+                    MAX_REGISTRATION_NAME_LEN
+                )));
+            }
+            let position = resolve_position(&point.position, space, scale, &field)?;
+            validate_position(&position, &field)?;
+            if !point.azimuth.is_finite() || !(0.0..360.0).contains(&point.azimuth) {
+                return Err(err("azimuth must be in [0, 360)"));
+            }
+            if !point.distance.is_finite() || point.distance < 0.0 {
+                return Err(err("distance must be a non-negative number"));
+            }
+            Ok(RegistrationPoint {
+                gun,
+                name: name.to_string(),
+                position,
+                azimuth: point.azimuth,
+                distance: point.distance,
+            })
+        })
+        .collect()
+}
+
+/// Check custom rings against the plan's marker counts, trimming their labels.
+fn validate_custom_rings(
+    rings: &[CustomRingInput],
+    positions: &PlanPositions,
+) -> async_graphql::Result<Vec<CustomRing>> {
+    if rings.len() > MAX_CUSTOM_RINGS {
+        return Err(async_graphql::Error::new(format!(
+            "custom_rings: too many entries ({}, max {})",
+            rings.len(),
+            MAX_CUSTOM_RINGS
+        )));
+    }
+    rings
+        .iter()
+        .enumerate()
+        .map(|(i, ring)| {
+            let err =
+                |msg: &str| async_graphql::Error::new(format!("custom_rings[{}]: {}", i, msg));
+            let anchor = RingAnchor::from(ring.anchor);
+            let markers = match anchor {
+                RingAnchor::Gun => positions.guns.len(),
+                RingAnchor::Target => positions.targets.len(),
+                RingAnchor::Spotter => positions.spotters.len(),
+            };
+            let index = usize::try_from(ring.index)
+                .ok()
+                .filter(|&i| i < markers)
+                .ok_or_else(|| err("index out of range"))?;
+            if !ring.radius.is_finite() || ring.radius <= 0.0 || ring.radius > MAX_RING_RADIUS {
+                return Err(err(&format!(
+                    "radius must be above 0 and at most {} m",
+                    MAX_RING_RADIUS
+                )));
+            }
+            let label = ring.label.trim();
+            if label.chars().count() > MAX_LABEL_LEN {
+                return Err(err(&format!(
+                    "label must be {} characters or fewer",
+                    MAX_LABEL_LEN
+                )));
+            }
+            Ok(CustomRing {
+                anchor,
+                index,
+                radius: ring.radius,
+                label: label.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Check no-fire zone radii: no more than there are zones, each within
+/// [`MAX_NO_FIRE_RADIUS`]. `field_name` names the list in errors.
+fn validate_no_fire_radii(
+    radii: &[f64],
+    zones: usize,
+    field_name: &str,
+) -> async_graphql::Result<()> {
+    if radii.len() > zones {
+        return Err(async_graphql::Error::new(format!(
+            "{}: {} entries for {} zones",
+            field_name,
+            radii.len(),
+            zones
+        )));
+    }
+    for (i, &radius) in radii.iter().enumerate() {
+        validate_no_fire_radius(radius, &format!("{}[{}]", field_name, i))?;
+    }
+    Ok(())
+}
+
+fn validate_no_fire_radius(radius: f64, field_name: &str) -> async_graphql::Result<()> {
+    if !radius.is_finite() || radius <= 0.0 || radius > MAX_NO_FIRE_RADIUS {
+        return Err(async_graphql::Error::new(format!(
+            "{}: radius must be above 0 and at most {} m",
+            field_name, MAX_NO_FIRE_RADIUS
+        )));
+    }
+    Ok(())
+}
+
+fn validate_wind_direction(dir: f64) -> async_graphql::Result<()> {
+    if !dir.is_finite() || !(0.0..360.0).contains(&dir) {
+        return Err(async_graphql::Error::new(
+            "wind_direction must be a finite number in range [0, 360)",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_wind_strength(strength: u32) -> async_graphql::Result<()> {
+    if strength > 5 {
+        return Err(async_graphql::Error::new(
+            "wind_strength must be between 0 and 5",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_impact_observation(
+    input: ImpactObservationInput,
+    weapon: &Weapon,
+) -> async_graphql::Result<ImpactObservation> {
+    if !input.azimuth.is_finite() || !(0.0..360.0).contains(&input.azimuth) {
+        return Err(async_graphql::Error::new(
+            "azimuth must be a finite number in range [0, 360)",
+        ));
+    }
+    if !input.distance.is_finite() || input.distance <= 0.0 || input.distance > weapon.max_range {
+        return Err(async_graphql::Error::new(format!(
+            "distance must be within the weapon's range (up to {} m)",
+            weapon.max_range
+        )));
+    }
+    if let Some(direction) = input.wind_direction {
+        validate_wind_direction(direction)?;
+    }
+    validate_wind_strength(input.wind_strength)?;
+    let offsets = [input.compensated_drift.abs(), input.long, input.right];
+    if offsets
+        .iter()
+        .any(|m| !m.is_finite() || m.abs() > MAX_IMPACT_OFFSET)
+    {
+        return Err(async_graphql::Error::new(format!(
+            "Impact offsets must be at most {} m",
+            MAX_IMPACT_OFFSET
+        )));
+    }
+    Ok(ImpactObservation {
+        weapon_slug: input.weapon_id,
+        azimuth: input.azimuth,
+        distance: input.distance,
+        wind_direction: input.wind_direction,
+        wind_strength: input.wind_strength as u8,
+        compensated_drift: input.compensated_drift,
+        long: input.long,
+        right: input.right,
+    })
+}
+
+const MAX_IMAGE_URL_LEN: usize = 2048;
+/// Largest custom map image side accepted, in pixels.
+const MAX_IMAGE_SIDE_PX: u32 = 16384;
+
+/// Check a custom map image and its reference points.
+fn validate_calibration(input: &CalibrationInput) -> async_graphql::Result<MapCalibration> {
+    let image_url = input.image_url.trim();
+    if !(image_url.starts_with("https://") || image_url.starts_with("http://"))
+        || image_url.len() > MAX_IMAGE_URL_LEN
+    {
+        return Err(async_graphql::Error::new(format!(
+            "calibration.image_url must be an http(s) URL of at most {} characters",
+            MAX_IMAGE_URL_LEN
+        )));
+    }
+    for (side, field_name) in [(input.width, "width"), (input.height, "height")] {
+        if !(1..=MAX_IMAGE_SIDE_PX).contains(&side) {
+            return Err(async_graphql::Error::new(format!(
+                "calibration.{}: must be between 1 and {} pixels",
+                field_name, MAX_IMAGE_SIDE_PX
+            )));
+        }
+    }
+    let [a, b] = input.points.as_slice() else {
+        return Err(async_graphql::Error::new(
+            "calibration.points: exactly two reference points are needed",
+        ));
+    };
+    let mut points = Vec::with_capacity(2);
+    for (i, point) in [a, b].into_iter().enumerate() {
+        let field = format!("calibration.points[{}]", i);
+        let on_image = (0.0..=input.width as f64).contains(&point.image_x)
+            && (0.0..=input.height as f64).contains(&point.image_y);
+        if !on_image {
+            return Err(async_graphql::Error::new(format!(
+                "{}: image_x/image_y must be on the image",
+                field
+            )));
+        }
+        let space = if point.position.grid.is_some() {
+            CoordinateSpace::GridRef
+        } else {
+            CoordinateSpace::Meters
+        };
+        let meters = resolve_position(&point.position, space, MapScale::STANDARD, &field)?;
+        validate_position(&meters, &field)?;
+        points.push(CalibrationPoint {
+            px: Position {
+                x: point.image_x,
+                y: point.image_y,
+            },
+            meters,
+        });
     }
-    if let Some(positions) = &input.spotter_positions {
-        validate_positions(positions, "spotter_positions")?;
+    let calibration = MapCalibration {
+        image_url: image_url.to_string(),
+        width: input.width,
+        height: input.height,
+        points: [points[0], points[1]],
+    };
+    if calibration.scale().is_none() {
+        return Err(async_graphql::Error::new(
+            "calibration.points: reference points must be apart in both x and y, in the same orientation as the map",
+        ));
+    }
+    Ok(calibration)
+}
+
+/// Check a battery template input and convert it to the shared model.
+fn validate_battery_template(
+    input: BatteryTemplateInput,
+    assets: &Assets,
+) -> async_graphql::Result<BatteryTemplate> {
+    validate_name(&input.name)?;
+    if input.guns.is_empty() || input.guns.len() > MAX_TEMPLATE_GUNS {
+        return Err(async_graphql::Error::new(format!(
+            "guns: a template holds 1 to {} guns",
+            MAX_TEMPLATE_GUNS
+        )));
     }
+    let weapon_ids: Vec<String> = input.guns.iter().map(|g| g.weapon_id.clone()).collect();
+    validate_weapon_ids(&weapon_ids, &assets.weapons)?;
+    for (i, g) in input.guns.iter().enumerate() {
+        let on_map = g.offset_x.abs() <= MAP_WIDTH_M && g.offset_y.abs() <= MAP_HEIGHT_M;
+        if !(g.offset_x.is_finite() && g.offset_y.is_finite() && on_map) {
+            return Err(async_graphql::Error::new(format!(
+                "guns[{}]: offset must be finite and within the map size",
+                i
+            )));
+        }
+    }
+    Ok(BatteryTemplate {
+        name: input.name,
+        guns: input
+            .guns
+            .into_iter()
+            .map(|g| TemplateGun {
+                weapon_id: g.weapon_id,
+                offset: Position {
+                    x: g.offset_x,
+                    y: g.offset_y,
+                },
+            })
+            .collect(),
+    })
+}
+
+/// Plan positions converted to meters, with the custom map image they were
+/// placed on.
+#[derive(Default)]
+struct PlanPositions {
+    guns: Vec<Position>,
+    targets: Vec<Position>,
+    spotters: Vec<Position>,
+    intel: IntelMarkers,
+    calibration: Option<MapCalibration>,
+    shot_log: Vec<ShotRecord>,
+    registrations: Vec<RegistrationPoint>,
+    gun_ammo: Vec<String>,
+    range_rings: RangeRings,
+}
+
+/// Check a plan input, returning its positions in meters along with the
+/// per-gun data that needs checking against them.
+fn validate_create_plan(
+    input: &CreatePlanInput,
+    assets: &Assets,
+) -> async_graphql::Result<PlanPositions> {
+    validate_name(&input.name)?;
+    validate_map_id(&input.map_id, assets)?;
+    let weapons = profile_weapons(assets, input.profile)?;
+    validate_weapon_ids(&input.weapon_ids, weapons)?;
+    let space = input.coordinate_space;
+    let calibration = input
+        .calibration
+        .as_ref()
+        .map(validate_calibration)
+        .transpose()?;
+    let scale = match calibration.as_ref().and_then(MapCalibration::scale) {
+        Some(scale) => scale,
+        None => assets
+            .find_map_by_file_name(&input.map_id)
+            .map(GameMap::scale)
+            .unwrap_or_default(),
+    };
+    let mut positions = PlanPositions {
+        guns: resolve_positions(
+            input.gun_positions.as_deref(),
+            space,
+            scale,
+            "gun_positions",
+        )?,
+        targets: resolve_positions(
+            input.target_positions.as_deref(),
+            space,
+            scale,
+            "target_positions",
+        )?,
+        spotters: resolve_positions(
+            input.spotter_positions.as_deref(),
+            space,
+            scale,
+            "spotter_positions",
+        )?,
+        intel: IntelMarkers {
+            enemy_guns: resolve_positions(
+                input.enemy_gun_positions.as_deref(),
+                space,
+                scale,
+                "enemy_gun_positions",
+            )?,
+            fobs: resolve_positions(
+                input.fob_positions.as_deref(),
+                space,
+                scale,
+                "fob_positions",
+            )?,
+            bunkers: resolve_positions(
+                input.bunker_positions.as_deref(),
+                space,
+                scale,
+                "bunker_positions",
+            )?,
+            no_fire_zones: resolve_positions(
+                input.no_fire_zone_positions.as_deref(),
+                space,
+                scale,
+                "no_fire_zone_positions",
+            )?,
+            no_fire_radii: Vec::new(),
+        },
+        calibration,
+        shot_log: Vec::new(),
+        registrations: Vec::new(),
+        gun_ammo: Vec::new(),
+        range_rings: RangeRings::default(),
+    };
     if let Some(indices) = &input.gun_target_indices {
         let target_count = input
             .target_positions
@@ -357,13 +1962,317 @@ fn validate_create_plan(input: &CreatePlanInput, assets: &Assets) -> async_graph
             .unwrap_or(0);
         validate_gun_target_indices(indices, target_count)?;
     }
+    for (labels, field_name) in [
+        (&input.gun_labels, "gun_labels"),
+        (&input.target_labels, "target_labels"),
+        (&input.spotter_labels, "spotter_labels"),
+    ] {
+        if let Some(labels) = labels {
+            validate_labels(labels, field_name)?;
+        }
+    }
+    if let Some(statuses) = &input.target_statuses {
+        if statuses.len() > positions.targets.len() {
+            return Err(async_graphql::Error::new(format!(
+                "target_statuses: {} entries for {} targets",
+                statuses.len(),
+                positions.targets.len()
+            )));
+        }
+    }
+    if let Some(shots) = &input.shot_log {
+        positions.shot_log =
+            validate_shot_log(shots, positions.guns.len(), positions.targets.len())?;
+    }
+    if let Some(ammo) = &input.gun_ammo {
+        positions.gun_ammo = validate_gun_ammo(ammo, &input.weapon_ids, weapons)?;
+    }
+    if let Some(points) = &input.registrations {
+        positions.registrations =
+            validate_registrations(points, space, scale, positions.guns.len())?;
+    }
+    if let Some(hidden) = &input.gun_rings_hidden {
+        if hidden.len() > positions.guns.len() {
+            return Err(async_graphql::Error::new(format!(
+                "gun_rings_hidden: {} entries for {} guns",
+                hidden.len(),
+                positions.guns.len()
+            )));
+        }
+        positions.range_rings.hidden = hidden.clone();
+    }
+    if let Some(rings) = &input.custom_rings {
+        positions.range_rings.custom = validate_custom_rings(rings, &positions)?;
+    }
+    if let Some(radii) = &input.no_fire_zone_radii {
+        validate_no_fire_radii(
+            radii,
+            positions.intel.no_fire_zones.len(),
+            "no_fire_zone_radii",
+        )?;
+        positions.intel.no_fire_radii = radii.clone();
+    }
     if let Some(dir) = input.wind_direction {
         validate_wind_direction(dir)?;
     }
     if let Some(strength) = input.wind_strength {
         validate_wind_strength(strength)?;
     }
-    Ok(())
+    Ok(positions)
+}
+
+/// Work out the firing solution for every paired gun in a plan.
+fn fire_missions(plan: &models::Plan, assets: &Assets) -> Vec<GqlFireMission> {
+    let wind = plan.wind_direction.map(|direction| WindInput {
+        direction,
+        strength: plan.wind_strength,
+    });
+    let zones = plan.intel.no_fire_circles();
+    let weapons = assets.weapons_of(plan.weapon_profile).unwrap_or_default();
+    plan.gun_positions
+        .iter()
+        .enumerate()
+        .filter_map(|(gi, &gun)| {
+            let ti = plan.gun_target_indices.get(gi).copied().flatten()?;
+            let target = *plan.target_positions.get(ti)?;
+            let weapon_id = plan.weapon_ids.get(gi)?;
+            let ammo = models::gun_ammo(&plan.gun_ammo, gi);
+            let weapon = weapon_with_ammo(weapons, weapon_id, ammo).ok()?;
+            let sol = calc::firing_solution(gun, target, &weapon, wind.as_ref());
+            Some(GqlFireMission {
+                gun: gi as u32 + 1,
+                weapon_id: weapon_id.clone(),
+                weapon_name: weapon.display_name.clone(),
+                ammo: ammo.to_string(),
+                gun_grid: format_grid_coord(gun.x, gun.y),
+                target: ti as u32 + 1,
+                target_grid: format_grid_coord(target.x, target.y),
+                solution: GqlFiringSolution::with_danger_close(sol, target, &zones),
+            })
+        })
+        .collect()
+}
+
+/// Reject changes to a protected plan unless `edit_token` matches.
+fn require_edit_token(
+    storage: &Storage,
+    plan_id: &str,
+    edit_token: Option<&str>,
+) -> async_graphql::Result<()> {
+    match storage
+        .edit_token_matches(plan_id, edit_token)
+        .map_err(internal_err("Failed to check edit token"))?
+    {
+        Some(false) => Err(async_graphql::Error::new("Invalid edit token")),
+        _ => Ok(()),
+    }
+}
+
+fn cleanup_stats(
+    storage: &Storage,
+    retention: &RetentionPolicy,
+) -> async_graphql::Result<GqlCleanupStats> {
+    let cleanup = storage
+        .cleanup_stats()
+        .map_err(internal_err("Failed to get cleanup stats"))?;
+    Ok(GqlCleanupStats {
+        plans_expired: cleanup.plans_expired,
+        bytes_reclaimed: cleanup.bytes_reclaimed,
+        last_run_at: cleanup.last_run_at.and_then(timestamp_to_rfc3339),
+        retention_days: retention.plan_ttl_secs.map(|secs| secs as f64 / 86_400.0),
+    })
+}
+
+/// Gun placements per weapon within `scope` and their totals per faction.
+/// Weapons both sides field count towards each side's total.
+pub fn gun_placement_stats(
+    storage: &Storage,
+    assets: &Assets,
+    scope: PlacementScope,
+) -> Result<(Vec<GqlWeaponPlacementStat>, GqlFactionPlacementStats), String> {
+    let raw_counts = storage.get_gun_placement_counts(scope)?;
+
+    let mut colonial_total: u64 = 0;
+    let mut warden_total: u64 = 0;
+    let mut overall_total: u64 = 0;
+    let mut gun_placements = Vec::new();
+
+    for (slug, count) in raw_counts {
+        let (display_name, faction): (String, Faction) = if slug == UNASSIGNED_WEAPON {
+            ("Unassigned".to_string(), Faction::Both)
+        } else {
+            match assets.find_weapon_by_slug(&slug) {
+                Some(w) => (w.display_name.clone(), w.faction),
+                None => (slug.clone(), Faction::Both),
+            }
+        };
+        match faction {
+            Faction::Colonial => colonial_total += count,
+            Faction::Warden => warden_total += count,
+            Faction::Both => {
+                colonial_total += count;
+                warden_total += count;
+            }
+        }
+        overall_total += count;
+        gun_placements.push(GqlWeaponPlacementStat {
+            weapon_slug: slug,
+            display_name,
+            faction: faction.into(),
+            count,
+        });
+    }
+
+    let totals = GqlFactionPlacementStats {
+        colonial: colonial_total,
+        warden: warden_total,
+        total: overall_total,
+    };
+    Ok((gun_placements, totals))
+}
+
+/// Placements per map, most placements first. Maps no longer in the assets
+/// keep their file name as display name.
+fn map_placement_stats(
+    storage: &Storage,
+    assets: &Assets,
+) -> Result<Vec<GqlMapPlacementStat>, String> {
+    let mut maps: Vec<GqlMapPlacementStat> = Vec::new();
+    for (map_id, counter, count) in storage.placements_by_map()? {
+        // Rows come grouped by map
+        if maps.last().is_none_or(|m| m.map_id != map_id) {
+            let display_name = assets
+                .find_map_by_file_name(&map_id)
+                .map_or_else(|| map_id.clone(), |m| m.display_name.clone());
+            maps.push(GqlMapPlacementStat {
+                map_id,
+                display_name,
+                guns: 0,
+                targets: 0,
+                spotters: 0,
+            });
+        }
+        let Some(map) = maps.last_mut() else {
+            continue;
+        };
+        match counter.as_str() {
+            "gun" => map.guns += count,
+            "target" => map.targets += count,
+            "spotter" => map.spotters += count,
+            _ => {}
+        }
+    }
+    maps.sort_by_key(|m| std::cmp::Reverse(m.guns + m.targets + m.spotters));
+    Ok(maps)
+}
+
+/// The placements `stats` covers: `war`, the UTC dates `from` to `to` (both
+/// included, `to` defaulting to `today`), or everything.
+fn placement_scope(
+    war: Option<u32>,
+    from: Option<&str>,
+    to: Option<&str>,
+    today: u32,
+) -> async_graphql::Result<PlacementScope> {
+    let day = |field: &str, value: &str| -> async_graphql::Result<u32> {
+        let date = chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d").map_err(|_| {
+            async_graphql::Error::new(format!("{} must be a date like 2024-05-31", field))
+        })?;
+        let days = date
+            .signed_duration_since(chrono::DateTime::UNIX_EPOCH.date_naive())
+            .num_days();
+        u32::try_from(days)
+            .map_err(|_| async_graphql::Error::new(format!("{} is before 1970", field)))
+    };
+    match (war, from, to) {
+        (None, None, None) => Ok(PlacementScope::All),
+        (Some(war), None, None) => Ok(PlacementScope::War(war)),
+        (Some(_), _, _) => Err(async_graphql::Error::new(
+            "Pass either war or from/to, not both",
+        )),
+        (None, from, to) => {
+            let from = from.map(|v| day("from", v)).transpose()?.unwrap_or(0);
+            let to = to.map(|v| day("to", v)).transpose()?.unwrap_or(today);
+            if from > to {
+                return Err(async_graphql::Error::new("from is after to"));
+            }
+            Ok(PlacementScope::Days { from, to })
+        }
+    }
+}
+
+/// Day `day` since the Unix epoch as `YYYY-MM-DD`.
+fn day_to_date(day: u32) -> Option<String> {
+    chrono::DateTime::from_timestamp(day as i64 * 86_400, 0)
+        .map(|t| t.format("%Y-%m-%d").to_string())
+}
+
+fn timestamp_to_rfc3339(secs: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(secs, 0).map(|t| t.to_rfc3339())
+}
+
+fn trim_labels(labels: Option<Vec<String>>) -> Vec<String> {
+    labels
+        .unwrap_or_default()
+        .into_iter()
+        .map(|l| l.trim().to_string())
+        .collect()
+}
+
+/// Build a plan from validated input. Version starts at 0; timestamps are now.
+fn plan_from_input(
+    id: uuid::Uuid,
+    input: CreatePlanInput,
+    positions: PlanPositions,
+    retention: &RetentionPolicy,
+    war_number: Option<u32>,
+    weapon_data_version: Option<String>,
+) -> models::Plan {
+    let saved_at = chrono::Utc::now();
+    let now = saved_at.to_rfc3339();
+
+    models::Plan {
+        id,
+        name: input.name,
+        map_id: input.map_id,
+        weapon_ids: input.weapon_ids,
+        gun_ammo: positions.gun_ammo,
+        gun_position: None,
+        target_position: None,
+        spotter_position: None,
+        gun_positions: positions.guns,
+        target_positions: positions.targets,
+        spotter_positions: positions.spotters,
+        gun_target_indices: input
+            .gun_target_indices
+            .unwrap_or_default()
+            .into_iter()
+            .map(|o| o.map(|v| v as usize))
+            .collect(),
+        gun_labels: trim_labels(input.gun_labels),
+        target_labels: trim_labels(input.target_labels),
+        spotter_labels: trim_labels(input.spotter_labels),
+        target_statuses: input
+            .target_statuses
+            .unwrap_or_default()
+            .into_iter()
+            .map(TargetStatus::from)
+            .collect(),
+        wind_direction: input.wind_direction,
+        wind_strength: input.wind_strength.unwrap_or(0) as u8,
+        version: 0,
+        expires_at: retention.expires_at(saved_at.timestamp()),
+        war_number,
+        calibration: positions.calibration,
+        shot_log: positions.shot_log,
+        registrations: positions.registrations,
+        range_rings: positions.range_rings,
+        intel: positions.intel,
+        weapon_data_version,
+        weapon_profile: input.profile.into(),
+        created_at: now.clone(),
+        updated_at: now,
+    }
 }
 
 // Query root
@@ -377,7 +2286,7 @@ impl QueryRoot {
         ctx: &Context<'_>,
         active_only: Option<bool>,
     ) -> async_graphql::Result<Vec<GqlGameMap>> {
-        let assets = ctx_data::<Arc<Assets>>(ctx)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
         Ok(assets
             .maps
             .iter()
@@ -393,6 +2302,149 @@ impl QueryRoot {
                 file_name: m.file_name.clone(),
                 image_type: m.image_type.clone(),
                 active: m.active,
+                width: m.width,
+                height: m.height,
+                default_view: m.default_view.map(|v| GqlMapViewPreset {
+                    zoom: v.zoom,
+                    center: GqlPosition::from(&v.center),
+                }),
+            })
+            .collect())
+    }
+
+    /// Town halls, relic bases and other structures on a map, live from the
+    /// Foxhole War API. `null` while there's no data for the map: it isn't part
+    /// of the current war, the first poll hasn't finished or polling is off.
+    async fn map_structures(
+        &self,
+        ctx: &Context<'_>,
+        map_id: String,
+    ) -> async_graphql::Result<Option<GqlMapStructures>> {
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let map = assets
+            .find_map_by_file_name(&map_id)
+            .ok_or_else(|| async_graphql::Error::new(format!("Unknown map: {}", map_id)))?;
+        let war_maps = ctx_data::<Arc<WarMaps>>(ctx)?;
+        Ok(war_maps
+            .get(&map.war_api_map_name())
+            .map(|hex| GqlMapStructures::from(&*hex)))
+    }
+
+    /// A map's terrain mask as rows of cells, north first: `.` land, `~`
+    /// water, `#` impassable. `null` when the map has none.
+    async fn terrain_mask(
+        &self,
+        ctx: &Context<'_>,
+        map_id: String,
+    ) -> async_graphql::Result<Option<Vec<String>>> {
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        assets
+            .find_map_by_file_name(&map_id)
+            .ok_or_else(|| async_graphql::Error::new(format!("Unknown map: {}", map_id)))?;
+        Ok(assets.terrain_mask(&map_id).map(|mask| mask.rows()))
+    }
+
+    /// A battery template shared with `shareBatteryTemplate`.
+    async fn battery_template(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+    ) -> async_graphql::Result<Option<GqlBatteryTemplate>> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        Ok(storage
+            .battery_template(&id)
+            .map_err(internal_err("Failed to load battery template"))?
+            .map(|t| GqlBatteryTemplate::new(&id, t)))
+    }
+
+    /// Submitted impact observations pooled per weapon, beside the accuracy
+    /// and wind drift figures the planner uses. Weapons without any are left out.
+    async fn impact_research(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<GqlImpactSummary>> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let observations = storage
+            .impact_observations()
+            .map_err(internal_err("Failed to load impact observations"))?;
+        let summaries = observations
+            .chunk_by(|a, b| a.weapon_slug == b.weapon_slug)
+            .filter_map(|group| {
+                let slug = &group[0].weapon_slug;
+                let weapon = assets.find_weapon_by_slug(slug)?;
+                let summary = research::summarize(weapon, group)?;
+                Some(GqlImpactSummary {
+                    weapon_slug: slug.clone(),
+                    display_name: weapon.display_name.clone(),
+                    faction: weapon.faction.into(),
+                    samples: summary.samples as u64,
+                    mean_distance: summary.mean_distance,
+                    mean_miss: summary.mean_miss,
+                    mean_long: summary.mean_long,
+                    mean_right: summary.mean_right,
+                    predicted_accuracy_radius: summary.predicted_accuracy_radius,
+                    wind_samples: summary.wind_samples as u64,
+                    observed_wind_drift: summary.observed_wind_drift,
+                    predicted_wind_drift: summary.predicted_wind_drift,
+                })
+            })
+            .collect();
+        Ok(summaries)
+    }
+
+    /// The freshest wind report for a map, or `null` when nobody has reported
+    /// the wind there in the last 30 minutes.
+    async fn wind_report(
+        &self,
+        ctx: &Context<'_>,
+        map_id: String,
+    ) -> async_graphql::Result<Option<GqlWindReport>> {
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        if assets.find_map_by_file_name(&map_id).is_none() {
+            return Err(async_graphql::Error::new(format!(
+                "Unknown map: {}",
+                map_id
+            )));
+        }
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let report = storage
+            .wind_report(&map_id)
+            .map_err(internal_err("Failed to load wind report"))?;
+        let now = chrono::Utc::now().timestamp();
+        Ok(report
+            .filter(|r| now - r.reported_at <= WIND_REPORT_MAX_AGE_SECS)
+            .map(GqlWindReport::from))
+    }
+
+    /// Game patch the weapon figures are from, as set in `weapons.json`.
+    /// Saved plans record it as `weaponDataVersion`.
+    async fn game_version(
+        &self,
+        ctx: &Context<'_>,
+        profile: Option<GqlWeaponProfile>,
+    ) -> async_graphql::Result<Option<String>> {
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let profile = profile.unwrap_or_default();
+        profile_weapons(&assets, profile)?;
+        Ok(assets.game_version_of(profile.into()).map(str::to_string))
+    }
+
+    /// The weapon profiles this server has figures for, Live first.
+    async fn weapon_profiles(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<GqlWeaponProfileInfo>> {
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        Ok(WeaponProfile::ALL
+            .into_iter()
+            .filter_map(|profile| {
+                let weapons = assets.weapons_of(profile)?;
+                Some(GqlWeaponProfileInfo {
+                    profile: profile.into(),
+                    game_version: assets.game_version_of(profile).map(str::to_string),
+                    weapons: weapons.len(),
+                })
             })
             .collect())
     }
@@ -401,10 +2453,10 @@ impl QueryRoot {
         &self,
         ctx: &Context<'_>,
         faction: Option<GqlFaction>,
+        profile: Option<GqlWeaponProfile>,
     ) -> async_graphql::Result<Vec<GqlWeapon>> {
-        let assets = ctx_data::<Arc<Assets>>(ctx)?;
-        Ok(assets
-            .weapons
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        Ok(profile_weapons(&assets, profile.unwrap_or_default())?
             .iter()
             .filter(|w| match faction {
                 Some(f) => {
@@ -423,6 +2475,30 @@ impl QueryRoot {
                 acc_radius_max: w.acc_radius[1],
                 wind_drift_min: w.wind_drift[0],
                 wind_drift_max: w.wind_drift[1],
+                flight_time_min: w.flight_time[0],
+                flight_time_max: w.flight_time[1],
+                ammo: w
+                    .ammo
+                    .iter()
+                    .filter_map(|a| {
+                        let shell = w.with_ammo(&a.name)?;
+                        Some(GqlAmmoType {
+                            name: a.name.clone(),
+                            min_range: shell.min_range,
+                            max_range: shell.max_range,
+                            acc_radius_min: shell.acc_radius[0],
+                            acc_radius_max: shell.acc_radius[1],
+                            wind_drift_min: shell.wind_drift[0],
+                            wind_drift_max: shell.wind_drift[1],
+                            flight_time_min: shell.flight_time[0],
+                            flight_time_max: shell.flight_time[1],
+                        })
+                    })
+                    .collect(),
+                crew: w.deployment.crew,
+                deploy_time: w.deployment.deploy_time,
+                mount: w.deployment.mount.map(Into::into),
+                area_fire: w.area_fire,
             })
             .collect())
     }
@@ -432,110 +2508,212 @@ impl QueryRoot {
         ctx: &Context<'_>,
         input: CalculateInput,
     ) -> async_graphql::Result<GqlFiringSolution> {
-        let assets = ctx_data::<Arc<Assets>>(ctx)?;
-        let weapon = assets
-            .find_weapon_by_slug(&input.weapon_id)
-            .ok_or_else(|| {
-                async_graphql::Error::new(format!("Unknown weapon: {}", input.weapon_id))
-            })?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let weapon = weapon_with_ammo(
+            profile_weapons(&assets, input.profile)?,
+            &input.weapon_id,
+            input.ammo.as_deref().unwrap_or_default(),
+        )
+        .map_err(async_graphql::Error::new)?;
 
-        let gun = Position {
-            x: input.gun_position.x,
-            y: input.gun_position.y,
-        };
-        let target = Position {
-            x: input.target_position.x,
-            y: input.target_position.y,
+        let scale = match &input.map_id {
+            Some(map_id) => assets
+                .find_map_by_file_name(map_id)
+                .ok_or_else(|| async_graphql::Error::new(format!("Unknown map: {}", map_id)))?
+                .scale(),
+            None => MapScale::default(),
         };
+        let space = input.coordinate_space;
+        let gun = resolve_position(&input.gun_position, space, scale, "gun_position")?;
+        let target = resolve_position(&input.target_position, space, scale, "target_position")?;
         let wind = input.wind.map(|w| WindInput {
             direction: w.direction,
             strength: w.strength as u8,
         });
 
-        let sol = calc::firing_solution(gun, target, weapon, wind.as_ref());
+        let mut zones = Vec::new();
+        for (i, zone) in input.no_fire_zones.iter().flatten().enumerate() {
+            let field = format!("no_fire_zones[{}]", i);
+            validate_no_fire_radius(zone.radius, &field)?;
+            zones.push(NoFireZone {
+                center: resolve_position(&zone.center, space, scale, &field)?,
+                radius: zone.radius,
+            });
+        }
+
+        let sol = calc::firing_solution(gun, target, &weapon, wind.as_ref());
 
-        Ok(GqlFiringSolution {
-            azimuth: sol.azimuth,
-            distance: sol.distance,
-            in_range: sol.in_range,
-            accuracy_radius: sol.accuracy_radius,
-            wind_adjusted_azimuth: sol.wind_adjusted_azimuth,
-            wind_adjusted_distance: sol.wind_adjusted_distance,
-            wind_offset_meters: sol.wind_offset_meters,
-        })
+        Ok(GqlFiringSolution::with_danger_close(sol, target, &zones))
     }
 
+    /// A plan by its id or its short link slug.
     async fn plan(&self, ctx: &Context<'_>, id: ID) -> async_graphql::Result<Option<GqlPlan>> {
         let storage = ctx_data::<Arc<Storage>>(ctx)?;
-        let plan = storage.get_plan(&id).map_err(internal_err("Failed to load plan"))?;
+        let plan = storage.find_plan(&id).map_err(internal_err("Failed to load plan"))?;
         Ok(plan.map(GqlPlan::from))
     }
 
-    async fn stats(&self, ctx: &Context<'_>) -> async_graphql::Result<GqlStats> {
+    /// A plan's earlier versions, newest first. Each `updatePlan` adds the version it replaced.
+    async fn plan_versions(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+    ) -> async_graphql::Result<Vec<GqlPlan>> {
         let storage = ctx_data::<Arc<Storage>>(ctx)?;
-        let assets = ctx_data::<Arc<Assets>>(ctx)?;
-        let total_plans = storage.count_plans().map_err(internal_err("Failed to count plans"))?;
-        let db_size_bytes = storage
-            .db_size_bytes()
-            .map_err(internal_err("Failed to get database size"))?;
+        let versions = storage
+            .plan_versions(&id)
+            .map_err(internal_err("Failed to load plan versions"))?;
+        Ok(versions.into_iter().map(GqlPlan::from).collect())
+    }
 
-        let raw_counts = storage
-            .get_gun_placement_counts()
-            .map_err(internal_err("Failed to get gun placement counts"))?;
-
-        let mut colonial_total: u64 = 0;
-        let mut warden_total: u64 = 0;
-        let mut overall_total: u64 = 0;
-        let mut gun_placements = Vec::new();
-
-        for (slug, count) in raw_counts {
-            let (display_name, faction): (String, Faction) = if slug == UNASSIGNED_WEAPON {
-                ("Unassigned".to_string(), Faction::Both)
-            } else {
-                match assets.find_weapon_by_slug(&slug) {
-                    Some(w) => (w.display_name.clone(), w.faction),
-                    None => (slug.clone(), Faction::Both),
-                }
-            };
-            match faction {
-                Faction::Colonial => colonial_total += count,
-                Faction::Warden => warden_total += count,
-                Faction::Both => {
-                    colonial_total += count;
-                    warden_total += count;
-                }
-            }
-            overall_total += count;
-            gun_placements.push(GqlWeaponPlacementStat {
-                weapon_slug: slug,
-                display_name,
-                faction: faction.into(),
-                count,
-            });
-        }
+    /// A saved plan as a self-contained document (positions in meters, weapons,
+    /// wind, labels) that `importPlan` turns back into a plan.
+    async fn export_plan(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        #[graphql(default)] format: PlanFormat,
+    ) -> async_graphql::Result<Option<String>> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let plan = storage
+            .find_plan(&id)
+            .map_err(internal_err("Failed to load plan"))?;
+        Ok(plan.map(|plan| match format {
+            PlanFormat::Json => document::plan_document(&plan, &assets),
+        }))
+    }
 
-        let target_count = storage
-            .get_marker_placement_count("target")
-            .map_err(internal_err("Failed to get target placement count"))?;
-        let spotter_count = storage
-            .get_marker_placement_count("spotter")
-            .map_err(internal_err("Failed to get spotter placement count"))?;
+    /// Where created plans put their guns and targets on a map, per grid cell.
+    /// Cells nobody used are left out.
+    async fn placement_heatmap(
+        &self,
+        ctx: &Context<'_>,
+        map_id: String,
+    ) -> async_graphql::Result<GqlPlacementHeatmap> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let guns = storage
+            .placement_heatmap(&map_id, "gun")
+            .map_err(internal_err("Failed to load gun heatmap"))?;
+        let targets = storage
+            .placement_heatmap(&map_id, "target")
+            .map_err(internal_err("Failed to load target heatmap"))?;
+        Ok(GqlPlacementHeatmap {
+            guns: GqlHeatmapCell::from_counts(guns),
+            targets: GqlHeatmapCell::from_counts(targets),
+        })
+    }
+
+    /// Barrage sheet for a saved plan. Guns without a target or a known weapon are skipped.
+    async fn plan_fire_missions(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+    ) -> async_graphql::Result<Option<Vec<GqlFireMission>>> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let Some(plan) = storage
+            .find_plan(&id)
+            .map_err(internal_err("Failed to load plan"))?
+        else {
+            return Ok(None);
+        };
+        Ok(Some(fire_missions(&plan, &assets)))
+    }
+
+    /// Server statistics. Placement figures cover `war` only, or the UTC days
+    /// `from` to `to` (`YYYY-MM-DD`, both included; `to` defaults to today)
+    /// when given, otherwise every war. The other figures are always lifetime
+    /// totals.
+    async fn stats(
+        &self,
+        ctx: &Context<'_>,
+        war: Option<u32>,
+        from: Option<String>,
+        to: Option<String>,
+    ) -> async_graphql::Result<GqlStats> {
+        let today = day_of(chrono::Utc::now().timestamp());
+        let scope = placement_scope(war, from.as_deref(), to.as_deref(), today)?;
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let total_plans = storage.count_plans().map_err(internal_err("Failed to count plans"))?;
+        let legacy_encoded_plans = storage
+            .count_legacy_encoded_plans()
+            .map_err(internal_err("Failed to count legacy-encoded plans"))?;
+        let db_size_bytes = storage
+            .db_size_bytes()
+            .map_err(internal_err("Failed to get database size"))?;
+
+        let (gun_placements, gun_placement_totals) =
+            gun_placement_stats(storage, &assets, scope)
+                .map_err(internal_err("Failed to get gun placement counts"))?;
+
+        let target_count = storage
+            .get_marker_placement_count("target", scope)
+            .map_err(internal_err("Failed to get target placement count"))?;
+        let spotter_count = storage
+            .get_marker_placement_count("spotter", scope)
+            .map_err(internal_err("Failed to get spotter placement count"))?;
+        let deduped = storage
+            .get_deduped_placement_counts(scope)
+            .map_err(internal_err("Failed to get deduped placement counts"))?;
+        let deduped_count = |counter: &str| {
+            deduped
+                .iter()
+                .find(|(k, _)| k == counter)
+                .map_or(0, |(_, count)| *count)
+        };
+        let unique_planners = storage
+            .unique_planners(scope)
+            .map_err(internal_err("Failed to estimate unique planners"))?;
+        let placements_by_map = map_placement_stats(storage, &assets)
+            .map_err(internal_err("Failed to get placements per map"))?;
+        let wars = storage
+            .placement_wars()
+            .map_err(internal_err("Failed to list wars"))?;
+        let current_war = ctx_data::<Arc<CurrentWar>>(ctx)?.get();
+        let retention = ctx_data::<RetentionPolicy>(ctx)?;
+        let rejected = ctx_data::<Arc<RateLimiter>>(ctx)?.rejected();
 
+        let (from, to) = match scope {
+            PlacementScope::Days { from, to } => (Some(from), Some(to)),
+            _ => (None, None),
+        };
         Ok(GqlStats {
+            war,
+            from: from.and_then(day_to_date),
+            to: to.and_then(day_to_date),
+            current_war,
+            wars,
             total_plans,
+            legacy_encoded_plans,
             db_size_bytes,
             gun_placements,
-            gun_placement_totals: GqlFactionPlacementStats {
-                colonial: colonial_total,
-                warden: warden_total,
-                total: overall_total,
-            },
+            gun_placement_totals,
             marker_placements: GqlMarkerPlacementStats {
                 targets: target_count,
                 spotters: spotter_count,
             },
+            deduped_placements: GqlDedupedPlacementStats {
+                guns: deduped_count("gun"),
+                targets: deduped_count("target"),
+                spotters: deduped_count("spotter"),
+            },
+            unique_planners,
+            placements_by_map,
+            cleanup: cleanup_stats(storage, retention)?,
+            rate_limited: GqlRateLimitStats {
+                plan_creations: rejected.plan_creations,
+                tracking: rejected.tracking,
+            },
         })
     }
+
+    /// Operator queries. Needs `Authorization: Bearer <ADMIN_TOKEN>`.
+    async fn admin(&self, ctx: &Context<'_>) -> async_graphql::Result<AdminQuery> {
+        admin::authorize(ctx)?;
+        Ok(AdminQuery)
+    }
 }
 
 // Mutation root
@@ -549,76 +2727,334 @@ impl MutationRoot {
         ctx: &Context<'_>,
         input: CreatePlanInput,
     ) -> async_graphql::Result<GqlPlan> {
-        let assets = ctx_data::<Arc<Assets>>(ctx)?;
+        check_rate_limit(ctx, RateClass::PlanCreation)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
         let storage = ctx_data::<Arc<Storage>>(ctx)?;
-        if let Err(e) = validate_create_plan(&input, assets) {
+        let positions = validate_create_plan(&input, &assets).inspect_err(|e| {
             tracing::warn!(error = %e.message, "Plan validation failed");
-            return Err(e);
-        }
-        let now = chrono::Utc::now().to_rfc3339();
+        })?;
+        let retention = ctx_data::<RetentionPolicy>(ctx)?;
+        let war = ctx_data::<Arc<CurrentWar>>(ctx)?.get();
+        let version = assets
+            .game_version_of(input.profile.into())
+            .map(str::to_string);
+        let plan = plan_from_input(
+            uuid::Uuid::new_v4(),
+            input,
+            positions,
+            retention,
+            war,
+            version,
+        );
+        let edit_token = uuid::Uuid::new_v4().simple().to_string();
 
-        let to_positions = |v: Option<Vec<PositionInput>>| -> Vec<Position> {
-            v.unwrap_or_default()
-                .into_iter()
-                .map(|p| Position { x: p.x, y: p.y })
-                .collect()
+        storage
+            .create_plan(&plan, &edit_token)
+            .map_err(internal_err("Failed to save plan"))?;
+        record_placement_cells(storage, &plan);
+
+        tracing::info!(plan_id = %plan.id, map = %plan.map_id, "Plan created");
+        Ok(GqlPlan {
+            edit_token: Some(edit_token),
+            ..GqlPlan::from(plan)
+        })
+    }
+
+    /// Create a plan from a document made by `exportPlan`, with a new id and edit token.
+    async fn import_plan(
+        &self,
+        ctx: &Context<'_>,
+        document: String,
+    ) -> async_graphql::Result<GqlPlan> {
+        let input = document::plan_input_from_document(&document).map_err(|e| {
+            tracing::warn!(error = %e, "Plan import failed");
+            async_graphql::Error::new(e)
+        })?;
+        self.create_plan(ctx, input).await
+    }
+
+    /// Copy a saved plan into a new one with its own id and edit token, so a
+    /// shared template can be adapted without touching the original. The copy
+    /// is named `newName`, or the original's name with " (copy)" appended.
+    async fn fork_plan(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        new_name: Option<String>,
+    ) -> async_graphql::Result<GqlPlan> {
+        check_rate_limit(ctx, RateClass::PlanCreation)?;
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let source = storage
+            .find_plan(&id)
+            .map_err(internal_err("Failed to load plan"))?
+            .ok_or_else(|| async_graphql::Error::new("Plan not found"))?;
+        let name = match new_name {
+            Some(name) => name.trim().to_string(),
+            None => format!("{} (copy)", source.name),
         };
+        validate_name(&name)?;
 
+        let retention = ctx_data::<RetentionPolicy>(ctx)?;
+        let saved_at = chrono::Utc::now();
+        let now = saved_at.to_rfc3339();
         let plan = models::Plan {
             id: uuid::Uuid::new_v4(),
-            name: input.name,
-            map_id: input.map_id,
-            weapon_ids: input.weapon_ids,
-            gun_position: None,
-            target_position: None,
-            spotter_position: None,
-            gun_positions: to_positions(input.gun_positions),
-            target_positions: to_positions(input.target_positions),
-            spotter_positions: to_positions(input.spotter_positions),
-            gun_target_indices: input
-                .gun_target_indices
-                .unwrap_or_default()
-                .into_iter()
-                .map(|o| o.map(|v| v as usize))
-                .collect(),
-            wind_direction: input.wind_direction,
-            wind_strength: input.wind_strength.unwrap_or(0) as u8,
+            name,
+            version: 0,
+            expires_at: retention.expires_at(saved_at.timestamp()),
+            war_number: ctx_data::<Arc<CurrentWar>>(ctx)?.get(),
             created_at: now.clone(),
             updated_at: now,
+            ..source
         };
+        let edit_token = uuid::Uuid::new_v4().simple().to_string();
 
         storage
-            .save_plan(&plan)
+            .create_plan(&plan, &edit_token)
             .map_err(internal_err("Failed to save plan"))?;
 
-        tracing::info!(plan_id = %plan.id, map = %plan.map_id, "Plan created");
-        Ok(GqlPlan::from(plan))
+        tracing::info!(plan_id = %plan.id, source_id = %id.as_str(), "Plan forked");
+        Ok(GqlPlan {
+            edit_token: Some(edit_token),
+            ..GqlPlan::from(plan)
+        })
+    }
+
+    /// Save over an existing plan. `baseVersion` is the version the edit started
+    /// from; if someone saved in between, nothing is written and the conflict is
+    /// returned instead so the client can merge. Protected plans need `editToken`.
+    async fn update_plan(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        base_version: u32,
+        input: CreatePlanInput,
+        edit_token: Option<String>,
+    ) -> async_graphql::Result<GqlUpdatePlanResult> {
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let positions = validate_create_plan(&input, &assets).inspect_err(|e| {
+            tracing::warn!(error = %e.message, "Plan validation failed");
+        })?;
+        let uuid =
+            uuid::Uuid::parse_str(&id).map_err(|_| async_graphql::Error::new("Plan not found"))?;
+        require_edit_token(storage, &id, edit_token.as_deref())?;
+        let retention = ctx_data::<RetentionPolicy>(ctx)?;
+        // Storage keeps the war the plan was first saved in
+        let version = assets
+            .game_version_of(input.profile.into())
+            .map(str::to_string);
+        let plan = plan_from_input(uuid, input, positions, retention, None, version);
+
+        match storage
+            .update_plan(&plan, base_version)
+            .map_err(internal_err("Failed to update plan"))?
+        {
+            UpdateOutcome::Saved(saved) => {
+                tracing::info!(plan_id = %saved.id, version = saved.version, "Plan updated");
+                Ok(GqlUpdatePlanResult {
+                    plan: Some(saved.into()),
+                    conflict: None,
+                })
+            }
+            UpdateOutcome::Conflict(theirs) => {
+                tracing::info!(
+                    plan_id = %theirs.id,
+                    base_version,
+                    current_version = theirs.version,
+                    "Plan update conflict"
+                );
+                Ok(GqlUpdatePlanResult {
+                    plan: None,
+                    conflict: Some(GqlPlanConflict::new(&plan, theirs)),
+                })
+            }
+            UpdateOutcome::NotFound => Err(async_graphql::Error::new("Plan not found")),
+        }
+    }
+
+    /// Roll a plan back to an earlier version. The restore is saved as a new
+    /// version, so the state it replaces stays in the history too. Protected
+    /// plans need `editToken`.
+    async fn restore_plan_version(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        version: u32,
+        edit_token: Option<String>,
+    ) -> async_graphql::Result<GqlPlan> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        require_edit_token(storage, &id, edit_token.as_deref())?;
+        let current = storage
+            .get_plan(&id)
+            .map_err(internal_err("Failed to load plan"))?
+            .ok_or_else(|| async_graphql::Error::new("Plan not found"))?;
+        let mut restored = storage
+            .get_plan_version(&id, version)
+            .map_err(internal_err("Failed to load plan version"))?
+            .ok_or_else(|| async_graphql::Error::new("Version not found"))?;
+        let retention = ctx_data::<RetentionPolicy>(ctx)?;
+        let saved_at = chrono::Utc::now();
+        restored.updated_at = saved_at.to_rfc3339();
+        restored.expires_at = retention.expires_at(saved_at.timestamp());
+
+        match storage
+            .update_plan(&restored, current.version)
+            .map_err(internal_err("Failed to restore plan version"))?
+        {
+            UpdateOutcome::Saved(saved) => {
+                tracing::info!(
+                    plan_id = %saved.id,
+                    restored_version = version,
+                    version = saved.version,
+                    "Plan version restored"
+                );
+                Ok(saved.into())
+            }
+            UpdateOutcome::Conflict(_) => Err(async_graphql::Error::new(
+                "Plan changed while restoring, try again",
+            )),
+            UpdateOutcome::NotFound => Err(async_graphql::Error::new("Plan not found")),
+        }
+    }
+
+    /// Mark one target of a saved plan active, fired upon or destroyed, e.g. from
+    /// a bot during an op. Saved as a new version; protected plans need `editToken`.
+    async fn set_target_status(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        target_index: u32,
+        status: GqlTargetStatus,
+        edit_token: Option<String>,
+    ) -> async_graphql::Result<GqlPlan> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        require_edit_token(storage, &id, edit_token.as_deref())?;
+        let mut plan = storage
+            .get_plan(&id)
+            .map_err(internal_err("Failed to load plan"))?
+            .ok_or_else(|| async_graphql::Error::new("Plan not found"))?;
+        let index = target_index as usize;
+        if index >= plan.target_positions.len() {
+            return Err(async_graphql::Error::new(format!(
+                "target_index: plan has {} targets",
+                plan.target_positions.len()
+            )));
+        }
+        models::set_target_status(&mut plan.target_statuses, index, status.into());
+        let retention = ctx_data::<RetentionPolicy>(ctx)?;
+        let saved_at = chrono::Utc::now();
+        plan.updated_at = saved_at.to_rfc3339();
+        plan.expires_at = retention.expires_at(saved_at.timestamp());
+
+        match storage
+            .update_plan(&plan, plan.version)
+            .map_err(internal_err("Failed to update target status"))?
+        {
+            UpdateOutcome::Saved(saved) => {
+                tracing::info!(plan_id = %saved.id, target = index, "Target status set");
+                Ok(saved.into())
+            }
+            UpdateOutcome::Conflict(_) => Err(async_graphql::Error::new(
+                "Plan changed while updating, try again",
+            )),
+            UpdateOutcome::NotFound => Err(async_graphql::Error::new("Plan not found")),
+        }
+    }
+
+    /// Operator mutations. Needs `Authorization: Bearer <ADMIN_TOKEN>`.
+    async fn admin(&self, ctx: &Context<'_>) -> async_graphql::Result<AdminMutation> {
+        admin::authorize(ctx)?;
+        Ok(AdminMutation)
     }
 
-    async fn track_target_placement(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+    /// Delete a plan. Needs the edit token returned when it was created.
+    async fn delete_plan(
+        &self,
+        ctx: &Context<'_>,
+        id: ID,
+        edit_token: String,
+    ) -> async_graphql::Result<bool> {
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        match storage
+            .edit_token_matches(&id, Some(&edit_token))
+            .map_err(internal_err("Failed to check edit token"))?
+        {
+            Some(true) => {}
+            Some(false) => return Err(async_graphql::Error::new("Invalid edit token")),
+            // No token: either no such plan, or an old plan nobody can prove they own
+            None => {
+                let exists = storage
+                    .get_plan(&id)
+                    .map_err(internal_err("Failed to load plan"))?
+                    .is_some();
+                return Err(async_graphql::Error::new(if exists {
+                    "Plan has no edit token and can't be deleted"
+                } else {
+                    "Plan not found"
+                }));
+            }
+        }
+        storage
+            .delete_plan(&id)
+            .map_err(internal_err("Failed to delete plan"))?;
+        tracing::info!(plan_id = %id.as_str(), "Plan deleted");
+        Ok(true)
+    }
+
+    /// Count a target placed on `mapId`, when given.
+    async fn track_target_placement(
+        &self,
+        ctx: &Context<'_>,
+        map_id: Option<String>,
+    ) -> async_graphql::Result<bool> {
+        check_rate_limit(ctx, RateClass::Tracking)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        if let Some(map_id) = &map_id {
+            validate_map_id(map_id, &assets)?;
+        }
         let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let war = ctx_data::<Arc<CurrentWar>>(ctx)?.get();
+        let session = tracking::session(ctx);
+        let now = chrono::Utc::now().timestamp();
         storage
-            .increment_marker_placement("target")
+            .increment_marker_placement("target", war, map_id.as_deref(), session, now)
             .map_err(internal_err("Failed to track target placement"))?;
         tracing::info!("Target placement tracked");
         Ok(true)
     }
 
-    async fn track_spotter_placement(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+    /// Count a spotter placed on `mapId`, when given.
+    async fn track_spotter_placement(
+        &self,
+        ctx: &Context<'_>,
+        map_id: Option<String>,
+    ) -> async_graphql::Result<bool> {
+        check_rate_limit(ctx, RateClass::Tracking)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        if let Some(map_id) = &map_id {
+            validate_map_id(map_id, &assets)?;
+        }
         let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let war = ctx_data::<Arc<CurrentWar>>(ctx)?.get();
+        let session = tracking::session(ctx);
+        let now = chrono::Utc::now().timestamp();
         storage
-            .increment_marker_placement("spotter")
+            .increment_marker_placement("spotter", war, map_id.as_deref(), session, now)
             .map_err(internal_err("Failed to track spotter placement"))?;
         tracing::info!("Spotter placement tracked");
         Ok(true)
     }
 
+    /// Count a gun of `weaponSlug` placed on `mapId`, when given.
     async fn track_gun_placement(
         &self,
         ctx: &Context<'_>,
         weapon_slug: String,
+        map_id: Option<String>,
     ) -> async_graphql::Result<bool> {
-        let assets = ctx_data::<Arc<Assets>>(ctx)?;
+        check_rate_limit(ctx, RateClass::Tracking)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
         // Allow empty or "unassigned" for guns placed without a weapon
         if !weapon_slug.is_empty()
             && weapon_slug != UNASSIGNED_WEAPON
@@ -629,46 +3065,271 @@ impl MutationRoot {
                 weapon_slug
             )));
         }
+        if let Some(map_id) = &map_id {
+            validate_map_id(map_id, &assets)?;
+        }
         let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let war = ctx_data::<Arc<CurrentWar>>(ctx)?.get();
+        let session = tracking::session(ctx);
+        let now = chrono::Utc::now().timestamp();
         storage
-            .increment_gun_placement(&weapon_slug)
+            .increment_gun_placement(&weapon_slug, war, map_id.as_deref(), session, now)
             .map_err(internal_err("Failed to track gun placement"))?;
         tracing::info!(weapon = %weapon_slug, "Gun placement tracked");
         Ok(true)
     }
+
+    /// Store a battery template so others can load it by the returned `id`.
+    async fn share_battery_template(
+        &self,
+        ctx: &Context<'_>,
+        input: BatteryTemplateInput,
+    ) -> async_graphql::Result<GqlBatteryTemplate> {
+        check_rate_limit(ctx, RateClass::PlanCreation)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let template = validate_battery_template(input, &assets)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        storage
+            .save_battery_template(&id, &template)
+            .map_err(internal_err("Failed to save battery template"))?;
+        tracing::info!(template_id = %id, guns = template.guns.len(), "Battery template shared");
+        Ok(GqlBatteryTemplate::new(&id, template))
+    }
+
+    /// Share the wind currently observed in-game on a map, so others planning
+    /// there start from it. Returns the report now in effect for the map.
+    async fn report_wind(
+        &self,
+        ctx: &Context<'_>,
+        map_id: String,
+        wind: GqlWindInput,
+    ) -> async_graphql::Result<GqlWindReport> {
+        check_rate_limit(ctx, RateClass::Tracking)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        if assets.find_map_by_file_name(&map_id).is_none() {
+            return Err(async_graphql::Error::new(format!(
+                "Unknown map: {}",
+                map_id
+            )));
+        }
+        validate_wind_direction(wind.direction)?;
+        validate_wind_strength(wind.strength)?;
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        let report = storage
+            .report_wind(
+                &map_id,
+                &WindReport {
+                    direction: wind.direction,
+                    strength: wind.strength as u8,
+                    reported_at: chrono::Utc::now().timestamp(),
+                },
+            )
+            .map_err(internal_err("Failed to save wind report"))?;
+        tracing::info!(
+            map = %map_id,
+            direction = wind.direction,
+            strength = wind.strength,
+            "Wind reported"
+        );
+        Ok(report.into())
+    }
+
+    /// Record where a round landed against its firing solution, for the
+    /// accuracy research behind `impactResearch`.
+    async fn submit_impact_observation(
+        &self,
+        ctx: &Context<'_>,
+        input: ImpactObservationInput,
+    ) -> async_graphql::Result<bool> {
+        check_rate_limit(ctx, RateClass::Tracking)?;
+        let assets = ctx_data::<Arc<SharedAssets>>(ctx)?.current();
+        let weapon = assets
+            .find_weapon_by_slug(&input.weapon_id)
+            .ok_or_else(|| {
+                async_graphql::Error::new(format!("Unknown weapon: {}", input.weapon_id))
+            })?;
+        let observation = validate_impact_observation(input, weapon)?;
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        storage
+            .add_impact_observation(&observation)
+            .map_err(internal_err("Failed to save impact observation"))?;
+        tracing::info!(
+            weapon = %observation.weapon_slug,
+            long = observation.long,
+            right = observation.right,
+            "Impact observed"
+        );
+        Ok(true)
+    }
+
+    /// Take (or refresh) the advisory edit lock on a plan for this browser session.
+    ///
+    /// Call again before `expiresAt` to keep the lock. With `takeover` the lock is
+    /// taken even if another session holds it.
+    async fn acquire_plan_lock(
+        &self,
+        ctx: &Context<'_>,
+        plan_id: ID,
+        session_id: String,
+        takeover: Option<bool>,
+        edit_token: Option<String>,
+    ) -> async_graphql::Result<GqlPlanLock> {
+        validate_session_id(&session_id)?;
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        if storage
+            .get_plan(&plan_id)
+            .map_err(internal_err("Failed to load plan"))?
+            .is_none()
+        {
+            return Err(async_graphql::Error::new("Plan not found"));
+        }
+        // Only editors compete for the lock; viewers of a protected plan can't save anyway
+        require_edit_token(storage, &plan_id, edit_token.as_deref())?;
+        let now = chrono::Utc::now().timestamp();
+        let lock = storage
+            .acquire_plan_lock(
+                &plan_id,
+                &session_id,
+                now,
+                PLAN_LOCK_TTL_SECS,
+                takeover.unwrap_or(false),
+            )
+            .map_err(internal_err("Failed to acquire plan lock"))?;
+        let acquired = lock.holder == session_id;
+        if takeover.unwrap_or(false) {
+            tracing::info!(plan_id = %plan_id.as_str(), "Plan lock taken over");
+        }
+        Ok(GqlPlanLock {
+            acquired,
+            expires_at: chrono::DateTime::from_timestamp(lock.expires_at, 0)
+                .unwrap_or_default()
+                .to_rfc3339(),
+        })
+    }
+
+    /// Give up the edit lock on a plan. Returns `false` if this session didn't hold it.
+    async fn release_plan_lock(
+        &self,
+        ctx: &Context<'_>,
+        plan_id: ID,
+        session_id: String,
+    ) -> async_graphql::Result<bool> {
+        validate_session_id(&session_id)?;
+        let storage = ctx_data::<Arc<Storage>>(ctx)?;
+        storage
+            .release_plan_lock(&plan_id, &session_id)
+            .map_err(internal_err("Failed to release plan lock"))
+    }
 }
 
 pub type Schema = async_graphql::Schema<QueryRoot, MutationRoot, async_graphql::EmptySubscription>;
 
-pub fn build_schema(assets: Arc<Assets>, storage: Arc<Storage>) -> Schema {
+pub fn build_schema(
+    assets: Arc<SharedAssets>,
+    storage: Arc<Storage>,
+    retention: RetentionPolicy,
+    admin: AdminSettings,
+    rate_limiter: Arc<RateLimiter>,
+    war: Arc<CurrentWar>,
+    war_maps: Arc<WarMaps>,
+) -> Schema {
     async_graphql::Schema::build(QueryRoot, MutationRoot, async_graphql::EmptySubscription)
         .data(assets)
         .data(storage)
+        .data(retention)
+        .data(admin)
+        .data(rate_limiter)
+        .data(war)
+        .data(war_maps)
         .finish()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::Storage;
+    use crate::rate_limit::RateLimits;
+    use crate::storage::{BackupPolicy, Storage};
+    use std::path::Path;
 
-    fn test_assets() -> Arc<Assets> {
-        Arc::new(Assets {
+    fn test_assets() -> Arc<SharedAssets> {
+        let mortar = foxhole_shared::models::Weapon {
+            faction: Faction::Colonial,
+            display_name: "Test Mortar".to_string(),
+            min_range: 75.0,
+            max_range: 300.0,
+            acc_radius: [20.0, 35.0],
+            wind_drift: [5.0, 15.0],
+            flight_time: [3.0, 6.0],
+            ammo: vec![foxhole_shared::models::AmmoType {
+                name: "Flare".to_string(),
+                min_range: None,
+                max_range: None,
+                acc_radius: None,
+                wind_drift: None,
+                flight_time: Some([4.0, 8.0]),
+            }],
+            deployment: foxhole_shared::models::Deployment {
+                crew: Some(1),
+                deploy_time: None,
+                mount: Some(WeaponMount::Portable),
+            },
+            area_fire: false,
+        };
+        let assets = Assets {
             maps: vec![foxhole_shared::models::GameMap {
                 image_type: "webp".to_string(),
                 display_name: "Test Map".to_string(),
                 file_name: "test-map".to_string(),
                 active: true,
+                width: 4096,
+                height: 3552,
+                default_view: Some(foxhole_shared::models::MapViewPreset {
+                    zoom: 2.0,
+                    center: Position {
+                        x: 1000.0,
+                        y: 400.0,
+                    },
+                }),
+                war_api_name: Some("TestHex".to_string()),
             }],
-            weapons: vec![foxhole_shared::models::Weapon {
-                faction: Faction::Colonial,
-                display_name: "Test Mortar".to_string(),
-                min_range: 75.0,
-                max_range: 300.0,
-                acc_radius: [20.0, 35.0],
-                wind_drift: [5.0, 15.0],
-            }],
-        })
+            weapons: vec![mortar.clone()],
+            game_version: Some("test-1".to_string()),
+            // The Devbranch mortar reaches further, and a howitzer is only
+            // on the Devbranch
+            devbranch: Some(foxhole_shared::models::WeaponsFile {
+                game_version: Some("test-dev-1".to_string()),
+                weapons: vec![
+                    foxhole_shared::models::Weapon {
+                        max_range: 350.0,
+                        ..mortar.clone()
+                    },
+                    foxhole_shared::models::Weapon {
+                        display_name: "Test Howitzer".to_string(),
+                        min_range: 100.0,
+                        max_range: 500.0,
+                        ammo: vec![foxhole_shared::models::AmmoType {
+                            name: "Smoke".to_string(),
+                            min_range: None,
+                            max_range: Some(400.0),
+                            acc_radius: None,
+                            wind_drift: None,
+                            flight_time: None,
+                        }],
+                        ..mortar
+                    },
+                ],
+            }),
+            // Water down the east half of the map
+            terrain_masks: [(
+                "test-map".to_string(),
+                terrain::TerrainMask::parse("..~~\n..~~").unwrap(),
+            )]
+            .into(),
+        };
+        // Reloads read the repository's real game data
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../assets");
+        Arc::new(SharedAssets::new(assets, dir))
     }
 
     fn test_storage() -> (Arc<Storage>, tempfile::TempDir) {
@@ -678,10 +3339,54 @@ mod tests {
         (storage, dir)
     }
 
+    const TEST_ADMIN_TOKEN: &str = "test-admin-token";
+
+    /// The war `schema_with_context` reports as current.
+    const TEST_WAR: u32 = 117;
+
+    fn test_rate_limiter() -> Arc<RateLimiter> {
+        Arc::new(RateLimiter::new(RateLimits {
+            plan_creations_per_min: 2,
+            tracking_per_min: 3,
+            trust_forwarded: false,
+        }))
+    }
+
     fn schema_with_context() -> (Schema, tempfile::TempDir) {
         let assets = test_assets();
         let (storage, dir) = test_storage();
-        (build_schema(assets, storage), dir)
+        let retention = RetentionPolicy {
+            plan_ttl_secs: Some(30 * 86_400),
+        };
+        let admin = AdminSettings {
+            token: AdminToken::new(Some(TEST_ADMIN_TOKEN)),
+            backup: BackupPolicy {
+                dir: dir.path().join("backups"),
+                interval: None,
+                keep: 0,
+            },
+        };
+        let schema = build_schema(
+            assets,
+            storage,
+            retention,
+            admin,
+            test_rate_limiter(),
+            Arc::new(CurrentWar::fixed(TEST_WAR)),
+            Arc::new(WarMaps::default()),
+        );
+        (schema, dir)
+    }
+
+    /// Run `query` with `Authorization: Bearer <token>` as the HTTP handler passes it on.
+    async fn execute_as_admin(
+        schema: &Schema,
+        token: &str,
+        query: &str,
+    ) -> async_graphql::Response {
+        schema
+            .execute(async_graphql::Request::new(query).data(AdminCredential::bearer(token)))
+            .await
     }
 
     /// Build a schema with NO context data inserted — simulates a misconfigured server.
@@ -819,6 +3524,20 @@ mod tests {
         assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
     }
 
+    #[tokio::test]
+    async fn test_maps_query_returns_default_view() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute("{ maps { fileName defaultView { zoom center { x y grid } } } }")
+            .await;
+        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        let view = &data["maps"][0]["defaultView"];
+        assert_eq!(view["zoom"], 2.0);
+        assert_eq!(view["center"]["x"], 1000.0);
+        assert_eq!(view["center"]["y"], 400.0);
+    }
+
     #[tokio::test]
     async fn test_weapons_query_with_context_succeeds() {
         let (schema, _dir) = schema_with_context();
@@ -826,260 +3545,2666 @@ mod tests {
         assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
     }
 
-    // ---- Part 3: Input validation returns errors ----
-
     #[tokio::test]
-    async fn test_create_plan_unknown_map_returns_error() {
+    async fn test_weapons_query_returns_deployment() {
         let (schema, _dir) = schema_with_context();
         let resp = schema
-            .execute(
-                r#"mutation {
-                    createPlan(input: {
-                        name: "test",
-                        mapId: "nonexistent-map",
-                        weaponIds: []
-                    }) { id }
-                }"#,
-            )
+            .execute("{ weapons { crew deployTime mount } }")
             .await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("Unknown map"));
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["weapons"][0]["crew"], 1);
+        assert!(data["weapons"][0]["deployTime"].is_null());
+        assert_eq!(data["weapons"][0]["mount"], "PORTABLE");
     }
 
     #[tokio::test]
-    async fn test_create_plan_unknown_weapon_returns_error() {
+    async fn test_calculate_returns_time_of_flight() {
         let (schema, _dir) = schema_with_context();
         let resp = schema
             .execute(
-                r#"mutation {
-                    createPlan(input: {
-                        name: "test",
-                        mapId: "test-map",
-                        weaponIds: ["bogus-weapon"]
-                    }) { id }
-                }"#,
+                r#"{ calculate(input: {
+                    gunPosition: { x: 0, y: 0 },
+                    targetPosition: { x: 0, y: 300 },
+                    weaponId: "test-mortar"
+                }) { timeOfFlight } }"#,
             )
             .await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("Unknown weapon"));
+        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        let tof = data["calculate"]["timeOfFlight"].as_f64().unwrap();
+        assert!((tof - 6.0).abs() < 1e-9);
     }
 
     #[tokio::test]
-    async fn test_create_plan_unassigned_weapon_allowed() {
+    async fn test_calculate_flags_danger_close_zones() {
         let (schema, _dir) = schema_with_context();
         let resp = schema
             .execute(
-                r#"mutation {
-                    createPlan(input: {
-                        name: "test",
-                        mapId: "test-map",
-                        weaponIds: ["unassigned"]
-                    }) { id }
-                }"#,
+                r#"{ calculate(input: {
+                    gunPosition: { x: 0, y: 0 },
+                    targetPosition: { x: 0, y: 300 },
+                    weaponId: "test-mortar",
+                    noFireZones: [
+                        { center: { x: 0, y: 320 }, radius: 10 },
+                        { center: { x: 900, y: 900 }, radius: 10 }
+                    ]
+                }) { accuracyRadius dangerClose } }"#,
             )
             .await;
-        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert!(data["calculate"]["accuracyRadius"].as_f64().unwrap() > 10.0);
+        assert_eq!(data["calculate"]["dangerClose"], serde_json::json!([0]));
+
+        let resp = schema
+            .execute(
+                r#"{ calculate(input: {
+                    gunPosition: { x: 0, y: 0 },
+                    targetPosition: { x: 0, y: 300 },
+                    weaponId: "test-mortar",
+                    noFireZones: [{ center: { x: 0, y: 320 }, radius: -1 }]
+                }) { dangerClose } }"#,
+            )
+            .await;
+        assert!(resp.errors[0].message.contains("no_fire_zones[0]"));
     }
 
     #[tokio::test]
-    async fn test_create_plan_name_too_long_returns_error() {
+    async fn test_calculate_returns_impact_zone_for_area_fire() {
         let (schema, _dir) = schema_with_context();
-        let long_name = "x".repeat(201);
-        let query = format!(
-            r#"mutation {{
-                createPlan(input: {{
-                    name: "{}",
-                    mapId: "test-map",
-                    weaponIds: []
-                }}) {{ id }}
-            }}"#,
-            long_name
+        let impact_zone = |weapon: &'static str| {
+            let query = format!(
+                r#"{{ calculate(input: {{
+                    gunPosition: {{ x: 0, y: 0 }},
+                    targetPosition: {{ x: 400, y: 0 }},
+                    weaponId: "{weapon}"
+                }}) {{ impactZone {{ length width azimuth }} }} }}"#
+            );
+            let schema = schema.clone();
+            async move {
+                let resp = schema.execute(query).await;
+                assert!(
+                    resp.errors.is_empty(),
+                    "unexpected errors: {:?}",
+                    resp.errors
+                );
+                resp.data.into_json().unwrap()["calculate"]["impactZone"].clone()
+            }
+        };
+        assert!(impact_zone("test-mortar").await.is_null());
+
+        // The real game data has rocket artillery
+        let resp = execute_as_admin(
+            &schema,
+            TEST_ADMIN_TOKEN,
+            "mutation { admin { reloadAssets { weapons } } }",
+        )
+        .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
         );
-        let resp = schema.execute(&query).await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("200 characters"));
+        let zone = impact_zone("r-17-retiarius-skirmisher").await;
+        assert_eq!(zone["azimuth"].as_f64().unwrap(), 90.0);
+        assert!(zone["length"].as_f64().unwrap() > zone["width"].as_f64().unwrap());
     }
 
     #[tokio::test]
-    async fn test_create_plan_out_of_bounds_position_returns_error() {
+    async fn test_calculate_uses_ammo_figures() {
         let (schema, _dir) = schema_with_context();
-        let resp = schema
-            .execute(
-                r#"mutation {
-                    createPlan(input: {
-                        name: "test",
-                        mapId: "test-map",
+        let time_of_flight = |ammo: &str| {
+            let query = format!(
+                r#"{{ calculate(input: {{
+                    gunPosition: {{ x: 0, y: 0 }},
+                    targetPosition: {{ x: 0, y: 300 }},
+                    weaponId: "test-mortar",
+                    ammo: "{ammo}"
+                }}) {{ timeOfFlight }} }}"#
+            );
+            let schema = schema.clone();
+            async move { schema.execute(query).await }
+        };
+        let resp = time_of_flight("flare").await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let tof = resp.data.into_json().unwrap()["calculate"]["timeOfFlight"]
+            .as_f64()
+            .unwrap();
+        assert!((tof - 8.0).abs() < 1e-9);
+
+        let resp = time_of_flight("Gas").await;
+        assert!(resp.errors[0]
+            .message
+            .contains("Test Mortar doesn't fire \"Gas\""));
+    }
+
+    #[tokio::test]
+    async fn test_calculate_coordinate_spaces_agree() {
+        let (schema, _dir) = schema_with_context();
+        let distance = |space: &str, gun: &str, target: &str| {
+            let query = format!(
+                r#"{{ calculate(input: {{
+                    coordinateSpace: {space},
+                    gunPosition: {gun},
+                    targetPosition: {target},
+                    weaponId: "test-mortar"
+                }}) {{ distance }} }}"#
+            );
+            let schema = schema.clone();
+            async move {
+                let resp = schema.execute(query).await;
+                assert!(
+                    resp.errors.is_empty(),
+                    "unexpected errors: {:?}",
+                    resp.errors
+                );
+                resp.data.into_json().unwrap()["calculate"]["distance"]
+                    .as_f64()
+                    .unwrap()
+            }
+        };
+        // A1k5 and A3k5 centres are two cells (250 m) apart
+        let meters = distance("METERS", "{ x: 62.5, y: 62.5 }", "{ x: 62.5, y: 312.5 }").await;
+        let (px_x, px_y) = MapScale::STANDARD.meters_to_px(62.5, 62.5);
+        let (px_tx, px_ty) = MapScale::STANDARD.meters_to_px(62.5, 312.5);
+        let image_px = distance(
+            "IMAGE_PX",
+            &format!("{{ x: {px_x}, y: {px_y} }}"),
+            &format!("{{ x: {px_tx}, y: {px_ty} }}"),
+        )
+        .await;
+        // The test map image is twice the stock size, so the same points sit
+        // at twice the pixel offsets
+        let map_px = distance(
+            r#"IMAGE_PX, mapId: "test-map""#,
+            &format!("{{ x: {}, y: {} }}", px_x * 2.0, px_y * 2.0),
+            &format!("{{ x: {}, y: {} }}", px_tx * 2.0, px_ty * 2.0),
+        )
+        .await;
+        let grid_ref = distance("GRID_REF", r#"{ grid: "A1k5" }"#, r#"{ grid: "a3K5" }"#).await;
+        assert!((meters - 250.0).abs() < 1e-9);
+        assert!((image_px - 250.0).abs() < 1e-6);
+        assert!((map_px - 250.0).abs() < 1e-6);
+        assert!((grid_ref - 250.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_calculate_bad_grid_ref_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"{ calculate(input: {
+                    coordinateSpace: GRID_REF,
+                    gunPosition: { grid: "Z99" },
+                    targetPosition: { x: 1, y: 1 },
+                    weaponId: "test-mortar"
+                }) { distance } }"#,
+            )
+            .await;
+        assert!(resp.errors[0]
+            .message
+            .contains("gun_position: invalid grid reference \"Z99\""));
+
+        let resp = schema
+            .execute(
+                r#"{ calculate(input: {
+                    gunPosition: { grid: "A1" },
+                    targetPosition: { x: 1, y: 1 },
+                    weaponId: "test-mortar"
+                }) { distance } }"#,
+            )
+            .await;
+        assert!(resp.errors[0]
+            .message
+            .contains("gun_position: x and y are required"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_with_grid_refs_returns_grid_output() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        coordinateSpace: GRID_REF,
+                        name: "Grid",
+                        mapId: "test-map",
                         weaponIds: [],
-                        gunPositions: [{ x: 9999, y: 100 }]
-                    }) { id }
+                        gunPositions: [{ grid: "G9k3" }],
+                        targetPositions: [{ grid: "H10" }]
+                    }) { gunPositions { x y grid } targetPositions { grid } }
                 }"#,
             )
             .await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("out of bounds"));
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let gun = &data["createPlan"]["gunPositions"][0];
+        assert_eq!(gun["grid"], "G9k3");
+        let (x, y) = grid::parse_grid_coord("G9k3").unwrap();
+        assert_eq!(gun["x"].as_f64().unwrap(), x);
+        assert_eq!(gun["y"].as_f64().unwrap(), y);
+        // Cell-only references land on the centre keypad
+        assert_eq!(data["createPlan"]["targetPositions"][0]["grid"], "H10k5");
     }
 
     #[tokio::test]
-    async fn test_create_plan_negative_position_returns_error() {
+    async fn test_plans_record_the_weapon_data_version() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: { name: "Patch", mapId: "test-map", weaponIds: [] }) {
+                        id weaponDataVersion
+                    }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["createPlan"]["weaponDataVersion"], "test-1");
+        let id = data["createPlan"]["id"].as_str().unwrap();
+
+        let resp = schema
+            .execute(format!(
+                r#"{{ gameVersion plan(id: "{id}") {{ weaponDataVersion }} }}"#
+            ))
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["gameVersion"], "test-1");
+        assert_eq!(data["plan"]["weaponDataVersion"], "test-1");
+    }
+
+    #[tokio::test]
+    async fn test_plans_use_their_weapon_profile() {
+        let (schema, _dir) = schema_with_context();
+        let create = |profile: &str| {
+            format!(
+                r#"mutation {{
+                    createPlan(input: {{
+                        name: "Dev",
+                        mapId: "test-map",
+                        weaponIds: ["test-howitzer"],
+                        gunAmmo: ["Smoke"],
+                        gunPositions: [{{ x: 100, y: 100 }}],
+                        targetPositions: [{{ x: 100, y: 550 }}],
+                        gunTargetIndices: [0],
+                        profile: {profile}
+                    }}) {{ id profile weaponDataVersion }}
+                }}"#
+            )
+        };
+        // The howitzer isn't in the live figures
+        let resp = schema.execute(create("LIVE")).await;
+        assert!(resp.errors[0]
+            .message
+            .contains("Unknown weapon: test-howitzer"));
+
+        let resp = schema.execute(create("DEVBRANCH")).await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["createPlan"]["profile"], "DEVBRANCH");
+        assert_eq!(data["createPlan"]["weaponDataVersion"], "test-dev-1");
+        let id = data["createPlan"]["id"].as_str().unwrap();
+
+        let resp = schema
+            .execute(format!(
+                r#"{{ planFireMissions(id: "{id}") {{ weaponName ammo solution {{ inRange }} }} }}"#
+            ))
+            .await;
+        let data = resp.data.into_json().unwrap();
+        let missions = data["planFireMissions"].as_array().unwrap();
+        assert_eq!(missions.len(), 1);
+        assert_eq!(missions[0]["weaponName"], "Test Howitzer");
+        assert_eq!(missions[0]["ammo"], "Smoke");
+        // 450 m is within the howitzer's reach but past the smoke shell's
+        assert_eq!(missions[0]["solution"]["inRange"], false);
+    }
+
+    #[tokio::test]
+    async fn test_weapon_profiles() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"{
+                    weaponProfiles { profile gameVersion weapons }
+                    live: weapons { maxRange }
+                    devbranch: weapons(profile: DEVBRANCH) { maxRange }
+                    devVersion: gameVersion(profile: DEVBRANCH)
+                    calculate(input: {
+                        gunPosition: { x: 0, y: 0 },
+                        targetPosition: { x: 320, y: 0 },
+                        weaponId: "test-mortar",
+                        profile: DEVBRANCH
+                    }) { inRange }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["weaponProfiles"][0]["profile"], "LIVE");
+        assert_eq!(data["weaponProfiles"][0]["gameVersion"], "test-1");
+        assert_eq!(data["weaponProfiles"][1]["profile"], "DEVBRANCH");
+        assert_eq!(data["weaponProfiles"][1]["weapons"], 2);
+        assert_eq!(data["live"][0]["maxRange"], 300.0);
+        assert_eq!(data["devbranch"][0]["maxRange"], 350.0);
+        assert_eq!(data["devVersion"], "test-dev-1");
+        // Out of the live mortar's range, in the Devbranch one's
+        assert_eq!(data["calculate"]["inRange"], true);
+    }
+
+    #[tokio::test]
+    async fn test_exported_plan_imports_as_a_new_plan() {
         let (schema, _dir) = schema_with_context();
         let resp = schema
             .execute(
                 r#"mutation {
                     createPlan(input: {
-                        name: "test",
+                        name: "Op North",
                         mapId: "test-map",
-                        weaponIds: [],
-                        targetPositions: [{ x: -1, y: 100 }]
+                        weaponIds: ["test-mortar"],
+                        gunAmmo: ["Flare"],
+                        gunPositions: [{ x: 100.0, y: 200.0 }],
+                        targetPositions: [{ x: 300.0, y: 250.0 }],
+                        gunTargetIndices: [0],
+                        gunLabels: ["Left"],
+                        targetLabels: ["Bunker"],
+                        windDirection: 90.0,
+                        windStrength: 2
                     }) { id }
                 }"#,
             )
             .await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("out of bounds"));
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        let id = resp.data.into_json().unwrap()["createPlan"]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let query = format!(r#"{{ exportPlan(id: "{id}", format: JSON) }}"#);
+        let resp = schema.execute(query.as_str()).await;
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        let document = resp.data.into_json().unwrap()["exportPlan"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let json: serde_json::Value = serde_json::from_str(&document).unwrap();
+        assert_eq!(json["mapName"], "Test Map");
+        assert_eq!(json["weaponNames"], serde_json::json!(["Test Mortar"]));
+
+        let request = async_graphql::Request::new(
+            "mutation($document: String!) {
+                importPlan(document: $document) {
+                    id name editToken windDirection windStrength gunLabels targetLabels
+                    gunPositions { x y } targetPositions { x y } gunAmmo
+                }
+            }",
+        )
+        .variables(async_graphql::Variables::from_json(
+            serde_json::json!({ "document": document }),
+        ));
+        let resp = schema.execute(request).await;
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        let imported = &data["importPlan"];
+        assert_ne!(imported["id"], id.as_str());
+        assert!(imported["editToken"].is_string());
+        assert_eq!(imported["name"], "Op North");
+        assert_eq!(imported["windDirection"], 90.0);
+        assert_eq!(imported["windStrength"], 2);
+        assert_eq!(imported["gunLabels"], serde_json::json!(["Left"]));
+        assert_eq!(imported["targetLabels"], serde_json::json!(["Bunker"]));
+        assert_eq!(imported["gunAmmo"], serde_json::json!(["Flare"]));
+        assert_eq!(
+            imported["gunPositions"],
+            serde_json::json!([{ "x": 100.0, "y": 200.0 }])
+        );
+        assert_eq!(
+            imported["targetPositions"],
+            serde_json::json!([{ "x": 300.0, "y": 250.0 }])
+        );
     }
 
     #[tokio::test]
-    async fn test_create_plan_wind_strength_too_high_returns_error() {
+    async fn test_created_plans_feed_the_placement_heatmap() {
+        let (schema, _dir) = schema_with_context();
+        for _ in 0..2 {
+            let resp = schema
+                .execute(
+                    r#"mutation {
+                        createPlan(input: {
+                            name: "Heat",
+                            mapId: "test-map",
+                            weaponIds: [],
+                            gunPositions: [{ x: 800.0, y: 1050.0 }],
+                            targetPositions: [{ x: 900.0, y: 1060.0 }, { x: 10.0, y: 10.0 }]
+                        }) { id }
+                    }"#,
+                )
+                .await;
+            assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        }
+
+        let resp = schema
+            .execute(
+                r#"{ placementHeatmap(mapId: "test-map") {
+                    guns { col row grid count } targets { grid count }
+                } }"#,
+            )
+            .await;
+        assert!(resp.errors.is_empty(), "{:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(
+            data["placementHeatmap"]["guns"],
+            serde_json::json!([{ "col": 6, "row": 8, "grid": "G9", "count": 2 }])
+        );
+        assert_eq!(
+            data["placementHeatmap"]["targets"],
+            serde_json::json!([{ "grid": "A1", "count": 2 }, { "grid": "H9", "count": 2 }])
+        );
+
+        let resp = schema
+            .execute(r#"{ placementHeatmap(mapId: "other-map") { guns { count } } }"#)
+            .await;
+        assert_eq!(
+            resp.data.into_json().unwrap()["placementHeatmap"]["guns"],
+            serde_json::json!([])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_import_plan_rejects_other_documents() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(r#"mutation { importPlan(document: "{\"format\": \"kml\"}") { id } }"#)
+            .await;
+        assert_eq!(resp.errors.len(), 1);
+        assert_eq!(resp.errors[0].message, "Not a plan document");
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_with_calibration_places_image_pixels() {
         let (schema, _dir) = schema_with_context();
         let resp = schema
             .execute(
                 r#"mutation {
                     createPlan(input: {
-                        name: "test",
+                        coordinateSpace: IMAGE_PX,
+                        name: "Custom",
                         mapId: "test-map",
                         weaponIds: [],
-                        windStrength: 10
-                    }) { id }
+                        calibration: {
+                            imageUrl: "https://example.com/map.png",
+                            width: 1000,
+                            height: 1000,
+                            points: [
+                                { imageX: 100, imageY: 100, position: { x: 200, y: 200 } },
+                                { imageX: 900, imageY: 900, position: { x: 1800, y: 1800 } }
+                            ]
+                        },
+                        gunPositions: [{ x: 500, y: 250 }]
+                    }) {
+                        gunPositions { x y }
+                        calibration { imageUrl width points { imageX position { x } } }
+                    }
                 }"#,
             )
             .await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("wind_strength"));
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let gun = &data["createPlan"]["gunPositions"][0];
+        assert!((gun["x"].as_f64().unwrap() - 1000.0).abs() < 1e-9);
+        assert!((gun["y"].as_f64().unwrap() - 500.0).abs() < 1e-9);
+        let calibration = &data["createPlan"]["calibration"];
+        assert_eq!(calibration["imageUrl"], "https://example.com/map.png");
+        assert_eq!(calibration["width"], 1000);
+        assert_eq!(calibration["points"][1]["imageX"], 900.0);
+        assert_eq!(calibration["points"][1]["position"]["x"], 1800.0);
     }
 
     #[tokio::test]
-    async fn test_create_plan_wind_direction_out_of_range_returns_error() {
+    async fn test_create_plan_keeps_shot_log() {
         let (schema, _dir) = schema_with_context();
         let resp = schema
             .execute(
                 r#"mutation {
                     createPlan(input: {
-                        name: "test",
+                        coordinateSpace: METERS,
+                        name: "Adjusting",
                         mapId: "test-map",
                         weaponIds: [],
-                        windDirection: 400.0
-                    }) { id }
+                        gunPositions: [{ x: 100, y: 100 }],
+                        targetPositions: [{ x: 200, y: 100 }],
+                        shotLog: [
+                            { gun: 0, target: 0, azimuth: 90, distance: 100, result: " 30 m long " },
+                            { gun: 0, azimuth: 88.5, distance: 95 }
+                        ]
+                    }) {
+                        shotLog { gun target azimuth distance result }
+                    }
                 }"#,
             )
             .await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("wind_direction"));
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let log = &data["createPlan"]["shotLog"];
+        assert_eq!(log[0]["target"], 0);
+        assert_eq!(log[0]["result"], "30 m long");
+        assert!(log[1]["target"].is_null());
+        assert_eq!(log[1]["azimuth"], 88.5);
     }
 
     #[tokio::test]
-    async fn test_create_plan_gun_target_index_out_of_bounds_returns_error() {
+    async fn test_create_plan_rejects_bad_shot_log() {
+        let (schema, _dir) = schema_with_context();
+        for (shot, expected) in [
+            ("{ gun: 1, azimuth: 90, distance: 100 }", "gun index"),
+            (
+                "{ gun: 0, target: 3, azimuth: 90, distance: 100 }",
+                "target index",
+            ),
+            ("{ gun: 0, azimuth: 360, distance: 100 }", "azimuth"),
+            ("{ gun: 0, azimuth: 90, distance: -1 }", "distance"),
+        ] {
+            let resp = schema
+                .execute(format!(
+                    r#"mutation {{
+                        createPlan(input: {{
+                            coordinateSpace: METERS,
+                            name: "Bad",
+                            mapId: "test-map",
+                            weaponIds: [],
+                            gunPositions: [{{ x: 100, y: 100 }}],
+                            shotLog: [{shot}]
+                        }}) {{ id }}
+                    }}"#
+                ))
+                .await;
+            assert_eq!(resp.errors.len(), 1, "{shot}");
+            let message = &resp.errors[0].message;
+            assert!(
+                message.contains("shot_log[0]") && message.contains(expected),
+                "{message}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_keeps_registrations() {
         let (schema, _dir) = schema_with_context();
         let resp = schema
             .execute(
                 r#"mutation {
                     createPlan(input: {
-                        name: "test",
+                        coordinateSpace: METERS,
+                        name: "Registered",
                         mapId: "test-map",
-                        weaponIds: ["test-mortar"],
+                        weaponIds: [],
                         gunPositions: [{ x: 100, y: 100 }],
-                        targetPositions: [{ x: 200, y: 200 }],
-                        gunTargetIndices: [5]
-                    }) { id }
+                        registrations: [{
+                            gun: 0,
+                            name: " TRP-1 bridge ",
+                            position: { x: 200, y: 100 },
+                            azimuth: 90,
+                            distance: 100
+                        }]
+                    }) {
+                        registrations { gun name position { x y grid } azimuth distance }
+                    }
                 }"#,
             )
             .await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("out of bounds"));
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let point = &data["createPlan"]["registrations"][0];
+        assert_eq!(point["name"], "TRP-1 bridge");
+        assert_eq!(point["position"]["x"], 200.0);
+        assert_eq!(point["distance"], 100.0);
     }
 
     #[tokio::test]
-    async fn test_create_plan_too_many_gun_positions_returns_error() {
+    async fn test_create_plan_rejects_bad_registrations() {
         let (schema, _dir) = schema_with_context();
-        // Build 51 positions (exceeds MAX_POSITIONS of 50)
-        let positions: Vec<String> = (0..51).map(|_| "{ x: 100, y: 100 }".to_string()).collect();
-        let positions_str = positions.join(", ");
-        let query = format!(
-            r#"mutation {{
-                createPlan(input: {{
-                    name: "test",
-                    mapId: "test-map",
-                    weaponIds: [],
-                    gunPositions: [{}]
-                }}) {{ id }}
-            }}"#,
-            positions_str
-        );
-        let resp = schema.execute(&query).await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("too many entries"));
+        for (point, expected) in [
+            (
+                r#"{ gun: 1, name: "A", position: { x: 1, y: 1 }, azimuth: 0, distance: 5 }"#,
+                "gun index",
+            ),
+            (
+                r#"{ gun: 0, name: " ", position: { x: 1, y: 1 }, azimuth: 0, distance: 5 }"#,
+                "name",
+            ),
+            (
+                r#"{ gun: 0, name: "A", position: { x: -1, y: 1 }, azimuth: 0, distance: 5 }"#,
+                "out of bounds",
+            ),
+            (
+                r#"{ gun: 0, name: "A", position: { x: 1, y: 1 }, azimuth: 0, distance: -5 }"#,
+                "distance",
+            ),
+        ] {
+            let resp = schema
+                .execute(format!(
+                    r#"mutation {{
+                        createPlan(input: {{
+                            coordinateSpace: METERS,
+                            name: "Bad",
+                            mapId: "test-map",
+                            weaponIds: [],
+                            gunPositions: [{{ x: 100, y: 100 }}],
+                            registrations: [{point}]
+                        }}) {{ id }}
+                    }}"#
+                ))
+                .await;
+            assert_eq!(resp.errors.len(), 1, "{point}");
+            let message = &resp.errors[0].message;
+            assert!(
+                message.contains("registrations[0]") && message.contains(expected),
+                "{message}"
+            );
+        }
     }
 
     #[tokio::test]
-    async fn test_create_plan_too_many_weapon_ids_returns_error() {
+    async fn test_create_plan_keeps_range_rings() {
         let (schema, _dir) = schema_with_context();
-        let ids: Vec<String> = (0..51).map(|_| r#""unassigned""#.to_string()).collect();
-        let ids_str = ids.join(", ");
-        let query = format!(
-            r#"mutation {{
-                createPlan(input: {{
-                    name: "test",
-                    mapId: "test-map",
-                    weaponIds: [{}]
-                }}) {{ id }}
-            }}"#,
-            ids_str
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        coordinateSpace: METERS,
+                        name: "Rings",
+                        mapId: "test-map",
+                        weaponIds: ["test-mortar", "test-mortar"],
+                        gunPositions: [{ x: 100, y: 100 }, { x: 150, y: 100 }],
+                        spotterPositions: [{ x: 200, y: 200 }],
+                        gunRingsHidden: [false, true],
+                        customRings: [{ anchor: SPOTTER, index: 0, radius: 250, label: " Intel " }]
+                    }) {
+                        gunRingsHidden
+                        customRings { anchor index radius label }
+                    }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
         );
-        let resp = schema.execute(&query).await;
-        assert!(!resp.errors.is_empty());
-        assert!(resp.errors[0].message.contains("too many entries"));
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(
+            data["createPlan"]["gunRingsHidden"],
+            serde_json::json!([false, true])
+        );
+        let ring = &data["createPlan"]["customRings"][0];
+        assert_eq!(ring["anchor"], "SPOTTER");
+        assert_eq!(ring["radius"], 250.0);
+        assert_eq!(ring["label"], "Intel");
     }
 
     #[tokio::test]
-    async fn test_create_plan_at_max_positions_succeeds() {
+    async fn test_create_plan_keeps_intel_markers() {
         let (schema, _dir) = schema_with_context();
-        // Exactly 50 should be fine
-        let positions: Vec<String> = (0..50).map(|_| "{ x: 100, y: 100 }".to_string()).collect();
-        let positions_str = positions.join(", ");
-        let query = format!(
-            r#"mutation {{
-                createPlan(input: {{
-                    name: "test",
-                    mapId: "test-map",
-                    weaponIds: [],
-                    gunPositions: [{}]
-                }}) {{ id }}
-            }}"#,
-            positions_str
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        coordinateSpace: METERS,
+                        name: "Intel",
+                        mapId: "test-map",
+                        weaponIds: [],
+                        enemyGunPositions: [{ x: 900, y: 900 }],
+                        fobPositions: [{ x: 100, y: 100 }, { x: 120, y: 100 }],
+                        noFireZonePositions: [{ x: 300, y: 300 }]
+                    }) {
+                        enemyGunPositions { x y }
+                        fobPositions { x }
+                        bunkerPositions { x }
+                        noFireZonePositions { y }
+                    }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
         );
-        let resp = schema.execute(&query).await;
-        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        let plan = &data["createPlan"];
+        assert_eq!(plan["enemyGunPositions"][0]["x"], 900.0);
+        assert_eq!(plan["fobPositions"].as_array().unwrap().len(), 2);
+        assert_eq!(plan["bunkerPositions"], serde_json::json!([]));
+        assert_eq!(plan["noFireZonePositions"][0]["y"], 300.0);
+
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        coordinateSpace: METERS,
+                        name: "Intel",
+                        mapId: "test-map",
+                        weaponIds: [],
+                        bunkerPositions: [{ x: -5, y: 100 }]
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(resp.errors[0].message.contains("bunker_positions[0]"));
     }
 
     #[tokio::test]
-    async fn test_create_plan_valid_input_succeeds() {
+    async fn test_create_plan_keeps_no_fire_zone_radii() {
         let (schema, _dir) = schema_with_context();
         let resp = schema
             .execute(
                 r#"mutation {
                     createPlan(input: {
-                        name: "Valid Plan",
+                        coordinateSpace: METERS,
+                        name: "Zones",
                         mapId: "test-map",
-                        weaponIds: ["test-mortar"],
-                        gunPositions: [{ x: 100, y: 100 }],
-                        targetPositions: [{ x: 200, y: 200 }],
-                        gunTargetIndices: [0],
-                        windDirection: 90.0,
-                        windStrength: 3
-                    }) { id name }
+                        weaponIds: [],
+                        noFireZonePositions: [{ x: 300, y: 300 }, { x: 600, y: 300 }],
+                        noFireZoneRadii: [200]
+                    }) { noFireZoneRadii }
                 }"#,
             )
             .await;
-        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(
+            data["createPlan"]["noFireZoneRadii"],
+            serde_json::json!([200.0, 50.0])
+        );
+
+        for radii in ["[0]", "[5000]", "[100, 100]"] {
+            let resp = schema
+                .execute(format!(
+                    r#"mutation {{
+                        createPlan(input: {{
+                            coordinateSpace: METERS,
+                            name: "Zones",
+                            mapId: "test-map",
+                            weaponIds: [],
+                            noFireZonePositions: [{{ x: 300, y: 300 }}],
+                            noFireZoneRadii: {}
+                        }}) {{ id }}
+                    }}"#,
+                    radii
+                ))
+                .await;
+            assert!(
+                resp.errors[0].message.contains("no_fire_zone_radii"),
+                "{}: {:?}",
+                radii,
+                resp.errors
+            );
+        }
     }
 
+    #[tokio::test]
+    async fn test_create_plan_returns_a_slug_that_finds_the_plan() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: { name: "Short", mapId: "test-map", weaponIds: [] }) {
+                        id
+                        slug
+                    }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let id = data["createPlan"]["id"].as_str().unwrap();
+        let slug = data["createPlan"]["slug"].as_str().unwrap();
+        assert!(foxhole_shared::slug::is_slug(slug), "{slug}");
+
+        let resp = schema
+            .execute(format!(r#"{{ plan(id: "{slug}") {{ id name slug }} }}"#))
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["plan"]["id"], id);
+        assert_eq!(data["plan"]["name"], "Short");
+        assert_eq!(data["plan"]["slug"], slug);
+    }
+
+    #[tokio::test]
+    async fn test_plan_warns_of_guns_and_spotters_on_bad_ground() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "Wet feet", mapId: "test-map", weaponIds: ["", ""],
+                        gunPositions: [{ x: 600, y: 900 }, { x: 1500, y: 900 }],
+                        targetPositions: [{ x: 1600, y: 1000 }],
+                        spotterPositions: [{ x: 5, y: 5 }]
+                    }) {
+                        terrainWarnings { marker index issue message }
+                    }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        // The target in water is fine: only guns and spotters are checked
+        assert_eq!(
+            data["createPlan"]["terrainWarnings"],
+            serde_json::json!([
+                { "marker": "GUN", "index": 1, "issue": "WATER", "message": "Gun 2 is in water" },
+                {
+                    "marker": "SPOTTER",
+                    "index": 0,
+                    "issue": "OFF_MAP",
+                    "message": "Spotter 1 is outside the hex"
+                }
+            ])
+        );
+
+        let resp = schema
+            .execute(r#"{ terrainMask(mapId: "test-map") }"#)
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["terrainMask"], serde_json::json!(["..~~", "..~~"]));
+        let resp = schema.execute(r#"{ terrainMask(mapId: "nope") }"#).await;
+        assert!(resp.errors[0].message.contains("Unknown map"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_rejects_bad_range_rings() {
+        let (schema, _dir) = schema_with_context();
+        for (rings, expected) in [
+            (
+                r#"gunRingsHidden: [false, true]"#,
+                "gun_rings_hidden: 2 entries for 1 guns",
+            ),
+            (
+                r#"customRings: [{ anchor: SPOTTER, index: 0, radius: 250 }]"#,
+                "custom_rings[0]: index out of range",
+            ),
+            (
+                r#"customRings: [{ anchor: GUN, index: 0, radius: 0 }]"#,
+                "custom_rings[0]: radius",
+            ),
+            (
+                r#"customRings: [{ anchor: GUN, index: 0, radius: 5000 }]"#,
+                "custom_rings[0]: radius",
+            ),
+        ] {
+            let resp = schema
+                .execute(format!(
+                    r#"mutation {{
+                        createPlan(input: {{
+                            coordinateSpace: METERS,
+                            name: "Bad",
+                            mapId: "test-map",
+                            weaponIds: [],
+                            gunPositions: [{{ x: 100, y: 100 }}],
+                            {rings}
+                        }}) {{ id }}
+                    }}"#
+                ))
+                .await;
+            assert_eq!(resp.errors.len(), 1, "{rings}");
+            let message = &resp.errors[0].message;
+            assert!(message.contains(expected), "{message}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_rejects_bad_calibration() {
+        let (schema, _dir) = schema_with_context();
+        for (url, second) in [
+            (
+                "file:///etc/passwd",
+                "{ imageX: 900, imageY: 900, position: { x: 1800, y: 1800 } }",
+            ),
+            (
+                "https://example.com/map.png",
+                "{ imageX: 100, imageY: 900, position: { x: 200, y: 1800 } }",
+            ),
+            (
+                "https://example.com/map.png",
+                "{ imageX: 1200, imageY: 900, position: { x: 1800, y: 1800 } }",
+            ),
+        ] {
+            let query = format!(
+                r#"mutation {{
+                    createPlan(input: {{
+                        name: "Custom",
+                        mapId: "test-map",
+                        weaponIds: [],
+                        calibration: {{
+                            imageUrl: "{}",
+                            width: 1000,
+                            height: 1000,
+                            points: [{{ imageX: 100, imageY: 100, position: {{ x: 200, y: 200 }} }}, {}]
+                        }}
+                    }}) {{ id }}
+                }}"#,
+                url, second
+            );
+            let resp = schema.execute(query.as_str()).await;
+            assert!(!resp.errors.is_empty(), "accepted {} / {}", url, second);
+            assert!(resp.errors[0].message.contains("calibration"));
+        }
+    }
+
+    // ---- Part 3: Input validation returns errors ----
+
+    #[tokio::test]
+    async fn test_create_plan_unknown_map_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "test",
+                        mapId: "nonexistent-map",
+                        weaponIds: []
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("Unknown map"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_unknown_weapon_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "test",
+                        mapId: "test-map",
+                        weaponIds: ["bogus-weapon"]
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("Unknown weapon"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_unassigned_weapon_allowed() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "test",
+                        mapId: "test-map",
+                        weaponIds: ["unassigned"]
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_name_too_long_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let long_name = "x".repeat(201);
+        let query = format!(
+            r#"mutation {{
+                createPlan(input: {{
+                    name: "{}",
+                    mapId: "test-map",
+                    weaponIds: []
+                }}) {{ id }}
+            }}"#,
+            long_name
+        );
+        let resp = schema.execute(&query).await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("200 characters"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_out_of_bounds_position_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "test",
+                        mapId: "test-map",
+                        weaponIds: [],
+                        gunPositions: [{ x: 9999, y: 100 }]
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("out of bounds"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_negative_position_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "test",
+                        mapId: "test-map",
+                        weaponIds: [],
+                        targetPositions: [{ x: -1, y: 100 }]
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("out of bounds"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_wind_strength_too_high_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "test",
+                        mapId: "test-map",
+                        weaponIds: [],
+                        windStrength: 10
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("wind_strength"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_wind_direction_out_of_range_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "test",
+                        mapId: "test-map",
+                        weaponIds: [],
+                        windDirection: 400.0
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("wind_direction"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_gun_target_index_out_of_bounds_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "test",
+                        mapId: "test-map",
+                        weaponIds: ["test-mortar"],
+                        gunPositions: [{ x: 100, y: 100 }],
+                        targetPositions: [{ x: 200, y: 200 }],
+                        gunTargetIndices: [5]
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("out of bounds"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_too_many_gun_positions_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        // Build 51 positions (exceeds MAX_POSITIONS of 50)
+        let positions: Vec<String> = (0..51).map(|_| "{ x: 100, y: 100 }".to_string()).collect();
+        let positions_str = positions.join(", ");
+        let query = format!(
+            r#"mutation {{
+                createPlan(input: {{
+                    name: "test",
+                    mapId: "test-map",
+                    weaponIds: [],
+                    gunPositions: [{}]
+                }}) {{ id }}
+            }}"#,
+            positions_str
+        );
+        let resp = schema.execute(&query).await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("too many entries"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_too_many_weapon_ids_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let ids: Vec<String> = (0..51).map(|_| r#""unassigned""#.to_string()).collect();
+        let ids_str = ids.join(", ");
+        let query = format!(
+            r#"mutation {{
+                createPlan(input: {{
+                    name: "test",
+                    mapId: "test-map",
+                    weaponIds: [{}]
+                }}) {{ id }}
+            }}"#,
+            ids_str
+        );
+        let resp = schema.execute(&query).await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("too many entries"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_at_max_positions_succeeds() {
+        let (schema, _dir) = schema_with_context();
+        // Exactly 50 should be fine
+        let positions: Vec<String> = (0..50).map(|_| "{ x: 100, y: 100 }".to_string()).collect();
+        let positions_str = positions.join(", ");
+        let query = format!(
+            r#"mutation {{
+                createPlan(input: {{
+                    name: "test",
+                    mapId: "test-map",
+                    weaponIds: [],
+                    gunPositions: [{}]
+                }}) {{ id }}
+            }}"#,
+            positions_str
+        );
+        let resp = schema.execute(&query).await;
+        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_valid_input_succeeds() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "Valid Plan",
+                        mapId: "test-map",
+                        weaponIds: ["test-mortar"],
+                        gunPositions: [{ x: 100, y: 100 }],
+                        targetPositions: [{ x: 200, y: 200 }],
+                        gunTargetIndices: [0],
+                        windDirection: 90.0,
+                        windStrength: 3
+                    }) { id name }
+                }"#,
+            )
+            .await;
+        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_stores_trimmed_labels() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "Named",
+                        mapId: "test-map",
+                        weaponIds: ["test-mortar"],
+                        gunPositions: [{ x: 100, y: 100 }],
+                        targetPositions: [{ x: 200, y: 200 }],
+                        gunLabels: [" Alpha gun "],
+                        targetLabels: ["Bunker base east"]
+                    }) { gunLabels targetLabels spotterLabels }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let json = resp.data.into_json().unwrap();
+        let plan = &json["createPlan"];
+        assert_eq!(plan["gunLabels"], serde_json::json!(["Alpha gun"]));
+        assert_eq!(
+            plan["targetLabels"],
+            serde_json::json!(["Bunker base east"])
+        );
+        assert_eq!(plan["spotterLabels"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_label_too_long_returns_error() {
+        let (schema, _dir) = schema_with_context();
+        let query = format!(
+            r#"mutation {{
+                createPlan(input: {{
+                    name: "Named",
+                    mapId: "test-map",
+                    weaponIds: [],
+                    spotterLabels: ["{}"]
+                }}) {{ id }}
+            }}"#,
+            "x".repeat(MAX_LABEL_LEN + 1)
+        );
+        let resp = schema.execute(&query).await;
+        assert!(!resp.errors.is_empty());
+        assert!(resp.errors[0].message.contains("spotter_labels[0]"));
+    }
+
+    #[tokio::test]
+    async fn test_plan_fire_missions_lists_paired_guns() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    createPlan(input: {
+                        name: "Barrage",
+                        mapId: "test-map",
+                        weaponIds: ["test-mortar", "test-mortar", ""],
+                        gunPositions: [{ x: 100, y: 100 }, { x: 150, y: 100 }, { x: 200, y: 100 }],
+                        targetPositions: [{ x: 100, y: 300 }],
+                        gunTargetIndices: [0, null, 0]
+                    }) { id }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let id = resp.data.into_json().unwrap()["createPlan"]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let resp = schema
+            .execute(format!(
+                r#"{{ planFireMissions(id: "{}") {{
+                    gun weaponName gunGrid target targetGrid
+                    solution {{ azimuth distance inRange }}
+                }} }}"#,
+                id
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let missions = data["planFireMissions"].as_array().unwrap();
+        assert_eq!(missions.len(), 1);
+        assert_eq!(missions[0]["gun"], 1);
+        assert_eq!(missions[0]["target"], 1);
+        assert_eq!(missions[0]["weaponName"], "Test Mortar");
+        assert_eq!(missions[0]["gunGrid"], format_grid_coord(100.0, 100.0));
+        let sol = &missions[0]["solution"];
+        assert!((sol["distance"].as_f64().unwrap() - 200.0).abs() < 1e-9);
+        assert!((sol["azimuth"].as_f64().unwrap() - 180.0).abs() < 1e-9);
+        assert_eq!(sol["inRange"], true);
+    }
+
+    #[tokio::test]
+    async fn test_plan_fire_missions_unknown_plan_returns_null() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(r#"{ planFireMissions(id: "00000000-0000-0000-0000-000000000000") { gun } }"#)
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert!(data["planFireMissions"].is_null());
+    }
+
+    /// Create a plan, returning its id and edit token.
+    async fn create_test_plan(schema: &Schema) -> (String, String) {
+        let resp = schema
+            .execute(r#"mutation { createPlan(input: { name: "Lock", mapId: "test-map", weaponIds: [] }) { id editToken } }"#)
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let plan = &data["createPlan"];
+        (
+            plan["id"].as_str().unwrap().to_string(),
+            plan["editToken"].as_str().unwrap().to_string(),
+        )
+    }
+
+    async fn acquire_lock(
+        schema: &Schema,
+        id: &str,
+        token: &str,
+        session: &str,
+        takeover: bool,
+    ) -> bool {
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ acquirePlanLock(planId: "{}", sessionId: "{}", takeover: {}, editToken: "{}") {{ acquired expiresAt }} }}"#,
+                id, session, takeover, token
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        resp.data.into_json().unwrap()["acquirePlanLock"]["acquired"]
+            .as_bool()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_plan_lock_read_only_for_second_session() {
+        let (schema, _dir) = schema_with_context();
+        let (id, token) = create_test_plan(&schema).await;
+
+        assert!(acquire_lock(&schema, &id, &token, "tab-a", false).await);
+        assert!(!acquire_lock(&schema, &id, &token, "tab-b", false).await);
+        // Refreshing keeps it; takeover moves it
+        assert!(acquire_lock(&schema, &id, &token, "tab-a", false).await);
+        assert!(acquire_lock(&schema, &id, &token, "tab-b", true).await);
+        assert!(!acquire_lock(&schema, &id, &token, "tab-a", false).await);
+    }
+
+    #[tokio::test]
+    async fn test_release_plan_lock_frees_plan() {
+        let (schema, _dir) = schema_with_context();
+        let (id, token) = create_test_plan(&schema).await;
+        assert!(acquire_lock(&schema, &id, &token, "tab-a", false).await);
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ releasePlanLock(planId: "{}", sessionId: "tab-a") }}"#,
+                id
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        assert_eq!(resp.data.into_json().unwrap()["releasePlanLock"], true);
+        assert!(acquire_lock(&schema, &id, &token, "tab-b", false).await);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_plan_lock_unknown_plan_errors() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation { acquirePlanLock(planId: "00000000-0000-0000-0000-000000000000", sessionId: "tab-a") { acquired } }"#,
+            )
+            .await;
+        assert_eq!(resp.errors.len(), 1);
+        assert!(resp.errors[0].message.contains("Plan not found"));
+    }
+
+    fn update_mutation(id: &str, token: &str, base_version: u32, name: &str, guns: &str) -> String {
+        format!(
+            r#"mutation {{ updatePlan(id: "{}", editToken: "{}", baseVersion: {}, input: {{
+                name: "{}", mapId: "test-map", weaponIds: [], gunPositions: [{}]
+            }}) {{
+                plan {{ name version }}
+                conflict {{
+                    theirs {{ name version }}
+                    guns {{ onlyMine {{ x y }} onlyTheirs {{ x y }} }}
+                    nameChanged windChanged
+                }}
+            }} }}"#,
+            id, token, base_version, name, guns
+        )
+    }
+
+    #[tokio::test]
+    async fn test_update_plan_saves_and_bumps_version() {
+        let (schema, _dir) = schema_with_context();
+        let (id, token) = create_test_plan(&schema).await;
+
+        let resp = schema
+            .execute(update_mutation(
+                &id,
+                &token,
+                0,
+                "Edited",
+                "{ x: 10, y: 10 }",
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["updatePlan"]["plan"]["name"], "Edited");
+        assert_eq!(data["updatePlan"]["plan"]["version"], 1);
+        assert!(data["updatePlan"]["conflict"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_update_plan_stale_version_returns_conflict() {
+        let (schema, _dir) = schema_with_context();
+        let (id, token) = create_test_plan(&schema).await;
+        schema
+            .execute(update_mutation(
+                &id,
+                &token,
+                0,
+                "Theirs",
+                "{ x: 10, y: 10 }",
+            ))
+            .await;
+
+        let resp = schema
+            .execute(update_mutation(
+                &id,
+                &token,
+                0,
+                "Mine",
+                "{ x: 500, y: 500 }",
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let result = &data["updatePlan"];
+        assert!(result["plan"].is_null());
+        let conflict = &result["conflict"];
+        assert_eq!(conflict["theirs"]["name"], "Theirs");
+        assert_eq!(conflict["theirs"]["version"], 1);
+        assert_eq!(conflict["nameChanged"], true);
+        assert_eq!(conflict["windChanged"], false);
+        assert_eq!(conflict["guns"]["onlyMine"][0]["x"], 500.0);
+        assert_eq!(conflict["guns"]["onlyTheirs"][0]["x"], 10.0);
+    }
+
+    #[tokio::test]
+    async fn test_restore_plan_version_saves_as_new_version() {
+        let (schema, _dir) = schema_with_context();
+        let (id, token) = create_test_plan(&schema).await;
+        schema
+            .execute(update_mutation(&id, &token, 0, "Edited", "{ x: 10, y: 10 }"))
+            .await;
+
+        let resp = schema
+            .execute(format!(
+                r#"{{ planVersions(id: "{}") {{ name version }} }}"#,
+                id
+            ))
+            .await;
+        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(
+            data["planVersions"],
+            serde_json::json!([{ "name": "Lock", "version": 0 }])
+        );
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ restorePlanVersion(id: "{}", version: 0, editToken: "{}") {{ name version gunPositions {{ x }} }} }}"#,
+                id, token
+            ))
+            .await;
+        assert!(resp.errors.is_empty(), "unexpected errors: {:?}", resp.errors);
+        let data = resp.data.into_json().unwrap();
+        let plan = &data["restorePlanVersion"];
+        assert_eq!(plan["name"], "Lock");
+        assert_eq!(plan["version"], 2);
+        assert_eq!(plan["gunPositions"], serde_json::json!([]));
+
+        // The edit that was rolled back is kept in the history
+        let resp = schema
+            .execute(format!(r#"{{ planVersions(id: "{}") {{ name }} }}"#, id))
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["planVersions"][0]["name"], "Edited");
+    }
+
+    #[tokio::test]
+    async fn test_fork_plan_copies_into_new_plan() {
+        let (schema, _dir) = schema_with_context();
+        let (id, token) = create_test_plan(&schema).await;
+        schema
+            .execute(update_mutation(
+                &id,
+                &token,
+                0,
+                "Bridge defense",
+                "{ x: 10, y: 10 }",
+            ))
+            .await;
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ forkPlan(id: "{}", newName: " Bridge defense east ") {{ id name version editToken gunPositions {{ x }} }} }}"#,
+                id
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let fork = &data["forkPlan"];
+        assert_ne!(fork["id"], id.as_str());
+        assert_eq!(fork["name"], "Bridge defense east");
+        assert_eq!(fork["version"], 0);
+        assert_eq!(fork["gunPositions"], serde_json::json!([{ "x": 10.0 }]));
+        let fork_id = fork["id"].as_str().unwrap();
+        let fork_token = fork["editToken"].as_str().unwrap();
+        assert_ne!(fork_token, token);
+
+        // The copy has its own token; the original's doesn't open it
+        let resp = schema
+            .execute(update_mutation(fork_id, &token, 0, "Mine", ""))
+            .await;
+        assert!(resp.errors[0].message.contains("Invalid edit token"));
+        let resp = schema
+            .execute(update_mutation(fork_id, fork_token, 0, "Mine", ""))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+
+        // The original is untouched
+        let resp = schema
+            .execute(format!(r#"{{ plan(id: "{}") {{ name version }} }}"#, id))
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["plan"]["name"], "Bridge defense");
+        assert_eq!(data["plan"]["version"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_fork_plan_default_name_and_unknown_plan() {
+        let (schema, _dir) = schema_with_context();
+        let (id, _) = create_test_plan(&schema).await;
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ forkPlan(id: "{}") {{ name }} }}"#,
+                id
+            ))
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["forkPlan"]["name"], "Lock (copy)");
+
+        let resp = schema
+            .execute(
+                r#"mutation { forkPlan(id: "00000000-0000-0000-0000-000000000000") { name } }"#,
+            )
+            .await;
+        assert!(resp.errors[0].message.contains("Plan not found"));
+    }
+
+    #[tokio::test]
+    async fn test_set_target_status_saves_new_version() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(r#"mutation { createPlan(input: { name: "Op", mapId: "test-map", weaponIds: [], targetPositions: [{ x: 100, y: 100 }, { x: 200, y: 200 }] }) { id editToken targetStatuses } }"#)
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let plan = &data["createPlan"];
+        assert_eq!(
+            plan["targetStatuses"],
+            serde_json::json!(["ACTIVE", "ACTIVE"])
+        );
+        let (id, token) = (
+            plan["id"].as_str().unwrap(),
+            plan["editToken"].as_str().unwrap(),
+        );
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ setTargetStatus(id: "{}", targetIndex: 1, status: DESTROYED, editToken: "{}") {{ version targetStatuses }} }}"#,
+                id, token
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["setTargetStatus"]["version"], 1);
+        assert_eq!(
+            data["setTargetStatus"]["targetStatuses"],
+            serde_json::json!(["ACTIVE", "DESTROYED"])
+        );
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ setTargetStatus(id: "{}", targetIndex: 2, status: FIRED_UPON, editToken: "{}") {{ version }} }}"#,
+                id, token
+            ))
+            .await;
+        assert!(resp.errors[0].message.contains("plan has 2 targets"));
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ setTargetStatus(id: "{}", targetIndex: 0, status: FIRED_UPON, editToken: "wrong") {{ version }} }}"#,
+                id
+            ))
+            .await;
+        assert!(resp.errors[0].message.contains("Invalid edit token"));
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_rejects_extra_target_statuses() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(r#"mutation { createPlan(input: { name: "Op", mapId: "test-map", weaponIds: [], targetStatuses: [DESTROYED] }) { id } }"#)
+            .await;
+        assert!(resp.errors[0].message.contains("target_statuses"));
+    }
+
+    #[tokio::test]
+    async fn test_restore_plan_version_needs_edit_token_and_known_version() {
+        let (schema, _dir) = schema_with_context();
+        let (id, token) = create_test_plan(&schema).await;
+        schema
+            .execute(update_mutation(&id, &token, 0, "Edited", "{ x: 10, y: 10 }"))
+            .await;
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ restorePlanVersion(id: "{}", version: 0, editToken: "wrong") {{ version }} }}"#,
+                id
+            ))
+            .await;
+        assert!(resp.errors[0].message.contains("Invalid edit token"));
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ restorePlanVersion(id: "{}", version: 7, editToken: "{}") {{ version }} }}"#,
+                id, token
+            ))
+            .await;
+        assert!(resp.errors[0].message.contains("Version not found"));
+    }
+
+    #[tokio::test]
+    async fn test_update_plan_unknown_plan_errors() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(update_mutation(
+                "00000000-0000-0000-0000-000000000000",
+                "token",
+                0,
+                "Nope",
+                "",
+            ))
+            .await;
+        assert_eq!(resp.errors.len(), 1);
+        assert!(resp.errors[0].message.contains("Plan not found"));
+    }
+
+    #[tokio::test]
+    async fn test_created_plan_gets_retention_expiry() {
+        let (schema, _dir) = schema_with_context();
+        let (id, _) = create_test_plan(&schema).await;
+        let resp = schema
+            .execute(format!(
+                r#"{{ plan(id: "{id}") {{ createdAt expiresAt }} }}"#
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let created =
+            chrono::DateTime::parse_from_rfc3339(data["plan"]["createdAt"].as_str().unwrap())
+                .unwrap();
+        let expires =
+            chrono::DateTime::parse_from_rfc3339(data["plan"]["expiresAt"].as_str().unwrap())
+                .unwrap();
+        // Expiry is whole seconds; createdAt keeps the sub-second part
+        assert!(((expires - created).num_seconds() - 30 * 86_400).abs() <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_stats_includes_cleanup() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                "{ stats { cleanup { plansExpired bytesReclaimed lastRunAt retentionDays } } }",
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let cleanup = &data["stats"]["cleanup"];
+        assert_eq!(cleanup["plansExpired"], 0);
+        assert_eq!(cleanup["bytesReclaimed"], 0);
+        assert!(cleanup["lastRunAt"].is_null());
+        assert_eq!(cleanup["retentionDays"], 30.0);
+    }
+
+    /// Execute `query` as if it came over HTTP from `ip`.
+    async fn execute_from(schema: &Schema, ip: [u8; 4], query: &str) -> async_graphql::Response {
+        let client = ClientIp(std::net::IpAddr::from(ip));
+        schema
+            .execute(async_graphql::Request::new(query).data(client))
+            .await
+    }
+
+    fn is_rate_limited(resp: &async_graphql::Response) -> bool {
+        resp.errors.iter().any(|e| {
+            e.extensions
+                .as_ref()
+                .and_then(|ext| ext.get("code"))
+                .is_some_and(|code| *code == async_graphql::Value::from(RATE_LIMITED_CODE))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_tracking_rate_limited_per_client() {
+        let (schema, _dir) = schema_with_context();
+        let track = "mutation { trackTargetPlacement }";
+        for _ in 0..3 {
+            let resp = execute_from(&schema, [10, 0, 0, 1], track).await;
+            assert!(
+                resp.errors.is_empty(),
+                "unexpected errors: {:?}",
+                resp.errors
+            );
+        }
+        let resp = execute_from(&schema, [10, 0, 0, 1], track).await;
+        assert!(is_rate_limited(&resp));
+        assert_eq!(
+            resp.errors[0].message,
+            "Too many requests, please slow down"
+        );
+
+        // Other clients and in-process calls have their own budget
+        let resp = execute_from(&schema, [10, 0, 0, 2], track).await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let resp = schema.execute(track).await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+
+        let resp = schema
+            .execute(
+                "{ stats { markerPlacements { targets } rateLimited { planCreations tracking } } }",
+            )
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["stats"]["markerPlacements"]["targets"], 5);
+        assert_eq!(data["stats"]["rateLimited"]["tracking"], 1);
+        assert_eq!(data["stats"]["rateLimited"]["planCreations"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_stats_per_war() {
+        let (schema, _dir) = schema_with_context();
+        let (id, _) = create_test_plan(&schema).await;
+        let resp = schema
+            .execute(format!(r#"{{ plan(id: "{id}") {{ warNumber }} }}"#))
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["plan"]["warNumber"], TEST_WAR);
+
+        let storage = schema.data::<Arc<Storage>>().unwrap().clone();
+        storage
+            .increment_marker_placement("target", Some(TEST_WAR - 1), None, None, 0)
+            .unwrap();
+        let resp = schema.execute("mutation { trackTargetPlacement }").await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+
+        let query = "query($war: Int) { stats(war: $war) { war currentWar wars markerPlacements { targets } } }";
+        let stats_for = |war: serde_json::Value| {
+            let request = async_graphql::Request::new(query).variables(
+                async_graphql::Variables::from_json(serde_json::json!({ "war": war })),
+            );
+            let schema = schema.clone();
+            async move { schema.execute(request).await.data.into_json().unwrap() }
+        };
+        let lifetime = stats_for(serde_json::Value::Null).await;
+        assert!(lifetime["stats"]["war"].is_null());
+        assert_eq!(lifetime["stats"]["currentWar"], TEST_WAR);
+        assert_eq!(
+            lifetime["stats"]["wars"],
+            serde_json::json!([TEST_WAR - 1, TEST_WAR])
+        );
+        assert_eq!(lifetime["stats"]["markerPlacements"]["targets"], 2);
+        let current = stats_for(TEST_WAR.into()).await;
+        assert_eq!(current["stats"]["war"], TEST_WAR);
+        assert_eq!(current["stats"]["markerPlacements"]["targets"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_stats_by_date_range() {
+        let (schema, _dir) = schema_with_context();
+        let storage = schema.data::<Arc<Storage>>().unwrap().clone();
+        // A target on 1970-01-02 and one today
+        storage
+            .increment_marker_placement("target", None, None, None, 86_400)
+            .unwrap();
+        let resp = schema.execute("mutation { trackTargetPlacement }").await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+
+        let stats = |args: &str| {
+            let query = format!("{{ stats{args} {{ from to markerPlacements {{ targets }} }} }}");
+            let schema = schema.clone();
+            async move { schema.execute(query).await }
+        };
+        let data = stats(r#"(from: "1970-01-01", to: "1970-01-02")"#)
+            .await
+            .data
+            .into_json()
+            .unwrap();
+        assert_eq!(data["stats"]["from"], "1970-01-01");
+        assert_eq!(data["stats"]["to"], "1970-01-02");
+        assert_eq!(data["stats"]["markerPlacements"]["targets"], 1);
+
+        // `to` defaults to today
+        let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+        let data = stats(r#"(from: "1970-01-03")"#)
+            .await
+            .data
+            .into_json()
+            .unwrap();
+        assert_eq!(data["stats"]["to"], today.as_str());
+        assert_eq!(data["stats"]["markerPlacements"]["targets"], 1);
+
+        let data = stats("").await.data.into_json().unwrap();
+        assert!(data["stats"]["from"].is_null());
+        assert_eq!(data["stats"]["markerPlacements"]["targets"], 2);
+
+        for (args, error) in [
+            (r#"(war: 117, from: "2024-01-01")"#, "either war or from/to"),
+            (r#"(from: "2024-13-01")"#, "from must be a date"),
+            (r#"(to: "yesterday")"#, "to must be a date"),
+            (r#"(from: "1969-12-31")"#, "before 1970"),
+            (r#"(from: "2024-02-01", to: "2024-01-31")"#, "after to"),
+        ] {
+            let resp = stats(args).await;
+            assert!(
+                resp.errors[0].message.contains(error),
+                "{args}: {:?}",
+                resp.errors
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_placements_by_map() {
+        let (schema, _dir) = schema_with_context();
+        let storage = schema.data::<Arc<Storage>>().unwrap().clone();
+        // A map that has since left the rotation keeps its file name
+        storage
+            .increment_marker_placement("target", None, Some("OldHex"), None, 0)
+            .unwrap();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    a: trackGunPlacement(weaponSlug: "test-mortar", mapId: "test-map")
+                    b: trackGunPlacement(weaponSlug: "unassigned", mapId: "test-map")
+                    c: trackTargetPlacement(mapId: "test-map")
+                    d: trackSpotterPlacement
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+
+        let resp = schema
+            .execute("{ stats { placementsByMap { mapId displayName guns targets spotters } } }")
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(
+            data["stats"]["placementsByMap"],
+            serde_json::json!([
+                { "mapId": "test-map", "displayName": "Test Map", "guns": 2, "targets": 1, "spotters": 0 },
+                { "mapId": "OldHex", "displayName": "OldHex", "guns": 0, "targets": 1, "spotters": 0 },
+            ])
+        );
+
+        let resp = schema
+            .execute(r#"mutation { trackSpotterPlacement(mapId: "NoSuchHex") }"#)
+            .await;
+        assert!(resp.errors[0].message.contains("Unknown map: NoSuchHex"));
+    }
+
+    #[tokio::test]
+    async fn test_stats_deduped_placements() {
+        let (schema, _dir) = schema_with_context();
+        let track = |query: &str, session: Option<&str>| {
+            let mut request = async_graphql::Request::new(query);
+            if let Some(id) = session {
+                request = request.data(TrackingSession::new(id));
+            }
+            let schema = schema.clone();
+            async move { schema.execute(request).await }
+        };
+        let gun = r#"mutation { trackGunPlacement(weaponSlug: "test-mortar") }"#;
+        for session in [Some("tab-a"), Some("tab-a"), Some("tab-b"), None] {
+            let resp = track(gun, session).await;
+            assert!(
+                resp.errors.is_empty(),
+                "unexpected errors: {:?}",
+                resp.errors
+            );
+        }
+        track("mutation { trackSpotterPlacement }", Some("tab-a")).await;
+
+        let resp = schema
+            .execute(
+                "{ stats { gunPlacementTotals { total } markerPlacements { spotters } \
+                 dedupedPlacements { guns targets spotters } uniquePlanners } }",
+            )
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["stats"]["gunPlacementTotals"]["total"], 4);
+        assert_eq!(data["stats"]["markerPlacements"]["spotters"], 1);
+        assert_eq!(
+            data["stats"]["dedupedPlacements"],
+            serde_json::json!({ "guns": 2, "targets": 0, "spotters": 1 })
+        );
+        assert_eq!(data["stats"]["uniquePlanners"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_map_structures() {
+        let (schema, _dir) = schema_with_context();
+        let query = r#"{ mapStructures(mapId: "test-map") { updatedAt structures { kind team position { x y grid } location victoryBase scorched } } }"#;
+        let resp = schema.execute(query).await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        assert!(resp.data.into_json().unwrap()["mapStructures"].is_null());
+
+        schema.data::<Arc<WarMaps>>().unwrap().insert(
+            "TestHex".to_string(),
+            HexData {
+                structures: vec![MapStructure {
+                    kind: StructureKind::TownHall,
+                    team: Some(Faction::Warden),
+                    position: Position {
+                        x: 1000.0,
+                        y: 400.0,
+                    },
+                    location: Some("Abandoned Ward".to_string()),
+                    victory_base: true,
+                    scorched: false,
+                }],
+                updated_at: 1_700_000_000,
+            },
+        );
+        let resp = schema.execute(query).await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let hex = &data["mapStructures"];
+        assert_eq!(hex["updatedAt"], "2023-11-14T22:13:20+00:00");
+        let town = &hex["structures"][0];
+        assert_eq!(town["kind"], "TOWN_HALL");
+        assert_eq!(town["team"], "WARDEN");
+        assert_eq!(town["position"]["x"], 1000.0);
+        assert_eq!(town["location"], "Abandoned Ward");
+        assert_eq!(town["victoryBase"], true);
+
+        let resp = schema
+            .execute(r#"{ mapStructures(mapId: "nowhere") { updatedAt } }"#)
+            .await;
+        assert_eq!(resp.errors[0].message, "Unknown map: nowhere");
+    }
+
+    #[tokio::test]
+    async fn test_share_and_load_battery_template() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema
+            .execute(
+                r#"mutation {
+                    shareBatteryTemplate(input: {
+                        name: "Mortar pair",
+                        guns: [
+                            { weaponId: "test-mortar", offsetX: -25.0, offsetY: 0.0 },
+                            { weaponId: "test-mortar", offsetX: 25.0, offsetY: 0.0 }
+                        ]
+                    }) { id name }
+                }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let id = data["shareBatteryTemplate"]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(data["shareBatteryTemplate"]["name"], "Mortar pair");
+
+        let query = format!(
+            r#"{{ batteryTemplate(id: "{}") {{ name guns {{ weaponId offsetX offsetY }} }} }}"#,
+            id
+        );
+        let data = schema
+            .execute(query.as_str())
+            .await
+            .data
+            .into_json()
+            .unwrap();
+        let guns = &data["batteryTemplate"]["guns"];
+        assert_eq!(guns.as_array().unwrap().len(), 2);
+        assert_eq!(guns[1]["weaponId"], "test-mortar");
+        assert_eq!(guns[1]["offsetX"], 25.0);
+
+        let data = schema
+            .execute(r#"{ batteryTemplate(id: "nope") { name } }"#)
+            .await
+            .data
+            .into_json()
+            .unwrap();
+        assert!(data["batteryTemplate"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_share_battery_template_validates_guns() {
+        let (schema, _dir) = schema_with_context();
+        for (guns, message) in [
+            ("[]", "1 to 12 guns"),
+            (
+                r#"[{ weaponId: "no-such-gun", offsetX: 0.0, offsetY: 0.0 }]"#,
+                "Unknown weapon",
+            ),
+            (
+                r#"[{ weaponId: "test-mortar", offsetX: 5000.0, offsetY: 0.0 }]"#,
+                "within the map size",
+            ),
+        ] {
+            let query = format!(
+                r#"mutation {{ shareBatteryTemplate(input: {{ name: "Bad", guns: {} }}) {{ id }} }}"#,
+                guns
+            );
+            let resp = schema.execute(query.as_str()).await;
+            assert!(
+                resp.errors[0].message.contains(message),
+                "{}: {}",
+                guns,
+                resp.errors[0].message
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wind_reports() {
+        let (schema, _dir) = schema_with_context();
+        let query = r#"{ windReport(mapId: "test-map") { direction strength reportedAt } }"#;
+        let resp = schema.execute(query).await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        assert!(resp.data.into_json().unwrap()["windReport"].is_null());
+
+        // Reports past their age aren't offered
+        schema
+            .data::<Arc<Storage>>()
+            .unwrap()
+            .report_wind(
+                "test-map",
+                &WindReport {
+                    direction: 90.0,
+                    strength: 1,
+                    reported_at: chrono::Utc::now().timestamp() - WIND_REPORT_MAX_AGE_SECS - 60,
+                },
+            )
+            .unwrap();
+        let data = schema.execute(query).await.data.into_json().unwrap();
+        assert!(data["windReport"].is_null());
+
+        let resp = schema
+            .execute(
+                r#"mutation { reportWind(mapId: "test-map", wind: { direction: 135.0, strength: 3 }) { direction strength } }"#,
+            )
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["reportWind"]["strength"], 3);
+
+        let data = schema.execute(query).await.data.into_json().unwrap();
+        assert_eq!(data["windReport"]["direction"], 135.0);
+        assert_eq!(data["windReport"]["strength"], 3);
+        assert!(data["windReport"]["reportedAt"].is_string());
+
+        for bad in [
+            r#"mutation { reportWind(mapId: "test-map", wind: { direction: 360.0, strength: 1 }) { strength } }"#,
+            r#"mutation { reportWind(mapId: "test-map", wind: { direction: 90.0, strength: 6 }) { strength } }"#,
+            r#"mutation { reportWind(mapId: "nowhere", wind: { direction: 90.0, strength: 1 }) { strength } }"#,
+        ] {
+            let resp = schema.execute(bad).await;
+            assert_eq!(resp.errors.len(), 1, "expected an error for {bad}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_impact_research() {
+        let (schema, _dir) = schema_with_context();
+        let query = "{ impactResearch { weaponSlug displayName samples meanMiss meanLong windSamples observedWindDrift } }";
+        let data = schema.execute(query).await.data.into_json().unwrap();
+        assert_eq!(data["impactResearch"], serde_json::json!([]));
+
+        let submit = r#"mutation($input: ImpactObservationInput!) { submitImpactObservation(input: $input) }"#;
+        for input in [
+            serde_json::json!({ "weaponId": "test-mortar", "azimuth": 0.0, "distance": 200.0, "long": 3.0, "right": 4.0 }),
+            serde_json::json!({ "weaponId": "test-mortar", "azimuth": 0.0, "distance": 200.0, "long": -6.0, "right": -8.0,
+                    "windDirection": 90.0, "windStrength": 5 }),
+        ] {
+            let resp = schema
+                .execute(async_graphql::Request::new(submit).variables(
+                    async_graphql::Variables::from_json(serde_json::json!({ "input": input })),
+                ))
+                .await;
+            assert!(
+                resp.errors.is_empty(),
+                "unexpected errors: {:?}",
+                resp.errors
+            );
+        }
+
+        let data = schema.execute(query).await.data.into_json().unwrap();
+        let summary = &data["impactResearch"][0];
+        assert_eq!(summary["weaponSlug"], "test-mortar");
+        assert_eq!(summary["displayName"], "Test Mortar");
+        assert_eq!(summary["samples"], 2);
+        assert_eq!(summary["meanMiss"], 7.5);
+        assert_eq!(summary["meanLong"], -1.5);
+        assert_eq!(summary["windSamples"], 1);
+        // Landing 8 m left with the wind blowing right: 8 m upwind
+        assert_eq!(summary["observedWindDrift"], -8.0);
+
+        for bad in [
+            serde_json::json!({ "weaponId": "nothing", "azimuth": 0.0, "distance": 200.0, "long": 0.0, "right": 0.0 }),
+            serde_json::json!({ "weaponId": "test-mortar", "azimuth": 0.0, "distance": 400.0, "long": 0.0, "right": 0.0 }),
+            serde_json::json!({ "weaponId": "test-mortar", "azimuth": 0.0, "distance": 200.0, "long": 500.0, "right": 0.0 }),
+            serde_json::json!({ "weaponId": "test-mortar", "azimuth": 0.0, "distance": 200.0, "long": 0.0, "right": 0.0,
+                    "windDirection": 90.0, "windStrength": 6 }),
+        ] {
+            let resp = schema
+                .execute(async_graphql::Request::new(submit).variables(
+                    async_graphql::Variables::from_json(serde_json::json!({ "input": bad })),
+                ))
+                .await;
+            assert_eq!(resp.errors.len(), 1, "expected an error for {bad}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_plan_rate_limited() {
+        let (schema, _dir) = schema_with_context();
+        let create = r#"mutation { createPlan(input: { name: "Spam", mapId: "test-map", weaponIds: [] }) { id } }"#;
+        for _ in 0..2 {
+            let resp = execute_from(&schema, [10, 0, 0, 1], create).await;
+            assert!(
+                resp.errors.is_empty(),
+                "unexpected errors: {:?}",
+                resp.errors
+            );
+        }
+        let resp = execute_from(&schema, [10, 0, 0, 1], create).await;
+        assert!(is_rate_limited(&resp));
+
+        let resp = schema
+            .execute("{ stats { totalPlans legacyEncodedPlans rateLimited { planCreations } } }")
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["stats"]["totalPlans"], 2);
+        assert_eq!(data["stats"]["legacyEncodedPlans"], 0);
+        assert_eq!(data["stats"]["rateLimited"]["planCreations"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_admin_operations_check_token() {
+        let (schema, _dir) = schema_with_context();
+        let resp = schema.execute("{ admin { status { totalPlans } } }").await;
+        assert_eq!(resp.errors.len(), 1);
+        assert!(resp.errors[0].message.contains("Admin token required"));
+
+        let resp = execute_as_admin(&schema, "wrong", "{ admin { status { totalPlans } } }").await;
+        assert_eq!(resp.errors.len(), 1);
+        assert!(resp.errors[0].message.contains("Invalid admin token"));
+
+        let resp = execute_as_admin(
+            &schema,
+            "wrong",
+            r#"mutation { admin { deletePlan(id: "x") } }"#,
+        )
+        .await;
+        assert!(resp.errors[0].message.contains("Invalid admin token"));
+
+        create_test_plan(&schema).await;
+        let resp = execute_as_admin(
+            &schema,
+            TEST_ADMIN_TOKEN,
+            "{ admin { status { totalPlans schemaVersion cleanup { retentionDays } } } }",
+        )
+        .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["admin"]["status"]["totalPlans"], 1);
+        assert_eq!(
+            data["admin"]["status"]["schemaVersion"],
+            crate::storage::SCHEMA_VERSION
+        );
+        assert_eq!(data["admin"]["status"]["cleanup"]["retentionDays"], 30.0);
+
+        let resp = execute_as_admin(
+            &schema,
+            TEST_ADMIN_TOKEN,
+            "mutation { admin { purgeExpiredPlans { plansDeleted } } }",
+        )
+        .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["admin"]["purgeExpiredPlans"]["plansDeleted"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_admin_lists_and_deletes_any_plan() {
+        let (schema, _dir) = schema_with_context();
+        let (first, _) = create_test_plan(&schema).await;
+        create_test_plan(&schema).await;
+
+        let resp = execute_as_admin(
+            &schema,
+            TEST_ADMIN_TOKEN,
+            "{ admin { plans(limit: 1) { total plans { id name } } } }",
+        )
+        .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["admin"]["plans"]["total"], 2);
+        assert_eq!(data["admin"]["plans"]["plans"].as_array().unwrap().len(), 1);
+
+        let resp = execute_as_admin(
+            &schema,
+            TEST_ADMIN_TOKEN,
+            "{ admin { plans(limit: 500) { total } } }",
+        )
+        .await;
+        assert_eq!(resp.errors.len(), 1);
+
+        // No edit token needed
+        let query = format!(r#"mutation {{ admin {{ deletePlan(id: "{}") }} }}"#, first);
+        let resp = execute_as_admin(&schema, TEST_ADMIN_TOKEN, &query).await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["admin"]["deletePlan"], true);
+        let resp = execute_as_admin(&schema, TEST_ADMIN_TOKEN, &query).await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["admin"]["deletePlan"], false);
+    }
+
+    #[tokio::test]
+    async fn test_admin_reset_stats() {
+        let (schema, _dir) = schema_with_context();
+        schema
+            .execute("mutation { trackTargetPlacement trackSpotterPlacement }")
+            .await;
+
+        let resp = execute_as_admin(
+            &schema,
+            TEST_ADMIN_TOKEN,
+            "mutation { admin { resetStats } }",
+        )
+        .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["admin"]["resetStats"], 2);
+
+        let resp = schema
+            .execute("{ stats { markerPlacements { targets } } }")
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["stats"]["markerPlacements"]["targets"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_admin_backup_writes_copy() {
+        let (schema, dir) = schema_with_context();
+        create_test_plan(&schema).await;
+        let resp = execute_as_admin(
+            &schema,
+            TEST_ADMIN_TOKEN,
+            "mutation { admin { backup { path sizeBytes pruned } } }",
+        )
+        .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let path = data["admin"]["backup"]["path"].as_str().unwrap();
+        assert!(Path::new(path).starts_with(dir.path().join("backups")));
+        assert!(data["admin"]["backup"]["sizeBytes"].as_u64().unwrap() > 0);
+        assert_eq!(data["admin"]["backup"]["pruned"], 0);
+        let copy = Storage::open(Path::new(path)).unwrap();
+        assert_eq!(copy.count_plans().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reload_assets_requires_admin_token() {
+        let (schema, _dir) = schema_with_context();
+        let resp = execute_as_admin(
+            &schema,
+            "wrong",
+            "mutation { admin { reloadAssets { maps weapons } } }",
+        )
+        .await;
+        assert_eq!(resp.errors.len(), 1);
+
+        let resp = schema.execute("{ weapons { displayName } }").await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["weapons"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reload_assets_swaps_in_game_data() {
+        let (schema, _dir) = schema_with_context();
+        let resp = execute_as_admin(
+            &schema,
+            TEST_ADMIN_TOKEN,
+            "mutation { admin { reloadAssets { maps weapons } } }",
+        )
+        .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        let weapons = data["admin"]["reloadAssets"]["weapons"].as_u64().unwrap();
+        assert!(weapons > 1);
+        assert!(data["admin"]["reloadAssets"]["maps"].as_u64().unwrap() > 1);
+
+        // Later requests see the reloaded data
+        let resp = schema.execute("{ weapons { displayName } }").await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["weapons"].as_array().unwrap().len() as u64, weapons);
+    }
+
+    #[tokio::test]
+    async fn test_admin_operations_disabled_without_token() {
+        let (storage, dir) = test_storage();
+        let schema = build_schema(
+            test_assets(),
+            storage,
+            RetentionPolicy {
+                plan_ttl_secs: None,
+            },
+            AdminSettings {
+                token: AdminToken::new(Some("  ")),
+                backup: BackupPolicy {
+                    dir: dir.path().to_path_buf(),
+                    interval: None,
+                    keep: 0,
+                },
+            },
+            test_rate_limiter(),
+            Arc::new(CurrentWar::default()),
+            Arc::new(WarMaps::default()),
+        );
+        let resp = execute_as_admin(
+            &schema,
+            "",
+            "mutation { admin { purgeExpiredPlans { plansDeleted } } }",
+        )
+        .await;
+        assert_eq!(resp.errors.len(), 1);
+        assert!(resp.errors[0].message.contains("Admin API is disabled"));
+    }
+
+    #[tokio::test]
+    async fn test_update_plan_requires_edit_token() {
+        let (schema, _dir) = schema_with_context();
+        let (id, _) = create_test_plan(&schema).await;
+        let resp = schema
+            .execute(update_mutation(&id, "wrong", 0, "Vandal", ""))
+            .await;
+        assert_eq!(resp.errors.len(), 1);
+        assert!(resp.errors[0].message.contains("Invalid edit token"));
+
+        // Token is omitted entirely
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ updatePlan(id: "{id}", baseVersion: 0, input: {{ name: "Vandal", mapId: "test-map", weaponIds: [] }}) {{ plan {{ version }} }} }}"#
+            ))
+            .await;
+        assert!(resp.errors[0].message.contains("Invalid edit token"));
+
+        let resp = schema
+            .execute(format!(
+                r#"{{ plan(id: "{id}") {{ name protected editToken }} }}"#
+            ))
+            .await;
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["plan"]["name"], "Lock");
+        assert_eq!(data["plan"]["protected"], true);
+        // The token is never handed out again
+        assert!(data["plan"]["editToken"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_plan_without_token_stays_open() {
+        let (schema, _dir) = schema_with_context();
+        let storage = schema.data::<Arc<Storage>>().unwrap().clone();
+        let id = uuid::Uuid::new_v4();
+        let plan = plan_from_input(
+            id,
+            CreatePlanInput {
+                coordinate_space: CoordinateSpace::Meters,
+                name: "Legacy".to_string(),
+                map_id: "test-map".to_string(),
+                weapon_ids: vec![],
+                gun_ammo: None,
+                gun_positions: None,
+                target_positions: None,
+                spotter_positions: None,
+                enemy_gun_positions: None,
+                fob_positions: None,
+                bunker_positions: None,
+                no_fire_zone_positions: None,
+                no_fire_zone_radii: None,
+                gun_target_indices: None,
+                gun_labels: None,
+                target_labels: None,
+                spotter_labels: None,
+                target_statuses: None,
+                wind_direction: None,
+                wind_strength: None,
+                calibration: None,
+                shot_log: None,
+                registrations: None,
+                gun_rings_hidden: None,
+                custom_rings: None,
+                profile: GqlWeaponProfile::Live,
+            },
+            PlanPositions::default(),
+            &RetentionPolicy {
+                plan_ttl_secs: None,
+            },
+            None,
+            None,
+        );
+        storage.save_plan(&plan).unwrap();
+        let id = id.to_string();
+
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ updatePlan(id: "{id}", baseVersion: 0, input: {{ name: "Edited", mapId: "test-map", weaponIds: [] }}) {{ plan {{ version protected }} }} }}"#
+            ))
+            .await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        let data = resp.data.into_json().unwrap();
+        assert_eq!(data["updatePlan"]["plan"]["version"], 1);
+        assert_eq!(data["updatePlan"]["plan"]["protected"], false);
+
+        // ...but can't be deleted, since nobody can prove ownership
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ deletePlan(id: "{id}", editToken: "anything") }}"#
+            ))
+            .await;
+        assert!(resp.errors[0].message.contains("no edit token"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_plan_with_token() {
+        let (schema, _dir) = schema_with_context();
+        let (id, token) = create_test_plan(&schema).await;
+        let delete =
+            |token: &str| format!(r#"mutation {{ deletePlan(id: "{id}", editToken: "{token}") }}"#);
+
+        let resp = schema.execute(delete("wrong")).await;
+        assert!(resp.errors[0].message.contains("Invalid edit token"));
+
+        let resp = schema.execute(delete(&token)).await;
+        assert!(
+            resp.errors.is_empty(),
+            "unexpected errors: {:?}",
+            resp.errors
+        );
+        assert_eq!(resp.data.into_json().unwrap()["deletePlan"], true);
+
+        let resp = schema
+            .execute(format!(r#"{{ plan(id: "{id}") {{ id }} }}"#))
+            .await;
+        assert!(resp.data.into_json().unwrap()["plan"].is_null());
+        let resp = schema.execute(delete(&token)).await;
+        assert!(resp.errors[0].message.contains("Plan not found"));
+    }
+
+    #[tokio::test]
+    async fn test_plan_lock_requires_edit_token() {
+        let (schema, _dir) = schema_with_context();
+        let (id, _) = create_test_plan(&schema).await;
+        let resp = schema
+            .execute(format!(
+                r#"mutation {{ acquirePlanLock(planId: "{id}", sessionId: "viewer") {{ acquired }} }}"#
+            ))
+            .await;
+        assert!(resp.errors[0].message.contains("Invalid edit token"));
+    }
 }
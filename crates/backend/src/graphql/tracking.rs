@@ -0,0 +1,72 @@
+//! Anonymous sessions for placement tracking.
+//!
+//! The planner sends a random per-tab id in the `X-Session-Id` header with its
+//! `track*Placement` calls. The id identifies nobody; it only lets the server
+//! count one visitor placing and removing the same gun over and over once a
+//! day in the deduped placement stats. Calls without one are still counted in
+//! the raw stats.
+
+use async_graphql::Context;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use foxhole_shared::models::TRACKING_SESSION_HEADER;
+
+/// Longest session id accepted; the planner sends 32 hex digits.
+const MAX_SESSION_ID_LEN: usize = 64;
+
+/// The anonymous session id a request presented, if it was a usable one.
+#[derive(Clone, Default)]
+pub struct TrackingSession(Option<String>);
+
+impl TrackingSession {
+    fn parse(value: &str) -> Self {
+        let value = value.trim();
+        let valid = !value.is_empty()
+            && value.len() <= MAX_SESSION_ID_LEN
+            && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        TrackingSession(valid.then(|| value.to_string()))
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for TrackingSession {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .headers
+            .get(TRACKING_SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(TrackingSession::parse)
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+impl TrackingSession {
+    pub fn new(id: &str) -> Self {
+        TrackingSession::parse(id)
+    }
+}
+
+/// The request's session id, for the deduped placement counters.
+pub(super) fn session<'a>(ctx: &'a Context<'_>) -> Option<&'a str> {
+    ctx.data_opt::<TrackingSession>()?.0.as_deref()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_session_id() {
+        let id = |value: &str| TrackingSession::parse(value).0;
+        assert_eq!(
+            id("0f3a9c2e7b1d4e5f8a6b0c9d2e1f3a4b").as_deref(),
+            Some("0f3a9c2e7b1d4e5f8a6b0c9d2e1f3a4b")
+        );
+        assert_eq!(id(" 550e8400-e29b ").as_deref(), Some("550e8400-e29b"));
+        assert_eq!(id(""), None);
+        assert_eq!(id("not a session"), None);
+        assert_eq!(id(&"a".repeat(MAX_SESSION_ID_LEN + 1)), None);
+    }
+}
@@ -0,0 +1,225 @@
+//! Liveness and readiness probes for container orchestration.
+//!
+//! `/healthz` only says the process is serving requests; `/readyz` also checks
+//! the game assets, that the database accepts writes and that the disk holding
+//! it has room, answering 503 while any of them fails.
+
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::{routing::get, Json, Router};
+use serde::Serialize;
+
+use crate::assets::SharedAssets;
+use crate::storage::Storage;
+
+/// What the readiness probe inspects.
+#[derive(Clone)]
+pub struct HealthState {
+    pub assets: Arc<SharedAssets>,
+    pub storage: Arc<Storage>,
+    /// Below this much free disk space the instance reports itself not ready.
+    pub min_free_disk_bytes: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AssetsCheck {
+    ok: bool,
+    maps: usize,
+    weapons: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DatabaseCheck {
+    ok: bool,
+    size_bytes: Option<u64>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DiskCheck {
+    ok: bool,
+    /// `null` where the platform doesn't report free space; the check then passes.
+    free_bytes: Option<u64>,
+    min_free_bytes: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Readiness {
+    status: &'static str,
+    assets: AssetsCheck,
+    database: DatabaseCheck,
+    disk: DiskCheck,
+}
+
+async fn healthz() -> Json<serde_json::Value> {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+fn readiness(state: &HealthState) -> Readiness {
+    let current = state.assets.current();
+    let assets = AssetsCheck {
+        ok: !current.maps.is_empty() && !current.weapons.is_empty(),
+        maps: current.maps.len(),
+        weapons: current.weapons.len(),
+    };
+    let writable = state.storage.check_writable();
+    if let Err(e) = &writable {
+        tracing::error!(error = %e, "Readiness check: database not writable");
+    }
+    let database = DatabaseCheck {
+        ok: writable.is_ok(),
+        size_bytes: state.storage.db_size_bytes().ok(),
+        error: writable.err(),
+    };
+    let free_bytes = state.storage.free_disk_bytes();
+    let disk = DiskCheck {
+        ok: free_bytes.is_none_or(|free| free >= state.min_free_disk_bytes),
+        free_bytes,
+        min_free_bytes: state.min_free_disk_bytes,
+    };
+    let ready = assets.ok && database.ok && disk.ok;
+    Readiness {
+        status: if ready { "ready" } else { "not_ready" },
+        assets,
+        database,
+        disk,
+    }
+}
+
+async fn readyz(State(state): State<HealthState>) -> Response {
+    // The write probe waits on the database writer — keep it off the async workers
+    match tokio::task::spawn_blocking(move || readiness(&state)).await {
+        Ok(report) if report.status == "ready" => Json(report).into_response(),
+        Ok(report) => (StatusCode::SERVICE_UNAVAILABLE, Json(report)).into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "Readiness check panicked");
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+/// The `/healthz` and `/readyz` probe routes.
+pub fn health_router(state: HealthState) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assets::Assets;
+    use axum::body::Body;
+    use axum::http::Request;
+    use foxhole_shared::models::{Faction, GameMap, Weapon};
+    use tower::ServiceExt;
+
+    fn test_state(with_assets: bool, min_free_disk_bytes: u64) -> (HealthState, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open(&dir.path().join("test.redb")).unwrap();
+        let assets = if with_assets {
+            Assets {
+                maps: vec![GameMap {
+                    image_type: "webp".to_string(),
+                    display_name: "Test Map".to_string(),
+                    file_name: "test-map".to_string(),
+                    active: true,
+                    width: 2048,
+                    height: 1776,
+                    default_view: None,
+                    war_api_name: None,
+                }],
+                weapons: vec![Weapon {
+                    faction: Faction::Colonial,
+                    display_name: "Test Mortar".to_string(),
+                    min_range: 75.0,
+                    max_range: 300.0,
+                    acc_radius: [20.0, 35.0],
+                    wind_drift: [5.0, 15.0],
+                    flight_time: [3.0, 6.0],
+                    ammo: Vec::new(),
+                    deployment: Default::default(),
+                    area_fire: false,
+                }],
+                game_version: None,
+                devbranch: None,
+                terrain_masks: Default::default(),
+            }
+        } else {
+            Assets {
+                maps: vec![],
+                weapons: vec![],
+                game_version: None,
+                devbranch: None,
+                terrain_masks: Default::default(),
+            }
+        };
+        let state = HealthState {
+            assets: Arc::new(SharedAssets::new(assets, dir.path().to_path_buf())),
+            storage,
+            min_free_disk_bytes,
+        };
+        (state, dir)
+    }
+
+    async fn get_json(router: Router, uri: &str) -> (StatusCode, serde_json::Value) {
+        let resp = router
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = resp.status();
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_healthz_ok() {
+        let (state, _dir) = test_state(false, 0);
+        let (status, json) = get_json(health_router(state), "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["status"], "ok");
+    }
+
+    #[tokio::test]
+    async fn test_readyz_ready() {
+        let (state, _dir) = test_state(true, 0);
+        let (status, json) = get_json(health_router(state), "/readyz").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["status"], "ready");
+        assert_eq!(json["assets"]["maps"], 1);
+        assert_eq!(json["assets"]["weapons"], 1);
+        assert_eq!(json["database"]["ok"], true);
+        assert!(json["database"]["sizeBytes"].as_u64().unwrap() > 0);
+        assert_eq!(json["disk"]["ok"], true);
+    }
+
+    #[tokio::test]
+    async fn test_readyz_not_ready_without_assets() {
+        let (state, _dir) = test_state(false, 0);
+        let (status, json) = get_json(health_router(state), "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["status"], "not_ready");
+        assert_eq!(json["assets"]["ok"], false);
+        assert_eq!(json["database"]["ok"], true);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_readyz_not_ready_when_disk_low() {
+        let (state, _dir) = test_state(true, u64::MAX);
+        let (status, json) = get_json(health_router(state), "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(json["disk"]["ok"], false);
+        assert_eq!(json["disk"]["minFreeBytes"], u64::MAX);
+    }
+}
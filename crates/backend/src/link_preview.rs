@@ -0,0 +1,199 @@
+//! Link previews for shared plans.
+//!
+//! Chat apps like Discord don't run the frontend, so `/plan/{id}` gets the
+//! plan's name, map and marker counts as Open Graph / Twitter meta tags in
+//! `index.html` before it is sent.
+
+use foxhole_shared::models::Plan;
+
+use crate::assets::Assets;
+
+const APP_NAME: &str = "Foxhole Artillery Planner";
+
+/// Escape text for an HTML attribute value or element body.
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// "1 gun", "3 targets".
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {noun}")
+    } else {
+        format!("{n} {noun}s")
+    }
+}
+
+/// "Artillery plan on Deadlands: 3 guns, 2 targets, 1 spotter".
+fn plan_description(plan: &Plan, assets: &Assets) -> String {
+    let map = assets
+        .find_map_by_file_name(&plan.map_id)
+        .map_or(plan.map_id.as_str(), |m| m.display_name.as_str());
+    format!(
+        "Artillery plan on {map}: {}, {}, {}",
+        count(plan.gun_positions.len(), "gun"),
+        count(plan.target_positions.len(), "target"),
+        count(plan.spotter_positions.len(), "spotter"),
+    )
+}
+
+/// Meta tags describing `plan`. `origin` is the configured scheme and host of
+/// the site (e.g. `https://arty.dp42.dev`), which crawlers need for absolute
+/// URLs; an empty one leaves them relative.
+fn plan_meta_tags(plan: &Plan, assets: &Assets, origin: &str, page_path: &str) -> String {
+    let title = escape_html(&plan.name);
+    let description = escape_html(&plan_description(plan, assets));
+    let url = escape_html(&format!("{origin}{page_path}"));
+    let image = escape_html(&format!("{origin}/plan/{}/image.png", plan.id));
+    [
+        format!(r#"<meta property="og:site_name" content="{APP_NAME}">"#),
+        r#"<meta property="og:type" content="website">"#.to_string(),
+        format!(r#"<meta property="og:title" content="{title}">"#),
+        format!(r#"<meta property="og:description" content="{description}">"#),
+        format!(r#"<meta property="og:url" content="{url}">"#),
+        format!(r#"<meta property="og:image" content="{image}">"#),
+        r#"<meta name="twitter:card" content="summary_large_image">"#.to_string(),
+        format!(r#"<meta name="twitter:title" content="{title}">"#),
+        format!(r#"<meta name="twitter:description" content="{description}">"#),
+        format!(r#"<meta name="twitter:image" content="{image}">"#),
+    ]
+    .join("\n")
+}
+
+/// `index_html` with the plan's name as the page title and its meta tags
+/// added to the head. Pages without a `</head>` are left alone.
+pub fn plan_page(
+    index_html: &str,
+    plan: &Plan,
+    assets: &Assets,
+    origin: &str,
+    page_path: &str,
+) -> String {
+    let Some(head_end) = index_html.find("</head>") else {
+        return index_html.to_string();
+    };
+    let tags = plan_meta_tags(plan, assets, origin, page_path);
+    let mut html = format!(
+        "{}{tags}\n{}",
+        &index_html[..head_end],
+        &index_html[head_end..]
+    );
+
+    if let (Some(start), Some(end)) = (html.find("<title>"), html.find("</title>")) {
+        if start < end {
+            let title = format!("{} - {APP_NAME}", escape_html(&plan.name));
+            html.replace_range(start + "<title>".len()..end, &title);
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::models::{GameMap, Position};
+
+    fn test_assets() -> Assets {
+        Assets {
+            maps: vec![GameMap {
+                image_type: "webp".to_string(),
+                display_name: "Deadlands".to_string(),
+                file_name: "MapDeadlandsHex".to_string(),
+                active: true,
+                width: 1024,
+                height: 888,
+                default_view: None,
+                war_api_name: None,
+            }],
+            weapons: vec![],
+            game_version: None,
+            devbranch: None,
+            terrain_masks: Default::default(),
+        }
+    }
+
+    fn test_plan() -> Plan {
+        let at = Position { x: 1.0, y: 1.0 };
+        Plan {
+            id: uuid::Uuid::nil(),
+            name: "Hit <the> \"bunker\"".to_string(),
+            map_id: "MapDeadlandsHex".to_string(),
+            gun_positions: vec![at, at, at],
+            target_positions: vec![at, at],
+            spotter_positions: vec![at],
+            gun_target_indices: vec![None, None, None],
+            ..Plan::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_description() {
+        let mut plan = test_plan();
+        assert_eq!(
+            plan_description(&plan, &test_assets()),
+            "Artillery plan on Deadlands: 3 guns, 2 targets, 1 spotter"
+        );
+        // Unknown maps fall back to their id
+        plan.map_id = "MapGone".to_string();
+        plan.spotter_positions.clear();
+        assert_eq!(
+            plan_description(&plan, &test_assets()),
+            "Artillery plan on MapGone: 3 guns, 2 targets, 0 spotters"
+        );
+    }
+
+    #[test]
+    fn test_plan_page_injects_meta_tags() {
+        let index = "<html><head><title>{app_title}</title></head><body></body></html>";
+        let html = plan_page(
+            index,
+            &test_plan(),
+            &test_assets(),
+            "https://arty.example",
+            "/plan/brave-falcon-42",
+        );
+        assert!(html.contains(
+            "<title>Hit &lt;the&gt; &quot;bunker&quot; - Foxhole Artillery Planner</title>"
+        ));
+        assert!(html.contains(
+            r#"<meta property="og:title" content="Hit &lt;the&gt; &quot;bunker&quot;">"#
+        ));
+        assert!(html.contains(
+            r#"<meta property="og:url" content="https://arty.example/plan/brave-falcon-42">"#
+        ));
+        // The preview image is always fetched by the plan's id
+        let image = format!("https://arty.example/plan/{}/image.png", uuid::Uuid::nil());
+        assert!(html.contains(&format!(r#"<meta property="og:image" content="{image}">"#)));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary_large_image">"#));
+        assert!(html.find("twitter:image").unwrap() < html.find("</head>").unwrap());
+    }
+
+    #[test]
+    fn test_plan_page_without_origin_uses_relative_urls() {
+        let index = "<html><head></head><body></body></html>";
+        let html = plan_page(index, &test_plan(), &test_assets(), "", "/plan/p");
+        assert!(html.contains(r#"<meta property="og:url" content="/plan/p">"#));
+        let image = format!("/plan/{}/image.png", uuid::Uuid::nil());
+        assert!(html.contains(&format!(r#"<meta property="og:image" content="{image}">"#)));
+    }
+
+    #[test]
+    fn test_plan_page_without_head_is_unchanged() {
+        let index = "<p>Frontend not built yet.</p>";
+        assert_eq!(
+            plan_page(index, &test_plan(), &test_assets(), "http://x", "/plan/a"),
+            index
+        );
+    }
+}
@@ -1,22 +1,160 @@
 mod assets;
+mod cli;
+mod config;
+mod embedded;
+mod etag;
 mod graphql;
+mod health;
+mod link_preview;
+mod rate_limit;
+mod render;
 mod storage;
+mod war;
+mod war_map;
+mod war_report;
 
-use std::path::{Path, PathBuf};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use async_graphql::parser::types::{DocumentOperations, Selection};
 use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
-use axum::http::{HeaderValue, Method};
-use axum::{extract::DefaultBodyLimit, extract::State, response::Html, routing::get, Router};
+use axum::http::{header, HeaderName, HeaderValue, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::{
+    error_handling::HandleErrorLayer, extract::ConnectInfo, extract::DefaultBodyLimit,
+    extract::FromRequest, extract::Path as UrlPath, extract::Request, extract::State,
+    middleware::Next, response::Html, routing::get, Extension, Router,
+};
+use foxhole_shared::models::TRACKING_SESSION_HEADER;
+use tower::ServiceBuilder;
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
 use tower_http::compression::CompressionLayer;
 use tower_http::cors::CorsLayer;
+use tower_http::decompression::RequestDecompressionLayer;
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::services::ServeDir;
 use tower_http::set_header::SetResponseHeaderLayer;
+use tower_http::trace::TraceLayer;
+use tracing::Instrument;
 
-use graphql::Schema;
+use config::{Config, GraphqlLimits};
+use graphql::{AdminCredential, Schema, TrackingSession};
+use rate_limit::{ClientIp, RATE_LIMITED_CODE};
 
-async fn graphql_handler(State(schema): State<Schema>, req: GraphQLRequest) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+/// GraphQL operations taking at least this long are logged as warnings.
+const SLOW_GRAPHQL_MS: u64 = 1000;
+
+/// Largest request body accepted, measured after decompression.
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+/// Name to log a GraphQL request under: its `operationName`, the operation's
+/// name in the document, or the first root field of an anonymous operation.
+fn operation_label(req: &async_graphql::Request) -> String {
+    if let Some(name) = &req.operation_name {
+        return name.clone();
+    }
+    let Ok(doc) = async_graphql::parser::parse_query(&req.query) else {
+        return "(invalid)".to_string();
+    };
+    match doc.operations {
+        DocumentOperations::Single(op) => op
+            .node
+            .selection_set
+            .node
+            .items
+            .iter()
+            .find_map(|sel| match &sel.node {
+                Selection::Field(field) => Some(field.node.name.node.to_string()),
+                _ => None,
+            })
+            .unwrap_or_else(|| "(anonymous)".to_string()),
+        DocumentOperations::Multiple(ops) if ops.len() == 1 => {
+            ops.keys().next().map(|n| n.to_string()).unwrap_or_default()
+        }
+        DocumentOperations::Multiple(_) => "(multiple)".to_string(),
+    }
+}
+
+async fn graphql_handler(
+    State(schema): State<Schema>,
+    client: Option<Extension<ClientIp>>,
+    admin: AdminCredential,
+    session: TrackingSession,
+    req: GraphQLRequest,
+) -> Response {
+    let mut req = req.into_inner().data(admin).data(session);
+    if let Some(Extension(client)) = client {
+        req = req.data(client);
+    }
+    let operation = operation_label(&req);
+    let variables_bytes = serde_json::to_vec(&req.variables).map_or(0, |v| v.len());
+    let span = tracing::info_span!("graphql", %operation, variables_bytes);
+
+    let started = Instant::now();
+    let resp = schema.execute(req).instrument(span.clone()).await;
+    let duration_ms = started.elapsed().as_millis() as u64;
+    let rate_limited = resp.errors.iter().any(|e| {
+        e.extensions
+            .as_ref()
+            .and_then(|ext| ext.get("code"))
+            .is_some_and(|code| *code == async_graphql::Value::from(RATE_LIMITED_CODE))
+    });
+    let status = if rate_limited {
+        StatusCode::TOO_MANY_REQUESTS
+    } else {
+        StatusCode::OK
+    };
+
+    let errors = resp.errors.len();
+    let status_code = status.as_u16();
+    span.in_scope(|| {
+        if duration_ms >= SLOW_GRAPHQL_MS {
+            tracing::warn!(duration_ms, errors, status_code, "Slow GraphQL operation");
+        } else {
+            tracing::info!(duration_ms, errors, status_code, "GraphQL operation");
+        }
+    });
+    (status, GraphQLResponse::from(resp)).into_response()
+}
+
+/// The caller's address: the TCP peer, or with `trust_forwarded` the last
+/// `X-Forwarded-For` hop, which is the one the reverse proxy appended.
+fn client_ip(req: &Request, trust_forwarded: bool) -> Option<IpAddr> {
+    let forwarded = trust_forwarded
+        .then(|| req.headers().get("x-forwarded-for")?.to_str().ok())
+        .flatten()
+        .and_then(|v| v.rsplit(',').next()?.trim().parse().ok());
+    forwarded.or_else(|| {
+        req.extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    })
+}
+
+/// Tag each request with its [`ClientIp`] so the GraphQL rate limits can key on it.
+async fn attach_client_ip(
+    State(trust_forwarded): State<bool>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    if let Some(ip) = client_ip(&req, trust_forwarded) {
+        req.extensions_mut().insert(ClientIp(ip));
+    }
+    next.run(req).await
+}
+
+/// Read the whole body before the GraphQL extractor sees it. The extractor
+/// streams the body and reports any read error as a bad request; buffering it
+/// here turns a body cut off by [`RequestBodyLimitLayer`] into a 413.
+async fn buffer_body(req: Request, next: Next) -> Response {
+    let (parts, body) = req.into_parts();
+    let bytes = match axum::body::Bytes::from_request(Request::new(body), &()).await {
+        Ok(bytes) => bytes,
+        Err(rejection) => return rejection.into_response(),
+    };
+    next.run(Request::from_parts(parts, bytes.into())).await
 }
 
 async fn graphiql() -> Html<String> {
@@ -27,59 +165,407 @@ async fn graphiql() -> Html<String> {
     )
 }
 
-/// Build a cache-controlled static file router.
+/// Serve a file missing on disk from the binary's embedded copy of `bundle`.
+async fn embedded_file(bundle: embedded::Bundle, uri: Uri) -> Response {
+    match embedded::get(bundle, uri.path()) {
+        Some(file) => (
+            [(header::CONTENT_TYPE, file.mime), (header::ETAG, file.etag)],
+            file.data,
+        )
+            .into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// Build a cache-controlled static file router over `dir`, falling back to
+/// the embedded `bundle` for files that aren't on disk.
+///
+/// A `.br`/`.gz` sibling on disk (as `dx build --release` writes next to the
+/// WASM bundle) is sent as-is to clients that accept that encoding. Every
+/// file gets an `ETag` so revalidation can end in a 304.
 ///
 /// Separated so tests can exercise the caching layer with arbitrary directories.
-fn cached_static_router(dir: &Path, cache_header: &'static str) -> Router {
-    let layer = SetResponseHeaderLayer::overriding(
-        axum::http::header::CACHE_CONTROL,
-        HeaderValue::from_static(cache_header),
-    );
+fn cached_static_router(dir: &Path, bundle: embedded::Bundle, cache_header: HeaderValue) -> Router {
+    let layer = SetResponseHeaderLayer::overriding(axum::http::header::CACHE_CONTROL, cache_header);
+    let embedded = get(move |uri: Uri| embedded_file(bundle, uri));
     Router::new()
-        .fallback_service(ServeDir::new(dir))
+        .fallback_service(
+            ServeDir::new(dir)
+                .precompressed_br()
+                .precompressed_gzip()
+                .fallback(embedded),
+        )
+        .layer(axum::middleware::from_fn(etag::conditional_get))
         .layer(layer)
 }
 
-const CACHE_1DAY: &str = "public, max-age=86400, must-revalidate";
-const CACHE_IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Files the built frontend keeps at its root so they can control or
+/// describe the whole site: the service worker, web app manifest and icon.
+const APP_ROOT_FILES: [&str; 3] = ["/sw.js", "/manifest.webmanifest", "/icon.svg"];
+
+/// Serve [`APP_ROOT_FILES`] from `dir`, falling back to the embedded frontend.
+///
+/// They keep their names across deploys, so browsers revalidate them on every
+/// load; otherwise a new service worker could sit unseen behind an old cache.
+fn app_root_router(dir: &Path) -> Router {
+    let embedded = get(|uri: Uri| embedded_file(embedded::Bundle::Frontend, uri));
+    let files = ServeDir::new(dir)
+        .precompressed_br()
+        .precompressed_gzip()
+        .fallback(embedded);
+    APP_ROOT_FILES
+        .iter()
+        .fold(Router::new(), |router, path| {
+            router.route_service(path, files.clone())
+        })
+        .layer(axum::middleware::from_fn(etag::conditional_get))
+        .layer(SetResponseHeaderLayer::overriding(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-cache"),
+        ))
+}
+
+/// Compress responses on the fly, except ones too small to benefit and
+/// formats that are already compressed (map images, fonts, archives).
+/// Precompressed files keep their `Content-Encoding` and pass through untouched.
+fn compression_layer() -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(256)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE)
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::const_new("font/woff"))
+        .and(NotForContentType::const_new("application/zip"))
+        .and(NotForContentType::const_new("application/gzip"));
+    CompressionLayer::new().compress_when(predicate)
+}
+
+/// Render a saved plan as SVG, mapping storage errors and missing plans to HTTP statuses.
+fn plan_svg_or_status(state: &render::RenderState, id: &str) -> Result<String, StatusCode> {
+    match render::render_plan_svg(state, id) {
+        Ok(Some(svg)) => Ok(svg),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!(plan_id = %id, error = %e, "Failed to render plan image");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn plan_image_svg(
+    State(state): State<render::RenderState>,
+    UrlPath(id): UrlPath<String>,
+) -> Response {
+    match plan_svg_or_status(&state, &id) {
+        Ok(svg) => ([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+async fn plan_image_png(
+    State(state): State<render::RenderState>,
+    UrlPath(id): UrlPath<String>,
+) -> Response {
+    // Rasterizing a full map takes a while — keep it off the async workers
+    let result = tokio::task::spawn_blocking(move || {
+        let svg = plan_svg_or_status(&state, &id)?;
+        render::svg_to_png(&svg).map_err(|e| {
+            tracing::error!(plan_id = %id, error = %e, "Failed to rasterize plan image");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+    })
+    .await
+    .unwrap_or(Err(StatusCode::INTERNAL_SERVER_ERROR));
+
+    match result {
+        Ok(png) => ([(header::CONTENT_TYPE, "image/png")], png).into_response(),
+        Err(status) => status.into_response(),
+    }
+}
+
+/// The planner page for a saved plan, with link preview tags for chat apps.
+async fn serve_plan_page(
+    State(state): State<render::RenderState>,
+    UrlPath(id): UrlPath<String>,
+) -> Html<String> {
+    let index = index_html();
+    let plan = match state.storage.find_plan(&id) {
+        Ok(plan) => plan,
+        Err(e) => {
+            tracing::error!(plan_id = %id, error = %e, "Failed to load plan for link preview");
+            None
+        }
+    };
+    let Some(plan) = plan else {
+        return Html(index);
+    };
+    let assets = state.assets.current();
+    Html(link_preview::plan_page(
+        &index,
+        &plan,
+        &assets,
+        state.public_url.as_deref().unwrap_or_default(),
+        &format!("/plan/{id}"),
+    ))
+}
+
+/// Routes that show saved plans: the planner page with link previews, and
+/// the plan rendered as an image.
+fn plan_image_router(state: render::RenderState) -> Router {
+    Router::new()
+        .route("/plan/{id}", get(serve_plan_page))
+        .route("/plan/{id}/image.svg", get(plan_image_svg))
+        .route("/plan/{id}/image.png", get(plan_image_png))
+        .with_state(state)
+}
+
+/// Turn a timed-out request into a GraphQL-shaped error the frontend can show.
+async fn graphql_limit_error(err: tower::BoxError) -> Response {
+    let (status, message) = if err.is::<tower::timeout::error::Elapsed>() {
+        tracing::warn!("GraphQL request timed out");
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Server is busy, please try again",
+        )
+    } else {
+        tracing::error!(error = %err, "GraphQL request failed");
+        (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+    };
+    let body = serde_json::json!({ "errors": [{ "message": message }] });
+    (status, axum::Json(body)).into_response()
+}
+
+/// Apply the GraphQL timeout and concurrency limit to every route in `router`.
+fn with_graphql_limits(router: Router, limits: GraphqlLimits) -> Router {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(HandleErrorLayer::new(graphql_limit_error))
+            .timeout(limits.timeout)
+            .concurrency_limit(limits.max_concurrent),
+    )
+}
+
+/// The `/graphql` endpoint: GraphiQL on GET, queries on POST. Large clients
+/// send gzipped bodies (`Content-Encoding: gzip`); [`MAX_BODY_BYTES`] caps the
+/// decompressed stream, so a small body can't inflate without bound.
+/// `/graphql/schema` serves the SDL for client generators. Requests that hit
+/// a per-client rate limit get a 429.
+fn graphql_router(schema: Schema, limits: GraphqlLimits, trust_forwarded: bool) -> Router {
+    // Rendered once; the schema doesn't change while the server runs
+    let sdl = schema.sdl();
+    with_graphql_limits(
+        Router::new()
+            .route("/graphql", get(graphiql).post(graphql_handler))
+            .route(
+                "/graphql/schema",
+                get(move || async move {
+                    ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], sdl)
+                }),
+            )
+            .with_state(schema)
+            .layer(axum::middleware::from_fn(buffer_body))
+            .layer(RequestBodyLimitLayer::new(MAX_BODY_BYTES))
+            .layer(RequestDecompressionLayer::new())
+            .layer(axum::middleware::from_fn_with_state(
+                trust_forwarded,
+                attach_client_ip,
+            )),
+        limits,
+    )
+}
+
+/// Snapshot the database on `policy`'s interval, keeping its newest `keep`.
+fn spawn_scheduled_backups(
+    storage: Arc<storage::Storage>,
+    policy: storage::BackupPolicy,
+    every: Duration,
+) {
+    tokio::spawn(async move {
+        let first = storage::next_backup_in(&policy.dir, every, chrono::Utc::now().timestamp());
+        let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + first, every);
+        loop {
+            ticker.tick().await;
+            let storage = storage.clone();
+            let policy = policy.clone();
+            let now = chrono::Utc::now().timestamp();
+            match tokio::task::spawn_blocking(move || storage.backup(&policy, now)).await {
+                Ok(Ok(report)) => tracing::info!(
+                    path = %report.path.display(),
+                    size_bytes = report.size_bytes,
+                    pruned = report.pruned,
+                    "Scheduled database backup written"
+                ),
+                Ok(Err(e)) => tracing::error!(error = %e, "Scheduled database backup failed"),
+                Err(e) => tracing::error!(error = %e, "Scheduled database backup panicked"),
+            }
+        }
+    });
+}
+
+/// Run `--backup` or `--restore` against the configured database.
+fn run_maintenance(command: cli::Command, config: &Config) -> Result<(), String> {
+    let policy = &config.admin.backup;
+    let now = chrono::Utc::now().timestamp();
+    match command {
+        cli::Command::Backup => {
+            let report = storage::Storage::open(&config.db_path)?.backup(policy, now)?;
+            tracing::info!(
+                path = %report.path.display(),
+                size_bytes = report.size_bytes,
+                pruned = report.pruned,
+                "Database backed up"
+            );
+        }
+        cli::Command::Restore(snapshot) => {
+            if config.db_path.exists() {
+                // Kept unpruned, in case the wrong snapshot was picked
+                let saved = storage::Storage::open(&config.db_path)?.backup_to(&policy.dir, now)?;
+                tracing::info!(path = %saved.display(), "Saved the current database");
+            }
+            storage::Storage::restore(&snapshot, &config.db_path)?;
+            tracing::info!(
+                snapshot = %snapshot.display(),
+                db_path = %config.db_path.display(),
+                "Database restored"
+            );
+        }
+        cli::Command::Serve | cli::Command::Help => {}
+    }
+    Ok(())
+}
+
+/// Periodically delete expired plans and compact the database.
+fn spawn_plan_cleanup(
+    storage: Arc<storage::Storage>,
+    retention: storage::RetentionPolicy,
+    every: Duration,
+) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            let storage = storage.clone();
+            let now = chrono::Utc::now().timestamp();
+            match tokio::task::spawn_blocking(move || storage.run_cleanup(now, &retention)).await {
+                Ok(Ok(report)) if report.plans_deleted > 0 => tracing::info!(
+                    plans_deleted = report.plans_deleted,
+                    bytes_reclaimed = report.bytes_reclaimed,
+                    "Expired plans cleaned up"
+                ),
+                Ok(Ok(_)) => tracing::debug!("Plan cleanup found nothing to delete"),
+                Ok(Err(e)) => tracing::error!(error = %e, "Plan cleanup failed"),
+                Err(e) => tracing::error!(error = %e, "Plan cleanup task panicked"),
+            }
+        }
+    });
+}
 
 /// Build CORS layer from allowed origins.
 ///
-/// In production, set `CORS_ORIGIN` to the deployed domain (e.g. `https://arty.dp42.dev`).
-/// In development, localhost origins are allowed by default.
+/// Release builds allow only same-origin requests unless `CORS_ORIGIN` (or
+/// `server.cors_origins`) lists more, e.g. `https://arty.dp42.dev`. Debug
+/// builds allow the localhost dev origins by default.
 fn cors_layer(allowed_origins: &[HeaderValue]) -> CorsLayer {
     CorsLayer::new()
         .allow_origin(allowed_origins.to_vec())
         .allow_methods([Method::GET, Method::POST])
-        .allow_headers([axum::http::header::CONTENT_TYPE])
+        .allow_headers([
+            axum::http::header::CONTENT_TYPE,
+            axum::http::header::CONTENT_ENCODING,
+            axum::http::header::AUTHORIZATION,
+            HeaderName::from_static(TRACKING_SESSION_HEADER),
+        ])
+}
+
+/// Whether `origin` (e.g. `https://arty.dp42.dev`) names the host the request was sent to.
+fn is_same_origin(origin: &str, host: Option<&str>) -> bool {
+    let origin_host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    host.is_some_and(|h| h.eq_ignore_ascii_case(origin_host))
+}
+
+/// Refuse POSTs that a browser sent from a page on another, non-allowed origin.
+/// CORS alone only stops the page reading the response; the mutation would
+/// still run. Requests without an `Origin` header (scripts, bots) pass.
+async fn reject_foreign_origin(
+    State(allowed): State<Arc<[HeaderValue]>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if let (&Method::POST, Some(origin)) = (req.method(), req.headers().get(header::ORIGIN)) {
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|h| h.to_str().ok())
+            .or_else(|| req.uri().authority().map(|a| a.as_str()));
+        let same_origin = origin.to_str().is_ok_and(|o| is_same_origin(o, host));
+        if !same_origin && !allowed.contains(origin) {
+            tracing::warn!(origin = ?origin, "Rejected cross-origin request");
+            let body = serde_json::json!({ "errors": [{ "message": "Origin not allowed" }] });
+            return (StatusCode::FORBIDDEN, axum::Json(body)).into_response();
+        }
+    }
+    next.run(req).await
+}
+
+/// Apply [`reject_foreign_origin`] to every route in `router`.
+fn with_origin_check(router: Router, allowed_origins: &[HeaderValue]) -> Router {
+    let allowed: Arc<[HeaderValue]> = allowed_origins.into();
+    router.layer(axum::middleware::from_fn_with_state(
+        allowed,
+        reject_foreign_origin,
+    ))
 }
 
 /// Build the full application router.
-fn build_app(schema: Schema, allowed_origins: &[HeaderValue]) -> Router {
+fn build_app(
+    schema: Schema,
+    render_state: render::RenderState,
+    health_state: health::HealthState,
+    config: &Config,
+) -> Router {
     // Static file routers are stateless — merge them before adding app state
     let static_files = Router::new()
         .nest(
             "/static",
-            cached_static_router(Path::new("assets"), CACHE_1DAY),
+            cached_static_router(
+                Path::new("assets"),
+                embedded::Bundle::GameAssets,
+                config.cache.static_files.clone(),
+            ),
         )
         .nest(
             "/dist",
-            cached_static_router(Path::new("dist"), CACHE_IMMUTABLE),
+            cached_static_router(
+                Path::new("dist"),
+                embedded::Bundle::Frontend,
+                config.cache.immutable.clone(),
+            ),
         )
         .nest(
             "/assets",
-            cached_static_router(Path::new("dist/assets"), CACHE_IMMUTABLE),
-        );
+            cached_static_router(
+                Path::new("dist/assets"),
+                embedded::Bundle::FrontendAssets,
+                config.cache.immutable.clone(),
+            ),
+        )
+        .merge(app_root_router(Path::new("dist")));
 
     Router::new()
-        .route("/graphql", get(graphiql).post(graphql_handler))
         .route("/", get(serve_index))
-        .route("/plan/{id}", get(serve_index))
-        .with_state(schema)
+        .route("/admin", get(serve_index))
+        .route("/stats", get(serve_index))
+        .merge(with_origin_check(
+            graphql_router(schema, config.graphql, config.rate_limits.trust_forwarded),
+            &config.cors_origins,
+        ))
+        .merge(plan_image_router(render_state))
+        .merge(health::health_router(health_state))
         .merge(static_files)
-        .layer(DefaultBodyLimit::max(256 * 1024)) // 256 KB
-        .layer(cors_layer(allowed_origins))
-        .layer(CompressionLayer::new())
+        .layer(DefaultBodyLimit::max(MAX_BODY_BYTES))
+        .layer(cors_layer(&config.cors_origins))
+        .layer(compression_layer())
+        // Request/response events at DEBUG under the `tower_http` target
+        .layer(TraceLayer::new_for_http())
 }
 
 #[tokio::main]
@@ -91,37 +577,104 @@ async fn main() {
         )
         .init();
 
-    let assets_dir =
-        PathBuf::from(std::env::var("ASSETS_DIR").unwrap_or_else(|_| "assets".to_string()));
-    let loaded_assets = Arc::new(assets::Assets::load(&assets_dir).unwrap_or_else(|e| {
+    let command = cli::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}\n\n{}", e, cli::USAGE);
+        std::process::exit(2);
+    });
+    if command == cli::Command::Help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    let config = Config::load().unwrap_or_else(|e| {
+        tracing::error!(error = %e, "Invalid configuration");
+        std::process::exit(1);
+    });
+
+    if command != cli::Command::Serve {
+        if let Err(e) = run_maintenance(command, &config) {
+            tracing::error!(error = %e, "Maintenance command failed");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let loaded_assets = assets::Assets::load(&config.assets_dir).unwrap_or_else(|e| {
         tracing::error!(error = %e, "Failed to load game assets");
         std::process::exit(1);
-    }));
+    });
+    let loaded_assets = Arc::new(assets::SharedAssets::new(
+        loaded_assets,
+        config.assets_dir.clone(),
+    ));
 
-    let db_path =
-        PathBuf::from(std::env::var("DB_PATH").unwrap_or_else(|_| "data/plans.redb".to_string()));
-    if let Some(parent) = db_path.parent() {
+    if let Some(parent) = config.db_path.parent() {
         std::fs::create_dir_all(parent).expect("Failed to create database directory");
     }
-    let storage = storage::Storage::open(&db_path).unwrap_or_else(|e| {
+    let storage = storage::Storage::open(&config.db_path).unwrap_or_else(|e| {
         tracing::error!(error = %e, "Failed to open database");
         std::process::exit(1);
     });
 
-    let allowed_origins: Vec<HeaderValue> = match std::env::var("CORS_ORIGIN") {
-        Ok(origin) => vec![origin.parse().expect("Invalid CORS_ORIGIN value")],
-        Err(_) => vec![
-            "http://localhost:8080".parse().unwrap(),
-            "http://localhost:3000".parse().unwrap(),
-        ],
+    match config.retention.plan_ttl_secs {
+        Some(_) => spawn_plan_cleanup(storage.clone(), config.retention, config.cleanup_interval),
+        None => tracing::info!("Plan retention disabled, plans are kept forever"),
+    }
+    match config.admin.backup.interval {
+        Some(every) => spawn_scheduled_backups(storage.clone(), config.admin.backup.clone(), every),
+        None => tracing::info!("Scheduled backups disabled"),
+    }
+    let current_war = match &config.war {
+        war::WarSource::Fixed(number) => {
+            tracing::info!(war = number, "Using configured war number");
+            Arc::new(war::CurrentWar::fixed(*number))
+        }
+        war::WarSource::Api { url, every } => {
+            let current_war = Arc::new(war::CurrentWar::default());
+            war::spawn_war_poller(current_war.clone(), url.clone(), *every);
+            current_war
+        }
+        war::WarSource::Unknown => {
+            tracing::info!("War number unknown, only lifetime stats are kept");
+            Arc::new(war::CurrentWar::default())
+        }
     };
 
-    let schema = graphql::build_schema(loaded_assets, storage);
-    let app = build_app(schema, &allowed_origins);
+    let war_maps = Arc::new(war_map::WarMaps::default());
+    match &config.war_maps {
+        Some(settings) => war_map::spawn_map_poller(war_maps.clone(), settings.clone()),
+        None => tracing::info!("Live map data disabled"),
+    }
+    if let Some(settings) = &config.war_report {
+        war_report::spawn_war_reporter(storage.clone(), loaded_assets.clone(), settings.clone());
+    }
 
-    let port = std::env::var("PORT").unwrap_or_else(|_| "3000".to_string());
-    let addr = format!("0.0.0.0:{}", port);
-    tracing::info!(port = %port, "Server starting at http://localhost:{}", port);
+    let render_state = render::RenderState {
+        assets: loaded_assets.clone(),
+        storage: storage.clone(),
+        assets_dir: config.assets_dir.clone(),
+        public_url: config.public_url.clone(),
+    };
+    let health_state = health::HealthState {
+        assets: loaded_assets.clone(),
+        storage: storage.clone(),
+        min_free_disk_bytes: config.min_free_disk_bytes,
+    };
+    let rate_limiter = Arc::new(rate_limit::RateLimiter::new(config.rate_limits));
+    let schema = graphql::build_schema(
+        loaded_assets,
+        storage,
+        config.retention,
+        config.admin.clone(),
+        rate_limiter,
+        current_war,
+        war_maps,
+    );
+    let app = build_app(schema, render_state, health_state, &config);
+
+    let addr = config.bind_addr;
+    let port = addr.port();
+    tracing::info!(%addr, "Server starting at http://localhost:{}", port);
     tracing::info!("GraphiQL playground at http://localhost:{}/graphql", port);
 
     let listener = tokio::net::TcpListener::bind(&addr)
@@ -130,6 +683,8 @@ async fn main() {
             tracing::error!(addr = %addr, error = %e, "Failed to bind TCP listener");
             std::process::exit(1);
         });
+    // Peer addresses feed the per-client rate limits
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
     axum::serve(listener, app).await.unwrap_or_else(|e| {
         tracing::error!(error = %e, "Server error");
         std::process::exit(1);
@@ -137,11 +692,17 @@ async fn main() {
 }
 
 async fn serve_index() -> Html<String> {
-    // Try to serve the built frontend, fall back to a simple message
-    match std::fs::read_to_string("dist/index.html") {
-        Ok(html) => Html(html),
-        Err(_) => Html(
-            r#"<!DOCTYPE html>
+    Html(index_html())
+}
+
+fn index_html() -> String {
+    // Try the built frontend on disk, then the embedded copy, then a simple message
+    let html = embedded::read(Path::new("dist"), embedded::Bundle::Frontend, "index.html")
+        .ok()
+        .and_then(|data| String::from_utf8(data.into_owned()).ok());
+    match html {
+        Some(html) => html,
+        None => r#"<!DOCTYPE html>
 <html>
 <head><title>Foxhole Artillery Planner</title></head>
 <body>
@@ -149,14 +710,15 @@ async fn serve_index() -> Html<String> {
 <p>Frontend not built yet. Visit <a href="/graphql">GraphiQL</a> to explore the API.</p>
 </body>
 </html>"#
-                .to_string(),
-        ),
+            .to_string(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::CacheHeaders;
+    use crate::rate_limit::RateLimits;
     use axum::body::Body;
     use axum::http::{Request, StatusCode};
     use tower::ServiceExt;
@@ -164,11 +726,29 @@ mod tests {
     /// Build a test app that serves files from the given temp directories.
     fn test_app(assets_dir: &Path, dist_dir: &Path, dist_assets_dir: &Path) -> Router {
         Router::new()
-            .nest("/static", cached_static_router(assets_dir, CACHE_1DAY))
-            .nest("/dist", cached_static_router(dist_dir, CACHE_IMMUTABLE))
+            .nest(
+                "/static",
+                cached_static_router(
+                    assets_dir,
+                    embedded::Bundle::GameAssets,
+                    CacheHeaders::default().static_files,
+                ),
+            )
+            .nest(
+                "/dist",
+                cached_static_router(
+                    dist_dir,
+                    embedded::Bundle::Frontend,
+                    CacheHeaders::default().immutable,
+                ),
+            )
             .nest(
                 "/assets",
-                cached_static_router(dist_assets_dir, CACHE_IMMUTABLE),
+                cached_static_router(
+                    dist_assets_dir,
+                    embedded::Bundle::FrontendAssets,
+                    CacheHeaders::default().immutable,
+                ),
             )
     }
 
@@ -179,6 +759,55 @@ mod tests {
         dir
     }
 
+    #[tokio::test]
+    async fn test_app_root_files_are_revalidated() {
+        let dist_dir = temp_dir_with_file("sw.js", "self.addEventListener('fetch', () => {});");
+        std::fs::write(dist_dir.path().join("manifest.webmanifest"), "{}").unwrap();
+        let app = app_root_router(dist_dir.path());
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/sw.js")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("cache-control").unwrap(), "no-cache");
+        assert!(resp.headers().contains_key("etag"));
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/manifest.webmanifest")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/manifest+json"
+        );
+
+        // Only the listed files are exposed at the root
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/index.html")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn test_static_assets_have_1day_cache() {
         let assets_dir = temp_dir_with_file("maps.json", "[]");
@@ -281,7 +910,7 @@ mod tests {
         dist_dir: &Path,
         dist_assets_dir: &Path,
     ) -> Router {
-        test_app(assets_dir, dist_dir, dist_assets_dir).layer(CompressionLayer::new())
+        test_app(assets_dir, dist_dir, dist_assets_dir).layer(compression_layer())
     }
 
     /// Create a temp dir with a file containing enough content to trigger compression.
@@ -367,6 +996,165 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_precompressed_wasm_served_when_accepted() {
+        let assets_dir = temp_dir_with_file("maps.json", "[]");
+        let dist_dir = temp_dir_with_file("index.html", "<html></html>");
+        let dist_assets_dir = temp_dir_with_compressible_file("app_bg.wasm");
+        std::fs::write(
+            dist_assets_dir.path().join("app_bg.wasm.br"),
+            b"brotli bytes",
+        )
+        .unwrap();
+
+        let app = test_app_compressed(assets_dir.path(), dist_dir.path(), dist_assets_dir.path());
+
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/assets/app_bg.wasm")
+                    .header("accept-encoding", "br, gzip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("content-encoding").unwrap(), "br");
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/wasm"
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"brotli bytes");
+
+        // No .gz sibling: gzip-only clients get it compressed on the fly instead
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/assets/app_bg.wasm")
+                    .header("accept-encoding", "gzip")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.headers().get("content-encoding").unwrap(), "gzip");
+    }
+
+    #[tokio::test]
+    async fn test_map_images_not_recompressed() {
+        let assets_dir = temp_dir_with_compressible_file("map.webp");
+        let dist_dir = temp_dir_with_file("index.html", "<html></html>");
+        let dist_assets_dir = temp_dir_with_file("app.js", "");
+
+        let app = test_app_compressed(assets_dir.path(), dist_dir.path(), dist_assets_dir.path());
+
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri("/static/map.webp")
+                    .header("accept-encoding", "gzip, br")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get("content-encoding").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_map_image_revalidation_returns_304() {
+        let assets_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(assets_dir.path().join("images/maps")).unwrap();
+        std::fs::write(
+            assets_dir.path().join("images/maps/Deadlands.webp"),
+            [0u8; 4096],
+        )
+        .unwrap();
+        let dist_dir = temp_dir_with_file("index.html", "<html></html>");
+        let dist_assets_dir = temp_dir_with_file("app.js", "");
+
+        let app = test_app(assets_dir.path(), dist_dir.path(), dist_assets_dir.path());
+        let get_map = |if_none_match: Option<&str>| {
+            let mut req = Request::builder().uri("/static/images/maps/Deadlands.webp");
+            if let Some(tag) = if_none_match {
+                req = req.header("if-none-match", tag);
+            }
+            app.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let first = get_map(None).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = first
+            .headers()
+            .get("etag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let repeat = get_map(Some(&etag)).await.unwrap();
+        assert_eq!(repeat.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(repeat.headers().get("etag").unwrap(), etag.as_str());
+        assert_eq!(
+            repeat.headers().get("cache-control").unwrap(),
+            "public, max-age=86400, must-revalidate"
+        );
+        let body = axum::body::to_bytes(repeat.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+
+        let stale = get_map(Some("W/\"0000000000000000\"")).await.unwrap();
+        assert_eq!(stale.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_dist_bundles_revalidate_with_etag() {
+        let assets_dir = temp_dir_with_file("maps.json", "[]");
+        let dist_dir = temp_dir_with_file("index.html", "<html></html>");
+        let dist_assets_dir = temp_dir_with_file("app.js", "console.log()");
+
+        let app = test_app(assets_dir.path(), dist_dir.path(), dist_assets_dir.path());
+
+        let first = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/assets/app.js")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let etag = first.headers().get("etag").unwrap().clone();
+
+        let repeat = app
+            .oneshot(
+                Request::builder()
+                    .uri("/assets/app.js")
+                    .header("if-none-match", etag)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(repeat.status(), StatusCode::NOT_MODIFIED);
+        assert!(repeat
+            .headers()
+            .get("cache-control")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .contains("immutable"));
+    }
+
     #[tokio::test]
     async fn test_static_and_dist_have_different_cache_policies() {
         let assets_dir = temp_dir_with_file("data.json", "{}");
@@ -413,4 +1201,504 @@ mod tests {
         assert!(static_cc.contains("max-age=86400"));
         assert!(dist_cc.contains("max-age=31536000"));
     }
+
+    #[tokio::test]
+    async fn test_graphql_limits_time_out_slow_requests() {
+        let slow = Router::new().route(
+            "/graphql",
+            axum::routing::post(|| async {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                "done"
+            }),
+        );
+        let limits = GraphqlLimits {
+            max_concurrent: 1,
+            timeout: Duration::from_millis(20),
+        };
+        let resp = with_graphql_limits(slow, limits)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/graphql")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json["errors"][0]["message"],
+            "Server is busy, please try again"
+        );
+    }
+
+    const UNLIMITED: RateLimits = RateLimits {
+        plan_creations_per_min: 0,
+        tracking_per_min: 0,
+        trust_forwarded: false,
+    };
+
+    const TEST_ADMIN_TOKEN: &str = "test-admin-token";
+
+    /// Schema over an empty temp database with no maps or weapons.
+    fn empty_schema(dir: &Path, rate_limits: RateLimits) -> Schema {
+        graphql::build_schema(
+            Arc::new(assets::SharedAssets::new(
+                assets::Assets {
+                    maps: vec![],
+                    weapons: vec![],
+                    game_version: None,
+                    devbranch: None,
+                    terrain_masks: Default::default(),
+                },
+                dir.to_path_buf(),
+            )),
+            storage::Storage::open(&dir.join("test.redb")).unwrap(),
+            storage::RetentionPolicy {
+                plan_ttl_secs: None,
+            },
+            graphql::AdminSettings {
+                token: graphql::AdminToken::new(Some(TEST_ADMIN_TOKEN)),
+                backup: storage::BackupPolicy {
+                    dir: dir.join("backups"),
+                    interval: None,
+                    keep: 0,
+                },
+            },
+            Arc::new(rate_limit::RateLimiter::new(rate_limits)),
+            Arc::new(war::CurrentWar::default()),
+            Arc::new(war_map::WarMaps::default()),
+        )
+    }
+
+    #[test]
+    fn test_client_ip_prefers_proxy_hop_only_when_trusted() {
+        let req = Request::builder()
+            .header("x-forwarded-for", "1.2.3.4, 203.0.113.7")
+            .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 9], 4000))))
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(client_ip(&req, true), Some(IpAddr::from([203, 0, 113, 7])));
+        assert_eq!(client_ip(&req, false), Some(IpAddr::from([10, 0, 0, 9])));
+        let bare = Request::builder().body(Body::empty()).unwrap();
+        assert_eq!(client_ip(&bare, true), None);
+    }
+
+    #[test]
+    fn test_operation_label() {
+        let label = |query: &str| operation_label(&async_graphql::Request::new(query));
+        assert_eq!(
+            label("query FetchPlan($id: ID!) { plan(id: $id) { id } }"),
+            "FetchPlan"
+        );
+        assert_eq!(label("{ stats { totalPlans } }"), "stats");
+        assert_eq!(
+            label("mutation { trackTargetPlacement }"),
+            "trackTargetPlacement"
+        );
+        assert_eq!(
+            label("query A { maps { fileName } } query B { stats { totalPlans } }"),
+            "(multiple)"
+        );
+        assert_eq!(label("{ broken"), "(invalid)");
+        let named = async_graphql::Request::new("query A { a } query B { b }").operation_name("B");
+        assert_eq!(operation_label(&named), "B");
+    }
+
+    #[tokio::test]
+    async fn test_graphql_rate_limited_request_returns_429() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = empty_schema(
+            dir.path(),
+            RateLimits {
+                tracking_per_min: 1,
+                ..UNLIMITED
+            },
+        );
+        let router = graphql_router(schema, GraphqlLimits::default(), false);
+        let track = || {
+            Request::builder()
+                .method("POST")
+                .uri("/graphql")
+                .header("content-type", "application/json")
+                .extension(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))))
+                .body(Body::from(
+                    r#"{"query":"mutation { trackTargetPlacement }"}"#,
+                ))
+                .unwrap()
+        };
+
+        let resp = router.clone().oneshot(track()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = router.oneshot(track()).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            json["errors"][0]["message"],
+            "Too many requests, please slow down"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_graphql_passes_bearer_token_to_admin_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        let router = graphql_router(
+            empty_schema(dir.path(), UNLIMITED),
+            GraphqlLimits::default(),
+            false,
+        );
+        let admin_status = |authorization: Option<&str>| {
+            let mut req = Request::builder()
+                .method("POST")
+                .uri("/graphql")
+                .header("content-type", "application/json");
+            if let Some(value) = authorization {
+                req = req.header("authorization", value);
+            }
+            let body = Body::from(r#"{"query":"{ admin { status { totalPlans } } }"}"#);
+            router.clone().oneshot(req.body(body).unwrap())
+        };
+        let json = |resp: Response| async move {
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let resp = admin_status(Some(&format!("Bearer {}", TEST_ADMIN_TOKEN)))
+            .await
+            .unwrap();
+        assert_eq!(json(resp).await["data"]["admin"]["status"]["totalPlans"], 0);
+
+        let resp = admin_status(None).await.unwrap();
+        assert!(json(resp).await["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Admin token required"));
+        let resp = admin_status(Some("Bearer nope")).await.unwrap();
+        assert_eq!(
+            json(resp).await["errors"][0]["message"],
+            "Invalid admin token"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_graphql_accepts_gzipped_body() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let schema = empty_schema(dir.path(), UNLIMITED);
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(br#"{"query":"{ maps { fileName } }"}"#)
+            .unwrap();
+        let limits = GraphqlLimits::default();
+
+        let resp = graphql_router(schema, limits, false)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/graphql")
+                    .header("content-type", "application/json")
+                    .header("content-encoding", "gzip")
+                    .body(Body::from(gz.finish().unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["maps"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_graphql_rejects_gzipped_body_that_inflates_past_the_limit() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let schema = empty_schema(dir.path(), UNLIMITED);
+        // Whitespace compresses to almost nothing but inflates past the limit
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        gz.write_all(br#"{"query":"{ maps { fileName } }""#)
+            .unwrap();
+        gz.write_all(&vec![b' '; MAX_BODY_BYTES]).unwrap();
+        gz.write_all(b"}").unwrap();
+        let gz = gz.finish().unwrap();
+        assert!(gz.len() < 4 * 1024);
+        let limits = GraphqlLimits::default();
+
+        let resp = graphql_router(schema, limits, false)
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/graphql")
+                    .header("content-type", "application/json")
+                    .header("content-encoding", "gzip")
+                    .body(Body::from(gz))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_schema_serves_sdl() {
+        let dir = tempfile::tempdir().unwrap();
+        let schema = empty_schema(dir.path(), UNLIMITED);
+        let limits = GraphqlLimits::default();
+
+        let resp = graphql_router(schema, limits, false)
+            .oneshot(
+                Request::builder()
+                    .uri("/graphql/schema")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let sdl = String::from_utf8(body.to_vec()).unwrap();
+        assert!(sdl.contains("type QueryRoot"));
+        assert!(sdl.contains("createPlan(input: CreatePlanInput!): GqlPlan!"));
+    }
+
+    /// GraphQL router with the production origin check and CORS layers.
+    fn cors_test_app(dir: &Path) -> Router {
+        let allowed = [HeaderValue::from_static("https://arty.example.com")];
+        let router = graphql_router(
+            empty_schema(dir, UNLIMITED),
+            GraphqlLimits::default(),
+            false,
+        );
+        with_origin_check(router, &allowed).layer(cors_layer(&allowed))
+    }
+
+    fn track_from(origin: &str, host: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/graphql")
+            .header("content-type", "application/json")
+            .header("origin", origin)
+            .header("host", host)
+            .body(Body::from(
+                r#"{"query":"mutation { trackTargetPlacement }"}"#,
+            ))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cross_origin_mutation_from_disallowed_origin_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let resp = cors_test_app(dir.path())
+            .oneshot(track_from("https://evil.example.com", "arty.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        assert!(resp.headers().get("access-control-allow-origin").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_allowed_and_same_origin_mutations_pass() {
+        let dir = tempfile::tempdir().unwrap();
+        let resp = cors_test_app(dir.path())
+            .oneshot(track_from("https://arty.example.com", "api.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers()["access-control-allow-origin"],
+            "https://arty.example.com"
+        );
+
+        let resp = cors_test_app(dir.path())
+            .oneshot(track_from("http://localhost:3000", "localhost:3000"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_preflight_from_disallowed_origin_not_granted() {
+        let dir = tempfile::tempdir().unwrap();
+        let resp = cors_test_app(dir.path())
+            .oneshot(
+                Request::builder()
+                    .method("OPTIONS")
+                    .uri("/graphql")
+                    .header("origin", "https://evil.example.com")
+                    .header("access-control-request-method", "POST")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(resp.headers().get("access-control-allow-origin").is_none());
+    }
+
+    #[test]
+    fn test_is_same_origin() {
+        let host = Some("arty.dp42.dev");
+        assert!(is_same_origin("https://arty.dp42.dev", host));
+        assert!(is_same_origin("https://ARTY.dp42.dev", host));
+        assert!(!is_same_origin("https://evil.dp42.dev", host));
+        // A different port is a different origin
+        let dev_host = Some("localhost:3000");
+        assert!(!is_same_origin("http://localhost:8080", dev_host));
+        assert!(!is_same_origin("https://arty.dp42.dev", None));
+    }
+
+    /// Image router backed by a temp database holding one saved plan (no map images on disk).
+    fn image_test_app() -> (Router, String, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = storage::Storage::open(&dir.path().join("test.redb")).unwrap();
+        let plan = foxhole_shared::models::Plan {
+            id: uuid::Uuid::new_v4(),
+            name: "Image test".to_string(),
+            map_id: "test-map".to_string(),
+            gun_positions: vec![foxhole_shared::models::Position { x: 100.0, y: 100.0 }],
+            gun_target_indices: vec![None],
+            ..foxhole_shared::models::Plan::default()
+        };
+        storage.save_plan(&plan).unwrap();
+        let state = render::RenderState {
+            assets: Arc::new(assets::SharedAssets::new(
+                assets::Assets {
+                    maps: vec![],
+                    weapons: vec![],
+                    game_version: None,
+                    devbranch: None,
+                    terrain_masks: Default::default(),
+                },
+                dir.path().to_path_buf(),
+            )),
+            storage,
+            assets_dir: dir.path().to_path_buf(),
+            public_url: Some("https://arty.example".to_string()),
+        };
+        (plan_image_router(state), plan.id.to_string(), dir)
+    }
+
+    #[tokio::test]
+    async fn test_plan_image_svg_served() {
+        let (app, id, _dir) = image_test_app();
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/plan/{}/image.svg", id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/svg+xml");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.starts_with(b"<svg"));
+    }
+
+    #[tokio::test]
+    async fn test_plan_image_png_served() {
+        let (app, id, _dir) = image_test_app();
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/plan/{}/image.png", id))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("content-type").unwrap(), "image/png");
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.starts_with(b"\x89PNG"));
+    }
+
+    #[tokio::test]
+    async fn test_plan_page_has_link_preview_tags() {
+        let (app, id, _dir) = image_test_app();
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/plan/{}", id))
+                    // Previews use the configured URL, never the request's
+                    .header("host", "evil.example")
+                    .header("x-forwarded-proto", "gopher")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(body.to_vec()).unwrap();
+        assert!(html.contains(r#"<meta property="og:title" content="Image test">"#));
+        assert!(!html.contains("evil.example"));
+        assert!(html.contains(&format!(
+            r#"<meta property="og:image" content="https://arty.example/plan/{id}/image.png">"#
+        )));
+
+        // Unknown plans still get the planner, just without a preview
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/plan/{}", uuid::Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(!String::from_utf8(body.to_vec()).unwrap().contains("og:title"));
+    }
+
+    #[tokio::test]
+    async fn test_plan_image_unknown_plan_returns_404() {
+        let (app, _id, _dir) = image_test_app();
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/plan/{}/image.svg", uuid::Uuid::new_v4()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
 }
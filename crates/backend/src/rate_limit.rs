@@ -0,0 +1,189 @@
+//! Per-client token buckets for the mutations anyone can call without a token.
+//!
+//! The HTTP layer works out the caller's IP (see `client_ip` in `main.rs`) and
+//! hands it to the GraphQL request as a [`ClientIp`]; the rate-limited mutations
+//! then take a token from that client's bucket before doing any work.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Above this many tracked clients, buckets that have refilled are dropped.
+const MAX_TRACKED_BUCKETS: usize = 10_000;
+
+/// Error extension `code` set on errors from a rejected request.
+pub const RATE_LIMITED_CODE: &str = "RATE_LIMITED";
+
+/// Which budget a request draws from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateClass {
+    /// `createPlan` and `forkPlan`.
+    PlanCreation,
+    /// The `track*Placement` statistics mutations, `reportWind` and
+    /// `submitImpactObservation`.
+    Tracking,
+}
+
+/// Requests allowed per client per minute, also the burst size. `0` disables the limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimits {
+    pub plan_creations_per_min: u32,
+    pub tracking_per_min: u32,
+    /// Take the client IP from the `X-Forwarded-For` entry added by a reverse proxy.
+    pub trust_forwarded: bool,
+}
+
+impl RateLimits {
+    fn per_min(&self, class: RateClass) -> u32 {
+        match class {
+            RateClass::PlanCreation => self.plan_creations_per_min,
+            RateClass::Tracking => self.tracking_per_min,
+        }
+    }
+}
+
+/// The address a GraphQL request came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Requests turned away since startup, per class.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RejectedCounts {
+    pub plan_creations: u64,
+    pub tracking: u64,
+}
+
+pub struct RateLimiter {
+    limits: RateLimits,
+    buckets: Mutex<HashMap<(IpAddr, RateClass), Bucket>>,
+    rejected_plan_creations: AtomicU64,
+    rejected_tracking: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(limits: RateLimits) -> Self {
+        RateLimiter {
+            limits,
+            buckets: Mutex::new(HashMap::new()),
+            rejected_plan_creations: AtomicU64::new(0),
+            rejected_tracking: AtomicU64::new(0),
+        }
+    }
+
+    /// Take a token from `ip`'s bucket for `class`. Returns `false`, and counts
+    /// the rejection, when the bucket is empty.
+    pub fn check(&self, ip: IpAddr, class: RateClass, now: Instant) -> bool {
+        let per_min = self.limits.per_min(class);
+        if per_min == 0 {
+            return true;
+        }
+        let capacity = f64::from(per_min);
+        let refill_per_sec = capacity / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_TRACKED_BUCKETS {
+            buckets.retain(|(_, c), b| {
+                let cap = f64::from(self.limits.per_min(*c));
+                let elapsed = now.saturating_duration_since(b.updated).as_secs_f64();
+                b.tokens + elapsed * cap / 60.0 < cap
+            });
+        }
+        let bucket = buckets.entry((ip, class)).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return true;
+        }
+        drop(buckets);
+
+        let counter = match class {
+            RateClass::PlanCreation => &self.rejected_plan_creations,
+            RateClass::Tracking => &self.rejected_tracking,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        false
+    }
+
+    pub fn rejected(&self) -> RejectedCounts {
+        RejectedCounts {
+            plan_creations: self.rejected_plan_creations.load(Ordering::Relaxed),
+            tracking: self.rejected_tracking.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn limiter(plans: u32, tracking: u32) -> RateLimiter {
+        RateLimiter::new(RateLimits {
+            plan_creations_per_min: plans,
+            tracking_per_min: tracking,
+            trust_forwarded: false,
+        })
+    }
+
+    const IP_A: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+    const IP_B: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn test_burst_up_to_limit_then_rejects() {
+        let limiter = limiter(3, 0);
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check(IP_A, RateClass::PlanCreation, now));
+        }
+        assert!(!limiter.check(IP_A, RateClass::PlanCreation, now));
+        assert_eq!(limiter.rejected().plan_creations, 1);
+        assert_eq!(limiter.rejected().tracking, 0);
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = limiter(6, 0);
+        let start = Instant::now();
+        for _ in 0..6 {
+            assert!(limiter.check(IP_A, RateClass::PlanCreation, start));
+        }
+        assert!(!limiter.check(IP_A, RateClass::PlanCreation, start));
+        // 6 per minute refills one token every 10 seconds
+        let later = start + Duration::from_secs(10);
+        assert!(limiter.check(IP_A, RateClass::PlanCreation, later));
+        assert!(!limiter.check(IP_A, RateClass::PlanCreation, later));
+    }
+
+    #[test]
+    fn test_clients_and_classes_have_separate_buckets() {
+        let limiter = limiter(1, 1);
+        let now = Instant::now();
+        assert!(limiter.check(IP_A, RateClass::PlanCreation, now));
+        assert!(limiter.check(IP_B, RateClass::PlanCreation, now));
+        assert!(limiter.check(IP_A, RateClass::Tracking, now));
+        assert!(!limiter.check(IP_A, RateClass::Tracking, now));
+        assert_eq!(limiter.rejected().tracking, 1);
+    }
+
+    #[test]
+    fn test_zero_disables_limit() {
+        let limiter = limiter(0, 0);
+        let now = Instant::now();
+        for _ in 0..1000 {
+            assert!(limiter.check(IP_A, RateClass::Tracking, now));
+        }
+        assert_eq!(limiter.rejected(), RejectedCounts::default());
+    }
+}
@@ -0,0 +1,341 @@
+//! Server-side rendering of saved plans as standalone SVG / PNG map images.
+//!
+//! Reuses the overlay builder from `foxhole_shared::overlay`, so the exported
+//! image shows the same markers, range rings and firing lines as the planner.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use base64::Engine;
+use foxhole_shared::calc;
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::models::{self, Plan, Weapon, WindInput};
+use foxhole_shared::overlay::{
+    build_custom_rings, build_intel_markers, build_svg_content, marker_scale, GridWindow,
+    ShellSpread, MARKER_COLORS, REFERENCE_WIDTH,
+};
+
+use crate::assets::{Assets, SharedAssets};
+use crate::embedded::{self, Bundle};
+use crate::storage::Storage;
+
+/// Everything the image endpoints need: plans, weapon data and the map images on disk.
+#[derive(Clone)]
+pub struct RenderState {
+    pub assets: Arc<SharedAssets>,
+    pub storage: Arc<Storage>,
+    pub assets_dir: PathBuf,
+    /// Where the site is served from, for absolute URLs in link previews.
+    pub public_url: Option<String>,
+}
+
+/// Read the map image for a plan and return it as a `data:` URI, if present.
+fn map_image_data_uri(assets_dir: &Path, assets: &Assets, map_id: &str) -> Option<String> {
+    let map = assets.find_map_by_file_name(map_id)?;
+    let rel_path = format!("images/maps/{}.{}", map.file_name, map.image_type);
+    let bytes = embedded::read(assets_dir, Bundle::GameAssets, &rel_path)
+        .inspect_err(|e| {
+            let path = assets_dir.join(&rel_path);
+            tracing::warn!(path = %path.display(), error = %e, "Map image not readable");
+        })
+        .ok()?;
+    Some(format!(
+        "data:image/{};base64,{}",
+        map.image_type,
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    ))
+}
+
+/// Build a standalone SVG document for a plan (map image + overlay).
+///
+/// `map_image_href` is embedded as the background when given; otherwise the
+/// overlay is drawn on a plain dark background.
+pub fn plan_svg(plan: &Plan, assets: &Assets, map_image_href: Option<&str>) -> String {
+    let scale = assets
+        .find_map_by_file_name(&plan.map_id)
+        .map(|m| m.scale())
+        .unwrap_or_default();
+    let to_px = |p: &foxhole_shared::models::Position| scale.meters_to_px(p.x, p.y);
+    let guns: Vec<(f64, f64)> = plan.gun_positions.iter().map(to_px).collect();
+    let targets: Vec<(f64, f64)> = plan.target_positions.iter().map(to_px).collect();
+    let spotters: Vec<(f64, f64)> = plan.spotter_positions.iter().map(to_px).collect();
+    let intel = |list: &[models::Position]| -> Vec<(f64, f64)> { list.iter().map(to_px).collect() };
+
+    let loaded: Vec<Option<Weapon>> = (0..plan.gun_positions.len())
+        .map(|i| {
+            let weapon = plan
+                .weapon_ids
+                .get(i)
+                .and_then(|slug| assets.find_weapon_of(plan.weapon_profile, slug))?;
+            let ammo = models::gun_ammo(&plan.gun_ammo, i);
+            Some(weapon.with_ammo(ammo).unwrap_or_else(|| weapon.clone()))
+        })
+        .collect();
+    let gun_weapons: Vec<Option<&Weapon>> = loaded.iter().map(Option::as_ref).collect();
+
+    let wind = plan.wind_direction.map(|direction| WindInput {
+        direction,
+        strength: plan.wind_strength,
+    });
+
+    // No-fire zones any paired gun's shells could land in
+    let zones = plan.intel.no_fire_circles();
+    let mut danger_close = Vec::new();
+    let spreads_px: Vec<Option<ShellSpread>> = plan
+        .gun_positions
+        .iter()
+        .enumerate()
+        .map(|(gi, &gun)| {
+            let ti = plan.gun_target_indices.get(gi).copied().flatten()?;
+            let target = *plan.target_positions.get(ti)?;
+            let weapon = gun_weapons.get(gi).copied().flatten()?;
+            let sol = calc::firing_solution(gun, target, weapon, wind.as_ref());
+            danger_close.extend(calc::danger_close(target, sol.accuracy_radius, &zones));
+            Some(ShellSpread::from_solution(
+                sol.accuracy_radius,
+                sol.impact_zone.as_ref(),
+                scale,
+            ))
+        })
+        .collect();
+    danger_close.sort_unstable();
+    danger_close.dedup();
+
+    let mut overlay = build_svg_content(
+        &guns,
+        &targets,
+        &spotters,
+        &intel(&plan.intel.enemy_guns),
+        &gun_weapons,
+        &plan.range_rings.hidden,
+        &plan.gun_target_indices,
+        &spreads_px,
+        &[],
+        &plan.marker_labels(),
+        &plan.target_statuses,
+        1.0,
+        false,
+        GridWindow::ALL,
+        REFERENCE_WIDTH,
+        None,
+        None,
+        wind.as_ref(),
+        false,
+        &MARKER_COLORS,
+        scale,
+    );
+    overlay.push_str(&build_custom_rings(
+        &plan.range_rings.custom,
+        &guns,
+        &targets,
+        &spotters,
+        marker_scale(1.0, REFERENCE_WIDTH),
+        scale,
+    ));
+    overlay.push_str(&build_intel_markers(
+        &intel(&plan.intel.fobs),
+        &intel(&plan.intel.bunkers),
+        &intel(&plan.intel.no_fire_zones),
+        &plan.intel.no_fire_radii,
+        &danger_close,
+        marker_scale(1.0, REFERENCE_WIDTH),
+        None,
+        &MARKER_COLORS,
+        scale,
+    ));
+    let MapScale {
+        width_px: width,
+        height_px: height,
+        ..
+    } = scale;
+
+    let background = match map_image_href {
+        Some(href) => {
+            format!(r#"<image href="{href}" x="0" y="0" width="{width}" height="{height}"/>"#)
+        }
+        None => {
+            format!(r##"<rect x="0" y="0" width="{width}" height="{height}" fill="#1a1a1a"/>"##)
+        }
+    };
+
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">{background}{overlay}</svg>"#
+    )
+}
+
+/// Load a plan by id or slug and render it to SVG. `Ok(None)` if the plan doesn't exist.
+pub fn render_plan_svg(state: &RenderState, id: &str) -> Result<Option<String>, String> {
+    let Some(plan) = state.storage.find_plan(id)? else {
+        return Ok(None);
+    };
+    let assets = state.assets.current();
+    let href = map_image_data_uri(&state.assets_dir, &assets, &plan.map_id);
+    Ok(Some(plan_svg(&plan, &assets, href.as_deref())))
+}
+
+/// Rasterize an SVG document to PNG bytes.
+pub fn svg_to_png(svg: &str) -> Result<Vec<u8>, String> {
+    let mut options = resvg::usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = resvg::usvg::Tree::from_str(svg, &options)
+        .map_err(|e| format!("Failed to parse SVG: {}", e))?;
+
+    let size = tree.size().to_int_size();
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| "Failed to allocate image".to_string())?;
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::default(),
+        &mut pixmap.as_mut(),
+    );
+    pixmap
+        .encode_png()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::models::{CustomRing, Faction, GameMap, Position, RingAnchor};
+
+    fn test_assets() -> Assets {
+        Assets {
+            maps: vec![GameMap {
+                image_type: "webp".to_string(),
+                display_name: "Test Map".to_string(),
+                file_name: "test-map".to_string(),
+                active: true,
+                width: 1024,
+                height: 888,
+                default_view: None,
+                war_api_name: None,
+            }],
+            weapons: vec![Weapon {
+                faction: Faction::Colonial,
+                display_name: "Test Mortar".to_string(),
+                min_range: 75.0,
+                max_range: 300.0,
+                acc_radius: [20.0, 35.0],
+                wind_drift: [5.0, 15.0],
+                flight_time: [3.0, 6.0],
+                ammo: Vec::new(),
+                deployment: Default::default(),
+                area_fire: false,
+            }],
+            game_version: None,
+            devbranch: None,
+            terrain_masks: Default::default(),
+        }
+    }
+
+    fn test_plan(assets: &Assets) -> Plan {
+        Plan {
+            id: uuid::Uuid::new_v4(),
+            name: "Test".to_string(),
+            map_id: "test-map".to_string(),
+            weapon_ids: vec![assets.weapons[0].slug()],
+            gun_positions: vec![Position { x: 500.0, y: 500.0 }],
+            target_positions: vec![Position { x: 700.0, y: 500.0 }],
+            gun_target_indices: vec![Some(0)],
+            ..Plan::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_svg_is_standalone_document() {
+        let assets = test_assets();
+        let svg = plan_svg(&test_plan(&assets), &assets, None);
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg""#));
+        // Sized to the plan's map image
+        assert!(svg.contains(r#"viewBox="0 0 1024 888""#));
+        assert!(svg.ends_with("</svg>"));
+        // No map image: plain background
+        assert!(svg.contains("<rect"));
+        assert!(!svg.contains("<image"));
+        // Gun range ring and firing line to the paired target
+        assert!(svg.contains("<circle"));
+        assert!(svg.contains("<line"));
+    }
+
+    #[test]
+    fn test_plan_svg_applies_range_ring_settings() {
+        let assets = test_assets();
+        let mut plan = test_plan(&assets);
+        let shown = plan_svg(&plan, &assets, None);
+        plan.range_rings.set_shown(0, false);
+        let hidden = plan_svg(&plan, &assets, None);
+        assert!(hidden.matches("<circle").count() < shown.matches("<circle").count());
+        assert!(hidden.contains("<line"));
+
+        plan.range_rings.custom.push(CustomRing {
+            anchor: RingAnchor::Target,
+            index: 0,
+            radius: 250.0,
+            label: "Intel".to_string(),
+        });
+        let svg = plan_svg(&plan, &assets, None);
+        assert!(svg.contains(r#"class="custom-ring""#));
+        assert!(svg.contains(">Intel</text>"));
+    }
+
+    #[test]
+    fn test_plan_svg_draws_intel_markers() {
+        let assets = test_assets();
+        let mut plan = test_plan(&assets);
+        assert!(!plan_svg(&plan, &assets, None).contains("ENEMY"));
+        plan.intel.enemy_guns.push(Position { x: 200.0, y: 200.0 });
+        plan.intel.fobs.push(Position { x: 300.0, y: 300.0 });
+        plan.intel
+            .no_fire_zones
+            .push(Position { x: 400.0, y: 400.0 });
+        let svg = plan_svg(&plan, &assets, None);
+        assert!(svg.contains(">ENEMY</text>"));
+        assert!(svg.contains(">FOB</text>"));
+        assert!(svg.contains(r#"class="no-fire-zone""#));
+    }
+
+    #[test]
+    fn test_plan_svg_flags_danger_close_zones() {
+        let assets = test_assets();
+        let mut plan = test_plan(&assets);
+        // 30 m past the target: inside any shell's reach
+        plan.intel
+            .no_fire_zones
+            .push(Position { x: 730.0, y: 500.0 });
+        plan.intel.no_fire_radii.push(20.0);
+        let svg = plan_svg(&plan, &assets, None);
+        assert!(svg.contains(r#"class="no-fire-zone danger-close""#));
+        assert!(svg.contains(">DANGER CLOSE</text>"));
+    }
+
+    #[test]
+    fn test_plan_svg_embeds_map_image() {
+        let assets = test_assets();
+        let svg = plan_svg(
+            &test_plan(&assets),
+            &assets,
+            Some("data:image/webp;base64,AAAA"),
+        );
+        assert!(svg.contains(r#"<image href="data:image/webp;base64,AAAA""#));
+    }
+
+    #[test]
+    fn test_map_image_data_uri_reads_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("images/maps")).unwrap();
+        std::fs::write(dir.path().join("images/maps/test-map.webp"), b"abc").unwrap();
+        let assets = test_assets();
+        assert_eq!(
+            map_image_data_uri(dir.path(), &assets, "test-map").as_deref(),
+            Some("data:image/webp;base64,YWJj")
+        );
+        assert_eq!(map_image_data_uri(dir.path(), &assets, "unknown"), None);
+    }
+
+    #[test]
+    fn test_svg_to_png_produces_png() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="8"><rect width="16" height="8" fill="#f00"/></svg>"##;
+        let png = svg_to_png(svg).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    }
+}
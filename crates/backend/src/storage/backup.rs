@@ -0,0 +1,167 @@
+//! Snapshots of the database file: taking them, pruning old ones and putting
+//! one back in place of the live database.
+//!
+//! Snapshots are named `plans-<UTC timestamp>.redb`, so they sort by age and
+//! the scheduler can tell from the directory alone when the last one was taken.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use redb::{Database, ReadableDatabase};
+
+use super::{Storage, PLANS_TABLE};
+
+const SNAPSHOT_PREFIX: &str = "plans-";
+const SNAPSHOT_SUFFIX: &str = ".redb";
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Where snapshots go, how often they're taken and how many are kept.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupPolicy {
+    pub dir: PathBuf,
+    /// Time between scheduled snapshots; `None` only backs up on request.
+    pub interval: Option<Duration>,
+    /// Newest snapshots kept after each backup; `0` keeps them all.
+    pub keep: usize,
+}
+
+/// A snapshot written by [`Storage::backup`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupReport {
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    /// Older snapshots deleted to stay within [`BackupPolicy::keep`].
+    pub pruned: usize,
+}
+
+/// When the snapshot at `path` was taken, if it's named like one.
+fn snapshot_time(path: &Path) -> Option<i64> {
+    let stamp = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(SNAPSHOT_SUFFIX)?;
+    chrono::NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT)
+        .ok()
+        .map(|t| t.and_utc().timestamp())
+}
+
+/// Snapshots in `dir` with their timestamps, oldest first. Other files are
+/// ignored and a missing directory has none.
+fn list_snapshots(dir: &Path) -> Result<Vec<(i64, PathBuf)>, String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut snapshots: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|path| Some((snapshot_time(&path)?, path)))
+        .collect();
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// Delete all but the newest `keep` snapshots in `dir`. Returns how many went.
+pub fn prune_snapshots(dir: &Path, keep: usize) -> Result<usize, String> {
+    if keep == 0 {
+        return Ok(0);
+    }
+    let snapshots = list_snapshots(dir)?;
+    let excess = snapshots.len().saturating_sub(keep);
+    for (_, path) in &snapshots[..excess] {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+    }
+    Ok(excess)
+}
+
+/// Time until the next scheduled snapshot is due, counted from the newest one
+/// in `dir` so that restarting the server doesn't reset the clock.
+pub fn next_backup_in(dir: &Path, interval: Duration, now: i64) -> Duration {
+    let newest = list_snapshots(dir)
+        .ok()
+        .and_then(|snapshots| snapshots.last().map(|(taken, _)| *taken));
+    match newest {
+        Some(taken) => {
+            let due = taken.saturating_add(interval.as_secs() as i64);
+            Duration::from_secs(due.saturating_sub(now).max(0) as u64)
+        }
+        None => Duration::ZERO,
+    }
+}
+
+impl Storage {
+    /// Copy the database file into `dir` as `plans-<timestamp>.redb`. Returns
+    /// the new file's path.
+    ///
+    /// An open write transaction holds off other writers while the file is
+    /// copied, so the copy is the state after the last commit.
+    pub fn backup_to(&self, dir: &Path, now: i64) -> Result<PathBuf, String> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let stamp = chrono::DateTime::from_timestamp(now, 0)
+            .ok_or("Invalid backup timestamp")?
+            .format(STAMP_FORMAT);
+        let dest = dir.join(format!("{SNAPSHOT_PREFIX}{stamp}{SNAPSHOT_SUFFIX}"));
+        // Copy under a temporary name so a half-written backup is never mistaken for one
+        let partial = dest.with_extension("redb.partial");
+
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        let copied = std::fs::copy(&self.path, &partial);
+        write_txn.abort().map_err(|e| e.to_string())?;
+        copied.map_err(|e| format!("Failed to copy database to {}: {}", partial.display(), e))?;
+        std::fs::rename(&partial, &dest)
+            .map_err(|e| format!("Failed to move backup to {}: {}", dest.display(), e))?;
+        Ok(dest)
+    }
+
+    /// Take a snapshot into the policy's directory, then drop the ones past
+    /// its retention count.
+    pub fn backup(&self, policy: &BackupPolicy, now: i64) -> Result<BackupReport, String> {
+        let path = self.backup_to(&policy.dir, now)?;
+        let size_bytes = std::fs::metadata(&path).map_or(0, |m| m.len());
+        let pruned = prune_snapshots(&policy.dir, policy.keep)?;
+        Ok(BackupReport {
+            path,
+            size_bytes,
+            pruned,
+        })
+    }
+
+    /// Replace the database file at `db_path` with a copy of `snapshot`.
+    ///
+    /// Run this with the database closed: redb locks the file while it's open,
+    /// so the server has to be stopped first. The snapshot is checked to be a
+    /// plans database before anything is overwritten.
+    pub fn restore(snapshot: &Path, db_path: &Path) -> Result<(), String> {
+        if let (Ok(a), Ok(b)) = (
+            std::fs::canonicalize(snapshot),
+            std::fs::canonicalize(db_path),
+        ) {
+            if a == b {
+                return Err(format!("{} is the live database", snapshot.display()));
+            }
+        }
+        {
+            let db = Database::open(snapshot)
+                .map_err(|e| format!("Failed to open snapshot {}: {}", snapshot.display(), e))?;
+            let read_txn = db.begin_read().map_err(|e| e.to_string())?;
+            read_txn
+                .open_table(PLANS_TABLE)
+                .map_err(|e| format!("{} is not a plans database: {}", snapshot.display(), e))?;
+        }
+
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut partial = db_path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        std::fs::copy(snapshot, &partial)
+            .map_err(|e| format!("Failed to copy snapshot to {}: {}", partial.display(), e))?;
+        std::fs::rename(&partial, db_path)
+            .map_err(|e| format!("Failed to move snapshot to {}: {}", db_path.display(), e))
+    }
+}
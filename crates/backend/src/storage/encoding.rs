@@ -0,0 +1,202 @@
+//! How plans are laid out on disk.
+//!
+//! A stored plan is a one-byte format tag followed by the body. Rows written
+//! before tags existed are bare JSON objects, so a leading `{` marks the legacy
+//! encoding: they still read normally and switch to the binary form the next
+//! time the plan is saved.
+
+use foxhole_shared::models::{
+    IntelMarkers, MapCalibration, Plan, Position, RangeRings, RegistrationPoint, ShotRecord,
+    TargetStatus, WeaponProfile,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// `postcard` encoding of [`StoredPlan`].
+const FORMAT_POSTCARD: u8 = 1;
+/// First byte of every legacy JSON row.
+const LEGACY_JSON: u8 = b'{';
+
+/// The fields of a [`Plan`] in postcard's positional layout. Nothing in it,
+/// including the shared model types it holds, can be added, removed or
+/// reordered without breaking existing rows: a change to the stored fields
+/// goes through a migration in [`super::migrations`] that rewrites the rows.
+#[derive(Serialize, Deserialize)]
+struct StoredPlan {
+    id: Uuid,
+    name: String,
+    map_id: String,
+    weapon_ids: Vec<String>,
+    gun_ammo: Vec<String>,
+    gun_positions: Vec<Position>,
+    target_positions: Vec<Position>,
+    spotter_positions: Vec<Position>,
+    gun_target_indices: Vec<Option<usize>>,
+    gun_labels: Vec<String>,
+    target_labels: Vec<String>,
+    spotter_labels: Vec<String>,
+    target_statuses: Vec<TargetStatus>,
+    wind_direction: Option<f64>,
+    wind_strength: u8,
+    version: u32,
+    expires_at: Option<i64>,
+    war_number: Option<u32>,
+    calibration: Option<MapCalibration>,
+    shot_log: Vec<ShotRecord>,
+    registrations: Vec<RegistrationPoint>,
+    range_rings: RangeRings,
+    intel: IntelMarkers,
+    weapon_data_version: Option<String>,
+    weapon_profile: WeaponProfile,
+    created_at: String,
+    updated_at: String,
+}
+
+impl From<&Plan> for StoredPlan {
+    fn from(plan: &Plan) -> Self {
+        StoredPlan {
+            id: plan.id,
+            name: plan.name.clone(),
+            map_id: plan.map_id.clone(),
+            weapon_ids: plan.weapon_ids.clone(),
+            gun_ammo: plan.gun_ammo.clone(),
+            gun_positions: plan.gun_positions.clone(),
+            target_positions: plan.target_positions.clone(),
+            spotter_positions: plan.spotter_positions.clone(),
+            gun_target_indices: plan.gun_target_indices.clone(),
+            gun_labels: plan.gun_labels.clone(),
+            target_labels: plan.target_labels.clone(),
+            spotter_labels: plan.spotter_labels.clone(),
+            target_statuses: plan.target_statuses.clone(),
+            wind_direction: plan.wind_direction,
+            wind_strength: plan.wind_strength,
+            version: plan.version,
+            expires_at: plan.expires_at,
+            war_number: plan.war_number,
+            calibration: plan.calibration.clone(),
+            shot_log: plan.shot_log.clone(),
+            registrations: plan.registrations.clone(),
+            range_rings: plan.range_rings.clone(),
+            intel: plan.intel.clone(),
+            weapon_data_version: plan.weapon_data_version.clone(),
+            weapon_profile: plan.weapon_profile,
+            created_at: plan.created_at.clone(),
+            updated_at: plan.updated_at.clone(),
+        }
+    }
+}
+
+impl From<StoredPlan> for Plan {
+    fn from(stored: StoredPlan) -> Self {
+        Plan {
+            id: stored.id,
+            name: stored.name,
+            map_id: stored.map_id,
+            weapon_ids: stored.weapon_ids,
+            gun_ammo: stored.gun_ammo,
+            gun_position: None,
+            target_position: None,
+            spotter_position: None,
+            gun_positions: stored.gun_positions,
+            target_positions: stored.target_positions,
+            spotter_positions: stored.spotter_positions,
+            gun_target_indices: stored.gun_target_indices,
+            gun_labels: stored.gun_labels,
+            target_labels: stored.target_labels,
+            spotter_labels: stored.spotter_labels,
+            target_statuses: stored.target_statuses,
+            wind_direction: stored.wind_direction,
+            wind_strength: stored.wind_strength,
+            version: stored.version,
+            expires_at: stored.expires_at,
+            war_number: stored.war_number,
+            calibration: stored.calibration,
+            shot_log: stored.shot_log,
+            registrations: stored.registrations,
+            range_rings: stored.range_rings,
+            intel: stored.intel,
+            weapon_data_version: stored.weapon_data_version,
+            weapon_profile: stored.weapon_profile,
+            created_at: stored.created_at,
+            updated_at: stored.updated_at,
+        }
+    }
+}
+
+/// Encode `plan` in the current format.
+pub(super) fn encode_plan(plan: &Plan) -> Result<Vec<u8>, String> {
+    postcard::to_extend(&StoredPlan::from(plan), vec![FORMAT_POSTCARD])
+        .map_err(|e| format!("Failed to encode plan: {}", e))
+}
+
+/// Decode a stored plan in any format this build knows.
+pub(super) fn decode_plan(bytes: &[u8]) -> Result<Plan, String> {
+    match bytes.split_first() {
+        Some((&FORMAT_POSTCARD, body)) => postcard::from_bytes::<StoredPlan>(body)
+            .map(Plan::from)
+            .map_err(|e| format!("Failed to decode plan: {}", e)),
+        Some((&LEGACY_JSON, _)) => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+        Some((tag, _)) => Err(format!("Unknown plan format tag {:#04x}", tag)),
+        None => Err("Empty plan record".to_string()),
+    }
+}
+
+/// Whether a stored plan is still in the legacy JSON encoding.
+pub(super) fn is_legacy_json(bytes: &[u8]) -> bool {
+    bytes.first() == Some(&LEGACY_JSON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_rejects_unknown_formats() {
+        assert!(decode_plan(&[]).is_err());
+        let err = decode_plan(&[0x7f, 0, 0]).unwrap_err();
+        assert!(err.contains("0x7f"), "{err}");
+        assert!(decode_plan(&[FORMAT_POSTCARD, 0xff]).is_err());
+        assert!(is_legacy_json(b"{}"));
+        assert!(!is_legacy_json(&[FORMAT_POSTCARD]));
+    }
+
+    #[test]
+    fn test_every_field_round_trips() {
+        let mut plan: Plan = serde_json::from_str(
+            r#"{"id":"6c1d3f1e-8b7a-4c55-9a5e-2f0d7c3b9a10","name":"Push","map_id":"deadlands",
+            "weapon_ids":["cremari-mortar"],"gun_ammo":["Shrapnel"],
+            "gun_positions":[{"x":100.0,"y":200.0}],"target_positions":[{"x":150.0,"y":260.0}],
+            "gun_target_indices":[0],"gun_labels":["North"],"target_statuses":["FIRED_UPON"],
+            "wind_direction":90.0,"wind_strength":2,"version":3,"expires_at":1800000000,
+            "war_number":117,"weapon_data_version":"2026-10-18","weapon_profile":"DEVBRANCH",
+            "created_at":"2026-01-01T00:00:00Z","updated_at":"2026-01-02T00:00:00Z"}"#,
+        )
+        .unwrap();
+        plan.shot_log = vec![ShotRecord {
+            gun: 0,
+            target: Some(0),
+            azimuth: 247.3,
+            distance: 185.0,
+            result: "30 m long".to_string(),
+        }];
+        plan.registrations = vec![RegistrationPoint {
+            gun: 0,
+            name: "TRP-1".to_string(),
+            position: Position { x: 900.0, y: 640.0 },
+            azimuth: 92.3,
+            distance: 185.0,
+        }];
+        plan.range_rings.hidden = vec![true];
+        plan.intel
+            .no_fire_zones
+            .push(Position { x: 300.0, y: 400.0 });
+        plan.intel.no_fire_radii.push(250.0);
+
+        let encoded = encode_plan(&plan).unwrap();
+        assert_eq!(encoded[0], FORMAT_POSTCARD);
+        assert_eq!(
+            serde_json::to_value(decode_plan(&encoded).unwrap()).unwrap(),
+            serde_json::to_value(&plan).unwrap()
+        );
+    }
+}
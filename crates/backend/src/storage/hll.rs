@@ -0,0 +1,120 @@
+//! HyperLogLog sketches for counting distinct planner sessions.
+//!
+//! A sketch keeps, per register, the longest run of leading zero bits seen in
+//! the hashes routed to it, and estimates how many distinct values were added
+//! from those runs. It never stores the values themselves, so the counts can
+//! be kept per war and per day without keeping any session id around. Sketches
+//! of several days merge into the sketch of the whole range.
+
+use sha2::{Digest, Sha256};
+
+/// Bits of the hash picking the register; 2^10 registers give about 3% error.
+const PRECISION: u32 = 10;
+const REGISTERS: usize = 1 << PRECISION;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub(super) fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; REGISTERS],
+        }
+    }
+
+    /// The sketch stored as `bytes`; anything else than a stored sketch reads
+    /// as an empty one.
+    pub(super) fn from_bytes(bytes: &[u8]) -> Self {
+        if bytes.len() == REGISTERS {
+            HyperLogLog {
+                registers: bytes.to_vec(),
+            }
+        } else {
+            HyperLogLog::new()
+        }
+    }
+
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        &self.registers
+    }
+
+    /// Add `value`. Returns whether the sketch changed.
+    pub(super) fn add(&mut self, value: &str) -> bool {
+        let digest = Sha256::digest(value.as_bytes());
+        let mut first = [0u8; 8];
+        first.copy_from_slice(&digest[..8]);
+        let hash = u64::from_be_bytes(first);
+
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // The marker bit caps the run at the bits left after the index
+        let rest = (hash << PRECISION) | (1 << (PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Fold `other` in, as if its values had been added to this sketch.
+    pub(super) fn merge(&mut self, other: &HyperLogLog) {
+        for (mine, theirs) in self.registers.iter_mut().zip(&other.registers) {
+            *mine = (*mine).max(*theirs);
+        }
+    }
+
+    /// Estimated number of distinct values added.
+    pub(super) fn estimate(&self) -> u64 {
+        let m = REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+        let empty = self.registers.iter().filter(|&&r| r == 0).count();
+        // Few values: count them by the registers still empty
+        let estimate = if raw <= 2.5 * m && empty > 0 {
+            m * (m / empty as f64).ln()
+        } else {
+            raw
+        };
+        estimate.round() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_within_error() {
+        assert_eq!(HyperLogLog::new().estimate(), 0);
+        for count in [1u64, 10, 500, 20_000] {
+            let mut sketch = HyperLogLog::new();
+            for i in 0..count {
+                sketch.add(&format!("session-{i}"));
+            }
+            // Adding the same values again changes nothing
+            assert!(!sketch.add("session-0"));
+            let estimate = sketch.estimate() as f64;
+            let error = (estimate - count as f64).abs() / count as f64;
+            assert!(error < 0.1, "{count} values estimated as {estimate}");
+        }
+    }
+
+    #[test]
+    fn test_merge_and_bytes() {
+        let (mut a, mut b) = (HyperLogLog::new(), HyperLogLog::new());
+        for i in 0..300 {
+            a.add(&format!("session-{i}"));
+            b.add(&format!("session-{}", i + 200));
+        }
+        a.merge(&b);
+        let estimate = a.estimate() as f64;
+        assert!((estimate - 500.0).abs() < 50.0, "estimated {estimate}");
+
+        assert_eq!(HyperLogLog::from_bytes(a.as_bytes()), a);
+        assert_eq!(HyperLogLog::from_bytes(b"garbage"), HyperLogLog::new());
+    }
+}
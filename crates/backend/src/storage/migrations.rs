@@ -0,0 +1,130 @@
+//! Versioned upgrades of the stored data, run when the database is opened.
+//!
+//! The `meta` table records which schema version the file is at. Opening it
+//! runs every migration past that version in one write transaction with the
+//! version bump, so an interrupted upgrade leaves the file as it was. To change
+//! the stored format, append a migration to [`MIGRATIONS`]; its position in the
+//! list is the version it upgrades to.
+
+use foxhole_shared::models::Plan;
+use redb::{Database, ReadableTable, TableDefinition, WriteTransaction};
+
+use super::{PLANS_TABLE, PLAN_VERSIONS_TABLE};
+
+pub(super) const META_TABLE: TableDefinition<&str, u64> = TableDefinition::new("meta");
+const SCHEMA_VERSION_KEY: &str = "schema_version";
+
+struct Migration {
+    description: &'static str,
+    /// Upgrade the data in place. Returns how many records it changed.
+    run: fn(&WriteTransaction) -> Result<u64, String>,
+}
+
+const MIGRATIONS: &[Migration] = &[Migration {
+    description: "move single gun/target/spotter positions into the position lists",
+    run: promote_single_positions,
+}];
+
+/// The schema version this build reads and writes.
+pub const SCHEMA_VERSION: u64 = MIGRATIONS.len() as u64;
+
+fn stored_version(txn: &WriteTransaction) -> Result<u64, String> {
+    let meta = txn.open_table(META_TABLE).map_err(|e| e.to_string())?;
+    let version = meta
+        .get(SCHEMA_VERSION_KEY)
+        .map_err(|e| e.to_string())?
+        .map_or(0, |v| v.value());
+    Ok(version)
+}
+
+/// The schema version recorded in `db`; `0` before any migration has run.
+pub(super) fn schema_version(db: &Database) -> Result<u64, String> {
+    let txn = db.begin_write().map_err(|e| e.to_string())?;
+    let version = stored_version(&txn)?;
+    txn.abort().map_err(|e| e.to_string())?;
+    Ok(version)
+}
+
+/// Bring `db` up to [`SCHEMA_VERSION`]. A database from a newer build is
+/// refused rather than read with the wrong layout.
+pub(super) fn run(db: &Database) -> Result<(), String> {
+    let txn = db
+        .begin_write()
+        .map_err(|e| format!("Failed to begin migration txn: {}", e))?;
+    let from = stored_version(&txn)?;
+    if from > SCHEMA_VERSION {
+        return Err(format!(
+            "Database schema version {} is newer than this build supports ({}); \
+             upgrade the server or restore an older backup",
+            from, SCHEMA_VERSION
+        ));
+    }
+    for (version, migration) in (1..).zip(MIGRATIONS).skip(from as usize) {
+        let changed = (migration.run)(&txn)
+            .map_err(|e| format!("Migration to schema version {} failed: {}", version, e))?;
+        tracing::info!(
+            version,
+            changed,
+            migration = migration.description,
+            "Applied storage migration"
+        );
+    }
+    if from < SCHEMA_VERSION {
+        let mut meta = txn.open_table(META_TABLE).map_err(|e| e.to_string())?;
+        meta.insert(SCHEMA_VERSION_KEY, SCHEMA_VERSION)
+            .map_err(|e| e.to_string())?;
+    }
+    txn.commit()
+        .map_err(|e| format!("Failed to commit migrations: {}", e))
+}
+
+/// The plan JSON rewritten without legacy single-position fields, or `None`
+/// if it has none. Rows that don't parse are left for the reader to report.
+fn without_single_positions(json: &[u8]) -> Option<Vec<u8>> {
+    let mut plan: Plan = serde_json::from_slice(json).ok()?;
+    if plan.gun_position.is_none()
+        && plan.target_position.is_none()
+        && plan.spotter_position.is_none()
+    {
+        return None;
+    }
+    plan.migrate();
+    serde_json::to_vec(&plan).ok()
+}
+
+/// Schema 1: plans saved before multi-gun support kept one `gun_position`,
+/// `target_position` and `spotter_position`. Fold them into the lists in both
+/// the current plans and their archived versions.
+fn promote_single_positions(txn: &WriteTransaction) -> Result<u64, String> {
+    let mut plans = txn.open_table(PLANS_TABLE).map_err(|e| e.to_string())?;
+    let mut upgraded = Vec::new();
+    for entry in plans.iter().map_err(|e| e.to_string())? {
+        let (key, value) = entry.map_err(|e| e.to_string())?;
+        if let Some(json) = without_single_positions(value.value()) {
+            upgraded.push((key.value().to_string(), json));
+        }
+    }
+    for (id, json) in &upgraded {
+        plans
+            .insert(id.as_str(), json.as_slice())
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut versions = txn
+        .open_table(PLAN_VERSIONS_TABLE)
+        .map_err(|e| e.to_string())?;
+    let mut upgraded_versions = Vec::new();
+    for entry in versions.iter().map_err(|e| e.to_string())? {
+        let (key, value) = entry.map_err(|e| e.to_string())?;
+        if let Some(json) = without_single_positions(value.value()) {
+            let (id, version) = key.value();
+            upgraded_versions.push((id.to_string(), version, json));
+        }
+    }
+    for (id, version, json) in &upgraded_versions {
+        versions
+            .insert((id.as_str(), *version), json.as_slice())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok((upgraded.len() + upgraded_versions.len()) as u64)
+}
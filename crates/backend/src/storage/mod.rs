@@ -1,15 +1,316 @@
 use foxhole_shared::models::Plan;
-use redb::{Database, ReadableDatabase, ReadableTable, ReadableTableMetadata, TableDefinition};
+use foxhole_shared::research::ImpactObservation;
+use foxhole_shared::slug::slug_from_bytes;
+use foxhole_shared::template::BatteryTemplate;
+use redb::{
+    Database, ReadableDatabase, ReadableTable, ReadableTableMetadata, TableDefinition,
+    WriteTransaction,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+
+use encoding::{decode_plan, encode_plan};
+use hll::HyperLogLog;
+
+mod backup;
+mod encoding;
+mod hll;
+mod migrations;
+
+pub use backup::{next_backup_in, BackupPolicy};
+pub use migrations::SCHEMA_VERSION;
 
 const PLANS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("plans");
 const GUN_PLACEMENTS_TABLE: TableDefinition<&str, u64> = TableDefinition::new("gun_placements");
 const MARKER_PLACEMENTS_TABLE: TableDefinition<&str, u64> =
     TableDefinition::new("marker_placements");
+/// The same counters split by war number; placements made while the war was
+/// unknown only appear in the lifetime tables above.
+const GUN_PLACEMENTS_BY_WAR_TABLE: TableDefinition<(u32, &str), u64> =
+    TableDefinition::new("gun_placements_by_war");
+const MARKER_PLACEMENTS_BY_WAR_TABLE: TableDefinition<(u32, &str), u64> =
+    TableDefinition::new("marker_placements_by_war");
+/// The same counters split by UTC day, keyed by days since the Unix epoch.
+/// Placements made before these tables existed only appear in the ones above.
+const GUN_PLACEMENTS_BY_DAY_TABLE: TableDefinition<(u32, &str), u64> =
+    TableDefinition::new("gun_placements_by_day");
+const MARKER_PLACEMENTS_BY_DAY_TABLE: TableDefinition<(u32, &str), u64> =
+    TableDefinition::new("marker_placements_by_day");
+/// Lifetime placements per map, keyed by (map file name, "gun" or marker
+/// kind). Placements tracked without a map only appear in the tables above.
+const PLACEMENTS_BY_MAP_TABLE: TableDefinition<(&str, &str), u64> =
+    TableDefinition::new("placements_by_map");
+/// Placements counted at most once per anonymous session, weapon or marker
+/// kind and UTC day, keyed "gun", "target" or "spotter". Placements tracked
+/// without a session id only appear in the raw counters above.
+const DEDUPED_PLACEMENTS_TABLE: TableDefinition<&str, u64> =
+    TableDefinition::new("deduped_placements");
+const DEDUPED_PLACEMENTS_BY_WAR_TABLE: TableDefinition<(u32, &str), u64> =
+    TableDefinition::new("deduped_placements_by_war");
+const DEDUPED_PLACEMENTS_BY_DAY_TABLE: TableDefinition<(u32, &str), u64> =
+    TableDefinition::new("deduped_placements_by_day");
+/// What each session placed today, keyed by (UTC day, session id, "gun" or
+/// marker kind, weapon slug or marker kind). Earlier days are dropped.
+const SESSION_PLACEMENTS_TABLE: TableDefinition<(u32, &str, &str, &str), ()> =
+    TableDefinition::new("session_placements");
+/// HyperLogLog sketches of the sessions that placed anything, keyed ("all",
+/// 0), ("war", war number) or ("day", days since the Unix epoch).
+const UNIQUE_PLANNERS_TABLE: TableDefinition<(&str, u32), &[u8]> =
+    TableDefinition::new("unique_planners");
+/// Guns and targets of created plans per grid cell, keyed by (map file
+/// name, marker kind, column, row), for the community heatmap.
+const PLACEMENT_CELLS_TABLE: TableDefinition<(&str, &str, u32, u32), u64> =
+    TableDefinition::new("placement_cells");
+const PLAN_LOCKS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("plan_locks");
+const CLEANUP_STATS_TABLE: TableDefinition<&str, u64> = TableDefinition::new("cleanup_stats");
+/// SHA-256 of each plan's edit token. Plans saved before tokens existed have no entry.
+const EDIT_TOKENS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("plan_edit_tokens");
+/// Earlier states of each plan keyed by (plan id, version). The current state
+/// stays in `plans`; every update moves the one it replaces in here.
+const PLAN_VERSIONS_TABLE: TableDefinition<(&str, u32), &[u8]> =
+    TableDefinition::new("plan_versions");
+/// Latest in-game wind observation per map, keyed by map file name.
+const WIND_REPORTS_TABLE: TableDefinition<&str, &[u8]> = TableDefinition::new("wind_reports");
+/// Battery templates shared by id, stored as JSON.
+const BATTERY_TEMPLATES_TABLE: TableDefinition<&str, &[u8]> =
+    TableDefinition::new("battery_templates");
+/// Plan id behind each short link slug, and each plan's slug. Plans made
+/// before slugs existed have none.
+const PLAN_SLUGS_TABLE: TableDefinition<&str, &str> = TableDefinition::new("plan_slugs");
+const SLUG_BY_PLAN_TABLE: TableDefinition<&str, &str> = TableDefinition::new("plan_slug_by_plan");
+/// Observed impacts submitted for accuracy research, keyed by (weapon slug,
+/// random id) and stored as JSON.
+const IMPACT_OBSERVATIONS_TABLE: TableDefinition<(&str, &str), &[u8]> =
+    TableDefinition::new("impact_observations");
+/// Wars whose end-of-war report was posted, with when (Unix seconds).
+const WAR_REPORTS_TABLE: TableDefinition<u32, i64> = TableDefinition::new("war_reports");
+
+/// Earlier versions kept per plan; older ones are dropped as new ones arrive.
+pub const MAX_PLAN_VERSIONS: usize = 50;
+
+/// Random slugs tried for a new plan before it's left with its id alone.
+const SLUG_ATTEMPTS: usize = 16;
+
+#[cfg(unix)]
+fn free_disk_bytes(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    // An empty parent means the working directory
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let c_path = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stat` is only read after statvfs succeeds
+    let stat = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stat.assume_init()
+    };
+    #[allow(clippy::unnecessary_cast)] // field widths differ between platforms
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+fn free_disk_bytes(_dir: &Path) -> Option<u64> {
+    None
+}
+
+fn hash_edit_token(token: &str) -> [u8; 32] {
+    Sha256::digest(token.as_bytes()).into()
+}
+
+/// Which placements a count covers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlacementScope {
+    /// Every placement ever counted.
+    All,
+    /// Placements made during one war.
+    War(u32),
+    /// Placements made from day `from` to day `to`, both included, in days
+    /// since the Unix epoch (UTC).
+    Days { from: u32, to: u32 },
+}
+
+/// The UTC day `now` (Unix seconds) falls on, in days since the Unix epoch.
+pub fn day_of(now: i64) -> u32 {
+    now.div_euclid(86_400).clamp(0, u32::MAX as i64) as u32
+}
+
+/// A family of placement counters: all-time, per war and per UTC day.
+#[derive(Clone, Copy)]
+struct PlacementTables {
+    lifetime: TableDefinition<'static, &'static str, u64>,
+    by_war: TableDefinition<'static, (u32, &'static str), u64>,
+    by_day: TableDefinition<'static, (u32, &'static str), u64>,
+}
+
+const GUN_PLACEMENTS: PlacementTables = PlacementTables {
+    lifetime: GUN_PLACEMENTS_TABLE,
+    by_war: GUN_PLACEMENTS_BY_WAR_TABLE,
+    by_day: GUN_PLACEMENTS_BY_DAY_TABLE,
+};
+const MARKER_PLACEMENTS: PlacementTables = PlacementTables {
+    lifetime: MARKER_PLACEMENTS_TABLE,
+    by_war: MARKER_PLACEMENTS_BY_WAR_TABLE,
+    by_day: MARKER_PLACEMENTS_BY_DAY_TABLE,
+};
+const DEDUPED_PLACEMENTS: PlacementTables = PlacementTables {
+    lifetime: DEDUPED_PLACEMENTS_TABLE,
+    by_war: DEDUPED_PLACEMENTS_BY_WAR_TABLE,
+    by_day: DEDUPED_PLACEMENTS_BY_DAY_TABLE,
+};
+
+/// Add `session` to the unique planner sketches of all time, of `day` and,
+/// when the war is known, of that war.
+fn add_planner(
+    write_txn: &WriteTransaction,
+    session: &str,
+    war: Option<u32>,
+    day: u32,
+) -> Result<(), String> {
+    let mut table = write_txn
+        .open_table(UNIQUE_PLANNERS_TABLE)
+        .map_err(|e| e.to_string())?;
+    let mut keys = vec![("all", 0), ("day", day)];
+    if let Some(war) = war {
+        keys.push(("war", war));
+    }
+    for key in keys {
+        let mut sketch = match table.get(key).map_err(|e| e.to_string())? {
+            Some(bytes) => HyperLogLog::from_bytes(bytes.value()),
+            None => HyperLogLog::new(),
+        };
+        // Most placements come from sessions already counted
+        if sketch.add(session) {
+            table
+                .insert(key, sketch.as_bytes())
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Add one to `key`'s lifetime counter in `tables`, to its counter for the
+/// day of `now` and, when the war is known, to its counter for that war.
+fn add_placement(
+    write_txn: &WriteTransaction,
+    tables: PlacementTables,
+    key: &str,
+    war: Option<u32>,
+    now: i64,
+) -> Result<(), String> {
+    let mut table = write_txn
+        .open_table(tables.lifetime)
+        .map_err(|e| e.to_string())?;
+    let current = table
+        .get(key)
+        .map_err(|e| e.to_string())?
+        .map(|v| v.value())
+        .unwrap_or(0);
+    table.insert(key, current + 1).map_err(|e| e.to_string())?;
+    let mut buckets = vec![(tables.by_day, day_of(now))];
+    if let Some(war) = war {
+        buckets.push((tables.by_war, war));
+    }
+    for (definition, bucket) in buckets {
+        let mut table = write_txn
+            .open_table(definition)
+            .map_err(|e| e.to_string())?;
+        let current = table
+            .get((bucket, key))
+            .map_err(|e| e.to_string())?
+            .map(|v| v.value())
+            .unwrap_or(0);
+        table
+            .insert((bucket, key), current + 1)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// How long plans are kept after their last save.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetentionPolicy {
+    /// Seconds a plan lives after being saved; `None` keeps plans forever.
+    pub plan_ttl_secs: Option<i64>,
+}
+
+impl RetentionPolicy {
+    /// Expiry timestamp for a plan saved at `now`.
+    pub fn expires_at(&self, now: i64) -> Option<i64> {
+        self.plan_ttl_secs.map(|ttl| now + ttl)
+    }
+
+    /// Whether `plan` is past its expiry. Plans without an explicit expiry
+    /// (saved before retention existed) age from `updated_at`.
+    fn is_expired(&self, plan: &Plan, now: i64) -> bool {
+        let Some(ttl) = self.plan_ttl_secs else {
+            return false;
+        };
+        let expires_at = plan.expires_at.or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(&plan.updated_at)
+                .ok()
+                .map(|t| t.timestamp() + ttl)
+        });
+        expires_at.is_some_and(|t| t <= now)
+    }
+}
+
+/// Running totals of the expired-plan cleanup task.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CleanupStats {
+    pub plans_expired: u64,
+    pub bytes_reclaimed: u64,
+    /// Unix timestamp (seconds) of the last cleanup run, if any.
+    pub last_run_at: Option<i64>,
+}
+
+/// Outcome of a single cleanup run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CleanupReport {
+    pub plans_deleted: u64,
+    pub bytes_reclaimed: u64,
+}
+
+/// Advisory edit lock on a plan. Expires unless the holder keeps refreshing it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanLock {
+    /// Opaque per-tab session id of the editor holding the lock.
+    pub holder: String,
+    /// Unix timestamp (seconds) after which the lock is free again.
+    pub expires_at: i64,
+}
+
+/// Wind someone observed in-game on a map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindReport {
+    /// Bearing in degrees the wind blows towards.
+    pub direction: f64,
+    /// Wind strength level, 0 to 5.
+    pub strength: u8,
+    /// Unix timestamp (seconds) of the observation.
+    pub reported_at: i64,
+}
+
+/// Result of a versioned plan update.
+#[derive(Debug)]
+pub enum UpdateOutcome {
+    /// Stored; carries the plan as saved (version bumped).
+    Saved(Plan),
+    /// Someone else saved since `base_version` — carries their stored plan, untouched.
+    Conflict(Plan),
+    NotFound,
+}
 
 pub struct Storage {
-    db: Database,
+    // Compaction needs exclusive access; everything else shares it
+    db: RwLock<Database>,
     path: PathBuf,
 }
 
@@ -26,24 +327,148 @@ impl Storage {
             let _ = write_txn.open_table(PLANS_TABLE);
             let _ = write_txn.open_table(GUN_PLACEMENTS_TABLE);
             let _ = write_txn.open_table(MARKER_PLACEMENTS_TABLE);
+            let _ = write_txn.open_table(GUN_PLACEMENTS_BY_WAR_TABLE);
+            let _ = write_txn.open_table(MARKER_PLACEMENTS_BY_WAR_TABLE);
+            let _ = write_txn.open_table(GUN_PLACEMENTS_BY_DAY_TABLE);
+            let _ = write_txn.open_table(MARKER_PLACEMENTS_BY_DAY_TABLE);
+            let _ = write_txn.open_table(PLACEMENTS_BY_MAP_TABLE);
+            let _ = write_txn.open_table(DEDUPED_PLACEMENTS_TABLE);
+            let _ = write_txn.open_table(DEDUPED_PLACEMENTS_BY_WAR_TABLE);
+            let _ = write_txn.open_table(DEDUPED_PLACEMENTS_BY_DAY_TABLE);
+            let _ = write_txn.open_table(SESSION_PLACEMENTS_TABLE);
+            let _ = write_txn.open_table(UNIQUE_PLANNERS_TABLE);
+            let _ = write_txn.open_table(PLACEMENT_CELLS_TABLE);
+            let _ = write_txn.open_table(PLAN_LOCKS_TABLE);
+            let _ = write_txn.open_table(CLEANUP_STATS_TABLE);
+            let _ = write_txn.open_table(EDIT_TOKENS_TABLE);
+            let _ = write_txn.open_table(PLAN_VERSIONS_TABLE);
+            let _ = write_txn.open_table(WIND_REPORTS_TABLE);
+            let _ = write_txn.open_table(BATTERY_TEMPLATES_TABLE);
+            let _ = write_txn.open_table(PLAN_SLUGS_TABLE);
+            let _ = write_txn.open_table(SLUG_BY_PLAN_TABLE);
+            let _ = write_txn.open_table(IMPACT_OBSERVATIONS_TABLE);
+            let _ = write_txn.open_table(WAR_REPORTS_TABLE);
         }
         write_txn
             .commit()
             .map_err(|e| format!("Failed to commit initial txn: {}", e))?;
+        migrations::run(&db)?;
 
-        tracing::info!(path = %path.display(), "Database opened");
+        tracing::info!(
+            path = %path.display(),
+            schema_version = SCHEMA_VERSION,
+            "Database opened"
+        );
 
         Ok(Arc::new(Storage {
-            db,
+            db: RwLock::new(db),
             path: path.to_path_buf(),
         }))
     }
 
+    /// The schema version the database is at; [`SCHEMA_VERSION`] once opened.
+    pub fn schema_version(&self) -> Result<u64, String> {
+        migrations::schema_version(&self.db())
+    }
+
+    fn db(&self) -> RwLockReadGuard<'_, Database> {
+        self.db.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Keep `plan` as an earlier version of `plan_id`, dropping the oldest
+    /// versions past [`MAX_PLAN_VERSIONS`].
+    fn archive_version(
+        txn: &redb::WriteTransaction,
+        plan_id: &str,
+        plan: &Plan,
+    ) -> Result<(), String> {
+        let encoded = encode_plan(plan)?;
+        let mut versions = txn
+            .open_table(PLAN_VERSIONS_TABLE)
+            .map_err(|e| e.to_string())?;
+        versions
+            .insert((plan_id, plan.version), encoded.as_slice())
+            .map_err(|e| e.to_string())?;
+        let kept: Vec<u32> = versions
+            .range((plan_id, 0)..=(plan_id, u32::MAX))
+            .map_err(|e| e.to_string())?
+            .map(|entry| entry.map(|(key, _)| key.value().1))
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        for old in kept.iter().take(kept.len().saturating_sub(MAX_PLAN_VERSIONS)) {
+            versions
+                .remove((plan_id, *old))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Drop every archived version of `plan_id`.
+    fn remove_versions(txn: &redb::WriteTransaction, plan_id: &str) -> Result<(), String> {
+        let mut versions = txn
+            .open_table(PLAN_VERSIONS_TABLE)
+            .map_err(|e| e.to_string())?;
+        versions
+            .retain_in((plan_id, 0)..=(plan_id, u32::MAX), |_, _| false)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Give a new plan a free short link slug. After [`SLUG_ATTEMPTS`] taken
+    /// ones it goes without; the plan is still reachable by its id.
+    fn assign_slug(txn: &redb::WriteTransaction, plan_id: &str) -> Result<(), String> {
+        let mut slugs = txn
+            .open_table(PLAN_SLUGS_TABLE)
+            .map_err(|e| e.to_string())?;
+        for _ in 0..SLUG_ATTEMPTS {
+            let random = uuid::Uuid::new_v4().into_bytes();
+            let slug = slug_from_bytes([random[0], random[1], random[2], random[3]]);
+            if slugs
+                .get(slug.as_str())
+                .map_err(|e| e.to_string())?
+                .is_some()
+            {
+                continue;
+            }
+            slugs
+                .insert(slug.as_str(), plan_id)
+                .map_err(|e| e.to_string())?;
+            let mut by_plan = txn
+                .open_table(SLUG_BY_PLAN_TABLE)
+                .map_err(|e| e.to_string())?;
+            by_plan
+                .insert(plan_id, slug.as_str())
+                .map_err(|e| e.to_string())?;
+            return Ok(());
+        }
+        tracing::warn!(plan_id, "No free slug for new plan");
+        Ok(())
+    }
+
+    /// Free the slug of `plan_id`, if it has one.
+    fn remove_slug(txn: &redb::WriteTransaction, plan_id: &str) -> Result<(), String> {
+        let mut by_plan = txn
+            .open_table(SLUG_BY_PLAN_TABLE)
+            .map_err(|e| e.to_string())?;
+        let slug = by_plan
+            .remove(plan_id)
+            .map_err(|e| e.to_string())?
+            .map(|s| s.value().to_string());
+        if let Some(slug) = slug {
+            let mut slugs = txn
+                .open_table(PLAN_SLUGS_TABLE)
+                .map_err(|e| e.to_string())?;
+            slugs.remove(slug.as_str()).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Save a plan without an edit token, the way plans were stored before tokens existed.
+    #[cfg(test)]
     pub fn save_plan(&self, plan: &Plan) -> Result<(), String> {
         let json = serde_json::to_vec(plan).map_err(|e| e.to_string())?;
         let id_str = plan.id.to_string();
 
-        let write_txn = self.db.begin_write().map_err(|e| e.to_string())?;
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
         {
             let mut table = write_txn
                 .open_table(PLANS_TABLE)
@@ -56,189 +481,1010 @@ impl Storage {
         Ok(())
     }
 
+    /// Save a new plan together with the hash of its edit token, and give it
+    /// a short link slug.
+    pub fn create_plan(&self, plan: &Plan, edit_token: &str) -> Result<(), String> {
+        let encoded = encode_plan(plan)?;
+        let id_str = plan.id.to_string();
+        let hash = hash_edit_token(edit_token);
+
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        {
+            let mut plans = write_txn
+                .open_table(PLANS_TABLE)
+                .map_err(|e| e.to_string())?;
+            plans
+                .insert(id_str.as_str(), encoded.as_slice())
+                .map_err(|e| e.to_string())?;
+            let mut tokens = write_txn
+                .open_table(EDIT_TOKENS_TABLE)
+                .map_err(|e| e.to_string())?;
+            tokens
+                .insert(id_str.as_str(), hash.as_slice())
+                .map_err(|e| e.to_string())?;
+            Self::assign_slug(&write_txn, &id_str)?;
+        }
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Check `token` against a plan's stored edit token.
+    ///
+    /// `None` if the plan has no token (saved before tokens existed), otherwise
+    /// whether it matches. A missing `token` never matches.
+    pub fn edit_token_matches(
+        &self,
+        plan_id: &str,
+        token: Option<&str>,
+    ) -> Result<Option<bool>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(EDIT_TOKENS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let stored = table.get(plan_id).map_err(|e| e.to_string())?;
+        Ok(stored.map(|hash| token.is_some_and(|t| hash.value() == hash_edit_token(t).as_slice())))
+    }
+
+    /// Delete a plan with its edit token, lock and slug. Returns whether the plan existed.
+    pub fn delete_plan(&self, plan_id: &str) -> Result<bool, String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        let existed = {
+            let mut plans = write_txn
+                .open_table(PLANS_TABLE)
+                .map_err(|e| e.to_string())?;
+            let existed = plans.remove(plan_id).map_err(|e| e.to_string())?.is_some();
+            let mut tokens = write_txn
+                .open_table(EDIT_TOKENS_TABLE)
+                .map_err(|e| e.to_string())?;
+            tokens.remove(plan_id).map_err(|e| e.to_string())?;
+            let mut locks = write_txn
+                .open_table(PLAN_LOCKS_TABLE)
+                .map_err(|e| e.to_string())?;
+            locks.remove(plan_id).map_err(|e| e.to_string())?;
+            Self::remove_versions(&write_txn, plan_id)?;
+            Self::remove_slug(&write_txn, plan_id)?;
+            existed
+        };
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(existed)
+    }
+
     pub fn get_plan(&self, id: &str) -> Result<Option<Plan>, String> {
-        let read_txn = self.db.begin_read().map_err(|e| e.to_string())?;
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
         let table = read_txn
             .open_table(PLANS_TABLE)
             .map_err(|e| e.to_string())?;
 
         match table.get(id).map_err(|e| e.to_string())? {
-            Some(value) => {
-                let mut plan: Plan =
-                    serde_json::from_slice(value.value()).map_err(|e| e.to_string())?;
-                plan.migrate();
-                Ok(Some(plan))
+            Some(value) => Ok(Some(decode_plan(value.value())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// A plan by its id or its short link slug.
+    pub fn find_plan(&self, id_or_slug: &str) -> Result<Option<Plan>, String> {
+        if let Some(plan) = self.get_plan(id_or_slug)? {
+            return Ok(Some(plan));
+        }
+        match self.plan_id_for_slug(id_or_slug)? {
+            Some(id) => self.get_plan(&id),
+            None => Ok(None),
+        }
+    }
+
+    /// The plan a short link slug points to.
+    pub fn plan_id_for_slug(&self, slug: &str) -> Result<Option<String>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(PLAN_SLUGS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let id = table.get(slug).map_err(|e| e.to_string())?;
+        Ok(id.map(|id| id.value().to_string()))
+    }
+
+    /// A plan's short link slug, if it has one.
+    pub fn plan_slug(&self, plan_id: &str) -> Result<Option<String>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(SLUG_BY_PLAN_TABLE)
+            .map_err(|e| e.to_string())?;
+        let slug = table.get(plan_id).map_err(|e| e.to_string())?;
+        Ok(slug.map(|s| s.value().to_string()))
+    }
+
+    /// Overwrite a plan only if the stored version still equals `base_version`.
+    ///
+    /// The check and write happen in one transaction, so two concurrent saves
+    /// against the same version can't both win.
+    pub fn update_plan(&self, plan: &Plan, base_version: u32) -> Result<UpdateOutcome, String> {
+        let id_str = plan.id.to_string();
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        let outcome = {
+            let mut table = write_txn
+                .open_table(PLANS_TABLE)
+                .map_err(|e| e.to_string())?;
+            let stored = match table.get(id_str.as_str()).map_err(|e| e.to_string())? {
+                Some(value) => Some(decode_plan(value.value())?),
+                None => None,
+            };
+            match stored {
+                None => UpdateOutcome::NotFound,
+                Some(stored) if stored.version != base_version => UpdateOutcome::Conflict(stored),
+                Some(stored) => {
+                    Self::archive_version(&write_txn, &id_str, &stored)?;
+                    let mut saved = plan.clone();
+                    saved.version = base_version + 1;
+                    saved.created_at = stored.created_at;
+                    saved.war_number = stored.war_number;
+                    let encoded = encode_plan(&saved)?;
+                    table
+                        .insert(id_str.as_str(), encoded.as_slice())
+                        .map_err(|e| e.to_string())?;
+                    UpdateOutcome::Saved(saved)
+                }
             }
+        };
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(outcome)
+    }
+
+    /// A plan's earlier versions, newest first. The current version isn't included.
+    pub fn plan_versions(&self, id: &str) -> Result<Vec<Plan>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(PLAN_VERSIONS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let mut versions = Vec::new();
+        for entry in table
+            .range((id, 0)..=(id, u32::MAX))
+            .map_err(|e| e.to_string())?
+            .rev()
+        {
+            let (_, value) = entry.map_err(|e| e.to_string())?;
+            versions.push(decode_plan(value.value())?);
+        }
+        Ok(versions)
+    }
+
+    /// One earlier version of a plan, if it's still kept.
+    pub fn get_plan_version(&self, id: &str, version: u32) -> Result<Option<Plan>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(PLAN_VERSIONS_TABLE)
+            .map_err(|e| e.to_string())?;
+        match table.get((id, version)).map_err(|e| e.to_string())? {
+            Some(value) => Ok(Some(decode_plan(value.value())?)),
             None => Ok(None),
         }
     }
 
     pub fn count_plans(&self) -> Result<u64, String> {
-        let read_txn = self.db.begin_read().map_err(|e| e.to_string())?;
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
         let table = read_txn
             .open_table(PLANS_TABLE)
             .map_err(|e| e.to_string())?;
         table.len().map_err(|e| e.to_string())
     }
 
+    /// Plans whose current version is still stored as JSON. Each switches to the
+    /// binary encoding the next time it's saved.
+    pub fn count_legacy_encoded_plans(&self) -> Result<u64, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(PLANS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let mut count = 0;
+        for entry in table.iter().map_err(|e| e.to_string())? {
+            let (_, value) = entry.map_err(|e| e.to_string())?;
+            if encoding::is_legacy_json(value.value()) {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// A page of stored plans in id order. Rows that fail to parse are skipped.
+    pub fn list_plans(&self, offset: usize, limit: usize) -> Result<Vec<Plan>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(PLANS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let mut plans = Vec::new();
+        for entry in table
+            .iter()
+            .map_err(|e| e.to_string())?
+            .skip(offset)
+            .take(limit)
+        {
+            let (key, value) = entry.map_err(|e| e.to_string())?;
+            match decode_plan(value.value()) {
+                Ok(plan) => plans.push(plan),
+                Err(e) => {
+                    tracing::warn!(plan_id = key.value(), error = %e, "Skipping unreadable plan")
+                }
+            }
+        }
+        Ok(plans)
+    }
+
     pub fn db_size_bytes(&self) -> Result<u64, String> {
         std::fs::metadata(&self.path)
             .map(|m| m.len())
             .map_err(|e| e.to_string())
     }
 
-    pub fn increment_gun_placement(&self, weapon_slug: &str) -> Result<(), String> {
-        let write_txn = self.db.begin_write().map_err(|e| e.to_string())?;
-        {
+    /// Check the database still accepts writes by committing an empty write transaction.
+    pub fn check_writable(&self) -> Result<(), String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        write_txn.commit().map_err(|e| e.to_string())
+    }
+
+    /// Bytes available to this process on the filesystem holding the database,
+    /// or `None` where the platform doesn't report it.
+    pub fn free_disk_bytes(&self) -> Option<u64> {
+        free_disk_bytes(self.path.parent().unwrap_or(Path::new(".")))
+    }
+
+    /// Count one placement of `key` — a weapon slug or marker kind, counted
+    /// as `counter` ("gun" or the marker kind) per map and per session — at
+    /// `now` (Unix seconds) in `tables`. The per-map count needs `map_id`;
+    /// the deduped count needs a `session` that hasn't placed `key` today.
+    #[allow(clippy::too_many_arguments)]
+    fn increment_placement(
+        &self,
+        tables: PlacementTables,
+        counter: &str,
+        key: &str,
+        war: Option<u32>,
+        map_id: Option<&str>,
+        session: Option<&str>,
+        now: i64,
+    ) -> Result<(), String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        add_placement(&write_txn, tables, key, war, now)?;
+        if let Some(map_id) = map_id {
             let mut table = write_txn
-                .open_table(GUN_PLACEMENTS_TABLE)
+                .open_table(PLACEMENTS_BY_MAP_TABLE)
                 .map_err(|e| e.to_string())?;
             let current = table
-                .get(weapon_slug)
+                .get((map_id, counter))
                 .map_err(|e| e.to_string())?
                 .map(|v| v.value())
                 .unwrap_or(0);
             table
-                .insert(weapon_slug, current + 1)
+                .insert((map_id, counter), current + 1)
                 .map_err(|e| e.to_string())?;
         }
+        if let Some(session) = session {
+            let today = day_of(now);
+            let first_today = {
+                let mut seen = write_txn
+                    .open_table(SESSION_PLACEMENTS_TABLE)
+                    .map_err(|e| e.to_string())?;
+                seen.retain_in(..(today, "", "", ""), |_, _| false)
+                    .map_err(|e| e.to_string())?;
+                let first = seen
+                    .insert((today, session, counter, key), ())
+                    .map_err(|e| e.to_string())?
+                    .is_none();
+                first
+            };
+            if first_today {
+                add_placement(&write_txn, DEDUPED_PLACEMENTS, counter, war, now)?;
+            }
+            add_planner(&write_txn, session, war, today)?;
+        }
         write_txn.commit().map_err(|e| e.to_string())?;
         Ok(())
     }
 
-    pub fn increment_marker_placement(&self, kind: &str) -> Result<(), String> {
-        let write_txn = self.db.begin_write().map_err(|e| e.to_string())?;
-        {
-            let mut table = write_txn
-                .open_table(MARKER_PLACEMENTS_TABLE)
-                .map_err(|e| e.to_string())?;
-            let current = table
-                .get(kind)
-                .map_err(|e| e.to_string())?
-                .map(|v| v.value())
-                .unwrap_or(0);
-            table.insert(kind, current + 1).map_err(|e| e.to_string())?;
-        }
-        write_txn.commit().map_err(|e| e.to_string())?;
-        Ok(())
+    /// Count a gun placement on `map_id` by `session` at `now` (Unix seconds).
+    pub fn increment_gun_placement(
+        &self,
+        weapon_slug: &str,
+        war: Option<u32>,
+        map_id: Option<&str>,
+        session: Option<&str>,
+        now: i64,
+    ) -> Result<(), String> {
+        self.increment_placement(
+            GUN_PLACEMENTS,
+            "gun",
+            weapon_slug,
+            war,
+            map_id,
+            session,
+            now,
+        )
     }
 
-    pub fn get_marker_placement_count(&self, kind: &str) -> Result<u64, String> {
-        let read_txn = self.db.begin_read().map_err(|e| e.to_string())?;
-        let table = read_txn
-            .open_table(MARKER_PLACEMENTS_TABLE)
-            .map_err(|e| e.to_string())?;
-        Ok(table
-            .get(kind)
-            .map_err(|e| e.to_string())?
-            .map(|v| v.value())
-            .unwrap_or(0))
+    /// Count a marker placement on `map_id` by `session` at `now` (Unix seconds).
+    pub fn increment_marker_placement(
+        &self,
+        kind: &str,
+        war: Option<u32>,
+        map_id: Option<&str>,
+        session: Option<&str>,
+        now: i64,
+    ) -> Result<(), String> {
+        self.increment_placement(MARKER_PLACEMENTS, kind, kind, war, map_id, session, now)
     }
 
-    pub fn get_gun_placement_counts(&self) -> Result<Vec<(String, u64)>, String> {
-        let read_txn = self.db.begin_read().map_err(|e| e.to_string())?;
+    /// Lifetime placements per map, as (map file name, "gun" or marker kind,
+    /// count) in map order.
+    pub fn placements_by_map(&self) -> Result<Vec<(String, String, u64)>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
         let table = read_txn
-            .open_table(GUN_PLACEMENTS_TABLE)
+            .open_table(PLACEMENTS_BY_MAP_TABLE)
             .map_err(|e| e.to_string())?;
-        let mut result = Vec::new();
+        let mut counts = Vec::new();
         for entry in table.iter().map_err(|e| e.to_string())? {
             let (key, value) = entry.map_err(|e| e.to_string())?;
-            result.push((key.value().to_string(), value.value()));
+            let (map_id, counter) = key.value();
+            counts.push((map_id.to_string(), counter.to_string(), value.value()));
         }
-        Ok(result)
+        Ok(counts)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    fn temp_storage() -> (Arc<Storage>, tempfile::TempDir) {
-        let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("test.redb");
-        let storage = Storage::open(&path).unwrap();
-        (storage, dir)
+    /// Counters per key within `scope`. `lifetime` holds the all-time counters;
+    /// `by_war` and `by_day` the same split by war and by day.
+    fn placement_counts(
+        &self,
+        tables: PlacementTables,
+        scope: PlacementScope,
+    ) -> Result<Vec<(String, u64)>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let (definition, from, to) = match scope {
+            PlacementScope::All => {
+                let table = read_txn
+                    .open_table(tables.lifetime)
+                    .map_err(|e| e.to_string())?;
+                let mut result = Vec::new();
+                for entry in table.iter().map_err(|e| e.to_string())? {
+                    let (key, value) = entry.map_err(|e| e.to_string())?;
+                    result.push((key.value().to_string(), value.value()));
+                }
+                return Ok(result);
+            }
+            PlacementScope::War(war) => (tables.by_war, war, war),
+            PlacementScope::Days { from, to } if from > to => return Ok(Vec::new()),
+            PlacementScope::Days { from, to } => (tables.by_day, from, to),
+        };
+        let table = read_txn.open_table(definition).map_err(|e| e.to_string())?;
+        // Sum each key over the buckets in range
+        let mut totals = std::collections::BTreeMap::<String, u64>::new();
+        for entry in table
+            .range((from, "")..(to.saturating_add(1), ""))
+            .map_err(|e| e.to_string())?
+        {
+            let (key, value) = entry.map_err(|e| e.to_string())?;
+            *totals.entry(key.value().1.to_string()).or_default() += value.value();
+        }
+        Ok(totals.into_iter().collect())
     }
 
-    #[test]
-    fn test_increment_gun_placement_new_slug() {
-        let (storage, _dir) = temp_storage();
-        storage.increment_gun_placement("mortar").unwrap();
-        let counts = storage.get_gun_placement_counts().unwrap();
-        assert_eq!(counts, vec![("mortar".to_string(), 1)]);
+    /// Placements of marker `kind` within `scope`.
+    pub fn get_marker_placement_count(
+        &self,
+        kind: &str,
+        scope: PlacementScope,
+    ) -> Result<u64, String> {
+        let counts = self.placement_counts(MARKER_PLACEMENTS, scope)?;
+        Ok(counts
+            .into_iter()
+            .find(|(k, _)| k == kind)
+            .map_or(0, |(_, count)| count))
     }
 
-    #[test]
-    fn test_increment_gun_placement_accumulates() {
-        let (storage, _dir) = temp_storage();
-        for _ in 0..3 {
-            storage.increment_gun_placement("storm-cannon").unwrap();
-        }
-        let counts = storage.get_gun_placement_counts().unwrap();
-        assert_eq!(counts, vec![("storm-cannon".to_string(), 3)]);
+    /// Gun placements per weapon slug within `scope`.
+    pub fn get_gun_placement_counts(
+        &self,
+        scope: PlacementScope,
+    ) -> Result<Vec<(String, u64)>, String> {
+        self.placement_counts(GUN_PLACEMENTS, scope)
     }
 
-    #[test]
-    fn test_increment_multiple_slugs() {
-        let (storage, _dir) = temp_storage();
-        storage.increment_gun_placement("mortar").unwrap();
-        storage.increment_gun_placement("mortar").unwrap();
-        storage.increment_gun_placement("storm-cannon").unwrap();
-        let mut counts = storage.get_gun_placement_counts().unwrap();
-        counts.sort_by(|a, b| a.0.cmp(&b.0));
-        assert_eq!(
-            counts,
-            vec![("mortar".to_string(), 2), ("storm-cannon".to_string(), 1),]
-        );
+    /// Deduped placements within `scope`, keyed "gun", "target" or "spotter".
+    pub fn get_deduped_placement_counts(
+        &self,
+        scope: PlacementScope,
+    ) -> Result<Vec<(String, u64)>, String> {
+        self.placement_counts(DEDUPED_PLACEMENTS, scope)
     }
 
-    #[test]
-    fn test_get_gun_placement_counts_empty() {
-        let (storage, _dir) = temp_storage();
-        let counts = storage.get_gun_placement_counts().unwrap();
-        assert!(counts.is_empty());
+    /// Estimated number of distinct sessions that placed anything within
+    /// `scope`. Placements tracked without a session id aren't counted.
+    pub fn unique_planners(&self, scope: PlacementScope) -> Result<u64, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(UNIQUE_PLANNERS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let (kind, from, to) = match scope {
+            PlacementScope::All => ("all", 0, 0),
+            PlacementScope::War(war) => ("war", war, war),
+            PlacementScope::Days { from, to } => ("day", from, to),
+        };
+        // A range of days is the union of each day's sessions
+        let mut sketch = HyperLogLog::new();
+        if from <= to {
+            for entry in table
+                .range((kind, from)..=(kind, to))
+                .map_err(|e| e.to_string())?
+            {
+                let (_, bytes) = entry.map_err(|e| e.to_string())?;
+                sketch.merge(&HyperLogLog::from_bytes(bytes.value()));
+            }
+        }
+        Ok(sketch.estimate())
     }
 
-    #[test]
-    fn test_increment_marker_placement_new_kind() {
-        let (storage, _dir) = temp_storage();
-        storage.increment_marker_placement("target").unwrap();
-        assert_eq!(storage.get_marker_placement_count("target").unwrap(), 1);
+    /// Wars with any placements counted, oldest first.
+    pub fn placement_wars(&self) -> Result<Vec<u32>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let mut wars = Vec::new();
+        for definition in [GUN_PLACEMENTS_BY_WAR_TABLE, MARKER_PLACEMENTS_BY_WAR_TABLE] {
+            let table = read_txn.open_table(definition).map_err(|e| e.to_string())?;
+            for entry in table.iter().map_err(|e| e.to_string())? {
+                let (key, _) = entry.map_err(|e| e.to_string())?;
+                wars.push(key.value().0);
+            }
+        }
+        wars.sort_unstable();
+        wars.dedup();
+        Ok(wars)
     }
 
-    #[test]
-    fn test_increment_marker_placement_accumulates() {
-        let (storage, _dir) = temp_storage();
-        for _ in 0..3 {
-            storage.increment_marker_placement("spotter").unwrap();
+    /// Add one placement of marker `kind` on `map_id` for each of `cells`
+    /// (column, row); a cell listed twice counts twice.
+    pub fn add_placement_cells(
+        &self,
+        map_id: &str,
+        kind: &str,
+        cells: &[(usize, usize)],
+    ) -> Result<(), String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        {
+            let mut table = write_txn
+                .open_table(PLACEMENT_CELLS_TABLE)
+                .map_err(|e| e.to_string())?;
+            for &(col, row) in cells {
+                let key = (map_id, kind, col as u32, row as u32);
+                let current = table
+                    .get(key)
+                    .map_err(|e| e.to_string())?
+                    .map(|v| v.value())
+                    .unwrap_or(0);
+                table.insert(key, current + 1).map_err(|e| e.to_string())?;
+            }
         }
-        assert_eq!(storage.get_marker_placement_count("spotter").unwrap(), 3);
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(())
     }
 
-    #[test]
-    fn test_get_marker_placement_count_absent() {
-        let (storage, _dir) = temp_storage();
-        assert_eq!(storage.get_marker_placement_count("target").unwrap(), 0);
+    /// Placements of marker `kind` on `map_id` per grid cell, as (column,
+    /// row, count) in column-then-row order.
+    pub fn placement_heatmap(
+        &self,
+        map_id: &str,
+        kind: &str,
+    ) -> Result<Vec<(u32, u32, u64)>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(PLACEMENT_CELLS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let mut cells = Vec::new();
+        for entry in table
+            .range((map_id, kind, 0, 0)..=(map_id, kind, u32::MAX, u32::MAX))
+            .map_err(|e| e.to_string())?
+        {
+            let (key, value) = entry.map_err(|e| e.to_string())?;
+            let (_, _, col, row) = key.value();
+            cells.push((col, row, value.value()));
+        }
+        Ok(cells)
     }
 
-    fn test_plan(id: uuid::Uuid, name: &str) -> Plan {
-        use foxhole_shared::models::Position;
-        Plan {
-            id,
-            name: name.to_string(),
-            map_id: "test-map".to_string(),
+    /// Zero the gun and marker placement counters, lifetime, per war, per day
+    /// and per map, raw and deduped, and the unique planner estimates.
+    /// Returns how many lifetime counters were cleared.
+    pub fn reset_placement_stats(&self) -> Result<u64, String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        let mut cleared = 0;
+        for definition in [GUN_PLACEMENTS_TABLE, MARKER_PLACEMENTS_TABLE] {
+            let mut table = write_txn
+                .open_table(definition)
+                .map_err(|e| e.to_string())?;
+            cleared += table.len().map_err(|e| e.to_string())?;
+            table.retain(|_, _| false).map_err(|e| e.to_string())?;
+        }
+        // The per-war, per-day and per-map breakdowns and the deduped
+        // counters go too, but only the lifetime counters are reported
+        for definition in [
+            GUN_PLACEMENTS_BY_WAR_TABLE,
+            MARKER_PLACEMENTS_BY_WAR_TABLE,
+            GUN_PLACEMENTS_BY_DAY_TABLE,
+            MARKER_PLACEMENTS_BY_DAY_TABLE,
+            DEDUPED_PLACEMENTS_BY_WAR_TABLE,
+            DEDUPED_PLACEMENTS_BY_DAY_TABLE,
+        ] {
+            let mut table = write_txn
+                .open_table(definition)
+                .map_err(|e| e.to_string())?;
+            table.retain(|_, _| false).map_err(|e| e.to_string())?;
+        }
+        write_txn
+            .open_table(PLACEMENTS_BY_MAP_TABLE)
+            .map_err(|e| e.to_string())?
+            .retain(|_, _| false)
+            .map_err(|e| e.to_string())?;
+        write_txn
+            .open_table(DEDUPED_PLACEMENTS_TABLE)
+            .map_err(|e| e.to_string())?
+            .retain(|_, _| false)
+            .map_err(|e| e.to_string())?;
+        write_txn
+            .open_table(SESSION_PLACEMENTS_TABLE)
+            .map_err(|e| e.to_string())?
+            .retain(|_, _| false)
+            .map_err(|e| e.to_string())?;
+        write_txn
+            .open_table(UNIQUE_PLANNERS_TABLE)
+            .map_err(|e| e.to_string())?
+            .retain(|_, _| false)
+            .map_err(|e| e.to_string())?;
+        write_txn
+            .open_table(PLACEMENT_CELLS_TABLE)
+            .map_err(|e| e.to_string())?
+            .retain(|_, _| false)
+            .map_err(|e| e.to_string())?;
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(cleared)
+    }
+
+    /// Take or refresh the edit lock on a plan.
+    ///
+    /// The lock is granted when it is free, expired, already held by `holder`,
+    /// or `takeover` is set. Returns the lock in effect afterwards — compare its
+    /// holder with `holder` to see whether it was granted.
+    pub fn acquire_plan_lock(
+        &self,
+        plan_id: &str,
+        holder: &str,
+        now: i64,
+        ttl_secs: i64,
+        takeover: bool,
+    ) -> Result<PlanLock, String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        let lock = {
+            let mut table = write_txn
+                .open_table(PLAN_LOCKS_TABLE)
+                .map_err(|e| e.to_string())?;
+            let current: Option<PlanLock> = match table.get(plan_id).map_err(|e| e.to_string())? {
+                Some(value) => {
+                    Some(serde_json::from_slice(value.value()).map_err(|e| e.to_string())?)
+                }
+                None => None,
+            };
+            match current {
+                Some(lock) if lock.holder != holder && lock.expires_at > now && !takeover => lock,
+                _ => {
+                    let lock = PlanLock {
+                        holder: holder.to_string(),
+                        expires_at: now + ttl_secs,
+                    };
+                    let json = serde_json::to_vec(&lock).map_err(|e| e.to_string())?;
+                    table
+                        .insert(plan_id, json.as_slice())
+                        .map_err(|e| e.to_string())?;
+                    lock
+                }
+            }
+        };
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(lock)
+    }
+
+    /// Drop the edit lock on a plan if `holder` owns it. Returns whether a lock was removed.
+    pub fn release_plan_lock(&self, plan_id: &str, holder: &str) -> Result<bool, String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        let removed = {
+            let mut table = write_txn
+                .open_table(PLAN_LOCKS_TABLE)
+                .map_err(|e| e.to_string())?;
+            let owned = match table.get(plan_id).map_err(|e| e.to_string())? {
+                Some(value) => {
+                    let lock: PlanLock =
+                        serde_json::from_slice(value.value()).map_err(|e| e.to_string())?;
+                    lock.holder == holder
+                }
+                None => false,
+            };
+            if owned {
+                table.remove(plan_id).map_err(|e| e.to_string())?;
+            }
+            owned
+        };
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(removed)
+    }
+
+    /// Record a wind observation for a map. Only the freshest report is kept, so
+    /// one older than the stored report is ignored. Returns the report in effect.
+    pub fn report_wind(&self, map_id: &str, report: &WindReport) -> Result<WindReport, String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        let current = {
+            let mut table = write_txn
+                .open_table(WIND_REPORTS_TABLE)
+                .map_err(|e| e.to_string())?;
+            let stored: Option<WindReport> = match table.get(map_id).map_err(|e| e.to_string())? {
+                Some(value) => {
+                    Some(serde_json::from_slice(value.value()).map_err(|e| e.to_string())?)
+                }
+                None => None,
+            };
+            match stored {
+                Some(stored) if stored.reported_at > report.reported_at => stored,
+                _ => {
+                    let json = serde_json::to_vec(report).map_err(|e| e.to_string())?;
+                    table
+                        .insert(map_id, json.as_slice())
+                        .map_err(|e| e.to_string())?;
+                    report.clone()
+                }
+            }
+        };
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(current)
+    }
+
+    /// The freshest wind report for a map, if anyone has sent one.
+    pub fn wind_report(&self, map_id: &str) -> Result<Option<WindReport>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(WIND_REPORTS_TABLE)
+            .map_err(|e| e.to_string())?;
+        match table.get(map_id).map_err(|e| e.to_string())? {
+            Some(value) => serde_json::from_slice(value.value())
+                .map(Some)
+                .map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    }
+
+    /// Store a shared battery template under `id`.
+    pub fn save_battery_template(
+        &self,
+        id: &str,
+        template: &BatteryTemplate,
+    ) -> Result<(), String> {
+        let json = serde_json::to_vec(template).map_err(|e| e.to_string())?;
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        {
+            let mut table = write_txn
+                .open_table(BATTERY_TEMPLATES_TABLE)
+                .map_err(|e| e.to_string())?;
+            table
+                .insert(id, json.as_slice())
+                .map_err(|e| e.to_string())?;
+        }
+        write_txn.commit().map_err(|e| e.to_string())
+    }
+
+    pub fn battery_template(&self, id: &str) -> Result<Option<BatteryTemplate>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(BATTERY_TEMPLATES_TABLE)
+            .map_err(|e| e.to_string())?;
+        match table.get(id).map_err(|e| e.to_string())? {
+            Some(value) => serde_json::from_slice(value.value())
+                .map(Some)
+                .map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    }
+
+    pub fn add_impact_observation(&self, observation: &ImpactObservation) -> Result<(), String> {
+        let json = serde_json::to_vec(observation).map_err(|e| e.to_string())?;
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        {
+            let mut table = write_txn
+                .open_table(IMPACT_OBSERVATIONS_TABLE)
+                .map_err(|e| e.to_string())?;
+            table
+                .insert(
+                    (observation.weapon_slug.as_str(), id.as_str()),
+                    json.as_slice(),
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        write_txn.commit().map_err(|e| e.to_string())
+    }
+
+    /// Every submitted impact observation, grouped by weapon slug.
+    pub fn impact_observations(&self) -> Result<Vec<ImpactObservation>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(IMPACT_OBSERVATIONS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let mut observations = Vec::new();
+        for entry in table.iter().map_err(|e| e.to_string())? {
+            let (_, value) = entry.map_err(|e| e.to_string())?;
+            observations.push(serde_json::from_slice(value.value()).map_err(|e| e.to_string())?);
+        }
+        Ok(observations)
+    }
+
+    /// When the end-of-war report for `war` was posted, if it was.
+    pub fn war_reported_at(&self, war: u32) -> Result<Option<i64>, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(WAR_REPORTS_TABLE)
+            .map_err(|e| e.to_string())?;
+        Ok(table
+            .get(war)
+            .map_err(|e| e.to_string())?
+            .map(|v| v.value()))
+    }
+
+    pub fn record_war_report(&self, war: u32, posted_at: i64) -> Result<(), String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        {
+            let mut table = write_txn
+                .open_table(WAR_REPORTS_TABLE)
+                .map_err(|e| e.to_string())?;
+            table.insert(war, posted_at).map_err(|e| e.to_string())?;
+        }
+        write_txn.commit().map_err(|e| e.to_string())
+    }
+
+    /// Delete every plan the retention policy considers expired, along with
+    /// its edit token, lock and slug. Returns how many plans were deleted.
+    pub fn purge_expired_plans(&self, now: i64, policy: &RetentionPolicy) -> Result<u64, String> {
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        let deleted = {
+            let mut plans = write_txn
+                .open_table(PLANS_TABLE)
+                .map_err(|e| e.to_string())?;
+            let mut expired = Vec::new();
+            for entry in plans.iter().map_err(|e| e.to_string())? {
+                let (key, value) = entry.map_err(|e| e.to_string())?;
+                // Unreadable rows are left alone rather than silently dropped
+                let Ok(plan) = decode_plan(value.value()) else {
+                    continue;
+                };
+                if policy.is_expired(&plan, now) {
+                    expired.push(key.value().to_string());
+                }
+            }
+            let mut locks = write_txn
+                .open_table(PLAN_LOCKS_TABLE)
+                .map_err(|e| e.to_string())?;
+            let mut tokens = write_txn
+                .open_table(EDIT_TOKENS_TABLE)
+                .map_err(|e| e.to_string())?;
+            for id in &expired {
+                plans.remove(id.as_str()).map_err(|e| e.to_string())?;
+                locks.remove(id.as_str()).map_err(|e| e.to_string())?;
+                tokens.remove(id.as_str()).map_err(|e| e.to_string())?;
+                Self::remove_versions(&write_txn, id)?;
+                Self::remove_slug(&write_txn, id)?;
+            }
+            expired.len() as u64
+        };
+        write_txn.commit().map_err(|e| e.to_string())?;
+        Ok(deleted)
+    }
+
+    /// Compact the database file. Returns the number of bytes the file shrank by.
+    ///
+    /// Fails if a transaction is open at the time; the next run will retry.
+    pub fn compact(&self) -> Result<u64, String> {
+        let before = self.db_size_bytes()?;
+        {
+            let mut db = self.db.write().unwrap_or_else(|e| e.into_inner());
+            db.compact().map_err(|e| e.to_string())?;
+        }
+        let after = self.db_size_bytes()?;
+        Ok(before.saturating_sub(after))
+    }
+
+    /// Purge expired plans, compact if anything was deleted, and record the run.
+    pub fn run_cleanup(&self, now: i64, policy: &RetentionPolicy) -> Result<CleanupReport, String> {
+        let plans_deleted = self.purge_expired_plans(now, policy)?;
+        let bytes_reclaimed = if plans_deleted > 0 {
+            self.compact()?
+        } else {
+            0
+        };
+
+        let write_txn = self.db().begin_write().map_err(|e| e.to_string())?;
+        {
+            let mut table = write_txn
+                .open_table(CLEANUP_STATS_TABLE)
+                .map_err(|e| e.to_string())?;
+            let get = |table: &redb::Table<&str, u64>, key: &str| -> Result<u64, String> {
+                Ok(table
+                    .get(key)
+                    .map_err(|e| e.to_string())?
+                    .map(|v| v.value())
+                    .unwrap_or(0))
+            };
+            let expired_total = get(&table, "plans_expired")? + plans_deleted;
+            let reclaimed_total = get(&table, "bytes_reclaimed")? + bytes_reclaimed;
+            table
+                .insert("plans_expired", expired_total)
+                .map_err(|e| e.to_string())?;
+            table
+                .insert("bytes_reclaimed", reclaimed_total)
+                .map_err(|e| e.to_string())?;
+            table
+                .insert("last_run_at", now.max(0) as u64)
+                .map_err(|e| e.to_string())?;
+        }
+        write_txn.commit().map_err(|e| e.to_string())?;
+
+        Ok(CleanupReport {
+            plans_deleted,
+            bytes_reclaimed,
+        })
+    }
+
+    pub fn cleanup_stats(&self) -> Result<CleanupStats, String> {
+        let read_txn = self.db().begin_read().map_err(|e| e.to_string())?;
+        let table = read_txn
+            .open_table(CLEANUP_STATS_TABLE)
+            .map_err(|e| e.to_string())?;
+        let get = |key: &str| -> Result<Option<u64>, String> {
+            Ok(table
+                .get(key)
+                .map_err(|e| e.to_string())?
+                .map(|v| v.value()))
+        };
+        Ok(CleanupStats {
+            plans_expired: get("plans_expired")?.unwrap_or(0),
+            bytes_reclaimed: get("bytes_reclaimed")?.unwrap_or(0),
+            last_run_at: get("last_run_at")?.map(|t| t as i64),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tracking time used where the day doesn't matter.
+    const NOW: i64 = 1_700_000_000;
+
+    fn temp_storage() -> (Arc<Storage>, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.redb");
+        let storage = Storage::open(&path).unwrap();
+        (storage, dir)
+    }
+
+    #[test]
+    fn test_increment_gun_placement_new_slug() {
+        let (storage, _dir) = temp_storage();
+        storage
+            .increment_gun_placement("mortar", None, None, None, NOW)
+            .unwrap();
+        let counts = storage
+            .get_gun_placement_counts(PlacementScope::All)
+            .unwrap();
+        assert_eq!(counts, vec![("mortar".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_increment_gun_placement_accumulates() {
+        let (storage, _dir) = temp_storage();
+        for _ in 0..3 {
+            storage
+                .increment_gun_placement("storm-cannon", None, None, None, NOW)
+                .unwrap();
+        }
+        let counts = storage
+            .get_gun_placement_counts(PlacementScope::All)
+            .unwrap();
+        assert_eq!(counts, vec![("storm-cannon".to_string(), 3)]);
+    }
+
+    #[test]
+    fn test_increment_multiple_slugs() {
+        let (storage, _dir) = temp_storage();
+        storage
+            .increment_gun_placement("mortar", None, None, None, NOW)
+            .unwrap();
+        storage
+            .increment_gun_placement("mortar", None, None, None, NOW)
+            .unwrap();
+        storage
+            .increment_gun_placement("storm-cannon", None, None, None, NOW)
+            .unwrap();
+        let mut counts = storage
+            .get_gun_placement_counts(PlacementScope::All)
+            .unwrap();
+        counts.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            counts,
+            vec![("mortar".to_string(), 2), ("storm-cannon".to_string(), 1),]
+        );
+    }
+
+    #[test]
+    fn test_check_writable_and_free_disk() {
+        let (storage, _dir) = temp_storage();
+        storage.check_writable().unwrap();
+        if cfg!(unix) {
+            assert!(storage.free_disk_bytes().unwrap() > 0);
+        }
+    }
+
+    #[test]
+    fn test_get_gun_placement_counts_empty() {
+        let (storage, _dir) = temp_storage();
+        let counts = storage
+            .get_gun_placement_counts(PlacementScope::All)
+            .unwrap();
+        assert!(counts.is_empty());
+    }
+
+    #[test]
+    fn test_increment_marker_placement_new_kind() {
+        let (storage, _dir) = temp_storage();
+        storage
+            .increment_marker_placement("target", None, None, None, NOW)
+            .unwrap();
+        assert_eq!(
+            storage
+                .get_marker_placement_count("target", PlacementScope::All)
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_increment_marker_placement_accumulates() {
+        let (storage, _dir) = temp_storage();
+        for _ in 0..3 {
+            storage
+                .increment_marker_placement("spotter", None, None, None, NOW)
+                .unwrap();
+        }
+        assert_eq!(
+            storage
+                .get_marker_placement_count("spotter", PlacementScope::All)
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_get_marker_placement_count_absent() {
+        let (storage, _dir) = temp_storage();
+        assert_eq!(
+            storage
+                .get_marker_placement_count("target", PlacementScope::All)
+                .unwrap(),
+            0
+        );
+    }
+
+    fn test_plan(id: uuid::Uuid, name: &str) -> Plan {
+        use foxhole_shared::models::Position;
+        Plan {
+            id,
+            name: name.to_string(),
+            map_id: "test-map".to_string(),
             weapon_ids: vec!["mortar".to_string()],
-            gun_position: None,
-            target_position: None,
-            spotter_position: None,
             gun_positions: vec![Position { x: 100.0, y: 200.0 }],
             target_positions: vec![Position { x: 300.0, y: 400.0 }],
-            spotter_positions: vec![],
             gun_target_indices: vec![Some(0)],
             wind_direction: Some(90.0),
             wind_strength: 3,
             created_at: "2024-01-01T00:00:00Z".to_string(),
             updated_at: "2024-01-01T00:00:00Z".to_string(),
+            ..Plan::default()
         }
     }
 
@@ -297,4 +1543,997 @@ mod tests {
         assert_eq!(loaded.name, "Updated Name");
         assert_eq!(loaded.wind_strength, 5);
     }
+
+    #[test]
+    fn test_plan_lock_blocks_other_holder_until_expiry() {
+        let (storage, _dir) = temp_storage();
+        let lock = storage
+            .acquire_plan_lock("p1", "alice", 1000, 60, false)
+            .unwrap();
+        assert_eq!(lock.holder, "alice");
+        assert_eq!(lock.expires_at, 1060);
+
+        // Someone else is refused while the lock is live...
+        let lock = storage
+            .acquire_plan_lock("p1", "bob", 1030, 60, false)
+            .unwrap();
+        assert_eq!(lock.holder, "alice");
+        // ...and gets it once it has expired
+        let lock = storage
+            .acquire_plan_lock("p1", "bob", 1061, 60, false)
+            .unwrap();
+        assert_eq!(lock.holder, "bob");
+    }
+
+    #[test]
+    fn test_plan_lock_refresh_and_takeover() {
+        let (storage, _dir) = temp_storage();
+        storage
+            .acquire_plan_lock("p1", "alice", 1000, 60, false)
+            .unwrap();
+        let lock = storage
+            .acquire_plan_lock("p1", "alice", 1020, 60, false)
+            .unwrap();
+        assert_eq!(lock.expires_at, 1080);
+
+        let lock = storage
+            .acquire_plan_lock("p1", "bob", 1030, 60, true)
+            .unwrap();
+        assert_eq!(lock.holder, "bob");
+        // Locks are per plan
+        let other = storage
+            .acquire_plan_lock("p2", "alice", 1030, 60, false)
+            .unwrap();
+        assert_eq!(other.holder, "alice");
+    }
+
+    #[test]
+    fn test_release_plan_lock_only_by_holder() {
+        let (storage, _dir) = temp_storage();
+        storage
+            .acquire_plan_lock("p1", "alice", 1000, 60, false)
+            .unwrap();
+        assert!(!storage.release_plan_lock("p1", "bob").unwrap());
+        assert!(storage.release_plan_lock("p1", "alice").unwrap());
+        assert!(!storage.release_plan_lock("p1", "alice").unwrap());
+
+        let lock = storage
+            .acquire_plan_lock("p1", "bob", 1001, 60, false)
+            .unwrap();
+        assert_eq!(lock.holder, "bob");
+    }
+
+    #[test]
+    fn test_wind_report_keeps_freshest() {
+        let (storage, _dir) = temp_storage();
+        assert_eq!(storage.wind_report("deadlands").unwrap(), None);
+
+        let report = |strength, reported_at| WindReport {
+            direction: 90.0,
+            strength,
+            reported_at,
+        };
+        storage.report_wind("deadlands", &report(2, 1000)).unwrap();
+        storage.report_wind("deadlands", &report(4, 1100)).unwrap();
+        // A late-arriving older observation doesn't replace a newer one
+        let kept = storage.report_wind("deadlands", &report(1, 1050)).unwrap();
+        assert_eq!(kept, report(4, 1100));
+        assert_eq!(
+            storage.wind_report("deadlands").unwrap(),
+            Some(report(4, 1100))
+        );
+        // Reports are per map
+        assert_eq!(storage.wind_report("westgate").unwrap(), None);
+    }
+
+    #[test]
+    fn test_impact_observations_are_grouped_by_weapon() {
+        let (storage, _dir) = temp_storage();
+        let observation = |slug: &str, long: f64| ImpactObservation {
+            weapon_slug: slug.to_string(),
+            azimuth: 90.0,
+            distance: 150.0,
+            wind_direction: None,
+            wind_strength: 0,
+            compensated_drift: 0.0,
+            long,
+            right: 0.0,
+        };
+        storage
+            .add_impact_observation(&observation("storm-cannon", 1.0))
+            .unwrap();
+        storage
+            .add_impact_observation(&observation("mortar", 2.0))
+            .unwrap();
+        storage
+            .add_impact_observation(&observation("storm-cannon", 3.0))
+            .unwrap();
+
+        let stored = storage.impact_observations().unwrap();
+        let slugs: Vec<&str> = stored.iter().map(|o| o.weapon_slug.as_str()).collect();
+        assert_eq!(slugs, vec!["mortar", "storm-cannon", "storm-cannon"]);
+        let mut longs: Vec<f64> = stored[1..].iter().map(|o| o.long).collect();
+        longs.sort_by(f64::total_cmp);
+        assert_eq!(longs, vec![1.0, 3.0]);
+    }
+
+    #[test]
+    fn test_placements_by_day() {
+        const DAY: i64 = 86_400;
+        let (storage, _dir) = temp_storage();
+        let today = day_of(NOW);
+        storage
+            .increment_gun_placement("mortar", Some(117), None, None, NOW - 2 * DAY)
+            .unwrap();
+        storage
+            .increment_gun_placement("mortar", Some(117), None, None, NOW - DAY)
+            .unwrap();
+        storage
+            .increment_gun_placement("storm-cannon", None, None, None, NOW)
+            .unwrap();
+        storage
+            .increment_marker_placement("target", Some(118), None, None, NOW)
+            .unwrap();
+
+        let days = |from: u32, to: u32| PlacementScope::Days { from, to };
+        assert_eq!(
+            storage
+                .get_gun_placement_counts(days(today - 2, today))
+                .unwrap(),
+            vec![("mortar".to_string(), 2), ("storm-cannon".to_string(), 1)]
+        );
+        assert_eq!(
+            storage
+                .get_gun_placement_counts(days(today - 1, today - 1))
+                .unwrap(),
+            vec![("mortar".to_string(), 1)]
+        );
+        // Placements made while the war was unknown still count by day
+        assert_eq!(
+            storage
+                .get_gun_placement_counts(days(today, today))
+                .unwrap(),
+            vec![("storm-cannon".to_string(), 1)]
+        );
+        assert!(storage
+            .get_gun_placement_counts(days(today + 1, today + 7))
+            .unwrap()
+            .is_empty());
+        assert!(storage
+            .get_gun_placement_counts(days(today, today - 2))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            storage
+                .get_marker_placement_count("target", days(today, today))
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            storage
+                .get_marker_placement_count("target", days(today - 2, today - 1))
+                .unwrap(),
+            0
+        );
+
+        assert_eq!(day_of(0), 0);
+        assert_eq!(day_of(DAY - 1), 0);
+        assert_eq!(day_of(DAY), 1);
+        assert_eq!(day_of(-1), 0);
+    }
+
+    #[test]
+    fn test_placements_by_map() {
+        let (storage, _dir) = temp_storage();
+        for slug in ["mortar", "storm-cannon"] {
+            storage
+                .increment_gun_placement(slug, None, Some("MapDeadlandsHex"), None, NOW)
+                .unwrap();
+        }
+        storage
+            .increment_marker_placement("target", Some(117), Some("MapDeadlandsHex"), None, NOW)
+            .unwrap();
+        storage
+            .increment_marker_placement("spotter", None, Some("MapAcrithiaHex"), None, NOW)
+            .unwrap();
+        // Tracked without a map: only in the global counters
+        storage
+            .increment_gun_placement("mortar", None, None, None, NOW)
+            .unwrap();
+
+        let row = |map: &str, counter: &str, count| (map.to_string(), counter.to_string(), count);
+        assert_eq!(
+            storage.placements_by_map().unwrap(),
+            vec![
+                row("MapAcrithiaHex", "spotter", 1),
+                row("MapDeadlandsHex", "gun", 2),
+                row("MapDeadlandsHex", "target", 1),
+            ]
+        );
+        assert_eq!(
+            storage
+                .get_gun_placement_counts(PlacementScope::All)
+                .unwrap(),
+            vec![("mortar".to_string(), 2), ("storm-cannon".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_deduped_placements() {
+        const DAY: i64 = 86_400;
+        let (storage, _dir) = temp_storage();
+        let gun = |slug: &str, session: Option<&str>, now: i64| {
+            storage
+                .increment_gun_placement(slug, Some(117), None, session, now)
+                .unwrap()
+        };
+        // One session placing and re-placing the same weapon counts once a day
+        for _ in 0..5 {
+            gun("mortar", Some("alice"), NOW);
+        }
+        gun("storm-cannon", Some("alice"), NOW);
+        gun("mortar", Some("bob"), NOW);
+        gun("mortar", Some("alice"), NOW + DAY);
+        // Without a session id only the raw counters move
+        gun("mortar", None, NOW);
+        for _ in 0..3 {
+            storage
+                .increment_marker_placement("target", None, None, Some("alice"), NOW)
+                .unwrap();
+        }
+
+        assert_eq!(
+            storage
+                .get_gun_placement_counts(PlacementScope::All)
+                .unwrap(),
+            vec![("mortar".to_string(), 8), ("storm-cannon".to_string(), 1)]
+        );
+        assert_eq!(
+            storage
+                .get_deduped_placement_counts(PlacementScope::All)
+                .unwrap(),
+            vec![("gun".to_string(), 4), ("target".to_string(), 1)]
+        );
+        assert_eq!(
+            storage
+                .get_deduped_placement_counts(PlacementScope::War(117))
+                .unwrap(),
+            vec![("gun".to_string(), 4)]
+        );
+        let today = day_of(NOW);
+        assert_eq!(
+            storage
+                .get_deduped_placement_counts(PlacementScope::Days {
+                    from: today,
+                    to: today
+                })
+                .unwrap(),
+            vec![("gun".to_string(), 3), ("target".to_string(), 1)]
+        );
+
+        // Both sessions placed guns during the war, only alice the day after
+        assert_eq!(storage.unique_planners(PlacementScope::All).unwrap(), 2);
+        assert_eq!(
+            storage.unique_planners(PlacementScope::War(117)).unwrap(),
+            2
+        );
+        assert_eq!(
+            storage.unique_planners(PlacementScope::War(118)).unwrap(),
+            0
+        );
+        let days = |from: u32, to: u32| PlacementScope::Days { from, to };
+        assert_eq!(storage.unique_planners(days(today, today)).unwrap(), 2);
+        assert_eq!(
+            storage.unique_planners(days(today + 1, today + 1)).unwrap(),
+            1
+        );
+        assert_eq!(storage.unique_planners(days(today + 1, today)).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_war_reports_are_remembered() {
+        let (storage, _dir) = temp_storage();
+        assert_eq!(storage.war_reported_at(117).unwrap(), None);
+        storage.record_war_report(117, 1_700_000_000).unwrap();
+        assert_eq!(storage.war_reported_at(117).unwrap(), Some(1_700_000_000));
+        assert_eq!(storage.war_reported_at(118).unwrap(), None);
+    }
+
+    #[test]
+    fn test_battery_template_round_trip() {
+        use foxhole_shared::template::Formation;
+
+        let (storage, _dir) = temp_storage();
+        assert_eq!(storage.battery_template("t1").unwrap(), None);
+        let template = BatteryTemplate::formation("Triangle", "mortar", 3, 50.0, Formation::Ring);
+        storage.save_battery_template("t1", &template).unwrap();
+        assert_eq!(storage.battery_template("t1").unwrap(), Some(template));
+        assert_eq!(storage.battery_template("t2").unwrap(), None);
+    }
+
+    #[test]
+    fn test_update_plan_bumps_version() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        storage.save_plan(&test_plan(id, "Original")).unwrap();
+
+        let edited = test_plan(id, "Edited");
+        let UpdateOutcome::Saved(saved) = storage.update_plan(&edited, 0).unwrap() else {
+            panic!("expected save");
+        };
+        assert_eq!(saved.version, 1);
+        assert_eq!(saved.created_at, "2024-01-01T00:00:00Z");
+        assert_eq!(
+            storage.get_plan(&id.to_string()).unwrap().unwrap().name,
+            "Edited"
+        );
+    }
+
+    #[test]
+    fn test_update_plan_stale_version_conflicts() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        storage.save_plan(&test_plan(id, "Original")).unwrap();
+        storage.update_plan(&test_plan(id, "First"), 0).unwrap();
+
+        // Second editor still on version 0
+        match storage.update_plan(&test_plan(id, "Second"), 0).unwrap() {
+            UpdateOutcome::Conflict(theirs) => {
+                assert_eq!(theirs.name, "First");
+                assert_eq!(theirs.version, 1);
+            }
+            other => panic!("expected conflict, got {:?}", other),
+        }
+        assert_eq!(
+            storage.get_plan(&id.to_string()).unwrap().unwrap().name,
+            "First"
+        );
+
+        let missing = test_plan(uuid::Uuid::new_v4(), "Missing");
+        assert!(matches!(
+            storage.update_plan(&missing, 0).unwrap(),
+            UpdateOutcome::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_update_plan_archives_previous_version() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        let id_str = id.to_string();
+        storage.save_plan(&test_plan(id, "Original")).unwrap();
+        storage.update_plan(&test_plan(id, "First"), 0).unwrap();
+        storage.update_plan(&test_plan(id, "Second"), 1).unwrap();
+
+        let versions = storage.plan_versions(&id_str).unwrap();
+        let summary: Vec<(u32, &str)> = versions
+            .iter()
+            .map(|p| (p.version, p.name.as_str()))
+            .collect();
+        assert_eq!(summary, vec![(1, "First"), (0, "Original")]);
+        assert_eq!(
+            storage.get_plan_version(&id_str, 0).unwrap().unwrap().name,
+            "Original"
+        );
+        assert!(storage.get_plan_version(&id_str, 2).unwrap().is_none());
+
+        // A conflicting save archives nothing
+        storage.update_plan(&test_plan(id, "Stale"), 0).unwrap();
+        assert_eq!(storage.plan_versions(&id_str).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_plan_versions_are_capped() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        storage.save_plan(&test_plan(id, "v0")).unwrap();
+        for v in 0..(MAX_PLAN_VERSIONS as u32 + 5) {
+            storage
+                .update_plan(&test_plan(id, &format!("v{}", v + 1)), v)
+                .unwrap();
+        }
+        let versions = storage.plan_versions(&id.to_string()).unwrap();
+        assert_eq!(versions.len(), MAX_PLAN_VERSIONS);
+        assert_eq!(versions.last().unwrap().version, 5);
+    }
+
+    #[test]
+    fn test_delete_plan_removes_versions() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        let other = uuid::Uuid::new_v4();
+        storage.create_plan(&test_plan(id, "Doomed"), "t").unwrap();
+        storage.create_plan(&test_plan(other, "Kept"), "t").unwrap();
+        storage.update_plan(&test_plan(id, "Edited"), 0).unwrap();
+        storage.update_plan(&test_plan(other, "Edited"), 0).unwrap();
+
+        assert!(storage.delete_plan(&id.to_string()).unwrap());
+        assert!(storage.plan_versions(&id.to_string()).unwrap().is_empty());
+        assert_eq!(storage.plan_versions(&other.to_string()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_new_plans_get_a_slug() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4().to_string();
+        let other = uuid::Uuid::new_v4().to_string();
+        storage
+            .create_plan(&test_plan(id.parse().unwrap(), "Bridge"), "t")
+            .unwrap();
+        storage
+            .create_plan(&test_plan(other.parse().unwrap(), "Ford"), "t")
+            .unwrap();
+
+        let slug = storage.plan_slug(&id).unwrap().unwrap();
+        assert!(foxhole_shared::slug::is_slug(&slug), "{slug}");
+        assert_ne!(storage.plan_slug(&other).unwrap(), Some(slug.clone()));
+        assert_eq!(storage.plan_id_for_slug(&slug).unwrap(), Some(id.clone()));
+        assert_eq!(storage.find_plan(&slug).unwrap().unwrap().name, "Bridge");
+        assert_eq!(storage.find_plan(&id).unwrap().unwrap().name, "Bridge");
+        assert!(storage.find_plan("no-such-plan").unwrap().is_none());
+
+        // Deleting the plan frees its slug
+        assert!(storage.delete_plan(&id).unwrap());
+        assert_eq!(storage.plan_id_for_slug(&slug).unwrap(), None);
+        assert_eq!(storage.plan_slug(&id).unwrap(), None);
+        assert!(storage.plan_slug(&other).unwrap().is_some());
+    }
+
+    const DAY: i64 = 86_400;
+
+    #[test]
+    fn test_purge_expired_plans() {
+        let (storage, _dir) = temp_storage();
+        // test_plan's updated_at is 2024-01-01
+        let saved_at = chrono::DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")
+            .unwrap()
+            .timestamp();
+        let now = saved_at + 10 * DAY;
+        let policy = RetentionPolicy {
+            plan_ttl_secs: Some(30 * DAY),
+        };
+
+        let expired = uuid::Uuid::new_v4();
+        let mut plan = test_plan(expired, "Expired");
+        plan.expires_at = Some(now - 1);
+        storage.save_plan(&plan).unwrap();
+        storage
+            .acquire_plan_lock(&expired.to_string(), "tab", now, 60, false)
+            .unwrap();
+
+        let fresh = uuid::Uuid::new_v4();
+        let mut plan = test_plan(fresh, "Fresh");
+        plan.expires_at = Some(now + 60 * DAY);
+        storage.save_plan(&plan).unwrap();
+
+        // No explicit expiry: 10 days since updated_at is within the 30 day TTL
+        let legacy = uuid::Uuid::new_v4();
+        storage.save_plan(&test_plan(legacy, "Legacy")).unwrap();
+
+        assert_eq!(storage.purge_expired_plans(now, &policy).unwrap(), 1);
+        assert!(storage.get_plan(&expired.to_string()).unwrap().is_none());
+        assert!(storage.get_plan(&fresh.to_string()).unwrap().is_some());
+        assert!(storage.get_plan(&legacy.to_string()).unwrap().is_some());
+        // The expired plan's lock went with it
+        let lock = storage
+            .acquire_plan_lock(&expired.to_string(), "other", now, 60, false)
+            .unwrap();
+        assert_eq!(lock.holder, "other");
+
+        // Legacy plans age out from updated_at
+        let later = saved_at + 31 * DAY;
+        assert_eq!(storage.purge_expired_plans(later, &policy).unwrap(), 1);
+        assert!(storage.get_plan(&legacy.to_string()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_purge_disabled_keeps_everything() {
+        let (storage, _dir) = temp_storage();
+        let mut plan = test_plan(uuid::Uuid::new_v4(), "Old");
+        plan.expires_at = Some(0);
+        storage.save_plan(&plan).unwrap();
+        let policy = RetentionPolicy {
+            plan_ttl_secs: None,
+        };
+        assert_eq!(storage.purge_expired_plans(i64::MAX, &policy).unwrap(), 0);
+        assert_eq!(storage.count_plans().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_run_cleanup_records_stats() {
+        let (storage, _dir) = temp_storage();
+        assert_eq!(storage.cleanup_stats().unwrap(), CleanupStats::default());
+
+        for _ in 0..50 {
+            let mut plan = test_plan(uuid::Uuid::new_v4(), &"x".repeat(2000));
+            plan.expires_at = Some(100);
+            storage.save_plan(&plan).unwrap();
+        }
+        let policy = RetentionPolicy {
+            plan_ttl_secs: Some(DAY),
+        };
+        let report = storage.run_cleanup(200, &policy).unwrap();
+        assert_eq!(report.plans_deleted, 50);
+        assert_eq!(storage.count_plans().unwrap(), 0);
+
+        let stats = storage.cleanup_stats().unwrap();
+        assert_eq!(stats.plans_expired, 50);
+        assert_eq!(stats.bytes_reclaimed, report.bytes_reclaimed);
+        assert_eq!(stats.last_run_at, Some(200));
+
+        // Nothing left to delete: totals carry over, no compaction
+        let report = storage.run_cleanup(300, &policy).unwrap();
+        assert_eq!(report, CleanupReport::default());
+        let stats = storage.cleanup_stats().unwrap();
+        assert_eq!(stats.plans_expired, 50);
+        assert_eq!(stats.last_run_at, Some(300));
+    }
+
+    #[test]
+    fn test_edit_token_matches() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        storage
+            .create_plan(&test_plan(id, "Protected"), "secret")
+            .unwrap();
+        let id = id.to_string();
+        assert_eq!(
+            storage.edit_token_matches(&id, Some("secret")).unwrap(),
+            Some(true)
+        );
+        assert_eq!(
+            storage.edit_token_matches(&id, Some("guess")).unwrap(),
+            Some(false)
+        );
+        assert_eq!(storage.edit_token_matches(&id, None).unwrap(), Some(false));
+
+        // Plans saved without a token stay open
+        let legacy = uuid::Uuid::new_v4();
+        storage.save_plan(&test_plan(legacy, "Legacy")).unwrap();
+        assert_eq!(
+            storage
+                .edit_token_matches(&legacy.to_string(), None)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_list_plans_pages_in_id_order() {
+        let (storage, _dir) = temp_storage();
+        let mut ids: Vec<uuid::Uuid> = (0..5).map(|_| uuid::Uuid::new_v4()).collect();
+        for id in &ids {
+            storage.create_plan(&test_plan(*id, "Plan"), "t").unwrap();
+        }
+        ids.sort_by_key(|id| id.to_string());
+
+        let first = storage.list_plans(0, 3).unwrap();
+        let rest = storage.list_plans(3, 3).unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(rest.len(), 2);
+        let listed: Vec<_> = first.iter().chain(&rest).map(|p| p.id).collect();
+        assert_eq!(listed, ids);
+    }
+
+    #[test]
+    fn test_reset_placement_stats() {
+        let (storage, _dir) = temp_storage();
+        storage
+            .increment_gun_placement("mortar", None, Some("MapDeadlandsHex"), None, NOW)
+            .unwrap();
+        storage
+            .increment_gun_placement("storm-cannon", Some(117), None, None, NOW)
+            .unwrap();
+        storage
+            .increment_marker_placement("target", None, None, None, NOW)
+            .unwrap();
+        storage
+            .add_placement_cells("MapDeadlandsHex", "gun", &[(6, 8)])
+            .unwrap();
+
+        // Three lifetime counters; the war, day and map breakdowns and the
+        // heatmap are cleared without being counted
+        assert_eq!(storage.reset_placement_stats().unwrap(), 3);
+        assert!(storage.placements_by_map().unwrap().is_empty());
+        assert!(storage
+            .placement_heatmap("MapDeadlandsHex", "gun")
+            .unwrap()
+            .is_empty());
+        assert!(storage
+            .get_gun_placement_counts(PlacementScope::All)
+            .unwrap()
+            .is_empty());
+        assert!(storage
+            .get_gun_placement_counts(PlacementScope::War(117))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            storage
+                .get_marker_placement_count("target", PlacementScope::All)
+                .unwrap(),
+            0
+        );
+        assert!(storage.placement_wars().unwrap().is_empty());
+        let today = day_of(NOW);
+        assert!(storage
+            .get_gun_placement_counts(PlacementScope::Days {
+                from: today,
+                to: today
+            })
+            .unwrap()
+            .is_empty());
+        assert_eq!(storage.reset_placement_stats().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_placement_heatmap_counts_cells_per_map_and_kind() {
+        let (storage, _dir) = temp_storage();
+        storage
+            .add_placement_cells("MapDeadlandsHex", "gun", &[(6, 8), (6, 8), (2, 3)])
+            .unwrap();
+        storage
+            .add_placement_cells("MapDeadlandsHex", "gun", &[(6, 8)])
+            .unwrap();
+        storage
+            .add_placement_cells("MapDeadlandsHex", "target", &[(7, 8)])
+            .unwrap();
+        storage
+            .add_placement_cells("MapWestgateHex", "gun", &[(1, 1)])
+            .unwrap();
+
+        assert_eq!(
+            storage.placement_heatmap("MapDeadlandsHex", "gun").unwrap(),
+            vec![(2, 3, 1), (6, 8, 3)]
+        );
+        assert_eq!(
+            storage
+                .placement_heatmap("MapDeadlandsHex", "target")
+                .unwrap(),
+            vec![(7, 8, 1)]
+        );
+        assert!(storage
+            .placement_heatmap("MapOriginHex", "gun")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_placements_are_counted_per_war() {
+        let (storage, _dir) = temp_storage();
+        storage
+            .increment_gun_placement("mortar", Some(117), None, None, NOW)
+            .unwrap();
+        storage
+            .increment_gun_placement("mortar", Some(118), None, None, NOW)
+            .unwrap();
+        storage
+            .increment_gun_placement("mortar", Some(118), None, None, NOW)
+            .unwrap();
+        storage
+            .increment_gun_placement("storm-cannon", Some(118), None, None, NOW)
+            .unwrap();
+        storage
+            .increment_gun_placement("mortar", None, None, None, NOW)
+            .unwrap();
+        storage
+            .increment_marker_placement("target", Some(118), None, None, NOW)
+            .unwrap();
+
+        assert_eq!(
+            storage
+                .get_gun_placement_counts(PlacementScope::War(117))
+                .unwrap(),
+            vec![("mortar".to_string(), 1)]
+        );
+        assert_eq!(
+            storage
+                .get_gun_placement_counts(PlacementScope::War(118))
+                .unwrap(),
+            vec![("mortar".to_string(), 2), ("storm-cannon".to_string(), 1)]
+        );
+        assert_eq!(
+            storage
+                .get_gun_placement_counts(PlacementScope::All)
+                .unwrap(),
+            vec![("mortar".to_string(), 4), ("storm-cannon".to_string(), 1)]
+        );
+        assert_eq!(
+            storage
+                .get_marker_placement_count("target", PlacementScope::War(117))
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            storage
+                .get_marker_placement_count("target", PlacementScope::War(118))
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            storage
+                .get_marker_placement_count("target", PlacementScope::All)
+                .unwrap(),
+            1
+        );
+        assert_eq!(storage.placement_wars().unwrap(), vec![117, 118]);
+    }
+
+    #[test]
+    fn test_update_keeps_plan_war() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        let mut plan = test_plan(id, "Made in war 117");
+        plan.war_number = Some(117);
+        storage.create_plan(&plan, "t").unwrap();
+
+        plan.war_number = Some(118);
+        let UpdateOutcome::Saved(saved) = storage.update_plan(&plan, 0).unwrap() else {
+            panic!("update should be saved");
+        };
+        assert_eq!(saved.war_number, Some(117));
+        let loaded = storage.get_plan(&id.to_string()).unwrap().unwrap();
+        assert_eq!(loaded.war_number, Some(117));
+    }
+
+    #[test]
+    fn test_backup_is_a_readable_copy() {
+        let (storage, dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        storage
+            .create_plan(&test_plan(id, "Backed up"), "t")
+            .unwrap();
+
+        let backup_dir = dir.path().join("backups");
+        let path = storage.backup_to(&backup_dir, 1_700_000_000).unwrap();
+        assert_eq!(path, backup_dir.join("plans-20231114T221320Z.redb"));
+        assert_eq!(std::fs::read_dir(&backup_dir).unwrap().count(), 1);
+
+        // Later writes don't reach the backup
+        storage.delete_plan(&id.to_string()).unwrap();
+        let restored = Storage::open(&path).unwrap();
+        let plan = restored.get_plan(&id.to_string()).unwrap().unwrap();
+        assert_eq!(plan.name, "Backed up");
+    }
+
+    #[test]
+    fn test_backup_keeps_newest_snapshots() {
+        let (storage, dir) = temp_storage();
+        let policy = BackupPolicy {
+            dir: dir.path().join("backups"),
+            interval: None,
+            keep: 2,
+        };
+        std::fs::create_dir_all(&policy.dir).unwrap();
+        std::fs::write(policy.dir.join("notes.txt"), "not a snapshot").unwrap();
+
+        let t = 1_700_000_000;
+        let first = storage.backup(&policy, t).unwrap();
+        assert_eq!(first.pruned, 0);
+        assert!(first.size_bytes > 0);
+        storage.backup(&policy, t + 3600).unwrap();
+        let third = storage.backup(&policy, t + 7200).unwrap();
+        assert_eq!(third.pruned, 1);
+
+        assert!(!first.path.exists());
+        assert!(third.path.exists());
+        assert!(policy.dir.join("notes.txt").exists());
+        assert_eq!(std::fs::read_dir(&policy.dir).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn test_next_backup_counts_from_newest_snapshot() {
+        let (storage, dir) = temp_storage();
+        let backup_dir = dir.path().join("backups");
+        use std::time::Duration;
+
+        let hour = Duration::from_secs(3600);
+        let t = 1_700_000_000;
+        assert_eq!(next_backup_in(&backup_dir, hour, t), Duration::ZERO);
+
+        storage.backup_to(&backup_dir, t).unwrap();
+        assert_eq!(
+            next_backup_in(&backup_dir, hour, t + 600),
+            Duration::from_secs(3000)
+        );
+        assert_eq!(next_backup_in(&backup_dir, hour, t + 4000), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_restore_replaces_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("plans.redb");
+        let id = uuid::Uuid::new_v4();
+        let snapshot = {
+            let storage = Storage::open(&db_path).unwrap();
+            storage
+                .create_plan(&test_plan(id, "Before upgrade"), "t")
+                .unwrap();
+            let snapshot = storage.backup_to(&dir.path().join("backups"), 0).unwrap();
+            storage.delete_plan(&id.to_string()).unwrap();
+            snapshot
+        };
+
+        Storage::restore(&snapshot, &db_path).unwrap();
+        let storage = Storage::open(&db_path).unwrap();
+        let plan = storage.get_plan(&id.to_string()).unwrap().unwrap();
+        assert_eq!(plan.name, "Before upgrade");
+    }
+
+    #[test]
+    fn test_restore_rejects_bad_snapshots() {
+        let (storage, dir) = temp_storage();
+        let db_path = dir.path().join("test.redb");
+        let garbage = dir.path().join("garbage.redb");
+        std::fs::write(&garbage, b"not a database").unwrap();
+        drop(storage);
+
+        assert!(Storage::restore(&garbage, &db_path).is_err());
+        assert!(Storage::restore(&dir.path().join("missing.redb"), &db_path).is_err());
+        assert!(Storage::restore(&db_path, &db_path).is_err());
+        // The live database is untouched
+        Storage::open(&db_path).unwrap();
+    }
+
+    const LEGACY_PLAN_JSON: &str = r#"{
+        "id": "6c1d3f1e-8b7a-4c55-9a5e-2f0d7c3b9a10",
+        "name": "Single gun",
+        "map_id": "deadlands",
+        "gun_position": {"x": 10.0, "y": 20.0},
+        "target_position": {"x": 30.0, "y": 40.0},
+        "spotter_position": null,
+        "wind_direction": null,
+        "wind_strength": 0,
+        "created_at": "2023-06-01T00:00:00Z",
+        "updated_at": "2023-06-01T00:00:00Z"
+    }"#;
+
+    /// Write raw rows the way an old build left them and mark the file unmigrated.
+    fn store_legacy_rows(storage: &Storage, id: &str) {
+        let write_txn = storage.db().begin_write().unwrap();
+        {
+            let mut plans = write_txn.open_table(PLANS_TABLE).unwrap();
+            plans.insert(id, LEGACY_PLAN_JSON.as_bytes()).unwrap();
+            let mut versions = write_txn.open_table(PLAN_VERSIONS_TABLE).unwrap();
+            versions
+                .insert((id, 0), LEGACY_PLAN_JSON.as_bytes())
+                .unwrap();
+            let mut meta = write_txn.open_table(migrations::META_TABLE).unwrap();
+            meta.remove("schema_version").unwrap();
+        }
+        write_txn.commit().unwrap();
+    }
+
+    #[test]
+    fn test_new_database_is_at_current_schema() {
+        let (storage, _dir) = temp_storage();
+        assert_eq!(storage.schema_version().unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migration_upgrades_legacy_single_position_plans() {
+        use foxhole_shared::models::Position;
+
+        let (storage, dir) = temp_storage();
+        let id = "6c1d3f1e-8b7a-4c55-9a5e-2f0d7c3b9a10";
+        store_legacy_rows(&storage, id);
+        assert_eq!(storage.schema_version().unwrap(), 0);
+        drop(storage);
+
+        let storage = Storage::open(&dir.path().join("test.redb")).unwrap();
+        assert_eq!(storage.schema_version().unwrap(), SCHEMA_VERSION);
+        let plan = storage.get_plan(id).unwrap().unwrap();
+        assert_eq!(plan.gun_positions, vec![Position { x: 10.0, y: 20.0 }]);
+        assert_eq!(plan.target_positions, vec![Position { x: 30.0, y: 40.0 }]);
+        assert!(plan.spotter_positions.is_empty());
+        let archived = storage.get_plan_version(id, 0).unwrap().unwrap();
+        assert_eq!(archived.gun_positions.len(), 1);
+
+        // The stored rows themselves are rewritten in the current format
+        let read_txn = storage.db().begin_read().unwrap();
+        let plans = read_txn.open_table(PLANS_TABLE).unwrap();
+        let raw: serde_json::Value =
+            serde_json::from_slice(plans.get(id).unwrap().unwrap().value()).unwrap();
+        assert!(raw.get("gun_position").is_none());
+        assert_eq!(raw["gun_positions"][0]["x"], 10.0);
+    }
+
+    #[test]
+    fn test_migration_leaves_current_plans_alone() {
+        let (storage, dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        storage.create_plan(&test_plan(id, "Current"), "t").unwrap();
+        let before = storage.get_plan(&id.to_string()).unwrap().unwrap();
+        store_legacy_rows(&storage, "legacy");
+        drop(storage);
+
+        let storage = Storage::open(&dir.path().join("test.redb")).unwrap();
+        let after = storage.get_plan(&id.to_string()).unwrap().unwrap();
+        assert_eq!(after.gun_positions, before.gun_positions);
+        assert_eq!(after.gun_target_indices, before.gun_target_indices);
+    }
+
+    #[test]
+    fn test_open_refuses_newer_schema() {
+        let (storage, dir) = temp_storage();
+        let write_txn = storage.db().begin_write().unwrap();
+        {
+            let mut meta = write_txn.open_table(migrations::META_TABLE).unwrap();
+            meta.insert("schema_version", SCHEMA_VERSION + 1).unwrap();
+        }
+        write_txn.commit().unwrap();
+        drop(storage);
+
+        let err = Storage::open(&dir.path().join("test.redb")).err().unwrap();
+        assert!(err.contains("newer than this build"), "{err}");
+    }
+
+    fn raw_plan_row(storage: &Storage, id: &str) -> Vec<u8> {
+        let read_txn = storage.db().begin_read().unwrap();
+        let plans = read_txn.open_table(PLANS_TABLE).unwrap();
+        let row = plans.get(id).unwrap().unwrap().value().to_vec();
+        row
+    }
+
+    #[test]
+    fn test_plans_are_stored_in_binary_encoding() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        let mut plan = test_plan(id, "Compact");
+        plan.gun_labels = vec!["North battery".to_string()];
+        plan.target_statuses = vec![foxhole_shared::models::TargetStatus::FiredUpon];
+        plan.expires_at = Some(1_800_000_000);
+        storage.create_plan(&plan, "t").unwrap();
+
+        let row = raw_plan_row(&storage, &id.to_string());
+        assert!(!encoding::is_legacy_json(&row));
+        assert!(row.len() < serde_json::to_vec(&plan).unwrap().len());
+        assert_eq!(storage.count_legacy_encoded_plans().unwrap(), 0);
+
+        let loaded = storage.get_plan(&id.to_string()).unwrap().unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&plan).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_legacy_json_plans_convert_when_saved() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        storage.save_plan(&test_plan(id, "Old row")).unwrap();
+        assert_eq!(raw_plan_row(&storage, &id.to_string())[0], b'{');
+        assert_eq!(storage.count_legacy_encoded_plans().unwrap(), 1);
+
+        let mut plan = storage.get_plan(&id.to_string()).unwrap().unwrap();
+        assert_eq!(plan.name, "Old row");
+        plan.name = "New row".to_string();
+        assert!(matches!(
+            storage.update_plan(&plan, 0).unwrap(),
+            UpdateOutcome::Saved(_)
+        ));
+        assert_eq!(storage.count_legacy_encoded_plans().unwrap(), 0);
+        assert!(!encoding::is_legacy_json(&raw_plan_row(
+            &storage,
+            &id.to_string()
+        )));
+        let versions = storage.plan_versions(&id.to_string()).unwrap();
+        assert_eq!(versions[0].name, "Old row");
+    }
+
+    #[test]
+    fn test_delete_plan_removes_token() {
+        let (storage, _dir) = temp_storage();
+        let id = uuid::Uuid::new_v4();
+        storage
+            .create_plan(&test_plan(id, "Doomed"), "secret")
+            .unwrap();
+        let id = id.to_string();
+        assert!(storage.delete_plan(&id).unwrap());
+        assert!(storage.get_plan(&id).unwrap().is_none());
+        assert_eq!(
+            storage.edit_token_matches(&id, Some("secret")).unwrap(),
+            None
+        );
+        assert!(!storage.delete_plan(&id).unwrap());
+    }
 }
@@ -0,0 +1,124 @@
+//! Which Foxhole war new plans and placement counts are filed under.
+//!
+//! The number is either pinned with `WAR_NUMBER` or read from the official War
+//! API, polled in the background so a new war is picked up without a restart.
+//! Until the first answer arrives the war is unknown and only lifetime totals
+//! are counted.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Where the current war number comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WarSource {
+    /// Pinned by the operator.
+    Fixed(u32),
+    /// Polled from the War API rooted at `url`.
+    Api { url: String, every: Duration },
+    /// Neither; only lifetime totals are kept.
+    Unknown,
+}
+
+/// The war being fought now, shared by the resolvers and the poller.
+#[derive(Debug, Default)]
+pub struct CurrentWar(AtomicU32);
+
+impl CurrentWar {
+    /// A war number that never changes. `0` leaves the war unknown.
+    pub fn fixed(number: u32) -> Self {
+        CurrentWar(AtomicU32::new(number))
+    }
+
+    pub fn get(&self) -> Option<u32> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            n => Some(n),
+        }
+    }
+
+    /// Record the war number, returning the one it replaces.
+    fn replace(&self, number: u32) -> Option<u32> {
+        match self.0.swap(number, Ordering::Relaxed) {
+            0 => None,
+            n => Some(n),
+        }
+    }
+}
+
+/// The part of `/worldconquest/war` we need.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarState {
+    pub war_number: u32,
+    /// `WARDENS` or `COLONIALS` once the war is won, `NONE` until then.
+    pub winner: String,
+    /// Milliseconds since the Unix epoch; `None` before the war starts.
+    pub conquest_start_time: Option<i64>,
+    pub conquest_end_time: Option<i64>,
+}
+
+pub async fn fetch_war_state(client: &reqwest::Client, api_base: &str) -> Result<WarState, String> {
+    let url = format!("{}/worldconquest/war", api_base.trim_end_matches('/'));
+    client
+        .get(&url)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {}: {}", url, e))
+}
+
+/// Ask the War API for the current war now and then every `every`.
+pub fn spawn_war_poller(current: Arc<CurrentWar>, api_base: String, every: Duration) {
+    tokio::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            match fetch_war_state(&client, &api_base)
+                .await
+                .map(|s| s.war_number)
+            {
+                Ok(number) => match current.replace(number) {
+                    Some(previous) if previous == number => {}
+                    Some(previous) => tracing::info!(war = number, previous, "New war started"),
+                    None => tracing::info!(war = number, "Current war fetched"),
+                },
+                Err(e) => tracing::warn!(error = %e, "War API poll failed"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_war_tracks_changes() {
+        let war = CurrentWar::default();
+        assert_eq!(war.get(), None);
+        assert_eq!(war.replace(117), None);
+        assert_eq!(war.replace(118), Some(117));
+        assert_eq!(war.get(), Some(118));
+        assert_eq!(CurrentWar::fixed(0).get(), None);
+    }
+
+    #[test]
+    fn test_war_state_parses_api_response() {
+        let json = r#"{"warId":"abc","warNumber":117,"winner":"NONE","conquestStartTime":1700000000000,"conquestEndTime":null,"resistanceStartTime":null,"requiredVictoryTowns":32}"#;
+        let state: WarState = serde_json::from_str(json).unwrap();
+        assert_eq!(state.war_number, 117);
+        assert_eq!(state.winner, "NONE");
+        assert_eq!(state.conquest_start_time, Some(1_700_000_000_000));
+        assert_eq!(state.conquest_end_time, None);
+    }
+}
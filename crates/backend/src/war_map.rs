@@ -0,0 +1,376 @@
+//! Live map data from the Foxhole War API: town halls, relic bases and other
+//! structures on each hex, and which side holds them.
+//!
+//! A background task polls `/worldconquest/maps/{map}/dynamic/public` for every
+//! hex in the current war, sending the last `ETag` so an unchanged hex costs a
+//! 304. Each structure is named after the nearest place on the hex, taken from
+//! the hex's static data, which is fetched once.
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use foxhole_shared::grid;
+use foxhole_shared::models::{Faction, Position, StructureKind};
+use reqwest::header::{ETAG, IF_NONE_MATCH};
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+/// Icon flag the War API sets on bases that count towards victory.
+const FLAG_VICTORY_BASE: u32 = 0x01;
+/// Icon flag the War API sets on towns destroyed by a nuke.
+const FLAG_SCORCHED: u32 = 0x10;
+
+/// Where live map data is polled from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapPollSettings {
+    /// Root of the War API, e.g. `https://war-service-live.foxholeservices.com/api`.
+    pub url: String,
+    pub every: Duration,
+}
+
+/// A structure on a hex, as the planner shows it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapStructure {
+    pub kind: StructureKind,
+    /// `None` while no side holds it.
+    pub team: Option<Faction>,
+    /// Position in meters.
+    pub position: Position,
+    /// The nearest named place on the hex.
+    pub location: Option<String>,
+    pub victory_base: bool,
+    pub scorched: bool,
+}
+
+/// The latest structures known on one hex.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HexData {
+    pub structures: Vec<MapStructure>,
+    /// When the War API last changed the hex (Unix seconds).
+    pub updated_at: i64,
+}
+
+/// Live data per hex, keyed by War API map name.
+#[derive(Debug, Default)]
+pub struct WarMaps(RwLock<HashMap<String, Arc<HexData>>>);
+
+impl WarMaps {
+    pub fn get(&self, map_name: &str) -> Option<Arc<HexData>> {
+        self.0
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(map_name)
+            .cloned()
+    }
+
+    pub fn insert(&self, map_name: String, data: HexData) {
+        self.0
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(map_name, Arc::new(data));
+    }
+}
+
+/// Map which War API icons are worth showing to artillery.
+fn structure_kind(icon_type: u32) -> Option<StructureKind> {
+    match icon_type {
+        56..=58 => Some(StructureKind::TownHall),
+        45..=47 => Some(StructureKind::RelicBase),
+        27 => Some(StructureKind::Keep),
+        8 => Some(StructureKind::ForwardBase),
+        28 => Some(StructureKind::ObservationTower),
+        53 => Some(StructureKind::CoastalGun),
+        59 => Some(StructureKind::StormCannon),
+        60 => Some(StructureKind::IntelCenter),
+        _ => None,
+    }
+}
+
+fn team(team_id: &str) -> Option<Faction> {
+    match team_id {
+        "COLONIALS" => Some(Faction::Colonial),
+        "WARDENS" => Some(Faction::Warden),
+        _ => None,
+    }
+}
+
+/// A point on a hex in the War API's 0–1 coordinates, in meters.
+fn to_meters(x: f64, y: f64) -> Position {
+    Position {
+        x: x.clamp(0.0, 1.0) * grid::MAP_WIDTH_M,
+        y: y.clamp(0.0, 1.0) * grid::MAP_HEIGHT_M,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MapItem {
+    team_id: String,
+    icon_type: u32,
+    x: f64,
+    y: f64,
+    #[serde(default)]
+    flags: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MapTextItem {
+    text: String,
+    x: f64,
+    y: f64,
+}
+
+/// The part of `/worldconquest/maps/{map}/dynamic/public` we need.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DynamicMap {
+    map_items: Vec<MapItem>,
+    /// Milliseconds since the Unix epoch.
+    last_updated: i64,
+}
+
+/// The part of `/worldconquest/maps/{map}/static` we need.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StaticMap {
+    #[serde(default)]
+    map_text_items: Vec<MapTextItem>,
+}
+
+/// A named place on a hex, in meters.
+#[derive(Debug, Clone, PartialEq)]
+struct Place {
+    name: String,
+    position: Position,
+}
+
+fn places(static_map: StaticMap) -> Vec<Place> {
+    static_map
+        .map_text_items
+        .into_iter()
+        .map(|item| Place {
+            position: to_meters(item.x, item.y),
+            name: item.text,
+        })
+        .collect()
+}
+
+fn nearest_place(places: &[Place], position: Position) -> Option<String> {
+    let dist = |p: &Place| (p.position.x - position.x).hypot(p.position.y - position.y);
+    places
+        .iter()
+        .min_by(|a, b| dist(a).total_cmp(&dist(b)))
+        .map(|p| p.name.clone())
+}
+
+fn hex_data(dynamic: DynamicMap, places: &[Place]) -> HexData {
+    let structures = dynamic
+        .map_items
+        .into_iter()
+        .filter_map(|item| {
+            let kind = structure_kind(item.icon_type)?;
+            let position = to_meters(item.x, item.y);
+            Some(MapStructure {
+                kind,
+                team: team(&item.team_id),
+                location: nearest_place(places, position),
+                position,
+                victory_base: item.flags & FLAG_VICTORY_BASE != 0,
+                scorched: item.flags & FLAG_SCORCHED != 0,
+            })
+        })
+        .collect();
+    HexData {
+        structures,
+        updated_at: dynamic.last_updated / 1000,
+    }
+}
+
+async fn get_json<T: for<'de> Deserialize<'de>>(
+    client: &reqwest::Client,
+    url: &str,
+) -> Result<T, String> {
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {}: {}", url, e))
+}
+
+/// Fetch a hex's dynamic data unless it still matches `etag`. Returns the new
+/// `ETag` alongside the data, or `None` when nothing changed.
+async fn fetch_dynamic(
+    client: &reqwest::Client,
+    url: &str,
+    etag: Option<&str>,
+) -> Result<Option<(Option<String>, DynamicMap)>, String> {
+    let mut request = client.get(url);
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    let resp = request
+        .send()
+        .await
+        .and_then(|resp| resp.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+    if resp.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let etag = resp
+        .headers()
+        .get(ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let dynamic = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {}: {}", url, e))?;
+    Ok(Some((etag, dynamic)))
+}
+
+/// Poll the War API for every hex in the current war, now and then every `every`.
+pub fn spawn_map_poller(maps: Arc<WarMaps>, settings: MapPollSettings) {
+    tokio::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        let base = settings.url.trim_end_matches('/').to_string();
+        let mut etags: HashMap<String, String> = HashMap::new();
+        let mut hex_places: HashMap<String, Vec<Place>> = HashMap::new();
+        let mut ticker = tokio::time::interval(settings.every);
+        loop {
+            ticker.tick().await;
+            let names: Vec<String> =
+                match get_json(&client, &format!("{base}/worldconquest/maps")).await {
+                    Ok(names) => names,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "War API map list poll failed");
+                        continue;
+                    }
+                };
+            let mut updated = 0;
+            for name in names {
+                if !hex_places.contains_key(&name) {
+                    let url = format!("{base}/worldconquest/maps/{name}/static");
+                    match get_json::<StaticMap>(&client, &url).await {
+                        Ok(static_map) => {
+                            hex_places.insert(name.clone(), places(static_map));
+                        }
+                        Err(e) => {
+                            tracing::warn!(map = %name, error = %e, "War API static map fetch failed")
+                        }
+                    }
+                }
+                let url = format!("{base}/worldconquest/maps/{name}/dynamic/public");
+                match fetch_dynamic(&client, &url, etags.get(&name).map(String::as_str)).await {
+                    Ok(Some((etag, dynamic))) => {
+                        let places = hex_places.get(&name).map_or(&[][..], Vec::as_slice);
+                        maps.insert(name.clone(), hex_data(dynamic, places));
+                        match etag {
+                            Some(etag) => etags.insert(name, etag),
+                            None => etags.remove(&name),
+                        };
+                        updated += 1;
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!(map = %name, error = %e, "War API map poll failed"),
+                }
+            }
+            tracing::debug!(updated, "Polled War API map data");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DYNAMIC_JSON: &str = r#"{
+        "regionId": 38,
+        "scorchedVictoryTowns": 0,
+        "mapItems": [
+            {"teamId": "WARDENS", "iconType": 56, "x": 0.5, "y": 0.5, "flags": 41},
+            {"teamId": "COLONIALS", "iconType": 46, "x": 0.1, "y": 0.9, "flags": 0},
+            {"teamId": "NONE", "iconType": 17, "x": 0.3, "y": 0.3, "flags": 0},
+            {"teamId": "NONE", "iconType": 27, "x": 0.52, "y": 0.48, "flags": 16}
+        ],
+        "mapItemsC": [],
+        "mapItemsW": [],
+        "mapTextItems": [],
+        "lastUpdated": 1700000000123,
+        "version": 12
+    }"#;
+
+    const STATIC_JSON: &str = r#"{
+        "regionId": 38,
+        "mapItems": [],
+        "mapTextItems": [
+            {"text": "Abandoned Ward", "x": 0.49, "y": 0.51, "mapMarkerType": "Major"},
+            {"text": "Jade Cove", "x": 0.12, "y": 0.85, "mapMarkerType": "Minor"}
+        ],
+        "lastUpdated": 1600000000000,
+        "version": 3
+    }"#;
+
+    #[test]
+    fn test_hex_data_keeps_structures_and_names_them() {
+        let dynamic: DynamicMap = serde_json::from_str(DYNAMIC_JSON).unwrap();
+        let static_map: StaticMap = serde_json::from_str(STATIC_JSON).unwrap();
+        let data = hex_data(dynamic, &places(static_map));
+
+        assert_eq!(data.updated_at, 1_700_000_000);
+        // The refinery (icon 17) isn't a structure the planner shows
+        assert_eq!(data.structures.len(), 3);
+
+        let town = &data.structures[0];
+        assert_eq!(town.kind, StructureKind::TownHall);
+        assert_eq!(town.team, Some(Faction::Warden));
+        assert_eq!(
+            town.position,
+            Position {
+                x: grid::MAP_WIDTH_M / 2.0,
+                y: grid::MAP_HEIGHT_M / 2.0,
+            }
+        );
+        assert_eq!(town.location.as_deref(), Some("Abandoned Ward"));
+        assert!(town.victory_base);
+        assert!(!town.scorched);
+
+        let relic = &data.structures[1];
+        assert_eq!(relic.kind, StructureKind::RelicBase);
+        assert_eq!(relic.team, Some(Faction::Colonial));
+        assert_eq!(relic.location.as_deref(), Some("Jade Cove"));
+
+        let keep = &data.structures[2];
+        assert_eq!(keep.team, None);
+        assert!(keep.scorched);
+        assert!(!keep.victory_base);
+    }
+
+    #[test]
+    fn test_structures_without_static_data_are_unnamed() {
+        let dynamic: DynamicMap = serde_json::from_str(DYNAMIC_JSON).unwrap();
+        let data = hex_data(dynamic, &[]);
+        assert!(data.structures.iter().all(|s| s.location.is_none()));
+    }
+
+    #[test]
+    fn test_war_maps_replaces_hex_data() {
+        let maps = WarMaps::default();
+        assert!(maps.get("AcrithiaHex").is_none());
+        let data = |updated_at| HexData {
+            structures: vec![],
+            updated_at,
+        };
+        maps.insert("AcrithiaHex".to_string(), data(1));
+        maps.insert("AcrithiaHex".to_string(), data(2));
+        assert_eq!(maps.get("AcrithiaHex").unwrap().updated_at, 2);
+    }
+}
@@ -0,0 +1,304 @@
+//! End-of-war reports: once the War API declares a winner, the finished war's
+//! placement stats are posted to a webhook, the same numbers `shitpost-gen`
+//! fetches to write its posts from.
+//!
+//! Each war is reported once. Reported wars are recorded in the database, so a
+//! restart during the resistance phase doesn't post the report again.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::assets::SharedAssets;
+use crate::graphql::{gun_placement_stats, GqlFaction, GqlWeaponPlacementStat};
+use crate::storage::{PlacementScope, Storage};
+use crate::war::{fetch_war_state, WarState};
+
+/// Longest message a Discord webhook accepts, in characters.
+const MESSAGE_LIMIT: usize = 2000;
+
+/// Weapons listed by name; the rest are summed into one line.
+const LISTED_WEAPONS: usize = 10;
+
+/// Where end-of-war reports are posted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarReportSettings {
+    /// Root of the War API, polled for the war's winner.
+    pub url: String,
+    /// Discord (or compatible) webhook the report is posted to.
+    pub webhook: String,
+    pub every: Duration,
+}
+
+/// A finished war's placement stats.
+struct WarStats {
+    gun_placements: Vec<GqlWeaponPlacementStat>,
+    warden: u64,
+    colonial: u64,
+    total: u64,
+    targets: u64,
+    spotters: u64,
+    /// Estimated planner sessions that placed anything.
+    planners: u64,
+}
+
+fn war_stats(storage: &Storage, assets: &SharedAssets, war: u32) -> Result<WarStats, String> {
+    let (mut gun_placements, totals) =
+        gun_placement_stats(storage, &assets.current(), PlacementScope::War(war))?;
+    gun_placements.sort_by_key(|w| std::cmp::Reverse(w.count));
+    Ok(WarStats {
+        gun_placements,
+        warden: totals.warden,
+        colonial: totals.colonial,
+        total: totals.total,
+        targets: storage.get_marker_placement_count("target", PlacementScope::War(war))?,
+        spotters: storage.get_marker_placement_count("spotter", PlacementScope::War(war))?,
+        planners: storage.unique_planners(PlacementScope::War(war))?,
+    })
+}
+
+/// The side that won, or `None` while the war is still being fought.
+fn winner(state: &WarState) -> Option<&'static str> {
+    match state.winner.as_str() {
+        "WARDENS" => Some("Wardens"),
+        "COLONIALS" => Some("Colonials"),
+        _ => None,
+    }
+}
+
+fn faction_name(faction: GqlFaction) -> &'static str {
+    match faction {
+        GqlFaction::Colonial => "Colonial",
+        GqlFaction::Warden => "Warden",
+        GqlFaction::Both => "both sides",
+    }
+}
+
+/// The report's markdown, for a war that has a winner.
+fn format_report(state: &WarState, winner: &str, stats: &WarStats) -> String {
+    let mut out = format!(
+        "**War {} is over: the {} won.**\n",
+        state.war_number, winner
+    );
+    if let (Some(start), Some(end)) = (state.conquest_start_time, state.conquest_end_time) {
+        let secs = (end - start).max(0) / 1000;
+        out.push_str(&format!(
+            "Fought for {} days, {} hours.\n",
+            secs / 86_400,
+            secs % 86_400 / 3600
+        ));
+    }
+    out.push('\n');
+
+    if stats.total == 0 {
+        out.push_str("No guns were placed in the planner this war.\n");
+    } else {
+        out.push_str(&format!(
+            "**Gun placements:** {} Warden, {} Colonial, {} in total\n",
+            stats.warden, stats.colonial, stats.total
+        ));
+        for weapon in stats.gun_placements.iter().take(LISTED_WEAPONS) {
+            out.push_str(&format!(
+                "- {} ({}): {}\n",
+                weapon.display_name,
+                faction_name(weapon.faction),
+                weapon.count
+            ));
+        }
+        let rest = &stats.gun_placements[stats.gun_placements.len().min(LISTED_WEAPONS)..];
+        if !rest.is_empty() {
+            let count: u64 = rest.iter().map(|w| w.count).sum();
+            out.push_str(&format!("- {} other weapons: {}\n", rest.len(), count));
+        }
+    }
+    out.push_str(&format!("**Targets placed:** {}\n", stats.targets));
+    out.push_str(&format!("**Spotters placed:** {}\n", stats.spotters));
+    if stats.planners > 0 {
+        out.push_str(&format!(
+            "About {} gunners planned their fire with the planner.\n",
+            stats.planners
+        ));
+    }
+    out
+}
+
+/// `report` cut into webhook-sized messages between lines.
+fn messages(report: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    for line in report.trim().lines() {
+        let needed = current.chars().count() + line.chars().count() + 1;
+        if !current.is_empty() && needed > MESSAGE_LIMIT {
+            messages.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.trim().is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+async fn post(client: &reqwest::Client, webhook: &str, report: &str) -> Result<(), String> {
+    for content in messages(report) {
+        client
+            .post(webhook)
+            .json(&json!({ "content": content }))
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|e| format!("Failed to post to the report webhook: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Watch the War API for the current war's winner, now and then every
+/// `every`, and post each finished war's report once.
+pub fn spawn_war_reporter(
+    storage: Arc<Storage>,
+    assets: Arc<SharedAssets>,
+    settings: WarReportSettings,
+) {
+    tokio::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+        let mut ticker = tokio::time::interval(settings.every);
+        loop {
+            ticker.tick().await;
+            let state = match fetch_war_state(&client, &settings.url).await {
+                Ok(state) => state,
+                Err(e) => {
+                    tracing::warn!(error = %e, "War API poll for the war report failed");
+                    continue;
+                }
+            };
+            let Some(winner) = winner(&state) else {
+                continue;
+            };
+            let war = state.war_number;
+
+            let (db, shared) = (storage.clone(), assets.clone());
+            let stats = tokio::task::spawn_blocking(move || match db.war_reported_at(war)? {
+                Some(_) => Ok(None),
+                None => war_stats(&db, &shared, war).map(Some),
+            })
+            .await;
+            let stats = match stats {
+                Ok(Ok(Some(stats))) => stats,
+                Ok(Ok(None)) => continue,
+                Ok(Err(e)) => {
+                    tracing::error!(war, error = %e, "Failed to gather war report stats");
+                    continue;
+                }
+                Err(e) => {
+                    tracing::error!(war, error = %e, "War report task panicked");
+                    continue;
+                }
+            };
+
+            let report = format_report(&state, winner, &stats);
+            if let Err(e) = post(&client, &settings.webhook, &report).await {
+                // Tried again on the next poll
+                tracing::warn!(war, error = %e, "War report not posted");
+                continue;
+            }
+            let db = storage.clone();
+            let now = chrono::Utc::now().timestamp();
+            match tokio::task::spawn_blocking(move || db.record_war_report(war, now)).await {
+                Ok(Ok(())) => tracing::info!(war, winner, "War report posted"),
+                Ok(Err(e)) => tracing::error!(war, error = %e, "Failed to record the war report"),
+                Err(e) => tracing::error!(war, error = %e, "War report task panicked"),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(winner: &str) -> WarState {
+        WarState {
+            war_number: 117,
+            winner: winner.to_string(),
+            conquest_start_time: Some(1_700_000_000_000),
+            conquest_end_time: Some(1_700_000_000_000 + (23 * 86_400 + 4 * 3600) * 1000),
+        }
+    }
+
+    fn weapon(name: &str, faction: GqlFaction, count: u64) -> GqlWeaponPlacementStat {
+        GqlWeaponPlacementStat {
+            weapon_slug: name.to_lowercase(),
+            display_name: name.to_string(),
+            faction,
+            count,
+        }
+    }
+
+    #[test]
+    fn test_only_a_won_war_is_reported() {
+        assert_eq!(winner(&state("NONE")), None);
+        assert_eq!(winner(&state("WARDENS")), Some("Wardens"));
+        assert_eq!(winner(&state("COLONIALS")), Some("Colonials"));
+    }
+
+    #[test]
+    fn test_format_report() {
+        let mut gun_placements = vec![
+            weapon("Cremari", GqlFaction::Colonial, 25),
+            weapon("Storm Cannon", GqlFaction::Both, 4),
+        ];
+        gun_placements
+            .extend((0..10).map(|i| weapon(&format!("Mortar {i}"), GqlFaction::Warden, 1)));
+        let stats = WarStats {
+            gun_placements,
+            warden: 14,
+            colonial: 29,
+            total: 39,
+            targets: 100,
+            spotters: 3,
+            planners: 57,
+        };
+        let report = format_report(&state("WARDENS"), "Wardens", &stats);
+        assert!(report
+            .starts_with("**War 117 is over: the Wardens won.**\nFought for 23 days, 4 hours.\n"));
+        assert!(report.contains("**Gun placements:** 14 Warden, 29 Colonial, 39 in total\n"));
+        assert!(report.contains("- Cremari (Colonial): 25\n- Storm Cannon (both sides): 4\n"));
+        // Past the listed weapons the rest are summed up
+        assert!(report.contains("- Mortar 7 (Warden): 1\n- 2 other weapons: 2\n"));
+        assert!(report.contains("**Targets placed:** 100\n"));
+        assert!(report.ends_with(
+            "**Spotters placed:** 3\nAbout 57 gunners planned their fire with the planner.\n"
+        ));
+
+        let empty = WarStats {
+            gun_placements: vec![],
+            warden: 0,
+            colonial: 0,
+            total: 0,
+            targets: 0,
+            spotters: 0,
+            planners: 0,
+        };
+        let report = format_report(&state("COLONIALS"), "Colonials", &empty);
+        assert!(report.contains("No guns were placed in the planner this war."));
+        assert!(!report.contains("gunners"));
+    }
+
+    #[test]
+    fn test_messages_split_between_lines() {
+        let line = "x".repeat(600);
+        let report = [line.as_str(); 5].join("\n");
+        let parts = messages(&report);
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0], [line.as_str(); 3].join("\n"));
+        assert!(parts.iter().all(|m| m.chars().count() <= MESSAGE_LIMIT));
+        assert_eq!(messages("short"), vec!["short"]);
+    }
+}
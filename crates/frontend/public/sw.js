@@ -0,0 +1,97 @@
+// Service worker: keeps the planner usable in the field without a connection.
+//
+// - Pages fall back to the cached app shell; every route serves the same index.
+// - Hashed bundles (/assets) and map images are cached on first use.
+// - Game data (/static/*.json) is served from cache and refreshed behind it.
+// - GraphQL is left alone; the app falls back to local calculations and
+//   queues plan saves itself.
+
+const VERSION = "v1";
+const SHELL_CACHE = `shell-${VERSION}`;
+const ASSET_CACHE = `assets-${VERSION}`;
+const SHELL = ["/", "/manifest.webmanifest", "/icon.svg"];
+
+// The bundles index.html loads, so the app starts offline after one visit
+async function precacheShell() {
+  const shell = await caches.open(SHELL_CACHE);
+  await shell.addAll(SHELL);
+  const index = await shell.match("/");
+  if (!index) return;
+  const html = await index.text();
+  const bundles = [...html.matchAll(/["'](\/assets\/[^"']+)["']/g)].map(
+    (m) => m[1],
+  );
+  const assets = await caches.open(ASSET_CACHE);
+  await assets.addAll([...new Set(bundles)]);
+}
+
+self.addEventListener("install", (event) => {
+  event.waitUntil(precacheShell().then(() => self.skipWaiting()));
+});
+
+self.addEventListener("activate", (event) => {
+  const keep = [SHELL_CACHE, ASSET_CACHE];
+  event.waitUntil(
+    caches
+      .keys()
+      .then((names) =>
+        Promise.all(
+          names.filter((n) => !keep.includes(n)).map((n) => caches.delete(n)),
+        ),
+      )
+      .then(() => self.clients.claim()),
+  );
+});
+
+async function networkFirstPage(request) {
+  const shell = await caches.open(SHELL_CACHE);
+  try {
+    const response = await fetch(request);
+    if (response.ok) await shell.put("/", response.clone());
+    return response;
+  } catch (err) {
+    const cached = await shell.match("/");
+    if (cached) return cached;
+    throw err;
+  }
+}
+
+async function cacheFirst(request) {
+  const cache = await caches.open(ASSET_CACHE);
+  const cached = await cache.match(request);
+  if (cached) return cached;
+  const response = await fetch(request);
+  if (response.ok) await cache.put(request, response.clone());
+  return response;
+}
+
+async function staleWhileRevalidate(request) {
+  const cache = await caches.open(ASSET_CACHE);
+  const cached = await cache.match(request);
+  const refresh = fetch(request).then((response) => {
+    if (response.ok) cache.put(request, response.clone());
+    return response;
+  });
+  if (cached) {
+    refresh.catch(() => {});
+    return cached;
+  }
+  return refresh;
+}
+
+self.addEventListener("fetch", (event) => {
+  const { request } = event;
+  const url = new URL(request.url);
+  if (request.method !== "GET" || url.origin !== self.location.origin) return;
+
+  if (request.mode === "navigate") {
+    event.respondWith(networkFirstPage(request));
+  } else if (
+    url.pathname.startsWith("/assets/") ||
+    url.pathname.startsWith("/static/images/")
+  ) {
+    event.respondWith(cacheFirst(request));
+  } else if (url.pathname.startsWith("/static/") || SHELL.includes(url.pathname)) {
+    event.respondWith(staleWhileRevalidate(request));
+  }
+});
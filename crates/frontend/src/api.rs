@@ -1,14 +1,76 @@
+use std::io::Write;
+
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::models::{
+    CalibrationPoint, CustomRing, Deployment, FiringSolution, ImpactZone, IntelMarkers,
+    MapCalibration, MapViewPreset, Position, RangeRings, RegistrationPoint, ShotRecord,
+    StructureKind, TargetStatus, WeaponMount, WeaponProfile,
+};
+use foxhole_shared::overlay::{HeatCell, MarkerLabels};
+use foxhole_shared::research::ImpactObservation;
+use foxhole_shared::template::{BatteryTemplate, TemplateGun};
+use foxhole_shared::terrain::TerrainMask;
 use serde::{Deserialize, Serialize};
 
+use crate::offline;
+
+/// Request bodies at least this large are gzipped before sending.
+const GZIP_MIN_BYTES: usize = 1024;
+
+/// Largest request body the server accepts, measured after decompression.
+pub const MAX_REQUEST_BYTES: usize = 256 * 1024;
+
+/// Bytes of a serialized payload, and how many actually go over the wire.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PayloadSize {
+    pub raw: usize,
+    pub sent: usize,
+}
+
+/// Gzip a request body if it's big enough to benefit. Returns the body and
+/// whether it was compressed.
+fn encode_body(body: Vec<u8>) -> (Vec<u8>, bool) {
+    if body.len() < GZIP_MIN_BYTES {
+        return (body, false);
+    }
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    match encoder.write_all(&body).and_then(|_| encoder.finish()) {
+        Ok(gz) if gz.len() < body.len() => (gz, true),
+        _ => (body, false),
+    }
+}
+
+/// Size of the given mutation variables as they would be sent.
+pub fn payload_size(variables: &serde_json::Value) -> PayloadSize {
+    let body = serde_json::to_vec(variables).unwrap_or_default();
+    let raw = body.len();
+    let (sent, _) = encode_body(body);
+    PayloadSize {
+        raw,
+        sent: sent.len(),
+    }
+}
+
+/// The `WeaponProfile` enum value for `profile`.
+fn profile_name(profile: WeaponProfile) -> &'static str {
+    match profile {
+        WeaponProfile::Live => "LIVE",
+        WeaponProfile::Devbranch => "DEVBRANCH",
+    }
+}
+
 /// Build the variables JSON for a calculate query.
+#[allow(clippy::too_many_arguments)]
 pub fn build_calculate_variables(
     gun_x: f64,
     gun_y: f64,
     target_x: f64,
     target_y: f64,
     weapon_id: &str,
+    ammo: &str,
     wind_direction: Option<f64>,
     wind_strength: Option<u32>,
+    profile: WeaponProfile,
 ) -> serde_json::Value {
     let wind = match (wind_direction, wind_strength) {
         (Some(dir), Some(str)) if str > 0 => {
@@ -22,7 +84,9 @@ pub fn build_calculate_variables(
             "gunPosition": { "x": gun_x, "y": gun_y },
             "targetPosition": { "x": target_x, "y": target_y },
             "weaponId": weapon_id,
-            "wind": wind
+            "ammo": ammo,
+            "wind": wind,
+            "profile": profile_name(profile)
         }
     })
 }
@@ -33,12 +97,21 @@ pub fn build_create_plan_variables(
     name: &str,
     map_id: &str,
     weapon_ids: &[String],
+    gun_ammo: &[String],
     gun_positions: &[(f64, f64)],
     target_positions: &[(f64, f64)],
     spotter_positions: &[(f64, f64)],
     gun_target_indices: &[Option<usize>],
+    labels: &MarkerLabels,
+    target_statuses: &[TargetStatus],
     wind_direction: Option<f64>,
     wind_strength: Option<u32>,
+    calibration: Option<&MapCalibration>,
+    shot_log: &[ShotRecord],
+    registrations: &[RegistrationPoint],
+    range_rings: &RangeRings,
+    intel: &IntelMarkers,
+    profile: WeaponProfile,
 ) -> serde_json::Value {
     let to_json = |positions: &[(f64, f64)]| -> serde_json::Value {
         positions
@@ -58,21 +131,112 @@ pub fn build_create_plan_variables(
             "name": name,
             "mapId": map_id,
             "weaponIds": weapon_ids,
+            "gunAmmo": gun_ammo,
             "gunPositions": to_json(gun_positions),
             "targetPositions": to_json(target_positions),
             "spotterPositions": to_json(spotter_positions),
             "gunTargetIndices": indices_json,
+            "gunLabels": labels.guns,
+            "targetLabels": labels.targets,
+            "spotterLabels": labels.spotters,
+            "targetStatuses": target_statuses,
             "windDirection": wind_direction,
-            "windStrength": wind_strength
+            "windStrength": wind_strength,
+            "calibration": calibration.map(calibration_input),
+            "shotLog": shot_log.iter().map(shot_record_input).collect::<Vec<_>>(),
+            "registrations": registrations.iter().map(registration_input).collect::<Vec<_>>(),
+            "gunRingsHidden": range_rings.hidden,
+            "customRings": range_rings.custom,
+            "enemyGunPositions": intel.enemy_guns,
+            "fobPositions": intel.fobs,
+            "bunkerPositions": intel.bunkers,
+            "noFireZonePositions": intel.no_fire_zones,
+            "noFireZoneRadii": intel.no_fire_radii,
+            "profile": profile
         }
     })
 }
 
-/// Build a shareable plan URL from origin and plan ID.
+/// `CalibrationInput` JSON for a custom map image.
+fn calibration_input(calibration: &MapCalibration) -> serde_json::Value {
+    let points: Vec<serde_json::Value> = calibration
+        .points
+        .iter()
+        .map(|p| {
+            serde_json::json!({
+                "imageX": p.px.x,
+                "imageY": p.px.y,
+                "position": { "x": p.meters.x, "y": p.meters.y }
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "imageUrl": calibration.image_url,
+        "width": calibration.width,
+        "height": calibration.height,
+        "points": points
+    })
+}
+
+/// `ShotRecordInput` JSON for one logged shot.
+fn shot_record_input(shot: &ShotRecord) -> serde_json::Value {
+    serde_json::json!({
+        "gun": shot.gun as i32,
+        "target": shot.target.map(|t| t as i32),
+        "azimuth": shot.azimuth,
+        "distance": shot.distance,
+        "result": shot.result
+    })
+}
+
+/// `RegistrationPointInput` JSON for one registration point (meters).
+fn registration_input(point: &RegistrationPoint) -> serde_json::Value {
+    serde_json::json!({
+        "gun": point.gun as i32,
+        "name": point.name,
+        "position": { "x": point.position.x, "y": point.position.y },
+        "azimuth": point.azimuth,
+        "distance": point.distance
+    })
+}
+
+/// Path of a stock map's image.
+pub fn map_image_url(file_name: &str) -> String {
+    format!("/static/images/maps/{}.webp", file_name)
+}
+
+/// Build a shareable plan URL from origin and plan ID or slug.
 pub fn build_plan_url(origin: &str, plan_id: &str) -> String {
     format!("{}/plan/{}", origin, plan_id)
 }
 
+/// Query parameter that opens a plan in the read-only view.
+const READONLY_QUERY: &str = "readonly=1";
+
+/// A plan link that opens read-only, for handing a finished plan to gun crews.
+pub fn readonly_plan_url(plan_url: &str) -> String {
+    format!("{}?{}", plan_url, READONLY_QUERY)
+}
+
+/// Whether a page's query string (e.g. `?readonly=1`) asks for the read-only view.
+pub fn is_readonly_query(search: &str) -> bool {
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .any(|param| param == READONLY_QUERY || param == "readonly")
+}
+
+/// The plan ID in a pasted plan link ("https://…/plan/abc-123?x") or a bare ID.
+pub fn plan_id_from_link(text: &str) -> Option<String> {
+    let text = text.trim();
+    let id = match text.rsplit_once("/plan/") {
+        Some((_, rest)) => rest.split(['?', '#', '/']).next().unwrap_or_default(),
+        None => text,
+    };
+    (!id.is_empty() && !id.contains(|c: char| c.is_whitespace() || c == '/'))
+        .then(|| id.to_string())
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct GraphQLRequest {
     pub query: String,
@@ -101,18 +265,55 @@ fn api_url() -> String {
 async fn query<T: for<'de> Deserialize<'de>>(
     query_str: &str,
     variables: Option<serde_json::Value>,
+) -> Result<T, String> {
+    send_query(query_str, variables, None, None).await
+}
+
+/// Run a `track*Placement` mutation, sending this tab's anonymous session id
+/// so repeat placements by one visitor can be counted once.
+async fn track_query<T: for<'de> Deserialize<'de>>(
+    query_str: &str,
+    variables: serde_json::Value,
+) -> Result<T, String> {
+    let session = tracking_session_id();
+    send_query(query_str, Some(variables), None, Some(&session)).await
+}
+
+/// Run an operator query, sending `admin_token` as a bearer token.
+async fn admin_query<T: for<'de> Deserialize<'de>>(
+    admin_token: &str,
+    query_str: &str,
+) -> Result<T, String> {
+    let data: AdminResponse<T> = send_query(query_str, None, Some(admin_token), None).await?;
+    Ok(data.admin)
+}
+
+async fn send_query<T: for<'de> Deserialize<'de>>(
+    query_str: &str,
+    variables: Option<serde_json::Value>,
+    bearer: Option<&str>,
+    session: Option<&str>,
 ) -> Result<T, String> {
     let req = GraphQLRequest {
         query: query_str.to_string(),
         variables,
     };
 
-    let resp = reqwest::Client::new()
+    let body = serde_json::to_vec(&req).map_err(|e| e.to_string())?;
+    let (body, gzipped) = encode_body(body);
+    let mut builder = reqwest::Client::new()
         .post(api_url())
-        .json(&req)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?;
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if gzipped {
+        builder = builder.header(reqwest::header::CONTENT_ENCODING, "gzip");
+    }
+    if let Some(token) = bearer {
+        builder = builder.bearer_auth(token);
+    }
+    if let Some(session) = session {
+        builder = builder.header(foxhole_shared::models::TRACKING_SESSION_HEADER, session);
+    }
+    let resp = builder.body(body).send().await.map_err(|e| e.to_string())?;
 
     let gql_resp: GraphQLResponse<T> = resp.json().await.map_err(|e| e.to_string())?;
 
@@ -125,6 +326,21 @@ async fn query<T: for<'de> Deserialize<'de>>(
     gql_resp.data.ok_or_else(|| "No data returned".to_string())
 }
 
+/// Run a read-only query, keeping its last answer on this device under
+/// `cache_key` to fall back on when the server can't be reached.
+async fn cached_query<T: for<'de> Deserialize<'de>>(
+    cache_key: &str,
+    query_str: &str,
+) -> Result<T, String> {
+    match query::<serde_json::Value>(query_str, None).await {
+        Ok(data) => {
+            offline::remember(cache_key, &data);
+            serde_json::from_value(data).map_err(|e| e.to_string())
+        }
+        Err(e) => offline::recall(cache_key).ok_or(e),
+    }
+}
+
 // Types mirroring the GraphQL schema
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -133,6 +349,17 @@ pub struct MapData {
     pub display_name: String,
     pub file_name: String,
     pub active: bool,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub default_view: Option<MapViewPreset>,
+}
+
+impl MapData {
+    /// Pixel-to-meter scale for this map's image.
+    pub fn scale(&self) -> MapScale {
+        MapScale::for_image(self.width as f64, self.height as f64)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -147,6 +374,81 @@ pub struct WeaponData {
     pub acc_radius_max: f64,
     pub wind_drift_min: f64,
     pub wind_drift_max: f64,
+    #[serde(default)]
+    pub flight_time_min: f64,
+    #[serde(default)]
+    pub flight_time_max: f64,
+    #[serde(default)]
+    pub ammo: Vec<AmmoData>,
+    #[serde(default)]
+    pub crew: Option<u32>,
+    #[serde(default)]
+    pub deploy_time: Option<f64>,
+    #[serde(default)]
+    pub mount: Option<WeaponMount>,
+    #[serde(default)]
+    pub area_fire: bool,
+}
+
+/// A weapon's figures when firing one of its special shells.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmmoData {
+    pub name: String,
+    pub min_range: f64,
+    pub max_range: f64,
+    pub acc_radius_min: f64,
+    pub acc_radius_max: f64,
+    pub wind_drift_min: f64,
+    pub wind_drift_max: f64,
+    #[serde(default)]
+    pub flight_time_min: f64,
+    #[serde(default)]
+    pub flight_time_max: f64,
+}
+
+impl WeaponData {
+    /// The weapon's crew and setup, as far as the server knows them.
+    pub fn deployment(&self) -> Deployment {
+        Deployment {
+            crew: self.crew,
+            deploy_time: self.deploy_time,
+            mount: self.mount,
+        }
+    }
+
+    /// Convert to the shared weapon model for client-side calculations.
+    pub fn to_weapon(&self) -> foxhole_shared::models::Weapon {
+        use foxhole_shared::models::{AmmoType, Faction, Weapon};
+        let faction = match self.faction.as_str() {
+            "COLONIAL" => Faction::Colonial,
+            "WARDEN" => Faction::Warden,
+            _ => Faction::Both,
+        };
+        Weapon {
+            faction,
+            display_name: self.display_name.clone(),
+            min_range: self.min_range,
+            max_range: self.max_range,
+            acc_radius: [self.acc_radius_min, self.acc_radius_max],
+            wind_drift: [self.wind_drift_min, self.wind_drift_max],
+            flight_time: [self.flight_time_min, self.flight_time_max],
+            ammo: self
+                .ammo
+                .iter()
+                .map(|a| AmmoType {
+                    name: a.name.clone(),
+                    min_range: Some(a.min_range),
+                    max_range: Some(a.max_range),
+                    acc_radius: Some([a.acc_radius_min, a.acc_radius_max]),
+                    wind_drift: Some([a.wind_drift_min, a.wind_drift_max]),
+                    flight_time: Some([a.flight_time_min, a.flight_time_max]),
+                })
+                .collect(),
+            deployment: self.deployment(),
+            area_fire: self.area_fire,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -159,6 +461,25 @@ pub struct FiringSolutionData {
     pub wind_adjusted_azimuth: Option<f64>,
     pub wind_adjusted_distance: Option<f64>,
     pub wind_offset_meters: Option<f64>,
+    pub time_of_flight: Option<f64>,
+    #[serde(default)]
+    pub impact_zone: Option<ImpactZone>,
+}
+
+impl From<FiringSolution> for FiringSolutionData {
+    fn from(sol: FiringSolution) -> Self {
+        FiringSolutionData {
+            azimuth: sol.azimuth,
+            distance: sol.distance,
+            in_range: sol.in_range,
+            accuracy_radius: sol.accuracy_radius,
+            wind_adjusted_azimuth: sol.wind_adjusted_azimuth,
+            wind_adjusted_distance: sol.wind_adjusted_distance,
+            wind_offset_meters: sol.wind_offset_meters,
+            time_of_flight: sol.time_of_flight,
+            impact_zone: sol.impact_zone,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Deserialize)]
@@ -168,6 +489,17 @@ pub struct PositionData {
     pub y: f64,
 }
 
+/// A structure on the live War API map (position in meters).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapStructureData {
+    pub kind: StructureKind,
+    /// `"COLONIAL"` or `"WARDEN"`; `None` while unclaimed.
+    pub team: Option<String>,
+    pub position: PositionData,
+    pub location: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PlanData {
@@ -176,13 +508,194 @@ pub struct PlanData {
     pub map_id: String,
     #[serde(default)]
     pub weapon_ids: Vec<String>,
+    #[serde(default)]
+    pub gun_ammo: Vec<String>,
     pub gun_positions: Vec<PositionData>,
     pub target_positions: Vec<PositionData>,
     pub spotter_positions: Vec<PositionData>,
     #[serde(default)]
+    pub enemy_gun_positions: Vec<PositionData>,
+    #[serde(default)]
+    pub fob_positions: Vec<PositionData>,
+    #[serde(default)]
+    pub bunker_positions: Vec<PositionData>,
+    #[serde(default)]
+    pub no_fire_zone_positions: Vec<PositionData>,
+    /// Radius (m) of each no-fire zone; zones past the end use the default.
+    #[serde(default)]
+    pub no_fire_zone_radii: Vec<f64>,
+    #[serde(default)]
     pub gun_target_indices: Vec<Option<i32>>,
+    #[serde(default)]
+    pub gun_labels: Vec<String>,
+    #[serde(default)]
+    pub target_labels: Vec<String>,
+    #[serde(default)]
+    pub spotter_labels: Vec<String>,
+    #[serde(default)]
+    pub target_statuses: Vec<TargetStatus>,
     pub wind_direction: Option<f64>,
     pub wind_strength: u32,
+    #[serde(default)]
+    pub version: u32,
+    /// Whether saving over the plan needs its edit token.
+    #[serde(default)]
+    pub protected: bool,
+    /// Only set on the plan returned by `createPlan`.
+    #[serde(default)]
+    pub edit_token: Option<String>,
+    /// Short link, e.g. `brave-falcon-42`; plans made before slugs have none.
+    #[serde(default)]
+    pub slug: Option<String>,
+    /// Only requested for version history entries.
+    #[serde(default)]
+    pub updated_at: Option<String>,
+    /// Custom map image the plan was drawn on.
+    #[serde(default)]
+    pub calibration: Option<CalibrationData>,
+    #[serde(default)]
+    pub shot_log: Vec<ShotRecordData>,
+    #[serde(default)]
+    pub registrations: Vec<RegistrationPointData>,
+    #[serde(default)]
+    pub gun_rings_hidden: Vec<bool>,
+    #[serde(default)]
+    pub custom_rings: Vec<CustomRing>,
+    /// Game patch of the weapon data the plan was last saved with.
+    #[serde(default)]
+    pub weapon_data_version: Option<String>,
+}
+
+impl PlanData {
+    /// Warning for a plan saved with other weapon data than the planner's
+    /// `current` data, whose ranges may have changed since.
+    pub fn weapon_data_notice(&self, current: Option<&str>) -> Option<String> {
+        match (self.weapon_data_version.as_deref(), current) {
+            (Some(saved), Some(current)) if saved != current => Some(format!(
+                "This plan was saved with weapon data from {saved}; the planner now uses {current}. Ranges may have changed, so check the firing solutions."
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// A named point a gun has registered on, with its stored solution.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistrationPointData {
+    pub gun: i32,
+    pub name: String,
+    pub position: PositionData,
+    pub azimuth: f64,
+    pub distance: f64,
+}
+
+impl RegistrationPointData {
+    pub fn to_registration(&self) -> RegistrationPoint {
+        RegistrationPoint {
+            gun: self.gun.max(0) as usize,
+            name: self.name.clone(),
+            position: Position {
+                x: self.position.x,
+                y: self.position.y,
+            },
+            azimuth: self.azimuth,
+            distance: self.distance,
+        }
+    }
+}
+
+/// A logged adjustment round.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShotRecordData {
+    pub gun: i32,
+    pub target: Option<i32>,
+    pub azimuth: f64,
+    pub distance: f64,
+    #[serde(default)]
+    pub result: String,
+}
+
+impl ShotRecordData {
+    pub fn to_shot_record(&self) -> ShotRecord {
+        ShotRecord {
+            gun: self.gun.max(0) as usize,
+            target: self.target.map(|t| t.max(0) as usize),
+            azimuth: self.azimuth,
+            distance: self.distance,
+            result: self.result.clone(),
+        }
+    }
+}
+
+/// A pixel on a custom map image and where it is on the map (meters).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibrationPointData {
+    pub image_x: f64,
+    pub image_y: f64,
+    pub position: PositionData,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibrationData {
+    pub image_url: String,
+    pub width: u32,
+    pub height: u32,
+    pub points: Vec<CalibrationPointData>,
+}
+
+impl CalibrationData {
+    /// The shared model; `None` unless there are exactly two points.
+    pub fn to_calibration(&self) -> Option<MapCalibration> {
+        let point = |p: &CalibrationPointData| CalibrationPoint {
+            px: Position {
+                x: p.image_x,
+                y: p.image_y,
+            },
+            meters: Position {
+                x: p.position.x,
+                y: p.position.y,
+            },
+        };
+        let [a, b] = self.points.as_slice() else {
+            return None;
+        };
+        Some(MapCalibration {
+            image_url: self.image_url.clone(),
+            width: self.width,
+            height: self.height,
+            points: [point(a), point(b)],
+        })
+    }
+}
+
+/// Markers of one kind that only one side of a conflicting save has (meters).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkerDiffData {
+    pub only_mine: Vec<PositionData>,
+    pub only_theirs: Vec<PositionData>,
+}
+
+/// Someone else saved the plan first; `theirs` is what's stored now.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanConflictData {
+    pub theirs: PlanData,
+    pub guns: MarkerDiffData,
+    pub targets: MarkerDiffData,
+    pub spotters: MarkerDiffData,
+    pub name_changed: bool,
+    pub wind_changed: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdatePlanResult {
+    pub plan: Option<PlanData>,
+    pub conflict: Option<PlanConflictData>,
 }
 
 // API functions
@@ -193,41 +706,340 @@ pub struct MapsResponse {
 }
 
 pub async fn fetch_maps() -> Result<Vec<MapData>, String> {
-    let resp: MapsResponse = query(
-        r#"query { maps(activeOnly: true) { displayName fileName active } }"#,
-        None,
+    let resp: MapsResponse = cached_query(
+        "offline_maps",
+        r#"query { maps(activeOnly: true) { displayName fileName active width height defaultView { zoom center { x y } } } }"#,
     )
     .await?;
     Ok(resp.maps)
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TerrainMaskResponse {
+    terrain_mask: Option<Vec<String>>,
+}
+
+/// A map's water and impassable ground, kept for offline use; `None` when
+/// the server has no mask for it.
+pub async fn fetch_terrain_mask(map_id: &str) -> Result<Option<TerrainMask>, String> {
+    let resp: TerrainMaskResponse = cached_query(
+        &format!("offline_terrain_mask_{map_id}"),
+        &format!(
+            "query {{ terrainMask(mapId: {}) }}",
+            serde_json::Value::from(map_id)
+        ),
+    )
+    .await?;
+    resp.terrain_mask
+        .map(|rows| TerrainMask::from_rows(rows.iter().map(String::as_str)))
+        .transpose()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MapStructuresResponse {
+    map_structures: Option<MapStructuresData>,
+}
+
+#[derive(Deserialize)]
+struct MapStructuresData {
+    structures: Vec<MapStructureData>,
+}
+
+/// Live structures on a map; empty when the server has no War API data for it.
+pub async fn fetch_map_structures(map_id: &str) -> Result<Vec<MapStructureData>, String> {
+    let resp: MapStructuresResponse = query(
+        r#"query($mapId: String!) { mapStructures(mapId: $mapId) { structures { kind team position { x y } location } } }"#,
+        Some(serde_json::json!({ "mapId": map_id })),
+    )
+    .await?;
+    Ok(resp
+        .map_structures
+        .map(|m| m.structures)
+        .unwrap_or_default())
+}
+
+/// Guns or targets created plans put in one grid cell.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HeatmapCellData {
+    pub col: usize,
+    pub row: usize,
+    pub count: u64,
+}
+
+impl HeatmapCellData {
+    pub fn to_heat_cell(&self) -> HeatCell {
+        HeatCell {
+            col: self.col,
+            row: self.row,
+            count: self.count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct PlacementHeatmapData {
+    pub guns: Vec<HeatmapCellData>,
+    pub targets: Vec<HeatmapCellData>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlacementHeatmapResponse {
+    placement_heatmap: PlacementHeatmapData,
+}
+
+/// Where the community's plans put guns and targets on a map, per grid cell.
+pub async fn fetch_placement_heatmap(map_id: &str) -> Result<PlacementHeatmapData, String> {
+    let resp: PlacementHeatmapResponse = query(
+        r#"query($mapId: String!) { placementHeatmap(mapId: $mapId) { guns { col row count } targets { col row count } } }"#,
+        Some(serde_json::json!({ "mapId": map_id })),
+    )
+    .await?;
+    Ok(resp.placement_heatmap)
+}
+
+/// The freshest wind someone reported for a map.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindReportData {
+    pub direction: f64,
+    pub strength: u32,
+    pub reported_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindReportResponse {
+    wind_report: Option<WindReportData>,
+}
+
+/// The latest wind report for a map; `None` when there's no recent one.
+pub async fn fetch_wind_report(map_id: &str) -> Result<Option<WindReportData>, String> {
+    let resp: WindReportResponse = query(
+        r#"query($mapId: String!) { windReport(mapId: $mapId) { direction strength reportedAt } }"#,
+        Some(serde_json::json!({ "mapId": map_id })),
+    )
+    .await?;
+    Ok(resp.wind_report)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportWindResponse {
+    report_wind: WindReportData,
+}
+
+/// Share the wind observed in-game on a map.
+pub async fn report_wind(
+    map_id: &str,
+    direction: f64,
+    strength: u32,
+) -> Result<WindReportData, String> {
+    let resp: ReportWindResponse = query(
+        r#"mutation($mapId: String!, $wind: GqlWindInput!) {
+            reportWind(mapId: $mapId, wind: $wind) { direction strength reportedAt }
+        }"#,
+        Some(serde_json::json!({
+            "mapId": map_id,
+            "wind": { "direction": direction, "strength": strength },
+        })),
+    )
+    .await?;
+    Ok(resp.report_wind)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubmitImpactObservationResponse {
+    submit_impact_observation: bool,
+}
+
+/// Report where a round landed, for the accuracy research.
+pub async fn submit_impact_observation(observation: &ImpactObservation) -> Result<bool, String> {
+    let resp: SubmitImpactObservationResponse = query(
+        r#"mutation($input: ImpactObservationInput!) {
+            submitImpactObservation(input: $input)
+        }"#,
+        Some(serde_json::json!({
+            "input": {
+                "weaponId": observation.weapon_slug,
+                "azimuth": observation.azimuth,
+                "distance": observation.distance,
+                "windDirection": observation.wind_direction,
+                "windStrength": observation.wind_strength,
+                "compensatedDrift": observation.compensated_drift,
+                "long": observation.long,
+                "right": observation.right,
+            },
+        })),
+    )
+    .await?;
+    Ok(resp.submit_impact_observation)
+}
+
+/// A shared battery template as the API returns it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatteryTemplateData {
+    pub id: String,
+    pub name: String,
+    pub guns: Vec<TemplateGunData>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateGunData {
+    pub weapon_id: String,
+    pub offset_x: f64,
+    pub offset_y: f64,
+}
+
+impl BatteryTemplateData {
+    pub fn to_template(&self) -> BatteryTemplate {
+        BatteryTemplate {
+            name: self.name.clone(),
+            guns: self
+                .guns
+                .iter()
+                .map(|g| TemplateGun {
+                    weapon_id: g.weapon_id.clone(),
+                    offset: Position {
+                        x: g.offset_x,
+                        y: g.offset_y,
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Build the variables JSON for a share battery template mutation.
+pub fn build_share_template_variables(template: &BatteryTemplate) -> serde_json::Value {
+    let guns: Vec<serde_json::Value> = template
+        .guns
+        .iter()
+        .map(|g| {
+            serde_json::json!({
+                "weaponId": g.weapon_id,
+                "offsetX": g.offset.x,
+                "offsetY": g.offset.y
+            })
+        })
+        .collect();
+    serde_json::json!({ "input": { "name": template.name, "guns": guns } })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShareBatteryTemplateResponse {
+    share_battery_template: BatteryTemplateData,
+}
+
+/// Store a template on the server; returns the id others load it with.
+pub async fn share_battery_template(template: &BatteryTemplate) -> Result<String, String> {
+    let resp: ShareBatteryTemplateResponse = query(
+        r#"mutation($input: BatteryTemplateInput!) {
+            shareBatteryTemplate(input: $input) { id }
+        }"#,
+        Some(build_share_template_variables(template)),
+    )
+    .await?;
+    Ok(resp.share_battery_template.id)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatteryTemplateResponse {
+    battery_template: Option<BatteryTemplateData>,
+}
+
+pub async fn fetch_battery_template(id: &str) -> Result<Option<BatteryTemplate>, String> {
+    let resp: BatteryTemplateResponse = query(
+        r#"query($id: ID!) {
+            batteryTemplate(id: $id) { id name guns { weaponId offsetX offsetY } }
+        }"#,
+        Some(serde_json::json!({ "id": id })),
+    )
+    .await?;
+    Ok(resp
+        .battery_template
+        .as_ref()
+        .map(BatteryTemplateData::to_template))
+}
+
 #[derive(Deserialize)]
 pub struct WeaponsResponse {
     pub weapons: Vec<WeaponData>,
 }
 
-pub async fn fetch_weapons() -> Result<Vec<WeaponData>, String> {
-    let resp: WeaponsResponse = query(
-        r#"query { weapons { slug faction displayName minRange maxRange accRadiusMin accRadiusMax windDriftMin windDriftMax } }"#,
-        None,
+/// The weapons of `profile`; each profile is kept for offline use separately.
+pub async fn fetch_weapons(profile: WeaponProfile) -> Result<Vec<WeaponData>, String> {
+    let cache_key = match profile {
+        WeaponProfile::Live => "offline_weapons".to_string(),
+        other => format!("offline_weapons_{}", profile_name(other).to_lowercase()),
+    };
+    let resp: WeaponsResponse = cached_query(
+        &cache_key,
+        &format!(
+            r#"query {{ weapons(profile: {}) {{ slug faction displayName minRange maxRange accRadiusMin accRadiusMax windDriftMin windDriftMax flightTimeMin flightTimeMax ammo {{ name minRange maxRange accRadiusMin accRadiusMax windDriftMin windDriftMax flightTimeMin flightTimeMax }} crew deployTime mount areaFire }} }}"#,
+            profile_name(profile)
+        ),
     )
     .await?;
     Ok(resp.weapons)
 }
 
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeaponProfileData {
+    pub profile: WeaponProfile,
+    #[serde(default)]
+    pub game_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeaponProfilesResponse {
+    pub weapon_profiles: Vec<WeaponProfileData>,
+}
+
+/// The weapon profiles the server has figures for.
+pub async fn fetch_weapon_profiles() -> Result<Vec<WeaponProfileData>, String> {
+    let resp: WeaponProfilesResponse =
+        query("query { weaponProfiles { profile gameVersion } }", None).await?;
+    Ok(resp.weapon_profiles)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameVersionResponse {
+    pub game_version: Option<String>,
+}
+
+/// Game patch the server's weapon data is from, if it says.
+pub async fn fetch_game_version() -> Result<Option<String>, String> {
+    let resp: GameVersionResponse = query("query { gameVersion }", None).await?;
+    Ok(resp.game_version)
+}
+
 #[derive(Deserialize)]
 pub struct CalculateResponse {
     pub calculate: FiringSolutionData,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn calculate(
     gun_x: f64,
     gun_y: f64,
     target_x: f64,
     target_y: f64,
     weapon_id: &str,
+    ammo: &str,
     wind_direction: Option<f64>,
     wind_strength: Option<u32>,
+    profile: WeaponProfile,
 ) -> Result<FiringSolutionData, String> {
     let variables = build_calculate_variables(
         gun_x,
@@ -235,8 +1047,10 @@ pub async fn calculate(
         target_x,
         target_y,
         weapon_id,
+        ammo,
         wind_direction,
         wind_strength,
+        profile,
     );
 
     let resp: CalculateResponse = query(
@@ -244,6 +1058,8 @@ pub async fn calculate(
             calculate(input: $input) {
                 azimuth distance inRange accuracyRadius
                 windAdjustedAzimuth windAdjustedDistance windOffsetMeters
+                timeOfFlight
+                impactZone { length width azimuth }
             }
         }"#,
         Some(variables),
@@ -258,42 +1074,131 @@ pub struct CreatePlanResponse {
     pub create_plan: PlanData,
 }
 
+/// Build the variables JSON for an update plan mutation.
 #[allow(clippy::too_many_arguments)]
-pub async fn create_plan(
+pub fn build_update_plan_variables(
+    id: &str,
+    edit_token: Option<&str>,
+    base_version: u32,
     name: &str,
     map_id: &str,
     weapon_ids: &[String],
+    gun_ammo: &[String],
     gun_positions: &[(f64, f64)],
     target_positions: &[(f64, f64)],
     spotter_positions: &[(f64, f64)],
     gun_target_indices: &[Option<usize>],
+    labels: &MarkerLabels,
+    target_statuses: &[TargetStatus],
     wind_direction: Option<f64>,
     wind_strength: Option<u32>,
-) -> Result<PlanData, String> {
-    let variables = build_create_plan_variables(
+    calibration: Option<&MapCalibration>,
+    shot_log: &[ShotRecord],
+    registrations: &[RegistrationPoint],
+    range_rings: &RangeRings,
+    intel: &IntelMarkers,
+    profile: WeaponProfile,
+) -> serde_json::Value {
+    let mut variables = build_create_plan_variables(
         name,
         map_id,
         weapon_ids,
+        gun_ammo,
         gun_positions,
         target_positions,
         spotter_positions,
         gun_target_indices,
+        labels,
+        target_statuses,
         wind_direction,
         wind_strength,
+        calibration,
+        shot_log,
+        registrations,
+        range_rings,
+        intel,
+        profile,
     );
+    variables["id"] = serde_json::json!(id);
+    variables["editToken"] = serde_json::json!(edit_token);
+    variables["baseVersion"] = serde_json::json!(base_version);
+    variables
+}
 
-    let resp: CreatePlanResponse = query(
-        r#"mutation CreatePlan($input: CreatePlanInput!) {
-            createPlan(input: $input) {
-                id name mapId weaponIds
-                gunPositions { x y } targetPositions { x y } spotterPositions { x y }
-                gunTargetIndices windDirection windStrength
-            }
-        }"#,
-        Some(variables),
-    )
-    .await?;
-    Ok(resp.create_plan)
+#[derive(Deserialize)]
+pub struct UpdatePlanResponse {
+    #[serde(rename = "updatePlan")]
+    pub update_plan: UpdatePlanResult,
+}
+
+/// A plan save ready to send: the mutation variables from
+/// [`build_create_plan_variables`] or [`build_update_plan_variables`]. Kept
+/// whole so a save made offline can be sent once the connection is back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PlanSave {
+    Create(serde_json::Value),
+    Update(serde_json::Value),
+}
+
+impl PlanSave {
+    /// Id of the stored plan this save overwrites; `None` for a new plan.
+    pub fn plan_id(&self) -> Option<&str> {
+        match self {
+            PlanSave::Create(_) => None,
+            PlanSave::Update(variables) => variables["id"].as_str(),
+        }
+    }
+}
+
+/// Send a plan save. Creating always returns the new plan; updating with a
+/// stale `baseVersion` comes back as a conflict, not an error.
+pub async fn save_plan(save: &PlanSave) -> Result<UpdatePlanResult, String> {
+    match save {
+        PlanSave::Create(variables) => {
+            let resp: CreatePlanResponse = query(
+                r#"mutation CreatePlan($input: CreatePlanInput!) {
+                    createPlan(input: $input) {
+                        id name mapId weaponIds
+                        gunPositions { x y } targetPositions { x y } spotterPositions { x y } enemyGunPositions { x y } fobPositions { x y } bunkerPositions { x y } noFireZonePositions { x y } noFireZoneRadii
+                        gunTargetIndices gunLabels targetLabels spotterLabels targetStatuses windDirection windStrength version slug protected editToken calibration { imageUrl width height points { imageX imageY position { x y } } } shotLog { gun target azimuth distance result } registrations { gun name position { x y } azimuth distance } gunAmmo gunRingsHidden customRings { anchor index radius label }
+                    }
+                }"#,
+                Some(variables.clone()),
+            )
+            .await?;
+            Ok(UpdatePlanResult {
+                plan: Some(resp.create_plan),
+                conflict: None,
+            })
+        }
+        PlanSave::Update(variables) => {
+            let resp: UpdatePlanResponse = query(
+                r#"mutation UpdatePlan($id: ID!, $editToken: String, $baseVersion: Int!, $input: CreatePlanInput!) {
+                    updatePlan(id: $id, editToken: $editToken, baseVersion: $baseVersion, input: $input) {
+                        plan {
+                            id name mapId weaponIds
+                            gunPositions { x y } targetPositions { x y } spotterPositions { x y } enemyGunPositions { x y } fobPositions { x y } bunkerPositions { x y } noFireZonePositions { x y } noFireZoneRadii
+                            gunTargetIndices gunLabels targetLabels spotterLabels targetStatuses windDirection windStrength version slug protected calibration { imageUrl width height points { imageX imageY position { x y } } } shotLog { gun target azimuth distance result } registrations { gun name position { x y } azimuth distance } gunAmmo gunRingsHidden customRings { anchor index radius label }
+                        }
+                        conflict {
+                            theirs {
+                                id name mapId weaponIds
+                                gunPositions { x y } targetPositions { x y } spotterPositions { x y } enemyGunPositions { x y } fobPositions { x y } bunkerPositions { x y } noFireZonePositions { x y } noFireZoneRadii
+                                gunTargetIndices gunLabels targetLabels spotterLabels targetStatuses windDirection windStrength version protected calibration { imageUrl width height points { imageX imageY position { x y } } } shotLog { gun target azimuth distance result } registrations { gun name position { x y } azimuth distance } gunAmmo gunRingsHidden customRings { anchor index radius label }
+                            }
+                            guns { onlyMine { x y } onlyTheirs { x y } }
+                            targets { onlyMine { x y } onlyTheirs { x y } }
+                            spotters { onlyMine { x y } onlyTheirs { x y } }
+                            nameChanged windChanged
+                        }
+                    }
+                }"#,
+                Some(variables.clone()),
+            )
+            .await?;
+            Ok(resp.update_plan)
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -308,24 +1213,26 @@ pub struct TrackGunPlacementResponse {
 }
 
 /// Fire-and-forget gun placement tracking. Maps empty slugs to "unassigned".
-pub fn track_gun_placement_fire(weapon_slug: &str) {
+/// `map_id` is the map the gun went on, when it's a new gun.
+pub fn track_gun_placement_fire(weapon_slug: &str, map_id: Option<&str>) {
     let slug = if weapon_slug.is_empty() {
         foxhole_shared::models::UNASSIGNED_WEAPON.to_string()
     } else {
         weapon_slug.to_string()
     };
+    let map_id = map_id.map(str::to_string);
     wasm_bindgen_futures::spawn_local(async move {
-        let _ = track_gun_placement(&slug).await;
+        let _ = track_gun_placement(&slug, map_id.as_deref()).await;
     });
 }
 
-pub async fn track_gun_placement(weapon_slug: &str) -> Result<bool, String> {
-    let variables = serde_json::json!({ "weaponSlug": weapon_slug });
-    let resp: TrackGunPlacementResponse = query(
-        r#"mutation TrackGunPlacement($weaponSlug: String!) {
-            trackGunPlacement(weaponSlug: $weaponSlug)
+pub async fn track_gun_placement(weapon_slug: &str, map_id: Option<&str>) -> Result<bool, String> {
+    let variables = serde_json::json!({ "weaponSlug": weapon_slug, "mapId": map_id });
+    let resp: TrackGunPlacementResponse = track_query(
+        r#"mutation TrackGunPlacement($weaponSlug: String!, $mapId: String) {
+            trackGunPlacement(weaponSlug: $weaponSlug, mapId: $mapId)
         }"#,
-        Some(variables),
+        variables,
     )
     .await?;
     Ok(resp.track_gun_placement)
@@ -343,50 +1250,475 @@ pub struct TrackSpotterPlacementResponse {
     pub track_spotter_placement: bool,
 }
 
-pub fn track_target_placement_fire() {
+pub fn track_target_placement_fire(map_id: &str) {
+    let map_id = map_id.to_string();
     wasm_bindgen_futures::spawn_local(async move {
-        let _ = track_target_placement().await;
+        let _ = track_target_placement(&map_id).await;
     });
 }
 
-pub async fn track_target_placement() -> Result<bool, String> {
-    let resp: TrackTargetPlacementResponse =
-        query(r#"mutation { trackTargetPlacement }"#, None).await?;
+pub async fn track_target_placement(map_id: &str) -> Result<bool, String> {
+    let resp: TrackTargetPlacementResponse = track_query(
+        r#"mutation TrackTargetPlacement($mapId: String) {
+            trackTargetPlacement(mapId: $mapId)
+        }"#,
+        serde_json::json!({ "mapId": map_id }),
+    )
+    .await?;
     Ok(resp.track_target_placement)
 }
 
-pub fn track_spotter_placement_fire() {
-    wasm_bindgen_futures::spawn_local(async move {
-        let _ = track_spotter_placement().await;
-    });
+pub fn track_spotter_placement_fire(map_id: &str) {
+    let map_id = map_id.to_string();
+    wasm_bindgen_futures::spawn_local(async move {
+        let _ = track_spotter_placement(&map_id).await;
+    });
+}
+
+pub async fn track_spotter_placement(map_id: &str) -> Result<bool, String> {
+    let resp: TrackSpotterPlacementResponse = track_query(
+        r#"mutation TrackSpotterPlacement($mapId: String) {
+            trackSpotterPlacement(mapId: $mapId)
+        }"#,
+        serde_json::json!({ "mapId": map_id }),
+    )
+    .await?;
+    Ok(resp.track_spotter_placement)
+}
+
+pub async fn fetch_plan(id: &str) -> Result<Option<PlanData>, String> {
+    let variables = serde_json::json!({ "id": id });
+
+    let resp: FetchPlanResponse = query(
+        r#"query FetchPlan($id: ID!) {
+            plan(id: $id) {
+                id name mapId weaponIds
+                gunPositions { x y } targetPositions { x y } spotterPositions { x y } enemyGunPositions { x y } fobPositions { x y } bunkerPositions { x y } noFireZonePositions { x y } noFireZoneRadii
+                gunTargetIndices gunLabels targetLabels spotterLabels targetStatuses windDirection windStrength version slug protected calibration { imageUrl width height points { imageX imageY position { x y } } } shotLog { gun target azimuth distance result } registrations { gun name position { x y } azimuth distance } gunAmmo gunRingsHidden customRings { anchor index radius label } weaponDataVersion
+            }
+        }"#,
+        Some(variables),
+    )
+    .await?;
+    Ok(resp.plan)
+}
+
+#[derive(Deserialize)]
+pub struct ForkPlanResponse {
+    #[serde(rename = "forkPlan")]
+    pub fork_plan: PlanData,
+}
+
+/// Copy a saved plan into a new one. The copy comes back with its own edit token.
+pub async fn fork_plan(id: &str, new_name: Option<&str>) -> Result<PlanData, String> {
+    let variables = serde_json::json!({ "id": id, "newName": new_name });
+
+    let resp: ForkPlanResponse = query(
+        r#"mutation ForkPlan($id: ID!, $newName: String) {
+            forkPlan(id: $id, newName: $newName) {
+                id name mapId weaponIds
+                gunPositions { x y } targetPositions { x y } spotterPositions { x y } enemyGunPositions { x y } fobPositions { x y } bunkerPositions { x y } noFireZonePositions { x y } noFireZoneRadii
+                gunTargetIndices gunLabels targetLabels spotterLabels targetStatuses windDirection windStrength version slug protected editToken calibration { imageUrl width height points { imageX imageY position { x y } } } shotLog { gun target azimuth distance result } registrations { gun name position { x y } azimuth distance } gunAmmo gunRingsHidden customRings { anchor index radius label }
+            }
+        }"#,
+        Some(variables),
+    )
+    .await?;
+    Ok(resp.fork_plan)
+}
+
+#[derive(Deserialize)]
+pub struct PlanVersionsResponse {
+    #[serde(rename = "planVersions")]
+    pub plan_versions: Vec<PlanData>,
+}
+
+/// Earlier saved versions of a plan, newest first.
+pub async fn fetch_plan_versions(id: &str) -> Result<Vec<PlanData>, String> {
+    let variables = serde_json::json!({ "id": id });
+
+    let resp: PlanVersionsResponse = query(
+        r#"query PlanVersions($id: ID!) {
+            planVersions(id: $id) {
+                id name mapId weaponIds
+                gunPositions { x y } targetPositions { x y } spotterPositions { x y } enemyGunPositions { x y } fobPositions { x y } bunkerPositions { x y } noFireZonePositions { x y } noFireZoneRadii
+                gunTargetIndices gunLabels targetLabels spotterLabels targetStatuses windDirection windStrength version protected updatedAt calibration { imageUrl width height points { imageX imageY position { x y } } } shotLog { gun target azimuth distance result } registrations { gun name position { x y } azimuth distance } gunAmmo gunRingsHidden customRings { anchor index radius label }
+            }
+        }"#,
+        Some(variables),
+    )
+    .await?;
+    Ok(resp.plan_versions)
+}
+
+#[derive(Deserialize)]
+pub struct RestorePlanVersionResponse {
+    #[serde(rename = "restorePlanVersion")]
+    pub restore_plan_version: PlanData,
+}
+
+/// Save an earlier version back as the plan's newest version.
+pub async fn restore_plan_version(
+    id: &str,
+    version: u32,
+    edit_token: Option<&str>,
+) -> Result<PlanData, String> {
+    let variables = serde_json::json!({ "id": id, "version": version, "editToken": edit_token });
+
+    let resp: RestorePlanVersionResponse = query(
+        r#"mutation RestorePlanVersion($id: ID!, $version: Int!, $editToken: String) {
+            restorePlanVersion(id: $id, version: $version, editToken: $editToken) {
+                id name mapId weaponIds
+                gunPositions { x y } targetPositions { x y } spotterPositions { x y } enemyGunPositions { x y } fobPositions { x y } bunkerPositions { x y } noFireZonePositions { x y } noFireZoneRadii
+                gunTargetIndices gunLabels targetLabels spotterLabels targetStatuses windDirection windStrength version protected calibration { imageUrl width height points { imageX imageY position { x y } } } shotLog { gun target azimuth distance result } registrations { gun name position { x y } azimuth distance } gunAmmo gunRingsHidden customRings { anchor index radius label }
+            }
+        }"#,
+        Some(variables),
+    )
+    .await?;
+    Ok(resp.restore_plan_version)
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeaponPlacementData {
+    pub weapon_slug: String,
+    pub display_name: String,
+    /// `COLONIAL`, `WARDEN` or `BOTH`.
+    pub faction: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct FactionPlacementData {
+    pub colonial: u64,
+    pub warden: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct MarkerPlacementData {
+    pub targets: u64,
+    pub spotters: u64,
+}
+
+/// Placements with one visitor's repeats on a day counted once.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DedupedPlacementData {
+    pub guns: u64,
+    pub targets: u64,
+    pub spotters: u64,
+}
+
+/// Guns, targets and spotters placed on one map, over all wars.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MapPlacementData {
+    pub map_id: String,
+    pub display_name: String,
+    pub guns: u64,
+    pub targets: u64,
+    pub spotters: u64,
+}
+
+/// Usage statistics for the public stats page.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsData {
+    pub war: Option<u32>,
+    pub current_war: Option<u32>,
+    pub wars: Vec<u32>,
+    pub total_plans: u64,
+    pub gun_placements: Vec<WeaponPlacementData>,
+    pub gun_placement_totals: FactionPlacementData,
+    pub marker_placements: MarkerPlacementData,
+    pub deduped_placements: DedupedPlacementData,
+    pub unique_planners: u64,
+    pub placements_by_map: Vec<MapPlacementData>,
+}
+
+#[derive(Deserialize)]
+pub struct StatsResponse {
+    pub stats: StatsData,
+}
+
+/// Placement statistics for one war, or all of them when `war` is `None`.
+pub async fn fetch_stats(war: Option<u32>) -> Result<StatsData, String> {
+    let variables = serde_json::json!({ "war": war });
+
+    let resp: StatsResponse = query(
+        r#"query Stats($war: Int) {
+            stats(war: $war) {
+                war currentWar wars totalPlans
+                gunPlacements { weaponSlug displayName faction count }
+                gunPlacementTotals { colonial warden total }
+                markerPlacements { targets spotters }
+                dedupedPlacements { guns targets spotters } uniquePlanners
+                placementsByMap { mapId displayName guns targets spotters }
+            }
+        }"#,
+        Some(variables),
+    )
+    .await?;
+    Ok(resp.stats)
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlanLockData {
+    pub acquired: bool,
+    pub expires_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct AcquirePlanLockResponse {
+    #[serde(rename = "acquirePlanLock")]
+    pub acquire_plan_lock: PlanLockData,
+}
+
+/// Random id for this browser tab, kept in sessionStorage so a reload keeps the lock.
+pub fn lock_session_id() -> String {
+    tab_id("lock_session_id")
+}
+
+/// Random id for this browser tab, sent with placement tracking. Kept apart
+/// from the lock id so tracked placements can't be tied to a plan.
+fn tracking_session_id() -> String {
+    tab_id("tracking_session_id")
+}
+
+/// Random 32-hex-digit id stored under `key` in sessionStorage, made on first use.
+fn tab_id(key: &str) -> String {
+    let window = web_sys::window();
+    let storage = window
+        .as_ref()
+        .and_then(|w| w.session_storage().ok().flatten());
+    if let Some(id) = storage
+        .as_ref()
+        .and_then(|s| s.get_item(key).ok().flatten())
+    {
+        return id;
+    }
+    let mut bytes = [0u8; 16];
+    if let Some(crypto) = window.as_ref().and_then(|w| w.crypto().ok()) {
+        let _ = crypto.get_random_values_with_u8_array(&mut bytes);
+    }
+    let id: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    if let Some(storage) = storage {
+        let _ = storage.set_item(key, &id);
+    }
+    id
+}
+
+pub async fn acquire_plan_lock(
+    plan_id: &str,
+    session_id: &str,
+    takeover: bool,
+    edit_token: Option<&str>,
+) -> Result<PlanLockData, String> {
+    let variables = serde_json::json!({
+        "planId": plan_id,
+        "sessionId": session_id,
+        "takeover": takeover,
+        "editToken": edit_token,
+    });
+
+    let resp: AcquirePlanLockResponse = query(
+        r#"mutation AcquirePlanLock($planId: ID!, $sessionId: String!, $takeover: Boolean, $editToken: String) {
+            acquirePlanLock(planId: $planId, sessionId: $sessionId, takeover: $takeover, editToken: $editToken) {
+                acquired expiresAt
+            }
+        }"#,
+        Some(variables),
+    )
+    .await?;
+    Ok(resp.acquire_plan_lock)
+}
+
+fn edit_token_key(plan_id: &str) -> String {
+    format!("edit_token:{}", plan_id)
+}
+
+/// Edit token for a plan this browser created, if any (kept in localStorage).
+pub fn load_edit_token(plan_id: &str) -> Option<String> {
+    let storage: Option<web_sys::Storage> =
+        web_sys::window().and_then(|w| w.local_storage().ok().flatten());
+    storage.and_then(|s| s.get_item(&edit_token_key(plan_id)).ok().flatten())
+}
+
+pub fn save_edit_token(plan_id: &str, token: &str) {
+    let storage: Option<web_sys::Storage> =
+        web_sys::window().and_then(|w| w.local_storage().ok().flatten());
+    if let Some(storage) = storage {
+        let _ = storage.set_item(&edit_token_key(plan_id), token);
+    }
+}
+
+const ADMIN_TOKEN_KEY: &str = "admin_token";
+
+/// Admin token entered on the admin page, kept in sessionStorage for this tab.
+pub fn load_admin_token() -> Option<String> {
+    let storage: Option<web_sys::Storage> =
+        web_sys::window().and_then(|w| w.session_storage().ok().flatten());
+    storage.and_then(|s| s.get_item(ADMIN_TOKEN_KEY).ok().flatten())
+}
+
+pub fn save_admin_token(token: Option<&str>) {
+    let storage: Option<web_sys::Storage> =
+        web_sys::window().and_then(|w| w.session_storage().ok().flatten());
+    if let Some(storage) = storage {
+        let _ = match token {
+            Some(token) => storage.set_item(ADMIN_TOKEN_KEY, token),
+            None => storage.remove_item(ADMIN_TOKEN_KEY),
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupStatsData {
+    pub plans_expired: u64,
+    pub bytes_reclaimed: u64,
+    pub last_run_at: Option<String>,
+    pub retention_days: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminStatusData {
+    pub total_plans: u64,
+    pub db_size_bytes: u64,
+    pub schema_version: u64,
+    pub cleanup: CleanupStatsData,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupReportData {
+    pub plans_deleted: u64,
+    pub bytes_reclaimed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AssetsReportData {
+    pub maps: usize,
+    pub weapons: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupReportData {
+    pub path: String,
+    pub size_bytes: u64,
+}
+
+/// Operator results arrive under the `admin` field.
+#[derive(Deserialize)]
+pub struct AdminResponse<T> {
+    pub admin: T,
+}
+
+#[derive(Deserialize)]
+pub struct AdminStatusResponse {
+    pub status: AdminStatusData,
+}
+
+#[derive(Deserialize)]
+pub struct PurgeExpiredPlansResponse {
+    #[serde(rename = "purgeExpiredPlans")]
+    pub purge_expired_plans: CleanupReportData,
+}
+
+#[derive(Deserialize)]
+pub struct ReloadAssetsResponse {
+    #[serde(rename = "reloadAssets")]
+    pub reload_assets: AssetsReportData,
+}
+
+#[derive(Deserialize)]
+pub struct ResetStatsResponse {
+    #[serde(rename = "resetStats")]
+    pub reset_stats: u64,
+}
+
+#[derive(Deserialize)]
+pub struct BackupResponse {
+    pub backup: BackupReportData,
+}
+
+pub async fn fetch_admin_status(admin_token: &str) -> Result<AdminStatusData, String> {
+    let resp: AdminStatusResponse = admin_query(
+        admin_token,
+        r#"query AdminStatus {
+            admin {
+                status {
+                    totalPlans dbSizeBytes schemaVersion
+                    cleanup { plansExpired bytesReclaimed lastRunAt retentionDays }
+                }
+            }
+        }"#,
+    )
+    .await?;
+    Ok(resp.status)
+}
+
+pub async fn purge_expired_plans(admin_token: &str) -> Result<CleanupReportData, String> {
+    let resp: PurgeExpiredPlansResponse = admin_query(
+        admin_token,
+        r#"mutation PurgeExpiredPlans {
+            admin { purgeExpiredPlans { plansDeleted bytesReclaimed } }
+        }"#,
+    )
+    .await?;
+    Ok(resp.purge_expired_plans)
+}
+
+pub async fn reload_assets(admin_token: &str) -> Result<AssetsReportData, String> {
+    let resp: ReloadAssetsResponse = admin_query(
+        admin_token,
+        r#"mutation ReloadAssets { admin { reloadAssets { maps weapons } } }"#,
+    )
+    .await?;
+    Ok(resp.reload_assets)
 }
 
-pub async fn track_spotter_placement() -> Result<bool, String> {
-    let resp: TrackSpotterPlacementResponse =
-        query(r#"mutation { trackSpotterPlacement }"#, None).await?;
-    Ok(resp.track_spotter_placement)
+/// Zero the placement statistics. Returns how many counters were cleared.
+pub async fn reset_stats(admin_token: &str) -> Result<u64, String> {
+    let resp: ResetStatsResponse = admin_query(
+        admin_token,
+        r#"mutation ResetStats { admin { resetStats } }"#,
+    )
+    .await?;
+    Ok(resp.reset_stats)
 }
 
-pub async fn fetch_plan(id: &str) -> Result<Option<PlanData>, String> {
-    let variables = serde_json::json!({ "id": id });
-
-    let resp: FetchPlanResponse = query(
-        r#"query FetchPlan($id: ID!) {
-            plan(id: $id) {
-                id name mapId weaponIds
-                gunPositions { x y } targetPositions { x y } spotterPositions { x y }
-                gunTargetIndices windDirection windStrength
-            }
-        }"#,
-        Some(variables),
+pub async fn backup_database(admin_token: &str) -> Result<BackupReportData, String> {
+    let resp: BackupResponse = admin_query(
+        admin_token,
+        r#"mutation BackupDatabase { admin { backup { path sizeBytes } } }"#,
     )
     .await?;
-    Ok(resp.plan)
+    Ok(resp.backup)
+}
+
+/// Fire-and-forget lock release when leaving a plan.
+pub fn release_plan_lock_fire(plan_id: &str, session_id: &str) {
+    let variables = serde_json::json!({ "planId": plan_id, "sessionId": session_id });
+    wasm_bindgen_futures::spawn_local(async move {
+        let _: Result<serde_json::Value, String> = query(
+            r#"mutation ReleasePlanLock($planId: ID!, $sessionId: String!) {
+                releasePlanLock(planId: $planId, sessionId: $sessionId)
+            }"#,
+            Some(variables),
+        )
+        .await;
+    });
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use foxhole_shared::models::RingAnchor;
 
     // --- GraphQL request serialization ---
 
@@ -411,16 +1743,65 @@ mod tests {
         assert!(json.get("variables").is_none());
     }
 
+    #[test]
+    fn test_small_bodies_are_sent_as_is() {
+        let (body, gzipped) = encode_body(b"{}".to_vec());
+        assert!(!gzipped);
+        assert_eq!(body, b"{}");
+    }
+
+    #[test]
+    fn test_large_bodies_are_gzipped() {
+        let guns: Vec<(f64, f64)> = (0..200).map(|i| (i as f64, 500.0)).collect();
+        let variables = build_create_plan_variables(
+            "Barrage",
+            "deadlands",
+            &vec!["storm-cannon".to_string(); guns.len()],
+            &[],
+            &guns,
+            &[],
+            &[],
+            &vec![None; guns.len()],
+            &MarkerLabels::default(),
+            &[],
+            None,
+            None,
+            None,
+            &[],
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
+        );
+        let raw = serde_json::to_vec(&variables).unwrap();
+        let (body, gzipped) = encode_body(raw.clone());
+        assert!(gzipped);
+
+        let mut decoded = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(&body[..]), &mut decoded)
+            .unwrap();
+        assert_eq!(decoded, raw);
+
+        let size = payload_size(&variables);
+        assert_eq!(size.raw, raw.len());
+        assert_eq!(size.sent, body.len());
+        assert!(size.sent < size.raw);
+    }
+
     // --- Response deserialization ---
 
     #[test]
     fn test_maps_response_deserializes() {
-        let json = r#"{"maps":[{"displayName":"Deadlands","fileName":"deadlands","active":true}]}"#;
+        let json = r#"{"maps":[{"displayName":"Deadlands","fileName":"deadlands","active":true,"width":1024,"height":888}]}"#;
         let resp: MapsResponse = serde_json::from_str(json).unwrap();
         assert_eq!(resp.maps.len(), 1);
         assert_eq!(resp.maps[0].display_name, "Deadlands");
         assert_eq!(resp.maps[0].file_name, "deadlands");
         assert!(resp.maps[0].active);
+        assert_eq!(
+            resp.maps[0].scale().meters_per_px_x(),
+            2.0 * MapScale::STANDARD.meters_per_px_x()
+        );
     }
 
     #[test]
@@ -432,13 +1813,40 @@ mod tests {
         assert_eq!(resp.weapons[0].min_range, 400.0);
     }
 
+    #[test]
+    fn test_weapon_data_to_weapon() {
+        let json = r#"{"weapons":[{"slug":"storm-cannon","faction":"WARDEN","displayName":"Storm Cannon","minRange":400.0,"maxRange":1000.0,"accRadiusMin":50.0,"accRadiusMax":50.0,"windDriftMin":20.0,"windDriftMax":50.0,"crew":1,"deployTime":null,"mount":"EMPLACED"}]}"#;
+        let resp: WeaponsResponse = serde_json::from_str(json).unwrap();
+        let weapon = resp.weapons[0].to_weapon();
+        assert_eq!(weapon.deployment.crew, Some(1));
+        assert_eq!(weapon.deployment.mount, Some(WeaponMount::Emplaced));
+        assert_eq!(weapon.faction, foxhole_shared::models::Faction::Warden);
+        assert_eq!(weapon.max_range, 1000.0);
+        assert_eq!(weapon.wind_drift, [20.0, 50.0]);
+        assert_eq!(weapon.slug(), "storm-cannon");
+    }
+
+    #[test]
+    fn test_admin_status_response_deserializes() {
+        let json = r#"{"admin":{"status":{"totalPlans":12,"dbSizeBytes":4096,"schemaVersion":1,"cleanup":{"plansExpired":3,"bytesReclaimed":512,"lastRunAt":null,"retentionDays":90.0}}}}"#;
+        let resp: AdminResponse<AdminStatusResponse> = serde_json::from_str(json).unwrap();
+        let status = resp.admin.status;
+        assert_eq!(status.total_plans, 12);
+        assert_eq!(status.schema_version, 1);
+        assert_eq!(status.cleanup.plans_expired, 3);
+        assert_eq!(status.cleanup.last_run_at, None);
+        assert_eq!(status.cleanup.retention_days, Some(90.0));
+    }
+
     #[test]
     fn test_firing_solution_deserializes() {
-        let json = r#"{"calculate":{"azimuth":45.0,"distance":200.0,"inRange":true,"accuracyRadius":15.0,"windAdjustedAzimuth":44.5,"windAdjustedDistance":201.0,"windOffsetMeters":8.0}}"#;
+        let json = r#"{"calculate":{"azimuth":45.0,"distance":200.0,"inRange":true,"accuracyRadius":15.0,"windAdjustedAzimuth":44.5,"windAdjustedDistance":201.0,"windOffsetMeters":8.0,"timeOfFlight":6.5,"impactZone":{"length":30.0,"width":15.0,"azimuth":44.5}}}"#;
         let resp: CalculateResponse = serde_json::from_str(json).unwrap();
         assert!((resp.calculate.azimuth - 45.0).abs() < 1e-9);
         assert!(resp.calculate.in_range);
         assert_eq!(resp.calculate.wind_adjusted_azimuth, Some(44.5));
+        assert_eq!(resp.calculate.time_of_flight, Some(6.5));
+        assert_eq!(resp.calculate.impact_zone.unwrap().length, 30.0);
     }
 
     #[test]
@@ -447,6 +1855,8 @@ mod tests {
         let resp: CalculateResponse = serde_json::from_str(json).unwrap();
         assert!(resp.calculate.wind_adjusted_azimuth.is_none());
         assert!(resp.calculate.wind_offset_meters.is_none());
+        assert!(resp.calculate.time_of_flight.is_none());
+        assert!(resp.calculate.impact_zone.is_none());
     }
 
     #[test]
@@ -480,6 +1890,36 @@ mod tests {
         assert_eq!(plan.gun_target_indices, vec![Some(0), None]);
     }
 
+    #[test]
+    fn test_weapon_data_notice() {
+        let json = r#"{"plan":{"id":"abc-123","name":"Plan","mapId":"deadlands","gunPositions":[],"targetPositions":[],"spotterPositions":[],"windDirection":null,"windStrength":0,"weaponDataVersion":"2026-08-01"}}"#;
+        let resp: FetchPlanResponse = serde_json::from_str(json).unwrap();
+        let plan = resp.plan.unwrap();
+        assert_eq!(plan.weapon_data_version.as_deref(), Some("2026-08-01"));
+        let notice = plan.weapon_data_notice(Some("2026-10-18")).unwrap();
+        assert!(notice.contains("from 2026-08-01; the planner now uses 2026-10-18"));
+        assert_eq!(plan.weapon_data_notice(Some("2026-08-01")), None);
+        // Nothing to compare against: no warning
+        assert_eq!(plan.weapon_data_notice(None), None);
+        let unstamped = PlanData {
+            weapon_data_version: None,
+            ..plan
+        };
+        assert_eq!(unstamped.weapon_data_notice(Some("2026-10-18")), None);
+    }
+
+    #[test]
+    fn test_weapon_profiles_response_deserializes() {
+        let json = r#"{"weaponProfiles":[{"profile":"LIVE","gameVersion":"2026-10-18"},{"profile":"DEVBRANCH","gameVersion":null}]}"#;
+        let resp: WeaponProfilesResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.weapon_profiles[0].profile, WeaponProfile::Live);
+        assert_eq!(
+            resp.weapon_profiles[0].game_version.as_deref(),
+            Some("2026-10-18")
+        );
+        assert_eq!(resp.weapon_profiles[1].profile, WeaponProfile::Devbranch);
+    }
+
     #[test]
     fn test_plan_data_null() {
         let json = r#"{"plan":null}"#;
@@ -499,25 +1939,57 @@ mod tests {
 
     #[test]
     fn test_build_calculate_variables_no_wind() {
-        let vars = build_calculate_variables(10.0, 20.0, 30.0, 40.0, "storm-cannon", None, None);
+        let vars = build_calculate_variables(
+            10.0,
+            20.0,
+            30.0,
+            40.0,
+            "storm-cannon",
+            "",
+            None,
+            None,
+            WeaponProfile::Live,
+        );
         assert_eq!(vars["input"]["gunPosition"]["x"], 10.0);
         assert_eq!(vars["input"]["gunPosition"]["y"], 20.0);
         assert_eq!(vars["input"]["targetPosition"]["x"], 30.0);
         assert_eq!(vars["input"]["weaponId"], "storm-cannon");
+        assert_eq!(vars["input"]["profile"], "LIVE");
         assert!(vars["input"]["wind"].is_null());
     }
 
     #[test]
     fn test_build_calculate_variables_with_wind() {
-        let vars =
-            build_calculate_variables(0.0, 0.0, 100.0, 100.0, "mortar", Some(270.0), Some(3));
+        let vars = build_calculate_variables(
+            0.0,
+            0.0,
+            100.0,
+            100.0,
+            "mortar",
+            "Shrapnel",
+            Some(270.0),
+            Some(3),
+            WeaponProfile::Devbranch,
+        );
+        assert_eq!(vars["input"]["ammo"], "Shrapnel");
+        assert_eq!(vars["input"]["profile"], "DEVBRANCH");
         assert_eq!(vars["input"]["wind"]["direction"], 270.0);
         assert_eq!(vars["input"]["wind"]["strength"], 3);
     }
 
     #[test]
     fn test_build_calculate_variables_zero_strength_wind_is_null() {
-        let vars = build_calculate_variables(0.0, 0.0, 100.0, 100.0, "mortar", Some(90.0), Some(0));
+        let vars = build_calculate_variables(
+            0.0,
+            0.0,
+            100.0,
+            100.0,
+            "mortar",
+            "",
+            Some(90.0),
+            Some(0),
+            WeaponProfile::Live,
+        );
         assert!(vars["input"]["wind"].is_null());
     }
 
@@ -527,12 +1999,21 @@ mod tests {
             "My Plan",
             "deadlands",
             &["storm-cannon".to_string()],
+            &[],
             &[(10.0, 20.0)],
             &[(30.0, 40.0)],
             &[],
             &[Some(0)],
+            &MarkerLabels::default(),
+            &[],
             Some(180.0),
             Some(2),
+            None,
+            &[],
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
         );
         assert_eq!(vars["input"]["name"], "My Plan");
         assert_eq!(vars["input"]["mapId"], "deadlands");
@@ -554,8 +2035,17 @@ mod tests {
             &[],
             &[],
             &[],
+            &[],
+            &MarkerLabels::default(),
+            &[],
+            None,
             None,
             None,
+            &[],
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
         );
         assert_eq!(vars["input"]["gunPositions"].as_array().unwrap().len(), 0);
         assert_eq!(
@@ -575,17 +2065,376 @@ mod tests {
             "Mixed",
             "deadlands",
             &["mortar".to_string(), "mortar".to_string()],
+            &[],
             &[(10.0, 20.0), (50.0, 60.0)],
             &[(30.0, 40.0)],
             &[],
             &[Some(0), None],
+            &MarkerLabels::default(),
+            &[],
             None,
             None,
+            None,
+            &[],
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
         );
         assert_eq!(vars["input"]["gunTargetIndices"][0], 0);
         assert!(vars["input"]["gunTargetIndices"][1].is_null());
     }
 
+    #[test]
+    fn test_create_plan_response_keeps_edit_token() {
+        let json = r#"{"createPlan":{"id":"abc-123","name":"Mine","mapId":"deadlands","weaponIds":[],"gunPositions":[],"targetPositions":[],"spotterPositions":[],"gunTargetIndices":[],"windDirection":null,"windStrength":0,"version":0,"protected":true,"editToken":"f00d","slug":"brave-falcon-42"}}"#;
+        let resp: CreatePlanResponse = serde_json::from_str(json).unwrap();
+        assert!(resp.create_plan.protected);
+        assert_eq!(resp.create_plan.edit_token.as_deref(), Some("f00d"));
+        assert_eq!(resp.create_plan.slug.as_deref(), Some("brave-falcon-42"));
+        assert_eq!(edit_token_key("abc-123"), "edit_token:abc-123");
+    }
+
+    #[test]
+    fn test_fork_plan_response_keeps_edit_token() {
+        let json = r#"{"forkPlan":{"id":"def-456","name":"Template (copy)","mapId":"deadlands","gunPositions":[],"targetPositions":[],"spotterPositions":[],"windDirection":null,"windStrength":0,"version":0,"protected":true,"editToken":"beef"}}"#;
+        let resp: ForkPlanResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.fork_plan.id, "def-456");
+        assert_eq!(resp.fork_plan.edit_token.as_deref(), Some("beef"));
+    }
+
+    #[test]
+    fn test_plan_versions_response_deserializes() {
+        let json = r#"{"planVersions":[{"id":"abc-123","name":"Old","mapId":"deadlands","weaponIds":[],"gunPositions":[{"x":1.0,"y":2.0}],"targetPositions":[],"spotterPositions":[],"gunTargetIndices":[null],"windDirection":null,"windStrength":0,"version":2,"protected":false,"updatedAt":"2026-01-01T00:00:00+00:00"}]}"#;
+        let resp: PlanVersionsResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(resp.plan_versions.len(), 1);
+        assert_eq!(resp.plan_versions[0].version, 2);
+        assert_eq!(
+            resp.plan_versions[0].updated_at.as_deref(),
+            Some("2026-01-01T00:00:00+00:00")
+        );
+    }
+
+    #[test]
+    fn test_build_create_plan_variables_sends_labels() {
+        let labels = MarkerLabels {
+            guns: vec!["Alpha gun".to_string()],
+            ..Default::default()
+        };
+        let vars = build_create_plan_variables(
+            "Named",
+            "deadlands",
+            &["mortar".to_string()],
+            &[],
+            &[(10.0, 20.0)],
+            &[],
+            &[],
+            &[None],
+            &labels,
+            &[],
+            None,
+            None,
+            None,
+            &[],
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
+        );
+        assert_eq!(vars["input"]["gunLabels"][0], "Alpha gun");
+        assert_eq!(vars["input"]["targetLabels"].as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_build_create_plan_variables_sends_target_statuses() {
+        let vars = build_create_plan_variables(
+            "Op",
+            "deadlands",
+            &[],
+            &[],
+            &[],
+            &[(10.0, 20.0), (30.0, 40.0)],
+            &[],
+            &[],
+            &MarkerLabels::default(),
+            &[TargetStatus::Active, TargetStatus::Destroyed],
+            None,
+            None,
+            None,
+            &[],
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
+        );
+        assert_eq!(
+            vars["input"]["targetStatuses"],
+            serde_json::json!(["ACTIVE", "DESTROYED"])
+        );
+    }
+
+    #[test]
+    fn test_build_update_plan_variables() {
+        let vars = build_update_plan_variables(
+            "abc-123",
+            Some("secret"),
+            4,
+            "Mine",
+            "deadlands",
+            &[],
+            &[],
+            &[(10.0, 20.0)],
+            &[],
+            &[],
+            &[None],
+            &MarkerLabels::default(),
+            &[],
+            None,
+            None,
+            None,
+            &[],
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Devbranch,
+        );
+        assert_eq!(vars["id"], "abc-123");
+        assert_eq!(vars["baseVersion"], 4);
+        assert_eq!(vars["editToken"], "secret");
+        assert_eq!(vars["input"]["name"], "Mine");
+        assert_eq!(vars["input"]["gunPositions"][0]["x"], 10.0);
+        assert_eq!(vars["input"]["profile"], "DEVBRANCH");
+    }
+
+    #[test]
+    fn test_battery_template_round_trips_through_api() {
+        let json = r#"{"id":"t1","name":"Pair","guns":[{"weaponId":"mortar","offsetX":-10.0,"offsetY":0.0},{"weaponId":"mortar","offsetX":10.0,"offsetY":5.0}]}"#;
+        let data: BatteryTemplateData = serde_json::from_str(json).unwrap();
+        let template = data.to_template();
+        assert_eq!(template.name, "Pair");
+        assert_eq!(template.guns[1].offset, Position { x: 10.0, y: 5.0 });
+
+        let vars = build_share_template_variables(&template);
+        assert_eq!(vars["input"]["name"], "Pair");
+        assert_eq!(vars["input"]["guns"][1]["weaponId"], "mortar");
+        assert_eq!(vars["input"]["guns"][1]["offsetY"], 5.0);
+    }
+
+    #[test]
+    fn test_plan_calibration_round_trips_into_variables() {
+        let json = r#"{"imageUrl":"https://example.com/rustard.png","width":1000,"height":800,"points":[{"imageX":100.0,"imageY":100.0,"position":{"x":200.0,"y":200.0}},{"imageX":900.0,"imageY":700.0,"position":{"x":1800.0,"y":1400.0}}]}"#;
+        let data: CalibrationData = serde_json::from_str(json).unwrap();
+        let calibration = data.to_calibration().unwrap();
+        let scale = calibration.scale().unwrap();
+        let (x, y) = scale.px_to_meters(500.0, 400.0);
+        assert!((x - 1000.0).abs() < 1e-9 && (y - 800.0).abs() < 1e-9);
+
+        let vars = build_create_plan_variables(
+            "Custom",
+            "deadlands",
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &MarkerLabels::default(),
+            &[],
+            None,
+            None,
+            Some(&calibration),
+            &[],
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
+        );
+        let sent = &vars["input"]["calibration"];
+        assert_eq!(sent["imageUrl"], "https://example.com/rustard.png");
+        assert_eq!(sent["height"], 800);
+        assert_eq!(sent["points"][1]["imageX"], 900.0);
+        assert_eq!(sent["points"][1]["position"]["y"], 1400.0);
+
+        let one_point = CalibrationData {
+            points: data.points[..1].to_vec(),
+            ..data
+        };
+        assert!(one_point.to_calibration().is_none());
+    }
+
+    #[test]
+    fn test_plan_shot_log_round_trips_into_variables() {
+        let json = r#"[{"gun":1,"target":null,"azimuth":92.5,"distance":180.0,"result":"20 m long"},{"gun":0,"target":2,"azimuth":10.0,"distance":95.0}]"#;
+        let data: Vec<ShotRecordData> = serde_json::from_str(json).unwrap();
+        let log: Vec<ShotRecord> = data.iter().map(ShotRecordData::to_shot_record).collect();
+        assert_eq!(log[0].target, None);
+        assert_eq!(log[0].result, "20 m long");
+        assert_eq!(log[1].target, Some(2));
+        assert_eq!(log[1].result, "");
+
+        let vars = build_create_plan_variables(
+            "Adjusting",
+            "deadlands",
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &MarkerLabels::default(),
+            &[],
+            None,
+            None,
+            None,
+            &log,
+            &[],
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
+        );
+        let sent = &vars["input"]["shotLog"];
+        assert_eq!(sent[0]["gun"], 1);
+        assert!(sent[0]["target"].is_null());
+        assert_eq!(sent[0]["result"], "20 m long");
+        assert_eq!(sent[1]["target"], 2);
+        assert_eq!(sent[1]["distance"], 95.0);
+    }
+
+    #[test]
+    fn test_plan_registrations_round_trip_into_variables() {
+        let json = r#"[{"gun":2,"name":"TRP-1 bridge","position":{"x":900.0,"y":640.0},"azimuth":92.3,"distance":185.0}]"#;
+        let data: Vec<RegistrationPointData> = serde_json::from_str(json).unwrap();
+        let points: Vec<RegistrationPoint> = data
+            .iter()
+            .map(RegistrationPointData::to_registration)
+            .collect();
+        assert_eq!(points[0].gun, 2);
+        assert_eq!(points[0].position, Position { x: 900.0, y: 640.0 });
+
+        let vars = build_create_plan_variables(
+            "Registered",
+            "deadlands",
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &MarkerLabels::default(),
+            &[],
+            None,
+            None,
+            None,
+            &[],
+            &points,
+            &RangeRings::default(),
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
+        );
+        let sent = &vars["input"]["registrations"][0];
+        assert_eq!(sent["gun"], 2);
+        assert_eq!(sent["name"], "TRP-1 bridge");
+        assert_eq!(sent["position"]["y"], 640.0);
+        assert_eq!(sent["azimuth"], 92.3);
+    }
+
+    #[test]
+    fn test_plan_range_rings_round_trip_into_variables() {
+        let json = r#"{"id":"abc","name":"Rings","mapId":"deadlands","weaponIds":[],
+            "gunPositions":[],"targetPositions":[],"spotterPositions":[],"windDirection":null,"windStrength":0,
+            "gunRingsHidden":[false,true],"customRings":[{"anchor":"SPOTTER","index":0,"radius":250.0,"label":"Intel"}]}"#;
+        let plan: PlanData = serde_json::from_str(json).unwrap();
+        assert_eq!(plan.gun_rings_hidden, vec![false, true]);
+        assert_eq!(plan.custom_rings[0].anchor, RingAnchor::Spotter);
+
+        let rings = RangeRings {
+            hidden: plan.gun_rings_hidden,
+            custom: plan.custom_rings,
+        };
+        let vars = build_create_plan_variables(
+            "Rings",
+            "deadlands",
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &MarkerLabels::default(),
+            &[],
+            None,
+            None,
+            None,
+            &[],
+            &[],
+            &rings,
+            &IntelMarkers::default(),
+            WeaponProfile::Live,
+        );
+        assert_eq!(vars["input"]["gunRingsHidden"][1], true);
+        let sent = &vars["input"]["customRings"][0];
+        assert_eq!(sent["anchor"], "SPOTTER");
+        assert_eq!(sent["index"], 0);
+        assert_eq!(sent["radius"], 250.0);
+        assert_eq!(sent["label"], "Intel");
+    }
+
+    #[test]
+    fn test_plan_intel_round_trips_into_variables() {
+        let json = r#"{"id":"abc","name":"Intel","mapId":"deadlands","weaponIds":[],
+            "gunPositions":[],"targetPositions":[],"spotterPositions":[],"windDirection":null,"windStrength":0,
+            "enemyGunPositions":[{"x":10.0,"y":20.0}],"noFireZonePositions":[{"x":30.0,"y":40.0}]}"#;
+        let plan: PlanData = serde_json::from_str(json).unwrap();
+        assert_eq!(plan.enemy_gun_positions.len(), 1);
+        // Older servers don't send FOBs or bunkers
+        assert!(plan.fob_positions.is_empty());
+
+        let intel = IntelMarkers {
+            enemy_guns: vec![Position { x: 10.0, y: 20.0 }],
+            no_fire_zones: vec![Position { x: 30.0, y: 40.0 }],
+            no_fire_radii: vec![200.0],
+            ..Default::default()
+        };
+        let vars = build_create_plan_variables(
+            "Intel",
+            "deadlands",
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &MarkerLabels::default(),
+            &[],
+            None,
+            None,
+            None,
+            &[],
+            &[],
+            &RangeRings::default(),
+            &intel,
+            WeaponProfile::Live,
+        );
+        assert_eq!(vars["input"]["enemyGunPositions"][0]["y"], 20.0);
+        assert_eq!(vars["input"]["noFireZonePositions"][0]["x"], 30.0);
+        assert_eq!(vars["input"]["noFireZoneRadii"], serde_json::json!([200.0]));
+        assert_eq!(vars["input"]["fobPositions"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_update_plan_conflict_deserializes() {
+        let json = r#"{"updatePlan":{"plan":null,"conflict":{"theirs":{"id":"abc-123","name":"Theirs","mapId":"deadlands","weaponIds":[],"gunPositions":[],"targetPositions":[],"spotterPositions":[],"gunTargetIndices":[],"windDirection":null,"windStrength":0,"version":3},"guns":{"onlyMine":[{"x":1.0,"y":2.0}],"onlyTheirs":[]},"targets":{"onlyMine":[],"onlyTheirs":[]},"spotters":{"onlyMine":[],"onlyTheirs":[]},"nameChanged":true,"windChanged":false}}}"#;
+        let resp: UpdatePlanResponse = serde_json::from_str(json).unwrap();
+        assert!(resp.update_plan.plan.is_none());
+        let conflict = resp.update_plan.conflict.unwrap();
+        assert_eq!(conflict.theirs.version, 3);
+        // Older servers don't send these
+        assert!(!conflict.theirs.protected);
+        assert!(conflict.theirs.edit_token.is_none());
+        assert_eq!(conflict.guns.only_mine.len(), 1);
+        assert!(conflict.name_changed);
+    }
+
     // --- URL builder ---
 
     #[test]
@@ -607,6 +2456,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_readonly_plan_url() {
+        let url = readonly_plan_url("https://artillery.example.com/plan/brave-falcon-42");
+        assert_eq!(
+            url,
+            "https://artillery.example.com/plan/brave-falcon-42?readonly=1"
+        );
+        assert_eq!(plan_id_from_link(&url).as_deref(), Some("brave-falcon-42"));
+    }
+
+    #[test]
+    fn test_is_readonly_query() {
+        assert!(is_readonly_query("?readonly=1"));
+        assert!(is_readonly_query("?view=1&readonly"));
+        assert!(!is_readonly_query(""));
+        assert!(!is_readonly_query("?readonly=0"));
+        assert!(!is_readonly_query("?notreadonly=1"));
+    }
+
+    #[test]
+    fn test_plan_id_from_link() {
+        assert_eq!(
+            plan_id_from_link(" https://artillery.example.com/plan/abc-123?view=1 ").as_deref(),
+            Some("abc-123")
+        );
+        assert_eq!(plan_id_from_link("abc-123").as_deref(), Some("abc-123"));
+        assert_eq!(plan_id_from_link("https://example.com/plan/"), None);
+        assert_eq!(plan_id_from_link("not a link"), None);
+        assert_eq!(plan_id_from_link(""), None);
+    }
+
     // --- Gun placement tracking ---
 
     #[test]
@@ -0,0 +1,280 @@
+use dioxus::prelude::*;
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::models::Position;
+use foxhole_shared::template::{BatteryTemplate, Formation, MAX_TEMPLATE_GUNS};
+
+use crate::api;
+use crate::coords;
+
+const TEMPLATES_KEY: &str = "battery_templates";
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window().and_then(|w| w.local_storage().ok().flatten())
+}
+
+/// Templates saved on this device, kept in localStorage as JSON.
+fn load_templates() -> Vec<BatteryTemplate> {
+    local_storage()
+        .and_then(|s| s.get_item(TEMPLATES_KEY).ok().flatten())
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn save_templates(templates: &[BatteryTemplate]) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(templates)) {
+        let _ = storage.set_item(TEMPLATES_KEY, &json);
+    }
+}
+
+/// The typed name, or one numbered after the templates already saved.
+fn template_name(typed: &str, existing: usize) -> String {
+    let typed = typed.trim();
+    if typed.is_empty() {
+        format!("Battery {}", existing + 1)
+    } else {
+        typed.to_string()
+    }
+}
+
+/// Guns placed on the map (`map` px) with their weapons, in meters. Guns
+/// without a weapon are left out.
+fn placed_guns(
+    positions: &[(f64, f64)],
+    weapon_ids: &[String],
+    map: MapScale,
+) -> Vec<(String, Position)> {
+    positions
+        .iter()
+        .zip(weapon_ids)
+        .filter(|(_, w)| !w.is_empty())
+        .map(|(&(x, y), w)| {
+            let (x, y) = coords::map_px_to_meters(x, y, map);
+            (w.clone(), Position { x, y })
+        })
+        .collect()
+}
+
+/// Save gun layouts and stamp them onto the map. Picking "Stamp" arms a
+/// template; the map view places it at the next click.
+#[component]
+pub fn BatteryTemplates(
+    gun_positions: Vec<(f64, f64)>,
+    gun_weapon_ids: Vec<String>,
+    map_scale: MapScale,
+    /// Weapon new formations are made of.
+    selected_weapon: String,
+    /// Template the next map click stamps.
+    armed: Signal<Option<BatteryTemplate>>,
+) -> Element {
+    let mut templates = use_signal(load_templates);
+    let mut name = use_signal(String::new);
+    let mut formation = use_signal(|| Formation::Ring);
+    let mut count = use_signal(|| 3usize);
+    let mut spacing = use_signal(|| 50.0f64);
+    let mut import_code = use_signal(String::new);
+    let mut shared = use_signal(|| None::<(usize, String)>);
+    let mut error = use_signal(|| None::<String>);
+
+    let mut add_template = move |template: BatteryTemplate| {
+        let mut list = templates.write();
+        list.push(template);
+        save_templates(&list);
+        name.set(String::new());
+        error.set(None);
+    };
+
+    let current_guns = placed_guns(&gun_positions, &gun_weapon_ids, map_scale);
+    let armed_name = armed.read().as_ref().map(|t| t.name.clone());
+
+    rsx! {
+        div { class: "panel battery-templates",
+            h3 { "Battery templates" }
+            for (i, t) in templates.read().iter().cloned().enumerate() {
+                div { class: "template-row",
+                    span { class: "template-name", "{t.name} ({t.guns.len()})" }
+                    button {
+                        class: if armed_name.as_deref() == Some(t.name.as_str()) { "active" } else { "" },
+                        title: "Click the map to place these guns",
+                        onclick: {
+                            let t = t.clone();
+                            move |_| armed.set(Some(t.clone()))
+                        },
+                        "Stamp"
+                    }
+                    button {
+                        class: "secondary",
+                        title: "Get a code others can import this template with",
+                        onclick: {
+                            let t = t.clone();
+                            move |_| {
+                                let t = t.clone();
+                                spawn(async move {
+                                    match api::share_battery_template(&t).await {
+                                        Ok(id) => shared.set(Some((i, id))),
+                                        Err(e) => error.set(Some(format!("Failed to share: {e}"))),
+                                    }
+                                });
+                            }
+                        },
+                        "Share"
+                    }
+                    button {
+                        class: "secondary",
+                        "aria-label": "Delete template {t.name}",
+                        onclick: move |_| {
+                            let mut list = templates.write();
+                            if i < list.len() {
+                                list.remove(i);
+                            }
+                            save_templates(&list);
+                            shared.set(None);
+                        },
+                        "\u{00d7}"
+                    }
+                }
+                if let Some((_, code)) = shared.read().as_ref().filter(|(si, _)| *si == i) {
+                    input {
+                        class: "template-code",
+                        readonly: true,
+                        "aria-label": "Share code for {t.name}",
+                        value: "{code}",
+                    }
+                }
+            }
+            if let Some(armed_name) = armed_name {
+                p { class: "template-hint",
+                    "Click the map to place {armed_name}. "
+                    button { class: "secondary", onclick: move |_| armed.set(None), "Cancel" }
+                }
+            }
+            input {
+                r#type: "text",
+                "aria-label": "Template name",
+                placeholder: "Template name",
+                value: "{name}",
+                oninput: move |evt: Event<FormData>| name.set(evt.value()),
+            }
+            div { class: "template-formation",
+                select {
+                    "aria-label": "Formation",
+                    onchange: move |evt: Event<FormData>| {
+                        formation.set(if evt.value() == "line" { Formation::Line } else { Formation::Ring });
+                    },
+                    option { value: "ring", selected: *formation.read() == Formation::Ring, "Ring" }
+                    option { value: "line", selected: *formation.read() == Formation::Line, "Line" }
+                }
+                input {
+                    r#type: "number",
+                    min: "1",
+                    max: "{MAX_TEMPLATE_GUNS}",
+                    "aria-label": "Guns",
+                    value: "{count}",
+                    oninput: move |evt: Event<FormData>| {
+                        if let Ok(n) = evt.value().parse::<usize>() {
+                            count.set(n.clamp(1, MAX_TEMPLATE_GUNS));
+                        }
+                    },
+                }
+                input {
+                    r#type: "number",
+                    min: "1",
+                    "aria-label": "Spacing (m)",
+                    value: "{spacing}",
+                    oninput: move |evt: Event<FormData>| {
+                        if let Ok(m) = evt.value().parse::<f64>() {
+                            if m.is_finite() && m > 0.0 {
+                                spacing.set(m);
+                            }
+                        }
+                    },
+                }
+                span { "m" }
+            }
+            div { class: "template-actions",
+                button {
+                    disabled: selected_weapon.is_empty(),
+                    title: "Guns of the active weapon in this formation",
+                    onclick: move |_| {
+                        let n = templates.read().len();
+                        add_template(BatteryTemplate::formation(
+                            &template_name(&name.read(), n),
+                            &selected_weapon,
+                            *count.read(),
+                            *spacing.read(),
+                            *formation.read(),
+                        ));
+                    },
+                    "Add formation"
+                }
+                button {
+                    class: "secondary",
+                    disabled: current_guns.is_empty() || current_guns.len() > MAX_TEMPLATE_GUNS,
+                    title: "The guns on the map, as they're laid out",
+                    onclick: move |_| {
+                        let n = templates.read().len();
+                        add_template(BatteryTemplate::from_guns(
+                            &template_name(&name.read(), n),
+                            &current_guns,
+                        ));
+                    },
+                    "Save current guns"
+                }
+            }
+            div { class: "template-import",
+                input {
+                    r#type: "text",
+                    "aria-label": "Template share code",
+                    placeholder: "Share code",
+                    value: "{import_code}",
+                    oninput: move |evt: Event<FormData>| import_code.set(evt.value()),
+                }
+                button {
+                    class: "secondary",
+                    disabled: import_code.read().trim().is_empty(),
+                    onclick: move |_| {
+                        let code = import_code.read().trim().to_string();
+                        spawn(async move {
+                            match api::fetch_battery_template(&code).await {
+                                Ok(Some(t)) => {
+                                    add_template(t);
+                                    import_code.set(String::new());
+                                }
+                                Ok(None) => error.set(Some("No template with that code".to_string())),
+                                Err(e) => error.set(Some(format!("Failed to import: {e}"))),
+                            }
+                        });
+                    },
+                    "Import"
+                }
+            }
+            if let Some(err) = error.read().clone() {
+                p { class: "template-error", "{err}" }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_name() {
+        assert_eq!(template_name("  Mortar line ", 4), "Mortar line");
+        assert_eq!(template_name(" ", 2), "Battery 3");
+    }
+
+    #[test]
+    fn test_placed_guns_skips_unassigned() {
+        let map = MapScale::STANDARD;
+        let guns = placed_guns(
+            &[(0.0, 0.0), (map.width_px, map.height_px)],
+            &["".to_string(), "mortar".to_string()],
+            map,
+        );
+        assert_eq!(guns.len(), 1);
+        assert_eq!(guns[0].0, "mortar");
+        let (x, y) = map.px_to_meters(map.width_px, map.height_px);
+        assert_eq!(guns[0].1, Position { x, y });
+    }
+}
@@ -0,0 +1,112 @@
+use dioxus::prelude::*;
+
+/// Finger travel (px) on the handle that counts as a swipe rather than a tap.
+const SWIPE_THRESHOLD: f64 = 30.0;
+
+/// How far the panel sheet is pulled up on narrow screens. Wide screens show
+/// the panels in a fixed sidebar and ignore it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SheetState {
+    /// Only the handle shows along the bottom of the map.
+    #[default]
+    Closed,
+    Half,
+    Full,
+}
+
+impl SheetState {
+    /// Open halfway when closed, close otherwise: a tap on the handle or the menu button.
+    pub fn toggled(self) -> Self {
+        match self {
+            SheetState::Closed => SheetState::Half,
+            _ => SheetState::Closed,
+        }
+    }
+
+    /// State after the finger moved `dy` px over the handle (negative is up).
+    /// Swipes step one size up or down; shorter moves count as a tap.
+    pub fn after_swipe(self, dy: f64) -> Self {
+        if dy <= -SWIPE_THRESHOLD {
+            match self {
+                SheetState::Closed => SheetState::Half,
+                _ => SheetState::Full,
+            }
+        } else if dy >= SWIPE_THRESHOLD {
+            match self {
+                SheetState::Full => SheetState::Half,
+                _ => SheetState::Closed,
+            }
+        } else {
+            self.toggled()
+        }
+    }
+
+    pub fn is_open(self) -> bool {
+        self != SheetState::Closed
+    }
+
+    /// Classes for the sidebar that becomes the sheet.
+    pub fn sidebar_class(self) -> &'static str {
+        match self {
+            SheetState::Closed => "sidebar",
+            SheetState::Half => "sidebar open",
+            SheetState::Full => "sidebar open full",
+        }
+    }
+}
+
+/// Grab handle along the top of the panel sheet: swipe it up or down to
+/// resize the sheet, or tap it to open or close. Hidden on wide screens.
+#[component]
+pub fn SheetHandle(state: Signal<SheetState>) -> Element {
+    let mut touch_start = use_signal(|| None::<f64>);
+    let expanded = state().is_open();
+
+    rsx! {
+        div {
+            class: "sheet-handle",
+            role: "button",
+            "aria-label": if expanded { "Hide panels" } else { "Show panels" },
+            "aria-expanded": "{expanded}",
+            ontouchstart: move |evt: Event<TouchData>| {
+                let y = evt.data().touches().first().map(|t| t.client_coordinates().y);
+                touch_start.set(y);
+            },
+            ontouchend: move |evt: Event<TouchData>| {
+                // Handled here, so the tap doesn't also arrive as a click
+                evt.prevent_default();
+                let Some(start) = touch_start.write().take() else {
+                    return;
+                };
+                let end = evt
+                    .data()
+                    .touches_changed()
+                    .first()
+                    .map_or(start, |t| t.client_coordinates().y);
+                state.set(state().after_swipe(end - start));
+            },
+            onclick: move |_| state.set(state().toggled()),
+            span { class: "sheet-grip" }
+            span { class: "sheet-title", "Panels" }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swipes_step_through_sizes() {
+        use SheetState::*;
+        assert_eq!(Closed.after_swipe(-80.0), Half);
+        assert_eq!(Half.after_swipe(-80.0), Full);
+        assert_eq!(Full.after_swipe(-80.0), Full);
+        assert_eq!(Full.after_swipe(80.0), Half);
+        assert_eq!(Half.after_swipe(80.0), Closed);
+        assert_eq!(Closed.after_swipe(80.0), Closed);
+        // A short move is a tap
+        assert_eq!(Closed.after_swipe(5.0), Half);
+        assert_eq!(Full.after_swipe(-5.0), Closed);
+    }
+}
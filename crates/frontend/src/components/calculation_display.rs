@@ -1,27 +1,764 @@
+use dioxus::html::input_data::keyboard_types::Key;
 use dioxus::prelude::*;
+use foxhole_shared::calc;
+use foxhole_shared::grid::{self, MapScale};
+use foxhole_shared::models::{
+    self, push_shot, set_gun_ammo, set_target_status, target_status, Position, RegistrationPoint,
+    ShotRecord, TargetStatus, MAX_REGISTRATIONS, MAX_REGISTRATION_NAME_LEN, MAX_SHOT_RESULT_LEN,
+};
+use foxhole_shared::overlay::MAX_LABEL_LEN;
+use foxhole_shared::research::{ImpactObservation, MAX_IMPACT_OFFSET};
 
-use crate::api::{FiringSolutionData, WeaponData};
-use crate::components::map_view::{MarkerKind, SelectedMarker};
+use crate::api::{self, FiringSolutionData, WeaponData};
+use crate::components::map_view::{MarkerKind, MarkerLabels, SelectedMarker};
+use crate::components::placement_action::{PlacementAction, PlacementActionSelect};
 use crate::coords;
+use crate::export;
+use crate::settings::Settings;
+
+/// Per-gun firing delay (seconds) so every gun on a shared target lands together.
+///
+/// The slowest shell on each target fires first (delay 0); faster guns wait the
+/// difference. `None` for unpaired guns or guns without flight-time data, and
+/// for targets engaged by a single gun.
+fn salvo_delays(
+    solutions: &[Option<FiringSolutionData>],
+    pairings: &[Option<usize>],
+) -> Vec<Option<f64>> {
+    let tof = |gi: usize| -> Option<f64> {
+        solutions
+            .get(gi)
+            .and_then(|s| s.as_ref())
+            .and_then(|s| s.time_of_flight)
+    };
+    (0..solutions.len())
+        .map(|gi| {
+            let ti = pairings.get(gi).copied().flatten()?;
+            let own = tof(gi)?;
+            let peers: Vec<f64> = (0..solutions.len())
+                .filter(|&other| pairings.get(other).copied().flatten() == Some(ti))
+                .filter_map(tof)
+                .collect();
+            if peers.len() < 2 {
+                return None;
+            }
+            let slowest = peers.iter().cloned().fold(f64::MIN, f64::max);
+            Some(slowest - own)
+        })
+        .collect()
+}
+
+/// Most shells a single gun's salvo can be set to.
+const MAX_SALVO: u32 = 20;
+
+/// Set a gun's salvo size, filling in one-shell entries for guns before it.
+fn set_salvo_size(sizes: &mut Vec<u32>, gun: usize, shells: u32) {
+    if sizes.len() <= gun {
+        sizes.resize(gun + 1, 1);
+    }
+    sizes[gun] = shells.clamp(1, MAX_SALVO);
+}
+
+/// Drop a stop from a gun's displacement route (`gun` and `position` as numbered in
+/// [`export::DisplacementStep`]).
+fn remove_displacement_stop(alternates: &mut [Vec<(f64, f64)>], gun: usize, position: usize) {
+    if let Some(stops) = alternates.get_mut(gun - 1) {
+        if position >= 2 && position - 2 < stops.len() {
+            stops.remove(position - 2);
+        }
+    }
+}
+
+/// One displacement step for the panel, e.g. `"Gun 1 → 2: G9k3  270.0°  50m"`.
+fn displacement_text(gun_name: &str, step: &export::DisplacementStep) -> String {
+    let mut text = format!("{} \u{2192} {}: {}", gun_name, step.position, step.grid);
+    if let (Some(az), Some(dist)) = (step.azimuth, step.distance) {
+        text.push_str(&format!(
+            "  {:.1}\u{00b0}  {:.0}m",
+            az,
+            (dist / 5.0).round() * 5.0
+        ));
+    }
+    text
+}
+
+/// Hit chance for one shell, plus for the whole salvo when there's more than one.
+/// Area-fire shells spread over their impact zone rather than the accuracy circle.
+fn hit_chance_text(sol: &FiringSolutionData, target_radius: f64, shells: u32) -> String {
+    let single = match &sol.impact_zone {
+        Some(zone) => calc::zone_hit_probability(zone, target_radius),
+        None => calc::hit_probability(sol.accuracy_radius, target_radius),
+    };
+    if shells > 1 {
+        let salvo = calc::salvo_chance(single, shells) * 100.0;
+        let single = single * 100.0;
+        format!("{single:.0}% ({salvo:.0}% in {shells})")
+    } else {
+        format!("{:.0}%", single * 100.0)
+    }
+}
+
+/// The accuracy stat: an area-fire gun's impact zone, else its accuracy circle.
+fn spread_stat(sol: &FiringSolutionData) -> (&'static str, String) {
+    match &sol.impact_zone {
+        Some(zone) => (
+            "Impact Zone",
+            format!("{:.0}\u{00d7}{:.0}m", zone.length, zone.width),
+        ),
+        None => ("Accuracy", format!("\u{00b1}{:.1}m", sol.accuracy_radius)),
+    }
+}
+
+/// Warning for a gun whose shells could land in the no-fire `zones`.
+fn danger_close_text(zones: &[usize]) -> Option<String> {
+    let numbers: Vec<String> = zones.iter().map(|z| (z + 1).to_string()).collect();
+    match numbers.as_slice() {
+        [] => None,
+        [one] => Some(format!(
+            "DANGER CLOSE: shells may land in no-fire zone {one}"
+        )),
+        many => Some(format!(
+            "DANGER CLOSE: shells may land in no-fire zones {}",
+            many.join(", ")
+        )),
+    }
+}
+
+/// Extra class for a target row: fired-upon targets dim, destroyed ones are struck through.
+fn status_class(status: TargetStatus) -> &'static str {
+    match status {
+        TargetStatus::Active => "",
+        TargetStatus::FiredUpon => " target-fired",
+        TargetStatus::Destroyed => " target-destroyed",
+    }
+}
+
+/// The marker's user-supplied name, else `base` numbered when there are several.
+pub fn marker_name(
+    labels: &MarkerLabels,
+    kind: MarkerKind,
+    index: usize,
+    base: &str,
+    total: usize,
+) -> String {
+    match labels.get(kind, index) {
+        Some(name) => name.to_string(),
+        None if total > 1 => format!("{base} {}", index + 1),
+        None => base.to_string(),
+    }
+}
+
+/// The map position (`map` px) of a typed grid reference, or what's wrong with it.
+fn grid_position(text: &str, map: MapScale) -> Result<(f64, f64), String> {
+    let (mx, my) = grid::parse_grid_coord(text)
+        .ok_or_else(|| "Enter a grid reference like G9k3 or G9k3k7".to_string())?;
+    let (x, y) = coords::meters_to_map_px(mx, my, map);
+    if !(0.0..=map.width_px).contains(&x) || !(0.0..=map.height_px).contains(&y) {
+        return Err("That grid reference is not on this map image".to_string());
+    }
+    Ok((x, y))
+}
+
+/// Place a marker, or move the selected one, at a typed grid reference.
+#[component]
+fn GridEntry(
+    map_scale: MapScale,
+    can_move: bool,
+    on_place: EventHandler<(PlacementAction, (f64, f64))>,
+) -> Element {
+    let mut text = use_signal(String::new);
+    let action = use_signal(|| PlacementAction::Place(MarkerKind::Gun));
+    let mut error = use_signal(|| None::<String>);
+    let mut submit = move || match grid_position(&text.read(), map_scale) {
+        Ok(pos) => {
+            error.set(None);
+            on_place.call((action.read().effective(can_move), pos));
+        }
+        Err(e) => error.set(Some(e)),
+    };
+
+    rsx! {
+        div { class: "grid-entry",
+            input {
+                r#type: "text",
+                "aria-label": "Grid reference",
+                placeholder: "G9k3",
+                value: "{text}",
+                oninput: move |evt: Event<FormData>| text.set(evt.value()),
+                onkeydown: move |evt: Event<KeyboardData>| {
+                    if evt.key() == Key::Enter {
+                        submit();
+                    }
+                },
+            }
+            PlacementActionSelect { action: action, can_move: can_move, label: "Grid reference action" }
+            button { onclick: move |_| submit(), "Go" }
+        }
+        if let Some(err) = error.read().clone() {
+            p { class: "grid-entry-error", "{err}" }
+        }
+    }
+}
+
+/// Meters typed into a correction field; blank means none.
+fn parse_meters(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Some(0.0);
+    }
+    text.parse::<f64>().ok().filter(|m| m.is_finite())
+}
+
+/// The next aim point with its azimuth and distance from the gun, after a
+/// spotter's "long/short, right/left" report on a shell fired at `aim`.
+fn corrected_aim(
+    gun: Position,
+    target: Position,
+    aim: Position,
+    spotter: Position,
+    long: &str,
+    right: &str,
+) -> Option<(Position, f64, f64)> {
+    let (long, right) = (parse_meters(long)?, parse_meters(right)?);
+    let next = calc::apply_observer_correction(spotter, target, aim, long, right);
+    Some((next, calc::azimuth(gun, next), calc::distance(gun, next)))
+}
+
+/// A spotter's report as logged with the shot, e.g. "20 m short, 5 m right".
+fn splash_report(long: f64, right: f64) -> String {
+    let part = |m: f64, pos: &str, neg: &str| {
+        (m.abs() >= 0.5).then(|| format!("{:.0} m {}", m.abs(), if m > 0.0 { pos } else { neg }))
+    };
+    let parts: Vec<String> = [part(long, "long", "short"), part(right, "right", "left")]
+        .into_iter()
+        .flatten()
+        .collect();
+    if parts.is_empty() {
+        "on target".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// Long/right offsets of an observed impact; `None` unless both are readable
+/// and within [`MAX_IMPACT_OFFSET`].
+fn impact_offsets(long: &str, right: &str) -> Option<(f64, f64)> {
+    let (long, right) = (parse_meters(long)?, parse_meters(right)?);
+    (long.abs() <= MAX_IMPACT_OFFSET && right.abs() <= MAX_IMPACT_OFFSET).then_some((long, right))
+}
+
+/// Firing data as dialed in: azimuth to a tenth, distance to 5 m.
+fn firing_text(azimuth: f64, distance: f64) -> String {
+    format!(
+        "AZ {:.1}\u{00b0} / {:.0} m",
+        azimuth,
+        (distance / 5.0).round() * 5.0
+    )
+}
+
+/// One line of a gun's shot log.
+fn shot_text(shot: &ShotRecord, target_name: Option<&str>) -> String {
+    let mut text = firing_text(shot.azimuth, shot.distance);
+    if let Some(name) = target_name {
+        text.push_str(&format!(" \u{2192} {name}"));
+    }
+    if !shot.result.is_empty() {
+        text.push_str(&format!(" \u{2014} {}", shot.result));
+    }
+    text
+}
+
+/// A gun's logged adjustment rounds, oldest first, with a field to log the
+/// next one. Copying an entry brings back a previous registration.
+#[component]
+fn ShotLog(
+    gun: usize,
+    gun_name: String,
+    /// Current firing data (wind-adjusted when there's wind), if any.
+    firing: Option<(f64, f64)>,
+    target: Option<usize>,
+    target_names: Vec<String>,
+    shot_log: Signal<Vec<ShotRecord>>,
+    read_only: bool,
+    on_before_change: EventHandler<()>,
+) -> Element {
+    let mut result = use_signal(String::new);
+    let mut copied = use_signal(|| None::<usize>);
+    let shots: Vec<ShotRecord> = shot_log
+        .read()
+        .iter()
+        .filter(|s| s.gun == gun)
+        .cloned()
+        .collect();
+    if shots.is_empty() && firing.is_none() {
+        return rsx! {};
+    }
+    let lines: Vec<String> = shots
+        .iter()
+        .map(|s| {
+            let target = s.target.and_then(|t| target_names.get(t));
+            shot_text(s, target.map(String::as_str))
+        })
+        .collect();
+
+    rsx! {
+        details { class: "shot-log",
+            summary { "Shot log ({shots.len()})" }
+            if lines.is_empty() {
+                p { class: "shot-log-hint", "No rounds logged yet." }
+            } else {
+                ol {
+                    for (n, line) in lines.into_iter().enumerate() {
+                        li { key: "{n}",
+                            span { "{line}" }
+                            {
+                                let chat_line = format!("{gun_name}: {line}");
+                                rsx! {
+                                    button {
+                                        class: "secondary",
+                                        title: "Copy to return to this registration",
+                                        "aria-label": "Copy shot {n + 1} for {gun_name}",
+                                        onclick: move |_| {
+                                            export::copy_to_clipboard(chat_line.clone());
+                                            copied.set(Some(n));
+                                            spawn(async move {
+                                                gloo_timers::future::TimeoutFuture::new(2000).await;
+                                                copied.set(None);
+                                            });
+                                        },
+                                        if *copied.read() == Some(n) { "Copied!" } else { "Copy" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if !read_only {
+                div { class: "shot-log-actions",
+                    if let Some((azimuth, distance)) = firing {
+                        input {
+                            r#type: "text",
+                            maxlength: "{MAX_SHOT_RESULT_LEN}",
+                            placeholder: "Observed, e.g. 20 m short",
+                            "aria-label": "Observed result for {gun_name}",
+                            value: "{result}",
+                            oninput: move |evt: Event<FormData>| result.set(evt.value()),
+                        }
+                        button {
+                            class: "secondary",
+                            onclick: move |_| {
+                                on_before_change.call(());
+                                let shot = ShotRecord {
+                                    gun,
+                                    target,
+                                    azimuth,
+                                    distance,
+                                    result: result.read().trim().to_string(),
+                                };
+                                push_shot(&mut shot_log.write(), shot);
+                                result.set(String::new());
+                            },
+                            "Log shot"
+                        }
+                    }
+                    if !shots.is_empty() {
+                        button {
+                            class: "secondary",
+                            onclick: move |_| {
+                                on_before_change.call(());
+                                shot_log.write().retain(|s| s.gun != gun);
+                            },
+                            "Clear"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A gun's registration points with their stored solutions, so it can switch
+/// between known targets without re-aiming, and a field to register its
+/// current target.
+#[component]
+fn Registrations(
+    gun: usize,
+    gun_name: String,
+    /// Current target (meters) and firing data for it, if the gun has both.
+    current: Option<(Position, f64, f64)>,
+    registrations: Signal<Vec<RegistrationPoint>>,
+    read_only: bool,
+    on_before_change: EventHandler<()>,
+) -> Element {
+    let mut name = use_signal(String::new);
+    let mut copied = use_signal(|| None::<usize>);
+    // Plan-wide indices of this gun's points, for removal
+    let points: Vec<(usize, RegistrationPoint)> = registrations
+        .read()
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.gun == gun)
+        .map(|(i, p)| (i, p.clone()))
+        .collect();
+    let full = registrations.read().len() >= MAX_REGISTRATIONS;
+    if points.is_empty() && (current.is_none() || read_only) {
+        return rsx! {};
+    }
+    let default_name = format!("TRP-{}", points.len() + 1);
+
+    rsx! {
+        div { class: "registrations",
+            h4 { "Registration points" }
+            if !points.is_empty() {
+                ul {
+                    for (n, (index, point)) in points.into_iter().enumerate() {
+                        {
+                            let line = firing_text(point.azimuth, point.distance);
+                            let chat_line = format!("{gun_name} \u{2192} {}: {line}", point.name);
+                            rsx! {
+                                li { key: "{index}",
+                                    span { class: "registration-name", "{point.name}" }
+                                    span { class: "registration-solution", "{line}" }
+                                    button {
+                                        class: "secondary",
+                                        "aria-label": "Copy {point.name} for {gun_name}",
+                                        onclick: move |_| {
+                                            export::copy_to_clipboard(chat_line.clone());
+                                            copied.set(Some(n));
+                                            spawn(async move {
+                                                gloo_timers::future::TimeoutFuture::new(2000).await;
+                                                copied.set(None);
+                                            });
+                                        },
+                                        if *copied.read() == Some(n) { "Copied!" } else { "Copy" }
+                                    }
+                                    if !read_only {
+                                        button {
+                                            class: "remove-marker-btn",
+                                            title: "Remove registration point",
+                                            onclick: move |_| {
+                                                on_before_change.call(());
+                                                registrations.write().remove(index);
+                                            },
+                                            "\u{2715}"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if let (Some((position, azimuth, distance)), false) = (current, read_only) {
+                div { class: "registration-entry",
+                    input {
+                        r#type: "text",
+                        maxlength: "{MAX_REGISTRATION_NAME_LEN}",
+                        placeholder: "{default_name}",
+                        "aria-label": "Registration point name for {gun_name}",
+                        value: "{name}",
+                        oninput: move |evt: Event<FormData>| name.set(evt.value()),
+                    }
+                    button {
+                        class: "secondary",
+                        disabled: full,
+                        title: if full { "This plan has the most registration points it can hold" } else { "Save the current target and its solution" },
+                        onclick: move |_| {
+                            let typed = name.read().trim().to_string();
+                            let point = RegistrationPoint {
+                                gun,
+                                name: if typed.is_empty() { default_name.clone() } else { typed },
+                                position,
+                                azimuth,
+                                distance,
+                            };
+                            on_before_change.call(());
+                            registrations.write().push(point);
+                            name.set(String::new());
+                        },
+                        "Register target"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Walk a gun onto its target from spotter reports on where shells landed.
+/// Applied corrections stack until reset; positions are in meters.
+#[component]
+fn ObserverCorrection(
+    gun: Position,
+    target: Position,
+    /// Where the current solution aims (wind-compensated when there's wind).
+    aim: Position,
+    spotters: Vec<(String, Position)>,
+    /// Azimuth, distance and spotter report of each round a correction is applied to.
+    on_fired: EventHandler<(f64, f64, String)>,
+) -> Element {
+    let mut spotter_idx = use_signal(|| 0usize);
+    let mut long = use_signal(String::new);
+    let mut right = use_signal(String::new);
+    let mut applied = use_signal(|| None::<Position>);
+
+    let spotter = spotters
+        .get(*spotter_idx.read())
+        .or(spotters.first())
+        .map(|(_, p)| *p);
+    let current = applied.read().unwrap_or(aim);
+    let next =
+        spotter.and_then(|sp| corrected_aim(gun, target, current, sp, &long.read(), &right.read()));
+
+    rsx! {
+        div { class: "observer-correction",
+            h4 { "Spotter correction" }
+            if spotters.len() > 1 {
+                select {
+                    "aria-label": "Reporting spotter",
+                    onchange: move |evt: Event<FormData>| {
+                        if let Ok(i) = evt.value().parse::<usize>() {
+                            spotter_idx.set(i);
+                        }
+                    },
+                    for (i, (name, _)) in spotters.iter().enumerate() {
+                        option { value: "{i}", selected: *spotter_idx.read() == i, "{name}" }
+                    }
+                }
+            }
+            div { class: "correction-inputs",
+                input {
+                    r#type: "number",
+                    "aria-label": "Splash long (m), negative for short",
+                    placeholder: "Long m (\u{2212} short)",
+                    value: "{long}",
+                    oninput: move |evt: Event<FormData>| long.set(evt.value()),
+                }
+                input {
+                    r#type: "number",
+                    "aria-label": "Splash right (m), negative for left",
+                    placeholder: "Right m (\u{2212} left)",
+                    value: "{right}",
+                    oninput: move |evt: Event<FormData>| right.set(evt.value()),
+                }
+            }
+            if let Some((aim_at, az, dist)) = next {
+                p { class: "correction-result",
+                    "Corrected: AZ {az:.1}\u{00b0} / {(dist / 5.0).round() * 5.0:.0} m"
+                }
+                div { class: "correction-actions",
+                    button {
+                        class: "secondary",
+                        title: "Fire this, then report the next splash",
+                        onclick: move |_| {
+                            let report = splash_report(
+                                parse_meters(&long.read()).unwrap_or(0.0),
+                                parse_meters(&right.read()).unwrap_or(0.0),
+                            );
+                            on_fired.call((calc::azimuth(gun, current), calc::distance(gun, current), report));
+                            applied.set(Some(aim_at));
+                            long.set(String::new());
+                            right.set(String::new());
+                        },
+                        "Apply"
+                    }
+                    if applied.read().is_some() {
+                        button { class: "secondary", onclick: move |_| applied.set(None), "Reset" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Research mode: report where a round fired with the current solution landed,
+/// so the weapon's accuracy and wind drift figures can be checked.
+#[component]
+fn ImpactReport(
+    gun_name: String,
+    weapon_slug: String,
+    /// Azimuth and distance the round was fired at.
+    firing: (f64, f64),
+    wind_direction: Option<f64>,
+    wind_strength: u32,
+    /// Wind drift (m) the firing data already allows for.
+    compensated_drift: f64,
+) -> Element {
+    let mut long = use_signal(String::new);
+    let mut right = use_signal(String::new);
+    let mut status = use_signal(|| None::<Result<(), String>>);
+    let offsets = impact_offsets(&long.read(), &right.read());
+
+    rsx! {
+        details { class: "impact-report",
+            summary { "Report impact" }
+            div { class: "correction-inputs",
+                input {
+                    r#type: "number",
+                    "aria-label": "Impact long (m) for {gun_name}, negative for short",
+                    placeholder: "Long m (\u{2212} short)",
+                    value: "{long}",
+                    oninput: move |evt: Event<FormData>| long.set(evt.value()),
+                }
+                input {
+                    r#type: "number",
+                    "aria-label": "Impact right (m) for {gun_name}, negative for left",
+                    placeholder: "Right m (\u{2212} left)",
+                    value: "{right}",
+                    oninput: move |evt: Event<FormData>| right.set(evt.value()),
+                }
+                button {
+                    class: "secondary",
+                    disabled: offsets.is_none(),
+                    onclick: move |_| {
+                        let Some((long_m, right_m)) = offsets else {
+                            return;
+                        };
+                        let observation = ImpactObservation {
+                            weapon_slug: weapon_slug.clone(),
+                            azimuth: firing.0.rem_euclid(360.0),
+                            distance: firing.1,
+                            wind_direction: wind_direction.filter(|_| wind_strength > 0),
+                            wind_strength: wind_strength as u8,
+                            compensated_drift,
+                            long: long_m,
+                            right: right_m,
+                        };
+                        spawn(async move {
+                            match api::submit_impact_observation(&observation).await {
+                                Ok(_) => {
+                                    long.set(String::new());
+                                    right.set(String::new());
+                                    status.set(Some(Ok(())));
+                                }
+                                Err(e) => status.set(Some(Err(e))),
+                            }
+                        });
+                    },
+                    "Submit"
+                }
+            }
+            match &*status.read() {
+                Some(Ok(())) => rsx! {
+                    p { class: "shot-log-hint", "Impact reported, thanks!" }
+                },
+                Some(Err(e)) => rsx! {
+                    p { class: "grid-entry-error", "{e}" }
+                },
+                None => rsx! {},
+            }
+        }
+    }
+}
 
 #[component]
 pub fn CalculationDisplay(
     solutions: Vec<Option<FiringSolutionData>>,
+    /// No-fire zones each gun's shells could land in, indexed like `solutions`.
+    danger_close: Vec<Vec<usize>>,
     gun_positions: Vec<(f64, f64)>,
     target_positions: Vec<(f64, f64)>,
     spotter_positions: Vec<(f64, f64)>,
     gun_weapon_ids: Signal<Vec<String>>,
     gun_target_indices: Signal<Vec<Option<usize>>>,
+    marker_labels: Signal<MarkerLabels>,
+    /// Shells per salvo for each gun; guns past the end fire one.
+    gun_salvo_sizes: Signal<Vec<u32>>,
+    /// Alternate firing positions per gun, in displacement order.
+    gun_alternates: Signal<Vec<Vec<(f64, f64)>>>,
+    /// Progress per target, toggled from the target rows.
+    target_statuses: Signal<Vec<TargetStatus>>,
+    /// Adjustment rounds fired, shown per gun.
+    shot_log: Signal<Vec<ShotRecord>>,
+    /// Registration points per gun (meters), listed with their stored solutions.
+    registrations: Signal<Vec<RegistrationPoint>>,
+    /// Ammo per gun; guns past the end fire their standard shell.
+    gun_ammo: Signal<Vec<String>>,
+    /// Azimuth unit and distance rounding for the firing solutions.
+    settings: Settings,
+    wind_direction: Option<f64>,
+    wind_strength: u32,
+    /// Size of the map image, for grid references and displacement solutions.
+    map_scale: MapScale,
+    /// Radius (m) a shell must land within to count as a hit.
+    target_radius: Signal<f64>,
     weapons: Vec<WeaponData>,
     selected_marker: Signal<Option<SelectedMarker>>,
     on_before_change: EventHandler<()>,
     on_remove: EventHandler<(MarkerKind, usize)>,
+    /// Place or move a marker at a typed grid reference (map px).
+    on_place: EventHandler<(PlacementAction, (f64, f64))>,
+    plan_name: String,
+    /// Hide remove buttons and lock the inline selectors (plan locked by someone else).
+    #[props(default)]
+    read_only: bool,
 ) -> Element {
+    let mut copied = use_signal(|| None::<&'static str>);
+    let mut copied_gun = use_signal(|| None::<usize>);
     let has_any_solution = solutions.iter().any(|s| s.is_some());
     let cur_selected = *selected_marker.read();
     let wids = gun_weapon_ids.read().clone();
+    let ammo = gun_ammo.read().clone();
     let pairings = gun_target_indices.read().clone();
+    let labels = marker_labels.read().clone();
+    let salvos = gun_salvo_sizes.read().clone();
+    let statuses = target_statuses.read().clone();
+    let hit_radius = *target_radius.read();
     let multiple_guns = gun_positions.len() > 1;
+    let target_names: Vec<String> = (0..target_positions.len())
+        .map(|ti| {
+            marker_name(
+                &labels,
+                MarkerKind::Target,
+                ti,
+                "Target",
+                target_positions.len(),
+            )
+        })
+        .collect();
+    let spotter_names: Vec<String> = (0..spotter_positions.len())
+        .map(|i| {
+            marker_name(
+                &labels,
+                MarkerKind::Spotter,
+                i,
+                "Spt",
+                spotter_positions.len(),
+            )
+        })
+        .collect();
+    let spotters_m: Vec<(String, Position)> = spotter_positions
+        .iter()
+        .zip(&spotter_names)
+        .map(|(&(x, y), name)| {
+            let (x, y) = coords::map_px_to_meters(x, y, map_scale);
+            (name.clone(), Position { x, y })
+        })
+        .collect();
+    let delays = salvo_delays(&solutions, &pairings);
+    let missions = export::fire_missions(
+        &solutions,
+        &gun_positions,
+        &target_positions,
+        &pairings,
+        &wids,
+        &ammo,
+        &weapons,
+        &labels,
+        map_scale,
+    );
+    let displacements = export::displacement_order(
+        &gun_positions,
+        &gun_alternates.read(),
+        &target_positions,
+        &pairings,
+        &wids,
+        &ammo,
+        &weapons,
+        wind_direction,
+        wind_strength,
+        map_scale,
+    );
 
     let colonial: Vec<&WeaponData> = weapons
         .iter()
@@ -45,24 +782,108 @@ pub fn CalculationDisplay(
                 p { style: "color: var(--text-dim); font-size: 13px;",
                     "Place gun and target to calculate."
                 }
+                fieldset { class: "lock-fieldset", disabled: read_only,
+                    GridEntry {
+                        map_scale: map_scale,
+                        can_move: cur_selected.is_some(),
+                        on_place: on_place,
+                    }
+                }
             }
         };
     }
 
+    // Name field for the selected marker (intel markers can't be named)
+    let label_target = cur_selected.and_then(|sm| {
+        let (noun, total) = match sm.kind {
+            MarkerKind::Gun => ("gun", gun_positions.len()),
+            MarkerKind::Target => ("target", target_positions.len()),
+            MarkerKind::Spotter => ("spotter", spotter_positions.len()),
+            MarkerKind::Enemy | MarkerKind::Fob | MarkerKind::Bunker | MarkerKind::NoFire => {
+                return None
+            }
+        };
+        (sm.index < total).then_some((sm, noun))
+    });
+
     rsx! {
         div { class: "panel",
             h3 { "Firing Solution" }
 
+            if has_any_solution {
+                label { class: "target-radius-row",
+                    "Hit radius"
+                    input {
+                        r#type: "number",
+                        min: "1",
+                        step: "1",
+                        "aria-label": "Hit radius in meters",
+                        value: "{hit_radius}",
+                        onchange: move |evt: Event<FormData>| {
+                            if let Ok(r) = evt.value().parse::<f64>() {
+                                target_radius.set(r.max(1.0));
+                            }
+                        },
+                    }
+                    "m"
+                }
+            }
+
+            if let Some((sm, noun)) = label_target {
+                input {
+                    class: "marker-label-input",
+                    r#type: "text",
+                    maxlength: "{MAX_LABEL_LEN}",
+                    placeholder: "Name this {noun}",
+                    "aria-label": "Name for {noun} {sm.index + 1}",
+                    disabled: read_only,
+                    value: "{labels.get(sm.kind, sm.index).unwrap_or_default()}",
+                    onchange: move |evt: Event<FormData>| {
+                        on_before_change.call(());
+                        marker_labels.write().set(sm.kind, sm.index, &evt.value());
+                    },
+                }
+            }
+
+            fieldset { class: "lock-fieldset", disabled: read_only,
+                GridEntry {
+                    map_scale: map_scale,
+                    can_move: cur_selected.is_some(),
+                    on_place: on_place,
+                }
+            }
+
             // Each gun with its assigned target and firing solution
             for (gun_idx, g) in gun_positions.iter().enumerate() {
                 {
                     let sol = solutions.get(gun_idx).and_then(|s| s.as_ref());
+                    let delay = delays.get(gun_idx).copied().flatten();
+                    let shells = salvos.get(gun_idx).copied().unwrap_or(1);
+                    let danger = danger_close.get(gun_idx).and_then(|zones| danger_close_text(zones));
                     let target_idx = pairings.get(gun_idx).and_then(|o| *o);
                     let target = target_idx.and_then(|ti| target_positions.get(ti));
+                    let firing = sol.map(|s| {
+                        (
+                            s.wind_adjusted_azimuth.unwrap_or(s.azimuth),
+                            s.wind_adjusted_distance.unwrap_or(s.distance),
+                        )
+                    });
+                    let registering = target.zip(firing).map(|(&(x, y), (az, dist))| {
+                        let (x, y) = coords::map_px_to_meters(x, y, map_scale);
+                        (Position { x, y }, az, dist)
+                    });
+
+                    let gun_name = marker_name(&labels, MarkerKind::Gun, gun_idx, "Gun", gun_positions.len());
+                    let target_name = target_idx
+                        .map(|ti| marker_name(&labels, MarkerKind::Target, ti, "Tgt", target_positions.len()))
+                        .unwrap_or_default();
 
                     let weapon_name = wids.get(gun_idx)
                         .and_then(|slug| weapons.iter().find(|w| w.slug == *slug))
-                        .map(|w| w.display_name.clone());
+                        .map(|w| match models::gun_ammo(&ammo, gun_idx) {
+                            "" => w.display_name.clone(),
+                            shell => format!("{} ({})", w.display_name, shell),
+                        });
 
                     let gun_selected = cur_selected == Some(SelectedMarker { kind: MarkerKind::Gun, index: gun_idx });
                     let paired_tgt_selected = target_idx
@@ -73,9 +894,9 @@ pub fn CalculationDisplay(
                         if multiple_guns {
                             h4 { style: "margin: 8px 0 4px; color: var(--text-dim);",
                                 if let Some(ref wn) = weapon_name {
-                                    "Gun {gun_idx + 1} — {wn}"
+                                    "{gun_name} — {wn}"
                                 } else {
-                                    "Gun {gun_idx + 1}"
+                                    "{gun_name}"
                                 }
                             }
                         } else if let Some(ref wn) = weapon_name {
@@ -96,20 +917,18 @@ pub fn CalculationDisplay(
                                             move |_| selected_marker.set(sel)
                                         },
                                         span { class: "coord-info gun-coord",
-                                            if multiple_guns {
-                                                "Gun {gun_idx + 1}: {coords::format_px_as_grid(g.0, g.1)}"
-                                            } else {
-                                                "Gun: {coords::format_px_as_grid(g.0, g.1)}"
-                                            }
+                                            "{gun_name}: {coords::format_px_as_grid(g.0, g.1, map_scale)}"
                                         }
-                                        button {
-                                            class: "remove-marker-btn",
-                                            title: "Remove gun",
-                                            onclick: move |evt: Event<MouseData>| {
-                                                evt.stop_propagation();
-                                                on_remove.call((MarkerKind::Gun, gun_idx));
-                                            },
-                                            "\u{2715}"
+                                        if !read_only {
+                                            button {
+                                                class: "remove-marker-btn",
+                                                title: "Remove gun",
+                                                onclick: move |evt: Event<MouseData>| {
+                                                    evt.stop_propagation();
+                                                    on_remove.call((MarkerKind::Gun, gun_idx));
+                                                },
+                                                "\u{2715}"
+                                            }
                                         }
                                     }
                                 }
@@ -125,21 +944,27 @@ pub fn CalculationDisplay(
                                                 let sel = if paired_tgt_selected { None } else { Some(SelectedMarker { kind: MarkerKind::Target, index: ti }) };
                                                 move |_| selected_marker.set(sel)
                                             },
-                                            span { class: "coord-info target-coord",
-                                                if target_positions.len() > 1 {
-                                                    "Tgt {ti + 1}: {coords::format_px_as_grid(t.0, t.1)}"
-                                                } else {
-                                                    "Tgt: {coords::format_px_as_grid(t.0, t.1)}"
-                                                }
+                                            span { class: "coord-info target-coord{status_class(target_status(&statuses, ti))}",
+                                                "{target_name}: {coords::format_px_as_grid(t.0, t.1, map_scale)}"
                                             }
-                                            button {
-                                                class: "remove-marker-btn",
-                                                title: "Remove target",
-                                                onclick: move |evt: Event<MouseData>| {
-                                                    evt.stop_propagation();
-                                                    on_remove.call((MarkerKind::Target, ti));
+                                            TargetStatusButton {
+                                                status: target_status(&statuses, ti),
+                                                disabled: read_only,
+                                                on_change: move |status| {
+                                                    on_before_change.call(());
+                                                    set_target_status(&mut target_statuses.write(), ti, status);
                                                 },
-                                                "\u{2715}"
+                                            }
+                                            if !read_only {
+                                                button {
+                                                    class: "remove-marker-btn",
+                                                    title: "Remove target",
+                                                    onclick: move |evt: Event<MouseData>| {
+                                                        evt.stop_propagation();
+                                                        on_remove.call((MarkerKind::Target, ti));
+                                                    },
+                                                    "\u{2715}"
+                                                }
                                             }
                                         }
                                     }
@@ -155,6 +980,12 @@ pub fn CalculationDisplay(
                         if gun_selected {
                             {
                                 let current_slug = wids.get(gun_idx).cloned().unwrap_or_default();
+                                let current_ammo = models::gun_ammo(&ammo, gun_idx).to_string();
+                                let ammo_names: Vec<String> = weapons
+                                    .iter()
+                                    .find(|w| w.slug == current_slug)
+                                    .map(|w| w.ammo.iter().map(|a| a.name.clone()).collect())
+                                    .unwrap_or_default();
                                 let current_target_val = match pairings.get(gun_idx).and_then(|o| *o) {
                                     Some(ti) => format!("{}", ti),
                                     None => String::new(),
@@ -163,6 +994,7 @@ pub fn CalculationDisplay(
                                     // Weapon selector
                                     select {
                                         class: "inline-weapon-select",
+                                        disabled: read_only,
                                         "aria-label": "Weapon for gun {gun_idx + 1}",
                                         value: "{current_slug}",
                                         onchange: {
@@ -170,11 +1002,14 @@ pub fn CalculationDisplay(
                                             move |evt: Event<FormData>| {
                                                 on_before_change.call(());
                                                 let new_slug = evt.value().to_string();
-                                                // Track weapon assignment
-                                                crate::api::track_gun_placement_fire(&new_slug);
+                                                // Track weapon assignment; the gun is already
+                                                // counted on its map
+                                                crate::api::track_gun_placement_fire(&new_slug, None);
                                                 if let Some(entry) = gun_weapon_ids.write().get_mut(idx) {
                                                     *entry = new_slug;
                                                 }
+                                                // Another gun's shells don't carry over
+                                                set_gun_ammo(&mut gun_ammo.write(), idx, "");
                                             }
                                         },
                                         option { value: "", "-- Select Weapon --" }
@@ -197,9 +1032,34 @@ pub fn CalculationDisplay(
                                             }
                                         }
                                     }
+                                    // Ammo selector, for weapons with special shells
+                                    if !ammo_names.is_empty() {
+                                        select {
+                                            class: "inline-weapon-select inline-ammo-select",
+                                            disabled: read_only,
+                                            "aria-label": "Ammo for gun {gun_idx + 1}",
+                                            value: "{current_ammo}",
+                                            onchange: {
+                                                let idx = gun_idx;
+                                                move |evt: Event<FormData>| {
+                                                    on_before_change.call(());
+                                                    set_gun_ammo(&mut gun_ammo.write(), idx, &evt.value());
+                                                }
+                                            },
+                                            option { value: "", selected: current_ammo.is_empty(), "Standard shell" }
+                                            for name in &ammo_names {
+                                                option {
+                                                    value: "{name}",
+                                                    selected: current_ammo == *name,
+                                                    "{name}"
+                                                }
+                                            }
+                                        }
+                                    }
                                     // Target selector
                                     select {
                                         class: "inline-weapon-select",
+                                        disabled: read_only,
                                         "aria-label": "Target for gun {gun_idx + 1}",
                                         value: "{current_target_val}",
                                         onchange: {
@@ -218,18 +1078,36 @@ pub fn CalculationDisplay(
                                             }
                                         },
                                         option { value: "", "-- No Target --" }
-                                        for (ti, tp) in target_positions.iter().enumerate() {
+                                        for (ti, (tp, name)) in target_positions.iter().zip(&target_names).enumerate() {
                                             option {
                                                 value: "{ti}",
                                                 selected: current_target_val == format!("{}", ti),
-                                                if target_positions.len() > 1 {
-                                                    "Target {ti + 1}: {coords::format_px_as_grid(tp.0, tp.1)}"
-                                                } else {
-                                                    "Target: {coords::format_px_as_grid(tp.0, tp.1)}"
-                                                }
+                                                "{name}: {coords::format_px_as_grid(tp.0, tp.1, map_scale)}"
                                             }
                                         }
                                     }
+                                    // Salvo size
+                                    label { class: "salvo-row",
+                                        "Salvo"
+                                        input {
+                                            r#type: "number",
+                                            min: "1",
+                                            max: "{MAX_SALVO}",
+                                            disabled: read_only,
+                                            "aria-label": "Shells per salvo for gun {gun_idx + 1}",
+                                            value: "{shells}",
+                                            onchange: {
+                                                let idx = gun_idx;
+                                                move |evt: Event<FormData>| {
+                                                    if let Ok(n) = evt.value().parse::<u32>() {
+                                                        on_before_change.call(());
+                                                        set_salvo_size(&mut gun_salvo_sizes.write(), idx, n);
+                                                    }
+                                                }
+                                            },
+                                        }
+                                        "shells"
+                                    }
                                 }
                             }
                         }
@@ -238,20 +1116,28 @@ pub fn CalculationDisplay(
                         if let Some(sol) = sol {
                             {
                                 let range_class = if sol.in_range { "value in-range" } else { "value out-of-range" };
-                                let rounded_dist = (sol.distance / 5.0).round() * 5.0;
+                                let rounded_dist = settings.round_distance(sol.distance);
+                                let azimuth = settings.format_azimuth(sol.azimuth);
+                                let (spread_label, spread_value) = spread_stat(sol);
+                                let chat_line = export::solution_text(sol);
+                                let gun_copied = *copied_gun.read() == Some(gun_idx);
                                 rsx! {
                                     div { class: "solution",
                                         div { class: "stat",
                                             div { class: "label", "Azimuth" }
-                                            div { class: "{range_class}", "{sol.azimuth:.1}\u{00b0}" }
+                                            div { class: "{range_class}", "{azimuth}" }
                                         }
                                         div { class: "stat",
                                             div { class: "label", "Distance" }
                                             div { class: "{range_class}", "{rounded_dist:.0}m" }
                                         }
                                         div { class: "stat",
-                                            div { class: "label", "Accuracy" }
-                                            div { class: "value", "\u{00b1}{sol.accuracy_radius:.1}m" }
+                                            div { class: "label", "{spread_label}" }
+                                            div { class: "value", "{spread_value}" }
+                                        }
+                                        div { class: "stat",
+                                            div { class: "label", "Hit Chance" }
+                                            div { class: "value", "{hit_chance_text(sol, hit_radius, shells)}" }
                                         }
                                         div { class: "stat",
                                             div { class: "label", "Status" }
@@ -259,17 +1145,80 @@ pub fn CalculationDisplay(
                                                 if sol.in_range { "IN RANGE" } else { "OUT OF RANGE" }
                                             }
                                         }
+                                        if let Some(tof) = sol.time_of_flight {
+                                            div { class: "stat",
+                                                div { class: "label", "Flight Time" }
+                                                div { class: "value", "~{tof:.1}s" }
+                                            }
+                                        }
+                                        if let Some(d) = delay {
+                                            div { class: "stat",
+                                                div { class: "label", "Fire Delay" }
+                                                div { class: "value",
+                                                    if d < 0.05 { "FIRE FIRST" } else { "+{d:.1}s" }
+                                                }
+                                            }
+                                        }
+                                    }
+                                    if let Some(ref text) = danger {
+                                        p { class: "danger-close-warning", role: "alert", "{text}" }
+                                    }
+                                    if let (true, Some(t), false) = (gun_selected, target, spotters_m.is_empty()) {
+                                        {
+                                            let to_m = |p: &(f64, f64)| {
+                                                let (x, y) = coords::map_px_to_meters(p.0, p.1, map_scale);
+                                                Position { x, y }
+                                            };
+                                            let gun_m = to_m(g);
+                                            let aim = calc::offset_by_bearing(
+                                                gun_m,
+                                                sol.wind_adjusted_distance.unwrap_or(sol.distance),
+                                                sol.wind_adjusted_azimuth.unwrap_or(sol.azimuth),
+                                            );
+                                            rsx! {
+                                                ObserverCorrection {
+                                                    key: "{gun_idx}-{target_idx:?}",
+                                                    gun: gun_m,
+                                                    target: to_m(t),
+                                                    aim: aim,
+                                                    spotters: spotters_m.clone(),
+                                                    on_fired: move |(azimuth, distance, result): (f64, f64, String)| {
+                                                        if read_only {
+                                                            return;
+                                                        }
+                                                        on_before_change.call(());
+                                                        let shot = ShotRecord { gun: gun_idx, target: target_idx, azimuth, distance, result };
+                                                        push_shot(&mut shot_log.write(), shot);
+                                                    },
+                                                }
+                                            }
+                                        }
+                                    }
+                                    button {
+                                        class: "secondary copy-solution-btn",
+                                        title: "Copy for squad chat: {chat_line}",
+                                        "aria-label": "Copy solution for {gun_name}",
+                                        onclick: move |_| {
+                                            export::copy_to_clipboard(chat_line.clone());
+                                            copied_gun.set(Some(gun_idx));
+                                            spawn(async move {
+                                                gloo_timers::future::TimeoutFuture::new(2000).await;
+                                                copied_gun.set(None);
+                                            });
+                                        },
+                                        if gun_copied { "Copied!" } else { "Copy" }
                                     }
                                     if let (Some(adj_az), Some(adj_dist)) = (sol.wind_adjusted_azimuth, sol.wind_adjusted_distance) {
                                         {
-                                            let rounded_adj = (adj_dist / 5.0).round() * 5.0;
+                                            let rounded_adj = settings.round_distance(adj_dist);
+                                            let adj_azimuth = settings.format_azimuth(adj_az);
                                             rsx! {
                                                 div { class: "wind-adjusted",
                                                     h4 { "Wind Adjusted" }
                                                     div { class: "solution",
                                                         div { class: "stat",
                                                             div { class: "label", "Azimuth" }
-                                                            div { class: "value", "{adj_az:.1}\u{00b0}" }
+                                                            div { class: "value", "{adj_azimuth}" }
                                                         }
                                                         div { class: "stat",
                                                             div { class: "label", "Distance" }
@@ -289,12 +1238,42 @@ pub fn CalculationDisplay(
                                 }
                             }
                         }
+
+                        ShotLog {
+                            gun: gun_idx,
+                            gun_name: gun_name.clone(),
+                            firing: firing,
+                            target: target_idx,
+                            target_names: target_names.clone(),
+                            shot_log: shot_log,
+                            read_only: read_only,
+                            on_before_change: on_before_change,
+                        }
+                        Registrations {
+                            gun: gun_idx,
+                            gun_name: gun_name.clone(),
+                            current: registering,
+                            registrations: registrations,
+                            read_only: read_only,
+                            on_before_change: on_before_change,
+                        }
+                        if let (true, Some(sol), Some(fired), Some(slug)) = (settings.research_mode, sol, firing, wids.get(gun_idx).filter(|s| !s.is_empty())) {
+                            ImpactReport {
+                                key: "{gun_idx}-{slug}",
+                                gun_name: gun_name.clone(),
+                                weapon_slug: slug.clone(),
+                                firing: fired,
+                                wind_direction: wind_direction,
+                                wind_strength: wind_strength,
+                                compensated_drift: sol.wind_offset_meters.unwrap_or(0.0),
+                            }
+                        }
                     }
                 }
             }
 
             // Unassigned targets (not paired with any gun)
-            for (ti, t) in target_positions.iter().enumerate() {
+            for (ti, (t, name)) in target_positions.iter().zip(&target_names).enumerate() {
                 if !assigned_targets.get(ti).copied().unwrap_or(false) {
                     {
                         let is_selected = cur_selected == Some(SelectedMarker { kind: MarkerKind::Target, index: ti });
@@ -306,21 +1285,27 @@ pub fn CalculationDisplay(
                                     let sel = if is_selected { None } else { Some(SelectedMarker { kind: MarkerKind::Target, index: ti }) };
                                     move |_| selected_marker.set(sel)
                                 },
-                                p { class: "coord-info",
-                                    if target_positions.len() > 1 {
-                                        "Target {ti + 1}: {coords::format_px_as_grid(t.0, t.1)} (unassigned)"
-                                    } else {
-                                        "Target: {coords::format_px_as_grid(t.0, t.1)} (unassigned)"
-                                    }
+                                p { class: "coord-info{status_class(target_status(&statuses, ti))}",
+                                    "{name}: {coords::format_px_as_grid(t.0, t.1, map_scale)} (unassigned)"
                                 }
-                                button {
-                                    class: "remove-marker-btn",
-                                    title: "Remove target",
-                                    onclick: move |evt: Event<MouseData>| {
-                                        evt.stop_propagation();
-                                        on_remove.call((MarkerKind::Target, ti));
+                                TargetStatusButton {
+                                    status: target_status(&statuses, ti),
+                                    disabled: read_only,
+                                    on_change: move |status| {
+                                        on_before_change.call(());
+                                        set_target_status(&mut target_statuses.write(), ti, status);
                                     },
-                                    "\u{2715}"
+                                }
+                                if !read_only {
+                                    button {
+                                        class: "remove-marker-btn",
+                                        title: "Remove target",
+                                        onclick: move |evt: Event<MouseData>| {
+                                            evt.stop_propagation();
+                                            on_remove.call((MarkerKind::Target, ti));
+                                        },
+                                        "\u{2715}"
+                                    }
                                 }
                             }
                         }
@@ -329,7 +1314,7 @@ pub fn CalculationDisplay(
             }
 
             // Spotters (informational) — clickable
-            for (i, s) in spotter_positions.iter().enumerate() {
+            for (i, (s, name)) in spotter_positions.iter().zip(&spotter_names).enumerate() {
                 {
                     let is_selected = cur_selected == Some(SelectedMarker { kind: MarkerKind::Spotter, index: i });
                     let cls = if is_selected { "marker-item selected" } else { "marker-item" };
@@ -341,20 +1326,50 @@ pub fn CalculationDisplay(
                                 move |_| selected_marker.set(sel)
                             },
                             p { class: "coord-info spotter-coord",
-                                if spotter_positions.len() > 1 {
-                                    "Spt {i + 1}: {coords::format_px_as_grid(s.0, s.1)}"
-                                } else {
-                                    "Spt: {coords::format_px_as_grid(s.0, s.1)}"
+                                "{name}: {coords::format_px_as_grid(s.0, s.1, map_scale)}"
+                            }
+                            if !read_only {
+                                button {
+                                    class: "remove-marker-btn",
+                                    title: "Remove spotter",
+                                    onclick: move |evt: Event<MouseData>| {
+                                        evt.stop_propagation();
+                                        on_remove.call((MarkerKind::Spotter, i));
+                                    },
+                                    "\u{2715}"
                                 }
                             }
-                            button {
-                                class: "remove-marker-btn",
-                                title: "Remove spotter",
-                                onclick: move |evt: Event<MouseData>| {
-                                    evt.stop_propagation();
-                                    on_remove.call((MarkerKind::Spotter, i));
-                                },
-                                "\u{2715}"
+                        }
+                    }
+                }
+            }
+
+            // Displacement order: each gun's alternate positions with their solutions
+            if !displacements.is_empty() {
+                h4 { style: "margin: 8px 0 4px; color: var(--text-dim);", "Displacement order" }
+                for step in displacements.clone() {
+                    {
+                        let gun_name = marker_name(&labels, MarkerKind::Gun, step.gun - 1, "Gun", gun_positions.len());
+                        let cls = match step.in_range {
+                            Some(true) => "coord-info displacement-step in-range-text",
+                            Some(false) => "coord-info displacement-step out-of-range-text",
+                            None => "coord-info displacement-step",
+                        };
+                        rsx! {
+                            div { class: "marker-item",
+                                p { class: "{cls}", "{displacement_text(&gun_name, &step)}" }
+                                if !read_only {
+                                    button {
+                                        class: "remove-marker-btn",
+                                        title: "Remove position",
+                                        onclick: move |evt: Event<MouseData>| {
+                                            evt.stop_propagation();
+                                            on_before_change.call(());
+                                            remove_displacement_stop(&mut gun_alternates.write(), step.gun, step.position);
+                                        },
+                                        "\u{2715}"
+                                    }
+                                }
                             }
                         }
                     }
@@ -367,6 +1382,289 @@ pub fn CalculationDisplay(
                     "Place gun and target to calculate."
                 }
             }
+
+            // Barrage sheet export
+            if !missions.is_empty() {
+                {
+                    let csv = export::to_csv(&missions);
+                    let chat = export::to_chat_text(&missions);
+                    let text = export::to_discord_text(&plan_name, &missions, &displacements);
+                    let label = *copied.read();
+                    rsx! {
+                        div { class: "export-row",
+                            button {
+                                class: "secondary",
+                                title: "Copy fire missions as CSV",
+                                onclick: move |_| {
+                                    export::copy_to_clipboard(csv.clone());
+                                    copied.set(Some("csv"));
+                                    spawn(async move {
+                                        gloo_timers::future::TimeoutFuture::new(2000).await;
+                                        copied.set(None);
+                                    });
+                                },
+                                if label == Some("csv") { "Copied!" } else { "Copy CSV" }
+                            }
+                            button {
+                                class: "secondary",
+                                title: "Copy fire missions as a text table for Discord",
+                                onclick: move |_| {
+                                    export::copy_to_clipboard(text.clone());
+                                    copied.set(Some("discord"));
+                                    spawn(async move {
+                                        gloo_timers::future::TimeoutFuture::new(2000).await;
+                                        copied.set(None);
+                                    });
+                                },
+                                if label == Some("discord") { "Copied!" } else { "Copy for Discord" }
+                            }
+                            button {
+                                class: "secondary",
+                                title: "Copy every gun's solution, one line each, for squad chat",
+                                onclick: move |_| {
+                                    export::copy_to_clipboard(chat.clone());
+                                    copied.set(Some("chat"));
+                                    spawn(async move {
+                                        gloo_timers::future::TimeoutFuture::new(2000).await;
+                                        copied.set(None);
+                                    });
+                                },
+                                if label == Some("chat") { "Copied!" } else { "Copy all" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Shows a target's status; clicking moves it on (active, fired upon, destroyed).
+#[component]
+fn TargetStatusButton(
+    status: TargetStatus,
+    disabled: bool,
+    on_change: EventHandler<TargetStatus>,
+) -> Element {
+    let next = status.next();
+    rsx! {
+        button {
+            class: "target-status-btn{status_class(status)}",
+            disabled,
+            title: "Mark {next.label().to_lowercase()}",
+            onclick: move |evt: Event<MouseData>| {
+                evt.stop_propagation();
+                on_change.call(next);
+            },
+            "{status.label()}"
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::models::ImpactZone;
+
+    #[test]
+    fn test_corrected_aim() {
+        let gun = Position { x: 500.0, y: 900.0 };
+        let target = Position { x: 500.0, y: 800.0 };
+        // Spotter east of the target, looking west: long is west, right is north
+        let spotter = Position { x: 700.0, y: 800.0 };
+        let (aim, az, dist) = corrected_aim(gun, target, target, spotter, "", " -20 ").unwrap();
+        assert!((aim.x - 500.0).abs() < 1e-9 && (aim.y - 780.0).abs() < 1e-9);
+        assert!(az.abs() < 1e-9 || (az - 360.0).abs() < 1e-9);
+        assert!((dist - 120.0).abs() < 1e-9);
+        assert_eq!(corrected_aim(gun, target, target, spotter, "far", ""), None);
+    }
+
+    #[test]
+    fn test_impact_offsets() {
+        assert_eq!(impact_offsets("12", ""), Some((12.0, 0.0)));
+        assert_eq!(impact_offsets(" -4.5 ", "8"), Some((-4.5, 8.0)));
+        assert_eq!(impact_offsets("400", "0"), None);
+        assert_eq!(impact_offsets("north", "0"), None);
+    }
+
+    #[test]
+    fn test_firing_text_rounds_as_dialed() {
+        assert_eq!(firing_text(92.34, 183.0), "AZ 92.3\u{00b0} / 185 m");
+        assert_eq!(firing_text(0.04, 2.0), "AZ 0.0\u{00b0} / 0 m");
+    }
+
+    #[test]
+    fn test_shot_log_lines() {
+        assert_eq!(splash_report(20.0, -5.0), "20 m long, 5 m left");
+        assert_eq!(splash_report(-15.0, 0.0), "15 m short");
+        assert_eq!(splash_report(0.2, 0.0), "on target");
+        let mut shot = ShotRecord {
+            gun: 0,
+            target: Some(1),
+            azimuth: 92.34,
+            distance: 183.0,
+            result: "15 m short".to_string(),
+        };
+        assert_eq!(
+            shot_text(&shot, Some("Tgt 2")),
+            "AZ 92.3\u{00b0} / 185 m \u{2192} Tgt 2 \u{2014} 15 m short"
+        );
+        shot.result.clear();
+        assert_eq!(shot_text(&shot, None), "AZ 92.3\u{00b0} / 185 m");
+    }
+
+    #[test]
+    fn test_grid_position() {
+        let map = MapScale::STANDARD;
+        let (mx, my) = grid::parse_grid_coord("G9k3").unwrap();
+        assert_eq!(grid_position(" g9k3 ", map), Ok(map.meters_to_px(mx, my)));
+        assert!(grid_position("Z9", map).unwrap_err().contains("G9k3"));
+        // A custom image covering only the north-west corner of the region
+        let corner = MapScale::calibrated(
+            1000.0,
+            1000.0,
+            ((0.0, 0.0), (0.0, 0.0)),
+            ((1000.0, 1000.0), (500.0, 500.0)),
+        )
+        .unwrap();
+        assert!(grid_position("A1", corner).is_ok());
+        assert!(grid_position("Q15", corner).unwrap_err().contains("not on"));
+    }
+
+    fn sol_with_tof(tof: Option<f64>) -> Option<FiringSolutionData> {
+        Some(FiringSolutionData {
+            azimuth: 0.0,
+            distance: 100.0,
+            in_range: true,
+            accuracy_radius: 10.0,
+            wind_adjusted_azimuth: None,
+            wind_adjusted_distance: None,
+            wind_offset_meters: None,
+            time_of_flight: tof,
+            impact_zone: None,
+        })
+    }
+
+    #[test]
+    fn test_status_class() {
+        assert_eq!(status_class(TargetStatus::Active), "");
+        assert_eq!(status_class(TargetStatus::Destroyed), " target-destroyed");
+    }
+
+    #[test]
+    fn test_remove_displacement_stop() {
+        let mut alternates = vec![vec![(1.0, 1.0), (2.0, 2.0)], vec![(3.0, 3.0)]];
+        remove_displacement_stop(&mut alternates, 1, 2);
+        assert_eq!(alternates[0], vec![(2.0, 2.0)]);
+        // Out-of-range numbers are ignored
+        remove_displacement_stop(&mut alternates, 2, 5);
+        remove_displacement_stop(&mut alternates, 3, 2);
+        assert_eq!(alternates[1], vec![(3.0, 3.0)]);
+    }
+
+    #[test]
+    fn test_displacement_text() {
+        let mut step = export::DisplacementStep {
+            gun: 1,
+            position: 2,
+            grid: "G9k3".to_string(),
+            target: Some(1),
+            azimuth: Some(270.04),
+            distance: Some(52.0),
+            wind_azimuth: None,
+            wind_distance: None,
+            in_range: Some(true),
+        };
+        assert_eq!(
+            displacement_text("Gun 1", &step),
+            "Gun 1 \u{2192} 2: G9k3  270.0\u{00b0}  50m"
+        );
+        step.azimuth = None;
+        step.distance = None;
+        assert_eq!(displacement_text("Alpha", &step), "Alpha \u{2192} 2: G9k3");
+    }
+
+    #[test]
+    fn test_set_salvo_size_fills_earlier_guns() {
+        let mut sizes = Vec::new();
+        set_salvo_size(&mut sizes, 2, 4);
+        assert_eq!(sizes, vec![1, 1, 4]);
+        set_salvo_size(&mut sizes, 0, 99);
+        assert_eq!(sizes, vec![MAX_SALVO, 1, 4]);
+        set_salvo_size(&mut sizes, 1, 0);
+        assert_eq!(sizes, vec![MAX_SALVO, 1, 4]);
+    }
+
+    #[test]
+    fn test_danger_close_text() {
+        assert_eq!(danger_close_text(&[]), None);
+        assert_eq!(
+            danger_close_text(&[1]).as_deref(),
+            Some("DANGER CLOSE: shells may land in no-fire zone 2")
+        );
+        assert_eq!(
+            danger_close_text(&[0, 2]).as_deref(),
+            Some("DANGER CLOSE: shells may land in no-fire zones 1, 3")
+        );
+    }
+
+    #[test]
+    fn test_hit_chance_text() {
+        let mut sol = sol_with_tof(None).unwrap();
+        sol.accuracy_radius = 20.0;
+        assert_eq!(hit_chance_text(&sol, 10.0, 1), "25%");
+        assert_eq!(hit_chance_text(&sol, 10.0, 2), "25% (44% in 2)");
+        assert_eq!(spread_stat(&sol), ("Accuracy", "\u{00b1}20.0m".to_string()));
+
+        // Rockets spread over a 40 x 20 m zone, half the circle's area
+        sol.impact_zone = Some(ImpactZone {
+            length: 40.0,
+            width: 20.0,
+            azimuth: 0.0,
+        });
+        assert_eq!(hit_chance_text(&sol, 10.0, 1), "50%");
+        assert_eq!(
+            spread_stat(&sol),
+            ("Impact Zone", "40\u{00d7}20m".to_string())
+        );
+    }
+
+    #[test]
+    fn test_salvo_delays_shared_target() {
+        let solutions = vec![sol_with_tof(Some(8.0)), sol_with_tof(Some(5.0))];
+        let delays = salvo_delays(&solutions, &[Some(0), Some(0)]);
+        assert_eq!(delays, vec![Some(0.0), Some(3.0)]);
+    }
+
+    #[test]
+    fn test_salvo_delays_single_gun_per_target() {
+        let solutions = vec![sol_with_tof(Some(8.0)), sol_with_tof(Some(5.0))];
+        let delays = salvo_delays(&solutions, &[Some(0), Some(1)]);
+        assert_eq!(delays, vec![None, None]);
+    }
+
+    #[test]
+    fn test_salvo_delays_skips_unknown_flight_time() {
+        let solutions = vec![
+            sol_with_tof(Some(8.0)),
+            sol_with_tof(None),
+            sol_with_tof(Some(6.0)),
+        ];
+        let delays = salvo_delays(&solutions, &[Some(0), Some(0), Some(0)]);
+        assert_eq!(delays, vec![Some(0.0), None, Some(2.0)]);
+    }
+
+    #[test]
+    fn test_marker_name_prefers_label() {
+        let labels = MarkerLabels {
+            guns: vec!["Alpha gun".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            marker_name(&labels, MarkerKind::Gun, 0, "Gun", 2),
+            "Alpha gun"
+        );
+        assert_eq!(marker_name(&labels, MarkerKind::Gun, 1, "Gun", 2), "Gun 2");
+        assert_eq!(marker_name(&labels, MarkerKind::Target, 0, "Tgt", 1), "Tgt");
+    }
+}
@@ -0,0 +1,244 @@
+use dioxus::prelude::*;
+use foxhole_shared::grid;
+use foxhole_shared::models::{CalibrationPoint, MapCalibration, Position};
+
+/// Text of one reference point's inputs: image pixel x, y and a grid reference.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct PointFields {
+    x: String,
+    y: String,
+    grid: String,
+}
+
+impl PointFields {
+    fn from_point(point: &CalibrationPoint) -> Self {
+        PointFields {
+            x: format!("{}", point.px.x),
+            y: format!("{}", point.px.y),
+            grid: grid::format_grid_coord(point.meters.x, point.meters.y),
+        }
+    }
+}
+
+/// Build a calibration from the panel's inputs, or say what's wrong with them.
+fn parse_calibration(
+    image_url: &str,
+    width: &str,
+    height: &str,
+    points: &[PointFields; 2],
+) -> Result<MapCalibration, String> {
+    let image_url = image_url.trim();
+    if !(image_url.starts_with("https://") || image_url.starts_with("http://")) {
+        return Err("Image URL must start with http:// or https://".to_string());
+    }
+    let side = |v: &str, name: &str| {
+        v.trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("Image {name} must be a whole number of pixels"))
+    };
+    let (width, height) = (side(width, "width")?, side(height, "height")?);
+    let mut parsed = Vec::with_capacity(2);
+    for (i, p) in points.iter().enumerate() {
+        let n = i + 1;
+        let px = p.x.trim().parse::<f64>().ok();
+        let py = p.y.trim().parse::<f64>().ok();
+        let (Some(px), Some(py)) = (px, py) else {
+            return Err(format!("Point {n}: enter the pixel x and y on the image"));
+        };
+        if !(0.0..=width as f64).contains(&px) || !(0.0..=height as f64).contains(&py) {
+            return Err(format!("Point {n}: pixel is outside the image"));
+        }
+        let Some((mx, my)) = grid::parse_grid_coord(&p.grid) else {
+            return Err(format!("Point {n}: enter a grid reference like G9k3"));
+        };
+        parsed.push(CalibrationPoint {
+            px: Position { x: px, y: py },
+            meters: Position { x: mx, y: my },
+        });
+    }
+    let calibration = MapCalibration {
+        image_url: image_url.to_string(),
+        width,
+        height,
+        points: [parsed[0], parsed[1]],
+    };
+    if calibration.scale().is_none() {
+        return Err("Pick points apart in both directions, matching the map's layout".to_string());
+    }
+    Ok(calibration)
+}
+
+/// Use a custom map image (a modded variant, say) lined up by two reference points.
+#[component]
+pub fn CalibrationPanel(
+    calibration: Option<MapCalibration>,
+    on_change: EventHandler<Option<MapCalibration>>,
+) -> Element {
+    let initial = calibration.clone();
+    let mut image_url = use_signal(|| {
+        initial
+            .as_ref()
+            .map(|c| c.image_url.clone())
+            .unwrap_or_default()
+    });
+    let mut width = use_signal(|| {
+        initial
+            .as_ref()
+            .map(|c| c.width.to_string())
+            .unwrap_or_default()
+    });
+    let mut height = use_signal(|| {
+        initial
+            .as_ref()
+            .map(|c| c.height.to_string())
+            .unwrap_or_default()
+    });
+    let mut points = use_signal(|| {
+        initial
+            .as_ref()
+            .map(|c| c.points.each_ref().map(PointFields::from_point))
+            .unwrap_or_default()
+    });
+    let mut error = use_signal(|| None::<String>);
+    let active = calibration.is_some();
+
+    rsx! {
+        div { class: "panel calibration-panel",
+            h3 { "Custom map image" }
+            input {
+                r#type: "url",
+                "aria-label": "Custom map image URL",
+                placeholder: "https://…/map.png",
+                value: "{image_url}",
+                oninput: move |evt: Event<FormData>| image_url.set(evt.value()),
+            }
+            div { class: "calibration-size",
+                input {
+                    r#type: "number",
+                    min: "1",
+                    "aria-label": "Image width",
+                    placeholder: "Width px",
+                    value: "{width}",
+                    oninput: move |evt: Event<FormData>| width.set(evt.value()),
+                }
+                input {
+                    r#type: "number",
+                    min: "1",
+                    "aria-label": "Image height",
+                    placeholder: "Height px",
+                    value: "{height}",
+                    oninput: move |evt: Event<FormData>| height.set(evt.value()),
+                }
+            }
+            for i in 0..2 {
+                div { class: "calibration-point",
+                    span { "{i + 1}" }
+                    input {
+                        r#type: "number",
+                        "aria-label": "Point {i + 1} image x",
+                        placeholder: "x px",
+                        value: "{points.read()[i].x}",
+                        oninput: move |evt: Event<FormData>| points.write()[i].x = evt.value(),
+                    }
+                    input {
+                        r#type: "number",
+                        "aria-label": "Point {i + 1} image y",
+                        placeholder: "y px",
+                        value: "{points.read()[i].y}",
+                        oninput: move |evt: Event<FormData>| points.write()[i].y = evt.value(),
+                    }
+                    input {
+                        r#type: "text",
+                        "aria-label": "Point {i + 1} grid reference",
+                        placeholder: "G9k3",
+                        value: "{points.read()[i].grid}",
+                        oninput: move |evt: Event<FormData>| points.write()[i].grid = evt.value(),
+                    }
+                }
+            }
+            div { class: "calibration-actions",
+                button {
+                    onclick: move |_| {
+                        match parse_calibration(
+                            &image_url.read(),
+                            &width.read(),
+                            &height.read(),
+                            &points.read(),
+                        ) {
+                            Ok(c) => {
+                                error.set(None);
+                                on_change.call(Some(c));
+                            }
+                            Err(e) => error.set(Some(e)),
+                        }
+                    },
+                    "Apply"
+                }
+                if active {
+                    button {
+                        class: "secondary",
+                        onclick: move |_| {
+                            error.set(None);
+                            on_change.call(None);
+                        },
+                        "Use stock map"
+                    }
+                }
+            }
+            if let Some(err) = error.read().clone() {
+                p { class: "calibration-error", "{err}" }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(x: &str, y: &str, grid: &str) -> PointFields {
+        PointFields {
+            x: x.to_string(),
+            y: y.to_string(),
+            grid: grid.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_calibration() {
+        let points = [fields("100", "120", "B2k5"), fields("900", "700", "N12k5")];
+        let c = parse_calibration(" https://example.com/map.png ", "1000", "800", &points).unwrap();
+        assert_eq!(c.image_url, "https://example.com/map.png");
+        assert_eq!((c.width, c.height), (1000, 800));
+        let (mx, my) = grid::parse_grid_coord("N12k5").unwrap();
+        assert_eq!(c.points[1].meters, Position { x: mx, y: my });
+        let scale = c.scale().unwrap();
+        let (x, y) = scale.px_to_meters(900.0, 700.0);
+        assert!((x - mx).abs() < 1e-9 && (y - my).abs() < 1e-9);
+
+        // Round-trips back into the inputs
+        assert_eq!(
+            PointFields::from_point(&c.points[0]),
+            fields("100", "120", "B2k5")
+        );
+    }
+
+    #[test]
+    fn test_parse_calibration_errors() {
+        let good = [fields("100", "120", "B2k5"), fields("900", "700", "N12k5")];
+        let err = |url: &str, w: &str, points: &[PointFields; 2]| {
+            parse_calibration(url, w, "800", points).unwrap_err()
+        };
+        assert!(err("ftp://x/map.png", "1000", &good).contains("http"));
+        assert!(err("https://x/map.png", "0", &good).contains("width"));
+        let off_image = [good[0].clone(), fields("1200", "700", "N12k5")];
+        assert!(err("https://x/map.png", "1000", &off_image).contains("outside"));
+        let bad_grid = [good[0].clone(), fields("900", "700", "Z99")];
+        assert!(err("https://x/map.png", "1000", &bad_grid).contains("grid reference"));
+        // Same row: the y scale can't be worked out
+        let same_row = [good[0].clone(), fields("900", "120", "N2k5")];
+        assert!(err("https://x/map.png", "1000", &same_row).contains("apart"));
+    }
+}
@@ -0,0 +1,159 @@
+use dioxus::html::input_data::keyboard_types::Key;
+use dioxus::prelude::*;
+
+use foxhole_shared::models::TargetStatus;
+
+use crate::components::map_view::{MarkerKind, SelectedMarker};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContextAction {
+    Remove,
+    Select,
+    Pair,
+    Duplicate,
+    /// Move a target on to this status.
+    SetStatus(TargetStatus),
+}
+
+/// An open context menu: where it sits (container-relative px) and which marker it targets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContextMenuState {
+    pub x: f64,
+    pub y: f64,
+    pub marker: SelectedMarker,
+}
+
+/// Label for the pair action, or `None` when pairing isn't possible.
+///
+/// Pairing links the right-clicked marker with the currently selected one,
+/// so it needs one gun and one target between them.
+pub fn pair_label(
+    marker: SelectedMarker,
+    selected: Option<SelectedMarker>,
+) -> Option<&'static str> {
+    let sel = selected?;
+    match (marker.kind, sel.kind) {
+        (MarkerKind::Target, MarkerKind::Gun) => Some("Pair with selected gun"),
+        (MarkerKind::Gun, MarkerKind::Target) => Some("Pair with selected target"),
+        _ => None,
+    }
+}
+
+fn kind_name(kind: MarkerKind) -> &'static str {
+    match kind {
+        MarkerKind::Gun => "gun",
+        MarkerKind::Target => "target",
+        MarkerKind::Spotter => "spotter",
+        MarkerKind::Enemy => "enemy gun",
+        MarkerKind::Fob => "FOB",
+        MarkerKind::Bunker => "observation bunker",
+        MarkerKind::NoFire => "no-fire zone",
+    }
+}
+
+#[component]
+pub fn ContextMenu(
+    state: ContextMenuState,
+    selected: Option<SelectedMarker>,
+    /// Current status when the menu is for a target.
+    #[props(default)]
+    target_status: Option<TargetStatus>,
+    on_action: EventHandler<ContextAction>,
+    on_close: EventHandler<()>,
+) -> Element {
+    let name = kind_name(state.marker.kind);
+    let pair = pair_label(state.marker, selected);
+    let is_selected = selected == Some(state.marker);
+    let style = format!("left: {}px; top: {}px;", state.x, state.y);
+
+    rsx! {
+        div {
+            class: "context-menu",
+            role: "menu",
+            style: "{style}",
+            // Keep clicks inside the menu from reaching the map (pan/placement)
+            onmousedown: move |evt: Event<MouseData>| evt.stop_propagation(),
+            onmouseup: move |evt: Event<MouseData>| evt.stop_propagation(),
+            oncontextmenu: move |evt: Event<MouseData>| {
+                evt.prevent_default();
+                evt.stop_propagation();
+            },
+            onkeydown: move |evt: Event<KeyboardData>| {
+                if evt.key() == Key::Escape {
+                    evt.stop_propagation();
+                    on_close.call(());
+                }
+            },
+
+            if !is_selected {
+                button {
+                    role: "menuitem",
+                    onclick: move |_| on_action.call(ContextAction::Select),
+                    "Select {name}"
+                }
+            }
+            if let Some(label) = pair {
+                button {
+                    role: "menuitem",
+                    onclick: move |_| on_action.call(ContextAction::Pair),
+                    "{label}"
+                }
+            }
+            if let Some(status) = target_status {
+                {
+                    let next = status.next();
+                    rsx! {
+                        button {
+                            role: "menuitem",
+                            onclick: move |_| on_action.call(ContextAction::SetStatus(next)),
+                            "Mark {next.label().to_lowercase()}"
+                        }
+                    }
+                }
+            }
+            button {
+                role: "menuitem",
+                onclick: move |_| on_action.call(ContextAction::Duplicate),
+                "Duplicate {name}"
+            }
+            button {
+                role: "menuitem",
+                class: "danger",
+                onclick: move |_| on_action.call(ContextAction::Remove),
+                "Remove {name}"
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(kind: MarkerKind, index: usize) -> SelectedMarker {
+        SelectedMarker { kind, index }
+    }
+
+    #[test]
+    fn test_pair_label_gun_and_target() {
+        let gun = marker(MarkerKind::Gun, 0);
+        let target = marker(MarkerKind::Target, 1);
+        assert_eq!(
+            pair_label(target, Some(gun)),
+            Some("Pair with selected gun")
+        );
+        assert_eq!(
+            pair_label(gun, Some(target)),
+            Some("Pair with selected target")
+        );
+    }
+
+    #[test]
+    fn test_pair_label_unavailable() {
+        let gun = marker(MarkerKind::Gun, 0);
+        let spotter = marker(MarkerKind::Spotter, 0);
+        assert_eq!(pair_label(gun, None), None);
+        assert_eq!(pair_label(gun, Some(marker(MarkerKind::Gun, 1))), None);
+        assert_eq!(pair_label(spotter, Some(gun)), None);
+    }
+}
@@ -0,0 +1,91 @@
+use dioxus::prelude::*;
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::overlay::threatened_guns;
+
+use crate::api::WeaponData;
+
+#[component]
+pub fn CounterBatteryPanel(
+    weapons: Vec<WeaponData>,
+    enemy_weapon: Signal<String>,
+    gun_positions: Vec<(f64, f64)>,
+    enemy_positions: Vec<(f64, f64)>,
+    map_scale: MapScale,
+    on_clear: EventHandler<()>,
+) -> Element {
+    let colonial: Vec<&WeaponData> = weapons
+        .iter()
+        .filter(|w| w.faction == "COLONIAL" || w.faction == "BOTH")
+        .collect();
+    let warden: Vec<&WeaponData> = weapons
+        .iter()
+        .filter(|w| w.faction == "WARDEN" || w.faction == "BOTH")
+        .collect();
+
+    let current = enemy_weapon.read().clone();
+    let threatened: Vec<usize> = weapons
+        .iter()
+        .find(|w| w.slug == current)
+        .map(|w| threatened_guns(&gun_positions, &enemy_positions, &w.to_weapon(), map_scale))
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &hit)| hit.then_some(i))
+        .collect();
+    let enemy_count = enemy_positions.len();
+
+    rsx! {
+        div { class: "panel",
+            h3 { "Counter-Battery" }
+            select {
+                "aria-label": "Select enemy weapon",
+                value: "{current}",
+                onchange: move |evt: Event<FormData>| {
+                    enemy_weapon.set(evt.value().to_string());
+                },
+                option { value: "", "-- Enemy Weapon --" }
+                optgroup { label: "Colonial",
+                    for w in colonial {
+                        option {
+                            value: "{w.slug}",
+                            selected: current == w.slug,
+                            "{w.display_name} ({w.min_range}-{w.max_range}m)"
+                        }
+                    }
+                }
+                optgroup { label: "Warden",
+                    for w in warden {
+                        option {
+                            value: "{w.slug}",
+                            selected: current == w.slug,
+                            "{w.display_name} ({w.min_range}-{w.max_range}m)"
+                        }
+                    }
+                }
+            }
+            if enemy_count == 0 {
+                p { style: "color: var(--text-dim); font-size: 13px; margin-top: 8px;",
+                    "Use Enemy mode (4) to mark known enemy guns. Shaded rings around your guns show where that weapon could fire from."
+                }
+            } else if current.is_empty() {
+                p { style: "color: var(--text-dim); font-size: 13px; margin-top: 8px;", "Pick the enemy weapon to check your guns." }
+            } else if threatened.is_empty() {
+                p { class: "in-range-text", style: "font-size: 13px; margin-top: 8px;", "No guns within reach of marked enemies." }
+            } else {
+                for gi in threatened {
+                    p { class: "coord-info out-of-range-text",
+                        "Gun {gi + 1} is within enemy reach"
+                    }
+                }
+            }
+            if enemy_count > 0 {
+                button {
+                    class: "secondary",
+                    style: "width: 100%; margin-top: 8px;",
+                    onclick: move |_| on_clear.call(()),
+                    "Clear {enemy_count} enemy marker(s)"
+                }
+            }
+        }
+    }
+}
@@ -33,13 +33,45 @@ pub fn HelpOverlay(show: Signal<bool>) -> Element {
                         span { class: "shortcut-keys", kbd { "3" } " / " kbd { "S" } }
                         span { "Spotter mode" }
                     }
+                    div { class: "shortcut-row",
+                        span { class: "shortcut-keys", kbd { "4" } " / " kbd { "E" } }
+                        span { "Enemy gun mode" }
+                    }
+                    div { class: "shortcut-row",
+                        span { class: "shortcut-keys", kbd { "5" } " / " kbd { "M" } }
+                        span { "Measure mode" }
+                    }
+                    div { class: "shortcut-row",
+                        span { class: "shortcut-keys", kbd { "6" } " / " kbd { "D" } }
+                        span { "Displacement mode" }
+                    }
+                    div { class: "shortcut-row",
+                        span { class: "shortcut-keys", kbd { "7" } " / " kbd { "F" } }
+                        span { "FOB mode" }
+                    }
+                    div { class: "shortcut-row",
+                        span { class: "shortcut-keys", kbd { "8" } " / " kbd { "B" } }
+                        span { "Observation bunker mode" }
+                    }
+                    div { class: "shortcut-row",
+                        span { class: "shortcut-keys", kbd { "9" } " / " kbd { "N" } }
+                        span { "No-fire zone mode" }
+                    }
                 }
 
                 div { class: "shortcut-section",
                     h3 { "Actions" }
                     div { class: "shortcut-row",
                         span { class: "shortcut-keys", kbd { "Del" } " / " kbd { "Backspace" } }
-                        span { "Remove selected marker" }
+                        span { "Remove selected marker(s)" }
+                    }
+                    div { class: "shortcut-row",
+                        span { class: "shortcut-keys", kbd { "Shift" } "+click / drag" }
+                        span { "Add markers to a group selection" }
+                    }
+                    div { class: "shortcut-row",
+                        span { class: "shortcut-keys", kbd { "\u{2190}" } kbd { "\u{2191}" } kbd { "\u{2192}" } kbd { "\u{2193}" } }
+                        span { "Nudge selection 10 m (Shift: 50 m)" }
                     }
                     div { class: "shortcut-row",
                         span { class: "shortcut-keys", kbd { "Esc" } }
@@ -92,6 +124,11 @@ pub fn HelpOverlay(show: Signal<bool>) -> Element {
                     p { "The radius of the impact circle around the target, shown as \u{00b1}Xm. Accuracy worsens with distance \u{2014} it interpolates linearly from the weapon's best accuracy at minimum range to worst accuracy at maximum range." }
                 }
 
+                div { class: "help-info-section",
+                    h3 { "Impact Zone" }
+                    p { "Rocket artillery fires area salvos rather than point shots. Its rockets string out along the line of fire, so it shows an impact zone instead of a circle, given as length \u{00d7} width: as long as the accuracy circle is wide, half as wide, and turned along the bearing the salvo is fired on." }
+                }
+
                 div { class: "help-info-section",
                     h3 { "Wind Compensation" }
                     p { "Wind pushes shells in the direction it blows toward (opposite of the \u{201c}from\u{201d} direction). Wind drift varies per weapon and increases with range \u{2014} it interpolates linearly from the weapon\u{2019}s minimum drift at close range to maximum drift at max range, then scales by wind strength (0\u{2013}5, divided by 5). The planner compensates by shifting the aim point against the wind and recalculating azimuth and distance to that corrected point." }
@@ -102,9 +139,19 @@ pub fn HelpOverlay(show: Signal<bool>) -> Element {
                     p { "Each gun is independently paired with a target. New guns auto-pair with the first unpaired target. Click a target while a gun is selected to manually pair them. Multiple guns can share the same target." }
                 }
 
+                div { class: "help-info-section",
+                    h3 { "Counter-Battery" }
+                    p { "Mark known enemy guns in Enemy mode and pick their weapon in the Counter-Battery panel. Each of your guns gets a shaded ring showing where that weapon would have to sit to hit it \u{2014} the ring darkens when a marked enemy is inside, meaning that gun is within retaliation range." }
+                }
+
+                div { class: "help-info-section",
+                    h3 { "Intel Markers" }
+                    p { "FOB, Bunker and No-fire modes mark friendly forward bases, observation bunkers and areas your shells must stay out of. In No-fire mode, click a zone's centre and then its edge (or the centre again for 50 m). When a gun's accuracy circle reaches a zone, its firing solution shows a DANGER CLOSE warning and the zone is outlined on the map. Intel markers and enemy guns are saved with the plan and merged like the rest, but they can't be named or paired." }
+                }
+
                 div { class: "help-info-section",
                     h3 { "Map Interactions" }
-                    p { "Left-click places markers or moves a selected one. Right-click removes the nearest marker. Scroll to zoom, drag to pan, double-click to reset view." }
+                    p { "Left-click places markers or moves a selected one; while a marker is selected, a readout next to the cursor previews the azimuth and distance it would get. Right-click a marker for a menu to select, pair, duplicate, or remove it (or enable instant remove under Preferences). Scroll to zoom, drag to pan, double-click to reset view. When zoomed out, nearby targets merge into a numbered badge \u{2014} click it to zoom in and expand them." }
                 }
 
                 button {
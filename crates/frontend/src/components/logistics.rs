@@ -0,0 +1,199 @@
+use dioxus::prelude::*;
+use foxhole_shared::logistics::{self, Shell, COVERAGE_CONFIDENCE};
+
+use crate::api::FiringSolutionData;
+use crate::components::calculation_display::marker_name;
+use crate::components::map_view::{MarkerKind, MarkerLabels};
+
+/// Coverage estimate for each target, from its most accurate paired gun.
+fn target_estimates(
+    solutions: &[Option<FiringSolutionData>],
+    pairings: &[Option<usize>],
+    targets: usize,
+    target_radius: f64,
+) -> Vec<Option<u32>> {
+    (0..targets)
+        .map(|ti| {
+            pairings
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| **p == Some(ti))
+                .filter_map(|(gi, _)| solutions.get(gi)?.as_ref())
+                .map(|s| s.accuracy_radius)
+                .min_by(f64::total_cmp)
+                .and_then(|radius| logistics::coverage_estimate(radius, target_radius))
+        })
+        .collect()
+}
+
+/// Shells by type when each target gets `shells[target]`, split evenly over
+/// the guns paired to it. Guns whose weapon has no known shell are left out.
+fn barrage_demand(
+    shells: &[u32],
+    pairings: &[Option<usize>],
+    weapon_ids: &[String],
+) -> Vec<(Shell, u32)> {
+    let mut demand = Vec::new();
+    for (ti, &n) in shells.iter().enumerate() {
+        let guns: Vec<Shell> = pairings
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| **p == Some(ti))
+            .filter_map(|(gi, _)| Shell::for_weapon(weapon_ids.get(gi)?))
+            .collect();
+        if guns.is_empty() {
+            continue;
+        }
+        let k = guns.len() as u32;
+        for (i, shell) in guns.into_iter().enumerate() {
+            let extra = u32::from((i as u32) < n % k);
+            demand.push((shell, n / k + extra));
+        }
+    }
+    demand
+}
+
+/// Shells, crates and vehicle loads for the planned barrage.
+#[component]
+pub fn LogisticsPanel(
+    solutions: Vec<Option<FiringSolutionData>>,
+    gun_weapon_ids: Vec<String>,
+    gun_target_indices: Vec<Option<usize>>,
+    target_count: usize,
+    marker_labels: MarkerLabels,
+    /// Radius (m) a shell must land within to count as a hit.
+    target_radius: f64,
+) -> Element {
+    // Shells typed in per target; blank ones use the estimate
+    let mut overrides = use_signal(Vec::<Option<u32>>::new);
+
+    let estimates = target_estimates(&solutions, &gun_target_indices, target_count, target_radius);
+    let typed = overrides.read().clone();
+    let shells: Vec<u32> = (0..target_count)
+        .map(|ti| {
+            typed
+                .get(ti)
+                .copied()
+                .flatten()
+                .or(estimates[ti])
+                .unwrap_or(0)
+        })
+        .collect();
+    let req = logistics::requisition(&barrage_demand(
+        &shells,
+        &gun_target_indices,
+        &gun_weapon_ids,
+    ));
+    let confidence = (COVERAGE_CONFIDENCE * 100.0).round();
+
+    rsx! {
+        div { class: "panel logistics",
+            h3 { "Logistics" }
+            if target_count == 0 {
+                p { class: "logistics-hint",
+                    "Place targets and pair guns to see the shells, crates and trucks a barrage needs."
+                }
+            } else {
+                ul {
+                    for ti in 0..target_count {
+                        {
+                            let name = marker_name(&marker_labels, MarkerKind::Target, ti, "Target", target_count);
+                            let value = typed.get(ti).copied().flatten().map(|n| n.to_string()).unwrap_or_default();
+                            let placeholder = estimates[ti].map(|n| n.to_string()).unwrap_or_else(|| "0".to_string());
+                            rsx! {
+                                li {
+                                    span { class: "logistics-target", "{name}" }
+                                    input {
+                                        r#type: "number",
+                                        min: "0",
+                                        "aria-label": "Shells for {name}",
+                                        placeholder: "{placeholder}",
+                                        value: "{value}",
+                                        oninput: move |evt: Event<FormData>| {
+                                            let mut typed = overrides.write();
+                                            if typed.len() <= ti {
+                                                typed.resize(ti + 1, None);
+                                            }
+                                            typed[ti] = evt.value().trim().parse::<u32>().ok();
+                                        },
+                                    }
+                                    span { class: "logistics-unit", "shells" }
+                                }
+                            }
+                        }
+                    }
+                }
+                if req.lines.is_empty() {
+                    p { class: "logistics-hint", "Pair guns with these targets to count their shells." }
+                } else {
+                    table { class: "logistics-table",
+                        thead {
+                            tr {
+                                th { "Shell" }
+                                th { "Shells" }
+                                th { "Crates" }
+                                th { "kg" }
+                            }
+                        }
+                        tbody {
+                            for line in req.lines.iter() {
+                                tr {
+                                    td { "{line.shell.name()}" }
+                                    td { "{line.shells}" }
+                                    td { "{line.crates}" }
+                                    td { "{line.weight_kg:.0}" }
+                                }
+                            }
+                        }
+                    }
+                    p { class: "logistics-total",
+                        "{req.crates} crates · {req.truck_loads} × truck · {req.flatbed_loads} × flatbed · {req.weight_kg:.0} kg"
+                    }
+                }
+                p { class: "logistics-hint",
+                    "Blank targets use the shells for a {confidence}% chance of a hit from their most accurate gun."
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solution(accuracy_radius: f64) -> Option<FiringSolutionData> {
+        Some(FiringSolutionData {
+            azimuth: 0.0,
+            distance: 100.0,
+            in_range: true,
+            accuracy_radius,
+            wind_adjusted_azimuth: None,
+            wind_adjusted_distance: None,
+            wind_offset_meters: None,
+            time_of_flight: None,
+            impact_zone: None,
+        })
+    }
+
+    #[test]
+    fn test_target_estimates_use_the_most_accurate_gun() {
+        let solutions = vec![solution(40.0), solution(20.0), None];
+        let pairings = vec![Some(0), Some(0), Some(1)];
+        let estimates = target_estimates(&solutions, &pairings, 3, 10.0);
+        assert_eq!(estimates, vec![Some(9), None, None]);
+    }
+
+    #[test]
+    fn test_barrage_demand_splits_shells_over_paired_guns() {
+        let ids = vec![
+            "cremari-mortar".to_string(),
+            "huber-exalt-150mm".to_string(),
+            "unknown-gun".to_string(),
+            "storm-cannon".to_string(),
+        ];
+        let pairings = vec![Some(0), Some(0), Some(0), None];
+        let demand = barrage_demand(&[7, 4], &pairings, &ids);
+        assert_eq!(demand, vec![(Shell::Mortar, 4), (Shell::Mm150, 3)]);
+    }
+}
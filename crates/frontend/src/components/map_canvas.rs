@@ -0,0 +1,668 @@
+//! Optional `<canvas>` backend for the marker layer.
+//!
+//! With the `canvas-overlay` feature the per-marker content (grid, range
+//! rings, firing lines, spreads and the gun/target/spotter/enemy markers) is
+//! drawn into a canvas through the 2D context instead of being rebuilt as SVG
+//! markup on every change; the other layers stay in the SVG above it. The
+//! scene is built as a flat list of [`Paint`] commands so it can be tested
+//! without a browser.
+//!
+//! The canvas leaves out what only the SVG draws: keypad sub-grid, enemy
+//! danger zones, wind drift, the compass rose, target clusters, the
+//! high-contrast outline and the selection ring's pulse.
+
+use foxhole_shared::grid::{self, MapScale};
+use foxhole_shared::models::{target_status, TargetStatus};
+use foxhole_shared::overlay::{
+    MarkerKind, MarkerLabels, SelectedMarker, ShellSpread, REFERENCE_WIDTH,
+};
+
+use crate::components::map_layers::{
+    CustomRings, DisplacementRoutes, GroupSelection, Heatmap, Intel, OverlayLayer, PlanComparison,
+    RenderContext, Structures,
+};
+
+/// Whether the app was built with the canvas overlay.
+pub const CANVAS_OVERLAY: bool = cfg!(feature = "canvas-overlay");
+
+/// DOM id of the overlay canvas.
+pub const CANVAS_ID: &str = "map-canvas";
+
+/// Most canvas pixels per map-image pixel; the backing store follows the zoom
+/// up to this so markers stay sharp without an oversized bitmap.
+#[cfg(feature = "canvas-overlay")]
+pub const MAX_CANVAS_RESOLUTION: f64 = 2.0;
+
+/// The SVG layers drawn over the canvas: [`MAP_LAYERS`] without the markers.
+///
+/// [`MAP_LAYERS`]: crate::components::map_layers::MAP_LAYERS
+pub const SVG_LAYERS_OVER_CANVAS: &[&dyn OverlayLayer] = &[
+    &Heatmap,
+    &Structures,
+    &PlanComparison,
+    &CustomRings,
+    &Intel,
+    &DisplacementRoutes,
+    &GroupSelection,
+];
+
+/// Fill and stroke of a shape. Colours are CSS colour strings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    pub fill: Option<&'static str>,
+    pub stroke: Option<&'static str>,
+    pub width: f64,
+    /// Dash and gap lengths, for a dashed stroke.
+    pub dash: Option<(f64, f64)>,
+    pub alpha: f64,
+}
+
+impl Style {
+    fn stroke(color: &'static str, width: f64) -> Self {
+        Style {
+            fill: None,
+            stroke: Some(color),
+            width,
+            dash: None,
+            alpha: 1.0,
+        }
+    }
+
+    fn filled(fill: &'static str, stroke: &'static str, width: f64) -> Self {
+        Style {
+            fill: Some(fill),
+            ..Style::stroke(stroke, width)
+        }
+    }
+
+    fn dashed(self, dash: f64, gap: f64) -> Self {
+        Style {
+            dash: Some((dash, gap)),
+            ..self
+        }
+    }
+
+    fn alpha(self, alpha: f64) -> Self {
+        Style { alpha, ..self }
+    }
+}
+
+/// How a piece of text is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextKind {
+    /// Bold marker label centred over its point, with a dark halo.
+    Label,
+    /// Grid column letter, centred on its point.
+    GridColumn,
+    /// Grid row number, starting at its point.
+    GridRow,
+}
+
+/// One drawing command, in map-image px.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    Circle {
+        center: (f64, f64),
+        radius: f64,
+        style: Style,
+    },
+    /// An ellipse with `radii` across and along, turned by `rotation` degrees.
+    Ellipse {
+        center: (f64, f64),
+        radii: (f64, f64),
+        rotation: f64,
+        style: Style,
+    },
+    /// A polyline, closed into a polygon when `closed`.
+    Path {
+        points: Vec<(f64, f64)>,
+        closed: bool,
+        style: Style,
+    },
+    Text {
+        pos: (f64, f64),
+        text: String,
+        size: f64,
+        color: &'static str,
+        kind: TextKind,
+    },
+}
+
+const GRID_LINE: &str = "rgba(255,255,255,0.15)";
+const GRID_LABEL: &str = "rgba(255,255,255,0.45)";
+
+fn line(from: (f64, f64), to: (f64, f64), style: Style) -> Paint {
+    Paint::Path {
+        points: vec![from, to],
+        closed: false,
+        style,
+    }
+}
+
+fn label(pos: (f64, f64), text: String, size: f64, color: &'static str) -> Paint {
+    Paint::Text {
+        pos,
+        text,
+        size,
+        color,
+        kind: TextKind::Label,
+    }
+}
+
+/// The marker's name, or "GUN 2" style when there are several.
+fn marker_label(
+    labels: &MarkerLabels,
+    kind: MarkerKind,
+    base: &str,
+    index: usize,
+    total: usize,
+) -> String {
+    match labels.get(kind, index) {
+        Some(name) => name.to_string(),
+        None if total <= 1 => base.to_string(),
+        None => format!("{base} {}", index + 1),
+    }
+}
+
+fn selection_ring(
+    scene: &mut Vec<Paint>,
+    selected: Option<SelectedMarker>,
+    kind: MarkerKind,
+    index: usize,
+    center: (f64, f64),
+    s: f64,
+) {
+    if selected == Some(SelectedMarker { kind, index }) {
+        scene.push(Paint::Circle {
+            center,
+            radius: 24.0 * s,
+            style: Style::stroke("white", 3.0 * s)
+                .dashed(6.0 * s, 4.0 * s)
+                .alpha(0.9),
+        });
+    }
+}
+
+fn grid(scene: &mut Vec<Paint>, mb: f64, map: MapScale) {
+    let grid_line = Style::stroke(GRID_LINE, mb);
+    for col in 0..=grid::GRID_COLS {
+        let x = map.grid_col_px(col);
+        scene.push(line((x, 0.0), (x, map.height_px), grid_line));
+    }
+    for row in 0..=grid::GRID_ROWS {
+        let y = map.grid_row_px(row);
+        scene.push(line((0.0, y), (map.width_px, y), grid_line));
+    }
+    let size = 18.0 * mb;
+    let col_step = map.width_px / grid::GRID_COLS as f64;
+    for col in 0..grid::GRID_COLS {
+        scene.push(Paint::Text {
+            pos: (col as f64 * col_step + col_step / 2.0, 24.0 * mb),
+            text: grid::col_letter(col).to_string(),
+            size,
+            color: GRID_LABEL,
+            kind: TextKind::GridColumn,
+        });
+    }
+    let row_step = map.height_px / grid::GRID_ROWS as f64;
+    for row in 0..grid::GRID_ROWS {
+        scene.push(Paint::Text {
+            pos: (8.0 * mb, row as f64 * row_step + row_step / 2.0 + 8.0 * mb),
+            text: (row + 1).to_string(),
+            size,
+            color: GRID_LABEL,
+            kind: TextKind::GridRow,
+        });
+    }
+}
+
+fn range_circles(scene: &mut Vec<Paint>, ctx: &RenderContext, s: f64) {
+    for (i, &gun) in ctx.guns.iter().enumerate() {
+        if ctx.range_rings.hidden.get(i).copied().unwrap_or(false) {
+            continue;
+        }
+        let Some(w) = ctx.gun_weapons.get(i).copied().flatten() else {
+            continue;
+        };
+        scene.push(Paint::Circle {
+            center: gun,
+            radius: ctx.map.meters_to_px_distance(w.max_range),
+            style: Style::filled(ctx.colors.max_range_fill, ctx.colors.gun, 3.0 * s),
+        });
+        scene.push(Paint::Circle {
+            center: gun,
+            radius: ctx.map.meters_to_px_distance(w.min_range),
+            style: Style::filled(ctx.colors.min_range_fill, ctx.colors.target, 2.0 * s)
+                .dashed(8.0 * s, 6.0 * s),
+        });
+    }
+}
+
+fn fire_missions(scene: &mut Vec<Paint>, ctx: &RenderContext, s: f64) {
+    let colors = ctx.colors;
+    for (gi, &gun) in ctx.guns.iter().enumerate() {
+        let Some(&target) = ctx
+            .gun_target_indices
+            .get(gi)
+            .copied()
+            .flatten()
+            .and_then(|ti| ctx.targets.get(ti))
+        else {
+            continue;
+        };
+        scene.push(line(
+            gun,
+            target,
+            Style::stroke(colors.firing_line_stroke, 3.0 * s).dashed(12.0 * s, 8.0 * s),
+        ));
+        let Some(spread) = ctx.spreads.get(gi).copied().flatten() else {
+            continue;
+        };
+        let style =
+            Style::filled(colors.accuracy_fill, colors.target, 2.0 * s).dashed(6.0 * s, 4.0 * s);
+        scene.push(match spread {
+            ShellSpread::Circle(radius) => Paint::Circle {
+                center: target,
+                radius,
+                style,
+            },
+            ShellSpread::Zone {
+                length,
+                width,
+                azimuth,
+            } => Paint::Ellipse {
+                center: target,
+                radii: (width / 2.0, length / 2.0),
+                rotation: azimuth,
+                style,
+            },
+        });
+        let shells = ctx.salvo_sizes.get(gi).copied().unwrap_or(1);
+        if shells > 1 {
+            let dot = Style {
+                fill: Some(colors.target),
+                stroke: None,
+                width: 0.0,
+                dash: None,
+                alpha: 0.8,
+            };
+            for center in spread.dispersion(target, shells) {
+                scene.push(Paint::Circle {
+                    center,
+                    radius: 2.5 * s,
+                    style: dot,
+                });
+            }
+        }
+    }
+}
+
+fn markers(scene: &mut Vec<Paint>, ctx: &RenderContext, s: f64) {
+    let colors = ctx.colors;
+    let fs = 16.0 * s;
+    let sw = 3.0 * s;
+    for (i, &(x, y)) in ctx.guns.iter().enumerate() {
+        let text = marker_label(ctx.labels, MarkerKind::Gun, "GUN", i, ctx.guns.len());
+        scene.push(Paint::Circle {
+            center: (x, y),
+            radius: 12.0 * s,
+            style: Style::filled(colors.gun, "white", sw),
+        });
+        scene.push(label((x, y - 20.0 * s), text, fs, "white"));
+        selection_ring(scene, ctx.selected, MarkerKind::Gun, i, (x, y), s);
+    }
+    for (i, &(x, y)) in ctx.targets.iter().enumerate() {
+        let text = marker_label(
+            ctx.labels,
+            MarkerKind::Target,
+            "TARGET",
+            i,
+            ctx.targets.len(),
+        );
+        let arm = 16.0 * s;
+        let status = target_status(ctx.target_statuses, i);
+        // Finished targets fade back, like the SVG markers
+        let alpha = if status == TargetStatus::Destroyed {
+            0.45
+        } else {
+            1.0
+        };
+        let cross = Style::stroke(colors.target, sw).alpha(alpha);
+        scene.push(line((x - arm, y), (x + arm, y), cross));
+        scene.push(line((x, y - arm), (x, y + arm), cross));
+        scene.push(Paint::Circle {
+            center: (x, y),
+            radius: 8.0 * s,
+            style: Style::filled(colors.target, "white", sw).alpha(alpha),
+        });
+        scene.push(label((x, y - 24.0 * s), text, fs, colors.target_label));
+        match status {
+            TargetStatus::Active => {}
+            TargetStatus::FiredUpon => scene.push(Paint::Circle {
+                center: (x, y),
+                radius: arm,
+                style: Style::stroke(colors.target, sw).dashed(4.0 * s, 4.0 * s),
+            }),
+            TargetStatus::Destroyed => scene.push(line(
+                (x - arm, y + arm),
+                (x + arm, y - arm),
+                Style::stroke("white", sw).alpha(alpha),
+            )),
+        }
+        selection_ring(scene, ctx.selected, MarkerKind::Target, i, (x, y), s);
+    }
+    for (i, &(x, y)) in ctx.spotters.iter().enumerate() {
+        let text = marker_label(
+            ctx.labels,
+            MarkerKind::Spotter,
+            "SPOTTER",
+            i,
+            ctx.spotters.len(),
+        );
+        scene.push(Paint::Circle {
+            center: (x, y),
+            radius: 10.0 * s,
+            style: Style::filled(colors.spotter, "white", sw),
+        });
+        scene.push(label((x, y - 20.0 * s), text, fs, colors.spotter_label));
+        selection_ring(scene, ctx.selected, MarkerKind::Spotter, i, (x, y), s);
+    }
+    for (i, &(x, y)) in ctx.enemies.iter().enumerate() {
+        let text = marker_label(ctx.labels, MarkerKind::Enemy, "ENEMY", i, ctx.enemies.len());
+        let r = 12.0 * s;
+        scene.push(Paint::Path {
+            points: vec![(x, y - r), (x + r, y), (x, y + r), (x - r, y)],
+            closed: true,
+            style: Style::filled(colors.enemy, "black", sw),
+        });
+        scene.push(label((x, y - 22.0 * s), text, fs, colors.enemy));
+        selection_ring(scene, ctx.selected, MarkerKind::Enemy, i, (x, y), s);
+    }
+}
+
+/// The marker layer as canvas drawing commands, bottom to top.
+pub fn marker_scene(ctx: &RenderContext) -> Vec<Paint> {
+    let mut scene = Vec::with_capacity(64 + 8 * (ctx.guns.len() + ctx.targets.len()));
+    let mobile_boost = (REFERENCE_WIDTH / ctx.container_width).max(1.0);
+    let s = ctx.marker_scale();
+    grid(&mut scene, mobile_boost, ctx.map);
+    range_circles(&mut scene, ctx, s);
+    fire_missions(&mut scene, ctx, s);
+    markers(&mut scene, ctx, s);
+    scene
+}
+
+/// Draw `scene` into the overlay canvas at `resolution` canvas px per
+/// map-image px, replacing what was there.
+#[cfg(feature = "canvas-overlay")]
+pub fn paint_scene(scene: &[Paint], resolution: f64, map: MapScale) -> Option<()> {
+    use std::f64::consts::TAU;
+    use wasm_bindgen::{JsCast, JsValue};
+    use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+    let canvas: HtmlCanvasElement = web_sys::window()?
+        .document()?
+        .get_element_by_id(CANVAS_ID)?
+        .dyn_into()
+        .ok()?;
+    let resolution = resolution.clamp(1.0, MAX_CANVAS_RESOLUTION);
+    let (w, h) = (
+        (map.width_px * resolution).round() as u32,
+        (map.height_px * resolution).round() as u32,
+    );
+    // Resizing clears the canvas, so only do it when the zoom calls for it
+    if canvas.width() != w || canvas.height() != h {
+        canvas.set_width(w);
+        canvas.set_height(h);
+    }
+    let c: CanvasRenderingContext2d = canvas.get_context("2d").ok()??.dyn_into().ok()?;
+    c.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).ok()?;
+    c.clear_rect(0.0, 0.0, w as f64, h as f64);
+    c.scale(resolution, resolution).ok()?;
+    c.set_line_join("round");
+
+    let finish = |style: &Style| {
+        c.set_global_alpha(style.alpha);
+        if let Some(fill) = style.fill {
+            c.set_fill_style_str(fill);
+            c.fill();
+        }
+        if let Some(stroke) = style.stroke {
+            let dash = dash_array(style.dash);
+            let _ = c.set_line_dash(&dash);
+            c.set_stroke_style_str(stroke);
+            c.set_line_width(style.width);
+            c.stroke();
+        }
+    };
+    fn dash_array(dash: Option<(f64, f64)>) -> JsValue {
+        let array = web_sys::js_sys::Array::new();
+        if let Some((d, g)) = dash {
+            array.push(&d.into());
+            array.push(&g.into());
+        }
+        array.into()
+    }
+
+    for paint in scene {
+        match paint {
+            Paint::Circle {
+                center,
+                radius,
+                style,
+            } => {
+                c.begin_path();
+                let _ = c.arc(center.0, center.1, radius.max(0.0), 0.0, TAU);
+                finish(style);
+            }
+            Paint::Ellipse {
+                center,
+                radii,
+                rotation,
+                style,
+            } => {
+                c.begin_path();
+                let _ = c.ellipse(
+                    center.0,
+                    center.1,
+                    radii.0.max(0.0),
+                    radii.1.max(0.0),
+                    rotation.to_radians(),
+                    0.0,
+                    TAU,
+                );
+                finish(style);
+            }
+            Paint::Path {
+                points,
+                closed,
+                style,
+            } => {
+                let Some((&(x0, y0), rest)) = points.split_first() else {
+                    continue;
+                };
+                c.begin_path();
+                c.move_to(x0, y0);
+                for &(x, y) in rest {
+                    c.line_to(x, y);
+                }
+                if *closed {
+                    c.close_path();
+                }
+                finish(style);
+            }
+            Paint::Text {
+                pos,
+                text,
+                size,
+                color,
+                kind,
+            } => {
+                c.set_global_alpha(1.0);
+                let _ = c.set_line_dash(&dash_array(None));
+                match kind {
+                    TextKind::Label => {
+                        c.set_font(&format!("700 {size}px sans-serif"));
+                        c.set_text_align("center");
+                        c.set_text_baseline("alphabetic");
+                        c.set_stroke_style_str("rgba(0,0,0,0.7)");
+                        c.set_line_width(size / 4.0);
+                        let _ = c.stroke_text(text, pos.0, pos.1);
+                    }
+                    TextKind::GridColumn | TextKind::GridRow => {
+                        c.set_font(&format!("600 {size}px monospace"));
+                        c.set_text_align(if *kind == TextKind::GridRow {
+                            "start"
+                        } else {
+                            "center"
+                        });
+                        c.set_text_baseline("middle");
+                    }
+                }
+                c.set_fill_style_str(color);
+                let _ = c.fill_text(text, pos.0, pos.1);
+            }
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::map_layers::{render_overlay, MAP_LAYERS};
+    use foxhole_shared::models::RangeRings;
+    use foxhole_shared::overlay::{GridWindow, Palette};
+
+    fn context<'a>(
+        guns: &'a [(f64, f64)],
+        targets: &'a [(f64, f64)],
+        pairings: &'a [Option<usize>],
+        labels: &'a MarkerLabels,
+        range_rings: &'a RangeRings,
+    ) -> RenderContext<'a> {
+        RenderContext {
+            guns,
+            targets,
+            spotters: &[],
+            enemies: &[],
+            fobs: &[],
+            bunkers: &[],
+            no_fire_zones: &[],
+            no_fire_radii: &[],
+            danger_close: &[],
+            gun_weapons: &[],
+            gun_target_indices: pairings,
+            spreads: &[],
+            salvo_sizes: &[],
+            gun_alternates: &[],
+            labels,
+            target_statuses: &[],
+            range_rings,
+            heatmap: &[],
+            heatmap_color: "red",
+            structures: &[],
+            plan_changes: &[],
+            group: &[],
+            selected: None,
+            displace_gun: None,
+            danger_weapon: None,
+            wind: None,
+            zoom: 1.0,
+            view: GridWindow::ALL,
+            container_width: REFERENCE_WIDTH,
+            always_keypad: false,
+            high_contrast: false,
+            colors: Palette::Warden.colors(),
+            map: MapScale::STANDARD,
+        }
+    }
+
+    fn labels_in(scene: &[Paint]) -> Vec<&str> {
+        scene
+            .iter()
+            .filter_map(|p| match p {
+                Paint::Text {
+                    text,
+                    kind: TextKind::Label,
+                    ..
+                } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_marker_scene_labels_markers_like_the_svg() {
+        let mut labels = MarkerLabels::default();
+        labels.set(MarkerKind::Gun, 1, "North");
+        let rings = RangeRings::default();
+        let guns = [(100.0, 100.0), (200.0, 100.0)];
+        let targets = [(150.0, 400.0)];
+        let ctx = context(&guns, &targets, &[], &labels, &rings);
+        assert_eq!(labels_in(&marker_scene(&ctx)), ["GUN 1", "North", "TARGET"]);
+    }
+
+    #[test]
+    fn test_marker_scene_draws_firing_lines_for_paired_guns() {
+        let (labels, rings) = (MarkerLabels::default(), RangeRings::default());
+        let guns = [(100.0, 100.0), (200.0, 100.0)];
+        let targets = [(150.0, 400.0)];
+        let firing_line = |scene: &[Paint]| {
+            scene
+                .iter()
+                .filter(|p| matches!(p, Paint::Path { points, .. } if points.first() == Some(&guns[0]) && points.last() == Some(&targets[0])))
+                .count()
+        };
+        let unpaired = marker_scene(&context(&guns, &targets, &[], &labels, &rings));
+        let paired = marker_scene(&context(&guns, &targets, &[Some(0)], &labels, &rings));
+        assert_eq!(firing_line(&unpaired), 0);
+        assert_eq!(firing_line(&paired), 1);
+    }
+
+    #[test]
+    fn test_svg_layers_over_canvas_leave_out_the_markers() {
+        let (labels, rings) = (MarkerLabels::default(), RangeRings::default());
+        let guns = [(100.0, 100.0)];
+        let ctx = context(&guns, &[], &[], &labels, &rings);
+        assert_eq!(SVG_LAYERS_OVER_CANVAS.len(), MAP_LAYERS.len() - 1);
+        assert!(render_overlay(MAP_LAYERS, &ctx).contains(r#"cx="100""#));
+        assert!(!render_overlay(SVG_LAYERS_OVER_CANVAS, &ctx).contains(r#"cx="100""#));
+    }
+
+    /// Both paths build a large plan's overlay within a 60 fps frame natively,
+    /// and the canvas one is the cheaper:
+    /// `cargo test -p foxhole-frontend --release -- --ignored bench`
+    #[test]
+    #[ignore]
+    fn bench_overlay_paths_with_many_markers() {
+        use std::hint::black_box;
+        use std::time::{Duration, Instant};
+        const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+        let (labels, rings) = (MarkerLabels::default(), RangeRings::default());
+        let at = |i: usize, row: f64| (40.0 + (i % 40) as f64 * 48.0, row + (i / 40) as f64 * 90.0);
+        for count in [100, 250, 500] {
+            let guns: Vec<_> = (0..count).map(|i| at(i, 200.0)).collect();
+            let targets: Vec<_> = (0..count).map(|i| at(i, 900.0)).collect();
+            let pairings: Vec<_> = (0..count).map(Some).collect();
+            let ctx = context(&guns, &targets, &pairings, &labels, &rings);
+            let runs = 200;
+            let start = Instant::now();
+            for _ in 0..runs {
+                black_box(render_overlay(MAP_LAYERS, &ctx));
+            }
+            let svg = start.elapsed() / runs;
+            let start = Instant::now();
+            for _ in 0..runs {
+                black_box(marker_scene(&ctx));
+                black_box(render_overlay(SVG_LAYERS_OVER_CANVAS, &ctx));
+            }
+            let canvas = start.elapsed() / runs;
+            assert!(svg < FRAME_BUDGET, "{count} markers: svg took {svg:?}");
+            assert!(
+                canvas < svg,
+                "{count} markers: canvas {canvas:?}, svg {svg:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! The map's SVG overlay as a stack of layers.
+//!
+//! Each [`OverlayLayer`] draws one kind of content from a shared
+//! [`RenderContext`], and [`render_overlay`] stacks them bottom to top. A new
+//! layer is a type implementing the trait plus a place in [`MAP_LAYERS`]; the
+//! input handling in `map_view` doesn't change for it.
+
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::merge::PlanChange;
+use foxhole_shared::models::{RangeRings, TargetStatus, Weapon, WindInput};
+use foxhole_shared::overlay::{
+    build_custom_rings, build_displacement_routes, build_group_selection, build_heatmap,
+    build_intel_markers, build_plan_comparison, build_structure_icons, build_svg_content,
+    marker_scale, GridWindow, HeatCell, MarkerLabels, SelectedMarker, ShellSpread, StructureIcon,
+    ThemeColors,
+};
+
+/// Everything the overlay is drawn from. Positions are in map-image px.
+pub struct RenderContext<'a> {
+    pub guns: &'a [(f64, f64)],
+    pub targets: &'a [(f64, f64)],
+    pub spotters: &'a [(f64, f64)],
+    pub enemies: &'a [(f64, f64)],
+    pub fobs: &'a [(f64, f64)],
+    pub bunkers: &'a [(f64, f64)],
+    pub no_fire_zones: &'a [(f64, f64)],
+    /// Radius (m) of each no-fire zone; zones past the end use the default.
+    pub no_fire_radii: &'a [f64],
+    /// No-fire zones some gun's shells could land in.
+    pub danger_close: &'a [usize],
+    /// Each gun's weapon with its ammo applied, if it has one.
+    pub gun_weapons: &'a [Option<&'a Weapon>],
+    pub gun_target_indices: &'a [Option<usize>],
+    pub spreads: &'a [Option<ShellSpread>],
+    pub salvo_sizes: &'a [u32],
+    pub gun_alternates: &'a [Vec<(f64, f64)>],
+    pub labels: &'a MarkerLabels,
+    pub target_statuses: &'a [TargetStatus],
+    pub range_rings: &'a RangeRings,
+    pub heatmap: &'a [HeatCell],
+    pub heatmap_color: &'a str,
+    pub structures: &'a [StructureIcon],
+    /// Markers that differ from the version being compared with.
+    pub plan_changes: &'a [PlanChange],
+    pub group: &'a [SelectedMarker],
+    pub selected: Option<SelectedMarker>,
+    /// The gun whose displacement route is being planned.
+    pub displace_gun: Option<usize>,
+    /// Enemy weapon whose danger zones are drawn around enemy guns.
+    pub danger_weapon: Option<&'a Weapon>,
+    pub wind: Option<&'a WindInput>,
+    pub zoom: f64,
+    /// Grid cells on screen; the grid and keypad are drawn only there.
+    pub view: GridWindow,
+    /// Width of the map container (px), which sizes markers on small screens.
+    pub container_width: f64,
+    pub always_keypad: bool,
+    pub high_contrast: bool,
+    pub colors: &'a ThemeColors,
+    pub map: MapScale,
+}
+
+impl RenderContext<'_> {
+    /// Marker size factor at the current zoom and container width.
+    pub fn marker_scale(&self) -> f64 {
+        marker_scale(self.zoom, self.container_width)
+    }
+}
+
+/// One kind of overlay content, drawn as SVG elements in map-image px.
+pub trait OverlayLayer {
+    fn build(&self, ctx: &RenderContext) -> String;
+}
+
+/// Community placement counts, shaded per grid cell.
+pub struct Heatmap;
+
+impl OverlayLayer for Heatmap {
+    fn build(&self, ctx: &RenderContext) -> String {
+        build_heatmap(ctx.heatmap, ctx.heatmap_color, ctx.map)
+    }
+}
+
+/// Live War API structures.
+pub struct Structures;
+
+impl OverlayLayer for Structures {
+    fn build(&self, ctx: &RenderContext) -> String {
+        build_structure_icons(ctx.structures, ctx.marker_scale())
+    }
+}
+
+/// Ghosts of the markers that moved since the compared version.
+pub struct PlanComparison;
+
+impl OverlayLayer for PlanComparison {
+    fn build(&self, ctx: &RenderContext) -> String {
+        build_plan_comparison(ctx.plan_changes, ctx.colors, ctx.marker_scale(), ctx.map)
+    }
+}
+
+/// Guns, targets, spotters and enemies with their rings, firing lines and spreads.
+pub struct Markers;
+
+impl OverlayLayer for Markers {
+    fn build(&self, ctx: &RenderContext) -> String {
+        build_svg_content(
+            ctx.guns,
+            ctx.targets,
+            ctx.spotters,
+            ctx.enemies,
+            ctx.gun_weapons,
+            &ctx.range_rings.hidden,
+            ctx.gun_target_indices,
+            ctx.spreads,
+            ctx.salvo_sizes,
+            ctx.labels,
+            ctx.target_statuses,
+            ctx.zoom,
+            ctx.always_keypad,
+            ctx.view,
+            ctx.container_width,
+            ctx.selected,
+            ctx.danger_weapon,
+            ctx.wind,
+            ctx.high_contrast,
+            ctx.colors,
+            ctx.map,
+        )
+    }
+}
+
+/// User-drawn rings around markers.
+pub struct CustomRings;
+
+impl OverlayLayer for CustomRings {
+    fn build(&self, ctx: &RenderContext) -> String {
+        build_custom_rings(
+            &ctx.range_rings.custom,
+            ctx.guns,
+            ctx.targets,
+            ctx.spotters,
+            ctx.marker_scale(),
+            ctx.map,
+        )
+    }
+}
+
+/// FOBs, observation bunkers and no-fire zones.
+pub struct Intel;
+
+impl OverlayLayer for Intel {
+    fn build(&self, ctx: &RenderContext) -> String {
+        build_intel_markers(
+            ctx.fobs,
+            ctx.bunkers,
+            ctx.no_fire_zones,
+            ctx.no_fire_radii,
+            ctx.danger_close,
+            ctx.marker_scale(),
+            ctx.selected,
+            ctx.colors,
+            ctx.map,
+        )
+    }
+}
+
+/// Each gun's route through its alternate firing positions.
+pub struct DisplacementRoutes;
+
+impl OverlayLayer for DisplacementRoutes {
+    fn build(&self, ctx: &RenderContext) -> String {
+        build_displacement_routes(
+            ctx.guns,
+            ctx.gun_alternates,
+            ctx.displace_gun,
+            ctx.colors.gun,
+            ctx.marker_scale(),
+        )
+    }
+}
+
+/// Rings around the markers in the multi-selection.
+pub struct GroupSelection;
+
+impl OverlayLayer for GroupSelection {
+    fn build(&self, ctx: &RenderContext) -> String {
+        build_group_selection(
+            ctx.group,
+            ctx.guns,
+            ctx.targets,
+            ctx.spotters,
+            ctx.enemies,
+            ctx.fobs,
+            ctx.bunkers,
+            ctx.no_fire_zones,
+            ctx.marker_scale(),
+        )
+    }
+}
+
+/// The map overlay's layers, bottom to top.
+pub const MAP_LAYERS: &[&dyn OverlayLayer] = &[
+    &Heatmap,
+    &Structures,
+    &PlanComparison,
+    &Markers,
+    &CustomRings,
+    &Intel,
+    &DisplacementRoutes,
+    &GroupSelection,
+];
+
+/// `layers` drawn bottom to top into an SVG covering the map image.
+pub fn render_overlay(layers: &[&dyn OverlayLayer], ctx: &RenderContext) -> String {
+    let content: String = layers.iter().map(|layer| layer.build(ctx)).collect();
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" preserveAspectRatio="none" style="position:absolute;top:0;left:0;width:100%;height:100%;pointer-events:none;z-index:5;">{}</svg>"#,
+        ctx.map.width_px, ctx.map.height_px, content
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::overlay::{Palette, REFERENCE_WIDTH};
+
+    fn context<'a>(
+        guns: &'a [(f64, f64)],
+        labels: &'a MarkerLabels,
+        range_rings: &'a RangeRings,
+    ) -> RenderContext<'a> {
+        RenderContext {
+            guns,
+            targets: &[],
+            spotters: &[],
+            enemies: &[],
+            fobs: &[],
+            bunkers: &[],
+            no_fire_zones: &[],
+            no_fire_radii: &[],
+            danger_close: &[],
+            gun_weapons: &[],
+            gun_target_indices: &[],
+            spreads: &[],
+            salvo_sizes: &[],
+            gun_alternates: &[],
+            labels,
+            target_statuses: &[],
+            range_rings,
+            heatmap: &[],
+            heatmap_color: "red",
+            structures: &[],
+            plan_changes: &[],
+            group: &[],
+            selected: None,
+            displace_gun: None,
+            danger_weapon: None,
+            wind: None,
+            zoom: 1.0,
+            view: GridWindow::ALL,
+            container_width: REFERENCE_WIDTH,
+            always_keypad: false,
+            high_contrast: false,
+            colors: Palette::Warden.colors(),
+            map: MapScale::STANDARD,
+        }
+    }
+
+    struct Tag(&'static str);
+
+    impl OverlayLayer for Tag {
+        fn build(&self, _ctx: &RenderContext) -> String {
+            format!("<{}/>", self.0)
+        }
+    }
+
+    #[test]
+    fn test_render_overlay_stacks_layers_in_order() {
+        let (labels, rings) = (MarkerLabels::default(), RangeRings::default());
+        let ctx = context(&[], &labels, &rings);
+        let svg = render_overlay(&[&Tag("below"), &Tag("above")], &ctx);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(&format!(
+            r#"viewBox="0 0 {} {}""#,
+            ctx.map.width_px, ctx.map.height_px
+        )));
+        assert!(svg.find("<below/>").unwrap() < svg.find("<above/>").unwrap());
+    }
+
+    #[test]
+    fn test_map_layers_draw_markers_from_the_context() {
+        let (labels, rings) = (MarkerLabels::default(), RangeRings::default());
+        let empty = render_overlay(MAP_LAYERS, &context(&[], &labels, &rings));
+        let gun = [(100.0, 100.0)];
+        let with_gun = render_overlay(MAP_LAYERS, &context(&gun, &labels, &rings));
+        assert!(with_gun.len() > empty.len());
+        assert!(Markers
+            .build(&context(&gun, &labels, &rings))
+            .contains(r#"cx="100""#));
+    }
+}
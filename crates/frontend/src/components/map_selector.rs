@@ -0,0 +1,281 @@
+use dioxus::prelude::*;
+use foxhole_shared::models::MapViewPreset;
+use wasm_bindgen::JsValue;
+
+use crate::api::{self, MapData};
+use crate::offline;
+
+const LAST_MAP_KEY: &str = "last_map";
+const PINNED_MAPS_KEY: &str = "pinned_maps";
+
+/// How many other maps' images are fetched ahead of a switch.
+const PREFETCH_LIMIT: usize = 6;
+
+/// How long a map is open before others are prefetched, so its own image and
+/// data load undisturbed.
+const PREFETCH_DELAY_MS: u32 = 5_000;
+
+fn view_key(file_name: &str) -> String {
+    format!("map_view:{}", file_name)
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window().and_then(|w| w.local_storage().ok().flatten())
+}
+
+/// The map last picked in the selector on this device.
+pub fn load_last_map() -> Option<String> {
+    local_storage().and_then(|s| s.get_item(LAST_MAP_KEY).ok().flatten())
+}
+
+fn save_last_map(file_name: &str) {
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(LAST_MAP_KEY, file_name);
+    }
+}
+
+/// Pinned map file names, kept in localStorage as a JSON array.
+fn load_pinned_maps() -> Vec<String> {
+    local_storage()
+        .and_then(|s| s.get_item(PINNED_MAPS_KEY).ok().flatten())
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn save_pinned_maps(pinned: &[String]) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(pinned)) {
+        let _ = storage.set_item(PINNED_MAPS_KEY, &json);
+    }
+}
+
+/// The view saved with "Save view as default" for this map on this device.
+pub fn load_saved_view(file_name: &str) -> Option<MapViewPreset> {
+    local_storage()
+        .and_then(|s| s.get_item(&view_key(file_name)).ok().flatten())
+        .and_then(|v| serde_json::from_str(&v).ok())
+}
+
+pub fn save_view(file_name: &str, view: &MapViewPreset) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(view)) {
+        let _ = storage.set_item(&view_key(file_name), &json);
+    }
+}
+
+/// Map to open with: the last-used one if it's still listed, else the first.
+pub fn default_map(maps: &[MapData], last_used: Option<&str>) -> Option<String> {
+    last_used
+        .filter(|last| maps.iter().any(|m| m.file_name == *last))
+        .map(str::to_string)
+        .or_else(|| maps.first().map(|m| m.file_name.clone()))
+}
+
+/// Pinned maps first, each group keeping the maps.json order.
+fn pinned_first<'a>(maps: &'a [MapData], pinned: &[String]) -> Vec<&'a MapData> {
+    let (mut ordered, rest): (Vec<&MapData>, Vec<&MapData>) =
+        maps.iter().partition(|m| pinned.contains(&m.file_name));
+    ordered.extend(rest);
+    ordered
+}
+
+/// Active maps to have ready besides `current`: pinned ones first, then the
+/// maps either side of it in the list, nearest first.
+fn prefetch_order<'a>(maps: &'a [MapData], current: &str, pinned: &[String]) -> Vec<&'a MapData> {
+    let others = |m: &&MapData| m.active && m.file_name != current;
+    let mut order: Vec<&MapData> = pinned_first(maps, pinned)
+        .into_iter()
+        .take_while(|m| pinned.contains(&m.file_name))
+        .filter(others)
+        .collect();
+    if let Some(at) = maps.iter().position(|m| m.file_name == current) {
+        for step in 1..maps.len() {
+            let after = maps.get(at + step);
+            let before = at.checked_sub(step).map(|i| &maps[i]);
+            order.extend(after.into_iter().chain(before).filter(others));
+        }
+    }
+    let mut seen = Vec::new();
+    order.retain(|m| {
+        let new = !seen.contains(&m.file_name);
+        seen.push(m.file_name.clone());
+        new
+    });
+    order.truncate(PREFETCH_LIMIT);
+    order
+}
+
+/// Resolves once the browser is idle, or straight away where it can't say.
+async fn idle() {
+    let promise = web_sys::js_sys::Promise::new(&mut |resolve, _| {
+        let waiting = web_sys::window().and_then(|w| w.request_idle_callback(&resolve).ok());
+        if waiting.is_none() {
+            let _ = resolve.call0(&JsValue::NULL);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+/// Pin `file_name`, or unpin it if it already is.
+fn toggle_pin(pinned: &mut Vec<String>, file_name: &str) {
+    if let Some(pos) = pinned.iter().position(|p| p == file_name) {
+        pinned.remove(pos);
+    } else {
+        pinned.push(file_name.to_string());
+    }
+}
+
+#[component]
+pub fn MapSelector(
+    maps: Vec<MapData>,
+    selected_map: Signal<String>,
+    /// Called with the new map's file name when the user picks one.
+    on_change: EventHandler<String>,
+    /// Remember the map's current zoom and position as where it opens.
+    on_save_view: EventHandler<()>,
+    #[props(default)] disabled: bool,
+) -> Element {
+    let mut pinned = use_signal(load_pinned_maps);
+
+    // Warm the cache (the service worker's too) with the maps a switch is
+    // likely to go to, one at a time while the browser is idle. Switching
+    // again stops the run.
+    let prefetch_maps = maps.clone();
+    use_effect(move || {
+        let current = selected_map.read().clone();
+        let origin = web_sys::window()
+            .and_then(|w| w.location().origin().ok())
+            .unwrap_or_default();
+        let urls: Vec<String> = prefetch_order(&prefetch_maps, &current, &pinned.peek())
+            .into_iter()
+            .map(|m| format!("{origin}{}", api::map_image_url(&m.file_name)))
+            .collect();
+        spawn(async move {
+            gloo_timers::future::TimeoutFuture::new(PREFETCH_DELAY_MS).await;
+            for url in urls {
+                idle().await;
+                if *selected_map.peek() != current || !offline::is_online() {
+                    return;
+                }
+                if let Ok(response) = reqwest::get(&url).await {
+                    let _ = response.bytes().await;
+                }
+            }
+        });
+    });
+
+    let current = selected_map.read().clone();
+    let is_pinned = pinned.read().contains(&current);
+    let ordered: Vec<(MapData, bool)> = pinned_first(&maps, &pinned.read())
+        .into_iter()
+        .map(|m| (m.clone(), pinned.read().contains(&m.file_name)))
+        .collect();
+
+    rsx! {
+        div { class: "panel",
+            h3 { "Map" }
+            div { class: "map-select-row",
+                select {
+                    "aria-label": "Select map",
+                    disabled: disabled,
+                    value: "{current}",
+                    onchange: move |evt: Event<FormData>| {
+                        let file_name = evt.value().to_string();
+                        save_last_map(&file_name);
+                        on_change.call(file_name);
+                    },
+                    for (m, starred) in ordered {
+                        option {
+                            value: "{m.file_name}",
+                            selected: current == m.file_name,
+                            if starred {
+                                "\u{2605} {m.display_name}"
+                            } else {
+                                "{m.display_name}"
+                            }
+                        }
+                    }
+                }
+                button {
+                    class: "secondary pin-map-btn",
+                    title: if is_pinned { "Unpin map" } else { "Pin map to the top of the list" },
+                    "aria-label": if is_pinned { "Unpin map" } else { "Pin map" },
+                    "aria-pressed": "{is_pinned}",
+                    onclick: move |_| {
+                        let file_name = selected_map.read().clone();
+                        toggle_pin(&mut pinned.write(), &file_name);
+                        save_pinned_maps(&pinned.read());
+                    },
+                    if is_pinned { "\u{2605}" } else { "\u{2606}" }
+                }
+            }
+            button {
+                class: "secondary save-view-btn",
+                title: "Open this map at the current zoom and position next time",
+                onclick: move |_| on_save_view.call(()),
+                "Save view as default"
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(file_name: &str) -> MapData {
+        MapData {
+            display_name: file_name.to_string(),
+            file_name: file_name.to_string(),
+            active: true,
+            width: 2048,
+            height: 1776,
+            default_view: None,
+        }
+    }
+
+    #[test]
+    fn test_default_map_prefers_last_used() {
+        let maps = vec![map("a"), map("b")];
+        assert_eq!(default_map(&maps, Some("b")), Some("b".to_string()));
+        assert_eq!(default_map(&maps, Some("gone")), Some("a".to_string()));
+        assert_eq!(default_map(&maps, None), Some("a".to_string()));
+        assert_eq!(default_map(&[], Some("b")), None);
+    }
+
+    #[test]
+    fn test_pinned_maps_sort_first() {
+        let maps = vec![map("a"), map("b"), map("c"), map("d")];
+        let pinned = vec!["d".to_string(), "b".to_string()];
+        let names: Vec<&str> = pinned_first(&maps, &pinned)
+            .iter()
+            .map(|m| m.file_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["b", "d", "a", "c"]);
+    }
+
+    #[test]
+    fn test_prefetch_order_pinned_then_nearest() {
+        let mut maps: Vec<MapData> = ["a", "b", "c", "d", "e", "f", "g", "h", "i"]
+            .into_iter()
+            .map(map)
+            .collect();
+        maps[3].active = false;
+        let pinned = vec!["h".to_string(), "e".to_string()];
+        let names: Vec<&str> = prefetch_order(&maps, "e", &pinned)
+            .iter()
+            .map(|m| m.file_name.as_str())
+            .collect();
+        // Pinned first, then alternating after/before, skipping the inactive
+        // map and ones already listed, up to the limit
+        assert_eq!(names, vec!["h", "f", "g", "c", "b", "i"]);
+        assert!(prefetch_order(&maps[..1], "a", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_toggle_pin() {
+        let mut pinned = vec!["a".to_string()];
+        toggle_pin(&mut pinned, "b");
+        assert_eq!(pinned, vec!["a", "b"]);
+        toggle_pin(&mut pinned, "a");
+        assert_eq!(pinned, vec!["b"]);
+    }
+}
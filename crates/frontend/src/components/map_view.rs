@@ -1,11 +1,38 @@
+use std::rc::Rc;
+
 use dioxus::html::geometry::WheelDelta;
 use dioxus::html::input_data::MouseButton;
 use dioxus::prelude::*;
-use foxhole_shared::grid;
+use foxhole_shared::overlay::{
+    build_measure_line, build_no_fire_draft, cluster_center, marker_scale, measure,
+    nearest_enemy_structure, structure_label, target_clusters, GridWindow, HeatCell, Palette,
+    ShellSpread, StructureIcon, CLUSTER_RADIUS, CLUSTER_ZOOM_THRESHOLD, REFERENCE_WIDTH,
+};
+use foxhole_shared::template::BatteryTemplate;
+use foxhole_shared::{
+    calc,
+    grid::MapScale,
+    merge::PlanChange,
+    models::{
+        self, remove_gun_registrations, remove_gun_shots, remove_target_shots, set_target_status,
+        target_status, MapViewPreset, Position, RangeRings, RegistrationPoint, ShotRecord,
+        TargetStatus, Weapon, WindInput, MAX_NO_FIRE_RADIUS, NO_FIRE_RADIUS_M,
+    },
+};
 
-use crate::api::WeaponData;
+use crate::api::{MapStructureData, WeaponData};
+use crate::components::context_menu::{ContextAction, ContextMenu, ContextMenuState};
+use crate::components::map_canvas::{
+    marker_scene, Paint, CANVAS_ID, CANVAS_OVERLAY, SVG_LAYERS_OVER_CANVAS,
+};
+use crate::components::map_layers::{render_overlay, RenderContext, MAP_LAYERS};
+use crate::components::map_selector::{load_saved_view, save_view};
 use crate::coords;
 use crate::pages::planner::{capture_snapshot, push_undo, PlanSnapshot};
+use crate::settings::Settings;
+use crate::worker::{OverlayJob, OverlayWorker};
+
+pub use foxhole_shared::overlay::{MarkerKind, MarkerLabels, SelectedMarker};
 
 const MAP_CONTAINER_ID: &str = "artillery-map-container";
 
@@ -22,54 +49,58 @@ const ZOOM_STEP: f64 = 1.1;
 /// Distance threshold (in map-image pixels, before zoom) for right-click removal.
 const REMOVE_THRESHOLD: f64 = 60.0;
 
-// --- Faction theme colors for SVG markers ---
+/// How much further a fingertip may land from a marker than a mouse pointer.
+const TOUCH_HIT_SCALE: f64 = 1.75;
+
+/// A new target placed this close (meters) to an enemy-held live structure snaps onto it.
+const STRUCTURE_SNAP_RADIUS_M: f64 = 40.0;
+
+/// Cursor movement (container px) needed before the move preview recomputes.
+const PREVIEW_MIN_STEP: f64 = 2.0;
+
+/// Offset of the move preview readout from the cursor (container px).
+const PREVIEW_CURSOR_OFFSET: f64 = 14.0;
+
+/// A no-fire zone drawn smaller than this (m) gets the default radius instead.
+const MIN_DRAWN_NO_FIRE_RADIUS: f64 = 10.0;
 
+/// A marker being dragged with the mouse.
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Faction {
-    Warden,
-    Colonial,
+struct MarkerDrag {
+    marker: SelectedMarker,
+    /// Client position of the mouse-down.
+    start: (f64, f64),
+    /// Past the drag threshold: the undo snapshot is taken and the marker follows the cursor.
+    moved: bool,
 }
 
-struct ThemeColors {
-    gun: &'static str,
-    target: &'static str,
-    spotter: &'static str,
-    target_label: &'static str,
-    spotter_label: &'static str,
-    min_range_fill: &'static str,
-    firing_line_stroke: &'static str,
-    accuracy_fill: &'static str,
+/// Whether the pointer has moved far enough from `start` to count as a drag.
+fn past_drag_threshold(start: (f64, f64), current: (f64, f64), threshold: f64) -> bool {
+    (current.0 - start.0).abs() > threshold || (current.1 - start.1).abs() > threshold
 }
 
-const MARKER_COLORS: ThemeColors = ThemeColors {
-    gun: "#5ab882",
-    target: "#c43030",
-    spotter: "#4a8fd4",
-    target_label: "#f0a0a0",
-    spotter_label: "#b3d4f0",
-    min_range_fill: "rgba(196,48,48,0.12)",
-    firing_line_stroke: "rgba(196,48,48,0.85)",
-    accuracy_fill: "rgba(196,48,48,0.25)",
-};
+// --- Faction theme ---
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum PlacementMode {
-    Gun,
-    Target,
-    Spotter,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Faction {
+    Warden,
+    Colonial,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum MarkerKind {
+pub enum PlacementMode {
     Gun,
     Target,
     Spotter,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct SelectedMarker {
-    pub kind: MarkerKind,
-    pub index: usize,
+    Enemy,
+    Fob,
+    Bunker,
+    NoFire,
+    /// Click two points to read the distance and bearing; nothing is placed.
+    Measure,
+    /// Click a gun, then the alternate positions it displaces to after firing.
+    Displace,
 }
 
 // ---------------------------------------------------------------------------
@@ -108,21 +139,59 @@ fn zoom_pan_at_cursor(
 /// Clamp pan values so the map can't be dragged off-screen.
 ///
 /// The map image is rendered at `width: 100%` of the container, so its actual
-/// rendered height is `container_w * (MAP_HEIGHT_PX / MAP_WIDTH_PX)`, which may
+/// rendered height is `container_w * (map.height_px / map.width_px)`, which may
 /// exceed the container height.  We must account for this so the user can pan
 /// down to see the full map.
-fn clamp_pan(pan_x: f64, pan_y: f64, zoom: f64, container_w: f64, container_h: f64) -> (f64, f64) {
+fn clamp_pan(
+    pan_x: f64,
+    pan_y: f64,
+    zoom: f64,
+    container_w: f64,
+    container_h: f64,
+    map: MapScale,
+) -> (f64, f64) {
     let content_w = container_w * zoom;
-    let content_h = container_w * (grid::MAP_HEIGHT_PX / grid::MAP_WIDTH_PX) * zoom;
+    let content_h = container_w * (map.height_px / map.width_px) * zoom;
     let min_pan_x = -(content_w - container_w).max(0.0);
     let min_pan_y = -(content_h - container_h).max(0.0);
     (pan_x.clamp(min_pan_x, 0.0), pan_y.clamp(min_pan_y, 0.0))
 }
 
+/// Pan offsets that put map pixel `center` in the middle of the container at `zoom`.
+fn pan_for_center(
+    center: (f64, f64),
+    zoom: f64,
+    container_w: f64,
+    container_h: f64,
+    map: MapScale,
+) -> (f64, f64) {
+    let scale = container_w / map.width_px;
+    let pan_x = container_w / 2.0 - center.0 * scale * zoom;
+    let pan_y = container_h / 2.0 - center.1 * scale * zoom;
+    clamp_pan(pan_x, pan_y, zoom, container_w, container_h, map)
+}
+
+/// The grid cells visible in a `container_w` × `container_h` container at
+/// `zoom` and the given pan.
+fn visible_grid(
+    container_w: f64,
+    container_h: f64,
+    zoom: f64,
+    pan_x: f64,
+    pan_y: f64,
+    map: MapScale,
+) -> GridWindow {
+    let corner = |x, y| coords::client_to_map_px_zoomed(x, y, container_w, zoom, pan_x, pan_y, map);
+    match (corner(0.0, 0.0), corner(container_w, container_h)) {
+        (Some(min), Some(max)) => GridWindow::around(min, max, map),
+        _ => GridWindow::ALL,
+    }
+}
+
 /// Apply `clamp_pan` using the live container dimensions.
-fn clamp_pan_to_container(pan_x: f64, pan_y: f64, zoom: f64) -> (f64, f64) {
+fn clamp_pan_to_container(pan_x: f64, pan_y: f64, zoom: f64, map: MapScale) -> (f64, f64) {
     match container_rect() {
-        Some(rect) => clamp_pan(pan_x, pan_y, zoom, rect.width(), rect.height()),
+        Some(rect) => clamp_pan(pan_x, pan_y, zoom, rect.width(), rect.height(), map),
         None => (pan_x, pan_y),
     }
 }
@@ -154,6 +223,17 @@ fn point_distance(a: (f64, f64), b: (f64, f64)) -> f64 {
     (dx * dx + dy * dy).sqrt()
 }
 
+/// How close (map-image px) a click must land to a marker to hit it at `zoom`.
+/// Touch gets a bigger allowance, since a fingertip covers more than a pointer.
+fn hit_threshold(zoom: f64, touch: bool) -> f64 {
+    let threshold = REMOVE_THRESHOLD / zoom.min(5.0);
+    if touch {
+        threshold * TOUCH_HIT_SCALE
+    } else {
+        threshold
+    }
+}
+
 /// Find the index of the nearest position within `threshold` (Euclidean distance).
 fn find_nearest(positions: &[(f64, f64)], click: (f64, f64), threshold: f64) -> Option<usize> {
     let mut best_idx = None;
@@ -170,16 +250,85 @@ fn find_nearest(positions: &[(f64, f64)], click: (f64, f64), threshold: f64) ->
     best_idx
 }
 
-/// Remove a marker by kind and index, fixing up gun-target pairings.
+/// If the click lands on a cluster badge, zoom in on it and return `true`.
+///
+/// Only meaningful with no marker selected (a click then moves the selection).
+fn expand_cluster_at(
+    client_x: f64,
+    client_y: f64,
+    targets: &[(f64, f64)],
+    zoom: &mut Signal<f64>,
+    pan_x: &mut Signal<f64>,
+    pan_y: &mut Signal<f64>,
+    map: MapScale,
+) -> bool {
+    let old_z = *zoom.read();
+    if old_z >= CLUSTER_ZOOM_THRESHOLD {
+        return false;
+    }
+    let Some(rect) = container_rect() else {
+        return false;
+    };
+    let Some(click) = coords::click_to_map_px_zoomed(
+        client_x,
+        client_y,
+        MAP_CONTAINER_ID,
+        old_z,
+        *pan_x.read(),
+        *pan_y.read(),
+        map,
+    ) else {
+        return false;
+    };
+    let clusters = target_clusters(targets, old_z, rect.width(), None);
+    let badge_r = CLUSTER_RADIUS * marker_scale(old_z, rect.width());
+    let hit = clusters
+        .iter()
+        .any(|members| dist(&cluster_center(targets, members), click) <= badge_r);
+    if !hit {
+        return false;
+    }
+    let cx = client_x - rect.left();
+    let cy = client_y - rect.top();
+    let new_z = CLUSTER_ZOOM_THRESHOLD;
+    let (new_px, new_py) = zoom_pan_at_cursor(cx, cy, old_z, new_z, *pan_x.read(), *pan_y.read());
+    let (px, py) = clamp_pan(new_px, new_py, new_z, rect.width(), rect.height(), map);
+    zoom.set(new_z);
+    pan_x.set(px);
+    pan_y.set(py);
+    true
+}
+
+/// Remove a marker by kind and index, fixing up gun-target pairings, names,
+/// the shot log, registration points, ammo choices, range rings and no-fire
+/// zone radii.
+#[allow(clippy::too_many_arguments)]
 pub fn remove_marker(
     kind: MarkerKind,
     index: usize,
     gun_positions: &mut Signal<Vec<(f64, f64)>>,
     target_positions: &mut Signal<Vec<(f64, f64)>>,
     spotter_positions: &mut Signal<Vec<(f64, f64)>>,
+    enemy_positions: &mut Signal<Vec<(f64, f64)>>,
+    fob_positions: &mut Signal<Vec<(f64, f64)>>,
+    bunker_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_radii: &mut Signal<Vec<f64>>,
     gun_weapon_ids: &mut Signal<Vec<String>>,
     gun_target_indices: &mut Signal<Vec<Option<usize>>>,
+    marker_labels: &mut Signal<MarkerLabels>,
+    gun_salvo_sizes: &mut Signal<Vec<u32>>,
+    gun_alternates: &mut Signal<Vec<Vec<(f64, f64)>>>,
+    target_statuses: &mut Signal<Vec<TargetStatus>>,
+    shot_log: &mut Signal<Vec<ShotRecord>>,
+    registrations: &mut Signal<Vec<RegistrationPoint>>,
+    gun_ammo: &mut Signal<Vec<String>>,
+    range_rings: &mut Signal<RangeRings>,
 ) {
+    marker_labels.write().remove(kind, index);
+    if let Some(anchor) = kind.ring_anchor() {
+        range_rings.write().remove_marker(anchor, index);
+    }
     match kind {
         MarkerKind::Gun => {
             gun_positions.write().remove(index);
@@ -188,9 +337,28 @@ pub fn remove_marker(
             if index < pairings.len() {
                 pairings.remove(index);
             }
+            let mut salvos = gun_salvo_sizes.write();
+            if index < salvos.len() {
+                salvos.remove(index);
+            }
+            let mut alternates = gun_alternates.write();
+            if index < alternates.len() {
+                alternates.remove(index);
+            }
+            remove_gun_shots(&mut shot_log.write(), index);
+            remove_gun_registrations(&mut registrations.write(), index);
+            let mut ammo = gun_ammo.write();
+            if index < ammo.len() {
+                ammo.remove(index);
+            }
         }
         MarkerKind::Target => {
             target_positions.write().remove(index);
+            let mut statuses = target_statuses.write();
+            if index < statuses.len() {
+                statuses.remove(index);
+            }
+            remove_target_shots(&mut shot_log.write(), index);
             let mut pairings = gun_target_indices.write();
             for entry in pairings.iter_mut() {
                 if let Some(ti) = entry {
@@ -205,408 +373,569 @@ pub fn remove_marker(
         MarkerKind::Spotter => {
             spotter_positions.write().remove(index);
         }
+        MarkerKind::Enemy => {
+            enemy_positions.write().remove(index);
+        }
+        MarkerKind::Fob => {
+            fob_positions.write().remove(index);
+        }
+        MarkerKind::Bunker => {
+            bunker_positions.write().remove(index);
+        }
+        MarkerKind::NoFire => {
+            no_fire_positions.write().remove(index);
+            let mut radii = no_fire_radii.write();
+            if index < radii.len() {
+                radii.remove(index);
+            }
+        }
     }
 }
 
-// ---------------------------------------------------------------------------
-// SVG builder
-// ---------------------------------------------------------------------------
+/// Selection after removing `(kind, index)`: cleared if it was the removed
+/// marker, shifted down if it came after it in the same list.
+pub fn selection_after_remove(
+    current: Option<SelectedMarker>,
+    kind: MarkerKind,
+    index: usize,
+) -> Option<SelectedMarker> {
+    match current {
+        Some(sm) if sm.kind == kind && sm.index == index => None,
+        Some(sm) if sm.kind == kind && sm.index > index => Some(SelectedMarker {
+            kind,
+            index: sm.index - 1,
+        }),
+        other => other,
+    }
+}
 
-/// Reference container width (desktop map panel) used to normalize marker sizes.
-const REFERENCE_WIDTH: f64 = 960.0;
+/// Add `marker` to the multi-selection, or take it out if it's already in.
+pub fn toggle_group_member(group: &mut Vec<SelectedMarker>, marker: SelectedMarker) {
+    if let Some(pos) = group.iter().position(|m| *m == marker) {
+        group.remove(pos);
+    } else {
+        group.push(marker);
+    }
+}
 
-/// Build the full SVG content as a string for reliable rendering.
-/// Positions are in native map-image pixel space (2048×1776).
+/// Markers inside the rectangle spanned by corners `a` and `b` (map-image px).
 #[allow(clippy::too_many_arguments)]
-fn build_svg_content(
+fn markers_in_rect(
+    a: (f64, f64),
+    b: (f64, f64),
     guns: &[(f64, f64)],
     targets: &[(f64, f64)],
     spotters: &[(f64, f64)],
-    gun_weapons: &[Option<&WeaponData>],
-    gun_target_indices: &[Option<usize>],
-    accuracy_radii_px: &[Option<f64>],
-    zoom: f64,
-    container_width: f64,
-    selected: Option<SelectedMarker>,
-    colors: &ThemeColors,
-) -> String {
-    let mut svg = String::with_capacity(8192);
-
-    // Scale factor: keeps markers, strokes, and labels a consistent physical
-    // size on screen regardless of container width.  On a 960 px desktop panel
-    // the boost is 1.0; on a 430 px phone it's ~2.2×.
-    let mobile_boost = (REFERENCE_WIDTH / container_width).max(1.0);
-    let s = mobile_boost / zoom.min(5.0);
-
-    build_grid_lines(&mut svg, mobile_boost);
-    build_grid_labels(&mut svg, mobile_boost);
-    if zoom >= 3.0 {
-        build_keypad_lines(&mut svg, mobile_boost);
-        build_keypad_labels(&mut svg, mobile_boost);
-    }
-    build_range_circles(&mut svg, guns, gun_weapons, s, colors);
-    build_firing_lines(&mut svg, guns, targets, gun_target_indices, s, colors);
-    build_accuracy_circles(
-        &mut svg,
-        guns,
-        targets,
-        gun_target_indices,
-        accuracy_radii_px,
-        s,
-        colors,
-    );
-    build_gun_markers(&mut svg, guns, s, selected, colors);
-    build_target_markers(&mut svg, targets, s, selected, colors);
-    build_spotter_markers(&mut svg, spotters, s, selected, colors);
-
-    svg
+    enemies: &[(f64, f64)],
+    fobs: &[(f64, f64)],
+    bunkers: &[(f64, f64)],
+    no_fire_zones: &[(f64, f64)],
+) -> Vec<SelectedMarker> {
+    let (min_x, max_x) = (a.0.min(b.0), a.0.max(b.0));
+    let (min_y, max_y) = (a.1.min(b.1), a.1.max(b.1));
+    let lists = [
+        (MarkerKind::Gun, guns),
+        (MarkerKind::Target, targets),
+        (MarkerKind::Spotter, spotters),
+        (MarkerKind::Enemy, enemies),
+        (MarkerKind::Fob, fobs),
+        (MarkerKind::Bunker, bunkers),
+        (MarkerKind::NoFire, no_fire_zones),
+    ];
+    lists
+        .into_iter()
+        .flat_map(|(kind, list)| {
+            list.iter()
+                .enumerate()
+                .filter(|(_, p)| (min_x..=max_x).contains(&p.0) && (min_y..=max_y).contains(&p.1))
+                .map(move |(index, _)| SelectedMarker { kind, index })
+        })
+        .collect()
 }
 
-fn build_grid_lines(svg: &mut String, mb: f64) {
-    let sw = 1.0 * mb;
-    for col in 0..=grid::GRID_COLS {
-        let x = grid::grid_col_px(col);
-        svg.push_str(&format!(
-            r#"<line x1="{x}" y1="0" x2="{x}" y2="{}" stroke="rgba(255,255,255,0.15)" stroke-width="{sw}"/>"#,
-            grid::MAP_HEIGHT_PX
-        ));
-    }
-    for row in 0..=grid::GRID_ROWS {
-        let y = grid::grid_row_px(row);
-        svg.push_str(&format!(
-            r#"<line x1="0" y1="{y}" x2="{}" y2="{y}" stroke="rgba(255,255,255,0.15)" stroke-width="{sw}"/>"#,
-            grid::MAP_WIDTH_PX
-        ));
+/// Shrink `delta` so that every point stays on the map after moving by it,
+/// keeping the group's shape instead of squashing it against the edge.
+fn clamp_group_delta(points: &[(f64, f64)], delta: (f64, f64), map: MapScale) -> (f64, f64) {
+    let Some(&first) = points.first() else {
+        return delta;
+    };
+    let (mut min, mut max) = (first, first);
+    for &(x, y) in points {
+        min = (min.0.min(x), min.1.min(y));
+        max = (max.0.max(x), max.1.max(y));
     }
+    (
+        delta.0.clamp(-min.0, map.width_px - max.0),
+        delta.1.clamp(-min.1, map.height_px - max.1),
+    )
 }
 
-fn build_grid_labels(svg: &mut String, mb: f64) {
-    let fs = 18.0 * mb;
-    let col_step = grid::MAP_WIDTH_PX / grid::GRID_COLS as f64;
-    let col_y = 24.0 * mb;
-    for col in 0..grid::GRID_COLS {
-        let x = col as f64 * col_step + col_step / 2.0;
-        let letter = grid::col_letter(col);
-        svg.push_str(&format!(
-            r#"<text x="{x}" y="{col_y}" fill="rgba(255,255,255,0.45)" font-size="{fs}" font-family="monospace" font-weight="600" text-anchor="middle" dominant-baseline="central">{letter}</text>"#
-        ));
-    }
-    let row_step = grid::MAP_HEIGHT_PX / grid::GRID_ROWS as f64;
-    let row_x = 8.0 * mb;
-    for row in 0..grid::GRID_ROWS {
-        let y = row as f64 * row_step + row_step / 2.0 + 8.0 * mb;
-        let num = row + 1;
-        svg.push_str(&format!(
-            r#"<text x="{row_x}" y="{y}" fill="rgba(255,255,255,0.45)" font-size="{fs}" font-family="monospace" font-weight="600" text-anchor="start" dominant-baseline="central">{num}</text>"#
-        ));
+/// Arrow-key nudge for the selection (map-image px): 10 m, or 50 m with Shift.
+pub fn nudge_delta(key: &Key, large: bool, map: MapScale) -> Option<(f64, f64)> {
+    let step_m = if large { 50.0 } else { 10.0 };
+    let (dx, dy) = map.meters_to_px(step_m, step_m);
+    match key {
+        Key::ArrowUp => Some((0.0, -dy)),
+        Key::ArrowDown => Some((0.0, dy)),
+        Key::ArrowLeft => Some((-dx, 0.0)),
+        Key::ArrowRight => Some((dx, 0.0)),
+        _ => None,
     }
 }
 
-fn build_keypad_lines(svg: &mut String, mb: f64) {
-    let cell_w = grid::MAP_WIDTH_PX / grid::GRID_COLS as f64;
-    let cell_h = grid::MAP_HEIGHT_PX / grid::GRID_ROWS as f64;
-    let third_w = cell_w / 3.0;
-    let third_h = cell_h / 3.0;
-    let sw = 0.6 * mb;
-
-    for col in 0..grid::GRID_COLS {
-        let x0 = grid::grid_col_px(col);
-        for i in 1..3 {
-            let x = x0 + third_w * i as f64;
-            svg.push_str(&format!(
-                r#"<line x1="{x}" y1="0" x2="{x}" y2="{}" stroke="rgba(255,255,255,0.08)" stroke-width="{sw}"/>"#,
-                grid::MAP_HEIGHT_PX
-            ));
+/// Move every marker in `group` by `delta` (map-image px) as one formation.
+#[allow(clippy::too_many_arguments)]
+pub fn translate_group(
+    group: &[SelectedMarker],
+    delta: (f64, f64),
+    map: MapScale,
+    gun_positions: &mut Signal<Vec<(f64, f64)>>,
+    target_positions: &mut Signal<Vec<(f64, f64)>>,
+    spotter_positions: &mut Signal<Vec<(f64, f64)>>,
+    enemy_positions: &mut Signal<Vec<(f64, f64)>>,
+    fob_positions: &mut Signal<Vec<(f64, f64)>>,
+    bunker_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_positions: &mut Signal<Vec<(f64, f64)>>,
+) {
+    let mut lists = [
+        (MarkerKind::Gun, gun_positions),
+        (MarkerKind::Target, target_positions),
+        (MarkerKind::Spotter, spotter_positions),
+        (MarkerKind::Enemy, enemy_positions),
+        (MarkerKind::Fob, fob_positions),
+        (MarkerKind::Bunker, bunker_positions),
+        (MarkerKind::NoFire, no_fire_positions),
+    ];
+    let points: Vec<(f64, f64)> = group
+        .iter()
+        .filter_map(|m| {
+            let (_, list) = lists.iter().find(|(kind, _)| *kind == m.kind)?;
+            list.read().get(m.index).copied()
+        })
+        .collect();
+    let (dx, dy) = clamp_group_delta(&points, delta, map);
+    for (kind, list) in lists.iter_mut() {
+        if !group.iter().any(|m| m.kind == *kind) {
+            continue;
         }
-    }
-    for row in 0..grid::GRID_ROWS {
-        let y0 = grid::grid_row_px(row);
-        for i in 1..3 {
-            let y = y0 + third_h * i as f64;
-            svg.push_str(&format!(
-                r#"<line x1="0" y1="{y}" x2="{}" y2="{y}" stroke="rgba(255,255,255,0.08)" stroke-width="{sw}"/>"#,
-                grid::MAP_WIDTH_PX
-            ));
+        let mut list = list.write();
+        for m in group.iter().filter(|m| m.kind == *kind) {
+            if let Some(p) = list.get_mut(m.index) {
+                *p = (p.0 + dx, p.1 + dy);
+            }
         }
     }
 }
 
-fn build_keypad_labels(svg: &mut String, mb: f64) {
-    let cell_w = grid::MAP_WIDTH_PX / grid::GRID_COLS as f64;
-    let cell_h = grid::MAP_HEIGHT_PX / grid::GRID_ROWS as f64;
-    let third_w = cell_w / 3.0;
-    let third_h = cell_h / 3.0;
-    // Gentler boost — these labels sit inside small keypad cells
-    let fs = 10.0 * mb.sqrt();
-
-    // Numpad layout: row 0 (top) = 7 8 9, row 1 (mid) = 4 5 6, row 2 (bot) = 1 2 3
-    const KEYPAD: [[u8; 3]; 3] = [[7, 8, 9], [4, 5, 6], [1, 2, 3]];
-
-    for col in 0..grid::GRID_COLS {
-        let x0 = grid::grid_col_px(col);
-        for row in 0..grid::GRID_ROWS {
-            let y0 = grid::grid_row_px(row);
-            for (kr, keypad_row) in KEYPAD.iter().enumerate() {
-                for (kc, &label) in keypad_row.iter().enumerate() {
-                    let cx = x0 + third_w * kc as f64 + third_w / 2.0;
-                    let cy = y0 + third_h * kr as f64 + third_h / 2.0;
-                    svg.push_str(&format!(
-                        r#"<text x="{cx}" y="{cy}" fill="rgba(255,255,255,0.2)" font-size="{fs}" font-family="monospace" text-anchor="middle" dominant-baseline="central">{label}</text>"#
-                    ));
-                }
-            }
-        }
+/// Remove every marker in `group`, highest index first so the rest stay valid.
+#[allow(clippy::too_many_arguments)]
+pub fn remove_group(
+    group: &[SelectedMarker],
+    gun_positions: &mut Signal<Vec<(f64, f64)>>,
+    target_positions: &mut Signal<Vec<(f64, f64)>>,
+    spotter_positions: &mut Signal<Vec<(f64, f64)>>,
+    enemy_positions: &mut Signal<Vec<(f64, f64)>>,
+    fob_positions: &mut Signal<Vec<(f64, f64)>>,
+    bunker_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_radii: &mut Signal<Vec<f64>>,
+    gun_weapon_ids: &mut Signal<Vec<String>>,
+    gun_target_indices: &mut Signal<Vec<Option<usize>>>,
+    marker_labels: &mut Signal<MarkerLabels>,
+    gun_salvo_sizes: &mut Signal<Vec<u32>>,
+    gun_alternates: &mut Signal<Vec<Vec<(f64, f64)>>>,
+    target_statuses: &mut Signal<Vec<TargetStatus>>,
+    shot_log: &mut Signal<Vec<ShotRecord>>,
+    registrations: &mut Signal<Vec<RegistrationPoint>>,
+    gun_ammo: &mut Signal<Vec<String>>,
+    range_rings: &mut Signal<RangeRings>,
+) {
+    let mut ordered = group.to_vec();
+    ordered.sort_by_key(|m| std::cmp::Reverse(m.index));
+    for m in ordered {
+        remove_marker(
+            m.kind,
+            m.index,
+            gun_positions,
+            target_positions,
+            spotter_positions,
+            enemy_positions,
+            fob_positions,
+            bunker_positions,
+            no_fire_positions,
+            no_fire_radii,
+            gun_weapon_ids,
+            gun_target_indices,
+            marker_labels,
+            gun_salvo_sizes,
+            gun_alternates,
+            target_statuses,
+            shot_log,
+            registrations,
+            gun_ammo,
+            range_rings,
+        );
     }
 }
 
-fn build_range_circles(
-    svg: &mut String,
-    guns: &[(f64, f64)],
-    gun_weapons: &[Option<&WeaponData>],
-    s: f64,
-    colors: &ThemeColors,
-) {
-    for (i, &(gx, gy)) in guns.iter().enumerate() {
-        let Some(w) = gun_weapons.get(i).and_then(|o| *o) else {
-            continue;
-        };
-        let max_r = coords::meters_to_image_px(w.max_range);
-        let sw1 = 3.0 * s;
-        let gun_color = colors.gun;
-        svg.push_str(&format!(
-            r##"<circle cx="{gx}" cy="{gy}" r="{max_r}" fill="rgba(90,184,130,0.06)" stroke="{gun_color}" stroke-width="{sw1}" stroke-opacity="0.6"/>"##
-        ));
-        let min_r = coords::meters_to_image_px(w.min_range);
-        let sw2 = 2.0 * s;
-        let da1 = 8.0 * s;
-        let da2 = 6.0 * s;
-        let min_fill = colors.min_range_fill;
-        let target_color = colors.target;
-        svg.push_str(&format!(
-            r##"<circle cx="{gx}" cy="{gy}" r="{min_r}" fill="{min_fill}" stroke="{target_color}" stroke-width="{sw2}" stroke-dasharray="{da1} {da2}" stroke-opacity="0.5"/>"##
-        ));
+/// Add a measure point: the second click finishes the line, the next one starts a new one.
+fn add_measure_point(points: &mut Vec<(f64, f64)>, p: (f64, f64)) {
+    if points.len() != 1 {
+        points.clear();
     }
+    points.push(p);
 }
 
-fn build_firing_lines(
-    svg: &mut String,
-    guns: &[(f64, f64)],
-    targets: &[(f64, f64)],
-    gun_target_indices: &[Option<usize>],
-    s: f64,
-    colors: &ThemeColors,
+/// Add a no-fire zone at `center` reaching out to the clicked `edge` (map px).
+/// Clicking the centre again keeps the default radius; wider zones are capped.
+fn add_no_fire_zone(
+    positions: &mut Vec<(f64, f64)>,
+    radii: &mut Vec<f64>,
+    center: (f64, f64),
+    edge: (f64, f64),
+    map: MapScale,
 ) {
-    for (gun_idx, &(gx, gy)) in guns.iter().enumerate() {
-        let target_idx = gun_target_indices.get(gun_idx).and_then(|o| *o);
-        if let Some(ti) = target_idx {
-            if let Some(&(tx, ty)) = targets.get(ti) {
-                let sw = 3.0 * s;
-                let da1 = 12.0 * s;
-                let da2 = 8.0 * s;
-                let stroke = colors.firing_line_stroke;
-                svg.push_str(&format!(
-                    r#"<line x1="{gx}" y1="{gy}" x2="{tx}" y2="{ty}" stroke="{stroke}" stroke-width="{sw}" stroke-dasharray="{da1} {da2}"/>"#
-                ));
-            }
-        }
-    }
+    let (drawn, _) = measure(center, edge, map);
+    let radius = if drawn < MIN_DRAWN_NO_FIRE_RADIUS {
+        NO_FIRE_RADIUS_M
+    } else {
+        drawn.min(MAX_NO_FIRE_RADIUS)
+    };
+    radii.resize(positions.len(), NO_FIRE_RADIUS_M);
+    radii.push(radius);
+    positions.push(center);
 }
 
-fn build_accuracy_circles(
-    svg: &mut String,
+/// What a click does in Displace mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DisplaceClick {
+    /// Start planning this gun's route.
+    PickGun(usize),
+    /// Add the next alternate position for this gun.
+    AddStop(usize),
+    Nothing,
+}
+
+/// A click on a gun picks it; anywhere else adds a stop to the picked gun's route.
+fn displace_click(
+    click: (f64, f64),
+    threshold: f64,
     guns: &[(f64, f64)],
-    targets: &[(f64, f64)],
-    gun_target_indices: &[Option<usize>],
-    accuracy_radii_px: &[Option<f64>],
-    s: f64,
-    colors: &ThemeColors,
-) {
-    // Draw accuracy circle at the target for each paired gun that has a solution
-    for (gun_idx, _) in guns.iter().enumerate() {
-        let target_idx = gun_target_indices.get(gun_idx).and_then(|o| *o);
-        let acc_r = accuracy_radii_px.get(gun_idx).and_then(|o| *o);
-        if let (Some(ti), Some(acc_r)) = (target_idx, acc_r) {
-            if let Some(&(tx, ty)) = targets.get(ti) {
-                let sw = 2.0 * s;
-                let da1 = 6.0 * s;
-                let da2 = 4.0 * s;
-                let fill = colors.accuracy_fill;
-                let target_color = colors.target;
-                svg.push_str(&format!(
-                    r##"<circle cx="{tx}" cy="{ty}" r="{acc_r}" fill="{fill}" stroke="{target_color}" stroke-width="{sw}" stroke-dasharray="{da1} {da2}"/>"##
-                ));
-            }
-        }
+    active: Option<usize>,
+) -> DisplaceClick {
+    match find_nearest(guns, click, threshold) {
+        Some(gi) => DisplaceClick::PickGun(gi),
+        None => match active.filter(|&gi| gi < guns.len()) {
+            Some(gi) => DisplaceClick::AddStop(gi),
+            None => DisplaceClick::Nothing,
+        },
     }
 }
 
-/// Generate marker label: no number suffix for single markers, numbered for multiple.
-fn marker_label(base: &str, index: usize, total: usize) -> String {
-    if total <= 1 {
-        base.to_string()
-    } else {
-        format!("{} {}", base, index + 1)
+/// Append `stop` to gun `gi`'s route, growing the per-gun list as needed.
+fn add_displacement_stop(alternates: &mut Vec<Vec<(f64, f64)>>, gi: usize, stop: (f64, f64)) {
+    if alternates.len() <= gi {
+        alternates.resize(gi + 1, Vec::new());
     }
+    alternates[gi].push(stop);
 }
 
-fn build_gun_markers(
-    svg: &mut String,
+/// Find the marker nearest to `click`, preferring the active placement mode's list.
+#[allow(clippy::too_many_arguments)]
+fn find_marker_at(
+    click: (f64, f64),
+    threshold: f64,
+    mode: PlacementMode,
     guns: &[(f64, f64)],
-    s: f64,
-    selected: Option<SelectedMarker>,
-    colors: &ThemeColors,
-) {
-    let total = guns.len();
-    for (i, &(gx, gy)) in guns.iter().enumerate() {
-        let r = 12.0 * s;
-        let sw = 3.0 * s;
-        let fs = 16.0 * s;
-        let ty = gy - 20.0 * s;
-        let tsw = 4.0 * s;
-        let label = marker_label("GUN", i, total);
-        let gun_color = colors.gun;
-        svg.push_str(&format!(r##"<g role="img"><title>{label}</title>"##));
-        svg.push_str(&format!(
-            r##"<circle cx="{gx}" cy="{gy}" r="{r}" fill="{gun_color}" stroke="white" stroke-width="{sw}"/>"##
-        ));
-        svg.push_str(&format!(
-            r##"<text x="{gx}" y="{ty}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text>"##
-        ));
-        if selected
-            == Some(SelectedMarker {
-                kind: MarkerKind::Gun,
-                index: i,
-            })
-        {
-            build_selection_ring(svg, gx, gy, s);
+    targets: &[(f64, f64)],
+    spotters: &[(f64, f64)],
+    enemies: &[(f64, f64)],
+    fobs: &[(f64, f64)],
+    bunkers: &[(f64, f64)],
+    no_fire_zones: &[(f64, f64)],
+) -> Option<(MarkerKind, usize)> {
+    // Check active placement mode's list first for priority
+    let mode_hit = match mode {
+        PlacementMode::Gun => {
+            find_nearest(guns, click, threshold).map(|idx| (MarkerKind::Gun, idx))
+        }
+        PlacementMode::Target => {
+            find_nearest(targets, click, threshold).map(|idx| (MarkerKind::Target, idx))
+        }
+        PlacementMode::Spotter => {
+            find_nearest(spotters, click, threshold).map(|idx| (MarkerKind::Spotter, idx))
+        }
+        PlacementMode::Enemy => {
+            find_nearest(enemies, click, threshold).map(|idx| (MarkerKind::Enemy, idx))
+        }
+        PlacementMode::Fob => {
+            find_nearest(fobs, click, threshold).map(|idx| (MarkerKind::Fob, idx))
         }
-        svg.push_str("</g>");
+        PlacementMode::Bunker => {
+            find_nearest(bunkers, click, threshold).map(|idx| (MarkerKind::Bunker, idx))
+        }
+        PlacementMode::NoFire => {
+            find_nearest(no_fire_zones, click, threshold).map(|idx| (MarkerKind::NoFire, idx))
+        }
+        PlacementMode::Displace => {
+            find_nearest(guns, click, threshold).map(|idx| (MarkerKind::Gun, idx))
+        }
+        PlacementMode::Measure => None,
+    };
+
+    // If nothing found in the active mode's list, check all lists
+    mode_hit.or_else(|| {
+        [
+            (MarkerKind::Gun, guns),
+            (MarkerKind::Target, targets),
+            (MarkerKind::Spotter, spotters),
+            (MarkerKind::Enemy, enemies),
+            (MarkerKind::Fob, fobs),
+            (MarkerKind::Bunker, bunkers),
+            (MarkerKind::NoFire, no_fire_zones),
+        ]
+        .into_iter()
+        .filter_map(|(kind, list)| {
+            find_nearest(list, click, threshold).map(|idx| (idx, dist(&list[idx], click), kind))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(idx, _, kind)| (kind, idx))
+    })
+}
+
+/// Offset (map-image px) applied to duplicated markers so they don't stack exactly.
+const DUPLICATE_OFFSET: f64 = 20.0;
+
+/// Append a copy of a marker next to the original and return its index.
+/// Duplicated guns keep the original's weapon and target pairing.
+#[allow(clippy::too_many_arguments)]
+fn duplicate_marker(
+    kind: MarkerKind,
+    index: usize,
+    gun_positions: &mut Signal<Vec<(f64, f64)>>,
+    target_positions: &mut Signal<Vec<(f64, f64)>>,
+    spotter_positions: &mut Signal<Vec<(f64, f64)>>,
+    enemy_positions: &mut Signal<Vec<(f64, f64)>>,
+    fob_positions: &mut Signal<Vec<(f64, f64)>>,
+    bunker_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_positions: &mut Signal<Vec<(f64, f64)>>,
+    gun_weapon_ids: &mut Signal<Vec<String>>,
+    gun_target_indices: &mut Signal<Vec<Option<usize>>>,
+    map: MapScale,
+) -> Option<usize> {
+    let list = match kind {
+        MarkerKind::Gun => &mut *gun_positions,
+        MarkerKind::Target => &mut *target_positions,
+        MarkerKind::Spotter => &mut *spotter_positions,
+        MarkerKind::Enemy => &mut *enemy_positions,
+        MarkerKind::Fob => &mut *fob_positions,
+        MarkerKind::Bunker => &mut *bunker_positions,
+        MarkerKind::NoFire => &mut *no_fire_positions,
+    };
+    let (x, y) = *list.read().get(index)?;
+    let copy = (
+        (x + DUPLICATE_OFFSET).min(map.width_px),
+        (y + DUPLICATE_OFFSET).min(map.height_px),
+    );
+    list.write().push(copy);
+    let new_index = list.read().len() - 1;
+    if kind == MarkerKind::Gun {
+        let slug = gun_weapon_ids
+            .read()
+            .get(index)
+            .cloned()
+            .unwrap_or_default();
+        gun_weapon_ids.write().push(slug);
+        let pairing = gun_target_indices.read().get(index).copied().flatten();
+        gun_target_indices.write().push(pairing);
     }
+    Some(new_index)
 }
 
-fn build_target_markers(
-    svg: &mut String,
+/// One line of the live move preview: the solution a gun would get if the
+/// selected marker were dropped at the cursor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreviewReading {
+    /// 0-based gun index.
+    pub gun: usize,
+    pub azimuth: f64,
+    pub distance: f64,
+    /// `None` when the gun has no weapon to check range against.
+    pub in_range: Option<bool>,
+}
+
+/// Locally computed azimuth/distance for moving `marker` to `cursor` (map-image px),
+/// without a server round-trip. A target previews every gun paired with it;
+/// a gun previews its own paired target.
+pub fn move_preview(
+    marker: SelectedMarker,
+    cursor: (f64, f64),
+    guns: &[(f64, f64)],
     targets: &[(f64, f64)],
-    s: f64,
-    selected: Option<SelectedMarker>,
-    colors: &ThemeColors,
-) {
-    let total = targets.len();
-    for (i, &(tx, ty)) in targets.iter().enumerate() {
-        let arm = 16.0 * s;
-        let sw = 3.0 * s;
-        let r = 8.0 * s;
-        let fs = 16.0 * s;
-        let label_y = ty - 24.0 * s;
-        let tsw = 4.0 * s;
-        let label = marker_label("TARGET", i, total);
-        let target_color = colors.target;
-        let target_label = colors.target_label;
-        svg.push_str(&format!(r##"<g role="img"><title>{label}</title>"##));
-        svg.push_str(&format!(
-            r##"<line x1="{}" y1="{ty}" x2="{}" y2="{ty}" stroke="{target_color}" stroke-width="{sw}"/>"##,
-            tx - arm,
-            tx + arm
-        ));
-        svg.push_str(&format!(
-            r##"<line x1="{tx}" y1="{}" x2="{tx}" y2="{}" stroke="{target_color}" stroke-width="{sw}"/>"##,
-            ty - arm,
-            ty + arm
-        ));
-        svg.push_str(&format!(
-            r##"<circle cx="{tx}" cy="{ty}" r="{r}" fill="{target_color}" stroke="white" stroke-width="{sw}"/>"##
-        ));
-        svg.push_str(&format!(
-            r##"<text x="{tx}" y="{label_y}" fill="{target_label}" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text>"##
-        ));
-        if selected
-            == Some(SelectedMarker {
-                kind: MarkerKind::Target,
-                index: i,
-            })
-        {
-            build_selection_ring(svg, tx, ty, s);
-        }
-        svg.push_str("</g>");
-    }
+    pairings: &[Option<usize>],
+    gun_weapons: &[Option<&WeaponData>],
+    map: MapScale,
+) -> Vec<PreviewReading> {
+    let to_pos = |(x, y): (f64, f64)| {
+        let (mx, my) = coords::map_px_to_meters(x, y, map);
+        Position { x: mx, y: my }
+    };
+    // (gun index, gun position, target position) in meters
+    let pairs: Vec<(usize, Position, Position)> = match marker.kind {
+        MarkerKind::Target => pairings
+            .iter()
+            .enumerate()
+            .filter(|(_, p)| **p == Some(marker.index))
+            .filter_map(|(gi, _)| guns.get(gi).map(|&g| (gi, to_pos(g), to_pos(cursor))))
+            .collect(),
+        MarkerKind::Gun => pairings
+            .get(marker.index)
+            .copied()
+            .flatten()
+            .and_then(|ti| targets.get(ti))
+            .map(|&t| vec![(marker.index, to_pos(cursor), to_pos(t))])
+            .unwrap_or_default(),
+        MarkerKind::Spotter
+        | MarkerKind::Enemy
+        | MarkerKind::Fob
+        | MarkerKind::Bunker
+        | MarkerKind::NoFire => Vec::new(),
+    };
+    pairs
+        .into_iter()
+        .map(|(gi, gun, target)| {
+            let distance = calc::distance(gun, target);
+            let in_range = gun_weapons
+                .get(gi)
+                .copied()
+                .flatten()
+                .map(|w| distance >= w.min_range && distance <= w.max_range);
+            PreviewReading {
+                gun: gi,
+                azimuth: calc::azimuth(gun, target),
+                distance,
+                in_range,
+            }
+        })
+        .collect()
 }
 
-fn build_spotter_markers(
-    svg: &mut String,
-    spotters: &[(f64, f64)],
-    s: f64,
-    selected: Option<SelectedMarker>,
-    colors: &ThemeColors,
-) {
-    let total = spotters.len();
-    for (i, &(sx, sy)) in spotters.iter().enumerate() {
-        let r = 10.0 * s;
-        let sw = 3.0 * s;
-        let fs = 16.0 * s;
-        let label_y = sy - 20.0 * s;
-        let tsw = 4.0 * s;
-        let label = marker_label("SPOTTER", i, total);
-        let spotter_color = colors.spotter;
-        let spotter_label = colors.spotter_label;
-        svg.push_str(&format!(r##"<g role="img"><title>{label}</title>"##));
-        svg.push_str(&format!(
-            r##"<circle cx="{sx}" cy="{sy}" r="{r}" fill="{spotter_color}" stroke="white" stroke-width="{sw}"/>"##
-        ));
-        svg.push_str(&format!(
-            r##"<text x="{sx}" y="{label_y}" fill="{spotter_label}" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text>"##
-        ));
-        if selected
-            == Some(SelectedMarker {
-                kind: MarkerKind::Spotter,
-                index: i,
-            })
-        {
-            build_selection_ring(svg, sx, sy, s);
-        }
-        svg.push_str("</g>");
+/// A live structure from the API as the overlay draws it.
+fn structure_icon(structure: &MapStructureData, map: MapScale) -> StructureIcon {
+    let team = match structure.team.as_deref() {
+        Some("COLONIAL") => Some(models::Faction::Colonial),
+        Some("WARDEN") => Some(models::Faction::Warden),
+        _ => None,
+    };
+    StructureIcon {
+        kind: structure.kind,
+        team,
+        pos: coords::meters_to_map_px(structure.position.x, structure.position.y, map),
+        location: structure.location.clone(),
     }
 }
 
-/// Emit an animated dashed selection ring around a marker.
-fn build_selection_ring(svg: &mut String, cx: f64, cy: f64, s: f64) {
-    let r = 24.0 * s;
-    let sw = 3.0 * s;
-    let da1 = 6.0 * s;
-    let da2 = 4.0 * s;
-    svg.push_str(&format!(
-        r##"<circle cx="{cx}" cy="{cy}" r="{r}" fill="none" stroke="white" stroke-width="{sw}" stroke-dasharray="{da1} {da2}" opacity="0.9"><animate attributeName="opacity" values="0.5;1;0.5" dur="1.2s" repeatCount="indefinite"/></circle>"##
-    ));
+/// The game side for a faction theme, used to tell enemy structures apart.
+fn own_side(faction: Faction) -> models::Faction {
+    match faction {
+        Faction::Warden => models::Faction::Warden,
+        Faction::Colonial => models::Faction::Colonial,
+    }
 }
 
 // ---------------------------------------------------------------------------
 // Shared marker-placement logic (used by both mouse and touch handlers)
 // ---------------------------------------------------------------------------
 
+/// Weapons and positions (`map` px) of `template`'s guns centred on `center`,
+/// kept on the map.
+pub fn stamp_positions(
+    template: &BatteryTemplate,
+    center: (f64, f64),
+    map: MapScale,
+) -> Vec<(String, (f64, f64))> {
+    let (x, y) = coords::map_px_to_meters(center.0, center.1, map);
+    template
+        .stamp(Position { x, y })
+        .into_iter()
+        .map(|(weapon_id, p)| {
+            let (px, py) = coords::meters_to_map_px(p.x, p.y, map);
+            (
+                weapon_id,
+                (px.clamp(0.0, map.width_px), py.clamp(0.0, map.height_px)),
+            )
+        })
+        .collect()
+}
+
 /// Find the index of the first target not paired with any gun.
-fn find_first_unpaired_target(pairings: &[Option<usize>], target_count: usize) -> Option<usize> {
+pub fn find_first_unpaired_target(
+    pairings: &[Option<usize>],
+    target_count: usize,
+) -> Option<usize> {
     (0..target_count).find(|ti| !pairings.contains(&Some(*ti)))
 }
 
 /// Pair the first unpaired gun (None entry) with the given target index.
-fn pair_first_unpaired_gun(pairings: &mut [Option<usize>], target_idx: usize) {
+pub fn pair_first_unpaired_gun(pairings: &mut [Option<usize>], target_idx: usize) {
     if let Some(entry) = pairings.iter_mut().find(|p| p.is_none()) {
         *entry = Some(target_idx);
     }
 }
 
+/// Move a marker to a new map-image position.
+#[allow(clippy::too_many_arguments)]
+pub fn set_marker_position(
+    marker: SelectedMarker,
+    pos: (f64, f64),
+    gun_positions: &mut Signal<Vec<(f64, f64)>>,
+    target_positions: &mut Signal<Vec<(f64, f64)>>,
+    spotter_positions: &mut Signal<Vec<(f64, f64)>>,
+    enemy_positions: &mut Signal<Vec<(f64, f64)>>,
+    fob_positions: &mut Signal<Vec<(f64, f64)>>,
+    bunker_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_positions: &mut Signal<Vec<(f64, f64)>>,
+) {
+    let list = match marker.kind {
+        MarkerKind::Gun => gun_positions,
+        MarkerKind::Target => target_positions,
+        MarkerKind::Spotter => spotter_positions,
+        MarkerKind::Enemy => enemy_positions,
+        MarkerKind::Fob => fob_positions,
+        MarkerKind::Bunker => bunker_positions,
+        MarkerKind::NoFire => no_fire_positions,
+    };
+    if let Some(p) = list.write().get_mut(marker.index) {
+        *p = pos;
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn handle_marker_placement(
     img_x: f64,
     img_y: f64,
-    zoom: f64,
+    threshold: f64,
     selected_marker: &mut Signal<Option<SelectedMarker>>,
     placement_mode: &mut Signal<PlacementMode>,
     gun_positions: &mut Signal<Vec<(f64, f64)>>,
     target_positions: &mut Signal<Vec<(f64, f64)>>,
     spotter_positions: &mut Signal<Vec<(f64, f64)>>,
+    enemy_positions: &mut Signal<Vec<(f64, f64)>>,
+    fob_positions: &mut Signal<Vec<(f64, f64)>>,
+    bunker_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_radii: &mut Signal<Vec<f64>>,
+    no_fire_center: &mut Signal<Option<(f64, f64)>>,
     gun_weapon_ids: &mut Signal<Vec<String>>,
     gun_target_indices: &mut Signal<Vec<Option<usize>>>,
     selected_weapon_slug: &Signal<String>,
+    marker_labels: &mut Signal<MarkerLabels>,
+    armed_template: &mut Signal<Option<BatteryTemplate>>,
+    structures: &[StructureIcon],
+    own: models::Faction,
+    map: MapScale,
+    map_id: &str,
+    auto_cycle: bool,
     push_snapshot: &mut dyn FnMut(),
 ) {
     // Move-mode: if a marker is selected, move it instead of placing.
@@ -615,40 +944,69 @@ fn handle_marker_placement(
     let cur_sel = *selected_marker.read();
     if let Some(sm) = cur_sel {
         push_snapshot();
-        match sm.kind {
-            MarkerKind::Gun => {
-                let targets_snap = target_positions.read().clone();
-                let threshold = REMOVE_THRESHOLD / zoom.min(5.0);
-                if let Some(ti) = find_nearest(&targets_snap, (img_x, img_y), threshold) {
-                    // Click was near a target — pair the gun with it
-                    if let Some(entry) = gun_target_indices.write().get_mut(sm.index) {
-                        *entry = Some(ti);
-                    }
-                } else {
-                    // Click on empty space — move the gun
-                    if let Some(pos) = gun_positions.write().get_mut(sm.index) {
-                        *pos = (img_x, img_y);
-                    }
-                }
-            }
-            MarkerKind::Target => {
-                if let Some(pos) = target_positions.write().get_mut(sm.index) {
-                    *pos = (img_x, img_y);
-                }
-            }
-            MarkerKind::Spotter => {
-                if let Some(pos) = spotter_positions.write().get_mut(sm.index) {
-                    *pos = (img_x, img_y);
+        let targets_snap = target_positions.read().clone();
+        let near_target = find_nearest(&targets_snap, (img_x, img_y), threshold);
+        match near_target {
+            // Gun selected and click was near a target — pair the gun with it
+            Some(ti) if sm.kind == MarkerKind::Gun => {
+                if let Some(entry) = gun_target_indices.write().get_mut(sm.index) {
+                    *entry = Some(ti);
                 }
             }
+            _ => set_marker_position(
+                sm,
+                (img_x, img_y),
+                gun_positions,
+                target_positions,
+                spotter_positions,
+                enemy_positions,
+                fob_positions,
+                bunker_positions,
+                no_fire_positions,
+            ),
         }
         selected_marker.set(None);
         return;
     }
 
-    // Normal placement mode
-    push_snapshot();
+    // An armed battery template lands its guns around the click, all
+    // paired with the first target no gun is on yet
+    let template = armed_template.write().take();
+    if let Some(template) = template {
+        push_snapshot();
+        let pair =
+            find_first_unpaired_target(&gun_target_indices.read(), target_positions.read().len());
+        for (weapon_id, pos) in stamp_positions(&template, (img_x, img_y), map) {
+            crate::api::track_gun_placement_fire(&weapon_id, Some(map_id));
+            gun_positions.write().push(pos);
+            gun_weapon_ids.write().push(weapon_id);
+            gun_target_indices.write().push(pair);
+        }
+        return;
+    }
+
+    // Normal placement mode; measuring and displacement clicks are handled by the caller
     let mode = *placement_mode.read();
+    if matches!(mode, PlacementMode::Measure | PlacementMode::Displace) {
+        return;
+    }
+    // A no-fire zone takes two clicks: its centre, then its edge
+    if mode == PlacementMode::NoFire {
+        let Some(center) = no_fire_center.take() else {
+            no_fire_center.set(Some((img_x, img_y)));
+            return;
+        };
+        push_snapshot();
+        add_no_fire_zone(
+            &mut no_fire_positions.write(),
+            &mut no_fire_radii.write(),
+            center,
+            (img_x, img_y),
+            map,
+        );
+        return;
+    }
+    push_snapshot();
     match mode {
         PlacementMode::Gun => {
             gun_positions.write().push((img_x, img_y));
@@ -658,33 +1016,64 @@ fn handle_marker_placement(
             let unpaired = find_first_unpaired_target(&gun_target_indices.read(), target_positions.read().len());
             gun_target_indices.write().push(unpaired);
             // Fire-and-forget tracking
-            crate::api::track_gun_placement_fire(&slug);
+            crate::api::track_gun_placement_fire(&slug, Some(map_id));
         }
         PlacementMode::Target => {
             let targets_snap = target_positions.read().clone();
-            let threshold = REMOVE_THRESHOLD / zoom.min(5.0);
             if let Some(ti) = find_nearest(&targets_snap, (img_x, img_y), threshold) {
                 // Clicked near an existing target — pair the first unpaired gun with it
                 pair_first_unpaired_gun(&mut gun_target_indices.write(), ti);
-                crate::api::track_target_placement_fire();
+                crate::api::track_target_placement_fire(map_id);
             } else {
-                // Place a new target and auto-pair with first unpaired gun
-                target_positions.write().push((img_x, img_y));
+                // Place a new target and auto-pair with first unpaired gun.
+                // Near an enemy structure it lands on the structure, named after it.
+                let radius = coords::meters_to_image_px(STRUCTURE_SNAP_RADIUS_M, map);
+                let snap = nearest_enemy_structure(structures, (img_x, img_y), radius, own)
+                    .map(|si| &structures[si]);
+                target_positions
+                    .write()
+                    .push(snap.map_or((img_x, img_y), |s| s.pos));
                 let new_target_idx = target_positions.read().len() - 1;
+                if let Some(structure) = snap {
+                    marker_labels.write().set(
+                        MarkerKind::Target,
+                        new_target_idx,
+                        &structure_label(structure),
+                    );
+                }
                 pair_first_unpaired_gun(&mut gun_target_indices.write(), new_target_idx);
-                crate::api::track_target_placement_fire();
+                crate::api::track_target_placement_fire(map_id);
             }
         }
         PlacementMode::Spotter => {
             spotter_positions.write().push((img_x, img_y));
-            crate::api::track_spotter_placement_fire();
+            crate::api::track_spotter_placement_fire(map_id);
+        }
+        PlacementMode::Enemy => {
+            enemy_positions.write().push((img_x, img_y));
+        }
+        PlacementMode::Fob => {
+            fob_positions.write().push((img_x, img_y));
+        }
+        PlacementMode::Bunker => {
+            bunker_positions.write().push((img_x, img_y));
         }
+        PlacementMode::NoFire | PlacementMode::Measure | PlacementMode::Displace => {}
     }
     // Auto-cycle: Gun → Target → Gun for easy pairing
+    if !auto_cycle {
+        return;
+    }
     match mode {
         PlacementMode::Gun => placement_mode.set(PlacementMode::Target),
         PlacementMode::Target => placement_mode.set(PlacementMode::Gun),
-        PlacementMode::Spotter => {} // stay in spotter mode
+        PlacementMode::Spotter
+        | PlacementMode::Enemy
+        | PlacementMode::Fob
+        | PlacementMode::Bunker
+        | PlacementMode::NoFire
+        | PlacementMode::Measure
+        | PlacementMode::Displace => {} // stay in current mode
     }
 }
 
@@ -696,56 +1085,189 @@ fn handle_marker_placement(
 #[allow(clippy::too_many_arguments)]
 pub fn MapView(
     map_file_name: String,
+    /// Custom image to draw instead of the stock map.
+    #[props(default)]
+    image_url: Option<String>,
+    /// Size of the map image, which sets its meters-per-pixel scale.
+    map_scale: MapScale,
     placement_mode: Signal<PlacementMode>,
     gun_positions: Signal<Vec<(f64, f64)>>,
     target_positions: Signal<Vec<(f64, f64)>>,
     spotter_positions: Signal<Vec<(f64, f64)>>,
+    enemy_positions: Signal<Vec<(f64, f64)>>,
+    fob_positions: Signal<Vec<(f64, f64)>>,
+    bunker_positions: Signal<Vec<(f64, f64)>>,
+    no_fire_positions: Signal<Vec<(f64, f64)>>,
+    /// Radius (m) of each no-fire zone; zones past the end use the default.
+    no_fire_radii: Signal<Vec<f64>>,
     gun_weapon_ids: Signal<Vec<String>>,
     gun_target_indices: Signal<Vec<Option<usize>>>,
+    marker_labels: Signal<MarkerLabels>,
+    /// Shells per salvo for each gun; more than one draws the expected spread.
+    gun_salvo_sizes: Signal<Vec<u32>>,
+    /// Alternate firing positions per gun, drawn as displacement routes.
+    gun_alternates: Signal<Vec<Vec<(f64, f64)>>>,
+    /// Progress per target; destroyed targets are drawn struck through.
+    target_statuses: Signal<Vec<TargetStatus>>,
+    shot_log: Signal<Vec<ShotRecord>>,
+    registrations: Signal<Vec<RegistrationPoint>>,
+    /// Ammo per gun, which sets its range rings.
+    gun_ammo: Signal<Vec<String>>,
+    /// Which guns show their range rings, and the custom rings to draw.
+    range_rings: Signal<RangeRings>,
     selected_weapon_slug: Signal<String>,
+    enemy_weapon_slug: Signal<String>,
     weapons: Vec<WeaponData>,
-    accuracy_radii_px: ReadSignal<Vec<Option<f64>>>,
+    spreads_px: ReadSignal<Vec<Option<ShellSpread>>>,
+    /// No-fire zones each gun's shells could land in; those zones are flagged.
+    danger_close: ReadSignal<Vec<Vec<usize>>>,
     selected_marker: Signal<Option<SelectedMarker>>,
+    /// Markers picked with Shift+click or a Shift+drag box; dragged and nudged together.
+    group_selection: Signal<Vec<SelectedMarker>>,
     undo_stack: Signal<Vec<PlanSnapshot>>,
     redo_stack: Signal<Vec<PlanSnapshot>>,
     wind_direction: Signal<Option<f64>>,
     wind_strength: Signal<u32>,
     reset_view_counter: Signal<u64>,
+    /// Where to open the map when this device hasn't saved its own view of it.
+    default_view: Option<MapViewPreset>,
+    /// Bumped by the parent to save the current zoom and position for this map.
+    save_view_counter: Signal<u64>,
+    /// Right-click removes the nearest marker immediately instead of opening a menu.
+    instant_remove: bool,
+    /// Outline markers and lines in white/black and draw them larger.
+    high_contrast: ReadSignal<bool>,
+    /// Colors the markers and lines are drawn in.
+    palette: ReadSignal<Palette>,
+    /// Live War API structures on this map (meters), drawn under the markers.
+    /// New targets placed near one held by the other side snap onto it.
+    map_structures: ReadSignal<Vec<MapStructureData>>,
+    /// Community placement counts per grid cell, shaded under everything else.
+    heatmap: ReadSignal<Vec<HeatCell>>,
+    heatmap_color: &'static str,
+    /// The planner's side; structures held by the other side are snap targets.
+    faction: Faction,
+    /// User settings: placement auto-cycling and the keypad grid.
+    settings: ReadSignal<Settings>,
+    /// Markers that differ from the version being compared with, drawn as ghosts.
+    plan_changes: ReadSignal<Vec<PlanChange>>,
+    /// Battery template the next click stamps instead of placing a marker.
+    armed_template: Signal<Option<BatteryTemplate>>,
+    /// Someone else holds the plan's edit lock: allow panning and zooming only.
+    #[props(default)]
+    read_only: bool,
 ) -> Element {
-    let image_url = format!("/static/images/maps/{}.webp", map_file_name);
+    let image_url = image_url.unwrap_or_else(|| crate::api::map_image_url(&map_file_name));
 
     // Zoom / pan state (local — resets when component is re-created via `key`)
     let mut zoom = use_signal(|| 1.0_f64);
     let mut pan_x = use_signal(|| 0.0_f64);
     let mut pan_y = use_signal(|| 0.0_f64);
 
+    // Opening view: this device's saved view, else the map's default. Used once.
+    let mut initial_view = use_signal(|| load_saved_view(&map_file_name).or(default_view));
+
     // Reset zoom/pan when parent signals via reset_view_counter
     use_effect(move || {
         // Read the Signal inside the effect so Dioxus tracks it as a dependency
         let _counter = *reset_view_counter.read();
+        if let Some(view) = initial_view.with_mut(|v| v.take()) {
+            if let Some(rect) = container_rect() {
+                let z = view.zoom.clamp(ZOOM_MIN, ZOOM_MAX);
+                let center = coords::meters_to_map_px(view.center.x, view.center.y, map_scale);
+                let (px, py) = pan_for_center(center, z, rect.width(), rect.height(), map_scale);
+                zoom.set(z);
+                pan_x.set(px);
+                pan_y.set(py);
+                return;
+            }
+        }
         zoom.set(1.0);
         pan_x.set(0.0);
         pan_y.set(0.0);
     });
 
+    // Save the current view for this map when parent signals via save_view_counter
+    let view_file_name = map_file_name.clone();
+    let mut seen_save = use_signal(|| *save_view_counter.peek());
+    use_effect(move || {
+        let counter = *save_view_counter.read();
+        if counter == *seen_save.peek() {
+            return;
+        }
+        seen_save.set(counter);
+        let Some(rect) = container_rect() else {
+            return;
+        };
+        let z = *zoom.peek();
+        if let Some((cx, cy)) = coords::client_to_map_px_zoomed(
+            rect.width() / 2.0,
+            rect.height() / 2.0,
+            rect.width(),
+            z,
+            *pan_x.peek(),
+            *pan_y.peek(),
+            map_scale,
+        ) {
+            let (x, y) = coords::map_px_to_meters(cx, cy, map_scale);
+            save_view(
+                &view_file_name,
+                &MapViewPreset {
+                    zoom: z,
+                    center: Position { x, y },
+                },
+            );
+        }
+    });
+
     // Mutable bindings for undo/redo (Signal is Copy)
     let mut undo_stack = undo_stack;
     let mut redo_stack = redo_stack;
 
+    // Live structures in map-image px, for drawing and for target snapping
+    let structure_icons = use_memo(move || {
+        map_structures
+            .read()
+            .iter()
+            .map(|s| structure_icon(s, map_scale))
+            .collect::<Vec<_>>()
+    });
+
     // Local closure to snapshot state before mutations
     let mut push_snapshot = move || {
         let snap = capture_snapshot(
             &gun_positions,
             &target_positions,
             &spotter_positions,
+            &enemy_positions,
+            &fob_positions,
+            &bunker_positions,
+            &no_fire_positions,
+            &no_fire_radii,
             &gun_weapon_ids,
             &gun_target_indices,
+            &marker_labels,
+            &gun_salvo_sizes,
+            &gun_alternates,
+            &target_statuses,
+            &shot_log,
+            &registrations,
+            &gun_ammo,
+            &range_rings,
             &wind_direction,
             &wind_strength,
         );
         push_undo(&mut undo_stack, &mut redo_stack, snap);
     };
 
+    // Right-click context menu (None when closed)
+    let mut context_menu = use_signal(|| None::<ContextMenuState>);
+
+    // Hover position (container px) for the live move preview
+    let mut preview_cursor = use_signal(|| None::<(f64, f64)>);
+    // The SVG memo takes ownership of `weapons`; the preview needs its own copy
+    let preview_weapons = weapons.clone();
+
     // Drag state (mouse)
     let mut is_dragging = use_signal(|| false);
     let mut did_drag = use_signal(|| false);
@@ -753,6 +1275,59 @@ pub fn MapView(
     let mut drag_start_y = use_signal(|| 0.0_f64);
     let mut drag_start_pan_x = use_signal(|| 0.0_f64);
     let mut drag_start_pan_y = use_signal(|| 0.0_f64);
+    // Marker under the mouse-down, if any: dragging moves it instead of panning
+    let mut marker_drag = use_signal(|| None::<MarkerDrag>);
+    // Shift+drag on empty map: selection box corners (container px)
+    let mut box_select = use_signal(|| None::<((f64, f64), (f64, f64))>);
+    // Measure mode: up to two clicked points (map px)
+    let mut measure_points = use_signal(Vec::<(f64, f64)>::new);
+
+    // Displace mode: the gun whose route is being planned
+    let mut displace_gun = use_signal(|| None::<usize>);
+    // No-fire mode: centre of the zone being drawn (map px)
+    let mut no_fire_center = use_signal(|| None::<(f64, f64)>);
+
+    // Leaving measure mode drops the line; leaving displace mode drops the
+    // picked gun; leaving no-fire mode drops a half-drawn zone
+    use_effect(move || {
+        let mode = *placement_mode.read();
+        if mode != PlacementMode::Measure {
+            measure_points.write().clear();
+        }
+        if mode != PlacementMode::Displace {
+            displace_gun.set(None);
+        }
+        if mode != PlacementMode::NoFire {
+            no_fire_center.set(None);
+        }
+    });
+
+    // Displace-mode click: pick a gun or extend its route
+    let mut handle_displace_click = move |client_x: f64, client_y: f64, touch: bool| {
+        let z = *zoom.read();
+        let Some(click) = coords::click_to_map_px_zoomed(
+            client_x,
+            client_y,
+            MAP_CONTAINER_ID,
+            z,
+            *pan_x.read(),
+            *pan_y.read(),
+            map_scale,
+        ) else {
+            return;
+        };
+        let threshold = hit_threshold(z, touch);
+        let guns = gun_positions.read().clone();
+        let active = *displace_gun.read();
+        match displace_click(click, threshold, &guns, active) {
+            DisplaceClick::PickGun(gi) => displace_gun.set(Some(gi)),
+            DisplaceClick::AddStop(gi) => {
+                push_snapshot();
+                add_displacement_stop(&mut gun_alternates.write(), gi, click);
+            }
+            DisplaceClick::Nothing => {}
+        }
+    };
 
     // Touch state
     let mut touch_start_pos = use_signal(|| None::<(f64, f64)>);
@@ -766,47 +1341,145 @@ pub fn MapView(
     let mut pinch_start_pan_x = use_signal(|| 0.0_f64);
     let mut pinch_start_pan_y = use_signal(|| 0.0_f64);
 
+    // Grid cells on screen. Only changes when panning crosses a grid line, so
+    // the overlay below doesn't rebuild on every pan.
+    let grid_view = use_memo(move || match container_rect() {
+        Some(rect) => visible_grid(
+            rect.width(),
+            rect.height(),
+            *zoom.read(),
+            *pan_x.read(),
+            *pan_y.read(),
+            map_scale,
+        ),
+        None => GridWindow::ALL,
+    });
+
+    // The overlay is drawn in a web worker once it's up; until then, or if it
+    // fails, it's drawn here. The canvas overlay doesn't use the worker.
+    let mut worker_ready = use_signal(|| false);
+    let mut worker_svg = use_signal(String::new);
+    let overlay_worker = use_hook(move || {
+        if CANVAS_OVERLAY {
+            return None;
+        }
+        OverlayWorker::spawn(
+            move |ready| worker_ready.set(ready),
+            move |svg| worker_svg.set(svg),
+        )
+        .map(Rc::new)
+    });
+
     // Memoize SVG generation — only recomputes when positions, zoom, selection,
-    // weapons, pairings, or accuracy radii change. Pan changes (pan_x/pan_y)
-    // are read outside this memo so they don't trigger SVG rebuilds.
-    let svg_html = use_memo(move || {
+    // weapons, pairings, or shell spreads change. Pan changes (pan_x/pan_y)
+    // are read outside this memo so they don't trigger SVG rebuilds. With the
+    // canvas overlay the markers come back as a scene for the canvas instead;
+    // with the worker the SVG comes back later in `worker_svg`.
+    let overlay = use_memo(move || {
         let guns = gun_positions.read();
         let targets = target_positions.read();
         let spotters = spotter_positions.read();
+        let enemies = enemy_positions.read();
+        let fobs = fob_positions.read();
+        let bunkers = bunker_positions.read();
+        let no_fire_zones = no_fire_positions.read();
         let wids = gun_weapon_ids.read();
         let pairings = gun_target_indices.read();
-        let acc_radii = accuracy_radii_px.read();
+        let labels = marker_labels.read();
+        let spreads = spreads_px.read();
+        let mode = *placement_mode.read();
 
-        let gun_weapons: Vec<Option<&WeaponData>> = wids
+        // Danger zones show once an enemy weapon is picked and enemies matter
+        let enemy_slug = enemy_weapon_slug.read();
+        let danger_weapon = weapons
             .iter()
-            .map(|slug| weapons.iter().find(|w| w.slug == *slug))
-            .collect();
-
-        let cur_zoom = *zoom.read();
-        let cur_selected = *selected_marker.read();
-        let colors = &MARKER_COLORS;
-        let cw = container_rect().map(|r| r.width()).unwrap_or(REFERENCE_WIDTH);
+            .find(|w| w.slug == *enemy_slug)
+            .filter(|_| mode == PlacementMode::Enemy || !enemies.is_empty())
+            .map(WeaponData::to_weapon);
 
-        let svg_content = build_svg_content(
-            &guns,
-            &targets,
-            &spotters,
-            &gun_weapons,
-            &pairings,
-            &acc_radii,
-            cur_zoom,
-            cw,
-            cur_selected,
-            colors,
-        );
-        format!(
-            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" preserveAspectRatio="none" style="position:absolute;top:0;left:0;width:100%;height:100%;pointer-events:none;z-index:5;">{}</svg>"#,
-            grid::MAP_WIDTH_PX,
-            grid::MAP_HEIGHT_PX,
-            svg_content
-        )
+        let ammo = gun_ammo.read();
+        let rings = range_rings.read();
+        let gun_weapons: Vec<Option<Weapon>> = wids
+            .iter()
+            .enumerate()
+            .map(|(i, slug)| {
+                let weapon = weapons.iter().find(|w| w.slug == *slug)?.to_weapon();
+                Some(weapon.with_ammo(models::gun_ammo(&ammo, i)).unwrap_or(weapon))
+            })
+            .collect();
+        let gun_weapons: Vec<Option<&Weapon>> = gun_weapons.iter().map(Option::as_ref).collect();
+        let wind = (*wind_direction.read()).map(|direction| WindInput {
+            direction,
+            strength: (*wind_strength.read()).min(u8::MAX as u32) as u8,
+        });
+
+        let mut danger_zones: Vec<usize> = danger_close.read().iter().flatten().copied().collect();
+        danger_zones.sort_unstable();
+        danger_zones.dedup();
+        let ctx = RenderContext {
+            guns: &guns,
+            targets: &targets,
+            spotters: &spotters,
+            enemies: &enemies,
+            fobs: &fobs,
+            bunkers: &bunkers,
+            no_fire_zones: &no_fire_zones,
+            no_fire_radii: &no_fire_radii.read(),
+            danger_close: &danger_zones,
+            gun_weapons: &gun_weapons,
+            gun_target_indices: &pairings,
+            spreads: &spreads,
+            salvo_sizes: &gun_salvo_sizes.read(),
+            gun_alternates: &gun_alternates.read(),
+            labels: &labels,
+            target_statuses: &target_statuses.read(),
+            range_rings: &rings,
+            heatmap: &heatmap.read(),
+            heatmap_color,
+            structures: &structure_icons.read(),
+            plan_changes: &plan_changes.read(),
+            group: &group_selection.read(),
+            selected: *selected_marker.read(),
+            displace_gun: (mode == PlacementMode::Displace)
+                .then(|| *displace_gun.read())
+                .flatten(),
+            danger_weapon: danger_weapon.as_ref(),
+            wind: wind.as_ref(),
+            zoom: *zoom.read(),
+            view: *grid_view.read(),
+            container_width: container_rect()
+                .map(|r| r.width())
+                .unwrap_or(REFERENCE_WIDTH),
+            always_keypad: settings.read().always_keypad,
+            high_contrast: *high_contrast.read(),
+            colors: palette.read().colors(),
+            map: map_scale,
+        };
+        if CANVAS_OVERLAY {
+            (
+                Some(render_overlay(SVG_LAYERS_OVER_CANVAS, &ctx)),
+                marker_scene(&ctx),
+            )
+        } else if let Some(worker) = overlay_worker.as_ref().filter(|_| *worker_ready.read()) {
+            worker.render(OverlayJob::new(&ctx, *palette.read()));
+            (None, Vec::<Paint>::new())
+        } else {
+            (Some(render_overlay(MAP_LAYERS, &ctx)), Vec::new())
+        }
+    });
+
+    // Repaint the canvas whenever the scene changes
+    #[cfg(feature = "canvas-overlay")]
+    use_effect(move || {
+        let overlay = overlay.read();
+        crate::components::map_canvas::paint_scene(&overlay.1, *zoom.read(), map_scale);
     });
 
+    let overlay_svg = match &overlay.read().0 {
+        Some(svg) => svg.clone(),
+        None => worker_svg.read().clone(),
+    };
+
     let cur_pan_x = *pan_x.read();
     let cur_pan_y = *pan_y.read();
     let cur_zoom = *zoom.read();
@@ -821,9 +1494,18 @@ pub fn MapView(
         "map-container dragging"
     } else if has_selection {
         "map-container move-mode"
+    } else if *placement_mode.read() == PlacementMode::Measure {
+        "map-container measure-mode"
+    } else if *placement_mode.read() == PlacementMode::Displace {
+        "map-container displace-mode"
     } else {
         "map-container"
     };
+    let container_class = if *high_contrast.read() {
+        format!("{container_class} high-contrast")
+    } else {
+        container_class.to_string()
+    };
 
     // Build coord readout tags (fresh signal reads — cheap, Dioxus deduplicates tracking)
     let guns = gun_positions.read();
@@ -839,7 +1521,7 @@ pub fn MapView(
             } else {
                 format!("GUN {}", i + 1)
             };
-            (label, coords::format_px_as_grid(pos.0, pos.1))
+            (label, coords::format_px_as_grid(pos.0, pos.1, map_scale))
         })
         .collect();
     let target_tags: Vec<(String, String)> = targets
@@ -851,7 +1533,7 @@ pub fn MapView(
             } else {
                 format!("TGT {}", i + 1)
             };
-            (label, coords::format_px_as_grid(pos.0, pos.1))
+            (label, coords::format_px_as_grid(pos.0, pos.1, map_scale))
         })
         .collect();
     let spotter_tags: Vec<(String, String)> = spotters
@@ -863,10 +1545,78 @@ pub fn MapView(
             } else {
                 format!("SPT {}", i + 1)
             };
-            (label, coords::format_px_as_grid(pos.0, pos.1))
+            (label, coords::format_px_as_grid(pos.0, pos.1, map_scale))
         })
         .collect();
 
+    // Live move preview: for the marker being dragged, or the selected marker
+    // while the map isn't being panned
+    let dragged_marker = marker_drag.read().filter(|d| d.moved).map(|d| d.marker);
+    let hover = *preview_cursor.read();
+    let preview = hover
+        .filter(|_| !dragging || dragged_marker.is_some())
+        .zip(dragged_marker.or(cur_selected))
+        .and_then(|(pos, marker)| {
+            let cw = container_rect().map(|r| r.width())?;
+            let cursor = coords::client_to_map_px_zoomed(
+                pos.0, pos.1, cw, cur_zoom, cur_pan_x, cur_pan_y, map_scale,
+            )?;
+            let wids = gun_weapon_ids.read();
+            let gun_weapons: Vec<Option<&WeaponData>> = wids
+                .iter()
+                .map(|slug| preview_weapons.iter().find(|w| w.slug == *slug))
+                .collect();
+            let readings = move_preview(
+                marker,
+                cursor,
+                &guns,
+                &targets,
+                &gun_target_indices.read(),
+                &gun_weapons,
+                map_scale,
+            );
+            (!readings.is_empty()).then_some((pos, readings))
+        });
+
+    // Measure line (between both points, or from the first point to the
+    // cursor) and the no-fire zone being drawn (from its centre to the cursor)
+    let draft_svg = {
+        let points = measure_points.read();
+        let cw = container_rect()
+            .map(|r| r.width())
+            .unwrap_or(REFERENCE_WIDTH);
+        let cursor = hover.and_then(|pos| {
+            coords::client_to_map_px_zoomed(
+                pos.0, pos.1, cw, cur_zoom, cur_pan_x, cur_pan_y, map_scale,
+            )
+        });
+        let end = match points.as_slice() {
+            [_, b] => Some(*b),
+            [_] => cursor,
+            _ => None,
+        };
+        let s = marker_scale(cur_zoom, cw);
+        let content = match (points.first().zip(end), *no_fire_center.read()) {
+            (Some((a, b)), _) => Some(build_measure_line(*a, b, s, map_scale)),
+            (None, Some(center)) => {
+                cursor.map(|edge| build_no_fire_draft(center, edge, s, map_scale))
+            }
+            (None, None) => None,
+        };
+        content.map(|content| {
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" preserveAspectRatio="none" style="position:absolute;top:0;left:0;width:100%;height:100%;pointer-events:none;z-index:6;">{}</svg>"#,
+                map_scale.width_px,
+                map_scale.height_px,
+                content
+            )
+        })
+    };
+
+    // Placements are tracked per map; one copy for each handler that places
+    let click_map_id = map_file_name.clone();
+    let touch_map_id = map_file_name;
+
     rsx! {
         div {
             id: MAP_CONTAINER_ID,
@@ -874,6 +1624,7 @@ pub fn MapView(
 
             onwheel: move |evt: Event<WheelData>| {
                 evt.prevent_default();
+                context_menu.set(None);
 
                 let delta_y = wheel_delta_y(evt.data().delta());
                 let factor = if delta_y < 0.0 { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
@@ -890,7 +1641,7 @@ pub fn MapView(
 
                 let (new_px, new_py) =
                     zoom_pan_at_cursor(cx, cy, old_z, new_z, *pan_x.read(), *pan_y.read());
-                let (px, py) = clamp_pan(new_px, new_py, new_z, rect.width(), rect.height());
+                let (px, py) = clamp_pan(new_px, new_py, new_z, rect.width(), rect.height(), map_scale);
 
                 zoom.set(new_z);
                 pan_x.set(px);
@@ -898,6 +1649,8 @@ pub fn MapView(
             },
 
             onmousedown: move |evt: Event<MouseData>| {
+                // Any press outside the menu dismisses it
+                context_menu.set(None);
                 // Only track drag/click for left mouse button
                 if evt.trigger_button() != Some(MouseButton::Primary) {
                     return;
@@ -909,23 +1662,139 @@ pub fn MapView(
                 drag_start_y.set(client.y);
                 drag_start_pan_x.set(*pan_x.read());
                 drag_start_pan_y.set(*pan_y.read());
+
+                // Pressing on a marker grabs it
+                let shift = evt.modifiers().contains(Modifiers::SHIFT);
+                let hit = if read_only
+                    || matches!(
+                        *placement_mode.read(),
+                        PlacementMode::Measure | PlacementMode::Displace
+                    )
+                {
+                    None
+                } else {
+                    coords::click_to_map_px_zoomed(
+                        client.x, client.y, MAP_CONTAINER_ID,
+                        *zoom.read(), *pan_x.read(), *pan_y.read(), map_scale,
+                    )
+                    .and_then(|click| {
+                        let threshold = hit_threshold(*zoom.read(), false);
+                        find_marker_at(
+                            click, threshold, *placement_mode.read(),
+                            &gun_positions.read(), &target_positions.read(),
+                            &spotter_positions.read(), &enemy_positions.read(),
+                            &fob_positions.read(), &bunker_positions.read(), &no_fire_positions.read(),
+                        )
+                    })
+                };
+                marker_drag.set(hit.map(|(kind, index)| MarkerDrag {
+                    marker: SelectedMarker { kind, index },
+                    start: (client.x, client.y),
+                    moved: false,
+                }));
+
+                // Shift+drag on empty map draws a selection box instead of panning
+                if shift && hit.is_none() && !read_only {
+                    if let Some(rect) = container_rect() {
+                        let pos = (client.x - rect.left(), client.y - rect.top());
+                        box_select.set(Some((pos, pos)));
+                    }
+                }
             },
 
             onmousemove: move |evt: Event<MouseData>| {
+                let client = evt.client_coordinates();
+                let boxed = *box_select.read();
+                if let Some((start, _)) = boxed {
+                    if let Some(rect) = container_rect() {
+                        let pos = (client.x - rect.left(), client.y - rect.top());
+                        box_select.set(Some((start, pos)));
+                    }
+                    return;
+                }
+                let grabbed = *marker_drag.read();
+                if let Some(mut drag) = grabbed.filter(|_| *is_dragging.read()) {
+                    if !drag.moved {
+                        if !past_drag_threshold(drag.start, (client.x, client.y), DRAG_THRESHOLD) {
+                            return;
+                        }
+                        // One undo step for the whole drag
+                        push_snapshot();
+                        drag.moved = true;
+                        marker_drag.set(Some(drag));
+                    }
+                    let Some(rect) = container_rect() else { return };
+                    let pos = (client.x - rect.left(), client.y - rect.top());
+                    let last = *preview_cursor.read();
+                    if last.is_some_and(|prev| point_distance(prev, pos) < PREVIEW_MIN_STEP) {
+                        return;
+                    }
+                    preview_cursor.set(Some(pos));
+                    if let Some(map_pos) = coords::click_to_map_px_zoomed(
+                        client.x, client.y, MAP_CONTAINER_ID,
+                        *zoom.read(), *pan_x.read(), *pan_y.read(), map_scale,
+                    ) {
+                        let group = group_selection.read().clone();
+                        if group.contains(&drag.marker) {
+                            // Dragging one member of the multi-selection moves them all
+                            let current = match drag.marker.kind {
+                                MarkerKind::Gun => gun_positions.read().get(drag.marker.index).copied(),
+                                MarkerKind::Target => target_positions.read().get(drag.marker.index).copied(),
+                                MarkerKind::Spotter => spotter_positions.read().get(drag.marker.index).copied(),
+                                MarkerKind::Enemy => enemy_positions.read().get(drag.marker.index).copied(),
+                                MarkerKind::Fob => fob_positions.read().get(drag.marker.index).copied(),
+                                MarkerKind::Bunker => bunker_positions.read().get(drag.marker.index).copied(),
+                                MarkerKind::NoFire => no_fire_positions.read().get(drag.marker.index).copied(),
+                            };
+                            if let Some(cur) = current {
+                                translate_group(
+                                    &group, (map_pos.0 - cur.0, map_pos.1 - cur.1), map_scale,
+                                    &mut gun_positions, &mut target_positions,
+                                    &mut spotter_positions, &mut enemy_positions,
+                                    &mut fob_positions, &mut bunker_positions, &mut no_fire_positions,
+                                );
+                            }
+                        } else {
+                            set_marker_position(
+                                drag.marker, map_pos,
+                                &mut gun_positions, &mut target_positions,
+                                &mut spotter_positions, &mut enemy_positions,
+                                &mut fob_positions, &mut bunker_positions, &mut no_fire_positions,
+                            );
+                        }
+                    }
+                    return;
+                }
                 if !*is_dragging.read() {
+                    // Track the cursor for the move preview, the open measure line
+                    // or the zone being drawn, throttled to real movement
+                    let measuring =
+                        measure_points.read().len() == 1 || no_fire_center.read().is_some();
+                    if !measuring && (read_only || selected_marker.read().is_none()) {
+                        return;
+                    }
+                    let Some(rect) = container_rect() else { return };
+                    let pos = (client.x - rect.left(), client.y - rect.top());
+                    let last = *preview_cursor.read();
+                    let moved = last
+                        .map(|prev| point_distance(prev, pos) >= PREVIEW_MIN_STEP)
+                        .unwrap_or(true);
+                    if moved {
+                        preview_cursor.set(Some(pos));
+                    }
                     return;
                 }
-                let client = evt.client_coordinates();
-                let dx = client.x - *drag_start_x.read();
-                let dy = client.y - *drag_start_y.read();
+                let start = (*drag_start_x.read(), *drag_start_y.read());
+                let dx = client.x - start.0;
+                let dy = client.y - start.1;
 
-                if !*did_drag.read() && (dx.abs() > DRAG_THRESHOLD || dy.abs() > DRAG_THRESHOLD) {
+                if !*did_drag.read() && past_drag_threshold(start, (client.x, client.y), DRAG_THRESHOLD) {
                     did_drag.set(true);
                 }
                 if *did_drag.read() {
                     let new_px = *drag_start_pan_x.read() + dx;
                     let new_py = *drag_start_pan_y.read() + dy;
-                    let (px, py) = clamp_pan_to_container(new_px, new_py, *zoom.read());
+                    let (px, py) = clamp_pan_to_container(new_px, new_py, *zoom.read(), map_scale);
                     pan_x.set(px);
                     pan_y.set(py);
                 }
@@ -936,19 +1805,90 @@ pub fn MapView(
                 let was_drag = *did_drag.read();
                 is_dragging.set(false);
 
+                // Finish a selection box: add everything inside it to the group
+                if let Some((start, end)) = box_select.take() {
+                    let cw = container_rect().map(|r| r.width()).unwrap_or(0.0);
+                    let (z, px, py) = (*zoom.read(), *pan_x.read(), *pan_y.read());
+                    let corners = coords::client_to_map_px_zoomed(start.0, start.1, cw, z, px, py, map_scale)
+                        .zip(coords::client_to_map_px_zoomed(end.0, end.1, cw, z, px, py, map_scale));
+                    if let Some((a, b)) = corners {
+                        let inside = markers_in_rect(
+                            a, b,
+                            &gun_positions.read(), &target_positions.read(),
+                            &spotter_positions.read(), &enemy_positions.read(),
+                            &fob_positions.read(), &bunker_positions.read(), &no_fire_positions.read(),
+                        );
+                        let mut group = group_selection.write();
+                        for m in inside {
+                            if !group.contains(&m) {
+                                group.push(m);
+                            }
+                        }
+                    }
+                    selected_marker.set(None);
+                    return;
+                }
+
+                // Releasing a dragged marker drops it where it is
+                let grabbed = marker_drag.take();
+                if grabbed.is_some_and(|d| d.moved) {
+                    preview_cursor.set(None);
+                    return;
+                }
+
+                // Shift+click on a marker adds it to or drops it from the group
+                if let Some(drag) = grabbed.filter(|_| evt.modifiers().contains(Modifiers::SHIFT)) {
+                    toggle_group_member(&mut group_selection.write(), drag.marker);
+                    selected_marker.set(None);
+                    return;
+                }
+
                 // A mouseup without drag movement = a click
                 if was_dragging && !was_drag {
                     let client = evt.client_coordinates();
+                    if *placement_mode.read() == PlacementMode::Measure {
+                        if let Some(p) = coords::click_to_map_px_zoomed(
+                            client.x, client.y, MAP_CONTAINER_ID,
+                            *zoom.read(), *pan_x.read(), *pan_y.read(), map_scale,
+                        ) {
+                            add_measure_point(&mut measure_points.write(), p);
+                        }
+                        return;
+                    }
+                    if *placement_mode.read() == PlacementMode::Displace {
+                        if !read_only {
+                            handle_displace_click(client.x, client.y, false);
+                        }
+                        return;
+                    }
+                    let targets_snap = target_positions.read().clone();
+                    if selected_marker.read().is_none()
+                        && expand_cluster_at(
+                            client.x, client.y, &targets_snap,
+                            &mut zoom, &mut pan_x, &mut pan_y, map_scale,
+                        )
+                    {
+                        return;
+                    }
+                    if read_only {
+                        return;
+                    }
                     if let Some((img_x, img_y)) = coords::click_to_map_px_zoomed(
                         client.x, client.y, MAP_CONTAINER_ID,
-                        *zoom.read(), *pan_x.read(), *pan_y.read(),
+                        *zoom.read(), *pan_x.read(), *pan_y.read(), map_scale,
                     ) {
                         handle_marker_placement(
-                            img_x, img_y, *zoom.read(),
+                            img_x, img_y, hit_threshold(*zoom.read(), false),
                             &mut selected_marker, &mut placement_mode,
                             &mut gun_positions, &mut target_positions, &mut spotter_positions,
+                            &mut enemy_positions,
+                            &mut fob_positions,
+                            &mut bunker_positions,
+                            &mut no_fire_positions, &mut no_fire_radii, &mut no_fire_center,
                             &mut gun_weapon_ids, &mut gun_target_indices,
-                            &selected_weapon_slug, &mut push_snapshot,
+                            &selected_weapon_slug, &mut marker_labels, &mut armed_template,
+                            &structure_icons.read(), own_side(faction), map_scale,
+                            &click_map_id, settings.read().auto_cycle, &mut push_snapshot,
                         );
                     }
                 }
@@ -956,73 +1896,72 @@ pub fn MapView(
 
             oncontextmenu: move |evt: Event<MouseData>| {
                 evt.prevent_default();
+                if read_only {
+                    return;
+                }
                 let client = evt.client_coordinates();
                 if let Some((img_x, img_y)) = coords::click_to_map_px_zoomed(
                     client.x, client.y, MAP_CONTAINER_ID,
-                    *zoom.read(), *pan_x.read(), *pan_y.read(),
+                    *zoom.read(), *pan_x.read(), *pan_y.read(), map_scale,
                 ) {
-                    let threshold = REMOVE_THRESHOLD / (*zoom.read()).min(5.0);
+                    let threshold = hit_threshold(*zoom.read(), false);
                     let click = (img_x, img_y);
 
                     // Clone positions to avoid borrow conflicts with Signal read/write
                     let guns_snap = gun_positions.read().clone();
                     let targets_snap = target_positions.read().clone();
                     let spotters_snap = spotter_positions.read().clone();
+                    let enemies_snap = enemy_positions.read().clone();
+                    let fobs_snap = fob_positions.read().clone();
+                    let bunkers_snap = bunker_positions.read().clone();
+                    let no_fire_snap = no_fire_positions.read().clone();
 
                     // Snapshot selection to avoid borrow conflicts
                     let cur_sel = *selected_marker.read();
 
-                    // Check active placement mode's list first for priority
-                    let mode = *placement_mode.read();
-                    let mode_hit = match mode {
-                        PlacementMode::Gun => find_nearest(&guns_snap, click, threshold)
-                            .map(|idx| (MarkerKind::Gun, idx)),
-                        PlacementMode::Target => find_nearest(&targets_snap, click, threshold)
-                            .map(|idx| (MarkerKind::Target, idx)),
-                        PlacementMode::Spotter => find_nearest(&spotters_snap, click, threshold)
-                            .map(|idx| (MarkerKind::Spotter, idx)),
-                    };
+                    let target = find_marker_at(
+                        click, threshold, *placement_mode.read(),
+                        &guns_snap, &targets_snap, &spotters_snap, &enemies_snap,
+                        &fobs_snap, &bunkers_snap, &no_fire_snap,
+                    );
 
-                    // If nothing found in the active mode's list, check all lists
-                    let target = mode_hit.or_else(|| {
-                        let gun_hit = find_nearest(&guns_snap, click, threshold)
-                            .map(|idx| (idx, dist(&guns_snap[idx], click), MarkerKind::Gun));
-                        let tgt_hit = find_nearest(&targets_snap, click, threshold)
-                            .map(|idx| (idx, dist(&targets_snap[idx], click), MarkerKind::Target));
-                        let spt_hit = find_nearest(&spotters_snap, click, threshold)
-                            .map(|idx| (idx, dist(&spotters_snap[idx], click), MarkerKind::Spotter));
-
-                        [gun_hit, tgt_hit, spt_hit]
-                            .into_iter()
-                            .flatten()
-                            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
-                            .map(|(idx, _, kind)| (kind, idx))
-                    });
-
-                    // Perform the removal and fixup
-                    if let Some((kind, idx)) = target {
+                    let Some((kind, idx)) = target else {
+                        context_menu.set(None);
+                        return;
+                    };
+                    if instant_remove {
                         push_snapshot();
+                        group_selection.set(Vec::new());
                         remove_marker(
                             kind, idx,
                             &mut gun_positions, &mut target_positions, &mut spotter_positions,
+                            &mut enemy_positions, &mut fob_positions, &mut bunker_positions, &mut no_fire_positions, &mut no_fire_radii,
                             &mut gun_weapon_ids, &mut gun_target_indices,
+                            &mut marker_labels,
+                            &mut gun_salvo_sizes,
+                            &mut gun_alternates,
+                            &mut target_statuses,
+                            &mut shot_log,
+                            &mut registrations,
+                            &mut gun_ammo,
+                            &mut range_rings,
                         );
-                        // Fixup selection
-                        if let Some(sm) = cur_sel {
-                            if sm.kind == kind {
-                                if sm.index == idx {
-                                    selected_marker.set(None);
-                                } else if sm.index > idx {
-                                    selected_marker.set(Some(SelectedMarker { kind, index: sm.index - 1 }));
-                                }
-                            }
-                        }
+                        selected_marker.set(selection_after_remove(cur_sel, kind, idx));
+                    } else if let Some(rect) = container_rect() {
+                        context_menu.set(Some(ContextMenuState {
+                            x: client.x - rect.left(),
+                            y: client.y - rect.top(),
+                            marker: SelectedMarker { kind, index: idx },
+                        }));
                     }
                 }
             },
 
             onmouseleave: move |_| {
                 is_dragging.set(false);
+                marker_drag.set(None);
+                box_select.set(None);
+                preview_cursor.set(None);
             },
 
             ondoubleclick: move |evt: Event<MouseData>| {
@@ -1091,7 +2030,7 @@ pub fn MapView(
                         cx, cy, old_z, new_z,
                         *pinch_start_pan_x.read(), *pinch_start_pan_y.read(),
                     );
-                    let (px, py) = clamp_pan(new_px, new_py, new_z, rect.width(), rect.height());
+                    let (px, py) = clamp_pan(new_px, new_py, new_z, rect.width(), rect.height(), map_scale);
                     zoom.set(new_z);
                     pan_x.set(px);
                     pan_y.set(py);
@@ -1108,7 +2047,7 @@ pub fn MapView(
                         if *touch_did_pan.read() {
                             let new_px = *touch_start_pan_x.read() + dx;
                             let new_py = *touch_start_pan_y.read() + dy;
-                            let (px, py) = clamp_pan_to_container(new_px, new_py, *zoom.read());
+                            let (px, py) = clamp_pan_to_container(new_px, new_py, *zoom.read(), map_scale);
                             pan_x.set(px);
                             pan_y.set(py);
                         }
@@ -1131,17 +2070,51 @@ pub fn MapView(
 
                 // Single-finger tap: if no pan occurred and all fingers are up, treat as tap
                 if remaining == 0 && !*touch_did_pan.read() {
-                    if let Some(start) = *touch_start_pos.read() {
+                    let start_pos = *touch_start_pos.read();
+                    if let Some(start) = start_pos {
+                        if *placement_mode.read() == PlacementMode::Measure {
+                            if let Some(p) = coords::click_to_map_px_zoomed(
+                                start.0, start.1, MAP_CONTAINER_ID,
+                                *zoom.read(), *pan_x.read(), *pan_y.read(), map_scale,
+                            ) {
+                                add_measure_point(&mut measure_points.write(), p);
+                            }
+                            touch_start_pos.set(None);
+                            return;
+                        }
+                        if *placement_mode.read() == PlacementMode::Displace {
+                            if !read_only {
+                                handle_displace_click(start.0, start.1, true);
+                            }
+                            touch_start_pos.set(None);
+                            return;
+                        }
+                        let targets_snap = target_positions.read().clone();
+                        let expanded = selected_marker.read().is_none()
+                            && expand_cluster_at(
+                                start.0, start.1, &targets_snap,
+                                &mut zoom, &mut pan_x, &mut pan_y, map_scale,
+                            );
+                        if expanded || read_only {
+                            touch_start_pos.set(None);
+                            return;
+                        }
                         if let Some((img_x, img_y)) = coords::click_to_map_px_zoomed(
                             start.0, start.1, MAP_CONTAINER_ID,
-                            *zoom.read(), *pan_x.read(), *pan_y.read(),
+                            *zoom.read(), *pan_x.read(), *pan_y.read(), map_scale,
                         ) {
                             handle_marker_placement(
-                                img_x, img_y, *zoom.read(),
+                                img_x, img_y, hit_threshold(*zoom.read(), true),
                                 &mut selected_marker, &mut placement_mode,
                                 &mut gun_positions, &mut target_positions, &mut spotter_positions,
+                                &mut enemy_positions,
+                                &mut fob_positions,
+                                &mut bunker_positions,
+                                &mut no_fire_positions, &mut no_fire_radii, &mut no_fire_center,
                                 &mut gun_weapon_ids, &mut gun_target_indices,
-                                &selected_weapon_slug, &mut push_snapshot,
+                                &selected_weapon_slug, &mut marker_labels, &mut armed_template,
+                                &structure_icons.read(), own_side(faction), map_scale,
+                                &touch_map_id, settings.read().auto_cycle, &mut push_snapshot,
                             );
                         }
                     }
@@ -1166,10 +2139,24 @@ pub fn MapView(
 
                 img { src: "{image_url}", draggable: "false" }
 
+                if CANVAS_OVERLAY {
+                    canvas {
+                        id: CANVAS_ID,
+                        style: "position:absolute;top:0;left:0;width:100%;height:100%;pointer-events:none;z-index:5;",
+                    }
+                }
+
                 div {
-                    dangerous_inner_html: "{svg_html}",
+                    dangerous_inner_html: "{overlay_svg}",
                     style: "position:absolute;top:0;left:0;width:100%;height:100%;pointer-events:none;",
                 }
+
+                if let Some(svg) = draft_svg {
+                    div {
+                        dangerous_inner_html: "{svg}",
+                        style: "position:absolute;top:0;left:0;width:100%;height:100%;pointer-events:none;",
+                    }
+                }
             }
 
             // Coordinate readout (outside the transform so it stays fixed)
@@ -1190,6 +2177,105 @@ pub fn MapView(
                     }
                 }
             }
+
+            if let Some((a, b)) = *box_select.read() {
+                div {
+                    class: "box-select",
+                    style: "left: {a.0.min(b.0)}px; top: {a.1.min(b.1)}px; width: {(a.0 - b.0).abs()}px; height: {(a.1 - b.1).abs()}px;",
+                }
+            }
+
+            if let Some(((px, py), readings)) = preview {
+                div {
+                    class: "move-preview",
+                    style: "left: {px + PREVIEW_CURSOR_OFFSET}px; top: {py + PREVIEW_CURSOR_OFFSET}px;",
+                    for r in readings {
+                        {
+                            let cls = match r.in_range {
+                                Some(true) => "in-range-text",
+                                Some(false) => "out-of-range-text",
+                                None => "",
+                            };
+                            let rounded = (r.distance / 5.0).round() * 5.0;
+                            rsx! {
+                                div { class: "{cls}",
+                                    "G{r.gun + 1}  {r.azimuth:.1}\u{00b0}  {rounded:.0}m"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(menu) = *context_menu.read() {
+                ContextMenu {
+                    state: menu,
+                    selected: cur_selected,
+                    target_status: (menu.marker.kind == MarkerKind::Target)
+                        .then(|| target_status(&target_statuses.read(), menu.marker.index)),
+                    on_close: move |_| context_menu.set(None),
+                    on_action: move |action: ContextAction| {
+                        context_menu.set(None);
+                        let SelectedMarker { kind, index } = menu.marker;
+                        let cur_sel = *selected_marker.read();
+                        match action {
+                            ContextAction::Select => {
+                                selected_marker.set(Some(menu.marker));
+                            }
+                            ContextAction::Pair => {
+                                let pair = match (kind, cur_sel) {
+                                    (MarkerKind::Target, Some(sm)) if sm.kind == MarkerKind::Gun => Some((sm.index, index)),
+                                    (MarkerKind::Gun, Some(sm)) if sm.kind == MarkerKind::Target => Some((index, sm.index)),
+                                    _ => None,
+                                };
+                                if let Some((gi, ti)) = pair {
+                                    push_snapshot();
+                                    if let Some(entry) = gun_target_indices.write().get_mut(gi) {
+                                        *entry = Some(ti);
+                                    }
+                                    selected_marker.set(None);
+                                }
+                            }
+                            ContextAction::Duplicate => {
+                                push_snapshot();
+                                let new_index = duplicate_marker(
+                                    kind, index,
+                                    &mut gun_positions, &mut target_positions, &mut spotter_positions,
+                                    &mut enemy_positions, &mut fob_positions, &mut bunker_positions, &mut no_fire_positions,
+                                    &mut gun_weapon_ids, &mut gun_target_indices,
+                                    map_scale,
+                                );
+                                if let Some(ni) = new_index {
+                                    selected_marker.set(Some(SelectedMarker { kind, index: ni }));
+                                }
+                            }
+                            ContextAction::SetStatus(status) => {
+                                push_snapshot();
+                                set_target_status(&mut target_statuses.write(), index, status);
+                            }
+                            ContextAction::Remove => {
+                                push_snapshot();
+                                group_selection.set(Vec::new());
+                                remove_marker(
+                                    kind, index,
+                                    &mut gun_positions, &mut target_positions, &mut spotter_positions,
+                                    &mut enemy_positions, &mut fob_positions, &mut bunker_positions, &mut no_fire_positions, &mut no_fire_radii,
+                                    &mut gun_weapon_ids, &mut gun_target_indices,
+                                    &mut marker_labels,
+                                    &mut gun_salvo_sizes,
+                                    &mut gun_alternates,
+                                    &mut target_statuses,
+                                    &mut shot_log,
+                                    &mut registrations,
+                                    &mut gun_ammo,
+                                    &mut range_rings,
+                                );
+                                selected_marker.set(selection_after_remove(cur_sel, kind, index));
+                            }
+                        }
+                    },
+                }
+            }
         }
     }
 }
@@ -1198,97 +2284,7 @@ pub fn MapView(
 mod tests {
     use super::*;
 
-    // --- build_firing_lines tests ---
-
-    #[test]
-    fn test_firing_lines_with_explicit_pairings() {
-        let guns = vec![(100.0, 200.0), (300.0, 400.0)];
-        let targets = vec![(150.0, 250.0), (350.0, 450.0)];
-        // Gun 0 → Target 1, Gun 1 → Target 0
-        let pairings = vec![Some(1), Some(0)];
-        let mut svg = String::new();
-        build_firing_lines(&mut svg, &guns, &targets, &pairings, 1.0, &MARKER_COLORS);
-        // Should draw line from gun 0 to target 1
-        assert!(svg.contains(r#"x1="100""#));
-        assert!(svg.contains(r#"y1="200""#));
-        assert!(svg.contains(r#"x2="350""#));
-        assert!(svg.contains(r#"y2="450""#));
-        // Should draw line from gun 1 to target 0
-        assert!(svg.contains(r#"x1="300""#));
-        assert!(svg.contains(r#"y1="400""#));
-        assert!(svg.contains(r#"x2="150""#));
-        assert!(svg.contains(r#"y2="250""#));
-    }
-
-    #[test]
-    fn test_firing_lines_unpaired_gun_draws_nothing() {
-        let guns = vec![(100.0, 200.0)];
-        let targets = vec![(150.0, 250.0)];
-        let pairings = vec![None]; // Gun 0 unpaired
-        let mut svg = String::new();
-        build_firing_lines(&mut svg, &guns, &targets, &pairings, 1.0, &MARKER_COLORS);
-        assert!(
-            svg.is_empty(),
-            "Unpaired gun should not produce a firing line"
-        );
-    }
-
-    #[test]
-    fn test_firing_lines_invalid_target_index_draws_nothing() {
-        let guns = vec![(100.0, 200.0)];
-        let targets = vec![(150.0, 250.0)];
-        let pairings = vec![Some(5)]; // Out-of-bounds target index
-        let mut svg = String::new();
-        build_firing_lines(&mut svg, &guns, &targets, &pairings, 1.0, &MARKER_COLORS);
-        assert!(
-            svg.is_empty(),
-            "Invalid target index should not produce a firing line"
-        );
-    }
-
-    #[test]
-    fn test_firing_lines_multiple_guns_same_target() {
-        let guns = vec![(100.0, 200.0), (300.0, 400.0)];
-        let targets = vec![(500.0, 600.0)];
-        // Both guns target the same target
-        let pairings = vec![Some(0), Some(0)];
-        let mut svg = String::new();
-        build_firing_lines(&mut svg, &guns, &targets, &pairings, 1.0, &MARKER_COLORS);
-        // Count the number of line elements — should be 2
-        let line_count = svg.matches("<line").count();
-        assert_eq!(
-            line_count, 2,
-            "Two guns pointing at same target should produce two lines"
-        );
-    }
-
-    // --- build_accuracy_circles tests ---
-
-    #[test]
-    fn test_accuracy_circles_with_pairings() {
-        let guns = vec![(100.0, 200.0)];
-        let targets = vec![(150.0, 250.0), (350.0, 450.0)];
-        let pairings = vec![Some(1)]; // Gun 0 → Target 1
-        let accuracy = vec![Some(10.0)];
-        let mut svg = String::new();
-        build_accuracy_circles(&mut svg, &guns, &targets, &pairings, &accuracy, 1.0, &MARKER_COLORS);
-        // Circle should be at target 1's position
-        assert!(svg.contains(r#"cx="350""#));
-        assert!(svg.contains(r#"cy="450""#));
-        // Should NOT be at target 0's position
-        assert!(!svg.contains(r#"cx="150""#));
-    }
-
-    #[test]
-    fn test_accuracy_circles_unpaired_gun_draws_nothing() {
-        let guns = vec![(100.0, 200.0)];
-        let targets = vec![(150.0, 250.0)];
-        let pairings = vec![None];
-        let accuracy = vec![Some(10.0)];
-        let mut svg = String::new();
-        build_accuracy_circles(&mut svg, &guns, &targets, &pairings, &accuracy, 1.0, &MARKER_COLORS);
-        assert!(svg.is_empty());
-    }
+    const MAP: MapScale = MapScale::STANDARD;
 
     // --- find_nearest tests ---
 
@@ -1313,17 +2309,123 @@ mod tests {
         assert_eq!(find_nearest(&positions, (102.0, 102.0), 30.0), Some(0));
     }
 
-    // --- marker_label tests ---
+    // --- context menu helpers ---
+
+    #[test]
+    fn test_selection_after_remove() {
+        let sel = |kind, index| Some(SelectedMarker { kind, index });
+        assert_eq!(
+            selection_after_remove(sel(MarkerKind::Gun, 1), MarkerKind::Gun, 1),
+            None
+        );
+        assert_eq!(
+            selection_after_remove(sel(MarkerKind::Gun, 2), MarkerKind::Gun, 0),
+            sel(MarkerKind::Gun, 1)
+        );
+        assert_eq!(
+            selection_after_remove(sel(MarkerKind::Target, 2), MarkerKind::Gun, 0),
+            sel(MarkerKind::Target, 2)
+        );
+        assert_eq!(selection_after_remove(None, MarkerKind::Gun, 0), None);
+    }
 
     #[test]
-    fn test_marker_label_single() {
-        assert_eq!(marker_label("GUN", 0, 1), "GUN");
+    fn test_find_marker_at_prefers_active_mode() {
+        let guns = vec![(100.0, 100.0)];
+        let targets = vec![(105.0, 100.0)];
+        let hit = find_marker_at(
+            (104.0, 100.0),
+            30.0,
+            PlacementMode::Gun,
+            &guns,
+            &targets,
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(hit, Some((MarkerKind::Gun, 0)));
+        let hit = find_marker_at(
+            (104.0, 100.0),
+            30.0,
+            PlacementMode::Spotter,
+            &guns,
+            &targets,
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(hit, Some((MarkerKind::Target, 0)));
+        let hit = find_marker_at(
+            (500.0, 500.0),
+            30.0,
+            PlacementMode::Gun,
+            &guns,
+            &targets,
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(hit, None);
+        // Intel markers are picked like any other
+        let fobs = vec![(300.0, 300.0)];
+        let hit = find_marker_at(
+            (305.0, 300.0),
+            30.0,
+            PlacementMode::Gun,
+            &guns,
+            &targets,
+            &[],
+            &[],
+            &fobs,
+            &[],
+            &[],
+        );
+        assert_eq!(hit, Some((MarkerKind::Fob, 0)));
     }
 
     #[test]
-    fn test_marker_label_multiple() {
-        assert_eq!(marker_label("GUN", 0, 3), "GUN 1");
-        assert_eq!(marker_label("GUN", 2, 3), "GUN 3");
+    fn test_touch_hits_reach_further() {
+        assert_eq!(hit_threshold(1.0, false), REMOVE_THRESHOLD);
+        assert_eq!(
+            hit_threshold(2.0, true),
+            REMOVE_THRESHOLD / 2.0 * TOUCH_HIT_SCALE
+        );
+        // Zooming past 5x stops shrinking the allowance
+        assert_eq!(hit_threshold(8.0, false), hit_threshold(5.0, false));
+        let guns = vec![(100.0, 100.0)];
+        let tap = (100.0 + REMOVE_THRESHOLD * 1.5, 100.0);
+        let hit = find_marker_at(
+            tap,
+            hit_threshold(1.0, false),
+            PlacementMode::Gun,
+            &guns,
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(hit, None);
+        let hit = find_marker_at(
+            tap,
+            hit_threshold(1.0, true),
+            PlacementMode::Gun,
+            &guns,
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(hit, Some((MarkerKind::Gun, 0)));
     }
 
     // --- clamp_pan tests ---
@@ -1332,7 +2434,7 @@ mod tests {
     fn test_clamp_pan_zoom1_map_fits_in_container() {
         // Container is taller than the map: no panning needed
         // container_w=2048, image_h = 2048*(1776/2048) = 1776, container_h=2000 > 1776
-        let (px, py) = clamp_pan(0.0, 0.0, 1.0, 2048.0, 2000.0);
+        let (px, py) = clamp_pan(0.0, 0.0, 1.0, 2048.0, 2000.0, MAP);
         assert!((px - 0.0).abs() < 0.01);
         assert!((py - 0.0).abs() < 0.01);
     }
@@ -1342,18 +2444,353 @@ mod tests {
         // Wide container: image renders taller than container
         // container_w=3200, image_h = 3200*(1776/2048) ≈ 2775, container_h=2000
         // min_pan_y = -(2775 - 2000) = -775
-        let (_, py) = clamp_pan(0.0, -400.0, 1.0, 3200.0, 2000.0);
+        let (_, py) = clamp_pan(0.0, -400.0, 1.0, 3200.0, 2000.0, MAP);
         assert!((py - (-400.0)).abs() < 0.01, "Should allow panning down");
-        let (_, py) = clamp_pan(0.0, -1000.0, 1.0, 3200.0, 2000.0);
-        let min_y = -(3200.0 * (grid::MAP_HEIGHT_PX / grid::MAP_WIDTH_PX) - 2000.0);
+        let (_, py) = clamp_pan(0.0, -1000.0, 1.0, 3200.0, 2000.0, MAP);
+        let min_y = -(3200.0 * (MAP.height_px / MAP.width_px) - 2000.0);
         assert!((py - min_y).abs() < 0.01, "Should clamp at min_pan_y");
     }
 
+    #[test]
+    fn test_visible_grid_follows_zoom_and_pan() {
+        let (cw, ch) = (1024.0, 700.0);
+        let whole = visible_grid(cw, ch, 1.0, 0.0, 0.0, MAP);
+        assert_eq!(whole.cols, GridWindow::ALL.cols);
+        let (px, py) = pan_for_center((1200.0, 900.0), 4.0, cw, ch, MAP);
+        let view = visible_grid(cw, ch, 4.0, px, py, MAP);
+        assert!(view.cols.1 - view.cols.0 < 8);
+        assert!(view.rows.1 - view.rows.0 < 8);
+        let col = (1200.0 / MAP.grid_col_px(1)) as usize;
+        assert!((view.cols.0..view.cols.1).contains(&col));
+    }
+
+    #[test]
+    fn test_pan_for_center_round_trips() {
+        let (cw, ch) = (1024.0, 700.0);
+        let center = (1200.0, 900.0);
+        let (px, py) = pan_for_center(center, 3.0, cw, ch, MAP);
+        let (x, y) =
+            coords::client_to_map_px_zoomed(cw / 2.0, ch / 2.0, cw, 3.0, px, py, MAP).unwrap();
+        assert!((x - center.0).abs() < 0.01);
+        assert!((y - center.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_pan_for_center_clamps_at_edges() {
+        // Centering on the top-left corner can't pan past the map edge.
+        let (px, py) = pan_for_center((0.0, 0.0), 2.0, 1024.0, 700.0, MAP);
+        assert!((px - 0.0).abs() < 0.01);
+        assert!((py - 0.0).abs() < 0.01);
+    }
+
     #[test]
     fn test_clamp_pan_prevents_positive_pan() {
         // Pan should never go positive (would show empty space on left/top)
-        let (px, py) = clamp_pan(50.0, 50.0, 1.0, 800.0, 600.0);
+        let (px, py) = clamp_pan(50.0, 50.0, 1.0, 800.0, 600.0, MAP);
         assert!((px - 0.0).abs() < 0.01);
         assert!((py - 0.0).abs() < 0.01);
     }
+
+    // --- move preview tests ---
+
+    fn weapon_with_range(min_range: f64, max_range: f64) -> WeaponData {
+        WeaponData {
+            slug: "test-gun".to_string(),
+            faction: "COLONIAL".to_string(),
+            display_name: "Test Gun".to_string(),
+            min_range,
+            max_range,
+            acc_radius_min: 10.0,
+            acc_radius_max: 20.0,
+            wind_drift_min: 5.0,
+            wind_drift_max: 10.0,
+            flight_time_min: 0.0,
+            flight_time_max: 0.0,
+            ammo: vec![],
+            crew: None,
+            deploy_time: None,
+            mount: None,
+            area_fire: false,
+        }
+    }
+
+    #[test]
+    fn test_stamp_positions_centres_template_and_stays_on_map() {
+        use foxhole_shared::template::Formation;
+
+        let template = BatteryTemplate::formation("Line", "mortar", 3, 100.0, Formation::Line);
+        let center = coords::meters_to_map_px(1000.0, 900.0, MAP);
+        let guns = stamp_positions(&template, center, MAP);
+        assert_eq!(guns.len(), 3);
+        assert!(guns.iter().all(|(w, _)| w == "mortar"));
+        let (x, y) = coords::map_px_to_meters(guns[2].1 .0, guns[2].1 .1, MAP);
+        assert!((x - 1100.0).abs() < 1e-9 && (y - 900.0).abs() < 1e-9);
+
+        // Stamped against the west edge, guns past it land on the edge
+        let guns = stamp_positions(&template, (0.0, center.1), MAP);
+        assert_eq!(guns[0].1 .0, 0.0);
+        assert!(guns[2].1 .0 > 0.0);
+    }
+
+    #[test]
+    fn test_move_preview_target_lists_paired_guns() {
+        let guns = vec![(100.0, 100.0), (300.0, 100.0), (500.0, 100.0)];
+        let targets = vec![(100.0, 400.0)];
+        let pairings = vec![Some(0), None, Some(0)];
+        let w = weapon_with_range(0.0, 10_000.0);
+        let gun_weapons = vec![Some(&w), None, None];
+        let marker = SelectedMarker {
+            kind: MarkerKind::Target,
+            index: 0,
+        };
+
+        let readings = move_preview(
+            marker,
+            (100.0, 300.0),
+            &guns,
+            &targets,
+            &pairings,
+            &gun_weapons,
+            MAP,
+        );
+        assert_eq!(readings.len(), 2);
+        assert_eq!(readings[0].gun, 0);
+        assert_eq!(readings[1].gun, 2);
+        // Straight down from gun 0 to the cursor (not the old target position)
+        assert!((readings[0].azimuth - 180.0).abs() < 1e-6);
+        let (_, my0) = coords::map_px_to_meters(0.0, 100.0, MAP);
+        let (_, my1) = coords::map_px_to_meters(0.0, 300.0, MAP);
+        assert!((readings[0].distance - (my1 - my0)).abs() < 1e-6);
+        assert_eq!(readings[0].in_range, Some(true));
+        assert_eq!(readings[1].in_range, None);
+    }
+
+    #[test]
+    fn test_move_preview_gun_uses_paired_target() {
+        let guns = vec![(100.0, 100.0)];
+        let targets = vec![(100.0, 400.0)];
+        let w = weapon_with_range(0.0, 10.0);
+        let marker = SelectedMarker {
+            kind: MarkerKind::Gun,
+            index: 0,
+        };
+
+        let readings = move_preview(
+            marker,
+            (100.0, 500.0),
+            &guns,
+            &targets,
+            &[Some(0)],
+            &[Some(&w)],
+            MAP,
+        );
+        assert_eq!(readings.len(), 1);
+        assert!(readings[0].azimuth.abs() < 1e-6 || (readings[0].azimuth - 360.0).abs() < 1e-6);
+        assert_eq!(readings[0].in_range, Some(false));
+
+        // Unpaired gun or non-firing marker: nothing to preview
+        assert!(move_preview(
+            marker,
+            (0.0, 0.0),
+            &guns,
+            &targets,
+            &[None],
+            &[Some(&w)],
+            MAP
+        )
+        .is_empty());
+        let spotter = SelectedMarker {
+            kind: MarkerKind::Spotter,
+            index: 0,
+        };
+        assert!(move_preview(
+            spotter,
+            (0.0, 0.0),
+            &guns,
+            &targets,
+            &[Some(0)],
+            &[Some(&w)],
+            MAP
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_past_drag_threshold() {
+        assert!(!past_drag_threshold(
+            (10.0, 10.0),
+            (13.0, 7.0),
+            DRAG_THRESHOLD
+        ));
+        assert!(past_drag_threshold(
+            (10.0, 10.0),
+            (13.5, 10.0),
+            DRAG_THRESHOLD
+        ));
+        assert!(past_drag_threshold(
+            (10.0, 10.0),
+            (10.0, 6.0),
+            DRAG_THRESHOLD
+        ));
+    }
+
+    fn gun(index: usize) -> SelectedMarker {
+        SelectedMarker {
+            kind: MarkerKind::Gun,
+            index,
+        }
+    }
+
+    #[test]
+    fn test_displace_click_picks_gun_then_adds_stops() {
+        let guns = [(100.0, 100.0), (500.0, 500.0)];
+        assert_eq!(
+            displace_click((105.0, 100.0), 20.0, &guns, None),
+            DisplaceClick::PickGun(0)
+        );
+        assert_eq!(
+            displace_click((300.0, 300.0), 20.0, &guns, None),
+            DisplaceClick::Nothing
+        );
+        assert_eq!(
+            displace_click((300.0, 300.0), 20.0, &guns, Some(1)),
+            DisplaceClick::AddStop(1)
+        );
+        // Clicking another gun switches to it
+        assert_eq!(
+            displace_click((500.0, 505.0), 20.0, &guns, Some(0)),
+            DisplaceClick::PickGun(1)
+        );
+        // A removed gun can't be extended
+        assert_eq!(
+            displace_click((300.0, 300.0), 20.0, &guns, Some(5)),
+            DisplaceClick::Nothing
+        );
+    }
+
+    #[test]
+    fn test_add_displacement_stop_grows_list() {
+        let mut alternates = Vec::new();
+        add_displacement_stop(&mut alternates, 2, (1.0, 1.0));
+        add_displacement_stop(&mut alternates, 2, (2.0, 2.0));
+        assert_eq!(alternates.len(), 3);
+        assert!(alternates[0].is_empty());
+        assert_eq!(alternates[2], vec![(1.0, 1.0), (2.0, 2.0)]);
+    }
+
+    #[test]
+    fn test_add_measure_point_restarts_after_two() {
+        let mut points = Vec::new();
+        add_measure_point(&mut points, (1.0, 1.0));
+        add_measure_point(&mut points, (2.0, 2.0));
+        assert_eq!(points, vec![(1.0, 1.0), (2.0, 2.0)]);
+        add_measure_point(&mut points, (3.0, 3.0));
+        assert_eq!(points, vec![(3.0, 3.0)]);
+    }
+
+    #[test]
+    fn test_add_no_fire_zone_takes_the_drawn_radius() {
+        let map = MapScale::STANDARD;
+        // A zone placed from the context menu has no radius of its own yet
+        let mut positions = vec![(50.0, 50.0)];
+        let mut radii = Vec::new();
+        let (cx, cy) = coords::map_px_to_meters(100.0, 100.0, map);
+        let edge = coords::meters_to_map_px(cx + 150.0, cy, map);
+        add_no_fire_zone(&mut positions, &mut radii, (100.0, 100.0), edge, map);
+        assert_eq!(positions, vec![(50.0, 50.0), (100.0, 100.0)]);
+        assert_eq!(radii[0], NO_FIRE_RADIUS_M);
+        assert!((radii[1] - 150.0).abs() < 1e-6);
+
+        // Clicking the centre twice keeps the default; huge zones are capped
+        add_no_fire_zone(
+            &mut positions,
+            &mut radii,
+            (100.0, 100.0),
+            (100.5, 100.0),
+            map,
+        );
+        assert_eq!(radii[2], NO_FIRE_RADIUS_M);
+        add_no_fire_zone(
+            &mut positions,
+            &mut radii,
+            (0.0, 0.0),
+            (map.width_px, map.height_px),
+            map,
+        );
+        assert_eq!(radii[3], MAX_NO_FIRE_RADIUS);
+        assert_eq!(radii.len(), positions.len());
+    }
+
+    #[test]
+    fn test_toggle_group_member() {
+        let mut group = vec![gun(0)];
+        toggle_group_member(&mut group, gun(2));
+        assert_eq!(group, vec![gun(0), gun(2)]);
+        toggle_group_member(&mut group, gun(0));
+        assert_eq!(group, vec![gun(2)]);
+    }
+
+    #[test]
+    fn test_markers_in_rect_any_corner_order() {
+        let guns = [(10.0, 10.0), (500.0, 500.0)];
+        let spotters = [(50.0, 80.0)];
+        let expected = vec![
+            gun(0),
+            SelectedMarker {
+                kind: MarkerKind::Spotter,
+                index: 0,
+            },
+        ];
+        let inside = markers_in_rect(
+            (0.0, 0.0),
+            (100.0, 100.0),
+            &guns,
+            &[],
+            &spotters,
+            &[],
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(inside, expected);
+        let inside = markers_in_rect(
+            (100.0, 100.0),
+            (0.0, 0.0),
+            &guns,
+            &[],
+            &spotters,
+            &[],
+            &[],
+            &[],
+            &[],
+        );
+        assert_eq!(inside, expected);
+    }
+
+    #[test]
+    fn test_clamp_group_delta_keeps_formation_on_map() {
+        let points = [(20.0, 100.0), (80.0, 150.0)];
+        // Free movement is untouched
+        assert_eq!(
+            clamp_group_delta(&points, (10.0, -50.0), MAP),
+            (10.0, -50.0)
+        );
+        // The leftmost point stops at the edge; the group moves by the same amount
+        assert_eq!(clamp_group_delta(&points, (-50.0, 0.0), MAP), (-20.0, 0.0));
+        let far = clamp_group_delta(&points, (0.0, 5000.0), MAP);
+        assert_eq!(far.1, MAP.height_px - 150.0);
+        assert_eq!(clamp_group_delta(&[], (3.0, 4.0), MAP), (3.0, 4.0));
+    }
+
+    #[test]
+    fn test_nudge_delta_directions() {
+        let (_, up) = nudge_delta(&Key::ArrowUp, false, MAP).unwrap();
+        assert!(up < 0.0);
+        let (right, _) = nudge_delta(&Key::ArrowRight, false, MAP).unwrap();
+        let (big_right, _) = nudge_delta(&Key::ArrowRight, true, MAP).unwrap();
+        assert!((big_right - right * 5.0).abs() < 1e-9);
+        // 10 m in map-image pixels
+        assert!((right - MAP.meters_to_px_distance(10.0)).abs() < 0.1);
+        assert_eq!(nudge_delta(&Key::Enter, false, MAP), None);
+    }
 }
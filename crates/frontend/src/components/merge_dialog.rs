@@ -0,0 +1,186 @@
+use dioxus::prelude::*;
+
+use crate::api::{MarkerDiffData, PlanConflictData};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MergeChoice {
+    /// Overwrite their version with mine.
+    KeepMine,
+    /// Drop my changes and load their version.
+    KeepTheirs,
+    /// Union of both sides' markers, then save.
+    Merge,
+    Cancel,
+}
+
+fn count_line(label: &str, diff: &MarkerDiffData) -> Option<String> {
+    let mine = diff.only_mine.len();
+    let theirs = diff.only_theirs.len();
+    if mine == 0 && theirs == 0 {
+        return None;
+    }
+    Some(format!("{label}: {mine} only yours, {theirs} only theirs"))
+}
+
+/// One line per difference between the two versions.
+pub fn conflict_summary(conflict: &PlanConflictData) -> Vec<String> {
+    let mut lines: Vec<String> = [
+        count_line("Guns", &conflict.guns),
+        count_line("Targets", &conflict.targets),
+        count_line("Spotters", &conflict.spotters),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if conflict.name_changed {
+        lines.push(format!("Name: theirs is \"{}\"", conflict.theirs.name));
+    }
+    if conflict.wind_changed {
+        lines.push("Wind settings differ".to_string());
+    }
+    if lines.is_empty() {
+        lines.push("Same markers, only pairings or weapons differ".to_string());
+    }
+    lines
+}
+
+#[component]
+pub fn MergeDialog(
+    conflict: Signal<Option<PlanConflictData>>,
+    on_choice: EventHandler<MergeChoice>,
+) -> Element {
+    let Some(current) = conflict.read().clone() else {
+        return rsx! {};
+    };
+    let lines = conflict_summary(&current);
+
+    rsx! {
+        div {
+            class: "help-overlay-backdrop",
+            onclick: move |_| on_choice.call(MergeChoice::Cancel),
+
+            div {
+                class: "help-overlay merge-dialog",
+                role: "dialog",
+                "aria-label": "Resolve plan conflict",
+                onclick: move |evt: Event<MouseData>| evt.stop_propagation(),
+
+                h2 { "Plan changed by someone else" }
+                p { class: "merge-intro",
+                    "Someone saved this plan while you were editing. Your changes have not been saved yet."
+                }
+                ul { class: "merge-summary",
+                    for line in lines {
+                        li { "{line}" }
+                    }
+                }
+                div { class: "merge-actions",
+                    button {
+                        title: "Add their markers to yours and save",
+                        onclick: move |_| on_choice.call(MergeChoice::Merge),
+                        "Merge markers"
+                    }
+                    button {
+                        class: "secondary",
+                        title: "Overwrite their version with yours",
+                        onclick: move |_| on_choice.call(MergeChoice::KeepMine),
+                        "Keep mine"
+                    }
+                    button {
+                        class: "secondary",
+                        title: "Discard your changes and load their version",
+                        onclick: move |_| on_choice.call(MergeChoice::KeepTheirs),
+                        "Keep theirs"
+                    }
+                    button {
+                        class: "secondary",
+                        onclick: move |_| on_choice.call(MergeChoice::Cancel),
+                        "Cancel"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{PlanData, PositionData};
+
+    fn diff(mine: usize, theirs: usize) -> MarkerDiffData {
+        let p = PositionData { x: 0.0, y: 0.0 };
+        MarkerDiffData {
+            only_mine: vec![p.clone(); mine],
+            only_theirs: vec![p; theirs],
+        }
+    }
+
+    fn conflict() -> PlanConflictData {
+        PlanConflictData {
+            theirs: PlanData {
+                id: "abc".to_string(),
+                name: "Their plan".to_string(),
+                map_id: "deadlands".to_string(),
+                weapon_ids: vec![],
+                gun_positions: vec![],
+                target_positions: vec![],
+                spotter_positions: vec![],
+                gun_target_indices: vec![],
+                gun_labels: vec![],
+                target_labels: vec![],
+                spotter_labels: vec![],
+                target_statuses: vec![],
+                wind_direction: None,
+                wind_strength: 0,
+                version: 2,
+                protected: false,
+                edit_token: None,
+                slug: None,
+                updated_at: None,
+                calibration: None,
+                shot_log: vec![],
+                registrations: vec![],
+                gun_ammo: vec![],
+                gun_rings_hidden: vec![],
+                custom_rings: vec![],
+                weapon_data_version: None,
+                enemy_gun_positions: vec![],
+                fob_positions: vec![],
+                bunker_positions: vec![],
+                no_fire_zone_positions: vec![],
+                no_fire_zone_radii: vec![],
+            },
+            guns: diff(1, 2),
+            targets: diff(0, 0),
+            spotters: diff(0, 1),
+            name_changed: false,
+            wind_changed: false,
+        }
+    }
+
+    #[test]
+    fn test_conflict_summary_lists_changed_kinds() {
+        let mut c = conflict();
+        c.name_changed = true;
+        assert_eq!(
+            conflict_summary(&c),
+            vec![
+                "Guns: 1 only yours, 2 only theirs",
+                "Spotters: 0 only yours, 1 only theirs",
+                "Name: theirs is \"Their plan\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conflict_summary_when_markers_match() {
+        let mut c = conflict();
+        c.guns = diff(0, 0);
+        c.spotters = diff(0, 0);
+        assert_eq!(
+            conflict_summary(&c),
+            vec!["Same markers, only pairings or weapons differ"]
+        );
+    }
+}
@@ -1,6 +1,22 @@
+pub mod battery_templates;
+pub mod bottom_sheet;
 pub mod calculation_display;
+pub mod calibration_panel;
+pub mod context_menu;
+pub mod counter_battery;
 pub mod help_overlay;
+pub mod logistics;
+pub mod map_canvas;
+pub mod map_layers;
+pub mod map_selector;
 pub mod map_view;
+pub mod merge_dialog;
+pub mod placement_action;
+pub mod plan_compare;
 pub mod plan_panel;
+pub mod plan_tabs;
+pub mod range_bearing;
+pub mod range_rings;
+pub mod settings_drawer;
 pub mod weapon_selector;
 pub mod wind_input;
@@ -0,0 +1,106 @@
+use dioxus::prelude::*;
+
+use crate::components::map_view::MarkerKind;
+
+/// What a typed-in position (range and bearing, grid reference) is used for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlacementAction {
+    /// Add a new marker of this kind there.
+    Place(MarkerKind),
+    /// Move the selected marker there.
+    MoveSelected,
+}
+
+impl PlacementAction {
+    /// The action to carry out: moving falls back to a new gun when there's
+    /// nothing to move.
+    pub fn effective(self, can_move: bool) -> PlacementAction {
+        match self {
+            PlacementAction::MoveSelected if !can_move => PlacementAction::Place(MarkerKind::Gun),
+            a => a,
+        }
+    }
+
+    fn value(self) -> &'static str {
+        match self {
+            PlacementAction::Place(MarkerKind::Target) => "target",
+            PlacementAction::Place(MarkerKind::Spotter) => "spotter",
+            PlacementAction::Place(MarkerKind::Enemy) => "enemy",
+            PlacementAction::Place(MarkerKind::Fob) => "fob",
+            PlacementAction::Place(MarkerKind::Bunker) => "bunker",
+            PlacementAction::Place(MarkerKind::NoFire) => "no-fire",
+            PlacementAction::Place(MarkerKind::Gun) => "gun",
+            PlacementAction::MoveSelected => "move",
+        }
+    }
+
+    fn from_value(value: &str) -> PlacementAction {
+        match value {
+            "target" => PlacementAction::Place(MarkerKind::Target),
+            "spotter" => PlacementAction::Place(MarkerKind::Spotter),
+            "enemy" => PlacementAction::Place(MarkerKind::Enemy),
+            "fob" => PlacementAction::Place(MarkerKind::Fob),
+            "bunker" => PlacementAction::Place(MarkerKind::Bunker),
+            "no-fire" => PlacementAction::Place(MarkerKind::NoFire),
+            "move" => PlacementAction::MoveSelected,
+            _ => PlacementAction::Place(MarkerKind::Gun),
+        }
+    }
+}
+
+/// Pick a new marker kind, or moving the selected marker.
+#[component]
+pub fn PlacementActionSelect(
+    action: Signal<PlacementAction>,
+    /// Whether "Move selected marker" can be picked.
+    can_move: bool,
+    label: String,
+) -> Element {
+    let current = action.read().effective(can_move).value();
+    rsx! {
+        select {
+            "aria-label": "{label}",
+            onchange: move |evt: Event<FormData>| action.set(PlacementAction::from_value(&evt.value())),
+            option { value: "gun", selected: current == "gun", "New gun" }
+            option { value: "target", selected: current == "target", "New target" }
+            option { value: "spotter", selected: current == "spotter", "New spotter" }
+            option { value: "fob", selected: current == "fob", "New FOB" }
+            option { value: "bunker", selected: current == "bunker", "New observation bunker" }
+            option { value: "no-fire", selected: current == "no-fire", "New no-fire zone" }
+            option {
+                value: "move",
+                disabled: !can_move,
+                selected: current == "move",
+                "Move selected marker"
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_values_round_trip() {
+        for action in [
+            PlacementAction::Place(MarkerKind::Gun),
+            PlacementAction::Place(MarkerKind::Target),
+            PlacementAction::Place(MarkerKind::Spotter),
+            PlacementAction::Place(MarkerKind::Enemy),
+            PlacementAction::Place(MarkerKind::Fob),
+            PlacementAction::Place(MarkerKind::Bunker),
+            PlacementAction::Place(MarkerKind::NoFire),
+            PlacementAction::MoveSelected,
+        ] {
+            assert_eq!(PlacementAction::from_value(action.value()), action);
+        }
+    }
+
+    #[test]
+    fn test_move_needs_a_selection() {
+        let mv = PlacementAction::MoveSelected;
+        assert_eq!(mv.effective(true), mv);
+        assert_eq!(mv.effective(false), PlacementAction::Place(MarkerKind::Gun));
+    }
+}
@@ -0,0 +1,130 @@
+use dioxus::prelude::*;
+use foxhole_shared::merge::{MarkerChange, PlanChange};
+
+use crate::components::calculation_display::marker_name;
+use crate::components::map_view::{MarkerKind, MarkerLabels};
+
+/// "Gun 2 moved 42 m" for one row of the comparison. `counts` are the
+/// current guns, targets and spotters.
+fn change_text(change: &PlanChange, labels: &MarkerLabels, counts: [usize; 3]) -> String {
+    let (base, count) = match change.kind {
+        MarkerKind::Gun => ("Gun", counts[0]),
+        MarkerKind::Target => ("Target", counts[1]),
+        MarkerKind::Spotter => ("Spotter", counts[2]),
+        MarkerKind::Enemy => ("Enemy", 0),
+        MarkerKind::Fob => ("FOB", 0),
+        MarkerKind::Bunker => ("Bunker", 0),
+        MarkerKind::NoFire => ("No-fire zone", 0),
+    };
+    // A removed marker goes by its number in the other version, which may be
+    // past the end of the current list
+    let no_labels = MarkerLabels::default();
+    let labels = match change.change {
+        MarkerChange::Removed(_) => &no_labels,
+        _ => labels,
+    };
+    let count = count.max(change.index + 1);
+    let name = marker_name(labels, change.kind, change.index, base, count);
+    match change.change {
+        MarkerChange::Moved { .. } => format!("{name} moved {:.0} m", change.change.distance()),
+        MarkerChange::Added(_) => format!("{name} added"),
+        MarkerChange::Removed(_) => format!("{name} removed"),
+    }
+}
+
+/// What changed from another version or plan to the one on the map. The map
+/// draws the other version's markers as ghosts while this is open.
+#[component]
+pub fn PlanComparePanel(
+    /// The version or plan compared against, e.g. "v3" or a plan name.
+    compared: String,
+    changes: Vec<PlanChange>,
+    marker_labels: MarkerLabels,
+    gun_count: usize,
+    target_count: usize,
+    spotter_count: usize,
+    on_close: EventHandler<()>,
+) -> Element {
+    let counts = [gun_count, target_count, spotter_count];
+
+    rsx! {
+        div { class: "panel plan-compare",
+            div { class: "plan-compare-header",
+                h3 { "Compare" }
+                button {
+                    class: "remove-marker-btn",
+                    "aria-label": "Stop comparing",
+                    onclick: move |_| on_close.call(()),
+                    "\u{2715}"
+                }
+            }
+            p { class: "plan-compare-intro",
+                "Changes since {compared}; its markers are drawn as dashed ghosts."
+            }
+            if changes.is_empty() {
+                p { class: "plan-history-empty", "No markers moved, added or removed." }
+            } else {
+                ul { class: "plan-compare-changes",
+                    for change in changes.iter() {
+                        li { "{change_text(change, &marker_labels, counts)}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::models::Position;
+
+    fn change(kind: MarkerKind, index: usize, change: MarkerChange) -> PlanChange {
+        PlanChange {
+            kind,
+            index,
+            change,
+        }
+    }
+
+    #[test]
+    fn test_change_text() {
+        let at = |x: f64, y: f64| Position { x, y };
+        let mut labels = MarkerLabels::default();
+        labels.set(MarkerKind::Gun, 0, "Left");
+        let moved = MarkerChange::Moved {
+            from: at(0.0, 0.0),
+            to: at(30.0, 40.0),
+        };
+        assert_eq!(
+            change_text(&change(MarkerKind::Gun, 0, moved), &labels, [2, 1, 0]),
+            "Left moved 50 m"
+        );
+        assert_eq!(
+            change_text(
+                &change(MarkerKind::Target, 0, MarkerChange::Added(at(1.0, 1.0))),
+                &labels,
+                [2, 1, 0]
+            ),
+            "Target added"
+        );
+        // Removed markers are numbered as in the other version, not named
+        // after whatever now holds their index
+        assert_eq!(
+            change_text(
+                &change(MarkerKind::Gun, 0, MarkerChange::Removed(at(1.0, 1.0))),
+                &labels,
+                [0, 0, 0]
+            ),
+            "Gun removed"
+        );
+        assert_eq!(
+            change_text(
+                &change(MarkerKind::Spotter, 2, MarkerChange::Removed(at(1.0, 1.0))),
+                &labels,
+                [1, 0, 1]
+            ),
+            "Spotter 3 removed"
+        );
+    }
+}
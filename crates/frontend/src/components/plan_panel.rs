@@ -1,12 +1,112 @@
 use dioxus::prelude::*;
 
+use crate::api::{self, PayloadSize, PlanData, MAX_REQUEST_BYTES};
+use crate::pages::admin::format_bytes;
+
+/// "Size: 40.2 KB (6.1 KB compressed)", or just the size when it goes out uncompressed.
+fn size_text(size: PayloadSize) -> String {
+    let raw = format_bytes(size.raw as u64);
+    if size.sent < size.raw {
+        format!(
+            "Size: {} ({} compressed)",
+            raw,
+            format_bytes(size.sent as u64)
+        )
+    } else {
+        format!("Size: {}", raw)
+    }
+}
+
+/// "v3 · 2026-01-01 12:30 · Plan name" for one row of the version history.
+fn version_label(plan: &PlanData) -> String {
+    let saved = plan
+        .updated_at
+        .as_deref()
+        .map(|t| t.get(..16).unwrap_or(t).replace('T', " "))
+        .unwrap_or_default();
+    format!("v{} \u{b7} {} \u{b7} {}", plan.version, saved, plan.name)
+}
+
+/// A share link with a button that copies it.
+#[component]
+fn CopyLink(label: &'static str, url: String) -> Element {
+    rsx! {
+        div { class: "plan-url",
+            input {
+                r#type: "text",
+                "aria-label": label,
+                readonly: true,
+                value: "{url}",
+            }
+            button {
+                class: "secondary",
+                onclick: move |_| {
+                    let url = url.clone();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        if let Some(window) = web_sys::window() {
+                            let clipboard = window.navigator().clipboard();
+                            let _ = wasm_bindgen_futures::JsFuture::from(
+                                clipboard.write_text(&url)
+                            ).await;
+                        }
+                    });
+                },
+                "Copy"
+            }
+        }
+    }
+}
+
 #[component]
 pub fn PlanPanel(
     plan_name: Signal<String>,
     plan_url: Signal<Option<String>>,
     save_error: Signal<Option<String>>,
+    /// A save made offline is waiting for the connection to come back.
+    #[props(default)]
+    save_queued: bool,
     on_save: EventHandler<()>,
+    /// Serialized size of the plan as it would be saved.
+    payload_size: PayloadSize,
+    /// Viewing someone else's protected plan: saving makes a new plan instead of updating it.
+    #[props(default)]
+    saves_copy: bool,
+    /// Id and version of the stored plan; enables the version history.
+    #[props(default)]
+    saved_plan: Option<(String, u32)>,
+    /// Another session holds the edit lock, so restoring is disabled.
+    #[props(default)]
+    read_only: bool,
+    /// Opened from a read-only link: the plan can be looked at but not saved.
+    #[props(default)]
+    view_only: bool,
+    /// Restore the given earlier version of the stored plan.
+    on_restore: EventHandler<u32>,
+    /// Compare the map with the given earlier version.
+    on_compare: EventHandler<PlanData>,
+    /// Compare the map with another stored plan, by link or ID.
+    on_compare_link: EventHandler<String>,
+    /// A stored plan is open, so it can be forked into a new one.
+    #[props(default)]
+    can_fork: bool,
+    on_fork: EventHandler<()>,
+    /// Last stateless link made, which carries the plan in its fragment.
+    stateless_url: Signal<Option<String>>,
+    /// Make a stateless link for the plan as it is and copy it.
+    on_copy_stateless: EventHandler<()>,
 ) -> Element {
+    let mut show_history = use_signal(|| false);
+    let mut compare_link = use_signal(String::new);
+    let history_plan = use_memo(use_reactive!(|saved_plan| saved_plan));
+    // Refetched whenever the panel opens or a save bumps the version
+    let versions = use_resource(move || async move {
+        let (id, _) = history_plan()?;
+        if !show_history() {
+            return None;
+        }
+        Some(api::fetch_plan_versions(&id).await)
+    });
+
     rsx! {
         div { class: "panel",
             h3 { "Plan" }
@@ -14,15 +114,113 @@ pub fn PlanPanel(
                 r#type: "text",
                 "aria-label": "Plan name",
                 placeholder: "Plan name...",
+                readonly: view_only,
                 value: "{plan_name}",
                 oninput: move |evt: Event<FormData>| {
                     plan_name.set(evt.value().to_string());
                 },
             }
-            div { style: "margin-top: 8px;",
+            if !view_only {
+                div { style: "margin-top: 8px;",
+                    button {
+                        onclick: move |_| on_save.call(()),
+                        "Save & Share"
+                    }
+                    if can_fork {
+                        button {
+                            class: "secondary fork-btn",
+                            title: "Copy the saved plan into a new plan of your own",
+                            onclick: move |_| on_fork.call(()),
+                            "Fork"
+                        }
+                    }
+                    button {
+                        class: "secondary stateless-link-btn",
+                        title: "Copy a link that carries the plan itself, no saving needed",
+                        onclick: move |_| on_copy_stateless.call(()),
+                        "Copy stateless link"
+                    }
+                }
+                if payload_size.raw > MAX_REQUEST_BYTES {
+                    p { class: "plan-size too-large", role: "status",
+                        "{size_text(payload_size)} \u{2014} over the {format_bytes(MAX_REQUEST_BYTES as u64)} limit, remove some markers before saving."
+                    }
+                } else {
+                    p { class: "plan-size", "{size_text(payload_size)}" }
+                }
+            }
+            if saved_plan.is_some() {
                 button {
-                    onclick: move |_| on_save.call(()),
-                    "Save & Share"
+                    class: "secondary history-toggle",
+                    "aria-expanded": "{show_history}",
+                    onclick: move |_| show_history.toggle(),
+                    if show_history() { "Hide history" } else { "History" }
+                }
+            }
+            if show_history() && saved_plan.is_some() {
+                div { class: "plan-history",
+                    match &*versions.read() {
+                        Some(Some(Ok(list))) if list.is_empty() => rsx! {
+                            p { class: "plan-history-empty", "No earlier versions yet." }
+                        },
+                        Some(Some(Ok(list))) => rsx! {
+                            ul {
+                                for plan in list.iter() {
+                                    li { key: "{plan.version}", class: "plan-history-entry",
+                                        span { "{version_label(plan)}" }
+                                        button {
+                                            class: "secondary",
+                                            disabled: read_only,
+                                            onclick: {
+                                                let version = plan.version;
+                                                move |_| on_restore.call(version)
+                                            },
+                                            "Restore"
+                                        }
+                                        button {
+                                            class: "secondary",
+                                            onclick: {
+                                                let plan = plan.clone();
+                                                move |_| on_compare.call(plan.clone())
+                                            },
+                                            "Compare"
+                                        }
+                                    }
+                                }
+                            }
+                        },
+                        Some(Some(Err(e))) => rsx! {
+                            p { class: "save-error", role: "alert", "Failed to load history: {e}" }
+                        },
+                        _ => rsx! {
+                            p { class: "plan-history-empty", "Loading history..." }
+                        },
+                    }
+                }
+            }
+            div { class: "plan-compare-row",
+                input {
+                    r#type: "text",
+                    "aria-label": "Plan to compare with",
+                    placeholder: "Plan link to compare...",
+                    value: "{compare_link}",
+                    oninput: move |evt: Event<FormData>| compare_link.set(evt.value()),
+                }
+                button {
+                    class: "secondary",
+                    disabled: compare_link.read().trim().is_empty(),
+                    onclick: move |_| on_compare_link.call(compare_link.read().clone()),
+                    "Compare"
+                }
+            }
+            if saves_copy && !view_only {
+                p { class: "plan-copy-note",
+                    "Only the plan's creator can change it. Saving shares your own copy."
+                }
+            }
+            if save_queued {
+                p { class: "plan-queued-note", role: "status",
+                    "Saved on this device. It will be shared once you're back online."
                 }
             }
             if let Some(err) = &*save_error.read() {
@@ -36,33 +234,49 @@ pub fn PlanPanel(
                 }
             }
             if let Some(url) = &*plan_url.read() {
-                div { class: "plan-url",
-                    input {
-                        r#type: "text",
-                        "aria-label": "Plan URL",
-                        readonly: true,
-                        value: "{url}",
-                    }
-                    button {
-                        class: "secondary",
-                        onclick: {
-                            let url = url.clone();
-                            move |_| {
-                                let url = url.clone();
-                                wasm_bindgen_futures::spawn_local(async move {
-                                    if let Some(window) = web_sys::window() {
-                                        let clipboard = window.navigator().clipboard();
-                                        let _ = wasm_bindgen_futures::JsFuture::from(
-                                            clipboard.write_text(&url)
-                                        ).await;
-                                    }
-                                });
-                            }
-                        },
-                        "Copy"
-                    }
-                }
+                CopyLink { label: "Plan URL", url: url.clone() }
+                p { class: "plan-url-label", "Read-only link for gun crews:" }
+                CopyLink { label: "Read-only plan URL", url: api::readonly_plan_url(url) }
+            }
+            if let Some(url) = &*stateless_url.read() {
+                p { class: "plan-url-label", "Stateless link, as the plan was when copied:" }
+                CopyLink { label: "Stateless plan URL", url: url.clone() }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_label() {
+        let plan: PlanData = serde_json::from_str(
+            r#"{"id":"p","name":"Push","mapId":"m","gunPositions":[],"targetPositions":[],"spotterPositions":[],"windDirection":null,"windStrength":0,"version":3,"updatedAt":"2026-01-01T12:30:45+00:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            version_label(&plan),
+            "v3 \u{b7} 2026-01-01 12:30 \u{b7} Push"
+        );
+    }
+
+    #[test]
+    fn test_size_text() {
+        assert_eq!(
+            size_text(PayloadSize {
+                raw: 300,
+                sent: 300
+            }),
+            "Size: 300 B"
+        );
+        assert_eq!(
+            size_text(PayloadSize {
+                raw: 40 * 1024,
+                sent: 6 * 1024,
+            }),
+            "Size: 40.0 KB (6.0 KB compressed)"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use dioxus::prelude::*;
+
+/// Tab bar for the plans open in this session, e.g. one per hex of an op.
+#[component]
+pub fn PlanTabs(
+    /// One label per tab, in order.
+    labels: Vec<String>,
+    active: usize,
+    on_select: EventHandler<usize>,
+    on_add: EventHandler<()>,
+    on_close: EventHandler<usize>,
+    /// Save every open plan at once.
+    on_save_all: EventHandler<()>,
+) -> Element {
+    let closable = labels.len() > 1;
+
+    rsx! {
+        div { class: "plan-tabs",
+            div { class: "plan-tab-list", role: "tablist", "aria-label": "Open plans",
+                for (index, label) in labels.iter().enumerate() {
+                    div {
+                        key: "{index}",
+                        class: if index == active { "plan-tab active" } else { "plan-tab" },
+                        button {
+                            class: "plan-tab-select",
+                            role: "tab",
+                            "aria-selected": "{index == active}",
+                            title: "{label}",
+                            onclick: move |_| on_select.call(index),
+                            "{label}"
+                        }
+                        if closable {
+                            button {
+                                class: "plan-tab-close",
+                                "aria-label": "Close {label}",
+                                onclick: move |_| on_close.call(index),
+                                "\u{2715}"
+                            }
+                        }
+                    }
+                }
+                button {
+                    class: "plan-tab-add",
+                    title: "Open another plan, e.g. for the next hex",
+                    "aria-label": "New plan tab",
+                    onclick: move |_| on_add.call(()),
+                    "+"
+                }
+            }
+            if closable {
+                button {
+                    class: "secondary plan-tabs-save",
+                    onclick: move |_| on_save_all.call(()),
+                    "Save all"
+                }
+            }
+        }
+    }
+}
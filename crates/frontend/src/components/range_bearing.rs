@@ -0,0 +1,226 @@
+use dioxus::prelude::*;
+use foxhole_shared::calc;
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::models::Position;
+
+use crate::components::calculation_display::marker_name;
+use crate::components::map_view::{MarkerKind, MarkerLabels, SelectedMarker};
+use crate::components::placement_action::{PlacementAction, PlacementActionSelect};
+use crate::coords;
+
+/// Markers a position can be measured from, named as in the firing table.
+fn marker_options(
+    guns: &[(f64, f64)],
+    targets: &[(f64, f64)],
+    spotters: &[(f64, f64)],
+    labels: &MarkerLabels,
+) -> Vec<(SelectedMarker, String, (f64, f64))> {
+    [
+        (MarkerKind::Spotter, "Spotter", spotters),
+        (MarkerKind::Gun, "Gun", guns),
+        (MarkerKind::Target, "Target", targets),
+    ]
+    .into_iter()
+    .flat_map(|(kind, base, positions)| {
+        positions.iter().enumerate().map(move |(index, &pos)| {
+            let name = marker_name(labels, kind, index, base, positions.len());
+            (SelectedMarker { kind, index }, name, pos)
+        })
+    })
+    .collect()
+}
+
+fn marker_key(marker: SelectedMarker) -> String {
+    let kind = match marker.kind {
+        MarkerKind::Gun => "gun",
+        MarkerKind::Target => "target",
+        MarkerKind::Spotter => "spotter",
+        MarkerKind::Enemy => "enemy",
+        MarkerKind::Fob => "fob",
+        MarkerKind::Bunker => "bunker",
+        MarkerKind::NoFire => "no-fire",
+    };
+    format!("{kind}-{}", marker.index)
+}
+
+/// The map position (`map` px) `distance` meters from `from` at `azimuth`
+/// degrees, or what's wrong with the inputs.
+fn bearing_position(
+    from: (f64, f64),
+    distance: &str,
+    azimuth: &str,
+    map: MapScale,
+) -> Result<(f64, f64), String> {
+    let distance = distance
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|d| d.is_finite() && *d >= 0.0)
+        .ok_or("Enter a distance in meters")?;
+    let azimuth = azimuth
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|a| a.is_finite())
+        .ok_or("Enter an azimuth in degrees")?;
+    let (x, y) = coords::map_px_to_meters(from.0, from.1, map);
+    let to = calc::offset_by_bearing(Position { x, y }, distance, azimuth.rem_euclid(360.0));
+    let (px, py) = coords::meters_to_map_px(to.x, to.y, map);
+    if !(0.0..=map.width_px).contains(&px) || !(0.0..=map.height_px).contains(&py) {
+        return Err("That position is off the map".to_string());
+    }
+    Ok((px, py))
+}
+
+/// Place or move a marker by typing its range and bearing from another one
+/// ("gun is 125 m at 40° from the spotter").
+#[component]
+pub fn RangeBearingPanel(
+    gun_positions: Vec<(f64, f64)>,
+    target_positions: Vec<(f64, f64)>,
+    spotter_positions: Vec<(f64, f64)>,
+    marker_labels: MarkerLabels,
+    map_scale: MapScale,
+    selected_marker: Option<SelectedMarker>,
+    on_place: EventHandler<(PlacementAction, (f64, f64))>,
+) -> Element {
+    let mut from_key = use_signal(String::new);
+    let mut distance = use_signal(String::new);
+    let mut azimuth = use_signal(String::new);
+    let action = use_signal(|| PlacementAction::Place(MarkerKind::Gun));
+    let mut error = use_signal(|| None::<String>);
+
+    let options = marker_options(
+        &gun_positions,
+        &target_positions,
+        &spotter_positions,
+        &marker_labels,
+    );
+    // Fall back to the first marker when the chosen one is gone
+    let from = options
+        .iter()
+        .find(|(m, _, _)| marker_key(*m) == *from_key.read())
+        .or(options.first())
+        .cloned();
+    let from_selected = from.as_ref().map(|(m, _, _)| marker_key(*m));
+    let preview = from.as_ref().and_then(|(_, _, pos)| {
+        bearing_position(*pos, &distance.read(), &azimuth.read(), map_scale).ok()
+    });
+    let preview_grid = preview.map(|(x, y)| coords::format_px_as_grid(x, y, map_scale));
+    // Moving needs a selection other than the marker measured from
+    let can_move = selected_marker.is_some_and(|s| Some(s) != from.as_ref().map(|(m, _, _)| *m));
+    let current_action = action.read().effective(can_move);
+
+    rsx! {
+        div { class: "panel range-bearing",
+            h3 { "Range & bearing" }
+            if options.is_empty() {
+                p { class: "range-bearing-hint", "Place a spotter or gun to measure from." }
+            } else {
+                select {
+                    "aria-label": "Measure from",
+                    onchange: move |evt: Event<FormData>| from_key.set(evt.value()),
+                    for (m, name, _) in options.iter() {
+                        option {
+                            value: "{marker_key(*m)}",
+                            selected: from_selected.as_deref() == Some(marker_key(*m).as_str()),
+                            "{name}"
+                        }
+                    }
+                }
+                div { class: "range-bearing-inputs",
+                    input {
+                        r#type: "number",
+                        min: "0",
+                        "aria-label": "Distance (m)",
+                        placeholder: "Distance m",
+                        value: "{distance}",
+                        oninput: move |evt: Event<FormData>| distance.set(evt.value()),
+                    }
+                    input {
+                        r#type: "number",
+                        "aria-label": "Azimuth (°)",
+                        placeholder: "Azimuth °",
+                        value: "{azimuth}",
+                        oninput: move |evt: Event<FormData>| azimuth.set(evt.value()),
+                    }
+                }
+                div { class: "range-bearing-actions",
+                    PlacementActionSelect {
+                        action: action,
+                        can_move: can_move,
+                        label: "Range and bearing action",
+                    }
+                    button {
+                        onclick: move |_| {
+                            let Some((_, _, pos)) = from.clone() else { return };
+                            match bearing_position(pos, &distance.read(), &azimuth.read(), map_scale) {
+                                Ok(to) => {
+                                    error.set(None);
+                                    on_place.call((current_action, to));
+                                }
+                                Err(e) => error.set(Some(e)),
+                            }
+                        },
+                        "Place"
+                    }
+                }
+                if let Some(grid) = preview_grid {
+                    p { class: "range-bearing-hint", "\u{2192} {grid}" }
+                }
+            }
+            if let Some(err) = error.read().clone() {
+                p { class: "range-bearing-error", "{err}" }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearing_position() {
+        let map = MapScale::STANDARD;
+        let from = (map.width_px / 2.0, map.height_px / 2.0);
+        let (x, y) = bearing_position(from, "125", " 40 ", map).unwrap();
+        let (fx, fy) = map.px_to_meters(from.0, from.1);
+        let (tx, ty) = map.px_to_meters(x, y);
+        let (a, b) = (Position { x: fx, y: fy }, Position { x: tx, y: ty });
+        assert!((calc::distance(a, b) - 125.0).abs() < 1e-6);
+        assert!((calc::azimuth(a, b) - 40.0).abs() < 1e-6);
+        // Negative azimuths wrap around
+        let west = bearing_position(from, "100", "-90", map).unwrap();
+        assert!(west.0 < from.0 && (west.1 - from.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_bearing_position_errors() {
+        let map = MapScale::STANDARD;
+        let corner = (1.0, 1.0);
+        assert!(bearing_position(corner, "", "40", map)
+            .unwrap_err()
+            .contains("distance"));
+        assert!(bearing_position(corner, "-5", "40", map)
+            .unwrap_err()
+            .contains("distance"));
+        assert!(bearing_position(corner, "100", "north", map)
+            .unwrap_err()
+            .contains("azimuth"));
+        assert!(bearing_position(corner, "100", "0", map)
+            .unwrap_err()
+            .contains("off the map"));
+    }
+
+    #[test]
+    fn test_marker_options_named_and_spotters_first() {
+        let mut labels = MarkerLabels::default();
+        labels.set(MarkerKind::Gun, 1, "Left");
+        let options = marker_options(&[(1.0, 1.0), (2.0, 2.0)], &[], &[(5.0, 5.0)], &labels);
+        let names: Vec<&str> = options.iter().map(|(_, n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["Spotter", "Gun 1", "Left"]);
+        assert_eq!(marker_key(options[2].0), "gun-1");
+        assert_eq!(options[2].2, (2.0, 2.0));
+    }
+}
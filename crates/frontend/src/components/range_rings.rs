@@ -0,0 +1,231 @@
+use dioxus::prelude::*;
+use foxhole_shared::models::{
+    CustomRing, RangeRings, RingAnchor, MAX_CUSTOM_RINGS, MAX_RING_RADIUS,
+};
+use foxhole_shared::overlay::custom_ring_label;
+
+use crate::components::calculation_display::marker_name;
+use crate::components::map_view::{MarkerKind, MarkerLabels};
+
+fn anchor_kind(anchor: RingAnchor) -> (MarkerKind, &'static str) {
+    match anchor {
+        RingAnchor::Gun => (MarkerKind::Gun, "Gun"),
+        RingAnchor::Target => (MarkerKind::Target, "Target"),
+        RingAnchor::Spotter => (MarkerKind::Spotter, "Spotter"),
+    }
+}
+
+fn anchor_key(anchor: RingAnchor, index: usize) -> String {
+    let kind = match anchor {
+        RingAnchor::Gun => "gun",
+        RingAnchor::Target => "target",
+        RingAnchor::Spotter => "spotter",
+    };
+    format!("{kind}-{index}")
+}
+
+/// Markers a ring can be drawn around, spotters first, with their keys and names.
+fn anchor_options(
+    counts: [(RingAnchor, usize); 3],
+    labels: &MarkerLabels,
+) -> Vec<(RingAnchor, usize, String)> {
+    counts
+        .into_iter()
+        .flat_map(|(anchor, count)| {
+            let (kind, base) = anchor_kind(anchor);
+            (0..count).map(move |i| (anchor, i, marker_name(labels, kind, i, base, count)))
+        })
+        .collect()
+}
+
+/// The radius typed in, or what's wrong with it.
+fn parse_radius(radius: &str) -> Result<f64, String> {
+    radius
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|r| r.is_finite() && *r > 0.0 && *r <= MAX_RING_RADIUS)
+        .ok_or_else(|| format!("Enter a radius above 0 and up to {MAX_RING_RADIUS:.0} m"))
+}
+
+/// Per-gun min/max ring toggles and rings drawn around any marker ("250 m
+/// intel range around the spotter").
+#[component]
+pub fn RangeRingsPanel(
+    gun_count: usize,
+    target_count: usize,
+    spotter_count: usize,
+    marker_labels: MarkerLabels,
+    range_rings: Signal<RangeRings>,
+    on_before_change: EventHandler<()>,
+) -> Element {
+    let mut anchor = use_signal(String::new);
+    let mut radius = use_signal(String::new);
+    let mut label = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+
+    let options = anchor_options(
+        [
+            (RingAnchor::Spotter, spotter_count),
+            (RingAnchor::Gun, gun_count),
+            (RingAnchor::Target, target_count),
+        ],
+        &marker_labels,
+    );
+    // Fall back to the first marker when the chosen one is gone
+    let chosen = options
+        .iter()
+        .find(|(a, i, _)| anchor_key(*a, *i) == *anchor.read())
+        .or(options.first())
+        .map(|(a, i, _)| (*a, *i));
+    let rings = range_rings.read().clone();
+    let full = rings.custom.len() >= MAX_CUSTOM_RINGS;
+
+    rsx! {
+        div { class: "panel range-rings",
+            h3 { "Range rings" }
+            if options.is_empty() {
+                p { class: "range-rings-hint", "Place a gun, target or spotter to draw rings around it." }
+            } else {
+                for gi in 0..gun_count {
+                    {
+                        let name = marker_name(&marker_labels, MarkerKind::Gun, gi, "Gun", gun_count);
+                        rsx! {
+                            label { class: "checkbox-row",
+                                input {
+                                    r#type: "checkbox",
+                                    checked: rings.shown(gi),
+                                    onchange: move |evt: Event<FormData>| {
+                                        on_before_change.call(());
+                                        range_rings.write().set_shown(gi, evt.checked());
+                                    },
+                                }
+                                "Min/max rings for {name}"
+                            }
+                        }
+                    }
+                }
+                if !rings.custom.is_empty() {
+                    ul { class: "custom-rings",
+                        for (ri, ring) in rings.custom.iter().enumerate() {
+                            {
+                                let (kind, base) = anchor_kind(ring.anchor);
+                                let count = match ring.anchor {
+                                    RingAnchor::Gun => gun_count,
+                                    RingAnchor::Target => target_count,
+                                    RingAnchor::Spotter => spotter_count,
+                                };
+                                let around = marker_name(&marker_labels, kind, ring.index, base, count);
+                                let text = custom_ring_label(ring);
+                                rsx! {
+                                    li {
+                                        span { class: "custom-ring-name", "{text} around {around}" }
+                                        button {
+                                            class: "remove-marker-btn",
+                                            "aria-label": "Remove ring {text} around {around}",
+                                            onclick: move |_| {
+                                                on_before_change.call(());
+                                                range_rings.write().custom.remove(ri);
+                                            },
+                                            "\u{00d7}"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                div { class: "range-rings-add",
+                    select {
+                        class: "inline-weapon-select",
+                        "aria-label": "Ring around",
+                        onchange: move |evt: Event<FormData>| anchor.set(evt.value()),
+                        for (a, i, name) in options.iter() {
+                            option {
+                                value: "{anchor_key(*a, *i)}",
+                                selected: chosen == Some((*a, *i)),
+                                "{name}"
+                            }
+                        }
+                    }
+                    input {
+                        r#type: "number",
+                        min: "1",
+                        max: "{MAX_RING_RADIUS}",
+                        "aria-label": "Ring radius (m)",
+                        placeholder: "Radius m",
+                        value: "{radius}",
+                        oninput: move |evt: Event<FormData>| radius.set(evt.value()),
+                    }
+                    input {
+                        r#type: "text",
+                        "aria-label": "Ring label",
+                        placeholder: "Label (optional)",
+                        value: "{label}",
+                        oninput: move |evt: Event<FormData>| label.set(evt.value()),
+                    }
+                    button {
+                        disabled: full,
+                        onclick: move |_| {
+                            let Some((ring_anchor, index)) = chosen else { return };
+                            let parsed = parse_radius(&radius.read());
+                            match parsed {
+                                Ok(r) => {
+                                    on_before_change.call(());
+                                    range_rings.write().custom.push(CustomRing {
+                                        anchor: ring_anchor,
+                                        index,
+                                        radius: r,
+                                        label: label.read().trim().to_string(),
+                                    });
+                                    error.set(None);
+                                    radius.set(String::new());
+                                    label.set(String::new());
+                                }
+                                Err(e) => error.set(Some(e)),
+                            }
+                        },
+                        "Add ring"
+                    }
+                }
+                if full {
+                    p { class: "range-rings-hint", "A plan keeps at most {MAX_CUSTOM_RINGS} rings." }
+                }
+            }
+            if let Some(err) = error.read().clone() {
+                p { class: "range-rings-error", "{err}" }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_radius() {
+        assert_eq!(parse_radius(" 250 "), Ok(250.0));
+        assert_eq!(parse_radius("1000"), Ok(MAX_RING_RADIUS));
+        for bad in ["", "0", "-5", "1001", "far", "NaN"] {
+            assert!(parse_radius(bad).unwrap_err().contains("radius"), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_anchor_options_named_and_spotters_first() {
+        let mut labels = MarkerLabels::default();
+        labels.set(MarkerKind::Gun, 1, "Left");
+        let options = anchor_options(
+            [
+                (RingAnchor::Spotter, 1),
+                (RingAnchor::Gun, 2),
+                (RingAnchor::Target, 0),
+            ],
+            &labels,
+        );
+        let names: Vec<&str> = options.iter().map(|(_, _, n)| n.as_str()).collect();
+        assert_eq!(names, vec!["Spotter", "Gun 1", "Left"]);
+        assert_eq!(anchor_key(options[2].0, options[2].1), "gun-1");
+    }
+}
@@ -0,0 +1,145 @@
+use dioxus::prelude::*;
+use foxhole_shared::models::WeaponProfile;
+
+use crate::api;
+use crate::components::map_view::Faction;
+use crate::settings::{save_settings, AngleUnit, Settings, ROUNDING_STEPS};
+
+/// Change the settings and save them.
+fn apply(mut settings: Signal<Settings>, change: impl FnOnce(&mut Settings)) {
+    change(&mut settings.write());
+    save_settings(&settings.read());
+}
+
+/// Slide-out drawer with the planner settings; each change is saved at once.
+#[component]
+pub fn SettingsDrawer(show: Signal<bool>, settings: Signal<Settings>) -> Element {
+    // Live is always there; other profiles only on servers that have them
+    let profiles = use_resource(api::fetch_weapon_profiles);
+    if !*show.read() {
+        return rsx! {};
+    }
+    let current = *settings.read();
+    let offered: Vec<WeaponProfile> = match &*profiles.read() {
+        Some(Ok(list)) => list.iter().map(|p| p.profile).collect(),
+        _ => vec![WeaponProfile::Live],
+    };
+    // A profile the server dropped stays listed so it can be switched away from
+    let show_profiles = offered.len() > 1 || current.weapon_profile != WeaponProfile::Live;
+
+    rsx! {
+        div {
+            class: "settings-backdrop",
+            onclick: move |_| show.set(false),
+        }
+        div { class: "settings-drawer", role: "dialog", "aria-label": "Settings",
+            div { class: "settings-header",
+                h2 { "Settings" }
+                button {
+                    class: "remove-marker-btn",
+                    "aria-label": "Close settings",
+                    onclick: move |_| show.set(false),
+                    "\u{2715}"
+                }
+            }
+            div { class: "settings-row",
+                span { "Azimuth in" }
+                select {
+                    class: "inline-weapon-select",
+                    "aria-label": "Azimuth unit",
+                    onchange: move |evt: Event<FormData>| {
+                        let unit = if evt.value() == "mils" { AngleUnit::Mils } else { AngleUnit::Degrees };
+                        apply(settings, |s| s.angle_unit = unit);
+                    },
+                    option { value: "degrees", selected: current.angle_unit == AngleUnit::Degrees, "Degrees" }
+                    option { value: "mils", selected: current.angle_unit == AngleUnit::Mils, "Mils (6400)" }
+                }
+            }
+            div { class: "settings-row",
+                span { "Round distances to" }
+                select {
+                    class: "inline-weapon-select",
+                    "aria-label": "Distance rounding",
+                    onchange: move |evt: Event<FormData>| {
+                        if let Ok(step) = evt.value().parse::<u32>() {
+                            apply(settings, |s| s.rounding_step = step);
+                        }
+                    },
+                    for step in ROUNDING_STEPS {
+                        option { value: "{step}", selected: current.rounding_step == step, "{step} m" }
+                    }
+                }
+            }
+            div { class: "settings-row",
+                span { "Open as" }
+                select {
+                    class: "inline-weapon-select",
+                    "aria-label": "Default faction",
+                    onchange: move |evt: Event<FormData>| {
+                        let faction = match evt.value().as_str() {
+                            "warden" => Some(Faction::Warden),
+                            "colonial" => Some(Faction::Colonial),
+                            _ => None,
+                        };
+                        apply(settings, |s| s.default_faction = faction);
+                    },
+                    option { value: "last", selected: current.default_faction.is_none(), "Last side used" }
+                    option { value: "warden", selected: current.default_faction == Some(Faction::Warden), "Warden" }
+                    option { value: "colonial", selected: current.default_faction == Some(Faction::Colonial), "Colonial" }
+                }
+            }
+            if show_profiles {
+                div { class: "settings-row",
+                    span { "Weapon figures" }
+                    select {
+                        class: "inline-weapon-select",
+                        "aria-label": "Weapon figures",
+                        onchange: move |evt: Event<FormData>| {
+                            let profile = if evt.value() == "devbranch" {
+                                WeaponProfile::Devbranch
+                            } else {
+                                WeaponProfile::Live
+                            };
+                            apply(settings, |s| s.weapon_profile = profile);
+                        },
+                        option { value: "live", selected: current.weapon_profile == WeaponProfile::Live, "Live" }
+                        option { value: "devbranch", selected: current.weapon_profile == WeaponProfile::Devbranch, "Devbranch (upcoming)" }
+                    }
+                }
+            }
+            label { class: "checkbox-row",
+                input {
+                    r#type: "checkbox",
+                    checked: current.auto_cycle,
+                    onchange: move |evt: Event<FormData>| {
+                        let enabled = evt.checked();
+                        apply(settings, |s| s.auto_cycle = enabled);
+                    },
+                }
+                "Switch between gun and target after placing"
+            }
+            label { class: "checkbox-row",
+                input {
+                    r#type: "checkbox",
+                    checked: current.always_keypad,
+                    onchange: move |evt: Event<FormData>| {
+                        let enabled = evt.checked();
+                        apply(settings, |s| s.always_keypad = enabled);
+                    },
+                }
+                "Always show the keypad grid"
+            }
+            label { class: "checkbox-row",
+                input {
+                    r#type: "checkbox",
+                    checked: current.research_mode,
+                    onchange: move |evt: Event<FormData>| {
+                        let enabled = evt.checked();
+                        apply(settings, |s| s.research_mode = enabled);
+                    },
+                }
+                "Accuracy research mode (experimental)"
+            }
+        }
+    }
+}
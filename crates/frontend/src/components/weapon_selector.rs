@@ -1,9 +1,14 @@
 use dioxus::prelude::*;
+use foxhole_shared::models::WeaponProfile;
 
 use crate::api::WeaponData;
 
 #[component]
-pub fn WeaponSelector(weapons: Vec<WeaponData>, selected_weapon: Signal<String>) -> Element {
+pub fn WeaponSelector(
+    weapons: Vec<WeaponData>,
+    selected_weapon: Signal<String>,
+    profile: WeaponProfile,
+) -> Element {
     let colonial: Vec<&WeaponData> = weapons
         .iter()
         .filter(|w| w.faction == "COLONIAL" || w.faction == "BOTH")
@@ -16,6 +21,11 @@ pub fn WeaponSelector(weapons: Vec<WeaponData>, selected_weapon: Signal<String>)
     rsx! {
         div { class: "panel",
             h3 { "Active Weapon" }
+            if profile == WeaponProfile::Devbranch {
+                p { class: "weapon-profile-note",
+                    "Devbranch figures: upcoming balance numbers, not the live game's."
+                }
+            }
             select {
                 "aria-label": "Select weapon",
                 value: "{selected_weapon}",
@@ -27,6 +37,7 @@ pub fn WeaponSelector(weapons: Vec<WeaponData>, selected_weapon: Signal<String>)
                     for w in colonial {
                         option {
                             value: "{w.slug}",
+                            title: w.deployment().summary(),
                             selected: *selected_weapon.read() == w.slug,
                             "{w.display_name} ({w.min_range}-{w.max_range}m)"
                         }
@@ -36,6 +47,7 @@ pub fn WeaponSelector(weapons: Vec<WeaponData>, selected_weapon: Signal<String>)
                     for w in warden {
                         option {
                             value: "{w.slug}",
+                            title: w.deployment().summary(),
                             selected: *selected_weapon.read() == w.slug,
                             "{w.display_name} ({w.min_range}-{w.max_range}m)"
                         }
@@ -1,10 +1,26 @@
 use dioxus::prelude::*;
 
+use crate::api::{self, WindReportData};
+
+/// Compass point for a bearing, e.g. `"NE"`; off-grid bearings show in degrees.
+fn direction_label(deg: f64) -> String {
+    const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+    if deg.rem_euclid(45.0) == 0.0 {
+        POINTS[(deg.rem_euclid(360.0) / 45.0) as usize].to_string()
+    } else {
+        format!("{:.0}\u{00b0}", deg)
+    }
+}
+
 #[component]
 pub fn WindInput(
     wind_direction: Signal<Option<f64>>,
     wind_strength: Signal<u32>,
     on_before_change: EventHandler<()>,
+    /// Map the wind is shared for.
+    map_id: String,
+    /// Freshest wind someone reported on this map, if recent.
+    wind_report: Signal<Option<WindReportData>>,
 ) -> Element {
     // Grid layout: NW N NE / W . E / SW S SE
     let grid_order: [(f64, &str); 8] = [
@@ -21,6 +37,9 @@ pub fn WindInput(
 
     let current_dir = *wind_direction.read();
     let current_str = *wind_strength.read();
+    let mut sharing = use_signal(|| false);
+    let mut share_error = use_signal(|| None::<String>);
+    let report = wind_report.read().clone();
 
     rsx! {
         div { class: "panel",
@@ -106,6 +125,49 @@ pub fn WindInput(
                 }
                 span { class: "value", "{current_str}" }
             }
+            div { class: "wind-report-row",
+                button {
+                    class: "secondary",
+                    title: "Share the wind you see in-game with everyone planning on this map",
+                    disabled: current_dir.is_none() || *sharing.read(),
+                    onclick: move |_| {
+                        let Some(direction) = current_dir else {
+                            return;
+                        };
+                        let map = map_id.clone();
+                        sharing.set(true);
+                        share_error.set(None);
+                        spawn(async move {
+                            match api::report_wind(&map, direction, current_str).await {
+                                Ok(report) => wind_report.set(Some(report)),
+                                Err(e) => share_error.set(Some(format!("Failed to share wind: {e}"))),
+                            }
+                            sharing.set(false);
+                        });
+                    },
+                    "Share wind"
+                }
+                if let Some(err) = share_error.read().clone() {
+                    span { class: "wind-report error", "{err}" }
+                } else if let Some(r) = report {
+                    span { class: "wind-report",
+                        "Latest report: {direction_label(r.direction)}, strength {r.strength}"
+                    }
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction_label() {
+        assert_eq!(direction_label(0.0), "N");
+        assert_eq!(direction_label(135.0), "SE");
+        assert_eq!(direction_label(315.0), "NW");
+        assert_eq!(direction_label(100.0), "100\u{00b0}");
+    }
+}
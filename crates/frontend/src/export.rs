@@ -0,0 +1,700 @@
+//! Fire mission (barrage sheet) export: CSV, a monospace text block for Discord
+//! and plain lines to paste into squad chat.
+
+use foxhole_shared::calc;
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::models::{self, Deployment, Position, WindInput};
+use foxhole_shared::overlay::{MarkerKind, MarkerLabels};
+
+use crate::api::{FiringSolutionData, WeaponData};
+use crate::coords;
+
+/// One line of the barrage sheet — a gun paired with a target and its solution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FireMission {
+    /// 1-based gun number as shown in the UI.
+    pub gun: usize,
+    /// Weapon name, with the ammo after it unless it's the standard shell.
+    pub weapon: Option<String>,
+    pub gun_grid: String,
+    /// 1-based target number as shown in the UI.
+    pub target: usize,
+    /// The target's name, e.g. the structure it was snapped onto.
+    pub target_label: Option<String>,
+    pub target_grid: String,
+    pub azimuth: f64,
+    pub distance: f64,
+    pub wind_azimuth: Option<f64>,
+    pub wind_distance: Option<f64>,
+    pub in_range: bool,
+    /// The weapon's crew and setup, for planning manpower.
+    pub deployment: Deployment,
+}
+
+/// Build barrage sheet rows from the planner state (positions in map-image px).
+///
+/// Guns without a target or without a solution are left out.
+#[allow(clippy::too_many_arguments)]
+pub fn fire_missions(
+    solutions: &[Option<FiringSolutionData>],
+    gun_positions: &[(f64, f64)],
+    target_positions: &[(f64, f64)],
+    pairings: &[Option<usize>],
+    weapon_ids: &[String],
+    gun_ammo: &[String],
+    weapons: &[WeaponData],
+    labels: &MarkerLabels,
+    map: MapScale,
+) -> Vec<FireMission> {
+    gun_positions
+        .iter()
+        .enumerate()
+        .filter_map(|(gi, g)| {
+            let sol = solutions.get(gi)?.as_ref()?;
+            let ti = pairings.get(gi).copied().flatten()?;
+            let t = target_positions.get(ti)?;
+            let data = weapon_ids
+                .get(gi)
+                .and_then(|slug| weapons.iter().find(|w| w.slug == *slug));
+            let weapon = data.map(|w| match models::gun_ammo(gun_ammo, gi) {
+                "" => w.display_name.clone(),
+                ammo => format!("{} ({})", w.display_name, ammo),
+            });
+            Some(FireMission {
+                gun: gi + 1,
+                weapon,
+                gun_grid: coords::format_px_as_grid(g.0, g.1, map),
+                target: ti + 1,
+                target_label: labels.get(MarkerKind::Target, ti).map(str::to_string),
+                target_grid: coords::format_px_as_grid(t.0, t.1, map),
+                azimuth: sol.azimuth,
+                distance: sol.distance,
+                wind_azimuth: sol.wind_adjusted_azimuth,
+                wind_distance: sol.wind_adjusted_distance,
+                in_range: sol.in_range,
+                deployment: data.map(WeaponData::deployment).unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// One move in a gun's displacement route, with its solution from the new spot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplacementStep {
+    /// 1-based gun number as shown in the UI.
+    pub gun: usize,
+    /// Firing position number; the gun's own spot is 1, so alternates start at 2.
+    pub position: usize,
+    pub grid: String,
+    /// 1-based target number, when the gun is paired.
+    pub target: Option<usize>,
+    pub azimuth: Option<f64>,
+    pub distance: Option<f64>,
+    pub wind_azimuth: Option<f64>,
+    pub wind_distance: Option<f64>,
+    pub in_range: Option<bool>,
+}
+
+/// The displacement order: each gun's alternate positions in the order it moves
+/// through them, solved against its paired target (positions in map-image px).
+#[allow(clippy::too_many_arguments)]
+pub fn displacement_order(
+    gun_positions: &[(f64, f64)],
+    alternates: &[Vec<(f64, f64)>],
+    target_positions: &[(f64, f64)],
+    pairings: &[Option<usize>],
+    weapon_ids: &[String],
+    gun_ammo: &[String],
+    weapons: &[WeaponData],
+    wind_direction: Option<f64>,
+    wind_strength: u32,
+    map: MapScale,
+) -> Vec<DisplacementStep> {
+    let to_pos = |(x, y): (f64, f64)| {
+        let (x, y) = coords::map_px_to_meters(x, y, map);
+        Position { x, y }
+    };
+    let wind = wind_direction.map(|direction| WindInput {
+        direction,
+        strength: wind_strength.min(u8::MAX as u32) as u8,
+    });
+    let mut steps = Vec::new();
+    for (gi, stops) in alternates.iter().enumerate().take(gun_positions.len()) {
+        let ti = pairings.get(gi).copied().flatten();
+        let target = ti.and_then(|ti| target_positions.get(ti)).copied();
+        let weapon = weapon_ids
+            .get(gi)
+            .and_then(|slug| weapons.iter().find(|w| w.slug == *slug))
+            .map(WeaponData::to_weapon)
+            .map(|w| w.with_ammo(models::gun_ammo(gun_ammo, gi)).unwrap_or(w));
+        for (si, &stop) in stops.iter().enumerate() {
+            let sol = target
+                .zip(weapon.as_ref())
+                .map(|(t, w)| calc::firing_solution(to_pos(stop), to_pos(t), w, wind.as_ref()));
+            steps.push(DisplacementStep {
+                gun: gi + 1,
+                position: si + 2,
+                grid: coords::format_px_as_grid(stop.0, stop.1, map),
+                target: ti.filter(|_| target.is_some()).map(|ti| ti + 1),
+                azimuth: sol.as_ref().map(|s| s.azimuth),
+                distance: sol.as_ref().map(|s| s.distance),
+                wind_azimuth: sol.as_ref().and_then(|s| s.wind_adjusted_azimuth),
+                wind_distance: sol.as_ref().and_then(|s| s.wind_adjusted_distance),
+                in_range: sol.as_ref().map(|s| s.in_range),
+            });
+        }
+    }
+    steps
+}
+
+/// Distances are read off in 5m steps in-game, same rounding as the solution panel.
+fn round_distance(d: f64) -> f64 {
+    (d / 5.0).round() * 5.0
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or newline.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub fn to_csv(missions: &[FireMission]) -> String {
+    let mut out = String::from(
+        "gun,weapon,gun_grid,target,target_grid,azimuth,distance,wind_azimuth,wind_distance,in_range,target_label,mount,crew,deploy_time\n",
+    );
+    for m in missions {
+        let wind_az = m
+            .wind_azimuth
+            .map(|a| format!("{:.1}", a))
+            .unwrap_or_default();
+        let wind_dist = m
+            .wind_distance
+            .map(|d| format!("{:.0}", round_distance(d)))
+            .unwrap_or_default();
+        let deployment = &m.deployment;
+        out.push_str(&format!(
+            "{},{},{},{},{},{:.1},{:.0},{},{},{},{},{},{},{}\n",
+            m.gun,
+            csv_field(m.weapon.as_deref().unwrap_or("")),
+            m.gun_grid,
+            m.target,
+            m.target_grid,
+            m.azimuth,
+            round_distance(m.distance),
+            wind_az,
+            wind_dist,
+            m.in_range,
+            csv_field(m.target_label.as_deref().unwrap_or("")),
+            deployment.mount.map(|v| v.to_string()).unwrap_or_default(),
+            deployment.crew.map(|v| v.to_string()).unwrap_or_default(),
+            deployment
+                .deploy_time
+                .map(|t| format!("{:.0}", t))
+                .unwrap_or_default(),
+        ));
+    }
+    out
+}
+
+/// Left-aligned columns two spaces apart, one row per line.
+fn push_table(out: &mut String, rows: &[Vec<String>]) {
+    let cols = rows[0].len();
+    let widths: Vec<usize> = (0..cols)
+        .map(|c| rows.iter().map(|r| r[c].chars().count()).max().unwrap_or(0))
+        .collect();
+    for r in rows {
+        let line: Vec<String> = r
+            .iter()
+            .zip(&widths)
+            .map(|(cell, w)| format!("{:<width$}", cell, width = w))
+            .collect();
+        out.push_str(line.join("  ").trim_end());
+        out.push('\n');
+    }
+}
+
+/// "Minimum crew: 5", the soldiers needed to man every gun on the sheet, with
+/// the guns whose crew isn't known listed after. `None` when no crew is known.
+fn crew_line(missions: &[FireMission]) -> Option<String> {
+    let crew: u32 = missions.iter().filter_map(|m| m.deployment.crew).sum();
+    if crew == 0 {
+        return None;
+    }
+    let unknown: Vec<String> = missions
+        .iter()
+        .filter(|m| m.deployment.crew.is_none())
+        .map(|m| format!("G{}", m.gun))
+        .collect();
+    Some(if unknown.is_empty() {
+        format!("Minimum crew: {crew}")
+    } else {
+        format!("Minimum crew: {crew} + {} (unknown)", unknown.join(", "))
+    })
+}
+
+/// Render the sheet as a fenced code block so columns stay aligned in Discord,
+/// followed by the displacement order when any gun has alternate positions.
+pub fn to_discord_text(
+    plan_name: &str,
+    missions: &[FireMission],
+    displacements: &[DisplacementStep],
+) -> String {
+    let title = if plan_name.trim().is_empty() {
+        "Fire missions".to_string()
+    } else {
+        format!("Fire missions: {}", plan_name.trim())
+    };
+    let has_wind = missions.iter().any(|m| m.wind_azimuth.is_some());
+    let has_names = missions.iter().any(|m| m.target_label.is_some());
+
+    let mut rows: Vec<Vec<String>> = vec![{
+        let mut h: Vec<String> = ["GUN", "WEAPON", "FROM", "TGT", "AT", "AZ", "DIST"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        if has_names {
+            h.insert(4, "NAME".to_string());
+        }
+        if has_wind {
+            h.push("WIND AZ".to_string());
+            h.push("WIND DIST".to_string());
+        }
+        h
+    }];
+    for m in missions {
+        let mut r = vec![
+            format!("G{}", m.gun),
+            m.weapon.clone().unwrap_or_else(|| "-".to_string()),
+            m.gun_grid.clone(),
+            format!("T{}", m.target),
+            m.target_grid.clone(),
+            format!("{:.1}\u{00b0}", m.azimuth),
+            if m.in_range {
+                format!("{:.0}m", round_distance(m.distance))
+            } else {
+                format!("{:.0}m!", round_distance(m.distance))
+            },
+        ];
+        if has_names {
+            r.insert(4, m.target_label.clone().unwrap_or_else(|| "-".to_string()));
+        }
+        if has_wind {
+            r.push(
+                m.wind_azimuth
+                    .map(|a| format!("{:.1}\u{00b0}", a))
+                    .unwrap_or_else(|| "-".to_string()),
+            );
+            r.push(
+                m.wind_distance
+                    .map(|d| format!("{:.0}m", round_distance(d)))
+                    .unwrap_or_else(|| "-".to_string()),
+            );
+        }
+        rows.push(r);
+    }
+
+    let mut out = format!("**{}**\n```\n", title);
+    push_table(&mut out, &rows);
+    if missions.iter().any(|m| !m.in_range) {
+        out.push_str("! = out of range\n");
+    }
+    if let Some(line) = crew_line(missions) {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out.push_str("```");
+    if !displacements.is_empty() {
+        out.push_str("\n**Displacement order**\n```\n");
+        push_table(&mut out, &displacement_rows(displacements, has_wind));
+        if displacements.iter().any(|d| d.in_range == Some(false)) {
+            out.push_str("! = out of range\n");
+        }
+        out.push_str("```");
+    }
+    out
+}
+
+/// "AZ 247.3° / 185 m (wind adj: 245.1° / 190 m)", flagged when out of range.
+fn solution_line(azimuth: f64, distance: f64, wind: Option<(f64, f64)>, in_range: bool) -> String {
+    let mut line = format!(
+        "AZ {:.1}\u{00b0} / {:.0} m",
+        azimuth,
+        round_distance(distance)
+    );
+    if let Some((az, dist)) = wind {
+        line.push_str(&format!(
+            " (wind adj: {:.1}\u{00b0} / {:.0} m)",
+            az,
+            round_distance(dist)
+        ));
+    }
+    if !in_range {
+        line.push_str(" OUT OF RANGE");
+    }
+    line
+}
+
+/// One gun's solution as a line for squad chat.
+pub fn solution_text(sol: &FiringSolutionData) -> String {
+    let wind = sol.wind_adjusted_azimuth.zip(sol.wind_adjusted_distance);
+    solution_line(sol.azimuth, sol.distance, wind, sol.in_range)
+}
+
+/// Every gun's solution for squad chat, one line per gun.
+pub fn to_chat_text(missions: &[FireMission]) -> String {
+    missions
+        .iter()
+        .map(|m| {
+            let target = m
+                .target_label
+                .clone()
+                .unwrap_or_else(|| format!("T{}", m.target));
+            let wind = m.wind_azimuth.zip(m.wind_distance);
+            format!(
+                "G{} \u{2192} {}: {}",
+                m.gun,
+                target,
+                solution_line(m.azimuth, m.distance, wind, m.in_range)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Table rows for the displacement order, header first.
+fn displacement_rows(steps: &[DisplacementStep], has_wind: bool) -> Vec<Vec<String>> {
+    let dash = || "-".to_string();
+    let mut header: Vec<String> = ["GUN", "POS", "AT", "TGT", "AZ", "DIST"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    if has_wind {
+        header.push("WIND AZ".to_string());
+        header.push("WIND DIST".to_string());
+    }
+    let mut rows = vec![header];
+    for d in steps {
+        let mut r = vec![
+            format!("G{}", d.gun),
+            d.position.to_string(),
+            d.grid.clone(),
+            d.target.map(|t| format!("T{}", t)).unwrap_or_else(dash),
+            d.azimuth
+                .map(|a| format!("{:.1}\u{00b0}", a))
+                .unwrap_or_else(dash),
+            d.distance
+                .map(|dist| {
+                    let mark = if d.in_range == Some(false) { "!" } else { "" };
+                    format!("{:.0}m{}", round_distance(dist), mark)
+                })
+                .unwrap_or_else(dash),
+        ];
+        if has_wind {
+            r.push(
+                d.wind_azimuth
+                    .map(|a| format!("{:.1}\u{00b0}", a))
+                    .unwrap_or_else(dash),
+            );
+            r.push(
+                d.wind_distance
+                    .map(|dist| format!("{:.0}m", round_distance(dist)))
+                    .unwrap_or_else(dash),
+            );
+        }
+        rows.push(r);
+    }
+    rows
+}
+
+/// Copy text to the system clipboard (fire-and-forget).
+pub fn copy_to_clipboard(text: String) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Some(window) = web_sys::window() {
+            let clipboard = window.navigator().clipboard();
+            let _ = wasm_bindgen_futures::JsFuture::from(clipboard.write_text(&text)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::models::WeaponMount;
+
+    fn solution(az: f64, dist: f64, in_range: bool) -> FiringSolutionData {
+        FiringSolutionData {
+            azimuth: az,
+            distance: dist,
+            in_range,
+            accuracy_radius: 25.0,
+            wind_adjusted_azimuth: None,
+            wind_adjusted_distance: None,
+            wind_offset_meters: None,
+            time_of_flight: None,
+            impact_zone: None,
+        }
+    }
+
+    fn mission(gun: usize, weapon: Option<&str>) -> FireMission {
+        FireMission {
+            gun,
+            weapon: weapon.map(|w| w.to_string()),
+            gun_grid: "G9k3".to_string(),
+            target: 1,
+            target_label: None,
+            target_grid: "H9k7".to_string(),
+            azimuth: 92.34,
+            distance: 183.0,
+            wind_azimuth: None,
+            wind_distance: None,
+            in_range: true,
+            deployment: Deployment::default(),
+        }
+    }
+
+    #[test]
+    fn test_fire_missions_skips_unpaired_guns() {
+        let solutions = vec![Some(solution(90.0, 200.0, true)), None];
+        let guns = vec![(100.0, 100.0), (200.0, 200.0)];
+        let targets = vec![(300.0, 100.0)];
+        let pairings = vec![Some(0), None];
+        let weapons = vec![WeaponData {
+            slug: "mortar".to_string(),
+            faction: "BOTH".to_string(),
+            display_name: "Mortar".to_string(),
+            min_range: 45.0,
+            max_range: 80.0,
+            acc_radius_min: 2.5,
+            acc_radius_max: 9.45,
+            wind_drift_min: 0.0,
+            wind_drift_max: 0.0,
+            flight_time_min: 0.0,
+            flight_time_max: 0.0,
+            ammo: vec![],
+            crew: Some(1),
+            deploy_time: None,
+            mount: Some(WeaponMount::Portable),
+            area_fire: false,
+        }];
+        let ids = vec!["mortar".to_string(), String::new()];
+        let mut labels = MarkerLabels::default();
+        labels.set(MarkerKind::Target, 0, "Town hall, Abandoned Ward");
+
+        let rows = fire_missions(
+            &solutions,
+            &guns,
+            &targets,
+            &pairings,
+            &ids,
+            &["Shrapnel".to_string()],
+            &weapons,
+            &labels,
+            MapScale::STANDARD,
+        );
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].gun, 1);
+        assert_eq!(rows[0].target, 1);
+        assert_eq!(
+            rows[0].target_label.as_deref(),
+            Some("Town hall, Abandoned Ward")
+        );
+        assert_eq!(rows[0].weapon.as_deref(), Some("Mortar (Shrapnel)"));
+        assert_eq!(rows[0].deployment.crew, Some(1));
+        assert_eq!(
+            rows[0].gun_grid,
+            coords::format_px_as_grid(100.0, 100.0, MapScale::STANDARD)
+        );
+    }
+
+    #[test]
+    fn test_displacement_order_solves_each_stop() {
+        let weapons = vec![WeaponData {
+            slug: "mortar".to_string(),
+            faction: "BOTH".to_string(),
+            display_name: "Mortar".to_string(),
+            min_range: 45.0,
+            max_range: 80.0,
+            acc_radius_min: 2.5,
+            acc_radius_max: 9.45,
+            wind_drift_min: 0.0,
+            wind_drift_max: 0.0,
+            flight_time_min: 0.0,
+            flight_time_max: 0.0,
+            ammo: vec![],
+            crew: Some(1),
+            deploy_time: None,
+            mount: Some(WeaponMount::Portable),
+            area_fire: false,
+        }];
+        let map = MapScale::STANDARD;
+        let target = coords::meters_to_map_px(1000.0, 1000.0, map);
+        let guns = vec![coords::meters_to_map_px(1000.0, 1060.0, map), (10.0, 10.0)];
+        let alternates = vec![
+            vec![
+                coords::meters_to_map_px(1050.0, 1000.0, map),
+                coords::meters_to_map_px(1000.0, 1200.0, map),
+            ],
+            vec![(20.0, 20.0)],
+        ];
+        let pairings = vec![Some(0), None];
+        let ids = vec!["mortar".to_string(), "mortar".to_string()];
+
+        let steps = displacement_order(
+            &guns,
+            &alternates,
+            &[target],
+            &pairings,
+            &ids,
+            &[],
+            &weapons,
+            None,
+            0,
+            map,
+        );
+        assert_eq!(steps.len(), 3);
+        assert_eq!((steps[0].gun, steps[0].position), (1, 2));
+        assert_eq!((steps[1].gun, steps[1].position), (1, 3));
+        assert_eq!(steps[0].target, Some(1));
+        // 50m east of the target: fire due west, in range
+        assert!((steps[0].azimuth.unwrap() - 270.0).abs() < 0.01);
+        assert!((steps[0].distance.unwrap() - 50.0).abs() < 0.01);
+        assert_eq!(steps[0].in_range, Some(true));
+        assert_eq!(steps[1].in_range, Some(false));
+        // Unpaired gun: the move is listed without a solution
+        assert_eq!((steps[2].gun, steps[2].target), (2, None));
+        assert_eq!(steps[2].azimuth, None);
+    }
+
+    #[test]
+    fn test_to_discord_text_appends_displacement_order() {
+        let step = DisplacementStep {
+            gun: 1,
+            position: 2,
+            grid: "G9k1".to_string(),
+            target: Some(1),
+            azimuth: Some(270.0),
+            distance: Some(52.0),
+            wind_azimuth: None,
+            wind_distance: None,
+            in_range: Some(true),
+        };
+        let text = to_discord_text("", &[mission(1, None)], &[step]);
+        let order = text.split("**Displacement order**").nth(1).unwrap();
+        assert!(order.contains("G1   2    G9k1  T1   270.0\u{00b0}  50m"));
+        assert!(order.ends_with("```"));
+        assert!(!to_discord_text("", &[mission(1, None)], &[]).contains("Displacement"));
+    }
+
+    #[test]
+    fn test_to_csv_rounds_and_quotes() {
+        let mut m = mission(1, Some("Mortar, Heavy"));
+        m.wind_azimuth = Some(95.06);
+        m.wind_distance = Some(188.0);
+        let csv = to_csv(&[m, mission(2, None)]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("gun,weapon,"));
+        assert_eq!(
+            lines[1],
+            "1,\"Mortar, Heavy\",G9k3,1,H9k7,92.3,185,95.1,190,true,,,,"
+        );
+        let mut named = mission(2, None);
+        named.target_label = Some("Town hall, Abandoned Ward".to_string());
+        assert_eq!(
+            to_csv(&[named]).lines().nth(1).unwrap(),
+            "2,,G9k3,1,H9k7,92.3,185,,,true,\"Town hall, Abandoned Ward\",,,"
+        );
+        assert_eq!(lines[2], "2,,G9k3,1,H9k7,92.3,185,,,true,,,,");
+
+        let mut towed = mission(3, Some("Lariat"));
+        towed.deployment = Deployment {
+            crew: Some(1),
+            deploy_time: Some(20.0),
+            mount: Some(WeaponMount::Towed),
+        };
+        assert!(to_csv(&[towed]).ends_with(",true,,Towed,1,20\n"));
+    }
+
+    #[test]
+    fn test_to_discord_text_sums_crew() {
+        let crewed = |gun: usize, crew: Option<u32>| FireMission {
+            deployment: Deployment {
+                crew,
+                ..Deployment::default()
+            },
+            ..mission(gun, None)
+        };
+        let text = to_discord_text("", &[crewed(1, Some(1)), crewed(2, Some(2))], &[]);
+        assert!(text.ends_with("Minimum crew: 3\n```"));
+        let text = to_discord_text("", &[crewed(1, Some(2)), crewed(2, None)], &[]);
+        assert!(text.contains("Minimum crew: 2 + G2 (unknown)\n"));
+        assert!(!to_discord_text("", &[mission(1, None)], &[]).contains("crew"));
+    }
+
+    #[test]
+    fn test_to_discord_text_aligns_columns() {
+        let mut far = mission(12, Some("Storm Cannon"));
+        far.in_range = false;
+        let text = to_discord_text("Op Sunrise", &[mission(1, Some("Mortar")), far], &[]);
+        assert!(text.starts_with("**Fire missions: Op Sunrise**\n```\n"));
+        assert!(text.ends_with("```"));
+        assert!(!text.contains("WIND"));
+        assert!(text.contains("185m!"));
+        assert!(text.contains("! = out of range"));
+
+        let body: Vec<&str> = text.lines().skip(2).take(3).collect();
+        let az_col = body[0].find("AZ").unwrap();
+        assert_eq!(body[1].find("92.3").unwrap(), az_col);
+        assert_eq!(body[2].find("92.3").unwrap(), az_col);
+    }
+
+    #[test]
+    fn test_to_discord_text_includes_wind_columns_when_present() {
+        let mut m = mission(1, None);
+        m.wind_azimuth = Some(100.0);
+        m.wind_distance = Some(200.0);
+        let text = to_discord_text("", &[m, mission(2, None)], &[]);
+        assert!(text.starts_with("**Fire missions**"));
+        assert!(text.contains("WIND AZ"));
+        assert!(text.contains("100.0\u{00b0}"));
+    }
+
+    #[test]
+    fn test_to_discord_text_names_labelled_targets() {
+        let mut named = mission(1, None);
+        named.target_label = Some("Relic base, Scorched Ridge".to_string());
+        let text = to_discord_text("", &[named, mission(2, None)], &[]);
+        let body: Vec<&str> = text.lines().skip(2).take(3).collect();
+        assert!(body[0].contains("TGT  NAME"));
+        assert!(body[1].contains("T1   Relic base, Scorched Ridge  H9k7"));
+        assert!(body[2].contains("T1   -"));
+        assert!(!to_discord_text("", &[mission(1, None)], &[]).contains("NAME"));
+    }
+
+    #[test]
+    fn test_solution_text() {
+        let mut sol = solution(247.34, 183.0, true);
+        assert_eq!(solution_text(&sol), "AZ 247.3\u{00b0} / 185 m");
+        sol.wind_adjusted_azimuth = Some(245.12);
+        sol.wind_adjusted_distance = Some(191.0);
+        sol.in_range = false;
+        assert_eq!(
+            solution_text(&sol),
+            "AZ 247.3\u{00b0} / 185 m (wind adj: 245.1\u{00b0} / 190 m) OUT OF RANGE"
+        );
+    }
+
+    #[test]
+    fn test_to_chat_text_one_line_per_gun() {
+        let mut named = mission(2, None);
+        named.target_label = Some("Bunker".to_string());
+        let text = to_chat_text(&[mission(1, None), named]);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "G1 \u{2192} T1: AZ 92.3\u{00b0} / 185 m");
+        assert!(lines[1].starts_with("G2 \u{2192} Bunker: AZ"));
+    }
+}
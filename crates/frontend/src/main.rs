@@ -1,7 +1,13 @@
 mod api;
 mod components;
 mod coords;
+mod export;
+mod offline;
 mod pages;
+mod persistence;
+mod settings;
+mod theme;
+mod worker;
 
 use dioxus::prelude::*;
 
@@ -11,6 +17,10 @@ enum Route {
     Home {},
     #[route("/plan/:id")]
     PlanView { id: String },
+    #[route("/admin")]
+    Admin {},
+    #[route("/stats")]
+    Stats {},
 }
 
 #[component]
@@ -22,11 +32,56 @@ fn Home() -> Element {
 
 #[component]
 fn PlanView(id: String) -> Element {
+    if foxhole_shared::slug::is_slug(&id) {
+        return rsx! {
+            PlanLink { slug: id }
+        };
+    }
     rsx! {
         pages::planner::Planner { plan_id: Some(id) }
     }
 }
 
+/// A short link: look up the plan and reopen it under its id, which edit
+/// tokens and locks are keyed by.
+#[component]
+fn PlanLink(slug: String) -> Element {
+    let plan = use_resource(use_reactive!(
+        |slug| async move { api::fetch_plan(&slug).await }
+    ));
+    use_effect(move || {
+        if let (Some(Ok(Some(plan))), Some(window)) = (&*plan.read(), web_sys::window()) {
+            let origin = window.location().origin().unwrap_or_default();
+            let _ = window
+                .location()
+                .replace(&api::build_plan_url(&origin, &plan.id));
+        }
+    });
+
+    let message = match &*plan.read() {
+        None | Some(Ok(Some(_))) => "Opening plan...".to_string(),
+        Some(Ok(None)) => format!("No plan called \"{slug}\"."),
+        Some(Err(e)) => format!("Failed to open plan: {e}"),
+    };
+    rsx! {
+        div { class: "plan-link-status", role: "status", "{message}" }
+    }
+}
+
+#[component]
+fn Admin() -> Element {
+    rsx! {
+        pages::admin::Admin {}
+    }
+}
+
+#[component]
+fn Stats() -> Element {
+    rsx! {
+        pages::stats::Stats {}
+    }
+}
+
 const CSS: Asset = asset!("/assets/main.css");
 const FAVICON: Asset = asset!("/assets/favicon.svg");
 
@@ -40,5 +95,10 @@ fn App() -> Element {
 }
 
 fn main() {
+    // The overlay worker runs this same bundle without a window
+    if worker::is_worker() {
+        worker::run();
+        return;
+    }
     launch(App);
 }
@@ -0,0 +1,198 @@
+//! Keeping the planner usable without a connection.
+//!
+//! The service worker (`public/sw.js`) caches the app itself and the map
+//! images. This module covers the GraphQL side: the last maps and weapons
+//! lists are kept on this device, firing solutions fall back to the shared
+//! calculator, and a save made offline waits here until it can be sent.
+
+use foxhole_shared::calc;
+use foxhole_shared::models::{Position, WindInput};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::api::{FiringSolutionData, PlanSave, WeaponData};
+
+const OUTBOX_KEY: &str = "plan_outbox";
+
+/// How often a save queued while offline is retried.
+pub const OUTBOX_RETRY_MS: u32 = 15_000;
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window().and_then(|w| w.local_storage().ok().flatten())
+}
+
+/// Whether the browser believes it has a connection; assumed so when it can't tell.
+pub fn is_online() -> bool {
+    web_sys::window()
+        .map(|w| w.navigator().on_line())
+        .unwrap_or(true)
+}
+
+/// Keep `value` on this device under `key`.
+pub fn remember<T: Serialize>(key: &str, value: &T) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(value)) {
+        let _ = storage.set_item(key, &json);
+    }
+}
+
+/// The value last kept under `key`, if it's still readable as a `T`.
+pub fn recall<T: DeserializeOwned>(key: &str) -> Option<T> {
+    local_storage()
+        .and_then(|s| s.get_item(key).ok().flatten())
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// Hold `save` until it can be sent. Each save carries the whole plan, so a
+/// newer one replaces whatever was waiting.
+pub fn queue_save(save: &PlanSave) {
+    remember(OUTBOX_KEY, save);
+}
+
+/// The save waiting to be sent, if any.
+pub fn queued_save() -> Option<PlanSave> {
+    recall(OUTBOX_KEY)
+}
+
+pub fn clear_queued_save() {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(OUTBOX_KEY);
+    }
+}
+
+/// Firing solution worked out in the browser, for when the server can't be
+/// reached. Positions are in meters. `None` for a weapon or shell not in
+/// `weapons`.
+pub fn local_solution(
+    weapons: &[WeaponData],
+    weapon_id: &str,
+    ammo: &str,
+    gun: (f64, f64),
+    target: (f64, f64),
+    wind_direction: Option<f64>,
+    wind_strength: u32,
+) -> Option<FiringSolutionData> {
+    let weapon = weapons
+        .iter()
+        .find(|w| w.slug == weapon_id)?
+        .to_weapon()
+        .with_ammo(ammo)?;
+    // Same rule as the calculate query: no wind unless it has some strength
+    let wind = wind_direction
+        .filter(|_| wind_strength > 0)
+        .map(|direction| WindInput {
+            direction,
+            strength: wind_strength.min(u8::MAX as u32) as u8,
+        });
+    let sol = calc::firing_solution(
+        Position { x: gun.0, y: gun.1 },
+        Position {
+            x: target.0,
+            y: target.1,
+        },
+        &weapon,
+        wind.as_ref(),
+    );
+    Some(sol.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::AmmoData;
+
+    fn mortar() -> WeaponData {
+        WeaponData {
+            slug: "cremari-mortar".to_string(),
+            faction: "BOTH".to_string(),
+            display_name: "Cremari Mortar".to_string(),
+            min_range: 45.0,
+            max_range: 80.0,
+            acc_radius_min: 2.5,
+            acc_radius_max: 9.45,
+            wind_drift_min: 1.0,
+            wind_drift_max: 3.0,
+            flight_time_min: 3.0,
+            flight_time_max: 5.0,
+            ammo: vec![AmmoData {
+                name: "Flare".to_string(),
+                min_range: 45.0,
+                max_range: 100.0,
+                acc_radius_min: 2.5,
+                acc_radius_max: 9.45,
+                wind_drift_min: 1.0,
+                wind_drift_max: 3.0,
+                flight_time_min: 4.0,
+                flight_time_max: 8.0,
+            }],
+            crew: None,
+            deploy_time: None,
+            mount: None,
+            area_fire: false,
+        }
+    }
+
+    /// Solution from a gun at the origin to a target `distance` m north.
+    fn solve(
+        weapon_id: &str,
+        ammo: &str,
+        distance: f64,
+        wind: Option<(f64, u32)>,
+    ) -> Option<FiringSolutionData> {
+        let (direction, strength) = wind.map_or((None, 0), |(d, s)| (Some(d), s));
+        local_solution(
+            &[mortar()],
+            weapon_id,
+            ammo,
+            (0.0, 0.0),
+            (0.0, distance),
+            direction,
+            strength,
+        )
+    }
+
+    #[test]
+    fn test_local_solution_matches_the_shared_calculator() {
+        let sol = solve("cremari-mortar", "", 60.0, None).unwrap();
+        let expected = calc::firing_solution(
+            Position { x: 0.0, y: 0.0 },
+            Position { x: 0.0, y: 60.0 },
+            &mortar().to_weapon(),
+            None,
+        );
+        assert_eq!(sol, FiringSolutionData::from(expected));
+        assert!(sol.in_range);
+        assert!(sol.time_of_flight.is_some());
+
+        // A flare reaches further than the standard shell
+        assert!(!solve("cremari-mortar", "", 90.0, None).unwrap().in_range);
+        assert!(
+            solve("cremari-mortar", "Flare", 90.0, None)
+                .unwrap()
+                .in_range
+        );
+        assert!(solve("storm-cannon", "", 60.0, None).is_none());
+        assert!(solve("cremari-mortar", "Gas", 60.0, None).is_none());
+    }
+
+    #[test]
+    fn test_local_solution_applies_wind_only_with_strength() {
+        let calm = solve("cremari-mortar", "", 60.0, Some((90.0, 0))).unwrap();
+        assert!(calm.wind_adjusted_azimuth.is_none());
+        let windy = solve("cremari-mortar", "", 60.0, Some((90.0, 3))).unwrap();
+        assert!(windy.wind_adjusted_azimuth.is_some());
+    }
+
+    #[test]
+    fn test_queued_save_round_trips_through_json() {
+        let save = PlanSave::Update(serde_json::json!({
+            "id": "abc-123",
+            "baseVersion": 4,
+            "input": { "name": "Op Hammer" },
+        }));
+        let json = serde_json::to_string(&save).unwrap();
+        let decoded: PlanSave = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, save);
+        assert_eq!(decoded.plan_id(), Some("abc-123"));
+        assert_eq!(PlanSave::Create(serde_json::json!({})).plan_id(), None);
+    }
+}
@@ -0,0 +1,269 @@
+use dioxus::prelude::*;
+
+use crate::api::{self, AdminStatusData};
+
+/// Human-readable size, e.g. `1.5 MB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn format_retention(days: Option<f64>) -> String {
+    match days {
+        Some(days) => format!("{} days after last save", days),
+        None => "Plans are kept forever".to_string(),
+    }
+}
+
+/// Operator page at `/admin`. Everything on it needs the server's `ADMIN_TOKEN`.
+#[component]
+pub fn Admin() -> Element {
+    let mut admin_token = use_signal(api::load_admin_token);
+    let mut token_input = use_signal(String::new);
+    let mut status = use_signal(|| None::<AdminStatusData>);
+    let mut error = use_signal(|| None::<String>);
+    let mut notice = use_signal(|| None::<String>);
+    let mut busy = use_signal(|| false);
+
+    // Reload the status whenever we log in; a rejected token logs us out again.
+    use_effect(move || {
+        let Some(token) = admin_token() else {
+            return;
+        };
+        spawn(async move {
+            match api::fetch_admin_status(&token).await {
+                Ok(s) => {
+                    status.set(Some(s));
+                    error.set(None);
+                }
+                Err(e) => {
+                    api::save_admin_token(None);
+                    admin_token.set(None);
+                    status.set(None);
+                    error.set(Some(e));
+                }
+            }
+        });
+    });
+
+    let mut log_in = move || {
+        let token = token_input.peek().trim().to_string();
+        if token.is_empty() {
+            return;
+        }
+        api::save_admin_token(Some(&token));
+        token_input.set(String::new());
+        notice.set(None);
+        admin_token.set(Some(token));
+    };
+
+    let purge = move |_| {
+        let Some(token) = admin_token() else {
+            return;
+        };
+        busy.set(true);
+        spawn(async move {
+            match api::purge_expired_plans(&token).await {
+                Ok(report) => {
+                    notice.set(Some(format!(
+                        "Deleted {} expired plan(s), reclaimed {}.",
+                        report.plans_deleted,
+                        format_bytes(report.bytes_reclaimed)
+                    )));
+                    error.set(None);
+                    if let Ok(s) = api::fetch_admin_status(&token).await {
+                        status.set(Some(s));
+                    }
+                }
+                Err(e) => error.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+
+    let reload = move |_| {
+        let Some(token) = admin_token() else {
+            return;
+        };
+        busy.set(true);
+        spawn(async move {
+            match api::reload_assets(&token).await {
+                Ok(report) => {
+                    notice.set(Some(format!(
+                        "Reloaded game data: {} map(s), {} weapon(s).",
+                        report.maps, report.weapons
+                    )));
+                    error.set(None);
+                }
+                Err(e) => error.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+
+    let reset_stats = move |_| {
+        let Some(token) = admin_token() else {
+            return;
+        };
+        busy.set(true);
+        spawn(async move {
+            match api::reset_stats(&token).await {
+                Ok(cleared) => {
+                    notice.set(Some(format!("Cleared {} placement counter(s).", cleared)));
+                    error.set(None);
+                }
+                Err(e) => error.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+
+    let backup = move |_| {
+        let Some(token) = admin_token() else {
+            return;
+        };
+        busy.set(true);
+        spawn(async move {
+            match api::backup_database(&token).await {
+                Ok(report) => {
+                    notice.set(Some(format!(
+                        "Backed up the database ({}) to {}.",
+                        format_bytes(report.size_bytes),
+                        report.path
+                    )));
+                    error.set(None);
+                }
+                Err(e) => error.set(Some(e)),
+            }
+            busy.set(false);
+        });
+    };
+
+    rsx! {
+        div { class: "admin-page",
+            div { class: "header",
+                h1 { "Instance Admin" }
+                div { class: "header-right",
+                    a { class: "toolbar-btn", href: "/", "Back to planner" }
+                    if admin_token().is_some() {
+                        button {
+                            class: "toolbar-btn",
+                            onclick: move |_| {
+                                api::save_admin_token(None);
+                                admin_token.set(None);
+                                status.set(None);
+                                notice.set(None);
+                            },
+                            "Log out"
+                        }
+                    }
+                }
+            }
+            div { class: "admin-content",
+                if admin_token().is_none() {
+                    div { class: "panel admin-login",
+                        h3 { "Log in" }
+                        p { class: "admin-hint",
+                            "Enter the ADMIN_TOKEN configured on the server."
+                        }
+                        input {
+                            r#type: "password",
+                            "aria-label": "Admin token",
+                            placeholder: "Admin token",
+                            value: "{token_input}",
+                            oninput: move |evt: Event<FormData>| token_input.set(evt.value()),
+                            onkeydown: move |evt: Event<KeyboardData>| {
+                                if evt.key() == Key::Enter {
+                                    log_in();
+                                }
+                            },
+                        }
+                        button { onclick: move |_| log_in(), "Log in" }
+                    }
+                } else {
+                    div { class: "panel",
+                        h3 { "Status" }
+                        if let Some(s) = &*status.read() {
+                            dl { class: "admin-stats",
+                                dt { "Saved plans" }
+                                dd { "{s.total_plans}" }
+                                dt { "Database size" }
+                                dd { "{format_bytes(s.db_size_bytes)}" }
+                                dt { "Schema version" }
+                                dd { "{s.schema_version}" }
+                                dt { "Retention" }
+                                dd { "{format_retention(s.cleanup.retention_days)}" }
+                                dt { "Plans expired" }
+                                dd { "{s.cleanup.plans_expired}" }
+                                dt { "Space reclaimed" }
+                                dd { "{format_bytes(s.cleanup.bytes_reclaimed)}" }
+                                dt { "Last cleanup" }
+                                dd { {s.cleanup.last_run_at.clone().unwrap_or_else(|| "Never".to_string())} }
+                            }
+                        } else {
+                            p { class: "admin-hint", "Loading..." }
+                        }
+                    }
+                    div { class: "panel",
+                        h3 { "Maintenance" }
+                        p { class: "admin-hint",
+                            "Delete expired plans and compact the database now instead of waiting for the next scheduled cleanup."
+                        }
+                        button { disabled: busy(), onclick: purge, "Purge expired plans" }
+                        p { class: "admin-hint",
+                            "Re-read maps.json and weapons.json from the server's assets directory, e.g. after a balance patch. Open planners pick up the new data when reloaded."
+                        }
+                        button { disabled: busy(), onclick: reload, "Reload game data" }
+                        p { class: "admin-hint",
+                            "Copy the database to the server's backup directory, or zero the gun, target and spotter placement statistics."
+                        }
+                        button { disabled: busy(), onclick: backup, "Back up database" }
+                        button { disabled: busy(), onclick: reset_stats, "Reset statistics" }
+                        if let Some(msg) = notice() {
+                            p { class: "admin-notice", role: "status", "{msg}" }
+                        }
+                    }
+                }
+                if let Some(err) = error() {
+                    div { class: "save-error", role: "alert",
+                        span { "{err}" }
+                        button {
+                            "aria-label": "Dismiss error",
+                            onclick: move |_| error.set(None),
+                            "\u{2715}"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MB");
+    }
+
+    #[test]
+    fn test_format_retention() {
+        assert_eq!(format_retention(Some(90.0)), "90 days after last save");
+        assert_eq!(format_retention(None), "Plans are kept forever");
+    }
+}
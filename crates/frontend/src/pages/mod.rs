@@ -1 +1,3 @@
+pub mod admin;
 pub mod planner;
+pub mod stats;
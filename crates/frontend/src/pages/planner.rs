@@ -1,14 +1,47 @@
 use dioxus::html::input_data::keyboard_types::{Key, Modifiers};
 use dioxus::prelude::*;
 
-use crate::api::{self, FiringSolutionData};
+use foxhole_shared::calc;
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::merge::{compare_plans, merge_markers, PlanChange, PlanMarkers};
+use foxhole_shared::models::{
+    self, destroyed_percent, IntelMarkers, MapCalibration, NoFireZone, Position, RangeRings,
+    RegistrationPoint, ShotRecord, TargetStatus, WeaponProfile,
+};
+use foxhole_shared::overlay::{HeatCell, ShellSpread};
+use foxhole_shared::template::BatteryTemplate;
+use foxhole_shared::terrain::{self, TerrainMask};
+
+use crate::api::{self, FiringSolutionData, MapData, PlanConflictData, PlanData};
+use crate::components::battery_templates::BatteryTemplates;
+use crate::components::bottom_sheet::{SheetHandle, SheetState};
 use crate::components::calculation_display::CalculationDisplay;
+use crate::components::calibration_panel::CalibrationPanel;
+use crate::components::counter_battery::CounterBatteryPanel;
 use crate::components::help_overlay::HelpOverlay;
-use crate::components::map_view::{remove_marker, Faction, MapView, MarkerKind, PlacementMode, SelectedMarker};
+use crate::components::logistics::LogisticsPanel;
+use crate::components::map_selector::{default_map, load_last_map, MapSelector};
+use crate::components::map_view::{
+    find_first_unpaired_target, nudge_delta, pair_first_unpaired_gun, remove_group, remove_marker,
+    selection_after_remove, set_marker_position, translate_group, Faction, MapView, MarkerKind,
+    MarkerLabels, PlacementMode, SelectedMarker,
+};
+use crate::components::merge_dialog::{MergeChoice, MergeDialog};
+use crate::components::placement_action::PlacementAction;
+use crate::components::plan_compare::PlanComparePanel;
 use crate::components::plan_panel::PlanPanel;
+use crate::components::plan_tabs::PlanTabs;
+use crate::components::range_bearing::RangeBearingPanel;
+use crate::components::range_rings::RangeRingsPanel;
+use crate::components::settings_drawer::SettingsDrawer;
 use crate::components::weapon_selector::WeaponSelector;
 use crate::components::wind_input::WindInput;
 use crate::coords;
+use crate::export;
+use crate::offline;
+use crate::persistence::{self, SharedPlan, StoredSession};
+use crate::settings::{load_settings, save_settings};
+use crate::theme::{load_theme, save_theme, Appearance};
 
 // ---------------------------------------------------------------------------
 // Undo / redo infrastructure
@@ -16,23 +49,72 @@ use crate::coords;
 
 const UNDO_LIMIT: usize = 50;
 
-#[derive(Clone, Debug)]
+/// Default radius (m) a shell must land within to count as a hit.
+const DEFAULT_TARGET_RADIUS: f64 = 10.0;
+
+/// How often a shared plan's edit lock is refreshed (the server expires it after 60 s).
+const PLAN_LOCK_REFRESH_MS: u32 = 20_000;
+
+/// How long the planner waits after the last change before autosaving the session.
+const AUTOSAVE_DELAY_MS: u32 = 1_000;
+
+/// How long marker and weapon changes must settle before firing solutions
+/// are recalculated, so a drag or a run of nudges costs one round of requests.
+const CALC_DEBOUNCE_MS: u32 = 150;
+
+#[derive(Clone, Debug, Default)]
 pub struct PlanSnapshot {
     pub gun_positions: Vec<(f64, f64)>,
     pub target_positions: Vec<(f64, f64)>,
     pub spotter_positions: Vec<(f64, f64)>,
+    pub enemy_positions: Vec<(f64, f64)>,
+    pub fob_positions: Vec<(f64, f64)>,
+    pub bunker_positions: Vec<(f64, f64)>,
+    pub no_fire_positions: Vec<(f64, f64)>,
+    /// Radius (m) of each no-fire zone; zones past the end use the default.
+    pub no_fire_radii: Vec<f64>,
     pub gun_weapon_ids: Vec<String>,
     pub gun_target_indices: Vec<Option<usize>>,
+    pub labels: MarkerLabels,
+    /// Shells per salvo for each gun; guns past the end fire one.
+    pub gun_salvo_sizes: Vec<u32>,
+    /// Alternate firing positions per gun, in the order it displaces to them.
+    pub gun_alternates: Vec<Vec<(f64, f64)>>,
+    /// Progress per target; targets past the end are active.
+    pub target_statuses: Vec<TargetStatus>,
+    /// Adjustment rounds fired, oldest first.
+    pub shot_log: Vec<ShotRecord>,
+    /// Registration points per gun. Kept in meters, so they don't move when
+    /// the map image changes.
+    pub registrations: Vec<RegistrationPoint>,
+    /// Ammo per gun; guns past the end fire their standard shell.
+    pub gun_ammo: Vec<String>,
+    /// Per-gun ring toggles and custom rings. Rings follow their marker's index.
+    pub range_rings: RangeRings,
     pub wind_direction: Option<f64>,
     pub wind_strength: u32,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn capture_snapshot(
     gun_positions: &Signal<Vec<(f64, f64)>>,
     target_positions: &Signal<Vec<(f64, f64)>>,
     spotter_positions: &Signal<Vec<(f64, f64)>>,
+    enemy_positions: &Signal<Vec<(f64, f64)>>,
+    fob_positions: &Signal<Vec<(f64, f64)>>,
+    bunker_positions: &Signal<Vec<(f64, f64)>>,
+    no_fire_positions: &Signal<Vec<(f64, f64)>>,
+    no_fire_radii: &Signal<Vec<f64>>,
     gun_weapon_ids: &Signal<Vec<String>>,
     gun_target_indices: &Signal<Vec<Option<usize>>>,
+    marker_labels: &Signal<MarkerLabels>,
+    gun_salvo_sizes: &Signal<Vec<u32>>,
+    gun_alternates: &Signal<Vec<Vec<(f64, f64)>>>,
+    target_statuses: &Signal<Vec<TargetStatus>>,
+    shot_log: &Signal<Vec<ShotRecord>>,
+    registrations: &Signal<Vec<RegistrationPoint>>,
+    gun_ammo: &Signal<Vec<String>>,
+    range_rings: &Signal<RangeRings>,
     wind_direction: &Signal<Option<f64>>,
     wind_strength: &Signal<u32>,
 ) -> PlanSnapshot {
@@ -40,8 +122,21 @@ pub fn capture_snapshot(
         gun_positions: gun_positions.read().clone(),
         target_positions: target_positions.read().clone(),
         spotter_positions: spotter_positions.read().clone(),
+        enemy_positions: enemy_positions.read().clone(),
+        fob_positions: fob_positions.read().clone(),
+        bunker_positions: bunker_positions.read().clone(),
+        no_fire_positions: no_fire_positions.read().clone(),
+        no_fire_radii: no_fire_radii.read().clone(),
         gun_weapon_ids: gun_weapon_ids.read().clone(),
         gun_target_indices: gun_target_indices.read().clone(),
+        labels: marker_labels.read().clone(),
+        gun_salvo_sizes: gun_salvo_sizes.read().clone(),
+        gun_alternates: gun_alternates.read().clone(),
+        target_statuses: target_statuses.read().clone(),
+        shot_log: shot_log.read().clone(),
+        registrations: registrations.read().clone(),
+        gun_ammo: gun_ammo.read().clone(),
+        range_rings: range_rings.read().clone(),
         wind_direction: *wind_direction.read(),
         wind_strength: *wind_strength.read(),
     }
@@ -53,16 +148,42 @@ fn restore_snapshot(
     gun_positions: &mut Signal<Vec<(f64, f64)>>,
     target_positions: &mut Signal<Vec<(f64, f64)>>,
     spotter_positions: &mut Signal<Vec<(f64, f64)>>,
+    enemy_positions: &mut Signal<Vec<(f64, f64)>>,
+    fob_positions: &mut Signal<Vec<(f64, f64)>>,
+    bunker_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_positions: &mut Signal<Vec<(f64, f64)>>,
+    no_fire_radii: &mut Signal<Vec<f64>>,
     gun_weapon_ids: &mut Signal<Vec<String>>,
     gun_target_indices: &mut Signal<Vec<Option<usize>>>,
+    marker_labels: &mut Signal<MarkerLabels>,
+    gun_salvo_sizes: &mut Signal<Vec<u32>>,
+    gun_alternates: &mut Signal<Vec<Vec<(f64, f64)>>>,
+    target_statuses: &mut Signal<Vec<TargetStatus>>,
+    shot_log: &mut Signal<Vec<ShotRecord>>,
+    registrations: &mut Signal<Vec<RegistrationPoint>>,
+    gun_ammo: &mut Signal<Vec<String>>,
+    range_rings: &mut Signal<RangeRings>,
     wind_direction: &mut Signal<Option<f64>>,
     wind_strength: &mut Signal<u32>,
 ) {
     gun_positions.set(snapshot.gun_positions.clone());
     target_positions.set(snapshot.target_positions.clone());
     spotter_positions.set(snapshot.spotter_positions.clone());
+    enemy_positions.set(snapshot.enemy_positions.clone());
+    fob_positions.set(snapshot.fob_positions.clone());
+    bunker_positions.set(snapshot.bunker_positions.clone());
+    no_fire_positions.set(snapshot.no_fire_positions.clone());
+    no_fire_radii.set(snapshot.no_fire_radii.clone());
     gun_weapon_ids.set(snapshot.gun_weapon_ids.clone());
     gun_target_indices.set(snapshot.gun_target_indices.clone());
+    marker_labels.set(snapshot.labels.clone());
+    gun_salvo_sizes.set(snapshot.gun_salvo_sizes.clone());
+    gun_alternates.set(snapshot.gun_alternates.clone());
+    target_statuses.set(snapshot.target_statuses.clone());
+    shot_log.set(snapshot.shot_log.clone());
+    registrations.set(snapshot.registrations.clone());
+    gun_ammo.set(snapshot.gun_ammo.clone());
+    range_rings.set(snapshot.range_rings.clone());
     wind_direction.set(snapshot.wind_direction);
     wind_strength.set(snapshot.wind_strength);
 }
@@ -81,6 +202,429 @@ pub fn push_undo(
     redo_stack.write().clear();
 }
 
+/// Image scale of map `file_name`; the stock size until the maps have loaded.
+fn map_scale_of(maps: &Option<Result<Vec<MapData>, String>>, file_name: &str) -> MapScale {
+    maps.as_ref()
+        .and_then(|r| r.as_ref().ok())
+        .and_then(|maps| maps.iter().find(|m| m.file_name == file_name))
+        .map(MapData::scale)
+        .unwrap_or_default()
+}
+
+/// Image scale a stored plan is drawn at: its custom image's, else its map's.
+fn plan_scale_of(maps: &Option<Result<Vec<MapData>, String>>, plan: &PlanData) -> MapScale {
+    plan.calibration
+        .as_ref()
+        .and_then(api::CalibrationData::to_calibration)
+        .as_ref()
+        .and_then(MapCalibration::scale)
+        .unwrap_or_else(|| map_scale_of(maps, &plan.map_id))
+}
+
+/// `snapshot` moved from `from` px to `to` px, keeping every marker where it
+/// is on the map.
+pub fn rescale_snapshot(snapshot: &PlanSnapshot, from: MapScale, to: MapScale) -> PlanSnapshot {
+    let move_px = |v: &[(f64, f64)]| -> Vec<(f64, f64)> {
+        v.iter()
+            .map(|&(x, y)| {
+                let (mx, my) = coords::map_px_to_meters(x, y, from);
+                coords::meters_to_map_px(mx, my, to)
+            })
+            .collect()
+    };
+    PlanSnapshot {
+        gun_positions: move_px(&snapshot.gun_positions),
+        target_positions: move_px(&snapshot.target_positions),
+        spotter_positions: move_px(&snapshot.spotter_positions),
+        enemy_positions: move_px(&snapshot.enemy_positions),
+        fob_positions: move_px(&snapshot.fob_positions),
+        bunker_positions: move_px(&snapshot.bunker_positions),
+        no_fire_positions: move_px(&snapshot.no_fire_positions),
+        gun_alternates: snapshot.gun_alternates.iter().map(|a| move_px(a)).collect(),
+        ..snapshot.clone()
+    }
+}
+
+/// Planner state (`map` px) for a stored plan (meters). Salvo sizes start at
+/// one and guns have no alternate positions.
+pub fn snapshot_from_plan(plan: &PlanData, map: MapScale) -> PlanSnapshot {
+    let to_px = |v: &[api::PositionData]| -> Vec<(f64, f64)> {
+        v.iter()
+            .map(|p| coords::meters_to_map_px(p.x, p.y, map))
+            .collect()
+    };
+    // Load explicit pairings, or fall back to index-based for old plans
+    let gun_target_indices = if plan.gun_target_indices.is_empty() {
+        let num_targets = plan.target_positions.len();
+        (0..plan.gun_positions.len())
+            .map(|i| if i < num_targets { Some(i) } else { None })
+            .collect()
+    } else {
+        plan.gun_target_indices
+            .iter()
+            .map(|o| o.map(|v| v as usize))
+            .collect()
+    };
+    PlanSnapshot {
+        gun_positions: to_px(&plan.gun_positions),
+        target_positions: to_px(&plan.target_positions),
+        spotter_positions: to_px(&plan.spotter_positions),
+        enemy_positions: to_px(&plan.enemy_gun_positions),
+        fob_positions: to_px(&plan.fob_positions),
+        bunker_positions: to_px(&plan.bunker_positions),
+        no_fire_positions: to_px(&plan.no_fire_zone_positions),
+        no_fire_radii: plan.no_fire_zone_radii.clone(),
+        gun_weapon_ids: plan.weapon_ids.clone(),
+        gun_target_indices,
+        labels: MarkerLabels {
+            guns: plan.gun_labels.clone(),
+            targets: plan.target_labels.clone(),
+            spotters: plan.spotter_labels.clone(),
+        },
+        gun_salvo_sizes: Vec::new(),
+        gun_alternates: Vec::new(),
+        target_statuses: plan.target_statuses.clone(),
+        shot_log: plan
+            .shot_log
+            .iter()
+            .map(api::ShotRecordData::to_shot_record)
+            .collect(),
+        registrations: plan
+            .registrations
+            .iter()
+            .map(api::RegistrationPointData::to_registration)
+            .collect(),
+        gun_ammo: plan.gun_ammo.clone(),
+        range_rings: RangeRings {
+            hidden: plan.gun_rings_hidden.clone(),
+            custom: plan.custom_rings.clone(),
+        },
+        wind_direction: plan.wind_direction,
+        wind_strength: plan.wind_strength,
+    }
+}
+
+/// My state with the markers only they placed added on (see `merge_markers`).
+/// Wind, the shot log, registration points, ammo and range rings stay mine. Both snapshots are in `map` px.
+pub fn merge_snapshots(mine: &PlanSnapshot, theirs: &PlanSnapshot, map: MapScale) -> PlanSnapshot {
+    let to_m = |v: &[(f64, f64)]| -> Vec<Position> {
+        v.iter()
+            .map(|&(x, y)| {
+                let (x, y) = coords::map_px_to_meters(x, y, map);
+                Position { x, y }
+            })
+            .collect()
+    };
+    let to_px = |v: &[Position]| -> Vec<(f64, f64)> {
+        v.iter()
+            .map(|p| coords::meters_to_map_px(p.x, p.y, map))
+            .collect()
+    };
+    let markers = |s: &PlanSnapshot| PlanMarkers {
+        gun_positions: to_m(&s.gun_positions),
+        weapon_ids: s.gun_weapon_ids.clone(),
+        target_positions: to_m(&s.target_positions),
+        spotter_positions: to_m(&s.spotter_positions),
+        gun_target_indices: s.gun_target_indices.clone(),
+        labels: s.labels.clone(),
+        target_statuses: s.target_statuses.clone(),
+        intel: intel_in_meters(
+            &s.enemy_positions,
+            &s.fob_positions,
+            &s.bunker_positions,
+            &s.no_fire_positions,
+            &s.no_fire_radii,
+            map,
+        ),
+    };
+    let merged = merge_markers(&markers(mine), &markers(theirs));
+    PlanSnapshot {
+        gun_positions: to_px(&merged.gun_positions),
+        target_positions: to_px(&merged.target_positions),
+        spotter_positions: to_px(&merged.spotter_positions),
+        enemy_positions: to_px(&merged.intel.enemy_guns),
+        fob_positions: to_px(&merged.intel.fobs),
+        bunker_positions: to_px(&merged.intel.bunkers),
+        no_fire_positions: to_px(&merged.intel.no_fire_zones),
+        no_fire_radii: merged.intel.no_fire_radii,
+        gun_weapon_ids: merged.weapon_ids,
+        gun_target_indices: merged.gun_target_indices,
+        labels: merged.labels,
+        // My guns keep their places at the front of the merged list
+        gun_salvo_sizes: mine.gun_salvo_sizes.clone(),
+        gun_alternates: mine.gun_alternates.clone(),
+        target_statuses: merged.target_statuses,
+        shot_log: mine.shot_log.clone(),
+        registrations: mine.registrations.clone(),
+        gun_ammo: mine.gun_ammo.clone(),
+        range_rings: mine.range_rings.clone(),
+        wind_direction: mine.wind_direction,
+        wind_strength: mine.wind_strength,
+    }
+}
+
+/// What changed from `other` (stored, meters) to the markers on the map (`map` px).
+pub fn changes_since(
+    other: &PlanData,
+    guns: &[(f64, f64)],
+    targets: &[(f64, f64)],
+    spotters: &[(f64, f64)],
+    map: MapScale,
+) -> Vec<PlanChange> {
+    let to_m = |v: &[(f64, f64)]| -> Vec<Position> {
+        v.iter()
+            .map(|&(x, y)| {
+                let (x, y) = coords::map_px_to_meters(x, y, map);
+                Position { x, y }
+            })
+            .collect()
+    };
+    let stored = |v: &[api::PositionData]| -> Vec<Position> {
+        v.iter().map(|p| Position { x: p.x, y: p.y }).collect()
+    };
+    let before = PlanMarkers {
+        gun_positions: stored(&other.gun_positions),
+        target_positions: stored(&other.target_positions),
+        spotter_positions: stored(&other.spotter_positions),
+        ..PlanMarkers::default()
+    };
+    let after = PlanMarkers {
+        gun_positions: to_m(guns),
+        target_positions: to_m(targets),
+        spotter_positions: to_m(spotters),
+        ..PlanMarkers::default()
+    };
+    compare_plans(&before, &after)
+}
+
+// ---------------------------------------------------------------------------
+// Plan tabs
+// ---------------------------------------------------------------------------
+
+/// A plan open in a tab. The planner's signals hold the active tab; the
+/// others wait here until switched to.
+#[derive(Clone, Debug, Default)]
+pub struct PlanTab {
+    /// Tells tabs apart while their places in the bar shift.
+    pub key: u64,
+    pub map_id: String,
+    pub calibration: Option<MapCalibration>,
+    pub plan_name: String,
+    /// Id and version of the stored plan, once saved.
+    pub saved_plan: Option<(String, u32)>,
+    pub plan_url: Option<String>,
+    /// Someone else's protected plan: saving makes a copy.
+    pub saves_copy: bool,
+    pub state: PlanSnapshot,
+    pub undo: Vec<PlanSnapshot>,
+    pub redo: Vec<PlanSnapshot>,
+}
+
+impl PlanTab {
+    /// A fresh, unsaved plan on `map_id`.
+    pub fn new(key: u64, map_id: &str) -> Self {
+        PlanTab {
+            key,
+            map_id: map_id.to_string(),
+            plan_name: "New Plan".to_string(),
+            ..PlanTab::default()
+        }
+    }
+
+    /// Whether there are markers that closing the tab would throw away.
+    pub fn has_markers(&self) -> bool {
+        let s = &self.state;
+        !(s.gun_positions.is_empty()
+            && s.target_positions.is_empty()
+            && s.spotter_positions.is_empty()
+            && s.enemy_positions.is_empty()
+            && s.fob_positions.is_empty()
+            && s.bunker_positions.is_empty()
+            && s.no_fire_positions.is_empty())
+    }
+
+    /// Image scale the tab's markers are in: its custom image's, else its map's.
+    fn scale(&self, maps: &Option<Result<Vec<MapData>, String>>) -> MapScale {
+        self.calibration
+            .as_ref()
+            .and_then(MapCalibration::scale)
+            .unwrap_or_else(|| map_scale_of(maps, &self.map_id))
+    }
+}
+
+/// Link to share a saved plan, by its short slug when it has one.
+fn share_url(plan: &PlanData) -> String {
+    let origin = web_sys::window()
+        .and_then(|w| w.location().origin().ok())
+        .unwrap_or_default();
+    api::build_plan_url(&origin, plan.slug.as_deref().unwrap_or(&plan.id))
+}
+
+/// "Push · Deadlands" for a tab; the map's file name stands in until maps load.
+fn tab_label(plan_name: &str, map_id: &str, maps: &[MapData]) -> String {
+    let map = maps
+        .iter()
+        .find(|m| m.file_name == map_id)
+        .map_or(map_id, |m| m.display_name.as_str());
+    let name = plan_name.trim();
+    if name.is_empty() {
+        map.to_string()
+    } else {
+        format!("{name} \u{b7} {map}")
+    }
+}
+
+/// Intel markers from the map (`map` px) in meters, as plans store them.
+pub fn intel_in_meters(
+    enemies: &[(f64, f64)],
+    fobs: &[(f64, f64)],
+    bunkers: &[(f64, f64)],
+    no_fire_zones: &[(f64, f64)],
+    no_fire_radii: &[f64],
+    map: MapScale,
+) -> IntelMarkers {
+    let to_m = |v: &[(f64, f64)]| -> Vec<Position> {
+        v.iter()
+            .map(|&(x, y)| {
+                let (x, y) = coords::map_px_to_meters(x, y, map);
+                Position { x, y }
+            })
+            .collect()
+    };
+    IntelMarkers {
+        enemy_guns: to_m(enemies),
+        fobs: to_m(fobs),
+        bunkers: to_m(bunkers),
+        no_fire_zones: to_m(no_fire_zones),
+        no_fire_radii: no_fire_radii.to_vec(),
+    }
+}
+
+/// No-fire zones each gun's accuracy circle reaches around its target, indexed
+/// like `solutions` (see `calc::danger_close`). Targets are in `map` px.
+pub fn danger_close_per_gun(
+    solutions: &[Option<FiringSolutionData>],
+    pairings: &[Option<usize>],
+    targets: &[(f64, f64)],
+    zones: &[NoFireZone],
+    map: MapScale,
+) -> Vec<Vec<usize>> {
+    solutions
+        .iter()
+        .enumerate()
+        .map(|(gi, sol)| {
+            let target = pairings
+                .get(gi)
+                .copied()
+                .flatten()
+                .and_then(|ti| targets.get(ti));
+            match (sol, target) {
+                (Some(sol), Some(&(x, y))) => {
+                    let (x, y) = coords::map_px_to_meters(x, y, map);
+                    calc::danger_close(Position { x, y }, sol.accuracy_radius, zones)
+                }
+                _ => Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// The save for a plan: an update of `existing`, checked against
+/// `base_version` (else its own version), or a new plan. `state` is in
+/// `scale` px; plans are stored in meters.
+#[allow(clippy::too_many_arguments)]
+fn plan_save(
+    name: &str,
+    map_id: &str,
+    calibration: Option<&MapCalibration>,
+    state: &PlanSnapshot,
+    existing: Option<(String, u32)>,
+    base_version: Option<u32>,
+    scale: MapScale,
+    profile: WeaponProfile,
+) -> api::PlanSave {
+    let to_meters = |positions: &[(f64, f64)]| -> Vec<(f64, f64)> {
+        positions
+            .iter()
+            .map(|p| coords::map_px_to_meters(p.0, p.1, scale))
+            .collect()
+    };
+    let guns = to_meters(&state.gun_positions);
+    let targets = to_meters(&state.target_positions);
+    let spotters = to_meters(&state.spotter_positions);
+    let intel = intel_in_meters(
+        &state.enemy_positions,
+        &state.fob_positions,
+        &state.bunker_positions,
+        &state.no_fire_positions,
+        &state.no_fire_radii,
+        scale,
+    );
+    match existing {
+        Some((id, version)) => api::PlanSave::Update(api::build_update_plan_variables(
+            &id,
+            api::load_edit_token(&id).as_deref(),
+            base_version.unwrap_or(version),
+            name,
+            map_id,
+            &state.gun_weapon_ids,
+            &state.gun_ammo,
+            &guns,
+            &targets,
+            &spotters,
+            &state.gun_target_indices,
+            &state.labels,
+            &state.target_statuses,
+            state.wind_direction,
+            Some(state.wind_strength),
+            calibration,
+            &state.shot_log,
+            &state.registrations,
+            &state.range_rings,
+            &intel,
+            profile,
+        )),
+        None => api::PlanSave::Create(api::build_create_plan_variables(
+            name,
+            map_id,
+            &state.gun_weapon_ids,
+            &state.gun_ammo,
+            &guns,
+            &targets,
+            &spotters,
+            &state.gun_target_indices,
+            &state.labels,
+            &state.target_statuses,
+            state.wind_direction,
+            Some(state.wind_strength),
+            calibration,
+            &state.shot_log,
+            &state.registrations,
+            &state.range_rings,
+            &intel,
+            profile,
+        )),
+    }
+}
+
+/// Take a plan's edit lock and keep refreshing it. While locked out it keeps
+/// polling too, so it picks the lock up once the other editor leaves.
+async fn hold_plan_lock(
+    plan_id: String,
+    session: String,
+    edit_token: Option<String>,
+    mut locked_out: Signal<bool>,
+) {
+    loop {
+        if let Ok(lock) =
+            api::acquire_plan_lock(&plan_id, &session, false, edit_token.as_deref()).await
+        {
+            locked_out.set(!lock.acquired);
+        }
+        gloo_timers::future::TimeoutFuture::new(PLAN_LOCK_REFRESH_MS).await;
+    }
+}
+
 // ---------------------------------------------------------------------------
 // DOM helpers
 // ---------------------------------------------------------------------------
@@ -126,40 +670,253 @@ fn save_faction(faction: Faction) {
     }
 }
 
+/// Read an on/off preference from localStorage (defaults to off).
+fn load_flag(key: &str) -> bool {
+    let storage: Option<web_sys::Storage> =
+        web_sys::window().and_then(|w| w.local_storage().ok().flatten());
+    storage
+        .and_then(|s| s.get_item(key).ok().flatten())
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+fn save_flag(key: &str, enabled: bool) {
+    let storage: Option<web_sys::Storage> =
+        web_sys::window().and_then(|w| w.local_storage().ok().flatten());
+    if let Some(storage) = storage {
+        let _ = storage.set_item(key, if enabled { "true" } else { "false" });
+    }
+}
+
+/// Community placements the map is shaded by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum HeatmapLayer {
+    #[default]
+    Off,
+    Guns,
+    Targets,
+}
+
+impl HeatmapLayer {
+    const KEY: &'static str = "heatmap_layer";
+
+    fn as_str(self) -> &'static str {
+        match self {
+            HeatmapLayer::Off => "off",
+            HeatmapLayer::Guns => "guns",
+            HeatmapLayer::Targets => "targets",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "guns" => HeatmapLayer::Guns,
+            "targets" => HeatmapLayer::Targets,
+            _ => HeatmapLayer::Off,
+        }
+    }
+
+    fn load() -> Self {
+        let storage: Option<web_sys::Storage> =
+            web_sys::window().and_then(|w| w.local_storage().ok().flatten());
+        storage
+            .and_then(|s| s.get_item(Self::KEY).ok().flatten())
+            .map_or(HeatmapLayer::Off, |v| Self::parse(&v))
+    }
+
+    fn save(self) {
+        let storage: Option<web_sys::Storage> =
+            web_sys::window().and_then(|w| w.local_storage().ok().flatten());
+        if let Some(storage) = storage {
+            let _ = storage.set_item(Self::KEY, self.as_str());
+        }
+    }
+}
+
+/// "2/5 targets destroyed (40%)" for the header, or `None` without targets.
+fn progress_text(statuses: &[TargetStatus], targets: usize) -> Option<String> {
+    let percent = destroyed_percent(statuses, targets)?;
+    let destroyed = statuses
+        .iter()
+        .take(targets)
+        .filter(|&&s| s == TargetStatus::Destroyed)
+        .count();
+    Some(format!(
+        "{destroyed}/{targets} targets destroyed ({percent}%)"
+    ))
+}
+
+/// "Gun 2 is in water" for each gun and spotter (map-image px) on bad
+/// ground, using the markers' names where they have them.
+fn terrain_warning_messages(
+    guns: &[(f64, f64)],
+    spotters: &[(f64, f64)],
+    scale: MapScale,
+    mask: Option<&TerrainMask>,
+    labels: &MarkerLabels,
+) -> Vec<String> {
+    let meters = |markers: &[(f64, f64)]| -> Vec<Position> {
+        markers
+            .iter()
+            .map(|&(x, y)| {
+                let (x, y) = coords::map_px_to_meters(x, y, scale);
+                Position { x, y }
+            })
+            .collect()
+    };
+    terrain::placement_warnings(&meters(guns), &meters(spotters), mask)
+        .into_iter()
+        .map(|w| {
+            let label = labels.get(w.marker, w.index).map_or_else(
+                || {
+                    let kind = if w.marker == MarkerKind::Gun {
+                        "Gun"
+                    } else {
+                        "Spotter"
+                    };
+                    format!("{kind} {}", w.index + 1)
+                },
+                str::to_string,
+            );
+            w.message(&label)
+        })
+        .collect()
+}
+
 #[component]
 pub fn Planner(plan_id: Option<String>) -> Element {
     // Data resources
     let mut maps_resource = use_resource(api::fetch_maps);
-    let mut weapons_resource = use_resource(api::fetch_weapons);
+    // User settings; the weapon figures planned with are one of them
+    let mut settings = use_signal(load_settings);
+    let weapon_profile = use_memo(move || settings.read().weapon_profile);
+    let mut weapons_resource = use_resource(move || api::fetch_weapons(weapon_profile()));
 
-    // UI state signals — positions are in native map-image pixel space (2048x1776)
+    // UI state signals — positions are in the selected map's native image pixels
     let mut selected_map = use_signal(String::new);
+    // Custom image the plan is drawn on instead of the stock map, if any
+    let mut calibration = use_signal(|| None::<MapCalibration>);
+    let map_scale = use_memo(move || {
+        calibration
+            .read()
+            .as_ref()
+            .and_then(MapCalibration::scale)
+            .unwrap_or_else(|| map_scale_of(&maps_resource.read(), &selected_map.read()))
+    });
     let mut selected_weapon = use_signal(String::new);
+    let enemy_weapon = use_signal(String::new);
     let mut placement_mode = use_signal(|| PlacementMode::Gun);
     let mut gun_positions = use_signal(Vec::<(f64, f64)>::new);
     let mut target_positions = use_signal(Vec::<(f64, f64)>::new);
     let mut spotter_positions = use_signal(Vec::<(f64, f64)>::new);
+    let mut enemy_positions = use_signal(Vec::<(f64, f64)>::new);
+    let mut fob_positions = use_signal(Vec::<(f64, f64)>::new);
+    let mut bunker_positions = use_signal(Vec::<(f64, f64)>::new);
+    let mut no_fire_positions = use_signal(Vec::<(f64, f64)>::new);
+    let mut no_fire_radii = use_signal(Vec::<f64>::new);
     let mut wind_direction = use_signal(|| None::<f64>);
     let mut wind_strength = use_signal(|| 0u32);
     let mut gun_weapon_ids = use_signal(Vec::<String>::new);
     let mut gun_target_indices = use_signal(Vec::<Option<usize>>::new);
+    let mut marker_labels = use_signal(MarkerLabels::default);
+    let mut gun_salvo_sizes = use_signal(Vec::<u32>::new);
+    let mut gun_alternates = use_signal(Vec::<Vec<(f64, f64)>>::new);
+    let mut target_statuses = use_signal(Vec::<TargetStatus>::new);
+    let mut shot_log = use_signal(Vec::<ShotRecord>::new);
+    let mut registrations = use_signal(Vec::<RegistrationPoint>::new);
+    let mut gun_ammo = use_signal(Vec::<String>::new);
+    let mut range_rings = use_signal(RangeRings::default);
+    // Radius (m) a shell must land within to count as a hit
+    let target_radius = use_signal(|| DEFAULT_TARGET_RADIUS);
     let mut selected_marker = use_signal(|| None::<SelectedMarker>);
+    // Battery template waiting for a map click to stamp it
+    let mut armed_template = use_signal(|| None::<BatteryTemplate>);
+    let mut group_selection = use_signal(Vec::<SelectedMarker>::new);
     let mut plan_name = use_signal(|| "New Plan".to_string());
     let mut plan_url = use_signal(|| None::<String>);
     let mut firing_solutions = use_signal(Vec::<Option<FiringSolutionData>>::new);
     let mut save_error = use_signal(|| None::<String>);
+    // Id + version of the stored plan being edited; saves update it instead of creating a new one
+    let mut saved_plan = use_signal(|| None::<(String, u32)>);
+    let mut saves_copy = use_signal(|| false);
+    let mut merge_conflict = use_signal(|| None::<PlanConflictData>);
+    // A save made offline is waiting to be sent
+    let mut save_queued = use_signal(|| offline::queued_save().is_some());
 
     // Undo / redo stacks
     let mut undo_stack = use_signal(Vec::<PlanSnapshot>::new);
     let mut redo_stack = use_signal(Vec::<PlanSnapshot>::new);
 
-    // Help overlay, view-reset signaling, and sidebar drawer
+    // Help overlay, view-reset signaling, and the panel sheet on narrow screens
     let mut show_help = use_signal(|| false);
     let mut reset_view_counter = use_signal(|| 0u64);
-    let mut sidebar_open = use_signal(|| false);
+    let mut save_view_counter = use_signal(|| 0u64);
+    let mut sheet = use_signal(SheetState::default);
+
+    // The faction theme the settings may fix at startup
+    let mut show_settings = use_signal(|| false);
+    let mut faction = use_signal(|| {
+        settings
+            .peek()
+            .default_faction
+            .unwrap_or_else(load_saved_faction)
+    });
+
+    // Right-click behaviour: context menu (default) or instant remove
+    let mut instant_remove = use_signal(|| load_flag("instant_remove"));
+    let mut theme = use_signal(load_theme);
+    let high_contrast = use_memo(move || theme.read().high_contrast);
+    let palette = use_memo(move || theme.read().palette(*faction.read()));
+    let mut show_structures = use_signal(|| load_flag("show_structures"));
+    let mut map_structures = use_signal(Vec::<api::MapStructureData>::new);
+    let mut heatmap_layer = use_signal(HeatmapLayer::load);
+    let mut heatmap = use_signal(Vec::<HeatCell>::new);
+    let mut terrain_mask = use_signal(|| None::<TerrainMask>);
+
+    // A plan opened from a read-only link (`?readonly=1`) stays read-only
+    let view_only = use_hook(|| {
+        plan_id.is_some()
+            && web_sys::window()
+                .and_then(|w| w.location().search().ok())
+                .is_some_and(|search| api::is_readonly_query(&search))
+    });
+    // Advisory edit lock on the page's plan: while another session holds it
+    // that plan is read-only
+    let mut locked_out = use_signal(|| false);
+    let lock_session = use_hook(api::lock_session_id);
+    let lock_plan_id = plan_id.clone();
+    let read_only = use_memo({
+        let lock_plan_id = lock_plan_id.clone();
+        move || {
+            let showing_locked_plan =
+                saved_plan.read().as_ref().map(|(id, _)| id) == lock_plan_id.as_ref();
+            view_only || (*locked_out.read() && showing_locked_plan)
+        }
+    });
+
+    // Plans open in tabs; the active one lives in the signals above
+    let mut tabs = use_signal(|| vec![PlanTab::default()]);
+    let mut active_tab = use_signal(|| 0usize);
+
+    // Plan carried whole in a stateless link's fragment, put on the map on load
+    let shared_plan = use_hook(|| {
+        lock_plan_id
+            .is_none()
+            .then(|| web_sys::window().and_then(|w| w.location().hash().ok()))
+            .flatten()
+            .and_then(|hash| persistence::shared_plan_from_hash(&hash))
+    });
+
+    // Autosaved session from last time, offered back on a fresh planner
+    let mut session_offer = use_signal(|| {
+        (lock_plan_id.is_none() && shared_plan.is_none())
+            .then(persistence::load_session)
+            .flatten()
+            .filter(|s| !s.is_empty())
+    });
 
-    // Faction theme
-    let mut faction = use_signal(load_saved_faction);
+    // Set when the loaded plan was saved with older weapon data
+    let mut weapon_data_notice = use_signal(|| None::<String>);
 
     // Auto-focus .app div on mount so keyboard shortcuts work immediately
     use_effect(|| {
@@ -175,76 +932,239 @@ pub fn Planner(plan_id: Option<String>) -> Element {
         }
     });
 
+    // Token for plans this browser created; needed to update them or take the lock
+    let edit_token = use_hook(|| lock_plan_id.as_deref().and_then(api::load_edit_token));
+    {
+        let plan_id = lock_plan_id.clone();
+        let session = lock_session.clone();
+        use_drop(move || {
+            if let Some(id) = &plan_id {
+                api::release_plan_lock_fire(id, &session);
+            }
+        });
+    }
+
     // Load plan if we have an ID
+    let loader_session = lock_session.clone();
+    let loader_token = edit_token.clone();
     let _plan_loader = use_resource(move || {
         let plan_id = plan_id.clone();
+        let session = loader_session.clone();
+        let token = loader_token.clone();
+        // Stored positions are in meters; placing them needs the map's image size
+        let maps = maps_resource.read().clone();
         async move {
+            if maps.is_none() {
+                return;
+            }
             if let Some(id) = plan_id {
                 if let Ok(Some(plan)) = api::fetch_plan(&id).await {
-                    selected_map.set(plan.map_id);
+                    selected_map.set(plan.map_id.clone());
                     if let Some(first) = plan.weapon_ids.first() {
                         selected_weapon.set(first.clone());
                     }
-                    let num_guns = plan.gun_positions.len();
-                    let num_targets = plan.target_positions.len();
-                    gun_weapon_ids.set(plan.weapon_ids);
-                    plan_name.set(plan.name);
-                    // Plan stores meter coordinates, convert to image pixels
-                    gun_positions.set(
-                        plan.gun_positions
-                            .iter()
-                            .map(|p| coords::meters_to_map_px(p.x, p.y))
-                            .collect(),
-                    );
-                    target_positions.set(
-                        plan.target_positions
-                            .iter()
-                            .map(|p| coords::meters_to_map_px(p.x, p.y))
-                            .collect(),
-                    );
-                    spotter_positions.set(
-                        plan.spotter_positions
-                            .iter()
-                            .map(|p| coords::meters_to_map_px(p.x, p.y))
-                            .collect(),
-                    );
-                    // Load explicit pairings, or fall back to index-based for old plans
-                    if plan.gun_target_indices.is_empty() {
-                        // Legacy plan: pair by index
-                        let indices: Vec<Option<usize>> = (0..num_guns)
-                            .map(|i| if i < num_targets { Some(i) } else { None })
-                            .collect();
-                        gun_target_indices.set(indices);
+                    plan_name.set(plan.name.clone());
+                    if plan.protected && token.is_none() {
+                        // Someone else's plan: edits stay local until saved as a copy
+                        saves_copy.set(true);
                     } else {
-                        gun_target_indices.set(
-                            plan.gun_target_indices
-                                .iter()
-                                .map(|o| o.map(|v| v as usize))
-                                .collect(),
-                        );
+                        saved_plan.set(Some((plan.id.clone(), plan.version)));
+                        // A read-only view never takes the lock, so it can't keep an editor out
+                        if !view_only {
+                            spawn(hold_plan_lock(id.clone(), session, token, locked_out));
+                        }
                     }
-                    if let Some(dir) = plan.wind_direction {
-                        wind_direction.set(Some(dir));
+                    calibration.set(plan.calibration.as_ref().and_then(|c| c.to_calibration()));
+                    if plan.weapon_data_version.is_some() {
+                        if let Ok(current) = api::fetch_game_version().await {
+                            weapon_data_notice.set(plan.weapon_data_notice(current.as_deref()));
+                        }
                     }
-                    wind_strength.set(plan.wind_strength);
+                    let snap = snapshot_from_plan(&plan, plan_scale_of(&maps, &plan));
+                    restore_snapshot(
+                        &snap,
+                        &mut gun_positions,
+                        &mut target_positions,
+                        &mut spotter_positions,
+                        &mut enemy_positions,
+                        &mut fob_positions,
+                        &mut bunker_positions,
+                        &mut no_fire_positions,
+                        &mut no_fire_radii,
+                        &mut gun_weapon_ids,
+                        &mut gun_target_indices,
+                        &mut marker_labels,
+                        &mut gun_salvo_sizes,
+                        &mut gun_alternates,
+                        &mut target_statuses,
+                        &mut shot_log,
+                        &mut registrations,
+                        &mut gun_ammo,
+                        &mut range_rings,
+                        &mut wind_direction,
+                        &mut wind_strength,
+                    );
+                }
+            }
+        }
+    });
+
+    // Open the plan from a stateless link, then drop the fragment so a reload
+    // falls back to the autosave rather than the link as it was
+    use_effect({
+        let shared_plan = shared_plan.clone();
+        move || {
+            let Some(plan) = shared_plan.clone() else {
+                return;
+            };
+            selected_map.set(plan.map_id);
+            calibration.set(plan.calibration);
+            plan_name.set(plan.plan_name);
+            restore_snapshot(
+                &PlanSnapshot::from(plan.state),
+                &mut gun_positions,
+                &mut target_positions,
+                &mut spotter_positions,
+                &mut enemy_positions,
+                &mut fob_positions,
+                &mut bunker_positions,
+                &mut no_fire_positions,
+                &mut no_fire_radii,
+                &mut gun_weapon_ids,
+                &mut gun_target_indices,
+                &mut marker_labels,
+                &mut gun_salvo_sizes,
+                &mut gun_alternates,
+                &mut target_statuses,
+                &mut shot_log,
+                &mut registrations,
+                &mut gun_ammo,
+                &mut range_rings,
+                &mut wind_direction,
+                &mut wind_strength,
+            );
+            if let Some(window) = web_sys::window() {
+                let path = window.location().pathname().unwrap_or_default();
+                if let Ok(history) = window.history() {
+                    let _ = history.replace_state_with_url(
+                        &wasm_bindgen::JsValue::NULL,
+                        "",
+                        Some(&path),
+                    );
+                }
+            }
+        }
+    });
+
+    // Live War API structures for the current map, while the overlay is on
+    let _structures_loader = use_resource(move || {
+        let map = selected_map.read().clone();
+        let show = *show_structures.read();
+        async move {
+            let structures = if show && !map.is_empty() {
+                api::fetch_map_structures(&map).await.unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            map_structures.set(structures);
+        }
+    });
+
+    // Water and impassable ground on the current map, for placement warnings
+    let _terrain_loader = use_resource(move || {
+        let map = selected_map.read().clone();
+        async move {
+            let mask = if map.is_empty() {
+                None
+            } else {
+                api::fetch_terrain_mask(&map).await.ok().flatten()
+            };
+            terrain_mask.set(mask);
+        }
+    });
+    let terrain_warnings = use_memo(move || {
+        terrain_warning_messages(
+            &gun_positions.read(),
+            &spotter_positions.read(),
+            *map_scale.read(),
+            terrain_mask.read().as_ref(),
+            &marker_labels.read(),
+        )
+    });
+
+    // Community placement counts for the current map, while the heatmap is on
+    let _heatmap_loader = use_resource(move || {
+        let map = selected_map.read().clone();
+        let layer = *heatmap_layer.read();
+        async move {
+            let cells = match layer {
+                HeatmapLayer::Off => Vec::new(),
+                _ if map.is_empty() => Vec::new(),
+                _ => {
+                    let data = api::fetch_placement_heatmap(&map).await.unwrap_or_default();
+                    let cells = if layer == HeatmapLayer::Guns {
+                        data.guns
+                    } else {
+                        data.targets
+                    };
+                    cells.iter().map(|c| c.to_heat_cell()).collect()
+                }
+            };
+            heatmap.set(cells);
+        }
+    });
+
+    // Freshest shared wind report for the map; pre-fills the wind when none is set
+    let mut wind_report = use_signal(|| None::<api::WindReportData>);
+    let _wind_report_loader = use_resource(move || {
+        let map = selected_map.read().clone();
+        async move {
+            let report = if map.is_empty() {
+                None
+            } else {
+                api::fetch_wind_report(&map).await.ok().flatten()
+            };
+            if let Some(r) = &report {
+                if wind_direction.peek().is_none() && !*read_only.peek() {
+                    wind_direction.set(Some(r.direction));
+                    wind_strength.set(r.strength);
                 }
             }
+            wind_report.set(report);
         }
     });
 
-    // Auto-calculate when inputs change — use explicit pairings
+    // Auto-calculate when inputs change — use explicit pairings. Each change
+    // bumps the generation; a run that sees a newer one stops without
+    // touching the solutions, so only the latest inputs land.
+    let mut calc_generation = use_signal(|| 0u64);
     let _calc_effect = use_resource(move || {
         let gun_wids = gun_weapon_ids.read().clone();
+        let ammo = gun_ammo.read().clone();
         let guns = gun_positions.read().clone();
         let targets = target_positions.read().clone();
         let pairings = gun_target_indices.read().clone();
         let w_dir = *wind_direction.read();
         let w_str = *wind_strength.read();
+        let scale = *map_scale.read();
+        let profile = *weapon_profile.read();
+        // To work solutions out in the browser when the server can't be reached
+        let weapons = match &*weapons_resource.read() {
+            Some(Ok(list)) => list.clone(),
+            _ => Vec::new(),
+        };
+        let generation = *calc_generation.peek() + 1;
+        calc_generation.set(generation);
+        let stale = move || *calc_generation.peek() != generation;
         async move {
             if guns.is_empty() {
                 firing_solutions.set(vec![]);
                 return;
             }
+            gloo_timers::future::TimeoutFuture::new(CALC_DEBOUNCE_MS).await;
+            if stale() {
+                return;
+            }
             let mut results = Vec::with_capacity(guns.len());
             for (i, g_px) in guns.iter().enumerate() {
                 let wid = gun_wids.get(i).cloned().unwrap_or_default();
@@ -255,17 +1175,82 @@ pub fn Planner(plan_id: Option<String>) -> Element {
                     continue;
                 }
                 let t_px = t_px.unwrap();
-                let (gx, gy) = coords::map_px_to_meters(g_px.0, g_px.1);
-                let (tx, ty) = coords::map_px_to_meters(t_px.0, t_px.1);
-                match api::calculate(gx, gy, tx, ty, &wid, w_dir, Some(w_str)).await {
+                let (gx, gy) = coords::map_px_to_meters(g_px.0, g_px.1, scale);
+                let (tx, ty) = coords::map_px_to_meters(t_px.0, t_px.1, scale);
+                let shell = models::gun_ammo(&ammo, i);
+                let solution =
+                    api::calculate(gx, gy, tx, ty, &wid, shell, w_dir, Some(w_str), profile).await;
+                if stale() {
+                    return;
+                }
+                match solution {
                     Ok(sol) => results.push(Some(sol)),
-                    Err(_) => results.push(None),
+                    Err(_) => results.push(offline::local_solution(
+                        &weapons,
+                        &wid,
+                        shell,
+                        (gx, gy),
+                        (tx, ty),
+                        w_dir,
+                        w_str,
+                    )),
                 }
             }
             firing_solutions.set(results);
         }
     });
 
+    // Autosave the session locally once changes settle, unless a restore is still on offer.
+    // A read-only view has nothing of the user's to keep.
+    let mut autosave_generation = use_signal(|| 0u64);
+    use_effect(move || {
+        if view_only {
+            return;
+        }
+        let snap = capture_snapshot(
+            &gun_positions,
+            &target_positions,
+            &spotter_positions,
+            &enemy_positions,
+            &fob_positions,
+            &bunker_positions,
+            &no_fire_positions,
+            &no_fire_radii,
+            &gun_weapon_ids,
+            &gun_target_indices,
+            &marker_labels,
+            &gun_salvo_sizes,
+            &gun_alternates,
+            &target_statuses,
+            &shot_log,
+            &registrations,
+            &gun_ammo,
+            &range_rings,
+            &wind_direction,
+            &wind_strength,
+        );
+        let session = StoredSession::new(
+            &selected_map.read(),
+            calibration.read().as_ref(),
+            &plan_name.read(),
+            saved_plan.read().clone(),
+            &snap,
+            &undo_stack.read(),
+            &redo_stack.read(),
+        );
+        if session_offer.read().is_some() {
+            return;
+        }
+        let generation = *autosave_generation.peek() + 1;
+        autosave_generation.set(generation);
+        spawn(async move {
+            gloo_timers::future::TimeoutFuture::new(AUTOSAVE_DELAY_MS).await;
+            if *autosave_generation.peek() == generation {
+                persistence::save_session(&session);
+            }
+        });
+    });
+
     // Wait for initial data
     let maps_state = maps_resource.read();
     let weapons_state = weapons_resource.read();
@@ -291,118 +1276,1069 @@ pub fn Planner(plan_id: Option<String>) -> Element {
             div { class: "error-state",
                 p { "Failed to load weapons: {e}" }
                 button { onclick: move |_| weapons_resource.restart(), "Retry" }
+                if *weapon_profile.read() != WeaponProfile::Live {
+                    // The server may have dropped the Devbranch figures since
+                    button {
+                        class: "secondary",
+                        onclick: move |_| {
+                            settings.write().weapon_profile = WeaponProfile::Live;
+                            save_settings(&settings.read());
+                        },
+                        "Use Live figures"
+                    }
+                }
             }
         };
     }
     let maps = maps_state.as_ref().unwrap().as_ref().unwrap().clone();
     let weapons = weapons_state.as_ref().unwrap().as_ref().unwrap().clone();
 
-    // Set default map if none selected
-    if selected_map.read().is_empty() && !maps.is_empty() {
-        selected_map.set(maps[0].file_name.clone());
+    // Open the map last used on this device, else the first one
+    if selected_map.read().is_empty() {
+        if let Some(map) = default_map(&maps, load_last_map().as_deref()) {
+            selected_map.set(map);
+        }
     }
 
     let current_map = selected_map.read().clone();
+    let custom_image_url = calibration
+        .read()
+        .as_ref()
+        .map(|c| c.image_url.clone())
+        .unwrap_or_default();
+    let current_default_view = maps
+        .iter()
+        .find(|m| m.file_name == current_map)
+        .and_then(|m| m.default_view);
 
-    // Compute accuracy radii in image pixels for the map overlay (one per gun, using pairings)
-    let accuracy_radii_px = use_memo(move || {
+    // Compute shell spreads in image pixels for the map overlay (one per gun, using pairings)
+    let spreads_px = use_memo(move || {
+        let scale = *map_scale.read();
         firing_solutions
             .read()
             .iter()
             .map(|sol| {
-                sol.as_ref()
-                    .map(|s| coords::meters_to_image_px(s.accuracy_radius))
+                sol.as_ref().map(|s| {
+                    ShellSpread::from_solution(s.accuracy_radius, s.impact_zone.as_ref(), scale)
+                })
             })
             .collect::<Vec<_>>()
     });
 
+    // No-fire zones each gun's shells could land in
+    let danger_close = use_memo(move || {
+        let scale = *map_scale.read();
+        let zones = intel_in_meters(
+            &[],
+            &[],
+            &[],
+            &no_fire_positions.read(),
+            &no_fire_radii.read(),
+            scale,
+        )
+        .no_fire_circles();
+        danger_close_per_gun(
+            &firing_solutions.read(),
+            &gun_target_indices.read(),
+            &target_positions.read(),
+            &zones,
+            scale,
+        )
+    });
+
     // Closure to push undo snapshot from planner-level code
     let mut push_snapshot = move || {
         let snap = capture_snapshot(
             &gun_positions,
             &target_positions,
             &spotter_positions,
+            &enemy_positions,
+            &fob_positions,
+            &bunker_positions,
+            &no_fire_positions,
+            &no_fire_radii,
             &gun_weapon_ids,
             &gun_target_indices,
+            &marker_labels,
+            &gun_salvo_sizes,
+            &gun_alternates,
+            &target_statuses,
+            &shot_log,
+            &registrations,
+            &gun_ammo,
+            &range_rings,
             &wind_direction,
             &wind_strength,
         );
         push_undo(&mut undo_stack, &mut redo_stack, snap);
     };
 
+    // Place or move a marker at a typed-in position (map px)
+    let place_typed = move |(action, pos): (PlacementAction, (f64, f64))| {
+        push_snapshot();
+        match action {
+            PlacementAction::Place(MarkerKind::Gun) => {
+                let slug = selected_weapon.read().clone();
+                gun_positions.write().push(pos);
+                gun_weapon_ids.write().push(slug.clone());
+                let unpaired = find_first_unpaired_target(
+                    &gun_target_indices.read(),
+                    target_positions.read().len(),
+                );
+                gun_target_indices.write().push(unpaired);
+                api::track_gun_placement_fire(&slug, Some(&selected_map.read()));
+            }
+            PlacementAction::Place(MarkerKind::Target) => {
+                target_positions.write().push(pos);
+                let ti = target_positions.read().len() - 1;
+                pair_first_unpaired_gun(&mut gun_target_indices.write(), ti);
+                api::track_target_placement_fire(&selected_map.read());
+            }
+            PlacementAction::Place(MarkerKind::Spotter) => {
+                spotter_positions.write().push(pos);
+                api::track_spotter_placement_fire(&selected_map.read());
+            }
+            PlacementAction::Place(MarkerKind::Enemy) => {
+                enemy_positions.write().push(pos);
+            }
+            PlacementAction::Place(MarkerKind::Fob) => {
+                fob_positions.write().push(pos);
+            }
+            PlacementAction::Place(MarkerKind::Bunker) => {
+                bunker_positions.write().push(pos);
+            }
+            PlacementAction::Place(MarkerKind::NoFire) => {
+                no_fire_positions.write().push(pos);
+            }
+            PlacementAction::MoveSelected => {
+                if let Some(marker) = *selected_marker.read() {
+                    set_marker_position(
+                        marker,
+                        pos,
+                        &mut gun_positions,
+                        &mut target_positions,
+                        &mut spotter_positions,
+                        &mut enemy_positions,
+                        &mut fob_positions,
+                        &mut bunker_positions,
+                        &mut no_fire_positions,
+                    );
+                }
+            }
+        }
+    };
+
+    // Switch to (or off) a custom map image. Markers, and the undo history,
+    // move to the new image's pixels so they stay put on the map.
+    let set_calibration = move |next: Option<MapCalibration>| {
+        let from = *map_scale.peek();
+        let to = next
+            .as_ref()
+            .and_then(MapCalibration::scale)
+            .unwrap_or_else(|| map_scale_of(&maps_resource.peek(), &selected_map.peek()));
+        let current = capture_snapshot(
+            &gun_positions,
+            &target_positions,
+            &spotter_positions,
+            &enemy_positions,
+            &fob_positions,
+            &bunker_positions,
+            &no_fire_positions,
+            &no_fire_radii,
+            &gun_weapon_ids,
+            &gun_target_indices,
+            &marker_labels,
+            &gun_salvo_sizes,
+            &gun_alternates,
+            &target_statuses,
+            &shot_log,
+            &registrations,
+            &gun_ammo,
+            &range_rings,
+            &wind_direction,
+            &wind_strength,
+        );
+        restore_snapshot(
+            &rescale_snapshot(&current, from, to),
+            &mut gun_positions,
+            &mut target_positions,
+            &mut spotter_positions,
+            &mut enemy_positions,
+            &mut fob_positions,
+            &mut bunker_positions,
+            &mut no_fire_positions,
+            &mut no_fire_radii,
+            &mut gun_weapon_ids,
+            &mut gun_target_indices,
+            &mut marker_labels,
+            &mut gun_salvo_sizes,
+            &mut gun_alternates,
+            &mut target_statuses,
+            &mut shot_log,
+            &mut registrations,
+            &mut gun_ammo,
+            &mut range_rings,
+            &mut wind_direction,
+            &mut wind_strength,
+        );
+        for mut stack in [undo_stack, redo_stack] {
+            for snap in stack.write().iter_mut() {
+                *snap = rescale_snapshot(snap, from, to);
+            }
+        }
+        calibration.set(next);
+    };
+
     // Action closures shared between keyboard handler and toolbar buttons
     let mut do_undo = move || {
         if let Some(snap) = undo_stack.write().pop() {
             let current = capture_snapshot(
-                &gun_positions, &target_positions, &spotter_positions,
-                &gun_weapon_ids, &gun_target_indices,
-                &wind_direction, &wind_strength,
+                &gun_positions,
+                &target_positions,
+                &spotter_positions,
+                &enemy_positions,
+                &fob_positions,
+                &bunker_positions,
+                &no_fire_positions,
+                &no_fire_radii,
+                &gun_weapon_ids,
+                &gun_target_indices,
+                &marker_labels,
+                &gun_salvo_sizes,
+                &gun_alternates,
+                &target_statuses,
+                &shot_log,
+                &registrations,
+                &gun_ammo,
+                &range_rings,
+                &wind_direction,
+                &wind_strength,
             );
             redo_stack.write().push(current);
             restore_snapshot(
                 &snap,
-                &mut gun_positions, &mut target_positions, &mut spotter_positions,
-                &mut gun_weapon_ids, &mut gun_target_indices,
-                &mut wind_direction, &mut wind_strength,
+                &mut gun_positions,
+                &mut target_positions,
+                &mut spotter_positions,
+                &mut enemy_positions,
+                &mut fob_positions,
+                &mut bunker_positions,
+                &mut no_fire_positions,
+                &mut no_fire_radii,
+                &mut gun_weapon_ids,
+                &mut gun_target_indices,
+                &mut marker_labels,
+                &mut gun_salvo_sizes,
+                &mut gun_alternates,
+                &mut target_statuses,
+                &mut shot_log,
+                &mut registrations,
+                &mut gun_ammo,
+                &mut range_rings,
+                &mut wind_direction,
+                &mut wind_strength,
             );
             selected_marker.set(None);
+            group_selection.set(Vec::new());
         }
     };
 
     let mut do_redo = move || {
         if let Some(snap) = redo_stack.write().pop() {
             let current = capture_snapshot(
-                &gun_positions, &target_positions, &spotter_positions,
-                &gun_weapon_ids, &gun_target_indices,
-                &wind_direction, &wind_strength,
+                &gun_positions,
+                &target_positions,
+                &spotter_positions,
+                &enemy_positions,
+                &fob_positions,
+                &bunker_positions,
+                &no_fire_positions,
+                &no_fire_radii,
+                &gun_weapon_ids,
+                &gun_target_indices,
+                &marker_labels,
+                &gun_salvo_sizes,
+                &gun_alternates,
+                &target_statuses,
+                &shot_log,
+                &registrations,
+                &gun_ammo,
+                &range_rings,
+                &wind_direction,
+                &wind_strength,
             );
             undo_stack.write().push(current);
             restore_snapshot(
                 &snap,
-                &mut gun_positions, &mut target_positions, &mut spotter_positions,
-                &mut gun_weapon_ids, &mut gun_target_indices,
-                &mut wind_direction, &mut wind_strength,
+                &mut gun_positions,
+                &mut target_positions,
+                &mut spotter_positions,
+                &mut enemy_positions,
+                &mut fob_positions,
+                &mut bunker_positions,
+                &mut no_fire_positions,
+                &mut no_fire_radii,
+                &mut gun_weapon_ids,
+                &mut gun_target_indices,
+                &mut marker_labels,
+                &mut gun_salvo_sizes,
+                &mut gun_alternates,
+                &mut target_statuses,
+                &mut shot_log,
+                &mut registrations,
+                &mut gun_ammo,
+                &mut range_rings,
+                &mut wind_direction,
+                &mut wind_strength,
             );
             selected_marker.set(None);
+            group_selection.set(Vec::new());
         }
     };
 
     let mut do_delete_selected = move || {
+        let group = group_selection.read().clone();
         let cur_sel = *selected_marker.read();
-        if let Some(sm) = cur_sel {
+        if !group.is_empty() {
+            push_snapshot();
+            remove_group(
+                &group,
+                &mut gun_positions,
+                &mut target_positions,
+                &mut spotter_positions,
+                &mut enemy_positions,
+                &mut fob_positions,
+                &mut bunker_positions,
+                &mut no_fire_positions,
+                &mut no_fire_radii,
+                &mut gun_weapon_ids,
+                &mut gun_target_indices,
+                &mut marker_labels,
+                &mut gun_salvo_sizes,
+                &mut gun_alternates,
+                &mut target_statuses,
+                &mut shot_log,
+                &mut registrations,
+                &mut gun_ammo,
+                &mut range_rings,
+            );
+        } else if let Some(sm) = cur_sel {
             push_snapshot();
             remove_marker(
-                sm.kind, sm.index,
-                &mut gun_positions, &mut target_positions, &mut spotter_positions,
-                &mut gun_weapon_ids, &mut gun_target_indices,
+                sm.kind,
+                sm.index,
+                &mut gun_positions,
+                &mut target_positions,
+                &mut spotter_positions,
+                &mut enemy_positions,
+                &mut fob_positions,
+                &mut bunker_positions,
+                &mut no_fire_positions,
+                &mut no_fire_radii,
+                &mut gun_weapon_ids,
+                &mut gun_target_indices,
+                &mut marker_labels,
+                &mut gun_salvo_sizes,
+                &mut gun_alternates,
+                &mut target_statuses,
+                &mut shot_log,
+                &mut registrations,
+                &mut gun_ammo,
+                &mut range_rings,
             );
-            selected_marker.set(None);
+        } else {
+            return;
         }
+        selected_marker.set(None);
+        group_selection.set(Vec::new());
     };
 
-    let app_class = if *faction.read() == Faction::Colonial {
-        "app colonial"
-    } else {
-        "app"
+    // Arrow keys shift the multi-selection (or the selected marker). A held key
+    // repeats without new snapshots, so the whole nudge undoes in one step.
+    let mut do_nudge = move |delta: (f64, f64), repeat: bool| -> bool {
+        let mut group = group_selection.read().clone();
+        if group.is_empty() {
+            group.extend(*selected_marker.read());
+        }
+        if group.is_empty() {
+            return false;
+        }
+        if !repeat {
+            push_snapshot();
+        }
+        translate_group(
+            &group,
+            delta,
+            *map_scale.read(),
+            &mut gun_positions,
+            &mut target_positions,
+            &mut spotter_positions,
+            &mut enemy_positions,
+            &mut fob_positions,
+            &mut bunker_positions,
+            &mut no_fire_positions,
+        );
+        true
     };
 
-    rsx! {
-        div {
-            class: "{app_class}",
-            tabindex: "0",
+    // Picking a single marker ends the multi-selection
+    use_effect(move || {
+        if selected_marker().is_some() {
+            group_selection.set(Vec::new());
+        }
+    });
 
-            onkeydown: move |evt: Event<KeyboardData>| {
-                let key = evt.key();
-                let mods = evt.data().modifiers();
-                let ctrl_or_cmd = mods.contains(Modifiers::CONTROL) || mods.contains(Modifiers::META);
-                let shift = mods.contains(Modifiers::SHIFT);
+    // Size of the plan as the save mutation would send it, shown under the save button
+    let payload_size = use_memo(move || {
+        let scale = *map_scale.read();
+        let to_meters = |positions: &[(f64, f64)]| -> Vec<(f64, f64)> {
+            positions
+                .iter()
+                .map(|p| coords::map_px_to_meters(p.0, p.1, scale))
+                .collect()
+        };
+        let variables = api::build_create_plan_variables(
+            &plan_name.read(),
+            &selected_map.read(),
+            &gun_weapon_ids.read(),
+            &gun_ammo.read(),
+            &to_meters(&gun_positions.read()),
+            &to_meters(&target_positions.read()),
+            &to_meters(&spotter_positions.read()),
+            &gun_target_indices.read(),
+            &marker_labels.read(),
+            &target_statuses.read(),
+            *wind_direction.read(),
+            Some(*wind_strength.read()),
+            calibration.read().as_ref(),
+            &shot_log.read(),
+            &registrations.read(),
+            &range_rings.read(),
+            &intel_in_meters(
+                &enemy_positions.read(),
+                &fob_positions.read(),
+                &bunker_positions.read(),
+                &no_fire_positions.read(),
+                &no_fire_radii.read(),
+                scale,
+            ),
+            *weapon_profile.read(),
+        );
+        api::payload_size(&variables)
+    });
 
-                // Skip most shortcuts when typing in an input
-                if is_input_focused() {
-                    if key == Key::Escape {
-                        // Blur the focused element
-                        if let Some(w) = web_sys::window() {
-                            if let Some(doc) = w.document() {
+    // Take in the server's answer to a save, sent now or from the offline queue
+    let mut finish_save = move |result: Result<api::UpdatePlanResult, String>| match result {
+        Ok(api::UpdatePlanResult {
+            plan: Some(plan), ..
+        }) => {
+            save_error.set(None);
+            if let Some(token) = &plan.edit_token {
+                api::save_edit_token(&plan.id, token);
+            }
+            saves_copy.set(false);
+            saved_plan.set(Some((plan.id.clone(), plan.version)));
+            plan_url.set(Some(share_url(&plan)));
+        }
+        Ok(api::UpdatePlanResult {
+            conflict: Some(conflict),
+            ..
+        }) => merge_conflict.set(Some(conflict)),
+        Ok(_) => save_error.set(Some("Failed to save: empty response".to_string())),
+        Err(e) => save_error.set(Some(format!("Failed to save: {e}"))),
+    };
+
+    // Save: update the stored plan if we have one (version-checked), else create it.
+    // `base_override` saves against a newer version, i.e. "keep mine" after a conflict.
+    let do_save = move |base_override: Option<u32>| {
+        let snap = capture_snapshot(
+            &gun_positions,
+            &target_positions,
+            &spotter_positions,
+            &enemy_positions,
+            &fob_positions,
+            &bunker_positions,
+            &no_fire_positions,
+            &no_fire_radii,
+            &gun_weapon_ids,
+            &gun_target_indices,
+            &marker_labels,
+            &gun_salvo_sizes,
+            &gun_alternates,
+            &target_statuses,
+            &shot_log,
+            &registrations,
+            &gun_ammo,
+            &range_rings,
+            &wind_direction,
+            &wind_strength,
+        );
+        let save = plan_save(
+            &plan_name.read(),
+            &selected_map.read(),
+            calibration.read().as_ref(),
+            &snap,
+            saved_plan.read().clone(),
+            base_override,
+            *map_scale.read(),
+            *weapon_profile.read(),
+        );
+        spawn(async move {
+            let result = api::save_plan(&save).await;
+            if result.is_err() && !offline::is_online() {
+                offline::queue_save(&save);
+                save_error.set(None);
+                save_queued.set(true);
+                return;
+            }
+            // This save supersedes one of the same plan still waiting in the queue
+            if offline::queued_save().is_some_and(|q| q.plan_id() == save.plan_id()) {
+                offline::clear_queued_save();
+                save_queued.set(false);
+            }
+            finish_save(result);
+        });
+    };
+
+    // Send a save made offline once the connection is back
+    use_future(move || async move {
+        loop {
+            gloo_timers::future::TimeoutFuture::new(offline::OUTBOX_RETRY_MS).await;
+            let Some(save) = offline::queued_save() else {
+                continue;
+            };
+            if !offline::is_online() {
+                continue;
+            }
+            let result = api::save_plan(&save).await;
+            if result.is_err() && !offline::is_online() {
+                continue;
+            }
+            offline::clear_queued_save();
+            save_queued.set(false);
+            let open_plan = saved_plan.peek().as_ref().map(|(id, _)| id.clone());
+            if save.plan_id() == open_plan.as_deref() {
+                finish_save(result);
+            } else if let Ok(api::UpdatePlanResult {
+                plan: Some(plan), ..
+            }) = &result
+            {
+                // Saved from another page: keep the right to edit it
+                if let Some(token) = &plan.edit_token {
+                    api::save_edit_token(&plan.id, token);
+                }
+            }
+        }
+    });
+
+    // Another version or plan to compare the map with, and what it's called
+    let mut compare_with = use_signal(|| None::<(String, PlanData)>);
+    let plan_changes = use_memo(move || match &*compare_with.read() {
+        Some((_, other)) => changes_since(
+            other,
+            &gun_positions.read(),
+            &target_positions.read(),
+            &spotter_positions.read(),
+            *map_scale.read(),
+        ),
+        None => Vec::new(),
+    });
+    // Plans on another map can't be compared; stop once the map changes
+    use_effect(move || {
+        let map = selected_map.read().clone();
+        if compare_with
+            .peek()
+            .as_ref()
+            .is_some_and(|(_, p)| p.map_id != map)
+        {
+            compare_with.set(None);
+        }
+    });
+    let compare_link = move |link: String| {
+        let Some(id) = api::plan_id_from_link(&link) else {
+            save_error.set(Some("That doesn't look like a plan link.".to_string()));
+            return;
+        };
+        spawn(async move {
+            match api::fetch_plan(&id).await {
+                Ok(Some(plan)) if plan.map_id != *selected_map.peek() => {
+                    save_error.set(Some("That plan is on another map.".to_string()));
+                }
+                Ok(Some(plan)) => {
+                    save_error.set(None);
+                    let name = if plan.name.is_empty() {
+                        "the other plan".to_string()
+                    } else {
+                        format!("\"{}\"", plan.name)
+                    };
+                    compare_with.set(Some((name, plan)));
+                }
+                Ok(None) => save_error.set(Some("Plan to compare not found.".to_string())),
+                Err(e) => save_error.set(Some(format!("Failed to load plan to compare: {e}"))),
+            }
+        });
+    };
+
+    // Restore an earlier stored version; the server saves it as the newest one
+    let restore_version = move |version: u32| {
+        let Some((id, _)) = saved_plan.read().clone() else {
+            return;
+        };
+        spawn(async move {
+            let token = api::load_edit_token(&id);
+            match api::restore_plan_version(&id, version, token.as_deref()).await {
+                Ok(plan) => {
+                    save_error.set(None);
+                    push_snapshot();
+                    let scale = plan_scale_of(&maps_resource.peek(), &plan);
+                    let snap = snapshot_from_plan(&plan, scale);
+                    restore_snapshot(
+                        &snap,
+                        &mut gun_positions,
+                        &mut target_positions,
+                        &mut spotter_positions,
+                        &mut enemy_positions,
+                        &mut fob_positions,
+                        &mut bunker_positions,
+                        &mut no_fire_positions,
+                        &mut no_fire_radii,
+                        &mut gun_weapon_ids,
+                        &mut gun_target_indices,
+                        &mut marker_labels,
+                        &mut gun_salvo_sizes,
+                        &mut gun_alternates,
+                        &mut target_statuses,
+                        &mut shot_log,
+                        &mut registrations,
+                        &mut gun_ammo,
+                        &mut range_rings,
+                        &mut wind_direction,
+                        &mut wind_strength,
+                    );
+                    plan_name.set(plan.name.clone());
+                    selected_map.set(plan.map_id.clone());
+                    calibration.set(plan.calibration.as_ref().and_then(|c| c.to_calibration()));
+                    selected_marker.set(None);
+                    group_selection.set(Vec::new());
+                    saved_plan.set(Some((plan.id.clone(), plan.version)));
+                }
+                Err(e) => save_error.set(Some(format!("Failed to restore: {e}"))),
+            }
+        });
+    };
+
+    // Fork the stored plan (ours, or the one we're viewing) and open the copy.
+    // A fresh page load picks up the copy's edit token and lock.
+    let fork_source = saved_plan
+        .read()
+        .as_ref()
+        .map(|(id, _)| id.clone())
+        .or_else(|| lock_plan_id.clone());
+    let fork_plan = {
+        let source = fork_source.clone();
+        move |_| {
+            let Some(id) = source.clone() else {
+                return;
+            };
+            let name = format!("{} (copy)", plan_name.read().trim());
+            spawn(async move {
+                match api::fork_plan(&id, Some(&name)).await {
+                    Ok(plan) => {
+                        if let Some(token) = &plan.edit_token {
+                            api::save_edit_token(&plan.id, token);
+                        }
+                        if let Some(window) = web_sys::window() {
+                            let origin = window.location().origin().unwrap_or_default();
+                            let _ = window
+                                .location()
+                                .set_href(&api::build_plan_url(&origin, &plan.id));
+                        }
+                    }
+                    Err(e) => save_error.set(Some(format!("Failed to fork: {e}"))),
+                }
+            });
+        }
+    };
+
+    // A link that carries the plan itself; works without the server
+    let mut stateless_url = use_signal(|| None::<String>);
+    let copy_stateless_link = move |_| {
+        let snap = capture_snapshot(
+            &gun_positions,
+            &target_positions,
+            &spotter_positions,
+            &enemy_positions,
+            &fob_positions,
+            &bunker_positions,
+            &no_fire_positions,
+            &no_fire_radii,
+            &gun_weapon_ids,
+            &gun_target_indices,
+            &marker_labels,
+            &gun_salvo_sizes,
+            &gun_alternates,
+            &target_statuses,
+            &shot_log,
+            &registrations,
+            &gun_ammo,
+            &range_rings,
+            &wind_direction,
+            &wind_strength,
+        );
+        let plan = SharedPlan::new(
+            &selected_map.read(),
+            calibration.read().as_ref(),
+            &plan_name.read(),
+            &snap,
+        );
+        let origin = web_sys::window()
+            .and_then(|w| w.location().origin().ok())
+            .unwrap_or_default();
+        let url = persistence::stateless_plan_url(&origin, &plan);
+        export::copy_to_clipboard(url.clone());
+        stateless_url.set(Some(url));
+    };
+
+    let resolve_conflict = move |choice: MergeChoice| {
+        let Some(conflict) = merge_conflict.read().clone() else {
+            return;
+        };
+        merge_conflict.set(None);
+        let theirs = &conflict.theirs;
+        match choice {
+            MergeChoice::KeepMine => do_save(Some(theirs.version)),
+            MergeChoice::KeepTheirs | MergeChoice::Merge => {
+                push_snapshot();
+                // Merging keeps my map and image; taking theirs switches to theirs
+                let scale = if choice == MergeChoice::Merge {
+                    *map_scale.read()
+                } else {
+                    plan_scale_of(&maps_resource.peek(), theirs)
+                };
+                let their_snap = snapshot_from_plan(theirs, scale);
+                let next = if choice == MergeChoice::Merge {
+                    let mine = capture_snapshot(
+                        &gun_positions,
+                        &target_positions,
+                        &spotter_positions,
+                        &enemy_positions,
+                        &fob_positions,
+                        &bunker_positions,
+                        &no_fire_positions,
+                        &no_fire_radii,
+                        &gun_weapon_ids,
+                        &gun_target_indices,
+                        &marker_labels,
+                        &gun_salvo_sizes,
+                        &gun_alternates,
+                        &target_statuses,
+                        &shot_log,
+                        &registrations,
+                        &gun_ammo,
+                        &range_rings,
+                        &wind_direction,
+                        &wind_strength,
+                    );
+                    merge_snapshots(&mine, &their_snap, scale)
+                } else {
+                    plan_name.set(theirs.name.clone());
+                    selected_map.set(theirs.map_id.clone());
+                    calibration.set(theirs.calibration.as_ref().and_then(|c| c.to_calibration()));
+                    their_snap
+                };
+                restore_snapshot(
+                    &next,
+                    &mut gun_positions,
+                    &mut target_positions,
+                    &mut spotter_positions,
+                    &mut enemy_positions,
+                    &mut fob_positions,
+                    &mut bunker_positions,
+                    &mut no_fire_positions,
+                    &mut no_fire_radii,
+                    &mut gun_weapon_ids,
+                    &mut gun_target_indices,
+                    &mut marker_labels,
+                    &mut gun_salvo_sizes,
+                    &mut gun_alternates,
+                    &mut target_statuses,
+                    &mut shot_log,
+                    &mut registrations,
+                    &mut gun_ammo,
+                    &mut range_rings,
+                    &mut wind_direction,
+                    &mut wind_strength,
+                );
+                selected_marker.set(None);
+                group_selection.set(Vec::new());
+                saved_plan.set(Some((theirs.id.clone(), theirs.version)));
+                if choice == MergeChoice::Merge {
+                    do_save(None);
+                }
+            }
+            MergeChoice::Cancel => {}
+        }
+    };
+
+    let app_class = theme.read().app_class(*faction.read());
+
+    // The active tab's state, to park it while another tab is shown
+    let current_tab = move || PlanTab {
+        key: tabs.read()[*active_tab.read()].key,
+        map_id: selected_map.read().clone(),
+        calibration: calibration.read().clone(),
+        plan_name: plan_name.read().clone(),
+        saved_plan: saved_plan.read().clone(),
+        plan_url: plan_url.read().clone(),
+        saves_copy: *saves_copy.read(),
+        state: capture_snapshot(
+            &gun_positions,
+            &target_positions,
+            &spotter_positions,
+            &enemy_positions,
+            &fob_positions,
+            &bunker_positions,
+            &no_fire_positions,
+            &no_fire_radii,
+            &gun_weapon_ids,
+            &gun_target_indices,
+            &marker_labels,
+            &gun_salvo_sizes,
+            &gun_alternates,
+            &target_statuses,
+            &shot_log,
+            &registrations,
+            &gun_ammo,
+            &range_rings,
+            &wind_direction,
+            &wind_strength,
+        ),
+        undo: undo_stack.read().clone(),
+        redo: redo_stack.read().clone(),
+    };
+    // Put a parked tab's state into the planner
+    let mut show_tab = move |tab: PlanTab| {
+        selected_map.set(tab.map_id);
+        calibration.set(tab.calibration);
+        plan_name.set(tab.plan_name);
+        saved_plan.set(tab.saved_plan);
+        plan_url.set(tab.plan_url);
+        saves_copy.set(tab.saves_copy);
+        restore_snapshot(
+            &tab.state,
+            &mut gun_positions,
+            &mut target_positions,
+            &mut spotter_positions,
+            &mut enemy_positions,
+            &mut fob_positions,
+            &mut bunker_positions,
+            &mut no_fire_positions,
+            &mut no_fire_radii,
+            &mut gun_weapon_ids,
+            &mut gun_target_indices,
+            &mut marker_labels,
+            &mut gun_salvo_sizes,
+            &mut gun_alternates,
+            &mut target_statuses,
+            &mut shot_log,
+            &mut registrations,
+            &mut gun_ammo,
+            &mut range_rings,
+            &mut wind_direction,
+            &mut wind_strength,
+        );
+        undo_stack.set(tab.undo);
+        redo_stack.set(tab.redo);
+        selected_marker.set(None);
+        group_selection.set(Vec::new());
+        compare_with.set(None);
+        save_error.set(None);
+    };
+    let switch_tab = move |index: usize| {
+        let current = *active_tab.read();
+        if index == current || index >= tabs.read().len() {
+            return;
+        }
+        let parked = current_tab();
+        tabs.write()[current] = parked;
+        let next = tabs.read()[index].clone();
+        show_tab(next);
+        active_tab.set(index);
+    };
+    let add_tab = move |_| {
+        let current = *active_tab.read();
+        let parked = current_tab();
+        tabs.write()[current] = parked;
+        let key = tabs.read().iter().map(|t| t.key).max().unwrap_or(0) + 1;
+        let tab = PlanTab::new(key, &selected_map.read());
+        tabs.write().push(tab.clone());
+        show_tab(tab);
+        active_tab.set(tabs.read().len() - 1);
+    };
+    let close_tab = move |index: usize| {
+        let current = *active_tab.read();
+        let count = tabs.read().len();
+        if count < 2 || index >= count {
+            return;
+        }
+        let has_markers = if index == current {
+            current_tab().has_markers()
+        } else {
+            tabs.read()[index].has_markers()
+        };
+        let confirmed = !has_markers
+            || web_sys::window()
+                .and_then(|w| {
+                    w.confirm_with_message("Close this plan? Changes that aren't saved are lost.")
+                        .ok()
+                })
+                .unwrap_or(true);
+        if !confirmed {
+            return;
+        }
+        if index == current {
+            // Show the neighbour that takes over the closed tab's place
+            let next = if index + 1 < count {
+                index + 1
+            } else {
+                index - 1
+            };
+            let tab = tabs.read()[next].clone();
+            show_tab(tab);
+        }
+        tabs.write().remove(index);
+        let active = if index < current || (index == current && index + 1 == count) {
+            current - 1
+        } else {
+            current
+        };
+        active_tab.set(active);
+    };
+    // Save every open plan: the active one as usual, the others from their tabs.
+    // Untouched new tabs are skipped.
+    let save_all = move |_| {
+        do_save(None);
+        let current = *active_tab.read();
+        let maps = maps_resource.peek().clone();
+        for (index, tab) in tabs.read().iter().enumerate() {
+            if index == current || (tab.saved_plan.is_none() && !tab.has_markers()) {
+                continue;
+            }
+            let save = plan_save(
+                &tab.plan_name,
+                &tab.map_id,
+                tab.calibration.as_ref(),
+                &tab.state,
+                tab.saved_plan.clone(),
+                None,
+                tab.scale(&maps),
+                *weapon_profile.read(),
+            );
+            let key = tab.key;
+            let name = tab.plan_name.clone();
+            spawn(async move {
+                let result = api::save_plan(&save).await;
+                // The tab may have been switched to while saving
+                if tabs.peek().get(*active_tab.peek()).map(|t| t.key) == Some(key) {
+                    finish_save(result);
+                    return;
+                }
+                match result {
+                    Ok(api::UpdatePlanResult {
+                        plan: Some(plan), ..
+                    }) => {
+                        if let Some(token) = &plan.edit_token {
+                            api::save_edit_token(&plan.id, token);
+                        }
+                        if let Some(tab) = tabs.write().iter_mut().find(|t| t.key == key) {
+                            tab.saved_plan = Some((plan.id.clone(), plan.version));
+                            tab.plan_url = Some(share_url(&plan));
+                            tab.saves_copy = false;
+                        }
+                    }
+                    Ok(api::UpdatePlanResult {
+                        conflict: Some(_), ..
+                    }) => save_error.set(Some(format!(
+                        "\"{name}\" was changed by someone else. Open its tab and save it to merge."
+                    ))),
+                    Ok(_) => {
+                        save_error.set(Some(format!("Failed to save \"{name}\": empty response")))
+                    }
+                    Err(e) => save_error.set(Some(format!("Failed to save \"{name}\": {e}"))),
+                }
+            });
+        }
+    };
+    let tab_labels: Vec<String> = {
+        let current = *active_tab.read();
+        tabs.read()
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                if index == current {
+                    tab_label(&plan_name.read(), &selected_map.read(), &maps)
+                } else {
+                    tab_label(&tab.plan_name, &tab.map_id, &maps)
+                }
+            })
+            .collect()
+    };
+
+    // Put back the autosaved session, undo history included
+    let restore_session = move |_| {
+        let Some(session) = session_offer.write().take() else {
+            return;
+        };
+        selected_map.set(session.map_id);
+        calibration.set(session.calibration);
+        plan_name.set(session.plan_name);
+        saved_plan.set(session.saved_plan);
+        restore_snapshot(
+            &PlanSnapshot::from(session.state),
+            &mut gun_positions,
+            &mut target_positions,
+            &mut spotter_positions,
+            &mut enemy_positions,
+            &mut fob_positions,
+            &mut bunker_positions,
+            &mut no_fire_positions,
+            &mut no_fire_radii,
+            &mut gun_weapon_ids,
+            &mut gun_target_indices,
+            &mut marker_labels,
+            &mut gun_salvo_sizes,
+            &mut gun_alternates,
+            &mut target_statuses,
+            &mut shot_log,
+            &mut registrations,
+            &mut gun_ammo,
+            &mut range_rings,
+            &mut wind_direction,
+            &mut wind_strength,
+        );
+        undo_stack.set(session.undo.into_iter().map(PlanSnapshot::from).collect());
+        redo_stack.set(session.redo.into_iter().map(PlanSnapshot::from).collect());
+        selected_marker.set(None);
+        group_selection.set(Vec::new());
+    };
+
+    rsx! {
+        div {
+            class: "{app_class}",
+            tabindex: "0",
+
+            onkeydown: move |evt: Event<KeyboardData>| {
+                let key = evt.key();
+                let mods = evt.data().modifiers();
+                let ctrl_or_cmd = mods.contains(Modifiers::CONTROL) || mods.contains(Modifiers::META);
+                let shift = mods.contains(Modifiers::SHIFT);
+
+                // Skip most shortcuts when typing in an input
+                if is_input_focused() {
+                    if key == Key::Escape {
+                        // Blur the focused element
+                        if let Some(w) = web_sys::window() {
+                            if let Some(doc) = w.document() {
                                 if let Some(active) = doc.active_element() {
                                     use wasm_bindgen::JsCast;
                                     if let Some(el) = active.dyn_ref::<web_sys::HtmlElement>() {
@@ -419,12 +2355,16 @@ pub fn Planner(plan_id: Option<String>) -> Element {
                     // Undo: Ctrl+Z / Cmd+Z (without Shift)
                     Key::Character(c) if c == "z" && ctrl_or_cmd && !shift => {
                         evt.prevent_default();
-                        do_undo();
+                        if !*read_only.read() {
+                            do_undo();
+                        }
                     }
                     // Redo: Ctrl+Shift+Z / Cmd+Shift+Z
                     Key::Character(c) if (c == "Z" || c == "z") && ctrl_or_cmd && shift => {
                         evt.prevent_default();
-                        do_redo();
+                        if !*read_only.read() {
+                            do_redo();
+                        }
                     }
                     // Placement modes
                     Key::Character(c) if c == "1" || c == "g" => {
@@ -436,28 +2376,60 @@ pub fn Planner(plan_id: Option<String>) -> Element {
                     Key::Character(c) if c == "3" || c == "s" => {
                         placement_mode.set(PlacementMode::Spotter);
                     }
+                    Key::Character(c) if c == "4" || c == "e" => {
+                        placement_mode.set(PlacementMode::Enemy);
+                    }
+                    Key::Character(c) if c == "5" || c == "m" => {
+                        placement_mode.set(PlacementMode::Measure);
+                    }
+                    Key::Character(c) if c == "6" || c == "d" => {
+                        placement_mode.set(PlacementMode::Displace);
+                    }
+                    Key::Character(c) if c == "7" || c == "f" => {
+                        placement_mode.set(PlacementMode::Fob);
+                    }
+                    Key::Character(c) if c == "8" || c == "b" => {
+                        placement_mode.set(PlacementMode::Bunker);
+                    }
+                    Key::Character(c) if c == "9" || c == "n" => {
+                        placement_mode.set(PlacementMode::NoFire);
+                    }
                     // Help overlay
                     Key::Character(c) if c == "h" || c == "?" => {
                         let current = *show_help.read();
                         show_help.set(!current);
                     }
-                    // Delete selected marker
-                    Key::Delete | Key::Backspace => {
+                    // Delete selected marker(s)
+                    Key::Delete | Key::Backspace if !*read_only.read() => {
                         do_delete_selected();
                     }
+                    // Nudge selected marker(s) 10 m, or 50 m with Shift
+                    Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight
+                        if !*read_only.read() =>
+                    {
+                        if let Some(delta) = nudge_delta(&key, shift, *map_scale.read()) {
+                            if do_nudge(delta, evt.data().is_auto_repeating()) {
+                                evt.prevent_default();
+                            }
+                        }
+                    }
                     // Reset zoom/pan
                     Key::Character(c) if c == "r" => {
                         let current = *reset_view_counter.read();
                         reset_view_counter.set(current + 1);
                     }
-                    // Escape: close sidebar, help, or deselect
+                    // Escape: close the panel sheet, help, or deselect
                     Key::Escape => {
-                        if *sidebar_open.read() {
-                            sidebar_open.set(false);
+                        if *show_settings.read() {
+                            show_settings.set(false);
+                        } else if sheet.read().is_open() {
+                            sheet.set(SheetState::Closed);
                         } else if *show_help.read() {
                             show_help.set(false);
                         } else {
                             selected_marker.set(None);
+                            group_selection.set(Vec::new());
+                            armed_template.set(None);
                         }
                     }
                     _ => {}
@@ -469,51 +2441,97 @@ pub fn Planner(plan_id: Option<String>) -> Element {
                 button {
                     class: "sidebar-toggle",
                     onclick: move |_| {
-                        let current = *sidebar_open.read();
-                        sidebar_open.set(!current);
+                        let current = *sheet.read();
+                        sheet.set(current.toggled());
                     },
                     "\u{2630}"
                 }
                 h1 { "Foxhole Artillery Planner" }
+                if let Some(progress) = progress_text(&target_statuses.read(), target_positions.read().len()) {
+                    span { class: "target-progress", role: "status", "{progress}" }
+                }
                 div { class: "placement-mode",
-                    button {
-                        class: if *placement_mode.read() == PlacementMode::Gun { "active-gun" } else { "" },
-                        onclick: move |_| placement_mode.set(PlacementMode::Gun),
-                        "Gun"
+                    if !view_only {
+                        button {
+                            class: if *placement_mode.read() == PlacementMode::Gun { "active-gun" } else { "" },
+                            onclick: move |_| placement_mode.set(PlacementMode::Gun),
+                            "Gun"
+                        }
+                        button {
+                            class: if *placement_mode.read() == PlacementMode::Target { "active-target" } else { "" },
+                            onclick: move |_| placement_mode.set(PlacementMode::Target),
+                            "Target"
+                        }
+                        button {
+                            class: if *placement_mode.read() == PlacementMode::Spotter { "active-spotter" } else { "" },
+                            onclick: move |_| placement_mode.set(PlacementMode::Spotter),
+                            "Spotter"
+                        }
+                        button {
+                            class: if *placement_mode.read() == PlacementMode::Enemy { "active-enemy" } else { "" },
+                            title: "Mark enemy gun positions for counter-battery",
+                            onclick: move |_| placement_mode.set(PlacementMode::Enemy),
+                            "Enemy"
+                        }
+                        button {
+                            class: if *placement_mode.read() == PlacementMode::Fob { "active-fob" } else { "" },
+                            title: "Mark forward operating bases",
+                            onclick: move |_| placement_mode.set(PlacementMode::Fob),
+                            "FOB"
+                        }
+                        button {
+                            class: if *placement_mode.read() == PlacementMode::Bunker { "active-bunker" } else { "" },
+                            title: "Mark observation bunkers",
+                            onclick: move |_| placement_mode.set(PlacementMode::Bunker),
+                            "Bunker"
+                        }
+                        button {
+                            class: if *placement_mode.read() == PlacementMode::NoFire { "active-no-fire" } else { "" },
+                            title: "Mark areas friendly fire must stay out of",
+                            onclick: move |_| placement_mode.set(PlacementMode::NoFire),
+                            "No-fire"
+                        }
                     }
                     button {
-                        class: if *placement_mode.read() == PlacementMode::Target { "active-target" } else { "" },
-                        onclick: move |_| placement_mode.set(PlacementMode::Target),
-                        "Target"
+                        class: if *placement_mode.read() == PlacementMode::Measure { "active-measure" } else { "" },
+                        title: "Click two points to measure distance and bearing",
+                        onclick: move |_| placement_mode.set(PlacementMode::Measure),
+                        "Measure"
                     }
-                    button {
-                        class: if *placement_mode.read() == PlacementMode::Spotter { "active-spotter" } else { "" },
-                        onclick: move |_| placement_mode.set(PlacementMode::Spotter),
-                        "Spotter"
+                    if !view_only {
+                        button {
+                            class: if *placement_mode.read() == PlacementMode::Displace { "active-displace" } else { "" },
+                            title: "Click a gun, then the positions it moves to after firing",
+                            onclick: move |_| placement_mode.set(PlacementMode::Displace),
+                            "Displace"
+                        }
                     }
                 }
                 div { class: "header-right",
                     div { class: "toolbar-actions",
-                        button {
-                            class: "toolbar-btn",
-                            disabled: undo_stack.read().is_empty(),
-                            title: "Undo (Ctrl+Z)",
-                            onclick: move |_| do_undo(),
-                            "\u{21B6}"
-                        }
-                        button {
-                            class: "toolbar-btn",
-                            disabled: redo_stack.read().is_empty(),
-                            title: "Redo (Ctrl+Shift+Z)",
-                            onclick: move |_| do_redo(),
-                            "\u{21B7}"
-                        }
-                        button {
-                            class: "toolbar-btn",
-                            disabled: selected_marker.read().is_none(),
-                            title: "Delete selected (Del)",
-                            onclick: move |_| do_delete_selected(),
-                            "\u{2715}"
+                        if !view_only {
+                            button {
+                                class: "toolbar-btn",
+                                disabled: *read_only.read() || undo_stack.read().is_empty(),
+                                title: "Undo (Ctrl+Z)",
+                                onclick: move |_| do_undo(),
+                                "\u{21B6}"
+                            }
+                            button {
+                                class: "toolbar-btn",
+                                disabled: *read_only.read() || redo_stack.read().is_empty(),
+                                title: "Redo (Ctrl+Shift+Z)",
+                                onclick: move |_| do_redo(),
+                                "\u{21B7}"
+                            }
+                            button {
+                                class: "toolbar-btn",
+                                disabled: *read_only.read()
+                                    || (selected_marker.read().is_none() && group_selection.read().is_empty()),
+                                title: "Delete selected (Del)",
+                                onclick: move |_| do_delete_selected(),
+                                "\u{2715}"
+                            }
                         }
                         button {
                             class: "toolbar-btn",
@@ -546,78 +2564,184 @@ pub fn Planner(plan_id: Option<String>) -> Element {
                 }
             }
 
-            // Sidebar backdrop (mobile drawer overlay)
+            // Backdrop behind the open panel sheet (narrow screens)
             div {
-                class: if *sidebar_open.read() { "sidebar-backdrop open" } else { "sidebar-backdrop" },
-                onclick: move |_| sidebar_open.set(false),
+                class: if sheet.read().is_open() { "sidebar-backdrop open" } else { "sidebar-backdrop" },
+                onclick: move |_| sheet.set(SheetState::Closed),
             }
 
             // Sidebar
-            div { class: if *sidebar_open.read() { "sidebar open" } else { "sidebar" },
-                // Map selector
-                div { class: "panel",
-                    h3 { "Map" }
-                    select {
-                        "aria-label": "Select map",
-                        value: "{selected_map}",
-                        onchange: move |evt: Event<FormData>| {
-                            push_snapshot();
-                            selected_map.set(evt.value().to_string());
-                            gun_positions.set(vec![]);
-                            target_positions.set(vec![]);
-                            spotter_positions.set(vec![]);
-                            gun_weapon_ids.set(vec![]);
-                            gun_target_indices.set(vec![]);
-                            selected_marker.set(None);
-                        },
-                        for m in &maps {
-                            option {
-                                value: "{m.file_name}",
-                                selected: *selected_map.read() == m.file_name,
-                                "{m.display_name}"
-                            }
-                        }
+            div { class: sheet.read().sidebar_class(),
+                SheetHandle { state: sheet }
+                if !view_only {
+                    PlanTabs {
+                        labels: tab_labels,
+                        active: *active_tab.read(),
+                        on_select: switch_tab,
+                        on_add: add_tab,
+                        on_close: close_tab,
+                        on_save_all: save_all,
+                    }
+                }
+                MapSelector {
+                    maps: maps.clone(),
+                    selected_map: selected_map,
+                    disabled: *read_only.read(),
+                    on_change: move |file_name: String| {
+                        push_snapshot();
+                        selected_map.set(file_name);
+                        calibration.set(None);
+                        gun_positions.set(vec![]);
+                        target_positions.set(vec![]);
+                        spotter_positions.set(vec![]);
+                        enemy_positions.set(vec![]);
+                        fob_positions.set(vec![]);
+                        bunker_positions.set(vec![]);
+                        no_fire_positions.set(vec![]);
+                        no_fire_radii.set(vec![]);
+                        gun_weapon_ids.set(vec![]);
+                        gun_target_indices.set(vec![]);
+                        marker_labels.set(MarkerLabels::default());
+                        gun_salvo_sizes.set(vec![]);
+                        gun_alternates.set(vec![]);
+                        target_statuses.set(vec![]);
+                        shot_log.set(vec![]);
+                        registrations.set(vec![]);
+                        gun_ammo.set(vec![]);
+                        range_rings.set(RangeRings::default());
+                        selected_marker.set(None);
+                        group_selection.set(Vec::new());
+                    },
+                    on_save_view: move |_| {
+                        let current = *save_view_counter.read();
+                        save_view_counter.set(current + 1);
+                    },
+                }
+
+                fieldset { class: "lock-fieldset", disabled: *read_only.read(),
+                    CalibrationPanel {
+                        key: "{current_map}-{custom_image_url}",
+                        calibration: calibration.read().clone(),
+                        on_change: set_calibration,
                     }
                 }
 
                 WeaponSelector {
                     weapons: weapons.clone(),
                     selected_weapon: selected_weapon,
+                    profile: *weapon_profile.read(),
                 }
 
-                WindInput {
-                    wind_direction: wind_direction,
-                    wind_strength: wind_strength,
-                    on_before_change: move |_| push_snapshot(),
+                fieldset { class: "lock-fieldset", disabled: *read_only.read(),
+                    BatteryTemplates {
+                        gun_positions: gun_positions.read().clone(),
+                        gun_weapon_ids: gun_weapon_ids.read().clone(),
+                        map_scale: *map_scale.read(),
+                        selected_weapon: selected_weapon.read().clone(),
+                        armed: armed_template,
+                    }
+                }
+
+                fieldset { class: "lock-fieldset", disabled: *read_only.read(),
+                    RangeBearingPanel {
+                        gun_positions: gun_positions.read().clone(),
+                        target_positions: target_positions.read().clone(),
+                        spotter_positions: spotter_positions.read().clone(),
+                        marker_labels: marker_labels.read().clone(),
+                        map_scale: *map_scale.read(),
+                        selected_marker: *selected_marker.read(),
+                        on_place: place_typed,
+                    }
+                }
+
+                fieldset { class: "lock-fieldset", disabled: *read_only.read(),
+                    WindInput {
+                        wind_direction: wind_direction,
+                        wind_strength: wind_strength,
+                        on_before_change: move |_| push_snapshot(),
+                        map_id: current_map.clone(),
+                        wind_report: wind_report,
+                    }
                 }
 
                 CalculationDisplay {
                     solutions: firing_solutions.read().clone(),
+                    danger_close: danger_close.read().clone(),
                     gun_positions: gun_positions.read().clone(),
                     target_positions: target_positions.read().clone(),
                     spotter_positions: spotter_positions.read().clone(),
                     gun_weapon_ids: gun_weapon_ids,
                     gun_target_indices: gun_target_indices,
+                    marker_labels: marker_labels,
+                    gun_salvo_sizes: gun_salvo_sizes,
+                    gun_alternates: gun_alternates,
+                    target_statuses: target_statuses,
+                    shot_log: shot_log,
+                    registrations: registrations,
+                    gun_ammo: gun_ammo,
+                    settings: *settings.read(),
+                    wind_direction: *wind_direction.read(),
+                    wind_strength: *wind_strength.read(),
+                    map_scale: *map_scale.read(),
+                    target_radius: target_radius,
                     weapons: weapons.clone(),
+                    plan_name: plan_name.read().clone(),
+                    read_only: *read_only.read(),
                     selected_marker: selected_marker,
                     on_before_change: move |_| push_snapshot(),
+                    on_place: place_typed,
                     on_remove: move |(kind, idx): (MarkerKind, usize)| {
                         push_snapshot();
+                        group_selection.set(Vec::new());
                         let cur_sel = *selected_marker.read();
                         remove_marker(
                             kind, idx,
                             &mut gun_positions, &mut target_positions, &mut spotter_positions,
-                            &mut gun_weapon_ids, &mut gun_target_indices,
+                            &mut enemy_positions, &mut fob_positions, &mut bunker_positions,
+                            &mut no_fire_positions, &mut no_fire_radii, &mut gun_weapon_ids, &mut gun_target_indices,
+                            &mut marker_labels, &mut gun_salvo_sizes, &mut gun_alternates,
+                            &mut target_statuses,
+                            &mut shot_log,
+                            &mut registrations,
+                            &mut gun_ammo,
+                            &mut range_rings,
                         );
-                        // Fixup selection
-                        if let Some(sm) = cur_sel {
-                            if sm.kind == kind {
-                                if sm.index == idx {
-                                    selected_marker.set(None);
-                                } else if sm.index > idx {
-                                    selected_marker.set(Some(SelectedMarker { kind, index: sm.index - 1 }));
-                                }
-                            }
+                        selected_marker.set(selection_after_remove(cur_sel, kind, idx));
+                    },
+                }
+
+                LogisticsPanel {
+                    solutions: firing_solutions.read().clone(),
+                    gun_weapon_ids: gun_weapon_ids.read().clone(),
+                    gun_target_indices: gun_target_indices.read().clone(),
+                    target_count: target_positions.read().len(),
+                    marker_labels: marker_labels.read().clone(),
+                    target_radius: *target_radius.read(),
+                }
+
+                fieldset { class: "lock-fieldset", disabled: *read_only.read(),
+                    RangeRingsPanel {
+                        gun_count: gun_positions.read().len(),
+                        target_count: target_positions.read().len(),
+                        spotter_count: spotter_positions.read().len(),
+                        marker_labels: marker_labels.read().clone(),
+                        range_rings: range_rings,
+                        on_before_change: move |_| push_snapshot(),
+                    }
+                }
+
+                CounterBatteryPanel {
+                    weapons: weapons.clone(),
+                    enemy_weapon: enemy_weapon,
+                    gun_positions: gun_positions.read().clone(),
+                    enemy_positions: enemy_positions.read().clone(),
+                    map_scale: *map_scale.read(),
+                    on_clear: move |_| {
+                        push_snapshot();
+                        enemy_positions.set(vec![]);
+                        group_selection.set(Vec::new());
+                        if selected_marker.read().is_some_and(|sm| sm.kind == MarkerKind::Enemy) {
+                            selected_marker.set(None);
                         }
                     },
                 }
@@ -626,45 +2750,129 @@ pub fn Planner(plan_id: Option<String>) -> Element {
                     plan_name: plan_name,
                     plan_url: plan_url,
                     save_error: save_error,
-                    on_save: move |_| {
-                        let map = selected_map.read().clone();
-                        let wids = gun_weapon_ids.read().clone();
-                        let name = plan_name.read().clone();
-                        let guns = gun_positions.read().clone();
-                        let targets = target_positions.read().clone();
-                        let spotters = spotter_positions.read().clone();
-                        let pairings = gun_target_indices.read().clone();
-                        let w_dir = *wind_direction.read();
-                        let w_str = *wind_strength.read();
-                        spawn(async move {
-                            // Convert pixel positions to meters for storage
-                            let gun_m: Vec<(f64, f64)> = guns.iter()
-                                .map(|g| coords::map_px_to_meters(g.0, g.1))
-                                .collect();
-                            let tgt_m: Vec<(f64, f64)> = targets.iter()
-                                .map(|t| coords::map_px_to_meters(t.0, t.1))
-                                .collect();
-                            let spt_m: Vec<(f64, f64)> = spotters.iter()
-                                .map(|s| coords::map_px_to_meters(s.0, s.1))
-                                .collect();
-                            match api::create_plan(
-                                &name, &map, &wids,
-                                &gun_m, &tgt_m, &spt_m,
-                                &pairings,
-                                w_dir, Some(w_str),
-                            ).await {
-                                Ok(plan) => {
-                                    save_error.set(None);
-                                    let window = web_sys::window().unwrap();
-                                    let origin = window.location().origin().unwrap();
-                                    plan_url.set(Some(api::build_plan_url(&origin, &plan.id)));
-                                }
-                                Err(e) => {
-                                    save_error.set(Some(format!("Failed to save: {e}")));
+                    save_queued: *save_queued.read(),
+                    on_save: move |_| do_save(None),
+                    payload_size: payload_size(),
+                    saves_copy: *saves_copy.read(),
+                    saved_plan: saved_plan.read().clone(),
+                    read_only: *read_only.read(),
+                    view_only: view_only,
+                    on_restore: restore_version,
+                    on_compare: move |plan: PlanData| {
+                        compare_with.set(Some((format!("v{}", plan.version), plan)));
+                    },
+                    on_compare_link: compare_link,
+                    can_fork: fork_source.is_some(),
+                    on_fork: fork_plan,
+                    stateless_url: stateless_url,
+                    on_copy_stateless: copy_stateless_link,
+                }
+                if let Some((compared, _)) = compare_with.read().clone() {
+                    PlanComparePanel {
+                        compared: compared,
+                        changes: plan_changes(),
+                        marker_labels: marker_labels.read().clone(),
+                        gun_count: gun_positions.read().len(),
+                        target_count: target_positions.read().len(),
+                        spotter_count: spotter_positions.read().len(),
+                        on_close: move |_| compare_with.set(None),
+                    }
+                }
+
+                div { class: "panel",
+                    h3 { "Preferences" }
+                    label { class: "checkbox-row",
+                        input {
+                            r#type: "checkbox",
+                            checked: *instant_remove.read(),
+                            onchange: move |evt: Event<FormData>| {
+                                let enabled = evt.checked();
+                                instant_remove.set(enabled);
+                                save_flag("instant_remove", enabled);
+                            },
+                        }
+                        "Right-click removes markers instantly"
+                    }
+                    label { class: "checkbox-row",
+                        input {
+                            r#type: "checkbox",
+                            checked: *high_contrast.read(),
+                            onchange: move |evt: Event<FormData>| {
+                                theme.write().high_contrast = evt.checked();
+                                save_theme(&theme.read());
+                            },
+                        }
+                        "High-contrast markers"
+                    }
+                    label { class: "checkbox-row",
+                        input {
+                            r#type: "checkbox",
+                            checked: theme.read().colorblind,
+                            onchange: move |evt: Event<FormData>| {
+                                theme.write().colorblind = evt.checked();
+                                save_theme(&theme.read());
+                            },
+                        }
+                        "Colorblind-safe colors"
+                    }
+                    div { class: "theme-row",
+                        span { "Theme" }
+                        select {
+                            class: "inline-weapon-select",
+                            "aria-label": "Theme",
+                            onchange: move |evt: Event<FormData>| {
+                                theme.write().appearance = if evt.value() == "light" {
+                                    Appearance::Light
+                                } else {
+                                    Appearance::Dark
+                                };
+                                save_theme(&theme.read());
+                            },
+                            option { value: "dark", selected: theme.read().appearance == Appearance::Dark, "Dark" }
+                            option { value: "light", selected: theme.read().appearance == Appearance::Light, "Light" }
+                        }
+                    }
+                    label { class: "checkbox-row",
+                        input {
+                            r#type: "checkbox",
+                            checked: *show_structures.read(),
+                            onchange: move |evt: Event<FormData>| {
+                                let enabled = evt.checked();
+                                show_structures.set(enabled);
+                                save_flag("show_structures", enabled);
+                            },
+                        }
+                        "Show live town halls and bases (War API)"
+                    }
+                    div { class: "theme-row",
+                        span { "Heatmap" }
+                        select {
+                            class: "inline-weapon-select",
+                            "aria-label": "Community heatmap",
+                            title: "Shade grid cells by how often the community's plans put guns or targets there",
+                            onchange: move |evt: Event<FormData>| {
+                                let layer = HeatmapLayer::parse(&evt.value());
+                                layer.save();
+                                heatmap_layer.set(layer);
+                            },
+                            for layer in [HeatmapLayer::Off, HeatmapLayer::Guns, HeatmapLayer::Targets] {
+                                option {
+                                    value: layer.as_str(),
+                                    selected: *heatmap_layer.read() == layer,
+                                    match layer {
+                                        HeatmapLayer::Off => "Off",
+                                        HeatmapLayer::Guns => "Popular gun spots",
+                                        HeatmapLayer::Targets => "Popular targets",
+                                    }
                                 }
                             }
-                        });
-                    },
+                        }
+                    }
+                    button {
+                        class: "settings-open-btn",
+                        onclick: move |_| show_settings.set(true),
+                        "Settings\u{2026}"
+                    }
                 }
 
                 div { class: "panel",
@@ -700,33 +2908,396 @@ pub fn Planner(plan_id: Option<String>) -> Element {
                         }
                         "."
                     }
+                    p {
+                        "See how the planner is used on the "
+                        a { href: "/stats", "statistics page" }
+                        "."
+                    }
                 }
             }
 
             // Map view
             if !current_map.is_empty() {
                 MapView {
-                    key: "{current_map}",
+                    key: "{current_map}-{custom_image_url}",
                     map_file_name: current_map,
+                    image_url: calibration.read().as_ref().map(|c| c.image_url.clone()),
+                    map_scale: *map_scale.read(),
                     placement_mode: placement_mode,
                     gun_positions: gun_positions,
                     target_positions: target_positions,
                     spotter_positions: spotter_positions,
+                    enemy_positions: enemy_positions,
+                    fob_positions: fob_positions,
+                    bunker_positions: bunker_positions,
+                    no_fire_positions: no_fire_positions,
+                    no_fire_radii: no_fire_radii,
                     gun_weapon_ids: gun_weapon_ids,
                     gun_target_indices: gun_target_indices,
+                    marker_labels: marker_labels,
+                    gun_salvo_sizes: gun_salvo_sizes,
+                    gun_alternates: gun_alternates,
+                    target_statuses: target_statuses,
+                    shot_log: shot_log,
+                    registrations: registrations,
+                    gun_ammo: gun_ammo,
+                    range_rings: range_rings,
                     selected_weapon_slug: selected_weapon,
+                    enemy_weapon_slug: enemy_weapon,
                     weapons: weapons.clone(),
-                    accuracy_radii_px: accuracy_radii_px,
+                    spreads_px: spreads_px,
+                    danger_close: danger_close,
                     selected_marker: selected_marker,
+                    group_selection: group_selection,
                     undo_stack: undo_stack,
                     redo_stack: redo_stack,
                     wind_direction: wind_direction,
                     wind_strength: wind_strength,
                     reset_view_counter: reset_view_counter,
+                    default_view: current_default_view,
+                    save_view_counter: save_view_counter,
+                    instant_remove: *instant_remove.read(),
+                    high_contrast: high_contrast,
+                    palette: palette,
+                    map_structures: map_structures,
+                    heatmap: heatmap,
+                    heatmap_color: if *heatmap_layer.read() == HeatmapLayer::Targets {
+                        palette.read().colors().target
+                    } else {
+                        palette.read().colors().gun
+                    },
+                    armed_template: armed_template,
+                    faction: *faction.read(),
+                    settings: settings,
+                    plan_changes: plan_changes,
+                    read_only: *read_only.read(),
+                }
+            }
+
+            if let Some(offer) = session_offer.read().as_ref() {
+                div { class: "lock-banner session-banner", role: "status",
+                    span { "Restore your unsaved session ({offer.summary()})?" }
+                    button { onclick: restore_session, "Restore" }
+                    button {
+                        class: "secondary",
+                        onclick: move |_| session_offer.set(None),
+                        "Discard"
+                    }
+                }
+            }
+
+            if !terrain_warnings.read().is_empty() {
+                div { class: "lock-banner terrain-banner", role: "status",
+                    for message in terrain_warnings.read().iter() {
+                        span { "{message}" }
+                    }
+                }
+            }
+
+            if let Some(notice) = weapon_data_notice.read().as_ref() {
+                div { class: "lock-banner weapon-data-banner", role: "status",
+                    span { "{notice}" }
+                    button {
+                        class: "secondary",
+                        onclick: move |_| weapon_data_notice.set(None),
+                        "Dismiss"
+                    }
+                }
+            }
+
+            if view_only {
+                div { class: "lock-banner view-only-banner", role: "status",
+                    span { "Read-only view: markers and firing data can't be changed from this link." }
+                }
+            } else if *read_only.read() {
+                div { class: "lock-banner", role: "status",
+                    span { "This plan is currently being edited by someone else. You're in read-only mode." }
+                    button {
+                        onclick: move |_| {
+                            let plan_id = lock_plan_id.clone();
+                            let session = lock_session.clone();
+                            let token = edit_token.clone();
+                            spawn(async move {
+                                let Some(id) = plan_id else { return };
+                                if let Ok(lock) = api::acquire_plan_lock(&id, &session, true, token.as_deref()).await {
+                                    locked_out.set(!lock.acquired);
+                                }
+                            });
+                        },
+                        "Take over editing"
+                    }
                 }
             }
 
             HelpOverlay { show: show_help }
+            SettingsDrawer { show: show_settings, settings: settings }
+            MergeDialog {
+                conflict: merge_conflict,
+                on_choice: resolve_conflict,
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan_data(guns: &[(f64, f64)], targets: &[(f64, f64)], pairs: Vec<Option<i32>>) -> PlanData {
+        let pos = |v: &[(f64, f64)]| v.iter().map(|&(x, y)| api::PositionData { x, y }).collect();
+        PlanData {
+            id: "p".to_string(),
+            name: "Theirs".to_string(),
+            map_id: "m".to_string(),
+            weapon_ids: guns.iter().map(|_| "w".to_string()).collect(),
+            gun_positions: pos(guns),
+            target_positions: pos(targets),
+            spotter_positions: vec![],
+            gun_target_indices: pairs,
+            gun_labels: vec![],
+            target_labels: vec![],
+            spotter_labels: vec![],
+            target_statuses: vec![],
+            wind_direction: Some(90.0),
+            wind_strength: 2,
+            version: 3,
+            protected: false,
+            edit_token: None,
+            slug: None,
+            updated_at: None,
+            calibration: None,
+            shot_log: vec![],
+            registrations: vec![],
+            gun_ammo: vec![],
+            gun_rings_hidden: vec![],
+            custom_rings: vec![],
+            weapon_data_version: None,
+            enemy_gun_positions: vec![],
+            fob_positions: vec![],
+            bunker_positions: vec![],
+            no_fire_zone_positions: vec![],
+            no_fire_zone_radii: vec![],
+        }
+    }
+
+    #[test]
+    fn test_rescale_snapshot_keeps_markers_in_place() {
+        let stock = MapScale::STANDARD;
+        // A custom image at half the stock resolution, offset 100 m in on both axes
+        let custom = MapScale::calibrated(
+            1000.0,
+            900.0,
+            ((0.0, 0.0), (100.0, 100.0)),
+            ((500.0, 400.0), (100.0 + 500.0 * 2.0, 100.0 + 400.0 * 2.0)),
+        )
+        .unwrap();
+        let gun = coords::meters_to_map_px(600.0, 500.0, stock);
+        let snap = PlanSnapshot {
+            gun_positions: vec![gun],
+            gun_alternates: vec![vec![gun]],
+            gun_weapon_ids: vec!["w".to_string()],
+            ..PlanSnapshot::default()
+        };
+        let moved = rescale_snapshot(&snap, stock, custom);
+        let (x, y) = moved.gun_positions[0];
+        assert!((x - 250.0).abs() < 1e-9 && (y - 200.0).abs() < 1e-9);
+        assert_eq!(moved.gun_alternates[0][0], moved.gun_positions[0]);
+        assert_eq!(moved.gun_weapon_ids, snap.gun_weapon_ids);
+
+        let back = rescale_snapshot(&moved, custom, stock);
+        assert!((back.gun_positions[0].0 - gun.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_heatmap_layer_round_trips() {
+        for layer in [HeatmapLayer::Off, HeatmapLayer::Guns, HeatmapLayer::Targets] {
+            assert_eq!(HeatmapLayer::parse(layer.as_str()), layer);
+        }
+        assert_eq!(HeatmapLayer::parse("bogus"), HeatmapLayer::Off);
+    }
+
+    #[test]
+    fn test_tab_label() {
+        let maps = vec![MapData {
+            display_name: "Deadlands".to_string(),
+            file_name: "MapDeadlandsHex".to_string(),
+            active: true,
+            width: 2048,
+            height: 1776,
+            default_view: None,
+        }];
+        assert_eq!(
+            tab_label(" Push ", "MapDeadlandsHex", &maps),
+            "Push \u{b7} Deadlands"
+        );
+        assert_eq!(tab_label("", "MapDeadlandsHex", &maps), "Deadlands");
+        // Before the maps load
+        assert_eq!(
+            tab_label("Push", "MapDeadlandsHex", &[]),
+            "Push \u{b7} MapDeadlandsHex"
+        );
+    }
+
+    #[test]
+    fn test_new_tab_has_no_markers() {
+        let mut tab = PlanTab::new(3, "MapDeadlandsHex");
+        assert_eq!(tab.key, 3);
+        assert_eq!(tab.plan_name, "New Plan");
+        assert!(!tab.has_markers());
+        tab.state.enemy_positions.push((1.0, 1.0));
+        assert!(tab.has_markers());
+    }
+
+    #[test]
+    fn test_plan_save_stores_meters() {
+        let scale = MapScale::STANDARD;
+        let state = PlanSnapshot {
+            gun_positions: vec![coords::meters_to_map_px(120.0, 80.0, scale)],
+            gun_weapon_ids: vec!["w".to_string()],
+            gun_target_indices: vec![None],
+            wind_strength: 3,
+            ..PlanSnapshot::default()
+        };
+        let api::PlanSave::Create(variables) = plan_save(
+            "Next hex",
+            "m",
+            None,
+            &state,
+            None,
+            None,
+            scale,
+            WeaponProfile::Live,
+        ) else {
+            panic!("an unsaved plan is created");
+        };
+        let input = &variables["input"];
+        assert_eq!(input["name"], "Next hex");
+        assert_eq!(input["windStrength"], 3);
+        let x = input["gunPositions"][0]["x"].as_f64().unwrap();
+        let y = input["gunPositions"][0]["y"].as_f64().unwrap();
+        assert!((x - 120.0).abs() < 1e-6 && (y - 80.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_terrain_warning_messages() {
+        let scale = MapScale::STANDARD;
+        let mask = TerrainMask::parse("..~~\n..~~").unwrap();
+        let mut labels = MarkerLabels::default();
+        labels.set(MarkerKind::Gun, 1, "Lariat");
+        // Land, then water on the east half; a spotter in the image's corner
+        let guns = [(800.0, 900.0), (1400.0, 900.0)];
+        let spotters = [(5.0, 5.0)];
+        assert_eq!(
+            terrain_warning_messages(&guns, &spotters, scale, Some(&mask), &labels),
+            vec!["Lariat is in water", "Spotter 1 is outside the hex"]
+        );
+        assert_eq!(
+            terrain_warning_messages(&guns, &[], scale, None, &labels),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_progress_text() {
+        use TargetStatus::*;
+        assert_eq!(progress_text(&[], 0), None);
+        assert_eq!(
+            progress_text(&[Destroyed, FiredUpon], 4).as_deref(),
+            Some("1/4 targets destroyed (25%)")
+        );
+    }
+
+    #[test]
+    fn test_snapshot_from_legacy_plan_pairs_by_index() {
+        let mut plan = plan_data(&[(100.0, 100.0), (200.0, 200.0)], &[(300.0, 300.0)], vec![]);
+        plan.fob_positions = vec![api::PositionData { x: 0.0, y: 0.0 }];
+        let snap = snapshot_from_plan(&plan, MapScale::STANDARD);
+        assert_eq!(snap.gun_target_indices, vec![Some(0), None]);
+        assert_eq!(snap.fob_positions, vec![(0.0, 0.0)]);
+        assert!(snap.enemy_positions.is_empty());
+        assert_eq!(snap.wind_strength, 2);
+        let (x, y) = coords::map_px_to_meters(
+            snap.gun_positions[0].0,
+            snap.gun_positions[0].1,
+            MapScale::STANDARD,
+        );
+        assert!((x - 100.0).abs() < 1e-6 && (y - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_changes_since_compares_in_meters() {
+        let scale = MapScale::STANDARD;
+        let other = plan_data(&[(100.0, 100.0)], &[(300.0, 300.0)], vec![Some(0)]);
+        let guns = [coords::meters_to_map_px(130.0, 140.0, scale)];
+        let targets = [coords::meters_to_map_px(300.0, 300.0, scale)];
+        let changes = changes_since(&other, &guns, &targets, &[], scale);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, MarkerKind::Gun);
+        assert!((changes[0].change.distance() - 50.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_danger_close_per_gun_checks_each_paired_target() {
+        let map = MapScale::STANDARD;
+        let target = (400.0, 400.0);
+        let (tx, ty) = coords::map_px_to_meters(target.0, target.1, map);
+        let zones = [NoFireZone {
+            center: Position {
+                x: tx + 40.0,
+                y: ty,
+            },
+            radius: 20.0,
+        }];
+        let sol = |accuracy_radius: f64| {
+            Some(FiringSolutionData {
+                azimuth: 0.0,
+                distance: 200.0,
+                in_range: true,
+                accuracy_radius,
+                wind_adjusted_azimuth: None,
+                wind_adjusted_distance: None,
+                wind_offset_meters: None,
+                time_of_flight: None,
+                impact_zone: None,
+            })
+        };
+        let danger = danger_close_per_gun(
+            &[sol(25.0), sol(5.0), sol(25.0), None],
+            &[Some(0), Some(0), None, Some(0)],
+            &[target],
+            &zones,
+            map,
+        );
+        assert_eq!(danger, vec![vec![0], vec![], vec![], vec![]]);
+    }
+
+    #[test]
+    fn test_merge_snapshots_adds_their_new_markers() {
+        let mine = snapshot_from_plan(
+            &plan_data(&[(100.0, 100.0)], &[(300.0, 300.0)], vec![Some(0)]),
+            MapScale::STANDARD,
+        );
+        let mut mine = mine;
+        mine.wind_strength = 4;
+        let mut theirs = snapshot_from_plan(
+            &plan_data(
+                &[(100.0, 100.0), (500.0, 500.0)],
+                &[(300.0, 300.0), (700.0, 700.0)],
+                vec![Some(0), Some(1)],
+            ),
+            MapScale::STANDARD,
+        );
+        theirs.labels.set(MarkerKind::Target, 1, "Bunker base east");
+        theirs.no_fire_positions = vec![(50.0, 60.0)];
+        let merged = merge_snapshots(&mine, &theirs, MapScale::STANDARD);
+        assert_eq!(merged.gun_positions.len(), 2);
+        assert_eq!(merged.target_positions.len(), 2);
+        assert_eq!(merged.gun_target_indices, vec![Some(0), Some(1)]);
+        assert_eq!(merged.wind_strength, 4);
+        assert_eq!(
+            merged.labels.get(MarkerKind::Target, 1),
+            Some("Bunker base east")
+        );
+        assert_eq!(merged.no_fire_positions.len(), 1);
+    }
+}
@@ -0,0 +1,256 @@
+use dioxus::prelude::*;
+
+use crate::api::{self, MapPlacementData, StatsData, WeaponPlacementData};
+
+/// "War 117", with "(current)" for the war being fought now.
+fn war_label(war: u32, current: Option<u32>) -> String {
+    if current == Some(war) {
+        format!("War {} (current)", war)
+    } else {
+        format!("War {}", war)
+    }
+}
+
+/// Weapons with placements, most placed first.
+fn ranked_placements(stats: &StatsData) -> Vec<WeaponPlacementData> {
+    let mut ranked: Vec<WeaponPlacementData> = stats
+        .gun_placements
+        .iter()
+        .filter(|p| p.count > 0)
+        .cloned()
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.display_name.cmp(&b.display_name))
+    });
+    ranked
+}
+
+fn map_total(map: &MapPlacementData) -> u64 {
+    map.guns + map.targets + map.spotters
+}
+
+/// `count` as a percentage of `max`, for bar widths.
+fn percent(count: u64, max: u64) -> f64 {
+    if max == 0 {
+        0.0
+    } else {
+        count as f64 * 100.0 / max as f64
+    }
+}
+
+fn faction_class(faction: &str) -> &'static str {
+    match faction {
+        "COLONIAL" => "stat-bar colonial",
+        "WARDEN" => "stat-bar warden",
+        _ => "stat-bar both",
+    }
+}
+
+/// Public usage page at `/stats`: plans saved and where guns, targets and
+/// spotters were placed, for all wars or one of them.
+#[component]
+pub fn Stats() -> Element {
+    let mut war = use_signal(|| None::<u32>);
+    let stats = use_resource(move || async move { api::fetch_stats(war()).await });
+
+    rsx! {
+        div { class: "admin-page stats-page",
+            div { class: "header",
+                h1 { "Planner Statistics" }
+                div { class: "header-right",
+                    a { class: "toolbar-btn", href: "/", "Back to planner" }
+                }
+            }
+            div { class: "admin-content",
+                match &*stats.read() {
+                    None => rsx! {
+                        p { class: "admin-hint", "Loading..." }
+                    },
+                    Some(Err(e)) => rsx! {
+                        div { class: "save-error", role: "alert",
+                            span { "Failed to load statistics: {e}" }
+                        }
+                    },
+                    Some(Ok(s)) => {
+                        let ranked = ranked_placements(s);
+                        let most = ranked.first().map_or(0, |p| p.count);
+                        let totals = &s.gun_placement_totals;
+                        let factions = totals.colonial + totals.warden;
+                        let busiest_map = s.placements_by_map.first().map_or(0, map_total);
+                        let deduped = &s.deduped_placements;
+                        rsx! {
+                            div { class: "panel",
+                                h3 { "War" }
+                                select {
+                                    "aria-label": "War",
+                                    value: "{war().map(|w| w.to_string()).unwrap_or_default()}",
+                                    onchange: move |evt: Event<FormData>| war.set(evt.value().parse().ok()),
+                                    option { value: "", "All wars" }
+                                    for w in s.wars.iter().copied() {
+                                        option { key: "{w}", value: "{w}", {war_label(w, s.current_war)} }
+                                    }
+                                }
+                            }
+                            div { class: "panel",
+                                h3 { "Totals" }
+                                dl { class: "admin-stats",
+                                    dt { "Saved plans" }
+                                    dd { "{s.total_plans}" }
+                                    dt { "Planners (estimated)" }
+                                    dd { "{s.unique_planners}" }
+                                    dt { "Guns placed" }
+                                    dd { "{totals.total}" }
+                                    dt { "Targets placed" }
+                                    dd { "{s.marker_placements.targets}" }
+                                    dt { "Spotters placed" }
+                                    dd { "{s.marker_placements.spotters}" }
+                                }
+                                if factions > 0 {
+                                    div {
+                                        class: "stat-faction-split",
+                                        "aria-label": "Colonial {totals.colonial}, Warden {totals.warden}",
+                                        div {
+                                            class: "stat-bar colonial",
+                                            style: "width: {percent(totals.colonial, factions)}%",
+                                            "Colonial {totals.colonial}"
+                                        }
+                                        div {
+                                            class: "stat-bar warden",
+                                            style: "width: {percent(totals.warden, factions)}%",
+                                            "Warden {totals.warden}"
+                                        }
+                                    }
+                                }
+                            }
+                            div { class: "panel",
+                                h3 { "Totals without repeats" }
+                                p { class: "admin-hint",
+                                    "A visitor placing the same weapon or marker again on the same day counts once."
+                                }
+                                dl { class: "admin-stats",
+                                    dt { "Guns placed" }
+                                    dd { "{deduped.guns}" }
+                                    dt { "Targets placed" }
+                                    dd { "{deduped.targets}" }
+                                    dt { "Spotters placed" }
+                                    dd { "{deduped.spotters}" }
+                                }
+                            }
+                            div { class: "panel",
+                                h3 { "Guns placed by weapon" }
+                                if ranked.is_empty() {
+                                    p { class: "admin-hint", "No guns placed yet." }
+                                }
+                                ul { class: "stat-chart",
+                                    for p in ranked {
+                                        li { key: "{p.weapon_slug}", class: "stat-row",
+                                            span { class: "stat-name", "{p.display_name}" }
+                                            div { class: "stat-track",
+                                                div {
+                                                    class: faction_class(&p.faction),
+                                                    style: "width: {percent(p.count, most)}%",
+                                                }
+                                            }
+                                            span { class: "stat-count", "{p.count}" }
+                                        }
+                                    }
+                                }
+                            }
+                            div { class: "panel",
+                                h3 { "Placements by map (all wars)" }
+                                if s.placements_by_map.is_empty() {
+                                    p { class: "admin-hint", "No placements on any map yet." }
+                                }
+                                ul { class: "stat-chart",
+                                    for m in s.placements_by_map.iter() {
+                                        li {
+                                            key: "{m.map_id}",
+                                            class: "stat-row",
+                                            title: "{m.guns} guns, {m.targets} targets, {m.spotters} spotters",
+                                            span { class: "stat-name", "{m.display_name}" }
+                                            div { class: "stat-track",
+                                                div {
+                                                    class: "stat-bar both",
+                                                    style: "width: {percent(map_total(m), busiest_map)}%",
+                                                }
+                                            }
+                                            span { class: "stat-count", "{map_total(m)}" }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{DedupedPlacementData, FactionPlacementData, MarkerPlacementData};
+
+    fn placement(name: &str, count: u64) -> WeaponPlacementData {
+        WeaponPlacementData {
+            weapon_slug: name.to_lowercase(),
+            display_name: name.to_string(),
+            faction: "WARDEN".to_string(),
+            count,
+        }
+    }
+
+    #[test]
+    fn test_war_label() {
+        assert_eq!(war_label(116, Some(117)), "War 116");
+        assert_eq!(war_label(117, Some(117)), "War 117 (current)");
+        assert_eq!(war_label(117, None), "War 117");
+    }
+
+    #[test]
+    fn test_ranked_placements() {
+        let stats = StatsData {
+            war: None,
+            current_war: None,
+            wars: vec![],
+            total_plans: 0,
+            gun_placements: vec![
+                placement("Mortar", 3),
+                placement("Howitzer", 0),
+                placement("Cremari", 9),
+                placement("Bonesaw", 3),
+            ],
+            gun_placement_totals: FactionPlacementData {
+                colonial: 0,
+                warden: 15,
+                total: 15,
+            },
+            marker_placements: MarkerPlacementData {
+                targets: 0,
+                spotters: 0,
+            },
+            deduped_placements: DedupedPlacementData {
+                guns: 0,
+                targets: 0,
+                spotters: 0,
+            },
+            unique_planners: 0,
+            placements_by_map: vec![],
+        };
+        let names: Vec<String> = ranked_placements(&stats)
+            .into_iter()
+            .map(|p| p.display_name)
+            .collect();
+        // Most placed first, ties by name, unplaced weapons left out
+        assert_eq!(names, vec!["Cremari", "Bonesaw", "Mortar"]);
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(3, 12), 25.0);
+        assert_eq!(percent(5, 0), 0.0);
+    }
+}
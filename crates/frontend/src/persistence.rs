@@ -0,0 +1,440 @@
+//! Local autosave of the whole planner session, so a crashed or closed tab
+//! can pick up where it left off.
+//!
+//! The session lives in `localStorage` as JSON. Positions are stored as the
+//! planner holds them (map-image px), together with the map and custom image
+//! that give them their scale.
+//!
+//! A plan can also travel in a link's fragment ("stateless link"), deflated
+//! and base64-encoded, so it can be shared without the server.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use foxhole_shared::models::{
+    MapCalibration, RangeRings, RegistrationPoint, ShotRecord, TargetStatus,
+};
+use foxhole_shared::overlay::MarkerLabels;
+use serde::{Deserialize, Serialize};
+
+use crate::pages::planner::PlanSnapshot;
+
+const SESSION_KEY: &str = "planner_session";
+
+/// Layout of [`StoredSession`]; sessions in any other layout are dropped.
+pub const SESSION_VERSION: u32 = 1;
+
+/// Most undo and redo steps kept with a session; older ones are dropped.
+pub const MAX_STORED_HISTORY: usize = 20;
+
+/// A [`PlanSnapshot`] in storable form.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StoredSnapshot {
+    pub gun_positions: Vec<(f64, f64)>,
+    pub target_positions: Vec<(f64, f64)>,
+    pub spotter_positions: Vec<(f64, f64)>,
+    pub enemy_positions: Vec<(f64, f64)>,
+    pub fob_positions: Vec<(f64, f64)>,
+    pub bunker_positions: Vec<(f64, f64)>,
+    pub no_fire_positions: Vec<(f64, f64)>,
+    pub no_fire_radii: Vec<f64>,
+    pub gun_weapon_ids: Vec<String>,
+    pub gun_target_indices: Vec<Option<usize>>,
+    pub gun_labels: Vec<String>,
+    pub target_labels: Vec<String>,
+    pub spotter_labels: Vec<String>,
+    pub gun_salvo_sizes: Vec<u32>,
+    pub gun_alternates: Vec<Vec<(f64, f64)>>,
+    pub target_statuses: Vec<TargetStatus>,
+    pub shot_log: Vec<ShotRecord>,
+    pub registrations: Vec<RegistrationPoint>,
+    pub gun_ammo: Vec<String>,
+    pub range_rings: RangeRings,
+    pub wind_direction: Option<f64>,
+    pub wind_strength: u32,
+}
+
+impl From<&PlanSnapshot> for StoredSnapshot {
+    fn from(s: &PlanSnapshot) -> Self {
+        StoredSnapshot {
+            gun_positions: s.gun_positions.clone(),
+            target_positions: s.target_positions.clone(),
+            spotter_positions: s.spotter_positions.clone(),
+            enemy_positions: s.enemy_positions.clone(),
+            fob_positions: s.fob_positions.clone(),
+            bunker_positions: s.bunker_positions.clone(),
+            no_fire_positions: s.no_fire_positions.clone(),
+            no_fire_radii: s.no_fire_radii.clone(),
+            gun_weapon_ids: s.gun_weapon_ids.clone(),
+            gun_target_indices: s.gun_target_indices.clone(),
+            gun_labels: s.labels.guns.clone(),
+            target_labels: s.labels.targets.clone(),
+            spotter_labels: s.labels.spotters.clone(),
+            gun_salvo_sizes: s.gun_salvo_sizes.clone(),
+            gun_alternates: s.gun_alternates.clone(),
+            target_statuses: s.target_statuses.clone(),
+            shot_log: s.shot_log.clone(),
+            registrations: s.registrations.clone(),
+            gun_ammo: s.gun_ammo.clone(),
+            range_rings: s.range_rings.clone(),
+            wind_direction: s.wind_direction,
+            wind_strength: s.wind_strength,
+        }
+    }
+}
+
+impl From<StoredSnapshot> for PlanSnapshot {
+    fn from(s: StoredSnapshot) -> Self {
+        PlanSnapshot {
+            gun_positions: s.gun_positions,
+            target_positions: s.target_positions,
+            spotter_positions: s.spotter_positions,
+            enemy_positions: s.enemy_positions,
+            fob_positions: s.fob_positions,
+            bunker_positions: s.bunker_positions,
+            no_fire_positions: s.no_fire_positions,
+            no_fire_radii: s.no_fire_radii,
+            gun_weapon_ids: s.gun_weapon_ids,
+            gun_target_indices: s.gun_target_indices,
+            labels: MarkerLabels {
+                guns: s.gun_labels,
+                targets: s.target_labels,
+                spotters: s.spotter_labels,
+            },
+            gun_salvo_sizes: s.gun_salvo_sizes,
+            gun_alternates: s.gun_alternates,
+            target_statuses: s.target_statuses,
+            shot_log: s.shot_log,
+            registrations: s.registrations,
+            gun_ammo: s.gun_ammo,
+            range_rings: s.range_rings,
+            wind_direction: s.wind_direction,
+            wind_strength: s.wind_strength,
+        }
+    }
+}
+
+/// Everything needed to put the planner back as it was.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredSession {
+    pub version: u32,
+    pub map_id: String,
+    #[serde(default)]
+    pub calibration: Option<MapCalibration>,
+    pub plan_name: String,
+    /// Id and version of the stored plan being edited, if it was saved.
+    #[serde(default)]
+    pub saved_plan: Option<(String, u32)>,
+    pub state: StoredSnapshot,
+    /// Oldest first, like the planner's undo stack.
+    #[serde(default)]
+    pub undo: Vec<StoredSnapshot>,
+    #[serde(default)]
+    pub redo: Vec<StoredSnapshot>,
+}
+
+impl StoredSession {
+    /// A session for the current planner state, keeping the newest
+    /// [`MAX_STORED_HISTORY`] undo and redo steps.
+    pub fn new(
+        map_id: &str,
+        calibration: Option<&MapCalibration>,
+        plan_name: &str,
+        saved_plan: Option<(String, u32)>,
+        state: &PlanSnapshot,
+        undo: &[PlanSnapshot],
+        redo: &[PlanSnapshot],
+    ) -> Self {
+        let newest = |stack: &[PlanSnapshot]| -> Vec<StoredSnapshot> {
+            let skip = stack.len().saturating_sub(MAX_STORED_HISTORY);
+            stack[skip..].iter().map(StoredSnapshot::from).collect()
+        };
+        StoredSession {
+            version: SESSION_VERSION,
+            map_id: map_id.to_string(),
+            calibration: calibration.cloned(),
+            plan_name: plan_name.to_string(),
+            saved_plan,
+            state: StoredSnapshot::from(state),
+            undo: newest(undo),
+            redo: newest(redo),
+        }
+    }
+
+    /// Whether there's nothing on the map worth offering back.
+    pub fn is_empty(&self) -> bool {
+        let s = &self.state;
+        s.gun_positions.is_empty()
+            && s.target_positions.is_empty()
+            && s.spotter_positions.is_empty()
+            && s.enemy_positions.is_empty()
+            && s.fob_positions.is_empty()
+            && s.bunker_positions.is_empty()
+            && s.no_fire_positions.is_empty()
+    }
+
+    /// "3 guns, 2 targets" for the restore prompt.
+    pub fn summary(&self) -> String {
+        let count = |n: usize, what: &str| match n {
+            1 => format!("1 {what}"),
+            n => format!("{n} {what}s"),
+        };
+        let s = &self.state;
+        [
+            (s.gun_positions.len(), "gun"),
+            (s.target_positions.len(), "target"),
+            (s.spotter_positions.len(), "spotter"),
+            (s.enemy_positions.len(), "enemy marker"),
+            (s.fob_positions.len(), "FOB"),
+            (s.bunker_positions.len(), "bunker"),
+            (s.no_fire_positions.len(), "no-fire zone"),
+        ]
+        .into_iter()
+        .filter(|&(n, _)| n > 0)
+        .map(|(n, what)| count(n, what))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// The session JSON, or `None` for one from another layout or a broken one.
+pub fn decode_session(json: &str) -> Option<StoredSession> {
+    serde_json::from_str::<StoredSession>(json)
+        .ok()
+        .filter(|s| s.version == SESSION_VERSION)
+}
+
+/// Key of the URL fragment that carries a stateless plan link.
+pub const SHARE_FRAGMENT_KEY: &str = "plan";
+
+/// Layout of [`SharedPlan`]; links in any other layout are ignored.
+pub const SHARE_VERSION: u32 = 1;
+
+/// Most a stateless link may inflate to, so a crafted one can't exhaust memory.
+const MAX_SHARED_PLAN_BYTES: u64 = 256 * 1024;
+
+/// A plan carried whole in a link's fragment, for sharing without the server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedPlan {
+    pub version: u32,
+    pub map_id: String,
+    #[serde(default)]
+    pub calibration: Option<MapCalibration>,
+    pub plan_name: String,
+    pub state: StoredSnapshot,
+}
+
+impl SharedPlan {
+    pub fn new(
+        map_id: &str,
+        calibration: Option<&MapCalibration>,
+        plan_name: &str,
+        state: &PlanSnapshot,
+    ) -> Self {
+        SharedPlan {
+            version: SHARE_VERSION,
+            map_id: map_id.to_string(),
+            calibration: calibration.cloned(),
+            plan_name: plan_name.to_string(),
+            state: StoredSnapshot::from(state),
+        }
+    }
+
+    /// Deflated JSON in URL-safe base64, for the link's fragment.
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+        let deflated = std::io::Write::write_all(&mut encoder, &json)
+            .and_then(|_| encoder.finish())
+            .unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(deflated)
+    }
+
+    /// The plan in an encoded fragment value, or `None` for a broken or
+    /// foreign one.
+    pub fn decode(encoded: &str) -> Option<SharedPlan> {
+        let deflated = URL_SAFE_NO_PAD.decode(encoded).ok()?;
+        let mut json = Vec::new();
+        let decoder = flate2::read::DeflateDecoder::new(&deflated[..]);
+        std::io::Read::read_to_end(
+            &mut std::io::Read::take(decoder, MAX_SHARED_PLAN_BYTES + 1),
+            &mut json,
+        )
+        .ok()?;
+        if json.len() as u64 > MAX_SHARED_PLAN_BYTES {
+            return None;
+        }
+        serde_json::from_slice::<SharedPlan>(&json)
+            .ok()
+            .filter(|p| p.version == SHARE_VERSION)
+    }
+}
+
+/// "https://host/#plan=..." — opens the planner with the plan already on the map.
+pub fn stateless_plan_url(origin: &str, plan: &SharedPlan) -> String {
+    format!("{}/#{}={}", origin, SHARE_FRAGMENT_KEY, plan.encode())
+}
+
+/// The shared plan in a location hash like "#plan=...", if it holds one.
+pub fn shared_plan_from_hash(hash: &str) -> Option<SharedPlan> {
+    let value = hash
+        .trim_start_matches('#')
+        .strip_prefix(SHARE_FRAGMENT_KEY)?
+        .strip_prefix('=')?;
+    SharedPlan::decode(value)
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window().and_then(|w| w.local_storage().ok().flatten())
+}
+
+/// The autosaved session, if there is one.
+pub fn load_session() -> Option<StoredSession> {
+    local_storage()
+        .and_then(|s| s.get_item(SESSION_KEY).ok().flatten())
+        .and_then(|json| decode_session(&json))
+}
+
+/// Replace the autosaved session; an empty one clears it.
+pub fn save_session(session: &StoredSession) {
+    if session.is_empty() {
+        clear_session();
+        return;
+    }
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(session)) {
+        let _ = storage.set_item(SESSION_KEY, &json);
+    }
+}
+
+pub fn clear_session() {
+    if let Some(storage) = local_storage() {
+        let _ = storage.remove_item(SESSION_KEY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::models::Position;
+
+    fn snapshot(guns: usize) -> PlanSnapshot {
+        PlanSnapshot {
+            gun_positions: (0..guns).map(|i| (i as f64 * 10.0, 5.0)).collect(),
+            target_positions: vec![(300.0, 120.0)],
+            gun_weapon_ids: vec!["cremari-mortar".to_string(); guns],
+            gun_target_indices: vec![Some(0); guns],
+            labels: MarkerLabels {
+                guns: vec!["Alpha".to_string()],
+                targets: vec![],
+                spotters: vec![],
+            },
+            target_statuses: vec![TargetStatus::FiredUpon],
+            registrations: vec![RegistrationPoint {
+                gun: 0,
+                name: "TRP-1".to_string(),
+                position: Position { x: 90.0, y: 64.0 },
+                azimuth: 92.3,
+                distance: 70.0,
+            }],
+            gun_ammo: vec!["Shrapnel".to_string()],
+            wind_direction: Some(270.0),
+            wind_strength: 3,
+            ..PlanSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn test_session_round_trips_through_json() {
+        let history: Vec<PlanSnapshot> = (0..3).map(snapshot).collect();
+        let session = StoredSession::new(
+            "DeadLandsHex",
+            None,
+            "Op Hammer",
+            Some(("abc-123".to_string(), 4)),
+            &snapshot(2),
+            &history,
+            &[],
+        );
+        let json = serde_json::to_string(&session).unwrap();
+        let decoded = decode_session(&json).unwrap();
+        assert_eq!(decoded, session);
+        assert_eq!(decoded.undo.len(), 3);
+        assert_eq!(decoded.summary(), "2 guns, 1 target");
+
+        let state = PlanSnapshot::from(decoded.state);
+        assert_eq!(state.labels.guns, vec!["Alpha"]);
+        assert_eq!(state.gun_ammo, vec!["Shrapnel"]);
+        assert_eq!(state.gun_positions, snapshot(2).gun_positions);
+    }
+
+    #[test]
+    fn test_session_keeps_the_newest_history() {
+        let history: Vec<PlanSnapshot> = (0..MAX_STORED_HISTORY + 5).map(snapshot).collect();
+        let session = StoredSession::new("m", None, "n", None, &snapshot(1), &history, &[]);
+        assert_eq!(session.undo.len(), MAX_STORED_HISTORY);
+        assert_eq!(session.undo[0].gun_positions.len(), 5);
+        assert_eq!(
+            session.undo.last().unwrap().gun_positions.len(),
+            MAX_STORED_HISTORY + 4
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_other_layouts() {
+        assert!(decode_session("not json").is_none());
+        let mut session = StoredSession::new("m", None, "n", None, &snapshot(1), &[], &[]);
+        session.version = SESSION_VERSION + 1;
+        assert!(decode_session(&serde_json::to_string(&session).unwrap()).is_none());
+        // Fields added later fall back to their defaults
+        let sparse = format!(
+            r#"{{"version":{SESSION_VERSION},"map_id":"m","plan_name":"n","state":{{"gun_positions":[[1.0,2.0]]}}}}"#
+        );
+        let decoded = decode_session(&sparse).unwrap();
+        assert_eq!(decoded.state.gun_positions, vec![(1.0, 2.0)]);
+        assert!(decoded.undo.is_empty());
+        assert!(!decoded.is_empty());
+        assert!(
+            StoredSession::new("m", None, "n", None, &PlanSnapshot::default(), &[], &[]).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_shared_plan_round_trips_through_the_fragment() {
+        let plan = SharedPlan::new("DeadLandsHex", None, "Op Hammer", &snapshot(3));
+        let url = stateless_plan_url("https://arty.example", &plan);
+        let hash = url.strip_prefix("https://arty.example/").unwrap();
+        assert!(hash.starts_with("#plan="));
+        let decoded = shared_plan_from_hash(hash).unwrap();
+        assert_eq!(decoded, plan);
+        assert_eq!(
+            PlanSnapshot::from(decoded.state).gun_positions,
+            snapshot(3).gun_positions
+        );
+        // Compression keeps a busy plan to a sensible link length
+        let busy = SharedPlan::new("m", None, "n", &snapshot(40));
+        let json_len = serde_json::to_string(&busy).unwrap().len();
+        assert!(busy.encode().len() < json_len / 2);
+    }
+
+    #[test]
+    fn test_shared_plan_rejects_other_fragments() {
+        assert!(shared_plan_from_hash("").is_none());
+        assert!(shared_plan_from_hash("#map").is_none());
+        assert!(shared_plan_from_hash("#plan=not-a-plan").is_none());
+        assert!(shared_plan_from_hash("#planet=abc").is_none());
+        let mut plan = SharedPlan::new("m", None, "n", &snapshot(1));
+        plan.version = SHARE_VERSION + 1;
+        assert!(SharedPlan::decode(&plan.encode()).is_none());
+        // A payload that inflates past the limit is refused
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+        std::io::Write::write_all(
+            &mut encoder,
+            &vec![b' '; MAX_SHARED_PLAN_BYTES as usize + 1],
+        )
+        .unwrap();
+        let bomb = URL_SAFE_NO_PAD.encode(encoder.finish().unwrap());
+        assert!(SharedPlan::decode(&bomb).is_none());
+    }
+}
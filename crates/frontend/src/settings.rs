@@ -0,0 +1,146 @@
+//! Preferences for how the planner behaves and shows its numbers, kept in
+//! `localStorage` as JSON.
+
+use foxhole_shared::models::WeaponProfile;
+use serde::{Deserialize, Serialize};
+
+use crate::components::map_view::Faction;
+
+const SETTINGS_KEY: &str = "settings";
+
+/// Distance rounding steps offered in the settings, in meters.
+pub const ROUNDING_STEPS: [u32; 3] = [1, 5, 10];
+
+/// NATO mils in a full circle.
+const MILS_PER_CIRCLE: f64 = 6400.0;
+
+/// How azimuths are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AngleUnit {
+    #[default]
+    Degrees,
+    Mils,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub angle_unit: AngleUnit,
+    /// Switch between gun and target placement after each one is placed.
+    pub auto_cycle: bool,
+    /// Firing distances are shown rounded to this many meters.
+    pub rounding_step: u32,
+    /// Side the planner opens on; `None` keeps the last one picked.
+    pub default_faction: Option<Faction>,
+    /// Draw the keypad grid at every zoom, not just close in.
+    pub always_keypad: bool,
+    /// Offer a form under each firing solution for reporting where rounds landed.
+    pub research_mode: bool,
+    /// Weapon figures to plan with; Devbranch only when the server has them.
+    pub weapon_profile: WeaponProfile,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            angle_unit: AngleUnit::Degrees,
+            auto_cycle: true,
+            rounding_step: 5,
+            default_faction: None,
+            always_keypad: false,
+            research_mode: false,
+            weapon_profile: WeaponProfile::Live,
+        }
+    }
+}
+
+impl Settings {
+    /// `meters` rounded to the chosen step.
+    pub fn round_distance(&self, meters: f64) -> f64 {
+        let step = self.rounding_step.max(1) as f64;
+        (meters / step).round() * step
+    }
+
+    /// An azimuth in degrees, shown in the chosen unit: "92.3°" or "1641 mil".
+    pub fn format_azimuth(&self, degrees: f64) -> String {
+        match self.angle_unit {
+            AngleUnit::Degrees => format!("{degrees:.1}\u{00b0}"),
+            AngleUnit::Mils => {
+                let mils = (degrees.rem_euclid(360.0) / 360.0 * MILS_PER_CIRCLE).round();
+                format!("{:.0} mil", mils % MILS_PER_CIRCLE)
+            }
+        }
+    }
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window().and_then(|w| w.local_storage().ok().flatten())
+}
+
+/// The stored settings JSON, or `None` when it can't be read.
+fn decode_settings(json: &str) -> Option<Settings> {
+    serde_json::from_str(json).ok()
+}
+
+pub fn load_settings() -> Settings {
+    local_storage()
+        .and_then(|s| s.get_item(SETTINGS_KEY).ok().flatten())
+        .and_then(|json| decode_settings(&json))
+        .unwrap_or_default()
+}
+
+pub fn save_settings(settings: &Settings) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(settings)) {
+        let _ = storage.set_item(SETTINGS_KEY, &json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_distance() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.round_distance(183.0), 185.0);
+        settings.rounding_step = 1;
+        assert_eq!(settings.round_distance(183.4), 183.0);
+        settings.rounding_step = 10;
+        assert_eq!(settings.round_distance(183.0), 180.0);
+        settings.rounding_step = 0;
+        assert_eq!(settings.round_distance(183.4), 183.0);
+    }
+
+    #[test]
+    fn test_format_azimuth() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.format_azimuth(92.34), "92.3\u{00b0}");
+        settings.angle_unit = AngleUnit::Mils;
+        assert_eq!(settings.format_azimuth(90.0), "1600 mil");
+        assert_eq!(settings.format_azimuth(92.34), "1642 mil");
+        // Just short of north wraps to 0 rather than 6400
+        assert_eq!(settings.format_azimuth(359.99), "0 mil");
+    }
+
+    #[test]
+    fn test_decode_settings() {
+        let settings = Settings {
+            angle_unit: AngleUnit::Mils,
+            auto_cycle: false,
+            rounding_step: 10,
+            default_faction: Some(Faction::Colonial),
+            always_keypad: true,
+            research_mode: true,
+            weapon_profile: WeaponProfile::Devbranch,
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(decode_settings(&json), Some(settings));
+        // Fields added later fall back to their defaults
+        let sparse = decode_settings(r#"{"rounding_step":1}"#).unwrap();
+        assert_eq!(sparse.rounding_step, 1);
+        assert!(sparse.auto_cycle);
+        assert_eq!(sparse.weapon_profile, WeaponProfile::Live);
+        assert_eq!(decode_settings("not json"), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! How the planner looks: dark or light panels, which colors the markers use,
+//! and high contrast. The choice is kept in `localStorage`.
+//!
+//! The app element carries the theme as classes (`colonial`, `light`,
+//! `colorblind`) that switch the CSS variables; the map overlay gets the
+//! matching [`Palette`].
+
+use foxhole_shared::overlay::Palette;
+use serde::{Deserialize, Serialize};
+
+use crate::components::map_view::Faction;
+
+const THEME_KEY: &str = "theme";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    #[default]
+    Dark,
+    Light,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub appearance: Appearance,
+    /// Colorblind-safe marker colors in place of the faction's.
+    pub colorblind: bool,
+    /// Bigger markers with an outline.
+    pub high_contrast: bool,
+}
+
+impl Theme {
+    /// The marker colors for playing as `faction`.
+    pub fn palette(&self, faction: Faction) -> Palette {
+        match faction {
+            _ if self.colorblind => Palette::Colorblind,
+            Faction::Warden => Palette::Warden,
+            Faction::Colonial => Palette::Colonial,
+        }
+    }
+
+    /// Classes for the app element, e.g. `"app colonial light"`.
+    pub fn app_class(&self, faction: Faction) -> String {
+        let mut class = String::from("app");
+        if faction == Faction::Colonial {
+            class.push_str(" colonial");
+        }
+        if self.appearance == Appearance::Light {
+            class.push_str(" light");
+        }
+        if self.colorblind {
+            class.push_str(" colorblind");
+        }
+        class
+    }
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window().and_then(|w| w.local_storage().ok().flatten())
+}
+
+/// The stored theme JSON, or `None` when it can't be read.
+fn decode_theme(json: &str) -> Option<Theme> {
+    serde_json::from_str(json).ok()
+}
+
+/// The saved theme. Before themes, high contrast was a flag of its own;
+/// it's carried over when there's no theme yet.
+pub fn load_theme() -> Theme {
+    let storage = local_storage();
+    let stored = storage
+        .as_ref()
+        .and_then(|s| s.get_item(THEME_KEY).ok().flatten())
+        .and_then(|json| decode_theme(&json));
+    stored.unwrap_or_else(|| Theme {
+        high_contrast: storage
+            .and_then(|s| s.get_item("high_contrast").ok().flatten())
+            .is_some_and(|v| v == "true"),
+        ..Theme::default()
+    })
+}
+
+pub fn save_theme(theme: &Theme) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(theme)) {
+        let _ = storage.set_item(THEME_KEY, &json);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_follows_faction_unless_colorblind() {
+        let mut theme = Theme::default();
+        assert_eq!(theme.palette(Faction::Warden), Palette::Warden);
+        assert_eq!(theme.palette(Faction::Colonial), Palette::Colonial);
+        theme.colorblind = true;
+        assert_eq!(theme.palette(Faction::Colonial), Palette::Colorblind);
+    }
+
+    #[test]
+    fn test_app_class() {
+        assert_eq!(Theme::default().app_class(Faction::Warden), "app");
+        let theme = Theme {
+            appearance: Appearance::Light,
+            colorblind: true,
+            high_contrast: true,
+        };
+        assert_eq!(
+            theme.app_class(Faction::Colonial),
+            "app colonial light colorblind"
+        );
+    }
+
+    #[test]
+    fn test_decode_theme() {
+        let theme = Theme {
+            appearance: Appearance::Light,
+            colorblind: false,
+            high_contrast: true,
+        };
+        let json = serde_json::to_string(&theme).unwrap();
+        assert_eq!(decode_theme(&json), Some(theme));
+        // Fields added later fall back to their defaults
+        assert_eq!(
+            decode_theme(r#"{"appearance":"light"}"#),
+            Some(Theme {
+                appearance: Appearance::Light,
+                ..Theme::default()
+            })
+        );
+        assert_eq!(decode_theme("not json"), None);
+    }
+}
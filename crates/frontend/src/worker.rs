@@ -0,0 +1,417 @@
+//! Building the map overlay off the main thread.
+//!
+//! The app's own WASM bundle is started a second time as a module web
+//! worker; `main` sees it has no window and runs [`run`] instead of the UI.
+//! The page sends it [`Request`]s and gets [`Response`]s back, both as JSON
+//! text, so dragging a marker only costs the main thread a serialization and
+//! an `innerHTML` swap. Heavier geometry can join as further request kinds.
+//!
+//! Jobs are coalesced: while the worker is busy only the newest job waits,
+//! so a drag never queues up work for positions already left behind. If the
+//! worker can't start (an older browser, or a bundle that needs a window),
+//! [`OverlayWorker::spawn`] reports it and the map keeps drawing in place.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use foxhole_shared::grid::MapScale;
+use foxhole_shared::merge::PlanChange;
+use foxhole_shared::models::{RangeRings, TargetStatus, Weapon, WindInput};
+use foxhole_shared::overlay::{
+    GridWindow, HeatCell, MarkerLabels, Palette, SelectedMarker, ShellSpread, StructureIcon,
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use crate::components::map_layers::{render_overlay, RenderContext, MAP_LAYERS};
+
+/// An owned copy of a [`RenderContext`] that can cross to the worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverlayJob {
+    guns: Vec<(f64, f64)>,
+    targets: Vec<(f64, f64)>,
+    spotters: Vec<(f64, f64)>,
+    enemies: Vec<(f64, f64)>,
+    fobs: Vec<(f64, f64)>,
+    bunkers: Vec<(f64, f64)>,
+    no_fire_zones: Vec<(f64, f64)>,
+    no_fire_radii: Vec<f64>,
+    danger_close: Vec<usize>,
+    gun_weapons: Vec<Option<Weapon>>,
+    gun_target_indices: Vec<Option<usize>>,
+    spreads: Vec<Option<ShellSpread>>,
+    salvo_sizes: Vec<u32>,
+    gun_alternates: Vec<Vec<(f64, f64)>>,
+    labels: MarkerLabels,
+    target_statuses: Vec<TargetStatus>,
+    range_rings: RangeRings,
+    heatmap: Vec<HeatCell>,
+    heatmap_color: String,
+    structures: Vec<StructureIcon>,
+    plan_changes: Vec<PlanChange>,
+    group: Vec<SelectedMarker>,
+    selected: Option<SelectedMarker>,
+    displace_gun: Option<usize>,
+    danger_weapon: Option<Weapon>,
+    wind: Option<WindInput>,
+    zoom: f64,
+    view: GridWindow,
+    container_width: f64,
+    always_keypad: bool,
+    high_contrast: bool,
+    palette: Palette,
+    map: MapScale,
+}
+
+impl OverlayJob {
+    /// The job for `ctx`, whose colours are `palette`'s.
+    pub fn new(ctx: &RenderContext, palette: Palette) -> Self {
+        OverlayJob {
+            guns: ctx.guns.to_vec(),
+            targets: ctx.targets.to_vec(),
+            spotters: ctx.spotters.to_vec(),
+            enemies: ctx.enemies.to_vec(),
+            fobs: ctx.fobs.to_vec(),
+            bunkers: ctx.bunkers.to_vec(),
+            no_fire_zones: ctx.no_fire_zones.to_vec(),
+            no_fire_radii: ctx.no_fire_radii.to_vec(),
+            danger_close: ctx.danger_close.to_vec(),
+            gun_weapons: ctx.gun_weapons.iter().map(|w| w.cloned()).collect(),
+            gun_target_indices: ctx.gun_target_indices.to_vec(),
+            spreads: ctx.spreads.to_vec(),
+            salvo_sizes: ctx.salvo_sizes.to_vec(),
+            gun_alternates: ctx.gun_alternates.to_vec(),
+            labels: ctx.labels.clone(),
+            target_statuses: ctx.target_statuses.to_vec(),
+            range_rings: ctx.range_rings.clone(),
+            heatmap: ctx.heatmap.to_vec(),
+            heatmap_color: ctx.heatmap_color.to_string(),
+            structures: ctx.structures.to_vec(),
+            plan_changes: ctx.plan_changes.to_vec(),
+            group: ctx.group.to_vec(),
+            selected: ctx.selected,
+            displace_gun: ctx.displace_gun,
+            danger_weapon: ctx.danger_weapon.cloned(),
+            wind: ctx.wind.cloned(),
+            zoom: ctx.zoom,
+            view: ctx.view,
+            container_width: ctx.container_width,
+            always_keypad: ctx.always_keypad,
+            high_contrast: ctx.high_contrast,
+            palette,
+            map: ctx.map,
+        }
+    }
+
+    /// The overlay SVG, as [`render_overlay`] draws it on the page.
+    pub fn render(&self) -> String {
+        let gun_weapons: Vec<Option<&Weapon>> =
+            self.gun_weapons.iter().map(Option::as_ref).collect();
+        let ctx = RenderContext {
+            guns: &self.guns,
+            targets: &self.targets,
+            spotters: &self.spotters,
+            enemies: &self.enemies,
+            fobs: &self.fobs,
+            bunkers: &self.bunkers,
+            no_fire_zones: &self.no_fire_zones,
+            no_fire_radii: &self.no_fire_radii,
+            danger_close: &self.danger_close,
+            gun_weapons: &gun_weapons,
+            gun_target_indices: &self.gun_target_indices,
+            spreads: &self.spreads,
+            salvo_sizes: &self.salvo_sizes,
+            gun_alternates: &self.gun_alternates,
+            labels: &self.labels,
+            target_statuses: &self.target_statuses,
+            range_rings: &self.range_rings,
+            heatmap: &self.heatmap,
+            heatmap_color: &self.heatmap_color,
+            structures: &self.structures,
+            plan_changes: &self.plan_changes,
+            group: &self.group,
+            selected: self.selected,
+            displace_gun: self.displace_gun,
+            danger_weapon: self.danger_weapon.as_ref(),
+            wind: self.wind.as_ref(),
+            zoom: self.zoom,
+            view: self.view,
+            container_width: self.container_width,
+            always_keypad: self.always_keypad,
+            high_contrast: self.high_contrast,
+            colors: self.palette.colors(),
+            map: self.map,
+        };
+        render_overlay(MAP_LAYERS, &ctx)
+    }
+}
+
+/// A message from the page to the worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Request {
+    Overlay(OverlayJob),
+}
+
+/// A message from the worker to the page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    /// The worker is listening; sent once at start-up.
+    Ready,
+    Overlay(String),
+}
+
+/// The worker's answer to `request`.
+pub fn handle(request: &Request) -> Response {
+    match request {
+        Request::Overlay(job) => Response::Overlay(job.render()),
+    }
+}
+
+/// Whether this copy of the app was started as the worker.
+pub fn is_worker() -> bool {
+    web_sys::window().is_none()
+}
+
+/// Serve requests until the worker is terminated.
+pub fn run() {
+    let scope: web_sys::DedicatedWorkerGlobalScope = web_sys::js_sys::global().unchecked_into();
+    let reply = scope.clone();
+    let on_message =
+        Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |event: web_sys::MessageEvent| {
+            let request = event
+                .data()
+                .as_string()
+                .and_then(|text| serde_json::from_str::<Request>(&text).ok());
+            if let Some(request) = request {
+                post(&reply, &handle(&request));
+            }
+        });
+    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+    post(&scope, &Response::Ready);
+}
+
+fn post(scope: &web_sys::DedicatedWorkerGlobalScope, response: &Response) {
+    if let Ok(text) = serde_json::to_string(response) {
+        let _ = scope.post_message(&JsValue::from_str(&text));
+    }
+}
+
+/// Jobs waiting for the worker: at most one being drawn and the newest
+/// one after it.
+#[derive(Debug, Default)]
+struct JobQueue {
+    ready: bool,
+    busy: bool,
+    waiting: Option<String>,
+}
+
+impl JobQueue {
+    /// Queue `job`; returns it if it should be sent now.
+    fn push(&mut self, job: String) -> Option<String> {
+        self.waiting = Some(job);
+        self.next()
+    }
+
+    /// The worker said it's ready; returns a job to send, if one waited.
+    fn ready(&mut self) -> Option<String> {
+        self.ready = true;
+        self.next()
+    }
+
+    /// The worker answered; returns the next job to send, if any.
+    fn done(&mut self) -> Option<String> {
+        self.busy = false;
+        self.next()
+    }
+
+    fn next(&mut self) -> Option<String> {
+        if !self.ready || self.busy {
+            return None;
+        }
+        let job = self.waiting.take()?;
+        self.busy = true;
+        Some(job)
+    }
+}
+
+#[wasm_bindgen]
+extern "C" {
+    /// URL of the app's JS glue, which the worker loads as well.
+    #[wasm_bindgen(thread_local_v2, js_namespace = ["import", "meta"], js_name = url)]
+    static GLUE_URL: JsValue;
+}
+
+/// The page's handle on the overlay worker. Dropping it stops the worker.
+pub struct OverlayWorker {
+    worker: web_sys::Worker,
+    /// Object URL of the start-up script, released with the worker.
+    script_url: String,
+    queue: Rc<RefCell<JobQueue>>,
+    _on_message: Closure<dyn FnMut(web_sys::MessageEvent)>,
+    _on_error: Closure<dyn FnMut()>,
+}
+
+impl OverlayWorker {
+    /// Start the worker. `on_ready` hears `true` once it can take jobs and
+    /// `false` if it fails; `on_svg` gets each finished overlay.
+    pub fn spawn(
+        on_ready: impl FnMut(bool) + 'static,
+        mut on_svg: impl FnMut(String) + 'static,
+    ) -> Option<Self> {
+        let glue = GLUE_URL.with(JsValue::as_string)?;
+        let script = format!("import init from {glue:?};\ninit();\n");
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("text/javascript");
+        let parts = web_sys::js_sys::Array::of1(&JsValue::from_str(&script));
+        let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).ok()?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).ok()?;
+        let worker_options = web_sys::WorkerOptions::new();
+        worker_options.set_type(web_sys::WorkerType::Module);
+        let Ok(worker) = web_sys::Worker::new_with_options(&url, &worker_options) else {
+            let _ = web_sys::Url::revoke_object_url(&url);
+            return None;
+        };
+
+        let queue = Rc::new(RefCell::new(JobQueue::default()));
+        let on_ready = Rc::new(RefCell::new(on_ready));
+        let (sender, jobs, ready) = (worker.clone(), queue.clone(), on_ready.clone());
+        let on_message = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(
+            move |event: web_sys::MessageEvent| {
+                let response = event
+                    .data()
+                    .as_string()
+                    .and_then(|text| serde_json::from_str::<Response>(&text).ok());
+                let next = match response {
+                    Some(Response::Ready) => {
+                        (ready.borrow_mut())(true);
+                        jobs.borrow_mut().ready()
+                    }
+                    Some(Response::Overlay(svg)) => {
+                        on_svg(svg);
+                        jobs.borrow_mut().done()
+                    }
+                    None => None,
+                };
+                if let Some(job) = next {
+                    let _ = sender.post_message(&JsValue::from_str(&job));
+                }
+            },
+        );
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        // A worker that fails to load or crashes hands drawing back to the page
+        let on_error = Closure::<dyn FnMut()>::new(move || (on_ready.borrow_mut())(false));
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        Some(OverlayWorker {
+            worker,
+            script_url: url,
+            queue,
+            _on_message: on_message,
+            _on_error: on_error,
+        })
+    }
+
+    /// Draw `job` in the worker; its SVG arrives through `on_svg`.
+    pub fn render(&self, job: OverlayJob) {
+        let Ok(text) = serde_json::to_string(&Request::Overlay(job)) else {
+            return;
+        };
+        if let Some(job) = self.queue.borrow_mut().push(text) {
+            let _ = self.worker.post_message(&JsValue::from_str(&job));
+        }
+    }
+}
+
+impl Drop for OverlayWorker {
+    fn drop(&mut self) {
+        self.worker.terminate();
+        let _ = web_sys::Url::revoke_object_url(&self.script_url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use foxhole_shared::overlay::{MarkerKind, REFERENCE_WIDTH};
+
+    fn context<'a>(
+        guns: &'a [(f64, f64)],
+        targets: &'a [(f64, f64)],
+        pairings: &'a [Option<usize>],
+        labels: &'a MarkerLabels,
+        range_rings: &'a RangeRings,
+    ) -> RenderContext<'a> {
+        RenderContext {
+            guns,
+            targets,
+            spotters: &[],
+            enemies: &[],
+            fobs: &[],
+            bunkers: &[],
+            no_fire_zones: &[],
+            no_fire_radii: &[],
+            danger_close: &[],
+            gun_weapons: &[],
+            gun_target_indices: pairings,
+            spreads: &[],
+            salvo_sizes: &[],
+            gun_alternates: &[],
+            labels,
+            target_statuses: &[],
+            range_rings,
+            heatmap: &[],
+            heatmap_color: "red",
+            structures: &[],
+            plan_changes: &[],
+            group: &[],
+            selected: None,
+            displace_gun: None,
+            danger_weapon: None,
+            wind: None,
+            zoom: 3.0,
+            view: GridWindow::ALL,
+            container_width: REFERENCE_WIDTH,
+            always_keypad: false,
+            high_contrast: false,
+            colors: Palette::Colonial.colors(),
+            map: MapScale::STANDARD,
+        }
+    }
+
+    #[test]
+    fn test_worker_draws_what_the_page_would() {
+        let mut labels = MarkerLabels::default();
+        labels.set(MarkerKind::Gun, 0, "Ridge <north>");
+        let rings = RangeRings::default();
+        let guns = [(100.5, 220.25), (300.0, 400.0)];
+        let targets = [(900.0, 120.0)];
+        let ctx = context(&guns, &targets, &[Some(0)], &labels, &rings);
+        let request = Request::Overlay(OverlayJob::new(&ctx, Palette::Colonial));
+        // Across the wire and back, as the worker sees it
+        let text = serde_json::to_string(&request).unwrap();
+        let received: Request = serde_json::from_str(&text).unwrap();
+        assert_eq!(serde_json::to_string(&received).unwrap(), text);
+        assert_eq!(
+            handle(&received),
+            Response::Overlay(render_overlay(MAP_LAYERS, &ctx))
+        );
+    }
+
+    #[test]
+    fn test_job_queue_keeps_only_the_newest_waiting_job() {
+        let mut queue = JobQueue::default();
+        // Nothing goes out before the worker is ready
+        assert_eq!(queue.push("a".into()), None);
+        assert_eq!(queue.push("b".into()), None);
+        assert_eq!(queue.ready(), Some("b".into()));
+        // One job at a time; later ones replace each other while it's drawn
+        assert_eq!(queue.push("c".into()), None);
+        assert_eq!(queue.push("d".into()), None);
+        assert_eq!(queue.done(), Some("d".into()));
+        assert_eq!(queue.done(), None);
+        assert_eq!(queue.push("e".into()), Some("e".into()));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::models::{FiringSolution, Position, Weapon, WindInput};
+use crate::models::{
+    DangerZone, FiringSolution, ImpactZone, NoFireZone, Position, Weapon, WindInput,
+};
 
 /// Euclidean distance between two positions.
 pub fn distance(a: Position, b: Position) -> f64 {
@@ -23,6 +25,37 @@ pub fn azimuth(gun: Position, target: Position) -> f64 {
     }
 }
 
+/// The position `dist` meters from `from` at compass `azimuth_deg`; the
+/// inverse of [`distance`] and [`azimuth`].
+pub fn offset_by_bearing(from: Position, dist: f64, azimuth_deg: f64) -> Position {
+    let rad = azimuth_deg.to_radians();
+    Position {
+        x: from.x + dist * rad.sin(),
+        y: from.y - dist * rad.cos(),
+    }
+}
+
+/// Where to aim next, from a spotter's report on the last shell.
+///
+/// The shell was fired at `aim` and seen `long` meters beyond `target`
+/// (negative: short) and `right` meters to its right (negative: left), both
+/// as seen along the spotter's line of sight to the target. Shifting the aim
+/// by the opposite of that miss puts the next shell on the target.
+pub fn apply_observer_correction(
+    spotter: Position,
+    target: Position,
+    aim: Position,
+    long: f64,
+    right: f64,
+) -> Position {
+    let los = azimuth(spotter, target);
+    let miss = offset_by_bearing(offset_by_bearing(aim, long, los), right, los + 90.0);
+    Position {
+        x: 2.0 * aim.x - miss.x,
+        y: 2.0 * aim.y - miss.y,
+    }
+}
+
 /// Interpolate accuracy radius for a given distance.
 /// acc_radius[0] at min_range, acc_radius[1] at max_range.
 pub fn accuracy_radius(weapon: &Weapon, dist: f64) -> f64 {
@@ -46,12 +79,78 @@ pub fn wind_drift_at_range(weapon: &Weapon, dist: f64) -> f64 {
     weapon.wind_drift[0] + t * (weapon.wind_drift[1] - weapon.wind_drift[0])
 }
 
+/// Estimated shell flight time in seconds for a given distance.
+/// flight_time[0] at min_range, flight_time[1] at max_range.
+/// Returns `None` when the weapon has no flight-time data.
+pub fn time_of_flight(weapon: &Weapon, dist: f64) -> Option<f64> {
+    if weapon.flight_time == [0.0, 0.0] {
+        return None;
+    }
+    let range_span = weapon.max_range - weapon.min_range;
+    if range_span <= 0.0 {
+        return Some(weapon.flight_time[0]);
+    }
+    let t = ((dist - weapon.min_range) / range_span).clamp(0.0, 1.0);
+    Some(weapon.flight_time[0] + t * (weapon.flight_time[1] - weapon.flight_time[0]))
+}
+
+/// Chance that one shell lands within `target_radius` of the aim point.
+/// Shells are taken to fall evenly over the accuracy circle, so this is the
+/// share of the circle's area the target covers.
+pub fn hit_probability(accuracy_radius: f64, target_radius: f64) -> f64 {
+    if target_radius <= 0.0 {
+        return 0.0;
+    }
+    if accuracy_radius <= target_radius {
+        return 1.0;
+    }
+    (target_radius / accuracy_radius).powi(2)
+}
+
+/// Chance that at least one shell of a `shells`-shell salvo hits the target.
+pub fn salvo_hit_probability(accuracy_radius: f64, target_radius: f64, shells: u32) -> f64 {
+    salvo_chance(hit_probability(accuracy_radius, target_radius), shells)
+}
+
+/// Chance that at least one of `shells` shells hits, when each one does
+/// with probability `single`.
+pub fn salvo_chance(single: f64, shells: u32) -> f64 {
+    1.0 - (1.0 - single).powi(shells as i32)
+}
+
+/// An area-fire salvo strings out along the line of fire: its impact zone is
+/// taken to be this many times longer than it is wide.
+pub const AREA_FIRE_ASPECT: f64 = 2.0;
+
+/// Impact zone of an area-fire salvo fired `dist` meters along `azimuth_deg`.
+/// It runs the width of the accuracy circle along the line of fire and is
+/// [`AREA_FIRE_ASPECT`] times narrower across it.
+pub fn impact_zone(weapon: &Weapon, dist: f64, azimuth_deg: f64) -> ImpactZone {
+    let length = 2.0 * accuracy_radius(weapon, dist);
+    ImpactZone {
+        length,
+        width: length / AREA_FIRE_ASPECT,
+        azimuth: azimuth_deg,
+    }
+}
+
+/// Chance that one rocket lands within `target_radius` of the aim point.
+/// Rockets are taken to fall evenly over the elliptical `zone`; once the
+/// target is wider than the zone this overestimates a little.
+pub fn zone_hit_probability(zone: &ImpactZone, target_radius: f64) -> f64 {
+    if target_radius <= 0.0 {
+        return 0.0;
+    }
+    let zone_area = (zone.length / 2.0) * (zone.width / 2.0);
+    (target_radius.powi(2) / zone_area).min(1.0)
+}
+
 /// Compute the wind offset vector in meters (dx_wind, dy_wind).
 /// Wind direction is where the wind pushes shells TO (the direction flags point).
 /// In Foxhole, wind flags fly in the direction of the wind, so players read the
 /// flag bearing directly from their compass.
 /// Drift magnitude scales with weapon type, range, and wind strength (0-5).
-fn wind_offset(wind: &WindInput, weapon: &Weapon, dist: f64) -> (f64, f64) {
+pub fn wind_offset(wind: &WindInput, weapon: &Weapon, dist: f64) -> (f64, f64) {
     let base_drift = wind_drift_at_range(weapon, dist);
     let clamped_strength = (wind.strength as f64).min(5.0);
     let strength_m = base_drift * (clamped_strength / 5.0);
@@ -91,6 +190,12 @@ pub fn firing_solution(
         }
         _ => (None, None, None),
     };
+    // The shell travels the distance actually fired, which is the wind-corrected one.
+    let tof = time_of_flight(weapon, wind_adjusted_distance.unwrap_or(dist));
+    // Rockets string out along the bearing they are actually fired on
+    let impact_zone = weapon
+        .area_fire
+        .then(|| impact_zone(weapon, dist, wind_adjusted_azimuth.unwrap_or(az)));
 
     FiringSolution {
         azimuth: az,
@@ -100,6 +205,39 @@ pub fn firing_solution(
         wind_adjusted_azimuth,
         wind_adjusted_distance,
         wind_offset_meters,
+        time_of_flight: tof,
+        impact_zone,
+    }
+}
+
+/// Counter-battery danger zone: where an enemy `weapon` must be to hit `gun`.
+/// Range limits are symmetric, so this is the weapon's range annulus centred
+/// on our own gun rather than on the enemy.
+pub fn counter_battery(gun: Position, weapon: &Weapon) -> DangerZone {
+    DangerZone {
+        center: gun,
+        min_radius: weapon.min_range,
+        max_radius: weapon.max_range,
+    }
+}
+
+/// Indexes of the `zones` that shells aimed at `target` could land in: those
+/// the accuracy circle (`accuracy_radius` m around it) reaches into. An
+/// area-fire salvo's impact zone fits inside the same circle.
+pub fn danger_close(target: Position, accuracy_radius: f64, zones: &[NoFireZone]) -> Vec<usize> {
+    zones
+        .iter()
+        .enumerate()
+        .filter(|(_, z)| distance(target, z.center) < accuracy_radius + z.radius)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+impl DangerZone {
+    /// Whether an enemy gun at `enemy` can reach this zone's gun.
+    pub fn contains(&self, enemy: Position) -> bool {
+        let d = distance(self.center, enemy);
+        d >= self.min_radius && d <= self.max_radius
     }
 }
 
@@ -116,9 +254,36 @@ mod tests {
             max_range: 300.0,
             acc_radius: [10.0, 30.0],
             wind_drift: [10.0, 30.0],
+            flight_time: [4.0, 8.0],
+            ammo: Vec::new(),
+            deployment: Default::default(),
+            area_fire: false,
         }
     }
 
+    #[test]
+    fn test_observer_correction_along_line_of_sight() {
+        // Spotter due south of the target, looking north
+        let spotter = Position { x: 500.0, y: 800.0 };
+        let target = Position { x: 500.0, y: 500.0 };
+        // 30 m long lands north of the target, so aim 30 m south
+        let aim = apply_observer_correction(spotter, target, target, 30.0, 0.0);
+        assert!((aim.x - 500.0).abs() < 1e-9 && (aim.y - 530.0).abs() < 1e-9);
+        // 15 m right is east for this spotter, so aim 15 m west
+        let aim = apply_observer_correction(spotter, target, target, 0.0, 15.0);
+        assert!((aim.x - 485.0).abs() < 1e-9 && (aim.y - 500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_observer_correction_shifts_from_current_aim() {
+        // Spotter west of the target, looking east: long is east, right is south
+        let spotter = Position { x: 0.0, y: 0.0 };
+        let target = Position { x: 100.0, y: 0.0 };
+        let aim = Position { x: 110.0, y: 5.0 };
+        let next = apply_observer_correction(spotter, target, aim, -20.0, -10.0);
+        assert!((next.x - 130.0).abs() < 1e-9 && (next.y - 15.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_distance_horizontal() {
         let a = Position { x: 0.0, y: 0.0 };
@@ -148,6 +313,17 @@ mod tests {
         assert!((azimuth(gun, target) - 90.0).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_offset_by_bearing_inverts_azimuth() {
+        let spotter = Position { x: 500.0, y: 500.0 };
+        let gun = offset_by_bearing(spotter, 125.0, 40.0);
+        assert!((distance(spotter, gun) - 125.0).abs() < 1e-9);
+        assert!((azimuth(spotter, gun) - 40.0).abs() < 1e-9);
+        // Due north is up the map
+        let north = offset_by_bearing(spotter, 100.0, 0.0);
+        assert!((north.x - 500.0).abs() < 1e-9 && (north.y - 400.0).abs() < 1e-9);
+    }
+
     #[test]
     fn test_azimuth_south() {
         let gun = Position { x: 0.0, y: 0.0 };
@@ -198,6 +374,62 @@ mod tests {
         assert!((wind_drift_at_range(&w, 200.0) - 20.0).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_counter_battery_zone_uses_enemy_ranges() {
+        let w = test_weapon();
+        let gun = Position { x: 500.0, y: 500.0 };
+        let zone = counter_battery(gun, &w);
+        assert!((zone.min_radius - 100.0).abs() < 1e-9);
+        assert!((zone.max_radius - 300.0).abs() < 1e-9);
+        // 200m east: inside the ring
+        assert!(zone.contains(Position { x: 700.0, y: 500.0 }));
+        // Too close (inside min range) and too far
+        assert!(!zone.contains(Position { x: 550.0, y: 500.0 }));
+        assert!(!zone.contains(Position { x: 900.0, y: 500.0 }));
+    }
+
+    #[test]
+    fn test_danger_close_when_accuracy_circle_reaches_zone() {
+        let target = Position { x: 500.0, y: 500.0 };
+        let zones = [
+            // 60 m away with a 50 m radius: a 20 m circle reaches it
+            NoFireZone {
+                center: Position { x: 560.0, y: 500.0 },
+                radius: 50.0,
+            },
+            // 200 m away: clear
+            NoFireZone {
+                center: Position { x: 500.0, y: 700.0 },
+                radius: 50.0,
+            },
+        ];
+        assert_eq!(danger_close(target, 20.0, &zones), vec![0]);
+        assert!(danger_close(target, 5.0, &zones).is_empty());
+        assert_eq!(danger_close(target, 160.0, &zones), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_time_of_flight_interpolates() {
+        let w = test_weapon();
+        assert!((time_of_flight(&w, 100.0).unwrap() - 4.0).abs() < 1e-9);
+        assert!((time_of_flight(&w, 200.0).unwrap() - 6.0).abs() < 1e-9);
+        assert!((time_of_flight(&w, 300.0).unwrap() - 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_time_of_flight_unknown() {
+        let mut w = test_weapon();
+        w.flight_time = [0.0, 0.0];
+        assert!(time_of_flight(&w, 200.0).is_none());
+        let sol = firing_solution(
+            Position { x: 0.0, y: 0.0 },
+            Position { x: 0.0, y: -200.0 },
+            &w,
+            None,
+        );
+        assert!(sol.time_of_flight.is_none());
+    }
+
     #[test]
     fn test_wind_drift_scales_with_strength() {
         // At max range with wind_drift=[10,30], base drift = 30m
@@ -242,6 +474,22 @@ mod tests {
         assert!((sol_max.wind_offset_meters.unwrap() - 30.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_hit_probability_is_area_share() {
+        assert!((hit_probability(20.0, 10.0) - 0.25).abs() < 1e-9);
+        assert_eq!(hit_probability(5.0, 10.0), 1.0);
+        assert_eq!(hit_probability(0.0, 10.0), 1.0);
+        assert_eq!(hit_probability(20.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_salvo_hit_probability() {
+        // 25% per shell: a 2-shell salvo misses only 0.75^2 of the time
+        assert!((salvo_hit_probability(20.0, 10.0, 1) - 0.25).abs() < 1e-9);
+        assert!((salvo_hit_probability(20.0, 10.0, 2) - 0.4375).abs() < 1e-9);
+        assert_eq!(salvo_hit_probability(20.0, 10.0, 0), 0.0);
+    }
+
     #[test]
     fn test_firing_solution_no_wind() {
         let gun = Position { x: 0.0, y: 0.0 };
@@ -252,6 +500,51 @@ mod tests {
         assert!((sol.distance - 200.0).abs() < 1e-6);
         assert!(sol.in_range);
         assert!(sol.wind_adjusted_azimuth.is_none());
+        assert!((sol.time_of_flight.unwrap() - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_area_fire_solution_has_impact_zone() {
+        let gun = Position { x: 0.0, y: 0.0 };
+        let target = Position { x: 200.0, y: 0.0 };
+        assert!(firing_solution(gun, target, &test_weapon(), None)
+            .impact_zone
+            .is_none());
+
+        let rockets = Weapon {
+            area_fire: true,
+            ..test_weapon()
+        };
+        let sol = firing_solution(gun, target, &rockets, None);
+        let zone = sol.impact_zone.unwrap();
+        // 20 m accuracy at 200 m: 40 m along the line of fire, 20 m across
+        assert!((zone.length - 40.0).abs() < 1e-9);
+        assert!((zone.width - 20.0).abs() < 1e-9);
+        assert!((zone.azimuth - 90.0).abs() < 1e-9);
+
+        // Lined up with the bearing the salvo is fired on, not the target's
+        let wind = WindInput {
+            direction: 0.0,
+            strength: 5,
+        };
+        let sol = firing_solution(gun, target, &rockets, Some(&wind));
+        assert_eq!(
+            sol.impact_zone.unwrap().azimuth,
+            sol.wind_adjusted_azimuth.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_zone_hit_probability_is_area_share() {
+        let zone = ImpactZone {
+            length: 40.0,
+            width: 20.0,
+            azimuth: 0.0,
+        };
+        // A 5 m target covers 25 of the zone's 200 square units (both times π)
+        assert!((zone_hit_probability(&zone, 5.0) - 0.125).abs() < 1e-9);
+        assert_eq!(zone_hit_probability(&zone, 30.0), 1.0);
+        assert_eq!(zone_hit_probability(&zone, 0.0), 0.0);
     }
 
     #[test]
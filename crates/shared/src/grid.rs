@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 /// Foxhole map grid system.
 ///
 /// Each hex region is 2184m wide x 1890m tall.
@@ -5,12 +7,14 @@
 /// The grid starts at map origin and does NOT span the full region — there's
 /// unused space past column Q (2125m) and row 15 (1875m).
 /// Each grid cell has a 3x3 keypad sub-grid (k1-k9).
-/// Map images are 2048x1776 pixels.
+/// Map images cover the whole region; their pixel size varies per map (see
+/// [`MapScale`]), the stock images being 2048x1776 pixels. Custom images can
+/// cover any part of it, aligned by calibration.
 // World dimensions in meters
 pub const MAP_WIDTH_M: f64 = 2184.0;
 pub const MAP_HEIGHT_M: f64 = 1890.0;
 
-// Map image dimensions in pixels
+// Stock map image dimensions in pixels, for maps that don't give their own
 pub const MAP_WIDTH_PX: f64 = 2048.0;
 pub const MAP_HEIGHT_PX: f64 = 1776.0;
 
@@ -28,23 +32,102 @@ pub const GRID_CELL_SIZE_M: f64 = 125.0;
 const GRID_WIDTH_M: f64 = GRID_COLS as f64 * GRID_CELL_SIZE_M; // 2125m
 const GRID_HEIGHT_M: f64 = GRID_ROWS as f64 * GRID_CELL_SIZE_M; // 1875m
 
-pub const METERS_PER_PIXEL_X: f64 = MAP_WIDTH_M / MAP_WIDTH_PX;
-pub const METERS_PER_PIXEL_Y: f64 = MAP_HEIGHT_M / MAP_HEIGHT_PX;
-
-/// Convert pixel coordinates to meter coordinates.
-pub fn px_to_meters(px_x: f64, px_y: f64) -> (f64, f64) {
-    (px_x * METERS_PER_PIXEL_X, px_y * METERS_PER_PIXEL_Y)
+/// How a map image lines up with the region: its pixel size, and where its
+/// pixels fall in meters. Stock images span the whole region, so their size
+/// alone fixes the scale; calibrated custom images carry their own.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MapScale {
+    pub width_px: f64,
+    pub height_px: f64,
+    /// Meters covered by one pixel along each axis.
+    pub meters_per_px: (f64, f64),
+    /// Position (meters) of the image's top-left corner.
+    pub origin_m: (f64, f64),
 }
 
-/// Convert meter coordinates to pixel coordinates.
-pub fn meters_to_px(m_x: f64, m_y: f64) -> (f64, f64) {
-    (m_x / METERS_PER_PIXEL_X, m_y / METERS_PER_PIXEL_Y)
+impl Default for MapScale {
+    fn default() -> Self {
+        Self::STANDARD
+    }
 }
 
-/// Convert a meter distance to pixel distance (using average scale).
-pub fn meters_to_px_distance(meters: f64) -> f64 {
-    let avg_scale = (MAP_WIDTH_PX / MAP_WIDTH_M + MAP_HEIGHT_PX / MAP_HEIGHT_M) / 2.0;
-    meters * avg_scale
+impl MapScale {
+    /// The stock 2048x1776 map images.
+    pub const STANDARD: MapScale = MapScale::for_image(MAP_WIDTH_PX, MAP_HEIGHT_PX);
+
+    /// An image of the given size spanning the whole region.
+    pub const fn for_image(width_px: f64, height_px: f64) -> MapScale {
+        MapScale {
+            width_px,
+            height_px,
+            meters_per_px: (MAP_WIDTH_M / width_px, MAP_HEIGHT_M / height_px),
+            origin_m: (0.0, 0.0),
+        }
+    }
+
+    /// An image aligned by two reference points, each an image pixel and the
+    /// position (meters) it shows. `None` when the points don't pin down both
+    /// axes: they share a row or column, or run the wrong way.
+    pub fn calibrated(
+        width_px: f64,
+        height_px: f64,
+        a: ((f64, f64), (f64, f64)),
+        b: ((f64, f64), (f64, f64)),
+    ) -> Option<MapScale> {
+        let ((apx, am), (bpx, bm)) = (a, b);
+        let axis = |pa: f64, pb: f64, ma: f64, mb: f64| {
+            let mpp = (mb - ma) / (pb - pa);
+            ((pb - pa).abs() >= 1.0 && mpp.is_finite() && mpp > 0.0).then_some(mpp)
+        };
+        let mpp_x = axis(apx.0, bpx.0, am.0, bm.0)?;
+        let mpp_y = axis(apx.1, bpx.1, am.1, bm.1)?;
+        Some(MapScale {
+            width_px,
+            height_px,
+            meters_per_px: (mpp_x, mpp_y),
+            origin_m: (am.0 - apx.0 * mpp_x, am.1 - apx.1 * mpp_y),
+        })
+    }
+
+    pub fn meters_per_px_x(&self) -> f64 {
+        self.meters_per_px.0
+    }
+
+    pub fn meters_per_px_y(&self) -> f64 {
+        self.meters_per_px.1
+    }
+
+    /// Convert pixel coordinates to meter coordinates.
+    pub fn px_to_meters(&self, px_x: f64, px_y: f64) -> (f64, f64) {
+        (
+            self.origin_m.0 + px_x * self.meters_per_px.0,
+            self.origin_m.1 + px_y * self.meters_per_px.1,
+        )
+    }
+
+    /// Convert meter coordinates to pixel coordinates.
+    pub fn meters_to_px(&self, m_x: f64, m_y: f64) -> (f64, f64) {
+        (
+            (m_x - self.origin_m.0) / self.meters_per_px.0,
+            (m_y - self.origin_m.1) / self.meters_per_px.1,
+        )
+    }
+
+    /// Convert a meter distance to pixel distance (using average scale).
+    pub fn meters_to_px_distance(&self, meters: f64) -> f64 {
+        let avg_scale = (1.0 / self.meters_per_px.0 + 1.0 / self.meters_per_px.1) / 2.0;
+        meters * avg_scale
+    }
+
+    /// Get the pixel X position for a grid column line (0-based column index).
+    pub fn grid_col_px(&self, col: usize) -> f64 {
+        (col as f64 * GRID_CELL_SIZE_M - self.origin_m.0) / self.meters_per_px.0
+    }
+
+    /// Get the pixel Y position for a grid row line (0-based row index).
+    pub fn grid_row_px(&self, row: usize) -> f64 {
+        (row as f64 * GRID_CELL_SIZE_M - self.origin_m.1) / self.meters_per_px.1
+    }
 }
 
 /// Column letter for a given column index (0-based). A=0, Q=16.
@@ -52,17 +135,23 @@ pub fn col_letter(col: usize) -> char {
     (b'A' + col as u8) as char
 }
 
+/// Grid cell (0-based column, row) holding a meter position; positions off
+/// the grid fall in the nearest edge cell.
+pub fn grid_cell(m_x: f64, m_y: f64) -> (usize, usize) {
+    let m_x = m_x.clamp(0.0, GRID_WIDTH_M - BOUNDARY_EPSILON);
+    let m_y = m_y.clamp(0.0, GRID_HEIGHT_M - BOUNDARY_EPSILON);
+    let col = (m_x / GRID_CELL_SIZE_M) as usize;
+    let row = (m_y / GRID_CELL_SIZE_M) as usize;
+    (col.min(GRID_COLS - 1), row.min(GRID_ROWS - 1))
+}
+
 /// Format a meter position as a Foxhole grid coordinate (e.g., "G9k3").
 pub fn format_grid_coord(m_x: f64, m_y: f64) -> String {
     // Clamp to grid bounds (not full map bounds)
     let m_x = m_x.clamp(0.0, GRID_WIDTH_M - BOUNDARY_EPSILON);
     let m_y = m_y.clamp(0.0, GRID_HEIGHT_M - BOUNDARY_EPSILON);
 
-    let col = (m_x / GRID_CELL_SIZE_M) as usize;
-    let row = (m_y / GRID_CELL_SIZE_M) as usize;
-
-    let col = col.min(GRID_COLS - 1);
-    let row = row.min(GRID_ROWS - 1);
+    let (col, row) = grid_cell(m_x, m_y);
 
     // Sub-grid position within the cell (0.0 to 1.0)
     let sub_x = (m_x - col as f64 * GRID_CELL_SIZE_M) / GRID_CELL_SIZE_M;
@@ -95,38 +184,133 @@ pub fn format_grid_coord(m_x: f64, m_y: f64) -> String {
     format!("{}{}k{}", col_letter(col), row + 1, keypad)
 }
 
-/// Get the pixel X position for a grid column line (0-based column index).
-pub fn grid_col_px(col: usize) -> f64 {
-    (col as f64 * GRID_CELL_SIZE_M) / METERS_PER_PIXEL_X
-}
-
-/// Get the pixel Y position for a grid row line (0-based row index).
-pub fn grid_row_px(row: usize) -> f64 {
-    (row as f64 * GRID_CELL_SIZE_M) / METERS_PER_PIXEL_Y
+/// Parse a grid coordinate ("G9k3", or just "G9") into meters.
+///
+/// Returns the centre of the keypad, or of the whole cell when no keypad is
+/// given. Further keypads refine the last one for a finer fix, e.g. "G9k3k7"
+/// is the top-left ninth of G9k3. Case-insensitive and ignores spaces; `None`
+/// for anything outside A1–Q15 / k1–k9.
+pub fn parse_grid_coord(s: &str) -> Option<(f64, f64)> {
+    let s = s
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    let mut chars = s.chars();
+    let col = chars.next()?;
+    if !col.is_ascii_uppercase() {
+        return None;
+    }
+    let col = (col as u8 - b'A') as usize;
+    let mut parts = chars.as_str().split('K');
+    let row = parts.next()?;
+    // Leading zeros / signs aren't valid grid notation
+    if row.is_empty() || row.starts_with('0') || !row.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let row: usize = row.parse().ok()?;
+    if col >= GRID_COLS || !(1..=GRID_ROWS).contains(&row) {
+        return None;
+    }
+    // Narrow a square (top-left corner, side) down one keypad at a time
+    let mut x = col as f64 * GRID_CELL_SIZE_M;
+    let mut y = (row - 1) as f64 * GRID_CELL_SIZE_M;
+    let mut size = GRID_CELL_SIZE_M;
+    for keypad in parts {
+        // Inverse of the numpad layout in `format_grid_coord`
+        let (kx, ky) = match keypad {
+            "7" => (0, 0),
+            "8" => (1, 0),
+            "9" => (2, 0),
+            "4" => (0, 1),
+            "5" => (1, 1),
+            "6" => (2, 1),
+            "1" => (0, 2),
+            "2" => (1, 2),
+            "3" => (2, 2),
+            _ => return None,
+        };
+        size /= 3.0;
+        x += kx as f64 * size;
+        y += ky as f64 * size;
+    }
+    Some((x + size / 2.0, y + size / 2.0))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const SCALE: MapScale = MapScale::STANDARD;
+
+    #[test]
+    fn test_grid_cell() {
+        assert_eq!(grid_cell(0.0, 0.0), (0, 0));
+        assert_eq!(grid_cell(130.0, 260.0), (1, 2));
+        // Off-grid positions land in the nearest edge cell
+        assert_eq!(grid_cell(-50.0, MAP_HEIGHT_M), (0, GRID_ROWS - 1));
+        assert_eq!(grid_cell(MAP_WIDTH_M, 10.0), (GRID_COLS - 1, 0));
+    }
+
     #[test]
     fn test_px_to_meters_origin() {
-        let (mx, my) = px_to_meters(0.0, 0.0);
+        let (mx, my) = SCALE.px_to_meters(0.0, 0.0);
         assert!((mx - 0.0).abs() < 1e-9);
         assert!((my - 0.0).abs() < 1e-9);
     }
 
     #[test]
     fn test_px_to_meters_corner() {
-        let (mx, my) = px_to_meters(MAP_WIDTH_PX, MAP_HEIGHT_PX);
+        let (mx, my) = SCALE.px_to_meters(MAP_WIDTH_PX, MAP_HEIGHT_PX);
+        assert!((mx - MAP_WIDTH_M).abs() < 0.1);
+        assert!((my - MAP_HEIGHT_M).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_scale_follows_image_size() {
+        // A double-resolution image covers the same region at twice the pixels
+        let hi_res = MapScale::for_image(4096.0, 3552.0);
+        let (mx, my) = hi_res.px_to_meters(4096.0, 3552.0);
         assert!((mx - MAP_WIDTH_M).abs() < 0.1);
         assert!((my - MAP_HEIGHT_M).abs() < 0.1);
+        let (px, py) = hi_res.meters_to_px(500.0, 500.0);
+        let (spx, spy) = SCALE.meters_to_px(500.0, 500.0);
+        assert!((px - 2.0 * spx).abs() < 1e-9);
+        assert!((py - 2.0 * spy).abs() < 1e-9);
+        assert!(
+            (hi_res.meters_to_px_distance(100.0) - 2.0 * SCALE.meters_to_px_distance(100.0)).abs()
+                < 1e-9
+        );
+        assert!((hi_res.grid_col_px(3) - 2.0 * SCALE.grid_col_px(3)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_calibrated_scale_maps_reference_points() {
+        // A crop of the region's centre at 2 px per meter
+        let a = ((100.0, 200.0), (800.0, 700.0));
+        let b = ((1100.0, 800.0), (1300.0, 1000.0));
+        let crop = MapScale::calibrated(1200.0, 900.0, a, b).unwrap();
+        for (px, m) in [a, b] {
+            let (mx, my) = crop.px_to_meters(px.0, px.1);
+            assert!((mx - m.0).abs() < 1e-9 && (my - m.1).abs() < 1e-9);
+            let (x, y) = crop.meters_to_px(m.0, m.1);
+            assert!((x - px.0).abs() < 1e-9 && (y - px.1).abs() < 1e-9);
+        }
+        assert!((crop.meters_to_px_distance(10.0) - 20.0).abs() < 1e-9);
+        // Column G (index 6) starts at 750 m, 50 m left of point a
+        assert!((crop.grid_col_px(6) - 0.0).abs() < 1e-9);
+
+        // Points in the same column, or running backwards, can't calibrate
+        let same_col = ((1100.0, 200.0), (1300.0, 1000.0));
+        assert_eq!(MapScale::calibrated(1200.0, 900.0, a, same_col), None);
+        let flipped = ((1100.0, 800.0), (300.0, 1000.0));
+        assert_eq!(MapScale::calibrated(1200.0, 900.0, a, flipped), None);
     }
 
     #[test]
     fn test_meters_to_px_roundtrip() {
-        let (mx, my) = px_to_meters(512.0, 444.0);
-        let (px, py) = meters_to_px(mx, my);
+        let (mx, my) = SCALE.px_to_meters(512.0, 444.0);
+        let (px, py) = SCALE.meters_to_px(mx, my);
         assert!((px - 512.0).abs() < 0.01);
         assert!((py - 444.0).abs() < 0.01);
     }
@@ -189,28 +373,72 @@ mod tests {
         assert_eq!(format_grid_coord(cs - 1.0, cs - 1.0), "A1k3");
     }
 
+    #[test]
+    fn test_parse_grid_coord_roundtrip() {
+        for col in 0..GRID_COLS {
+            for row in 1..=GRID_ROWS {
+                for k in 1..=9 {
+                    let coord = format!("{}{}k{}", col_letter(col), row, k);
+                    let (x, y) = parse_grid_coord(&coord).unwrap();
+                    assert_eq!(format_grid_coord(x, y), coord);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_grid_coord_cell_centre() {
+        let (x, y) = parse_grid_coord("a1").unwrap();
+        assert!((x - 62.5).abs() < 1e-9);
+        assert!((y - 62.5).abs() < 1e-9);
+        assert_eq!(parse_grid_coord(" g9K3 "), parse_grid_coord("G9k3"));
+    }
+
+    #[test]
+    fn test_parse_grid_coord_fine_keypad() {
+        // k7 of A1k3 is the top-left ninth of the bottom-right keypad
+        let third = GRID_CELL_SIZE_M / 3.0;
+        let (x, y) = parse_grid_coord("A1 k3 k7").unwrap();
+        assert!((x - (2.0 * third + third / 6.0)).abs() < 1e-9);
+        assert!((y - (2.0 * third + third / 6.0)).abs() < 1e-9);
+        // The middle sub-keypad is the keypad's own centre
+        let fine = parse_grid_coord("G9k3k5").unwrap();
+        let coarse = parse_grid_coord("G9k3").unwrap();
+        assert!((fine.0 - coarse.0).abs() < 1e-9 && (fine.1 - coarse.1).abs() < 1e-9);
+        assert_eq!(format_grid_coord(x, y), "A1k3");
+    }
+
+    #[test]
+    fn test_parse_grid_coord_rejects_invalid() {
+        for bad in [
+            "", "R1", "A0", "A16", "A01", "A1k0", "A1k10", "A1k", "1A", "Ak5", "A-1", "A1k3k",
+        ] {
+            assert_eq!(parse_grid_coord(bad), None, "{bad}");
+        }
+    }
+
     #[test]
     fn test_grid_col_px() {
-        assert!((grid_col_px(0) - 0.0).abs() < 1e-9);
-        let expected = GRID_CELL_SIZE_M / METERS_PER_PIXEL_X;
-        assert!((grid_col_px(1) - expected).abs() < 0.01);
+        assert!((SCALE.grid_col_px(0) - 0.0).abs() < 1e-9);
+        let expected = GRID_CELL_SIZE_M / SCALE.meters_per_px_x();
+        assert!((SCALE.grid_col_px(1) - expected).abs() < 0.01);
         // Last column line should be less than full image width
-        assert!(grid_col_px(GRID_COLS) < MAP_WIDTH_PX);
+        assert!(SCALE.grid_col_px(GRID_COLS) < MAP_WIDTH_PX);
     }
 
     #[test]
     fn test_grid_row_px() {
-        assert!((grid_row_px(0) - 0.0).abs() < 1e-9);
-        let expected = GRID_CELL_SIZE_M / METERS_PER_PIXEL_Y;
-        assert!((grid_row_px(1) - expected).abs() < 0.01);
+        assert!((SCALE.grid_row_px(0) - 0.0).abs() < 1e-9);
+        let expected = GRID_CELL_SIZE_M / SCALE.meters_per_px_y();
+        assert!((SCALE.grid_row_px(1) - expected).abs() < 0.01);
         // Last row line should be less than full image height
-        assert!(grid_row_px(GRID_ROWS) < MAP_HEIGHT_PX);
+        assert!(SCALE.grid_row_px(GRID_ROWS) < MAP_HEIGHT_PX);
     }
 
     #[test]
     fn test_meters_to_px_distance() {
         // 100m should convert to a reasonable pixel distance
-        let px = meters_to_px_distance(100.0);
+        let px = SCALE.meters_to_px_distance(100.0);
         assert!(px > 85.0 && px < 105.0); // roughly 94 pixels
     }
 }
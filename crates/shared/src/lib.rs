@@ -1,3 +1,10 @@
 pub mod calc;
 pub mod grid;
+pub mod logistics;
+pub mod merge;
 pub mod models;
+pub mod overlay;
+pub mod research;
+pub mod slug;
+pub mod template;
+pub mod terrain;
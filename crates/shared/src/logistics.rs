@@ -0,0 +1,213 @@
+//! Barrage logistics: the crates, vehicle loads and weight a planned barrage
+//! needs, so an operation can requisition its shells before the guns go up.
+//!
+//! Stack sizes and weights follow the game's factory crates. Update them here
+//! when a game patch changes them.
+
+use crate::calc;
+
+/// Crates a truck carries, one per inventory slot.
+pub const TRUCK_CRATES: u32 = 15;
+/// Crates packed into one shipping container, the load of a flatbed.
+pub const FLATBED_CRATES: u32 = 60;
+/// Chance of at least one hit the coverage estimate plans for.
+pub const COVERAGE_CONFIDENCE: f64 = 0.9;
+/// Most shells the coverage estimate asks for on one target.
+pub const MAX_ESTIMATE_SHELLS: u32 = 500;
+
+/// An artillery shell type, as it comes out of the factory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Shell {
+    Mortar,
+    Mm120,
+    Mm150,
+    Mm300,
+    Rocket3C,
+    Rocket4C,
+}
+
+impl Shell {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Mortar => "Mortar Shell",
+            Shell::Mm120 => "120mm",
+            Shell::Mm150 => "150mm",
+            Shell::Mm300 => "300mm",
+            Shell::Rocket3C => "3C-High Explosive Rocket",
+            Shell::Rocket4C => "4C-Fire Rocket",
+        }
+    }
+
+    /// Shells in one factory crate.
+    pub fn per_crate(self) -> u32 {
+        match self {
+            Shell::Mortar => 15,
+            Shell::Mm120 => 15,
+            Shell::Mm150 => 10,
+            Shell::Mm300 => 5,
+            Shell::Rocket3C => 10,
+            Shell::Rocket4C => 10,
+        }
+    }
+
+    /// Weight of one shell in kilograms.
+    pub fn weight_kg(self) -> f64 {
+        match self {
+            Shell::Mortar => 4.0,
+            Shell::Mm120 => 25.0,
+            Shell::Mm150 => 45.0,
+            Shell::Mm300 => 115.0,
+            Shell::Rocket3C => 60.0,
+            Shell::Rocket4C => 60.0,
+        }
+    }
+
+    /// The shell weapon `slug` fires, if it's one this table knows.
+    pub fn for_weapon(slug: &str) -> Option<Shell> {
+        let shell = match slug {
+            "cremari-mortar" => Shell::Mortar,
+            "120-68-koronides-field-gun"
+            | "conquerer-titan-120mm"
+            | "ac-b-trident"
+            | "lance-46-sarissa"
+            | "huber-lariat-120mm"
+            | "blacksteele-callahan-120mm" => Shell::Mm120,
+            "titan-150mm"
+            | "50-500-thunderbolt-cannon"
+            | "callahan-150mm"
+            | "flood-mk-ix-stain"
+            | "huber-exalt-150mm" => Shell::Mm150,
+            "storm-cannon" | "tempest-cannon-ra-2" => Shell::Mm300,
+            "r-17-retiarius-skirmisher" | "dae-3b-2-hades-net" | "niska-rycker-mk-ix-skycaller" => {
+                Shell::Rocket3C
+            }
+            "t13-deioneus-rocket-battery" | "rycker-4-3-f-wasp-nest" => Shell::Rocket4C,
+            _ => return None,
+        };
+        Some(shell)
+    }
+}
+
+/// Shells needed for a [`COVERAGE_CONFIDENCE`] chance of hitting within
+/// `target_radius`, or `None` when no number of shells gets there.
+pub fn coverage_estimate(accuracy_radius: f64, target_radius: f64) -> Option<u32> {
+    let p = calc::hit_probability(accuracy_radius, target_radius);
+    if p <= 0.0 {
+        return None;
+    }
+    if p >= 1.0 {
+        return Some(1);
+    }
+    let shells = ((1.0 - COVERAGE_CONFIDENCE).ln() / (1.0 - p).ln()).ceil();
+    Some((shells as u32).clamp(1, MAX_ESTIMATE_SHELLS))
+}
+
+/// Shells of one type and what carrying them takes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SupplyLine {
+    pub shell: Shell,
+    pub shells: u32,
+    pub crates: u32,
+    pub weight_kg: f64,
+}
+
+/// Everything a barrage needs brought up to the guns.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Requisition {
+    /// One line per shell type, in [`Shell`] order.
+    pub lines: Vec<SupplyLine>,
+    pub crates: u32,
+    /// Truck trips; a truck carries crates of any type.
+    pub truck_loads: u32,
+    /// Flatbed trips; each container holds a single shell type.
+    pub flatbed_loads: u32,
+    pub weight_kg: f64,
+}
+
+/// The requisition for `demand`, a list of shells needed by type. Types may
+/// repeat; their shells are added up before rounding up to whole crates.
+pub fn requisition(demand: &[(Shell, u32)]) -> Requisition {
+    let mut totals: Vec<(Shell, u32)> = Vec::new();
+    for &(shell, shells) in demand {
+        match totals.iter_mut().find(|(s, _)| *s == shell) {
+            Some((_, n)) => *n += shells,
+            None => totals.push((shell, shells)),
+        }
+    }
+    totals.retain(|&(_, n)| n > 0);
+    totals.sort_by_key(|&(s, _)| s);
+
+    let lines: Vec<SupplyLine> = totals
+        .into_iter()
+        .map(|(shell, shells)| SupplyLine {
+            shell,
+            shells,
+            crates: shells.div_ceil(shell.per_crate()),
+            weight_kg: shells as f64 * shell.weight_kg(),
+        })
+        .collect();
+    let crates = lines.iter().map(|l| l.crates).sum::<u32>();
+    Requisition {
+        crates,
+        truck_loads: crates.div_ceil(TRUCK_CRATES),
+        flatbed_loads: lines
+            .iter()
+            .map(|l| l.crates.div_ceil(FLATBED_CRATES))
+            .sum(),
+        weight_kg: lines.iter().map(|l| l.weight_kg).sum(),
+        lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::WeaponsFile;
+
+    #[test]
+    fn test_every_weapon_has_a_shell() {
+        let file: WeaponsFile =
+            serde_json::from_str(include_str!("../../../assets/weapons.json")).unwrap();
+        for weapon in &file.weapons {
+            assert!(
+                Shell::for_weapon(&weapon.slug()).is_some(),
+                "no shell for {}",
+                weapon.slug()
+            );
+        }
+        assert_eq!(Shell::for_weapon("cremari-mortar"), Some(Shell::Mortar));
+        assert_eq!(Shell::for_weapon("unknown-gun"), None);
+    }
+
+    #[test]
+    fn test_coverage_estimate() {
+        // Target bigger than the spread: one shell does it
+        assert_eq!(coverage_estimate(5.0, 10.0), Some(1));
+        // Each shell has a 1/4 chance; eight shells fall just short of 90%
+        assert_eq!(coverage_estimate(20.0, 10.0), Some(9));
+        assert_eq!(coverage_estimate(20.0, 0.0), None);
+        assert_eq!(coverage_estimate(1e6, 1.0), Some(MAX_ESTIMATE_SHELLS));
+    }
+
+    #[test]
+    fn test_requisition_rounds_up_to_crates_and_loads() {
+        let req = requisition(&[
+            (Shell::Mm150, 25),
+            (Shell::Mortar, 16),
+            (Shell::Mm150, 10),
+            (Shell::Mm300, 0),
+        ]);
+        assert_eq!(req.lines.len(), 2);
+        assert_eq!(req.lines[0].shell, Shell::Mortar);
+        assert_eq!((req.lines[0].shells, req.lines[0].crates), (16, 2));
+        assert_eq!((req.lines[1].shells, req.lines[1].crates), (35, 4));
+        assert_eq!(req.crates, 6);
+        assert_eq!(req.truck_loads, 1);
+        assert_eq!(req.flatbed_loads, 2);
+        assert!((req.weight_kg - (16.0 * 4.0 + 35.0 * 45.0)).abs() < 1e-9);
+
+        let big = requisition(&[(Shell::Mm120, 15 * 16)]);
+        assert_eq!((big.crates, big.truck_loads, big.flatbed_loads), (16, 2, 1));
+        assert_eq!(requisition(&[]), Requisition::default());
+    }
+}
@@ -0,0 +1,504 @@
+//! Diffing and merging the markers of two versions of a plan.
+//!
+//! Used when two editors save divergent versions: the server reports what each
+//! side has that the other doesn't, and the client can union the markers.
+//! [`compare_plans`] answers the other question, what changed from one
+//! version to the next, for the planner's compare view.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{target_status, IntelMarkers, Position, TargetStatus, NO_FIRE_RADIUS_M};
+use crate::overlay::{MarkerKind, MarkerLabels};
+
+/// Markers closer than this (meters) are treated as the same marker.
+pub const SAME_POSITION_M: f64 = 1.0;
+
+/// The mergeable part of a plan: markers, per-gun weapons, pairings, names,
+/// target progress and intel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlanMarkers {
+    pub gun_positions: Vec<Position>,
+    /// One weapon slug per gun (may be shorter than `gun_positions`).
+    pub weapon_ids: Vec<String>,
+    pub target_positions: Vec<Position>,
+    pub spotter_positions: Vec<Position>,
+    /// One entry per gun, indexing into `target_positions`.
+    pub gun_target_indices: Vec<Option<usize>>,
+    pub labels: MarkerLabels,
+    /// One entry per target (may be shorter than `target_positions`).
+    pub target_statuses: Vec<TargetStatus>,
+    pub intel: IntelMarkers,
+}
+
+#[cfg(feature = "uuid-support")]
+impl From<&crate::models::Plan> for PlanMarkers {
+    fn from(p: &crate::models::Plan) -> Self {
+        PlanMarkers {
+            gun_positions: p.gun_positions.clone(),
+            weapon_ids: p.weapon_ids.clone(),
+            target_positions: p.target_positions.clone(),
+            spotter_positions: p.spotter_positions.clone(),
+            gun_target_indices: p.gun_target_indices.clone(),
+            labels: p.marker_labels(),
+            target_statuses: p.target_statuses.clone(),
+            intel: p.intel.clone(),
+        }
+    }
+}
+
+/// Markers of one kind present on only one side.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarkerDiff {
+    pub only_mine: Vec<Position>,
+    pub only_theirs: Vec<Position>,
+}
+
+impl MarkerDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_mine.is_empty() && self.only_theirs.is_empty()
+    }
+}
+
+fn same_position(a: Position, b: Position) -> bool {
+    (a.x - b.x).hypot(a.y - b.y) < SAME_POSITION_M
+}
+
+fn find_position(list: &[Position], p: Position) -> Option<usize> {
+    list.iter().position(|&q| same_position(p, q))
+}
+
+pub fn diff_positions(mine: &[Position], theirs: &[Position]) -> MarkerDiff {
+    MarkerDiff {
+        only_mine: mine
+            .iter()
+            .copied()
+            .filter(|&p| find_position(theirs, p).is_none())
+            .collect(),
+        only_theirs: theirs
+            .iter()
+            .copied()
+            .filter(|&p| find_position(mine, p).is_none())
+            .collect(),
+    }
+}
+
+/// What happened to one marker from one version of a plan to another.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MarkerChange {
+    Moved { from: Position, to: Position },
+    Added(Position),
+    Removed(Position),
+}
+
+impl MarkerChange {
+    /// How far a moved marker went; 0 for one added or removed.
+    pub fn distance(&self) -> f64 {
+        match self {
+            MarkerChange::Moved { from, to } => (to.x - from.x).hypot(to.y - from.y),
+            MarkerChange::Added(_) | MarkerChange::Removed(_) => 0.0,
+        }
+    }
+}
+
+/// A marker that differs between two versions of a plan.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlanChange {
+    pub kind: MarkerKind,
+    /// Index in the newer version, or in the older one for a removed marker.
+    pub index: usize,
+    pub change: MarkerChange,
+}
+
+/// How one kind of marker changed from `before` to `after`. Markers still in
+/// place (within [`SAME_POSITION_M`]) match wherever they are in the list;
+/// the rest pair up in list order as moves, and what's left over was added
+/// or removed.
+pub fn compare_positions(
+    kind: MarkerKind,
+    before: &[Position],
+    after: &[Position],
+) -> Vec<PlanChange> {
+    let mut kept = vec![false; before.len()];
+    let mut changed = Vec::new();
+    for (ai, &a) in after.iter().enumerate() {
+        match (0..before.len()).find(|&bi| !kept[bi] && same_position(before[bi], a)) {
+            Some(bi) => kept[bi] = true,
+            None => changed.push(ai),
+        }
+    }
+    let gone: Vec<usize> = (0..before.len()).filter(|&bi| !kept[bi]).collect();
+
+    let mut changes: Vec<PlanChange> = changed
+        .iter()
+        .enumerate()
+        .map(|(k, &ai)| PlanChange {
+            kind,
+            index: ai,
+            change: match gone.get(k) {
+                Some(&bi) => MarkerChange::Moved {
+                    from: before[bi],
+                    to: after[ai],
+                },
+                None => MarkerChange::Added(after[ai]),
+            },
+        })
+        .collect();
+    changes.extend(gone.iter().skip(changed.len()).map(|&bi| PlanChange {
+        kind,
+        index: bi,
+        change: MarkerChange::Removed(before[bi]),
+    }));
+    changes
+}
+
+/// Guns, then targets, then spotters that differ from `before` to `after`.
+pub fn compare_plans(before: &PlanMarkers, after: &PlanMarkers) -> Vec<PlanChange> {
+    let mut changes =
+        compare_positions(MarkerKind::Gun, &before.gun_positions, &after.gun_positions);
+    changes.extend(compare_positions(
+        MarkerKind::Target,
+        &before.target_positions,
+        &after.target_positions,
+    ));
+    changes.extend(compare_positions(
+        MarkerKind::Spotter,
+        &before.spotter_positions,
+        &after.spotter_positions,
+    ));
+    changes
+}
+
+/// Append the positions in `theirs` that aren't already in `merged`.
+fn union_positions(merged: &mut Vec<Position>, theirs: &[Position]) {
+    for &p in theirs {
+        if find_position(merged, p).is_none() {
+            merged.push(p);
+        }
+    }
+}
+
+/// Append their no-fire zones that aren't already in `merged`, at the size
+/// they drew them.
+fn union_no_fire_zones(merged: &mut IntelMarkers, theirs: &IntelMarkers) {
+    for (zi, &z) in theirs.no_fire_zones.iter().enumerate() {
+        if find_position(&merged.no_fire_zones, z).is_some() {
+            continue;
+        }
+        let count = merged.no_fire_zones.len();
+        merged.no_fire_radii.resize(count, NO_FIRE_RADIUS_M);
+        merged.no_fire_zones.push(z);
+        merged.no_fire_radii.push(theirs.no_fire_radius(zi));
+    }
+}
+
+/// Give a merged marker their name unless I've already named it.
+fn adopt_label(
+    merged: &mut MarkerLabels,
+    theirs: &MarkerLabels,
+    kind: MarkerKind,
+    merged_index: usize,
+    their_index: usize,
+) {
+    if merged.get(kind, merged_index).is_some() {
+        return;
+    }
+    if let Some(label) = theirs.get(kind, their_index) {
+        merged.set(kind, merged_index, label);
+    }
+}
+
+/// Union of both sides' markers. Everything of mine is kept as-is (order,
+/// weapons, pairings, names); markers only they placed are appended, with
+/// their guns keeping their weapon and paired target. Names they gave to
+/// markers I left unnamed are taken too, and a target either side has got
+/// further with (fired upon, destroyed) keeps that status. Intel markers are
+/// unioned the same way.
+pub fn merge_markers(mine: &PlanMarkers, theirs: &PlanMarkers) -> PlanMarkers {
+    let mut merged = mine.clone();
+    merged.weapon_ids.resize(merged.gun_positions.len(), String::new());
+    merged
+        .gun_target_indices
+        .resize(merged.gun_positions.len(), None);
+
+    // Map their target indices into the merged target list
+    let target_index: Vec<usize> = theirs
+        .target_positions
+        .iter()
+        .map(|&t| {
+            find_position(&merged.target_positions, t).unwrap_or_else(|| {
+                merged.target_positions.push(t);
+                merged.target_positions.len() - 1
+            })
+        })
+        .collect();
+    merged
+        .target_statuses
+        .resize(merged.target_positions.len(), TargetStatus::Active);
+    for (ti, &mi) in target_index.iter().enumerate() {
+        let theirs_status = target_status(&theirs.target_statuses, ti);
+        if theirs_status > merged.target_statuses[mi] {
+            merged.target_statuses[mi] = theirs_status;
+        }
+        adopt_label(
+            &mut merged.labels,
+            &theirs.labels,
+            MarkerKind::Target,
+            mi,
+            ti,
+        );
+    }
+
+    for (gi, &gun) in theirs.gun_positions.iter().enumerate() {
+        if let Some(mi) = find_position(&mine.gun_positions, gun) {
+            adopt_label(&mut merged.labels, &theirs.labels, MarkerKind::Gun, mi, gi);
+            continue;
+        }
+        merged.gun_positions.push(gun);
+        let mi = merged.gun_positions.len() - 1;
+        adopt_label(&mut merged.labels, &theirs.labels, MarkerKind::Gun, mi, gi);
+        merged
+            .weapon_ids
+            .push(theirs.weapon_ids.get(gi).cloned().unwrap_or_default());
+        merged.gun_target_indices.push(
+            theirs
+                .gun_target_indices
+                .get(gi)
+                .copied()
+                .flatten()
+                .and_then(|ti| target_index.get(ti).copied()),
+        );
+    }
+
+    for (si, &s) in theirs.spotter_positions.iter().enumerate() {
+        let mi = find_position(&merged.spotter_positions, s).unwrap_or_else(|| {
+            merged.spotter_positions.push(s);
+            merged.spotter_positions.len() - 1
+        });
+        adopt_label(
+            &mut merged.labels,
+            &theirs.labels,
+            MarkerKind::Spotter,
+            mi,
+            si,
+        );
+    }
+
+    let intel = &mut merged.intel;
+    union_positions(&mut intel.enemy_guns, &theirs.intel.enemy_guns);
+    union_positions(&mut intel.fobs, &theirs.intel.fobs);
+    union_positions(&mut intel.bunkers, &theirs.intel.bunkers);
+    union_no_fire_zones(intel, &theirs.intel);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: f64, y: f64) -> Position {
+        Position { x, y }
+    }
+
+    #[test]
+    fn test_diff_positions_ignores_tiny_moves() {
+        let mine = vec![pos(100.0, 100.0), pos(200.0, 200.0)];
+        let theirs = vec![pos(100.3, 100.0), pos(300.0, 300.0)];
+        let diff = diff_positions(&mine, &theirs);
+        assert_eq!(diff.only_mine, vec![pos(200.0, 200.0)]);
+        assert_eq!(diff.only_theirs, vec![pos(300.0, 300.0)]);
+        assert!(diff_positions(&mine, &mine).is_empty());
+    }
+
+    #[test]
+    fn test_compare_positions_matches_markers_left_in_place() {
+        // Gun 1 was deleted, gun 2 stayed and gun 3 moved 50 m
+        let before = vec![pos(0.0, 0.0), pos(100.0, 100.0), pos(200.0, 200.0)];
+        let after = vec![pos(100.4, 100.0), pos(230.0, 240.0)];
+        let changes = compare_positions(MarkerKind::Gun, &before, &after);
+        assert_eq!(
+            changes,
+            vec![
+                PlanChange {
+                    kind: MarkerKind::Gun,
+                    index: 1,
+                    change: MarkerChange::Moved {
+                        from: pos(0.0, 0.0),
+                        to: pos(230.0, 240.0),
+                    },
+                },
+                PlanChange {
+                    kind: MarkerKind::Gun,
+                    index: 2,
+                    change: MarkerChange::Removed(pos(200.0, 200.0)),
+                },
+            ]
+        );
+        assert!(compare_positions(MarkerKind::Gun, &before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_compare_plans_reports_moves_and_additions() {
+        let before = PlanMarkers {
+            gun_positions: vec![pos(100.0, 100.0)],
+            target_positions: vec![pos(500.0, 500.0)],
+            ..PlanMarkers::default()
+        };
+        let after = PlanMarkers {
+            gun_positions: vec![pos(130.0, 140.0)],
+            target_positions: vec![pos(500.0, 500.0)],
+            spotter_positions: vec![pos(300.0, 300.0)],
+            ..PlanMarkers::default()
+        };
+        let changes = compare_plans(&before, &after);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].kind, MarkerKind::Gun);
+        assert_eq!(changes[0].change.distance(), 50.0);
+        assert_eq!(
+            changes[1],
+            PlanChange {
+                kind: MarkerKind::Spotter,
+                index: 0,
+                change: MarkerChange::Added(pos(300.0, 300.0)),
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_markers_appends_their_guns_with_pairings() {
+        let mine = PlanMarkers {
+            gun_positions: vec![pos(0.0, 0.0)],
+            weapon_ids: vec!["mortar".to_string()],
+            target_positions: vec![pos(100.0, 0.0)],
+            spotter_positions: vec![],
+            gun_target_indices: vec![Some(0)],
+            ..Default::default()
+        };
+        let theirs = PlanMarkers {
+            gun_positions: vec![pos(0.0, 0.0), pos(50.0, 50.0)],
+            weapon_ids: vec!["mortar".to_string(), "howitzer".to_string()],
+            target_positions: vec![pos(500.0, 500.0), pos(100.0, 0.0)],
+            spotter_positions: vec![pos(10.0, 10.0)],
+            gun_target_indices: vec![Some(1), Some(0)],
+            ..Default::default()
+        };
+
+        let merged = merge_markers(&mine, &theirs);
+        assert_eq!(merged.gun_positions, vec![pos(0.0, 0.0), pos(50.0, 50.0)]);
+        assert_eq!(merged.weapon_ids, vec!["mortar", "howitzer"]);
+        // Their target 0 is new (appended as 1); their target 1 is my target 0
+        assert_eq!(
+            merged.target_positions,
+            vec![pos(100.0, 0.0), pos(500.0, 500.0)]
+        );
+        assert_eq!(merged.gun_target_indices, vec![Some(0), Some(1)]);
+        assert_eq!(merged.spotter_positions, vec![pos(10.0, 10.0)]);
+    }
+
+    #[test]
+    fn test_merge_markers_unions_intel() {
+        let mine = PlanMarkers {
+            intel: IntelMarkers {
+                enemy_guns: vec![pos(0.0, 0.0)],
+                fobs: vec![pos(50.0, 50.0)],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let theirs = PlanMarkers {
+            intel: IntelMarkers {
+                enemy_guns: vec![pos(0.4, 0.0), pos(200.0, 0.0)],
+                no_fire_zones: vec![pos(10.0, 10.0)],
+                no_fire_radii: vec![120.0],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let merged = merge_markers(&mine, &theirs);
+        assert_eq!(
+            merged.intel.enemy_guns,
+            vec![pos(0.0, 0.0), pos(200.0, 0.0)]
+        );
+        assert_eq!(merged.intel.fobs, vec![pos(50.0, 50.0)]);
+        assert_eq!(merged.intel.no_fire_zones, vec![pos(10.0, 10.0)]);
+        assert_eq!(merged.intel.no_fire_radius(0), 120.0);
+    }
+
+    #[test]
+    fn test_merge_markers_is_identity_for_same_plan() {
+        let mine = PlanMarkers {
+            gun_positions: vec![pos(0.0, 0.0), pos(10.0, 0.0)],
+            weapon_ids: vec!["mortar".to_string()],
+            target_positions: vec![pos(100.0, 0.0)],
+            spotter_positions: vec![pos(5.0, 5.0)],
+            gun_target_indices: vec![Some(0)],
+            ..Default::default()
+        };
+        let merged = merge_markers(&mine, &mine);
+        assert_eq!(merged.gun_positions, mine.gun_positions);
+        assert_eq!(merged.weapon_ids, vec!["mortar", ""]);
+        assert_eq!(merged.gun_target_indices, vec![Some(0), None]);
+        assert_eq!(merged.target_positions, mine.target_positions);
+        assert_eq!(merged.spotter_positions, mine.spotter_positions);
+    }
+
+    #[test]
+    fn test_merge_markers_keeps_my_names_and_fills_in_theirs() {
+        let label = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let mine = PlanMarkers {
+            gun_positions: vec![pos(0.0, 0.0), pos(10.0, 0.0)],
+            target_positions: vec![pos(100.0, 0.0)],
+            labels: MarkerLabels {
+                guns: label(&["Alpha gun"]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let theirs = PlanMarkers {
+            gun_positions: vec![pos(0.0, 0.0), pos(10.0, 0.0), pos(50.0, 50.0)],
+            target_positions: vec![pos(100.0, 0.0)],
+            spotter_positions: vec![pos(5.0, 5.0)],
+            labels: MarkerLabels {
+                guns: label(&["Gun A", "Bravo gun", "Charlie gun"]),
+                targets: label(&["Bunker base east"]),
+                spotters: label(&["Hill OP"]),
+            },
+            ..Default::default()
+        };
+        let merged = merge_markers(&mine, &theirs);
+        assert_eq!(
+            merged.labels,
+            MarkerLabels {
+                guns: label(&["Alpha gun", "Bravo gun", "Charlie gun"]),
+                targets: label(&["Bunker base east"]),
+                spotters: label(&["Hill OP"]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_merge_markers_keeps_the_furthest_target_status() {
+        let mine = PlanMarkers {
+            target_positions: vec![pos(100.0, 0.0), pos(200.0, 0.0)],
+            target_statuses: vec![TargetStatus::Destroyed],
+            ..Default::default()
+        };
+        let theirs = PlanMarkers {
+            target_positions: vec![pos(200.0, 0.0), pos(100.0, 0.0), pos(300.0, 0.0)],
+            target_statuses: vec![
+                TargetStatus::FiredUpon,
+                TargetStatus::Active,
+                TargetStatus::Destroyed,
+            ],
+            ..Default::default()
+        };
+        let merged = merge_markers(&mine, &theirs);
+        assert_eq!(
+            merged.target_statuses,
+            vec![
+                TargetStatus::Destroyed,
+                TargetStatus::FiredUpon,
+                TargetStatus::Destroyed
+            ]
+        );
+    }
+}
@@ -1,11 +1,17 @@
 use serde::{Deserialize, Serialize};
 
+use crate::grid::{self, MapScale};
+
 #[cfg(feature = "uuid-support")]
 use uuid::Uuid;
 
 /// Weapon slug used for guns placed without a weapon selection.
 pub const UNASSIGNED_WEAPON: &str = "unassigned";
 
+/// Header carrying a browser tab's anonymous session id on placement tracking
+/// calls, so repeat placements by one visitor can be told apart.
+pub const TRACKING_SESSION_HEADER: &str = "x-session-id";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Faction {
     Colonial,
@@ -32,6 +38,93 @@ pub struct Weapon {
     pub max_range: f64,
     pub acc_radius: [f64; 2],
     pub wind_drift: [f64; 2],
+    /// Shell flight time in seconds at [min_range, max_range]. Zero when unknown.
+    #[serde(default)]
+    pub flight_time: [f64; 2],
+    /// Other shells it can fire. The fields above are its standard shell's.
+    #[serde(default)]
+    pub ammo: Vec<AmmoType>,
+    /// Crew and setup, for planning manpower.
+    #[serde(flatten, default)]
+    pub deployment: Deployment,
+    /// Fires area salvos, like rocket artillery, rather than point shots: its
+    /// shells land in an impact zone strung out along the line of fire.
+    #[serde(default)]
+    pub area_fire: bool,
+}
+
+/// How a weapon gets to its firing position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WeaponMount {
+    /// Carried and set up by infantry.
+    Portable,
+    /// Pulled into place by a truck.
+    Towed,
+    /// Fires from a vehicle or ship.
+    Vehicle,
+    /// Built in place.
+    Emplaced,
+}
+
+impl std::fmt::Display for WeaponMount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeaponMount::Portable => write!(f, "Portable"),
+            WeaponMount::Towed => write!(f, "Towed"),
+            WeaponMount::Vehicle => write!(f, "Vehicle"),
+            WeaponMount::Emplaced => write!(f, "Emplaced"),
+        }
+    }
+}
+
+/// What it takes to bring a weapon into action. Anything `weapons.json`
+/// leaves out is unknown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Deployment {
+    /// Fewest soldiers needed to fire it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crew: Option<u32>,
+    /// Seconds from reaching a firing position to being ready to fire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deploy_time: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mount: Option<WeaponMount>,
+}
+
+impl Deployment {
+    /// "Towed, crew 1, 20 s to deploy", with whatever is known; `None` when
+    /// nothing is.
+    pub fn summary(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            self.mount.map(|m| m.to_string()),
+            self.crew.map(|c| format!("crew {c}")),
+            self.deploy_time.map(|t| format!("{t:.0} s to deploy")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+/// A shell type that flies differently from a weapon's standard shell. Fields
+/// left out keep the weapon's own values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmmoType {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_range: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_range: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acc_radius: Option<[f64; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wind_drift: Option<[f64; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flight_time: Option<[f64; 2]>,
 }
 
 impl Weapon {
@@ -47,6 +140,115 @@ impl Weapon {
             .collect::<Vec<_>>()
             .join("-")
     }
+
+    /// The weapon as it fires `ammo`: a blank name is its standard shell.
+    /// `None` for a shell it doesn't fire.
+    pub fn with_ammo(&self, ammo: &str) -> Option<Weapon> {
+        let ammo = ammo.trim();
+        if ammo.is_empty() {
+            return Some(self.clone());
+        }
+        let shell = self
+            .ammo
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case(ammo))?;
+        Some(Weapon {
+            min_range: shell.min_range.unwrap_or(self.min_range),
+            max_range: shell.max_range.unwrap_or(self.max_range),
+            acc_radius: shell.acc_radius.unwrap_or(self.acc_radius),
+            wind_drift: shell.wind_drift.unwrap_or(self.wind_drift),
+            flight_time: shell.flight_time.unwrap_or(self.flight_time),
+            ammo: Vec::new(),
+            ..self.clone()
+        })
+    }
+}
+
+/// Contents of `weapons.json`: the weapons and the game patch their figures
+/// were taken from, if the file says. A bare list of weapons, the older
+/// layout, has no version.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "WeaponsFileLayout")]
+pub struct WeaponsFile {
+    pub game_version: Option<String>,
+    pub weapons: Vec<Weapon>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WeaponsFileLayout {
+    #[serde(rename_all = "camelCase")]
+    Versioned {
+        #[serde(default)]
+        game_version: Option<String>,
+        weapons: Vec<Weapon>,
+    },
+    List(Vec<Weapon>),
+}
+
+impl From<WeaponsFileLayout> for WeaponsFile {
+    fn from(layout: WeaponsFileLayout) -> Self {
+        match layout {
+            WeaponsFileLayout::Versioned {
+                game_version,
+                weapons,
+            } => WeaponsFile {
+                game_version,
+                weapons,
+            },
+            WeaponsFileLayout::List(weapons) => WeaponsFile {
+                game_version: None,
+                weapons,
+            },
+        }
+    }
+}
+
+/// Which weapon figures to plan with: the live game's, or the upcoming ones
+/// being tested on the Devbranch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WeaponProfile {
+    #[default]
+    Live,
+    Devbranch,
+}
+
+impl WeaponProfile {
+    pub const ALL: [WeaponProfile; 2] = [WeaponProfile::Live, WeaponProfile::Devbranch];
+
+    /// File in the assets directory holding the profile's weapons.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            WeaponProfile::Live => "weapons.json",
+            WeaponProfile::Devbranch => "weapons-devbranch.json",
+        }
+    }
+}
+
+impl std::fmt::Display for WeaponProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WeaponProfile::Live => write!(f, "Live"),
+            WeaponProfile::Devbranch => write!(f, "Devbranch"),
+        }
+    }
+}
+
+/// Ammo of gun `gun`: entries past the end of `ammo` are the standard shell.
+pub fn gun_ammo(ammo: &[String], gun: usize) -> &str {
+    ammo.get(gun).map(String::as_str).unwrap_or_default()
+}
+
+/// Set the ammo of gun `gun`, padding earlier guns with the standard shell.
+pub fn set_gun_ammo(ammo: &mut Vec<String>, gun: usize, name: &str) {
+    if ammo.len() <= gun {
+        if name.is_empty() {
+            return;
+        }
+        ammo.resize(gun + 1, String::new());
+    }
+    ammo[gun] = name.to_string();
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,22 +259,431 @@ pub struct GameMap {
     pub display_name: String,
     pub file_name: String,
     pub active: bool,
+    /// Map image size in pixels; the meters-per-pixel scale follows from it.
+    #[serde(default = "default_map_width")]
+    pub width: u32,
+    #[serde(default = "default_map_height")]
+    pub height: u32,
+    /// Where the planner opens this map; the whole map when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_view: Option<MapViewPreset>,
+    /// The hex's name in the Foxhole War API, where it doesn't follow from
+    /// `file_name` (see [`GameMap::war_api_map_name`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub war_api_name: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+fn default_map_width() -> u32 {
+    grid::MAP_WIDTH_PX as u32
+}
+
+fn default_map_height() -> u32 {
+    grid::MAP_HEIGHT_PX as u32
+}
+
+impl GameMap {
+    /// Pixel-to-meter scale for this map's image.
+    pub fn scale(&self) -> MapScale {
+        MapScale::for_image(self.width as f64, self.height as f64)
+    }
+
+    /// The hex's name in the Foxhole War API: `war_api_name` if set, otherwise
+    /// `file_name` in CamelCase with `Hex` appended (`callahans_passage` is
+    /// `CallahansPassageHex`).
+    pub fn war_api_map_name(&self) -> String {
+        if let Some(name) = &self.war_api_name {
+            return name.clone();
+        }
+        let mut name: String = self
+            .file_name
+            .split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                    None => String::new(),
+                }
+            })
+            .collect();
+        name.push_str("Hex");
+        name
+    }
+}
+
+/// An initial viewport: zoom level and the point (meters) to centre on.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MapViewPreset {
+    pub zoom: f64,
+    pub center: Position,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Position {
     pub x: f64,
     pub y: f64,
 }
 
+/// A pixel on a custom map image and the position (meters) it shows.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CalibrationPoint {
+    pub px: Position,
+    pub meters: Position,
+}
+
+/// A custom map image (a modded or annotated variant, say) lined up with the
+/// region by two reference points.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapCalibration {
+    /// Where the image is loaded from.
+    pub image_url: String,
+    /// Image size in pixels.
+    pub width: u32,
+    pub height: u32,
+    pub points: [CalibrationPoint; 2],
+}
+
+impl MapCalibration {
+    /// Pixel-to-meter scale for the image; `None` if the points can't pin it down.
+    pub fn scale(&self) -> Option<MapScale> {
+        let [a, b] = self
+            .points
+            .map(|p| ((p.px.x, p.px.y), (p.meters.x, p.meters.y)));
+        MapScale::calibrated(self.width as f64, self.height as f64, a, b)
+    }
+}
+
+/// How far along a target is during an op. Ordered by progress.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TargetStatus {
+    #[default]
+    Active,
+    FiredUpon,
+    Destroyed,
+}
+
+impl TargetStatus {
+    /// The status a toggle moves to: active, fired upon, destroyed, then back.
+    pub fn next(self) -> Self {
+        match self {
+            TargetStatus::Active => TargetStatus::FiredUpon,
+            TargetStatus::FiredUpon => TargetStatus::Destroyed,
+            TargetStatus::Destroyed => TargetStatus::Active,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TargetStatus::Active => "Active",
+            TargetStatus::FiredUpon => "Fired upon",
+            TargetStatus::Destroyed => "Destroyed",
+        }
+    }
+}
+
+/// A structure on the live War API map that artillery may want to hit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum StructureKind {
+    TownHall,
+    RelicBase,
+    Keep,
+    ForwardBase,
+    ObservationTower,
+    CoastalGun,
+    StormCannon,
+    IntelCenter,
+}
+
+impl StructureKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            StructureKind::TownHall => "Town hall",
+            StructureKind::RelicBase => "Relic base",
+            StructureKind::Keep => "Keep",
+            StructureKind::ForwardBase => "Forward base",
+            StructureKind::ObservationTower => "Observation tower",
+            StructureKind::CoastalGun => "Coastal gun",
+            StructureKind::StormCannon => "Storm cannon",
+            StructureKind::IntelCenter => "Intelligence center",
+        }
+    }
+
+    /// One-letter glyph drawn on the map icon.
+    pub fn glyph(self) -> char {
+        match self {
+            StructureKind::TownHall => 'T',
+            StructureKind::RelicBase => 'R',
+            StructureKind::Keep => 'K',
+            StructureKind::ForwardBase => 'F',
+            StructureKind::ObservationTower => 'O',
+            StructureKind::CoastalGun => 'C',
+            StructureKind::StormCannon => 'S',
+            StructureKind::IntelCenter => 'I',
+        }
+    }
+}
+
+/// Status of target `index`; targets past the end of `statuses` are active.
+pub fn target_status(statuses: &[TargetStatus], index: usize) -> TargetStatus {
+    statuses.get(index).copied().unwrap_or_default()
+}
+
+/// Set the status of target `index`, padding earlier targets as active.
+pub fn set_target_status(statuses: &mut Vec<TargetStatus>, index: usize, status: TargetStatus) {
+    if statuses.len() <= index {
+        statuses.resize(index + 1, TargetStatus::Active);
+    }
+    statuses[index] = status;
+}
+
+/// Whole percentage of `targets` that are destroyed, or `None` without targets.
+pub fn destroyed_percent(statuses: &[TargetStatus], targets: usize) -> Option<u32> {
+    if targets == 0 {
+        return None;
+    }
+    let destroyed = statuses
+        .iter()
+        .take(targets)
+        .filter(|&&s| s == TargetStatus::Destroyed)
+        .count();
+    Some((destroyed * 100 / targets) as u32)
+}
+
+/// Most shots a plan's log keeps.
+pub const MAX_SHOT_LOG: usize = 500;
+/// Longest observed-result note on a logged shot, in characters.
+pub const MAX_SHOT_RESULT_LEN: usize = 80;
+
+/// One round fired while adjusting a gun, with where the spotter saw it land.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShotRecord {
+    /// Index of the gun that fired, into the plan's guns.
+    pub gun: usize,
+    /// Index of the target it was fired at, if the gun had one.
+    pub target: Option<usize>,
+    pub azimuth: f64,
+    pub distance: f64,
+    /// What was observed, e.g. "30 m long, 15 m right" or "hit".
+    pub result: String,
+}
+
+/// Log a shot, trimming its result to `MAX_SHOT_RESULT_LEN` characters and
+/// dropping the oldest shots past `MAX_SHOT_LOG`.
+pub fn push_shot(log: &mut Vec<ShotRecord>, mut shot: ShotRecord) {
+    if let Some((cut, _)) = shot.result.char_indices().nth(MAX_SHOT_RESULT_LEN) {
+        shot.result.truncate(cut);
+    }
+    log.push(shot);
+    if log.len() > MAX_SHOT_LOG {
+        let excess = log.len() - MAX_SHOT_LOG;
+        log.drain(..excess);
+    }
+}
+
+/// Fix up a shot log after gun `index` is removed: its shots go and later
+/// guns move down one.
+pub fn remove_gun_shots(log: &mut Vec<ShotRecord>, index: usize) {
+    log.retain(|s| s.gun != index);
+    for shot in log.iter_mut().filter(|s| s.gun > index) {
+        shot.gun -= 1;
+    }
+}
+
+/// Fix up a shot log after target `index` is removed. Shots at it stay in
+/// their gun's history without a target.
+pub fn remove_target_shots(log: &mut [ShotRecord], index: usize) {
+    for shot in log.iter_mut() {
+        match shot.target {
+            Some(ti) if ti == index => shot.target = None,
+            Some(ti) if ti > index => shot.target = Some(ti - 1),
+            _ => {}
+        }
+    }
+}
+
+/// Most registration points a plan keeps.
+pub const MAX_REGISTRATIONS: usize = 100;
+/// Longest registration point name, in characters.
+pub const MAX_REGISTRATION_NAME_LEN: usize = 40;
+
+/// A named point a gun has registered on ("TRP-1 bridge"), with the firing
+/// data worked out for it when it was saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistrationPoint {
+    /// Index of the gun it was registered for, into the plan's guns.
+    pub gun: usize,
+    pub name: String,
+    /// Where the point is, in meters.
+    pub position: Position,
+    pub azimuth: f64,
+    pub distance: f64,
+}
+
+/// Fix up registration points after gun `index` is removed: its points go
+/// and later guns move down one.
+pub fn remove_gun_registrations(points: &mut Vec<RegistrationPoint>, index: usize) {
+    points.retain(|p| p.gun != index);
+    for point in points.iter_mut().filter(|p| p.gun > index) {
+        point.gun -= 1;
+    }
+}
+
+/// Most custom range rings a plan keeps.
+pub const MAX_CUSTOM_RINGS: usize = 50;
+/// Largest custom range ring, in meters.
+pub const MAX_RING_RADIUS: f64 = 1000.0;
+
+/// The kind of marker a custom range ring is drawn around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RingAnchor {
+    Gun,
+    Target,
+    Spotter,
+}
+
+/// A ring the planner drew around a marker ("250 m intel range around the
+/// spotter"). It follows the marker when it moves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomRing {
+    pub anchor: RingAnchor,
+    /// Index of the marker, into the plan's positions of that kind.
+    pub index: usize,
+    /// Radius in meters.
+    pub radius: f64,
+    /// Shown on the ring; blank shows the radius.
+    pub label: String,
+}
+
+/// Which range rings a plan draws: each gun's min/max rings unless they're
+/// hidden, plus the custom rings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RangeRings {
+    /// Guns whose min/max rings are hidden, indexed like the guns. May be
+    /// shorter; guns past the end show theirs.
+    #[serde(default)]
+    pub hidden: Vec<bool>,
+    #[serde(default)]
+    pub custom: Vec<CustomRing>,
+}
+
+impl RangeRings {
+    /// Whether gun `gun` has its min/max rings drawn.
+    pub fn shown(&self, gun: usize) -> bool {
+        !self.hidden.get(gun).copied().unwrap_or(false)
+    }
+
+    /// Show or hide gun `gun`'s min/max rings, padding earlier guns as shown.
+    pub fn set_shown(&mut self, gun: usize, shown: bool) {
+        if self.hidden.len() <= gun {
+            if shown {
+                return;
+            }
+            self.hidden.resize(gun + 1, false);
+        }
+        self.hidden[gun] = !shown;
+    }
+
+    /// Fix up the rings after marker `index` of kind `anchor` is removed: rings
+    /// around it go and later markers move down one.
+    pub fn remove_marker(&mut self, anchor: RingAnchor, index: usize) {
+        if anchor == RingAnchor::Gun && index < self.hidden.len() {
+            self.hidden.remove(index);
+        }
+        self.custom
+            .retain(|r| !(r.anchor == anchor && r.index == index));
+        for ring in self
+            .custom
+            .iter_mut()
+            .filter(|r| r.anchor == anchor && r.index > index)
+        {
+            ring.index -= 1;
+        }
+    }
+}
+
+/// Radius (m) of a no-fire zone placed without drawing its edge.
+pub const NO_FIRE_RADIUS_M: f64 = 50.0;
+/// Largest no-fire zone, in meters.
+pub const MAX_NO_FIRE_RADIUS: f64 = 1000.0;
+
+/// A circle friendly fire must stay out of, e.g. around one of our FOBs.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NoFireZone {
+    pub center: Position,
+    /// Meters.
+    pub radius: f64,
+}
+
+/// What the plan knows besides our own guns, targets and spotters: where the
+/// enemy's guns are, our FOBs and observation bunkers, and spots that must
+/// not be shelled.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntelMarkers {
+    #[serde(default)]
+    pub enemy_guns: Vec<Position>,
+    /// Friendly forward operating bases.
+    #[serde(default)]
+    pub fobs: Vec<Position>,
+    /// Friendly observation bunkers.
+    #[serde(default)]
+    pub bunkers: Vec<Position>,
+    /// Centres of no-fire zones.
+    #[serde(default)]
+    pub no_fire_zones: Vec<Position>,
+    /// Radius (m) of each no-fire zone, indexed like `no_fire_zones`. May be
+    /// shorter; zones past the end reach [`NO_FIRE_RADIUS_M`].
+    #[serde(default)]
+    pub no_fire_radii: Vec<f64>,
+}
+
+impl IntelMarkers {
+    pub fn is_empty(&self) -> bool {
+        self.enemy_guns.is_empty()
+            && self.fobs.is_empty()
+            && self.bunkers.is_empty()
+            && self.no_fire_zones.is_empty()
+    }
+
+    /// Radius (m) of no-fire zone `index`.
+    pub fn no_fire_radius(&self, index: usize) -> f64 {
+        no_fire_radius(&self.no_fire_radii, index)
+    }
+
+    /// The no-fire zones as circles, in `no_fire_zones` order.
+    pub fn no_fire_circles(&self) -> Vec<NoFireZone> {
+        self.no_fire_zones
+            .iter()
+            .enumerate()
+            .map(|(i, &center)| NoFireZone {
+                center,
+                radius: self.no_fire_radius(i),
+            })
+            .collect()
+    }
+}
+
+/// Radius (m) of no-fire zone `index` given the per-zone `radii`, which may
+/// be shorter than the zone list.
+pub fn no_fire_radius(radii: &[f64], index: usize) -> f64 {
+    radii.get(index).copied().unwrap_or(NO_FIRE_RADIUS_M)
+}
+
 #[cfg(feature = "uuid-support")]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Plan {
     pub id: Uuid,
     pub name: String,
     pub map_id: String,
     #[serde(default)]
     pub weapon_ids: Vec<String>,
+    /// Shell each gun fires, indexed like `weapon_ids`. May be shorter; missing
+    /// or blank entries are the standard shell.
+    #[serde(default)]
+    pub gun_ammo: Vec<String>,
     /// Legacy single-position field for backwards-compatible deserialization.
     #[serde(default, skip_serializing)]
     pub gun_position: Option<Position>,
@@ -90,8 +701,53 @@ pub struct Plan {
     /// Explicit gun→target pairing: one entry per gun, `Some(idx)` = paired with target at that index.
     #[serde(default)]
     pub gun_target_indices: Vec<Option<usize>>,
+    /// User-supplied marker names, indexed like the position lists. May be
+    /// shorter than them; missing or blank entries mean "unnamed".
+    #[serde(default)]
+    pub gun_labels: Vec<String>,
+    #[serde(default)]
+    pub target_labels: Vec<String>,
+    #[serde(default)]
+    pub spotter_labels: Vec<String>,
+    /// Progress per target, indexed like `target_positions`. May be shorter;
+    /// missing entries are active.
+    #[serde(default)]
+    pub target_statuses: Vec<TargetStatus>,
     pub wind_direction: Option<f64>,
     pub wind_strength: u8,
+    /// Bumped on every update; saves against an older version are conflicts.
+    #[serde(default)]
+    pub version: u32,
+    /// Unix timestamp (seconds) after which the cleanup task may delete the plan.
+    /// `None` for plans saved before retention existed; those age from `updated_at`.
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    /// The Foxhole war the plan was made in; `None` when it wasn't known.
+    #[serde(default)]
+    pub war_number: Option<u32>,
+    /// Custom map image the plan was drawn on, if any. Positions stay in
+    /// meters either way.
+    #[serde(default)]
+    pub calibration: Option<MapCalibration>,
+    /// Rounds fired while adjusting, oldest first.
+    #[serde(default)]
+    pub shot_log: Vec<ShotRecord>,
+    /// Named registration points per gun, with their stored solutions.
+    #[serde(default)]
+    pub registrations: Vec<RegistrationPoint>,
+    /// Per-gun ring toggles and custom rings.
+    #[serde(default)]
+    pub range_rings: RangeRings,
+    /// Enemy guns, FOBs, bunkers and no-fire zones.
+    #[serde(default)]
+    pub intel: IntelMarkers,
+    /// `gameVersion` of the weapon data the plan was last saved against;
+    /// `None` for plans saved before weapon data was versioned.
+    #[serde(default)]
+    pub weapon_data_version: Option<String>,
+    /// Weapon figures the plan's guns are checked and calculated with.
+    #[serde(default)]
+    pub weapon_profile: WeaponProfile,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -99,7 +755,7 @@ pub struct Plan {
 #[cfg(feature = "uuid-support")]
 impl Plan {
     /// Promote legacy single-position fields into the Vec fields if the Vecs
-    /// are empty. Call this after deserializing old plans.
+    /// are empty. The backend's storage migrations run this over old plans once.
     pub fn migrate(&mut self) {
         if self.gun_positions.is_empty() {
             if let Some(pos) = self.gun_position.take() {
@@ -117,6 +773,15 @@ impl Plan {
             }
         }
     }
+
+    /// The plan's marker names, for drawing and merging.
+    pub fn marker_labels(&self) -> crate::overlay::MarkerLabels {
+        crate::overlay::MarkerLabels {
+            guns: self.gun_labels.clone(),
+            targets: self.target_labels.clone(),
+            spotters: self.spotter_labels.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,12 +799,147 @@ pub struct FiringSolution {
     pub wind_adjusted_azimuth: Option<f64>,
     pub wind_adjusted_distance: Option<f64>,
     pub wind_offset_meters: Option<f64>,
+    pub time_of_flight: Option<f64>,
+    /// Where an area-fire salvo lands; `None` for point-fire weapons, whose
+    /// shells fall within `accuracy_radius` of the target.
+    #[serde(default)]
+    pub impact_zone: Option<ImpactZone>,
+}
+
+/// Footprint of an area-fire salvo, centred on the target.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactZone {
+    /// Meters along the line of fire.
+    pub length: f64,
+    /// Meters across the line of fire.
+    pub width: f64,
+    /// Compass bearing of the line of fire, in degrees.
+    pub azimuth: f64,
+}
+
+/// Ring around one of our guns from which an enemy weapon can reach it.
+/// An enemy gun sitting between `min_radius` and `max_radius` can fire on `center`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DangerZone {
+    pub center: Position,
+    pub min_radius: f64,
+    pub max_radius: f64,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn shot(gun: usize, target: Option<usize>) -> ShotRecord {
+        ShotRecord {
+            gun,
+            target,
+            azimuth: 90.0,
+            distance: 200.0,
+            result: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_weapons_file_layouts() {
+        let weapon = r#"{"faction":"Warden","displayName":"Mortar","minRange":45,"maxRange":80,
+            "accRadius":[5,12],"windDrift":[10,40],"flightTime":[3,5]}"#;
+        let file: WeaponsFile = serde_json::from_str(&format!(
+            r#"{{"gameVersion":"2026-10-18","weapons":[{weapon}]}}"#
+        ))
+        .unwrap();
+        assert_eq!(file.game_version.as_deref(), Some("2026-10-18"));
+        assert_eq!(file.weapons[0].slug(), "mortar");
+
+        let file: WeaponsFile = serde_json::from_str(&format!("[{weapon}]")).unwrap();
+        assert_eq!(file.game_version, None);
+        assert_eq!(file.weapons.len(), 1);
+
+        let file: WeaponsFile =
+            serde_json::from_str(&format!(r#"{{"weapons":[{weapon}]}}"#)).unwrap();
+        assert_eq!(file.game_version, None);
+
+        let shipped: WeaponsFile =
+            serde_json::from_str(include_str!("../../../assets/weapons.json")).unwrap();
+        assert!(!shipped.weapons.is_empty());
+    }
+
+    #[test]
+    fn test_removing_markers_fixes_up_shot_log() {
+        let mut log = vec![shot(0, Some(0)), shot(1, Some(2)), shot(2, Some(1))];
+        remove_gun_shots(&mut log, 1);
+        assert_eq!(log, vec![shot(0, Some(0)), shot(1, Some(1))]);
+        remove_target_shots(&mut log, 0);
+        assert_eq!(log, vec![shot(0, None), shot(1, Some(0))]);
+    }
+
+    #[test]
+    fn test_removing_a_gun_fixes_up_registrations() {
+        let point = |gun: usize, name: &str| RegistrationPoint {
+            gun,
+            name: name.to_string(),
+            position: Position { x: 100.0, y: 50.0 },
+            azimuth: 45.0,
+            distance: 120.0,
+        };
+        let mut points = vec![point(0, "TRP-1"), point(1, "TRP-2"), point(2, "TRP-3")];
+        remove_gun_registrations(&mut points, 1);
+        assert_eq!(points, vec![point(0, "TRP-1"), point(1, "TRP-3")]);
+    }
+
+    #[test]
+    fn test_push_shot_caps_log_and_result() {
+        let mut log: Vec<ShotRecord> = (0..MAX_SHOT_LOG).map(|g| shot(g, None)).collect();
+        let mut long = shot(7, None);
+        long.result = "é".repeat(MAX_SHOT_RESULT_LEN + 5);
+        push_shot(&mut log, long);
+        assert_eq!(log.len(), MAX_SHOT_LOG);
+        assert_eq!(log[0].gun, 1);
+        let last = log.last().unwrap();
+        assert_eq!(last.gun, 7);
+        assert_eq!(last.result.chars().count(), MAX_SHOT_RESULT_LEN);
+    }
+
+    #[test]
+    fn test_target_status_cycles_and_serializes() {
+        assert_eq!(TargetStatus::Active.next(), TargetStatus::FiredUpon);
+        assert_eq!(TargetStatus::FiredUpon.next(), TargetStatus::Destroyed);
+        assert_eq!(TargetStatus::Destroyed.next(), TargetStatus::Active);
+        assert_eq!(
+            serde_json::to_string(&TargetStatus::FiredUpon).unwrap(),
+            "\"FIRED_UPON\""
+        );
+        assert_eq!(
+            target_status(&[TargetStatus::Destroyed], 3),
+            TargetStatus::Active
+        );
+
+        let mut statuses = Vec::new();
+        set_target_status(&mut statuses, 2, TargetStatus::Destroyed);
+        assert_eq!(
+            statuses,
+            vec![
+                TargetStatus::Active,
+                TargetStatus::Active,
+                TargetStatus::Destroyed
+            ]
+        );
+    }
+
+    #[test]
+    fn test_destroyed_percent() {
+        use TargetStatus::*;
+        assert_eq!(destroyed_percent(&[], 0), None);
+        assert_eq!(destroyed_percent(&[], 4), Some(0));
+        assert_eq!(
+            destroyed_percent(&[Destroyed, FiredUpon, Destroyed], 3),
+            Some(66)
+        );
+        // Stale entries past the target count don't count
+        assert_eq!(destroyed_percent(&[Active, Destroyed], 1), Some(0));
+    }
+
     #[test]
     fn test_gun_target_indices_serialization_roundtrip() {
         let indices: Vec<Option<usize>> = vec![Some(0), None, Some(2)];
@@ -174,6 +974,66 @@ mod tests {
         assert_eq!(plan.gun_target_indices, vec![Some(0), None, Some(1)]);
     }
 
+    #[test]
+    fn test_map_size_defaults_to_stock_image() {
+        let map: GameMap = serde_json::from_str(
+            r#"{"type": "webp", "displayName": "Acrithia", "fileName": "acrithia", "active": true}"#,
+        )
+        .unwrap();
+        assert_eq!(map.scale(), MapScale::STANDARD);
+        let map: GameMap = serde_json::from_str(
+            r#"{"type": "webp", "displayName": "Acrithia", "fileName": "acrithia", "active": true, "width": 4096, "height": 3552}"#,
+        )
+        .unwrap();
+        assert_eq!(map.scale().width_px, 4096.0);
+        assert_eq!(map.scale().height_px, 3552.0);
+    }
+
+    #[test]
+    fn test_calibration_scale() {
+        let point = |px: (f64, f64), m: (f64, f64)| CalibrationPoint {
+            px: Position { x: px.0, y: px.1 },
+            meters: Position { x: m.0, y: m.1 },
+        };
+        let mut calibration = MapCalibration {
+            image_url: "https://example.com/deadlands-rustard.png".to_string(),
+            width: 1000,
+            height: 800,
+            points: [
+                point((0.0, 0.0), (500.0, 400.0)),
+                point((1000.0, 800.0), (1500.0, 1200.0)),
+            ],
+        };
+        let scale = calibration.scale().unwrap();
+        assert_eq!(scale.origin_m, (500.0, 400.0));
+        assert_eq!(scale.meters_per_px, (1.0, 1.0));
+        calibration.points[1] = point((0.0, 800.0), (500.0, 1200.0));
+        assert_eq!(calibration.scale(), None);
+    }
+
+    #[test]
+    fn test_war_api_map_name() {
+        let map = |file_name: &str, war_api_name: Option<&str>| GameMap {
+            image_type: "webp".to_string(),
+            display_name: String::new(),
+            file_name: file_name.to_string(),
+            active: true,
+            width: 2048,
+            height: 1776,
+            default_view: None,
+            war_api_name: war_api_name.map(str::to_string),
+        };
+        assert_eq!(
+            map("callahans_passage", None).war_api_map_name(),
+            "CallahansPassageHex"
+        );
+        assert_eq!(map("acrithia", None).war_api_map_name(), "AcrithiaHex");
+        assert_eq!(
+            map("marban_hollow", Some("MarbanHollow")).war_api_map_name(),
+            "MarbanHollow"
+        );
+    }
+
     #[test]
     fn test_weapon_slug_generation() {
         let weapon = Weapon {
@@ -183,7 +1043,96 @@ mod tests {
             max_range: 1000.0,
             acc_radius: [50.0, 50.0],
             wind_drift: [20.0, 50.0],
+            flight_time: [10.0, 25.0],
+            ammo: Vec::new(),
+            deployment: Deployment::default(),
+            area_fire: false,
         };
         assert_eq!(weapon.slug(), "storm-cannon");
     }
+
+    #[test]
+    fn test_weapon_with_ammo_overrides() {
+        let json = r#"{"faction":"Both","displayName":"Mortar","minRange":45,"maxRange":80,"accRadius":[5,12],"windDrift":[10,40],
+            "ammo":[{"name":"Shrapnel","accRadius":[8,15]},{"name":"Flare","maxRange":100}]}"#;
+        let weapon: Weapon = serde_json::from_str(json).unwrap();
+        let standard = weapon.with_ammo(" ").unwrap();
+        assert_eq!(standard.acc_radius, [5.0, 12.0]);
+
+        let shrapnel = weapon.with_ammo("shrapnel").unwrap();
+        assert_eq!(shrapnel.acc_radius, [8.0, 15.0]);
+        assert_eq!(shrapnel.max_range, 80.0);
+        assert_eq!(shrapnel.display_name, "Mortar");
+        assert_eq!(weapon.with_ammo("Flare").unwrap().max_range, 100.0);
+        assert!(weapon.with_ammo("Gas").is_none());
+    }
+
+    #[test]
+    fn test_weapon_deployment() {
+        let json = r#"{"faction":"Warden","displayName":"Lariat","minRange":100,"maxRange":300,"accRadius":[25,50],"windDrift":[10,25],
+            "mount":"TOWED","crew":1,"deployTime":20}"#;
+        let weapon: Weapon = serde_json::from_str(json).unwrap();
+        assert_eq!(weapon.deployment.mount, Some(WeaponMount::Towed));
+        assert_eq!(
+            weapon.deployment.summary().as_deref(),
+            Some("Towed, crew 1, 20 s to deploy")
+        );
+        // Shells keep the weapon's crew
+        assert_eq!(weapon.with_ammo("").unwrap().deployment.crew, Some(1));
+
+        let crew_only = Deployment {
+            crew: Some(2),
+            ..Deployment::default()
+        };
+        assert_eq!(crew_only.summary().as_deref(), Some("crew 2"));
+        assert_eq!(Deployment::default().summary(), None);
+    }
+
+    #[test]
+    fn test_set_gun_ammo_pads_with_standard_shell() {
+        let mut ammo = Vec::new();
+        set_gun_ammo(&mut ammo, 1, "");
+        assert!(ammo.is_empty());
+        set_gun_ammo(&mut ammo, 2, "Shrapnel");
+        assert_eq!(ammo, vec!["", "", "Shrapnel"]);
+        assert_eq!(gun_ammo(&ammo, 2), "Shrapnel");
+        assert_eq!(gun_ammo(&ammo, 5), "");
+    }
+
+    #[test]
+    fn test_range_rings_follow_removed_markers() {
+        let ring = |anchor: RingAnchor, index: usize| CustomRing {
+            anchor,
+            index,
+            radius: 250.0,
+            label: String::new(),
+        };
+        let mut rings = RangeRings::default();
+        rings.set_shown(1, true);
+        assert!(rings.hidden.is_empty());
+        rings.set_shown(2, false);
+        assert_eq!(rings.hidden, vec![false, false, true]);
+        assert!(!rings.shown(2) && rings.shown(5));
+
+        rings.custom = vec![
+            ring(RingAnchor::Spotter, 0),
+            ring(RingAnchor::Gun, 1),
+            ring(RingAnchor::Gun, 2),
+        ];
+        rings.remove_marker(RingAnchor::Gun, 1);
+        assert_eq!(rings.hidden, vec![false, true]);
+        assert_eq!(
+            rings.custom,
+            vec![ring(RingAnchor::Spotter, 0), ring(RingAnchor::Gun, 1)]
+        );
+        rings.remove_marker(RingAnchor::Target, 0);
+        assert_eq!(rings.custom.len(), 2);
+    }
+
+    #[test]
+    fn test_weapon_flight_time_defaults_to_zero() {
+        let json = r#"{"faction":"Both","displayName":"Old Gun","minRange":100,"maxRange":200,"accRadius":[10,20],"windDrift":[5,10]}"#;
+        let weapon: Weapon = serde_json::from_str(json).unwrap();
+        assert_eq!(weapon.flight_time, [0.0, 0.0]);
+    }
 }
@@ -0,0 +1,2673 @@
+//! SVG overlay builder: grid, range rings, firing lines and markers.
+//!
+//! Shared by the frontend map view and the backend's static plan images.
+//! Positions are in native map-image pixel space; a map's [`MapScale`] relates
+//! them to meters.
+
+use serde::{Deserialize, Serialize};
+
+use crate::calc;
+use crate::grid::{self, MapScale};
+use crate::merge::{MarkerChange, PlanChange};
+use crate::models::{
+    no_fire_radius, target_status, CustomRing, Faction, ImpactZone, Position, RingAnchor,
+    StructureKind, TargetStatus, Weapon, WindInput,
+};
+
+/// Below this zoom level, overlapping targets collapse into a count badge.
+pub const CLUSTER_ZOOM_THRESHOLD: f64 = 2.5;
+
+/// Cluster radius in map-image pixels at marker scale 1.0 (scaled like markers).
+pub const CLUSTER_RADIUS: f64 = 24.0;
+
+/// Marker and label size multiplier in high-contrast mode.
+pub const HIGH_CONTRAST_SCALE: f64 = 1.3;
+
+// --- Marker colors ---
+
+pub struct ThemeColors {
+    pub gun: &'static str,
+    pub target: &'static str,
+    pub spotter: &'static str,
+    pub target_label: &'static str,
+    pub spotter_label: &'static str,
+    pub max_range_fill: &'static str,
+    pub min_range_fill: &'static str,
+    pub firing_line_stroke: &'static str,
+    pub accuracy_fill: &'static str,
+    pub enemy: &'static str,
+    pub danger_fill: &'static str,
+    pub danger_fill_threatened: &'static str,
+}
+
+/// The default palette, used for images rendered on the server.
+pub const MARKER_COLORS: ThemeColors = WARDEN_COLORS;
+
+pub const WARDEN_COLORS: ThemeColors = ThemeColors {
+    gun: "#5ab882",
+    target: "#c43030",
+    spotter: "#4a8fd4",
+    target_label: "#f0a0a0",
+    spotter_label: "#b3d4f0",
+    max_range_fill: "rgba(90,184,130,0.06)",
+    min_range_fill: "rgba(196,48,48,0.12)",
+    firing_line_stroke: "rgba(196,48,48,0.85)",
+    accuracy_fill: "rgba(196,48,48,0.25)",
+    enemy: "#e08a2c",
+    danger_fill: "rgba(224,138,44,0.08)",
+    danger_fill_threatened: "rgba(224,138,44,0.28)",
+};
+
+/// Colonial olive and rust, with enemies in Warden blue.
+pub const COLONIAL_COLORS: ThemeColors = ThemeColors {
+    gun: "#8fb84a",
+    target: "#d0452b",
+    spotter: "#3fa7a0",
+    target_label: "#f4ab98",
+    spotter_label: "#a8e0da",
+    max_range_fill: "rgba(143,184,74,0.06)",
+    min_range_fill: "rgba(208,69,43,0.12)",
+    firing_line_stroke: "rgba(208,69,43,0.85)",
+    accuracy_fill: "rgba(208,69,43,0.25)",
+    enemy: "#5a8fd8",
+    danger_fill: "rgba(90,143,216,0.08)",
+    danger_fill_threatened: "rgba(90,143,216,0.28)",
+};
+
+/// Okabe-Ito colors, which stay apart with red-green colorblindness.
+pub const COLORBLIND_COLORS: ThemeColors = ThemeColors {
+    gun: "#56b4e9",
+    target: "#d55e00",
+    spotter: "#f0e442",
+    target_label: "#f5b78a",
+    spotter_label: "#f7f0a0",
+    max_range_fill: "rgba(86,180,233,0.06)",
+    min_range_fill: "rgba(213,94,0,0.12)",
+    firing_line_stroke: "rgba(213,94,0,0.85)",
+    accuracy_fill: "rgba(213,94,0,0.25)",
+    enemy: "#cc79a7",
+    danger_fill: "rgba(204,121,167,0.08)",
+    danger_fill_threatened: "rgba(204,121,167,0.28)",
+};
+
+/// Which set of marker colors the overlay is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Warden,
+    Colonial,
+    Colorblind,
+}
+
+impl Palette {
+    pub fn colors(self) -> &'static ThemeColors {
+        match self {
+            Palette::Warden => &WARDEN_COLORS,
+            Palette::Colonial => &COLONIAL_COLORS,
+            Palette::Colorblind => &COLORBLIND_COLORS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MarkerKind {
+    Gun,
+    Target,
+    Spotter,
+    /// An enemy gun.
+    Enemy,
+    /// A friendly forward operating base.
+    Fob,
+    /// A friendly observation bunker.
+    Bunker,
+    /// The centre of a no-fire zone.
+    NoFire,
+}
+
+impl MarkerKind {
+    /// The custom ring anchor for this kind; only our guns, targets and
+    /// spotters can have rings.
+    pub fn ring_anchor(self) -> Option<RingAnchor> {
+        match self {
+            MarkerKind::Gun => Some(RingAnchor::Gun),
+            MarkerKind::Target => Some(RingAnchor::Target),
+            MarkerKind::Spotter => Some(RingAnchor::Spotter),
+            MarkerKind::Enemy | MarkerKind::Fob | MarkerKind::Bunker | MarkerKind::NoFire => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SelectedMarker {
+    pub kind: MarkerKind,
+    pub index: usize,
+}
+
+/// Where a gun's shells land around its target, in map-image pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ShellSpread {
+    /// Point fire: anywhere within this radius.
+    Circle(f64),
+    /// Area fire: an ellipse `length` long along the compass bearing
+    /// `azimuth` and `width` wide across it.
+    Zone {
+        length: f64,
+        width: f64,
+        azimuth: f64,
+    },
+}
+
+impl ShellSpread {
+    /// The spread of a firing solution: its impact zone for area fire, else
+    /// its accuracy circle.
+    pub fn from_solution(
+        accuracy_radius: f64,
+        impact_zone: Option<&ImpactZone>,
+        scale: MapScale,
+    ) -> Self {
+        match impact_zone {
+            Some(zone) => ShellSpread::Zone {
+                length: scale.meters_to_px_distance(zone.length),
+                width: scale.meters_to_px_distance(zone.width),
+                azimuth: zone.azimuth,
+            },
+            None => ShellSpread::Circle(scale.meters_to_px_distance(accuracy_radius)),
+        }
+    }
+
+    /// Where `shells` shells of a salvo aimed at `center` can be expected to
+    /// land: [`dispersion_pattern`] stretched over the spread.
+    pub fn dispersion(&self, center: (f64, f64), shells: u32) -> Vec<(f64, f64)> {
+        match *self {
+            ShellSpread::Circle(radius) => dispersion_pattern(center, radius, shells),
+            ShellSpread::Zone {
+                length,
+                width,
+                azimuth,
+            } => {
+                let (sin, cos) = azimuth.to_radians().sin_cos();
+                dispersion_pattern((0.0, 0.0), 1.0, shells)
+                    .into_iter()
+                    .map(|(across, along)| {
+                        let (across, along) = (across * width / 2.0, along * length / 2.0);
+                        // Along the line of fire is up the map at azimuth 0
+                        (
+                            center.0 + across * cos + along * sin,
+                            center.1 + across * sin - along * cos,
+                        )
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Longest marker label accepted, in characters.
+pub const MAX_LABEL_LEN: usize = 40;
+
+/// User-supplied marker names, indexed like the marker lists. Lists may be
+/// shorter than the markers; missing or blank entries fall back to the
+/// generated "GUN 2" style label. Intel markers can't be named.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarkerLabels {
+    pub guns: Vec<String>,
+    pub targets: Vec<String>,
+    pub spotters: Vec<String>,
+}
+
+impl MarkerLabels {
+    fn list_mut(&mut self, kind: MarkerKind) -> Option<&mut Vec<String>> {
+        match kind {
+            MarkerKind::Gun => Some(&mut self.guns),
+            MarkerKind::Target => Some(&mut self.targets),
+            MarkerKind::Spotter => Some(&mut self.spotters),
+            MarkerKind::Enemy | MarkerKind::Fob | MarkerKind::Bunker | MarkerKind::NoFire => None,
+        }
+    }
+
+    /// The marker's name, if it has a non-blank one.
+    pub fn get(&self, kind: MarkerKind, index: usize) -> Option<&str> {
+        let list = match kind {
+            MarkerKind::Gun => &self.guns,
+            MarkerKind::Target => &self.targets,
+            MarkerKind::Spotter => &self.spotters,
+            MarkerKind::Enemy | MarkerKind::Fob | MarkerKind::Bunker | MarkerKind::NoFire => {
+                return None
+            }
+        };
+        list.get(index).map(|l| l.trim()).filter(|l| !l.is_empty())
+    }
+
+    /// Name a marker (blank clears it), padding the list as needed.
+    pub fn set(&mut self, kind: MarkerKind, index: usize, label: &str) {
+        let Some(list) = self.list_mut(kind) else {
+            return;
+        };
+        let label: String = label.trim().chars().take(MAX_LABEL_LEN).collect();
+        if list.len() <= index {
+            if label.is_empty() {
+                return;
+            }
+            list.resize(index + 1, String::new());
+        }
+        list[index] = label;
+    }
+
+    /// Drop a removed marker's name so later markers keep theirs.
+    pub fn remove(&mut self, kind: MarkerKind, index: usize) {
+        if let Some(list) = self.list_mut(kind) {
+            if index < list.len() {
+                list.remove(index);
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Marker clustering
+// ---------------------------------------------------------------------------
+
+/// Euclidean distance between two points.
+fn dist(a: &(f64, f64), b: (f64, f64)) -> f64 {
+    let dx = a.0 - b.0;
+    let dy = a.1 - b.1;
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Group positions that sit within `radius` of a cluster's seed point.
+///
+/// Greedy single pass: each unassigned position seeds a new cluster and pulls
+/// in every later unassigned position within `radius` of it. Returns member
+/// indices per cluster, in input order; singletons are included.
+pub fn cluster_positions(positions: &[(f64, f64)], radius: f64) -> Vec<Vec<usize>> {
+    let mut assigned = vec![false; positions.len()];
+    let mut clusters = Vec::new();
+    for seed in 0..positions.len() {
+        if assigned[seed] {
+            continue;
+        }
+        assigned[seed] = true;
+        let mut members = vec![seed];
+        for other in (seed + 1)..positions.len() {
+            if !assigned[other] && dist(&positions[other], positions[seed]) <= radius {
+                assigned[other] = true;
+                members.push(other);
+            }
+        }
+        clusters.push(members);
+    }
+    clusters
+}
+
+/// Mean position of the given member indices.
+pub fn cluster_center(positions: &[(f64, f64)], members: &[usize]) -> (f64, f64) {
+    let n = members.len().max(1) as f64;
+    let (sx, sy) = members.iter().fold((0.0, 0.0), |acc, &i| {
+        (acc.0 + positions[i].0, acc.1 + positions[i].1)
+    });
+    (sx / n, sy / n)
+}
+
+/// Marker scale factor shared by the SVG builder and hit-testing.
+pub fn marker_scale(zoom: f64, container_width: f64) -> f64 {
+    let mobile_boost = (REFERENCE_WIDTH / container_width).max(1.0);
+    mobile_boost / zoom.min(5.0)
+}
+
+/// Multi-member target clusters for the current zoom, or none above the threshold.
+///
+/// The selected target is never clustered so the selection ring stays visible.
+pub fn target_clusters(
+    targets: &[(f64, f64)],
+    zoom: f64,
+    container_width: f64,
+    selected: Option<SelectedMarker>,
+) -> Vec<Vec<usize>> {
+    if zoom >= CLUSTER_ZOOM_THRESHOLD {
+        return Vec::new();
+    }
+    let radius = CLUSTER_RADIUS * marker_scale(zoom, container_width);
+    let selected_target = selected
+        .filter(|sm| sm.kind == MarkerKind::Target)
+        .map(|sm| sm.index);
+    let candidates: Vec<usize> = (0..targets.len())
+        .filter(|&i| Some(i) != selected_target)
+        .collect();
+    let candidate_positions: Vec<(f64, f64)> = candidates.iter().map(|&i| targets[i]).collect();
+    cluster_positions(&candidate_positions, radius)
+        .into_iter()
+        .filter(|c| c.len() > 1)
+        .map(|c| c.into_iter().map(|i| candidates[i]).collect())
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// SVG builder
+// ---------------------------------------------------------------------------
+
+/// Reference container width (desktop map panel) used to normalize marker sizes.
+pub const REFERENCE_WIDTH: f64 = 960.0;
+
+/// The grid cells in view, as half-open column and row ranges. Grid lines,
+/// labels and the keypad are only drawn inside it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridWindow {
+    pub cols: (usize, usize),
+    pub rows: (usize, usize),
+}
+
+impl GridWindow {
+    /// The whole map.
+    pub const ALL: GridWindow = GridWindow {
+        cols: (0, grid::GRID_COLS),
+        rows: (0, grid::GRID_ROWS),
+    };
+
+    /// The cells under the map-image px rectangle from `min` to `max`, plus
+    /// one cell on each side so nothing pops in at the edges while panning.
+    pub fn around(min: (f64, f64), max: (f64, f64), scale: MapScale) -> Self {
+        let cell_w = scale.grid_col_px(1) - scale.grid_col_px(0);
+        let cell_h = scale.grid_row_px(1) - scale.grid_row_px(0);
+        let span = |lo: f64, hi: f64, origin: f64, cell: f64, count: usize| {
+            let first = ((lo - origin) / cell).floor() - 1.0;
+            let last = ((hi - origin) / cell).floor() + 2.0;
+            (
+                first.clamp(0.0, count as f64) as usize,
+                last.clamp(0.0, count as f64) as usize,
+            )
+        };
+        GridWindow {
+            cols: span(min.0, max.0, scale.grid_col_px(0), cell_w, grid::GRID_COLS),
+            rows: span(min.1, max.1, scale.grid_row_px(0), cell_h, grid::GRID_ROWS),
+        }
+    }
+}
+
+/// Build the full SVG content as a string for reliable rendering.
+/// Positions are in native map-image pixel space, sized by `scale`.
+/// `rings_hidden` lists the guns whose min/max rings are left out, indexed
+/// like the guns; guns past the end show theirs.
+/// The keypad grid shows from zoom 3 up, or at any zoom with `always_keypad`;
+/// it and the grid are only drawn inside `view`.
+#[allow(clippy::too_many_arguments)]
+pub fn build_svg_content(
+    guns: &[(f64, f64)],
+    targets: &[(f64, f64)],
+    spotters: &[(f64, f64)],
+    enemies: &[(f64, f64)],
+    gun_weapons: &[Option<&Weapon>],
+    rings_hidden: &[bool],
+    gun_target_indices: &[Option<usize>],
+    spreads_px: &[Option<ShellSpread>],
+    salvo_sizes: &[u32],
+    labels: &MarkerLabels,
+    target_statuses: &[TargetStatus],
+    zoom: f64,
+    always_keypad: bool,
+    view: GridWindow,
+    container_width: f64,
+    selected: Option<SelectedMarker>,
+    danger_weapon: Option<&Weapon>,
+    wind: Option<&WindInput>,
+    high_contrast: bool,
+    colors: &ThemeColors,
+    scale: MapScale,
+) -> String {
+    let mut svg = String::with_capacity(8192);
+
+    // Scale factor: keeps markers, strokes, and labels a consistent physical
+    // size on screen regardless of container width.  On a 960 px desktop panel
+    // the boost is 1.0; on a 430 px phone it's ~2.2×.
+    let mobile_boost = (REFERENCE_WIDTH / container_width).max(1.0);
+    let s = marker_scale(zoom, container_width);
+    let s = if high_contrast {
+        s * HIGH_CONTRAST_SCALE
+    } else {
+        s
+    };
+
+    build_grid_lines(&mut svg, mobile_boost, view, scale);
+    build_grid_labels(&mut svg, mobile_boost, view, scale);
+    if always_keypad || zoom >= 3.0 {
+        build_keypad_lines(&mut svg, mobile_boost, view, scale);
+        build_keypad_labels(&mut svg, mobile_boost, view, scale);
+    }
+    if let Some(w) = danger_weapon {
+        build_danger_zones(&mut svg, guns, enemies, w, s, colors, scale);
+    }
+    build_range_circles(&mut svg, guns, gun_weapons, rings_hidden, s, colors, scale);
+    if high_contrast {
+        build_outline_filter(&mut svg, s, scale);
+        svg.push_str(r#"<g filter="url(#hc-outline)">"#);
+    }
+    build_firing_lines(&mut svg, guns, targets, gun_target_indices, s, colors);
+    build_accuracy_circles(
+        &mut svg,
+        guns,
+        targets,
+        gun_target_indices,
+        spreads_px,
+        salvo_sizes,
+        s,
+        colors,
+    );
+    // Same rule as the calculate query: no wind unless it has some strength
+    let wind = wind.filter(|w| w.strength > 0);
+    if let Some(w) = wind {
+        build_wind_drift(
+            &mut svg,
+            guns,
+            targets,
+            gun_weapons,
+            gun_target_indices,
+            w,
+            s,
+            scale,
+        );
+    }
+    if let Some(&(gx, gy)) = selected
+        .filter(|sm| sm.kind == MarkerKind::Gun)
+        .and_then(|sm| guns.get(sm.index))
+    {
+        build_compass_rose(&mut svg, gx, gy, s);
+    }
+    build_gun_markers(&mut svg, guns, labels, s, selected, colors);
+    let clusters = target_clusters(targets, zoom, container_width, selected);
+    let mut clustered = vec![false; targets.len()];
+    for &i in clusters.iter().flatten() {
+        clustered[i] = true;
+    }
+    build_target_markers(
+        &mut svg,
+        targets,
+        labels,
+        target_statuses,
+        &clustered,
+        s,
+        selected,
+        colors,
+    );
+    build_cluster_badges(&mut svg, targets, &clusters, s, colors);
+    build_spotter_markers(&mut svg, spotters, labels, s, selected, colors);
+    build_enemy_markers(&mut svg, enemies, s, selected, colors);
+    if high_contrast {
+        svg.push_str("</g>");
+    }
+    if let Some(w) = wind {
+        build_wind_indicator(&mut svg, w, s, scale);
+    }
+
+    svg
+}
+
+/// SVG filter that rings everything it is applied to with a white then a
+/// black outline, so markers and lines stand out on any terrain colour.
+fn build_outline_filter(svg: &mut String, s: f64, scale: MapScale) {
+    let inner = 1.5 * s;
+    let outer = 3.0 * s;
+    let (w, h) = (scale.width_px, scale.height_px);
+    svg.push_str(&format!(
+        concat!(
+            r#"<defs><filter id="hc-outline" filterUnits="userSpaceOnUse" x="0" y="0" width="{w}" height="{h}">"#,
+            r#"<feMorphology in="SourceAlpha" operator="dilate" radius="{inner}" result="inner"/>"#,
+            r#"<feFlood flood-color="white"/><feComposite in2="inner" operator="in" result="white"/>"#,
+            r#"<feMorphology in="SourceAlpha" operator="dilate" radius="{outer}" result="outer"/>"#,
+            r#"<feFlood flood-color="black"/><feComposite in2="outer" operator="in" result="black"/>"#,
+            r#"<feMerge><feMergeNode in="black"/><feMergeNode in="white"/><feMergeNode in="SourceGraphic"/></feMerge>"#,
+            r#"</filter></defs>"#,
+        ),
+        w = w,
+        h = h,
+        inner = inner,
+        outer = outer,
+    ));
+}
+
+/// Where the lines bounding `view` start and end: the map edge when the view
+/// reaches it, else the outermost grid line in view.
+fn view_extent(view: GridWindow, scale: MapScale) -> ((f64, f64), (f64, f64)) {
+    let x = |col: usize| match col {
+        0 => 0.0,
+        c if c >= grid::GRID_COLS => scale.width_px,
+        c => scale.grid_col_px(c),
+    };
+    let y = |row: usize| match row {
+        0 => 0.0,
+        r if r >= grid::GRID_ROWS => scale.height_px,
+        r => scale.grid_row_px(r),
+    };
+    (
+        (x(view.cols.0), x(view.cols.1)),
+        (y(view.rows.0), y(view.rows.1)),
+    )
+}
+
+fn build_grid_lines(svg: &mut String, mb: f64, view: GridWindow, scale: MapScale) {
+    let sw = 1.0 * mb;
+    let ((x1, x2), (y1, y2)) = view_extent(view, scale);
+    for col in view.cols.0..=view.cols.1 {
+        let x = scale.grid_col_px(col);
+        svg.push_str(&format!(
+            r#"<line x1="{x}" y1="{y1}" x2="{x}" y2="{y2}" stroke="rgba(255,255,255,0.15)" stroke-width="{sw}"/>"#
+        ));
+    }
+    for row in view.rows.0..=view.rows.1 {
+        let y = scale.grid_row_px(row);
+        svg.push_str(&format!(
+            r#"<line x1="{x1}" y1="{y}" x2="{x2}" y2="{y}" stroke="rgba(255,255,255,0.15)" stroke-width="{sw}"/>"#
+        ));
+    }
+}
+
+fn build_grid_labels(svg: &mut String, mb: f64, view: GridWindow, scale: MapScale) {
+    let fs = 18.0 * mb;
+    let col_step = scale.width_px / grid::GRID_COLS as f64;
+    let col_y = 24.0 * mb;
+    for col in view.cols.0..view.cols.1 {
+        let x = col as f64 * col_step + col_step / 2.0;
+        let letter = grid::col_letter(col);
+        svg.push_str(&format!(
+            r#"<text x="{x}" y="{col_y}" fill="rgba(255,255,255,0.45)" font-size="{fs}" font-family="monospace" font-weight="600" text-anchor="middle" dominant-baseline="central">{letter}</text>"#
+        ));
+    }
+    let row_step = scale.height_px / grid::GRID_ROWS as f64;
+    let row_x = 8.0 * mb;
+    for row in view.rows.0..view.rows.1 {
+        let y = row as f64 * row_step + row_step / 2.0 + 8.0 * mb;
+        let num = row + 1;
+        svg.push_str(&format!(
+            r#"<text x="{row_x}" y="{y}" fill="rgba(255,255,255,0.45)" font-size="{fs}" font-family="monospace" font-weight="600" text-anchor="start" dominant-baseline="central">{num}</text>"#
+        ));
+    }
+}
+
+fn build_keypad_lines(svg: &mut String, mb: f64, view: GridWindow, scale: MapScale) {
+    let cell_w = scale.width_px / grid::GRID_COLS as f64;
+    let cell_h = scale.height_px / grid::GRID_ROWS as f64;
+    let third_w = cell_w / 3.0;
+    let third_h = cell_h / 3.0;
+    let sw = 0.6 * mb;
+    let ((x1, x2), (y1, y2)) = view_extent(view, scale);
+
+    for col in view.cols.0..view.cols.1 {
+        let x0 = scale.grid_col_px(col);
+        for i in 1..3 {
+            let x = x0 + third_w * i as f64;
+            svg.push_str(&format!(
+                r#"<line x1="{x}" y1="{y1}" x2="{x}" y2="{y2}" stroke="rgba(255,255,255,0.08)" stroke-width="{sw}"/>"#
+            ));
+        }
+    }
+    for row in view.rows.0..view.rows.1 {
+        let y0 = scale.grid_row_px(row);
+        for i in 1..3 {
+            let y = y0 + third_h * i as f64;
+            svg.push_str(&format!(
+                r#"<line x1="{x1}" y1="{y}" x2="{x2}" y2="{y}" stroke="rgba(255,255,255,0.08)" stroke-width="{sw}"/>"#
+            ));
+        }
+    }
+}
+
+fn build_keypad_labels(svg: &mut String, mb: f64, view: GridWindow, scale: MapScale) {
+    let cell_w = scale.width_px / grid::GRID_COLS as f64;
+    let cell_h = scale.height_px / grid::GRID_ROWS as f64;
+    let third_w = cell_w / 3.0;
+    let third_h = cell_h / 3.0;
+    // Gentler boost — these labels sit inside small keypad cells
+    let fs = 10.0 * mb.sqrt();
+
+    // Numpad layout: row 0 (top) = 7 8 9, row 1 (mid) = 4 5 6, row 2 (bot) = 1 2 3
+    const KEYPAD: [[u8; 3]; 3] = [[7, 8, 9], [4, 5, 6], [1, 2, 3]];
+
+    for col in view.cols.0..view.cols.1 {
+        let x0 = scale.grid_col_px(col);
+        for row in view.rows.0..view.rows.1 {
+            let y0 = scale.grid_row_px(row);
+            for (kr, keypad_row) in KEYPAD.iter().enumerate() {
+                for (kc, &label) in keypad_row.iter().enumerate() {
+                    let cx = x0 + third_w * kc as f64 + third_w / 2.0;
+                    let cy = y0 + third_h * kr as f64 + third_h / 2.0;
+                    svg.push_str(&format!(
+                        r#"<text x="{cx}" y="{cy}" fill="rgba(255,255,255,0.2)" font-size="{fs}" font-family="monospace" text-anchor="middle" dominant-baseline="central">{label}</text>"#
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn build_range_circles(
+    svg: &mut String,
+    guns: &[(f64, f64)],
+    gun_weapons: &[Option<&Weapon>],
+    rings_hidden: &[bool],
+    s: f64,
+    colors: &ThemeColors,
+    scale: MapScale,
+) {
+    for (i, &(gx, gy)) in guns.iter().enumerate() {
+        if rings_hidden.get(i).copied().unwrap_or(false) {
+            continue;
+        }
+        let Some(w) = gun_weapons.get(i).and_then(|o| *o) else {
+            continue;
+        };
+        let max_r = scale.meters_to_px_distance(w.max_range);
+        let sw1 = 3.0 * s;
+        let gun_color = colors.gun;
+        let max_fill = colors.max_range_fill;
+        svg.push_str(&format!(
+            r##"<circle cx="{gx}" cy="{gy}" r="{max_r}" fill="{max_fill}" stroke="{gun_color}" stroke-width="{sw1}" stroke-opacity="0.6"/>"##
+        ));
+        let min_r = scale.meters_to_px_distance(w.min_range);
+        let sw2 = 2.0 * s;
+        let da1 = 8.0 * s;
+        let da2 = 6.0 * s;
+        let min_fill = colors.min_range_fill;
+        let target_color = colors.target;
+        svg.push_str(&format!(
+            r##"<circle cx="{gx}" cy="{gy}" r="{min_r}" fill="{min_fill}" stroke="{target_color}" stroke-width="{sw2}" stroke-dasharray="{da1} {da2}" stroke-opacity="0.5"/>"##
+        ));
+    }
+}
+
+/// Distance in meters and compass bearing in degrees from `a` to `b` (map-image px).
+pub fn measure(a: (f64, f64), b: (f64, f64), scale: MapScale) -> (f64, f64) {
+    let to_pos = |(x, y): (f64, f64)| {
+        let (mx, my) = scale.px_to_meters(x, y);
+        Position { x: mx, y: my }
+    };
+    let (from, to) = (to_pos(a), to_pos(b));
+    (calc::distance(from, to), calc::azimuth(from, to))
+}
+
+/// Label for a measurement, e.g. `"312 m  047°"`.
+pub fn measure_label(distance_m: f64, bearing_deg: f64) -> String {
+    let bearing = bearing_deg.round() as u32 % 360;
+    format!("{:.0} m  {:03}\u{00b0}", distance_m, bearing)
+}
+
+/// Dashed measuring line from `a` to `b` with its distance and bearing at the midpoint.
+pub fn build_measure_line(a: (f64, f64), b: (f64, f64), s: f64, scale: MapScale) -> String {
+    let (distance, bearing) = measure(a, b, scale);
+    let label = measure_label(distance, bearing);
+    let sw = 3.0 * s;
+    let dash = 10.0 * s;
+    let r = 5.0 * s;
+    let fs = 16.0 * s;
+    let tsw = 4.0 * s;
+    let (mx, my) = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0 - 10.0 * s);
+    let mut svg = String::new();
+    svg.push_str(&format!(
+        r##"<g class="measure-line" role="img"><title>{label}</title><line x1="{}" y1="{}" x2="{}" y2="{}" stroke="white" stroke-width="{sw}" stroke-dasharray="{dash} {dash}"/>"##,
+        a.0, a.1, b.0, b.1
+    ));
+    for (x, y) in [a, b] {
+        svg.push_str(&format!(
+            r##"<circle cx="{x}" cy="{y}" r="{r}" fill="white" stroke="black" stroke-width="{}"/>"##,
+            s
+        ));
+    }
+    svg.push_str(&format!(
+        r##"<text x="{mx}" y="{my}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text></g>"##
+    ));
+    svg
+}
+
+/// A no-fire zone being drawn: a dashed circle from `center` out to the
+/// cursor at `edge` (map-image px), labelled with its radius.
+pub fn build_no_fire_draft(
+    center: (f64, f64),
+    edge: (f64, f64),
+    s: f64,
+    scale: MapScale,
+) -> String {
+    let (radius_m, _) = measure(center, edge, scale);
+    let r = ((edge.0 - center.0).powi(2) + (edge.1 - center.1).powi(2)).sqrt();
+    let sw = 3.0 * s;
+    let dash = 8.0 * s;
+    let fs = 16.0 * s;
+    let tsw = 4.0 * s;
+    let (cx, cy) = center;
+    format!(
+        r##"<g class="no-fire-draft"><circle cx="{cx}" cy="{cy}" r="{r}" fill="rgba(255,255,255,0.08)" stroke="white" stroke-width="{sw}" stroke-dasharray="{dash} {dash}"/><text x="{cx}" y="{}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{radius_m:.0} m</text></g>"##,
+        cy - r - 6.0 * s
+    )
+}
+
+/// A structure from the live War API map, positioned in map-image pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructureIcon {
+    pub kind: StructureKind,
+    /// The side holding it; `None` while unclaimed.
+    pub team: Option<Faction>,
+    pub pos: (f64, f64),
+    /// The nearest named place, shown in the tooltip.
+    pub location: Option<String>,
+}
+
+/// Name for a structure without its side, e.g. `"Town hall, Abandoned Ward"`.
+/// Targets snapped onto a structure are labelled with it.
+pub fn structure_label(icon: &StructureIcon) -> String {
+    match &icon.location {
+        Some(location) => format!("{}, {}", icon.kind.label(), location),
+        None => icon.kind.label().to_string(),
+    }
+}
+
+/// Tooltip text for a structure, e.g. `"Town hall, Abandoned Ward (Warden)"`.
+pub fn structure_title(icon: &StructureIcon) -> String {
+    let mut title = structure_label(icon);
+    match icon.team {
+        Some(Faction::Colonial) => title.push_str(" (Colonial)"),
+        Some(Faction::Warden) => title.push_str(" (Warden)"),
+        Some(Faction::Both) | None => {}
+    }
+    title
+}
+
+/// Index of the structure held by the other side nearest to `pos`, if one is
+/// within `radius`. Unclaimed structures are never snapped to.
+pub fn nearest_enemy_structure(
+    structures: &[StructureIcon],
+    pos: (f64, f64),
+    radius: f64,
+    own: Faction,
+) -> Option<usize> {
+    structures
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| matches!(s.team, Some(t) if t != own && t != Faction::Both))
+        .map(|(i, s)| (i, dist(&s.pos, pos)))
+        .filter(|&(_, d)| d <= radius)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+/// How often the community placed a kind of marker in one grid cell.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HeatCell {
+    pub col: usize,
+    pub row: usize,
+    pub count: u64,
+}
+
+/// Placement heatmap: each grid cell with placements filled in `color`, the
+/// busiest cell most strongly, under everything else on the map.
+pub fn build_heatmap(cells: &[HeatCell], color: &str, map_scale: MapScale) -> String {
+    let max = cells.iter().map(|c| c.count).max().unwrap_or(0);
+    let mut svg = String::new();
+    for cell in cells.iter().filter(|c| c.count > 0) {
+        let x = map_scale.grid_col_px(cell.col);
+        let y = map_scale.grid_row_px(cell.row);
+        let w = map_scale.grid_col_px(cell.col + 1) - x;
+        let h = map_scale.grid_row_px(cell.row + 1) - y;
+        let opacity = 0.1 + 0.45 * cell.count as f64 / max as f64;
+        svg.push_str(&format!(
+            r#"<rect class="heat-cell" x="{x}" y="{y}" width="{w}" height="{h}" fill="{color}" fill-opacity="{opacity:.2}"><title>{}{}: {}</title></rect>"#,
+            grid::col_letter(cell.col),
+            cell.row + 1,
+            cell.count
+        ));
+    }
+    svg
+}
+
+/// Live map structures: a square in the holding side's colour with a letter
+/// for the kind of structure, drawn faintly so planning markers stay on top.
+pub fn build_structure_icons(structures: &[StructureIcon], s: f64) -> String {
+    let half = 9.0 * s;
+    let size = 2.0 * half;
+    let sw = 1.5 * s;
+    let fs = 12.0 * s;
+    let mut svg = String::new();
+    for icon in structures {
+        let (x, y) = icon.pos;
+        let fill = match icon.team {
+            Some(Faction::Colonial) => "#4f7a3a",
+            Some(Faction::Warden) => "#2d5fa8",
+            Some(Faction::Both) | None => "#7a7a7a",
+        };
+        let ty = y + fs * 0.35;
+        svg.push_str(&format!(
+            r##"<g class="map-structure" opacity="0.85" role="img"><title>{}</title><rect x="{}" y="{}" width="{size}" height="{size}" rx="{sw}" fill="{fill}" stroke="black" stroke-width="{sw}"/><text x="{x}" y="{ty}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle">{}</text></g>"##,
+            escape_xml(&structure_title(icon)),
+            x - half,
+            y - half,
+            icon.kind.glyph()
+        ));
+    }
+    svg
+}
+
+/// Displacement routes: a dashed line from each gun through its alternate
+/// firing positions, each stop numbered (the gun's own spot is position 1).
+/// The route of `active` (the gun being planned) is drawn brighter.
+pub fn build_displacement_routes(
+    guns: &[(f64, f64)],
+    alternates: &[Vec<(f64, f64)>],
+    active: Option<usize>,
+    color: &str,
+    s: f64,
+) -> String {
+    let sw = 2.5 * s;
+    let dash = 8.0 * s;
+    let r = 9.0 * s;
+    let fs = 11.0 * s;
+    let mut svg = String::new();
+    for (gi, stops) in alternates.iter().enumerate() {
+        let Some(&start) = guns.get(gi) else {
+            continue;
+        };
+        if stops.is_empty() {
+            continue;
+        }
+        let opacity = if active == Some(gi) || active.is_none() {
+            0.9
+        } else {
+            0.45
+        };
+        let points: Vec<String> = std::iter::once(start)
+            .chain(stops.iter().copied())
+            .map(|(x, y)| format!("{x},{y}"))
+            .collect();
+        svg.push_str(&format!(
+            r##"<g class="displacement-route" opacity="{opacity}"><polyline points="{}" fill="none" stroke="{color}" stroke-width="{sw}" stroke-dasharray="{dash} {dash}"/>"##,
+            points.join(" ")
+        ));
+        for (si, &(x, y)) in stops.iter().enumerate() {
+            let ty = y + fs * 0.35;
+            svg.push_str(&format!(
+                r##"<circle cx="{x}" cy="{y}" r="{r}" fill="rgba(0,0,0,0.6)" stroke="{color}" stroke-width="{sw}"/><text x="{x}" y="{ty}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle">{}</text>"##,
+                si + 2
+            ));
+        }
+        svg.push_str("</g>");
+    }
+    svg
+}
+
+/// Text drawn on a custom ring: its label, or its radius when it has none.
+pub fn custom_ring_label(ring: &CustomRing) -> String {
+    match ring.label.trim() {
+        "" => format!("{:.0} m", ring.radius),
+        label => label.to_string(),
+    }
+}
+
+/// Custom range rings: a dotted circle around each ring's marker with its
+/// label at the top. Rings whose marker no longer exists are skipped.
+pub fn build_custom_rings(
+    rings: &[CustomRing],
+    guns: &[(f64, f64)],
+    targets: &[(f64, f64)],
+    spotters: &[(f64, f64)],
+    s: f64,
+    scale: MapScale,
+) -> String {
+    let sw = 2.0 * s;
+    let dash = 4.0 * s;
+    let fs = 12.0 * s;
+    let tsw = 3.0 * s;
+    let mut svg = String::new();
+    for ring in rings {
+        let markers = match ring.anchor {
+            RingAnchor::Gun => guns,
+            RingAnchor::Target => targets,
+            RingAnchor::Spotter => spotters,
+        };
+        let Some(&(cx, cy)) = markers.get(ring.index) else {
+            continue;
+        };
+        let r = scale.meters_to_px_distance(ring.radius);
+        let ty = cy - r - 4.0 * s;
+        svg.push_str(&format!(
+            r##"<g class="custom-ring"><circle cx="{cx}" cy="{cy}" r="{r}" fill="none" stroke="white" stroke-width="{sw}" stroke-dasharray="{dash} {dash}" stroke-opacity="0.8"/><text x="{cx}" y="{ty}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{}</text></g>"##,
+            escape_xml(&custom_ring_label(ring))
+        ));
+    }
+    svg
+}
+
+/// Ghosts of the version being compared against (`changes` in meters): a
+/// dashed ring where a moved or removed marker used to be, a dashed line with
+/// the distance from a moved marker's old spot to its new one, and a dashed
+/// halo around markers that are new.
+pub fn build_plan_comparison(
+    changes: &[PlanChange],
+    colors: &ThemeColors,
+    s: f64,
+    scale: MapScale,
+) -> String {
+    let r = 12.0 * s;
+    let halo = 18.0 * s;
+    let sw = 2.5 * s;
+    let dash = 5.0 * s;
+    let fs = 12.0 * s;
+    let tsw = 3.0 * s;
+    let mut svg = String::new();
+    for c in changes {
+        let color = match c.kind {
+            MarkerKind::Gun => colors.gun,
+            MarkerKind::Target => colors.target,
+            MarkerKind::Spotter | MarkerKind::Bunker => colors.spotter,
+            MarkerKind::Enemy => colors.enemy,
+            MarkerKind::Fob => colors.gun,
+            MarkerKind::NoFire => "white",
+        };
+        let ghost = |svg: &mut String, class: &str, p: Position| {
+            let (x, y) = scale.meters_to_px(p.x, p.y);
+            svg.push_str(&format!(
+                r##"<circle class="{class}" cx="{x}" cy="{y}" r="{r}" fill="{color}" fill-opacity="0.25" stroke="{color}" stroke-width="{sw}" stroke-dasharray="{dash} {dash}"/>"##
+            ));
+        };
+        match c.change {
+            MarkerChange::Moved { from, to } => {
+                let (x1, y1) = scale.meters_to_px(from.x, from.y);
+                let (x2, y2) = scale.meters_to_px(to.x, to.y);
+                let (mx, my) = ((x1 + x2) / 2.0, (y1 + y2) / 2.0);
+                svg.push_str(r#"<g class="compare-moved">"#);
+                ghost(&mut svg, "compare-ghost", from);
+                svg.push_str(&format!(
+                    r##"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="{color}" stroke-width="{sw}" stroke-dasharray="{dash} {dash}"/><text x="{mx}" y="{my}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{:.0} m</text></g>"##,
+                    c.change.distance()
+                ));
+            }
+            MarkerChange::Removed(p) => ghost(&mut svg, "compare-ghost compare-removed", p),
+            MarkerChange::Added(p) => {
+                let (x, y) = scale.meters_to_px(p.x, p.y);
+                svg.push_str(&format!(
+                    r##"<circle class="compare-added" cx="{x}" cy="{y}" r="{halo}" fill="none" stroke="{color}" stroke-width="{sw}" stroke-dasharray="{dash} {dash}"/>"##
+                ));
+            }
+        }
+    }
+    svg
+}
+
+/// For each of our guns, whether any enemy gun sits inside its counter-battery
+/// danger zone for the given enemy weapon. Positions are map-image pixels.
+pub fn threatened_guns(
+    guns: &[(f64, f64)],
+    enemies: &[(f64, f64)],
+    enemy_weapon: &Weapon,
+    scale: MapScale,
+) -> Vec<bool> {
+    let to_pos = |&(x, y): &(f64, f64)| {
+        let (mx, my) = scale.px_to_meters(x, y);
+        Position { x: mx, y: my }
+    };
+    guns.iter()
+        .map(|g| {
+            let zone = calc::counter_battery(to_pos(g), enemy_weapon);
+            enemies.iter().any(|e| zone.contains(to_pos(e)))
+        })
+        .collect()
+}
+
+/// Draw the inverse range ring around each gun: the area an enemy gun of the
+/// given weapon must occupy to hit it. Rings with an enemy inside are highlighted.
+fn build_danger_zones(
+    svg: &mut String,
+    guns: &[(f64, f64)],
+    enemies: &[(f64, f64)],
+    enemy_weapon: &Weapon,
+    s: f64,
+    colors: &ThemeColors,
+    scale: MapScale,
+) {
+    let threatened = threatened_guns(guns, enemies, enemy_weapon, scale);
+    let outer = scale.meters_to_px_distance(enemy_weapon.max_range);
+    let inner = scale.meters_to_px_distance(enemy_weapon.min_range);
+    let sw = 2.0 * s;
+    let da1 = 10.0 * s;
+    let da2 = 6.0 * s;
+    let enemy_color = colors.enemy;
+    for (i, &(gx, gy)) in guns.iter().enumerate() {
+        let fill = if threatened[i] {
+            colors.danger_fill_threatened
+        } else {
+            colors.danger_fill
+        };
+        // Annulus as two concentric circle subpaths with even-odd fill
+        let ring = |r: f64| {
+            format!(
+                "M {} {gy} a {r} {r} 0 1 0 {} 0 a {r} {r} 0 1 0 {} 0 Z",
+                gx - r,
+                2.0 * r,
+                -2.0 * r
+            )
+        };
+        let d = format!("{} {}", ring(outer), ring(inner));
+        svg.push_str(&format!(
+            r##"<path class="danger-zone" d="{d}" fill="{fill}" fill-rule="evenodd" stroke="{enemy_color}" stroke-width="{sw}" stroke-dasharray="{da1} {da2}" stroke-opacity="0.6"/>"##
+        ));
+    }
+}
+
+fn build_firing_lines(
+    svg: &mut String,
+    guns: &[(f64, f64)],
+    targets: &[(f64, f64)],
+    gun_target_indices: &[Option<usize>],
+    s: f64,
+    colors: &ThemeColors,
+) {
+    for (gun_idx, &(gx, gy)) in guns.iter().enumerate() {
+        let target_idx = gun_target_indices.get(gun_idx).and_then(|o| *o);
+        if let Some(ti) = target_idx {
+            if let Some(&(tx, ty)) = targets.get(ti) {
+                let sw = 3.0 * s;
+                let da1 = 12.0 * s;
+                let da2 = 8.0 * s;
+                let stroke = colors.firing_line_stroke;
+                svg.push_str(&format!(
+                    r#"<line x1="{gx}" y1="{gy}" x2="{tx}" y2="{ty}" stroke="{stroke}" stroke-width="{sw}" stroke-dasharray="{da1} {da2}"/>"#
+                ));
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_accuracy_circles(
+    svg: &mut String,
+    guns: &[(f64, f64)],
+    targets: &[(f64, f64)],
+    gun_target_indices: &[Option<usize>],
+    spreads_px: &[Option<ShellSpread>],
+    salvo_sizes: &[u32],
+    s: f64,
+    colors: &ThemeColors,
+) {
+    // Draw the accuracy circle, or an area-fire gun's impact zone, at the
+    // target for each paired gun that has a solution, with the expected shell
+    // spread when the gun fires a salvo
+    for (gun_idx, _) in guns.iter().enumerate() {
+        let target_idx = gun_target_indices.get(gun_idx).and_then(|o| *o);
+        let spread = spreads_px.get(gun_idx).and_then(|o| *o);
+        if let (Some(ti), Some(spread)) = (target_idx, spread) {
+            if let Some(&(tx, ty)) = targets.get(ti) {
+                let sw = 2.0 * s;
+                let da1 = 6.0 * s;
+                let da2 = 4.0 * s;
+                let fill = colors.accuracy_fill;
+                let target_color = colors.target;
+                match spread {
+                    ShellSpread::Circle(acc_r) => svg.push_str(&format!(
+                        r##"<circle cx="{tx}" cy="{ty}" r="{acc_r}" fill="{fill}" stroke="{target_color}" stroke-width="{sw}" stroke-dasharray="{da1} {da2}"/>"##
+                    )),
+                    ShellSpread::Zone {
+                        length,
+                        width,
+                        azimuth,
+                    } => svg.push_str(&format!(
+                        r##"<ellipse class="impact-zone" cx="{tx}" cy="{ty}" rx="{}" ry="{}" transform="rotate({azimuth} {tx} {ty})" fill="{fill}" stroke="{target_color}" stroke-width="{sw}" stroke-dasharray="{da1} {da2}"/>"##,
+                        width / 2.0,
+                        length / 2.0,
+                    )),
+                }
+                let shells = salvo_sizes.get(gun_idx).copied().unwrap_or(1);
+                if shells > 1 {
+                    let dot_r = 2.5 * s;
+                    svg.push_str(r#"<g class="dispersion">"#);
+                    for (x, y) in spread.dispersion((tx, ty), shells) {
+                        svg.push_str(&format!(
+                            r##"<circle cx="{x}" cy="{y}" r="{dot_r}" fill="{target_color}" fill-opacity="0.8"/>"##
+                        ));
+                    }
+                    svg.push_str("</g>");
+                }
+            }
+        }
+    }
+}
+
+/// Faint arrows from each paired target to where an uncorrected shell from
+/// its gun would land in this wind.
+#[allow(clippy::too_many_arguments)]
+fn build_wind_drift(
+    svg: &mut String,
+    guns: &[(f64, f64)],
+    targets: &[(f64, f64)],
+    gun_weapons: &[Option<&Weapon>],
+    gun_target_indices: &[Option<usize>],
+    wind: &WindInput,
+    s: f64,
+    scale: MapScale,
+) {
+    let sw = 2.0 * s;
+    let r = 3.0 * s;
+    for (gi, &gun) in guns.iter().enumerate() {
+        let Some(&target) = gun_target_indices
+            .get(gi)
+            .copied()
+            .flatten()
+            .and_then(|ti| targets.get(ti))
+        else {
+            continue;
+        };
+        let Some(weapon) = gun_weapons.get(gi).copied().flatten() else {
+            continue;
+        };
+        let (land_x, land_y) = wind_landing(gun, target, weapon, wind, scale);
+        let (tx, ty) = target;
+        svg.push_str(&format!(
+            r##"<g class="wind-drift" opacity="0.5"><line x1="{tx}" y1="{ty}" x2="{land_x}" y2="{land_y}" stroke="white" stroke-width="{sw}"/><circle cx="{land_x}" cy="{land_y}" r="{r}" fill="white"/></g>"##
+        ));
+    }
+}
+
+/// Where a shell aimed from `gun` straight at `target` (both map-image px)
+/// lands once `wind` has pushed it.
+pub fn wind_landing(
+    gun: (f64, f64),
+    target: (f64, f64),
+    weapon: &Weapon,
+    wind: &WindInput,
+    scale: MapScale,
+) -> (f64, f64) {
+    let (gx, gy) = scale.px_to_meters(gun.0, gun.1);
+    let (tx, ty) = scale.px_to_meters(target.0, target.1);
+    let dist = calc::distance(Position { x: gx, y: gy }, Position { x: tx, y: ty });
+    let (dx, dy) = calc::wind_offset(wind, weapon, dist);
+    scale.meters_to_px(tx + dx, ty + dy)
+}
+
+/// Wind indicator in the map's top-right corner: an arrow pointing the way
+/// the wind pushes shells, and one filled pip per level of strength.
+fn build_wind_indicator(svg: &mut String, wind: &WindInput, s: f64, scale: MapScale) {
+    let r = 28.0 * s;
+    let cx = scale.width_px - r - 12.0 * s;
+    let cy = r + 12.0 * s;
+    let strength = wind.strength.min(5);
+    let direction = wind.direction.rem_euclid(360.0);
+    let title = format!("Wind {direction:.0}\u{00b0}, strength {strength}");
+    let sw = 3.0 * s;
+    let (tail, tip, head) = (0.6 * r, -0.7 * r, 0.3 * r);
+    svg.push_str(&format!(
+        r##"<g class="wind-indicator" role="img"><title>{title}</title><circle cx="{cx}" cy="{cy}" r="{r}" fill="rgba(0,0,0,0.55)" stroke="white" stroke-width="{}"/><g transform="translate({cx} {cy}) rotate({direction})"><line x1="0" y1="{tail}" x2="0" y2="{}" stroke="white" stroke-width="{sw}" stroke-linecap="round"/><polygon points="0,{tip} {head},{} -{head},{}" fill="white"/></g>"##,
+        s,
+        tip + head,
+        tip + head,
+        tip + head,
+    ));
+    let pip_r = 3.5 * s;
+    let gap = 10.0 * s;
+    let py = cy + r + 8.0 * s;
+    for i in 0..5u8 {
+        let px = cx + (i as f64 - 2.0) * gap;
+        let fill = if i < strength { "white" } else { "none" };
+        svg.push_str(&format!(
+            r##"<circle class="wind-pip" cx="{px}" cy="{py}" r="{pip_r}" fill="{fill}" stroke="white" stroke-width="{}"/>"##,
+            s
+        ));
+    }
+    svg.push_str("</g>");
+}
+
+/// Where `shells` shells of a salvo can be expected to land inside an accuracy
+/// circle: one shell per equal-area ring, spread around by the golden angle so
+/// the pattern is even and stable between renders.
+pub fn dispersion_pattern(center: (f64, f64), radius: f64, shells: u32) -> Vec<(f64, f64)> {
+    let golden_angle = std::f64::consts::PI * (3.0 - 5f64.sqrt());
+    (0..shells)
+        .map(|i| {
+            let r = radius * ((i as f64 + 0.5) / shells as f64).sqrt();
+            let theta = i as f64 * golden_angle;
+            (center.0 + r * theta.cos(), center.1 + r * theta.sin())
+        })
+        .collect()
+}
+
+/// Generate marker label: no number suffix for single markers, numbered for multiple.
+fn marker_label(base: &str, index: usize, total: usize) -> String {
+    if total <= 1 {
+        base.to_string()
+    } else {
+        format!("{} {}", base, index + 1)
+    }
+}
+
+/// The user's name for a marker (escaped for SVG), or the generated label.
+fn display_label(
+    labels: &MarkerLabels,
+    kind: MarkerKind,
+    base: &str,
+    index: usize,
+    total: usize,
+) -> String {
+    match labels.get(kind, index) {
+        Some(name) => escape_xml(name),
+        None => marker_label(base, index, total),
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn build_gun_markers(
+    svg: &mut String,
+    guns: &[(f64, f64)],
+    labels: &MarkerLabels,
+    s: f64,
+    selected: Option<SelectedMarker>,
+    colors: &ThemeColors,
+) {
+    let total = guns.len();
+    for (i, &(gx, gy)) in guns.iter().enumerate() {
+        let r = 12.0 * s;
+        let sw = 3.0 * s;
+        let fs = 16.0 * s;
+        let ty = gy - 20.0 * s;
+        let tsw = 4.0 * s;
+        let label = display_label(labels, MarkerKind::Gun, "GUN", i, total);
+        let gun_color = colors.gun;
+        svg.push_str(&format!(r##"<g role="img"><title>{label}</title>"##));
+        svg.push_str(&format!(
+            r##"<circle cx="{gx}" cy="{gy}" r="{r}" fill="{gun_color}" stroke="white" stroke-width="{sw}"/>"##
+        ));
+        svg.push_str(&format!(
+            r##"<text x="{gx}" y="{ty}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text>"##
+        ));
+        if selected
+            == Some(SelectedMarker {
+                kind: MarkerKind::Gun,
+                index: i,
+            })
+        {
+            build_selection_ring(svg, gx, gy, s);
+        }
+        svg.push_str("</g>");
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_target_markers(
+    svg: &mut String,
+    targets: &[(f64, f64)],
+    labels: &MarkerLabels,
+    statuses: &[TargetStatus],
+    clustered: &[bool],
+    s: f64,
+    selected: Option<SelectedMarker>,
+    colors: &ThemeColors,
+) {
+    let total = targets.len();
+    for (i, &(tx, ty)) in targets.iter().enumerate() {
+        if clustered.get(i).copied().unwrap_or(false) {
+            continue;
+        }
+        let arm = 16.0 * s;
+        let sw = 3.0 * s;
+        let r = 8.0 * s;
+        let fs = 16.0 * s;
+        let label_y = ty - 24.0 * s;
+        let tsw = 4.0 * s;
+        let label = display_label(labels, MarkerKind::Target, "TARGET", i, total);
+        let target_color = colors.target;
+        let target_label = colors.target_label;
+        let status = target_status(statuses, i);
+        // Finished targets fade back; fired-upon ones get a dashed ring
+        let (class, title) = match status {
+            TargetStatus::Active => ("", label.clone()),
+            TargetStatus::FiredUpon => {
+                (r#" class="target-fired""#, format!("{label} (fired upon)"))
+            }
+            TargetStatus::Destroyed => (
+                r#" class="target-destroyed" opacity="0.45""#,
+                format!("{label} (destroyed)"),
+            ),
+        };
+        svg.push_str(&format!(r##"<g role="img"{class}><title>{title}</title>"##));
+        svg.push_str(&format!(
+            r##"<line x1="{}" y1="{ty}" x2="{}" y2="{ty}" stroke="{target_color}" stroke-width="{sw}"/>"##,
+            tx - arm,
+            tx + arm
+        ));
+        svg.push_str(&format!(
+            r##"<line x1="{tx}" y1="{}" x2="{tx}" y2="{}" stroke="{target_color}" stroke-width="{sw}"/>"##,
+            ty - arm,
+            ty + arm
+        ));
+        svg.push_str(&format!(
+            r##"<circle cx="{tx}" cy="{ty}" r="{r}" fill="{target_color}" stroke="white" stroke-width="{sw}"/>"##
+        ));
+        svg.push_str(&format!(
+            r##"<text x="{tx}" y="{label_y}" fill="{target_label}" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text>"##
+        ));
+        match status {
+            TargetStatus::Active => {}
+            TargetStatus::FiredUpon => svg.push_str(&format!(
+                r##"<circle cx="{tx}" cy="{ty}" r="{arm}" fill="none" stroke="{target_color}" stroke-width="{sw}" stroke-dasharray="{dash}"/>"##,
+                dash = 4.0 * s
+            )),
+            TargetStatus::Destroyed => svg.push_str(&format!(
+                r##"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="white" stroke-width="{sw}"/>"##,
+                tx - arm,
+                ty + arm,
+                tx + arm,
+                ty - arm
+            )),
+        }
+        if selected
+            == Some(SelectedMarker {
+                kind: MarkerKind::Target,
+                index: i,
+            })
+        {
+            build_selection_ring(svg, tx, ty, s);
+        }
+        svg.push_str("</g>");
+    }
+}
+
+/// Draw a count badge for each multi-target cluster, centred on its members.
+fn build_cluster_badges(
+    svg: &mut String,
+    targets: &[(f64, f64)],
+    clusters: &[Vec<usize>],
+    s: f64,
+    colors: &ThemeColors,
+) {
+    let r = CLUSTER_RADIUS * s * 0.75;
+    let sw = 3.0 * s;
+    let fs = 16.0 * s;
+    let target_color = colors.target;
+    for members in clusters {
+        let (cx, cy) = cluster_center(targets, members);
+        let count = members.len();
+        let ty = cy + fs * 0.35;
+        svg.push_str(&format!(
+            r##"<g role="img" class="cluster-badge"><title>{count} targets (click to expand)</title>"##
+        ));
+        svg.push_str(&format!(
+            r##"<circle cx="{cx}" cy="{cy}" r="{r}" fill="{target_color}" fill-opacity="0.85" stroke="white" stroke-width="{sw}"/>"##
+        ));
+        svg.push_str(&format!(
+            r##"<text x="{cx}" y="{ty}" fill="white" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle">{count}</text>"##
+        ));
+        svg.push_str("</g>");
+    }
+}
+
+fn build_spotter_markers(
+    svg: &mut String,
+    spotters: &[(f64, f64)],
+    labels: &MarkerLabels,
+    s: f64,
+    selected: Option<SelectedMarker>,
+    colors: &ThemeColors,
+) {
+    let total = spotters.len();
+    for (i, &(sx, sy)) in spotters.iter().enumerate() {
+        let r = 10.0 * s;
+        let sw = 3.0 * s;
+        let fs = 16.0 * s;
+        let label_y = sy - 20.0 * s;
+        let tsw = 4.0 * s;
+        let label = display_label(labels, MarkerKind::Spotter, "SPOTTER", i, total);
+        let spotter_color = colors.spotter;
+        let spotter_label = colors.spotter_label;
+        svg.push_str(&format!(r##"<g role="img"><title>{label}</title>"##));
+        svg.push_str(&format!(
+            r##"<circle cx="{sx}" cy="{sy}" r="{r}" fill="{spotter_color}" stroke="white" stroke-width="{sw}"/>"##
+        ));
+        svg.push_str(&format!(
+            r##"<text x="{sx}" y="{label_y}" fill="{spotter_label}" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text>"##
+        ));
+        if selected
+            == Some(SelectedMarker {
+                kind: MarkerKind::Spotter,
+                index: i,
+            })
+        {
+            build_selection_ring(svg, sx, sy, s);
+        }
+        svg.push_str("</g>");
+    }
+}
+
+fn build_enemy_markers(
+    svg: &mut String,
+    enemies: &[(f64, f64)],
+    s: f64,
+    selected: Option<SelectedMarker>,
+    colors: &ThemeColors,
+) {
+    let total = enemies.len();
+    for (i, &(ex, ey)) in enemies.iter().enumerate() {
+        let r = 12.0 * s;
+        let sw = 3.0 * s;
+        let fs = 16.0 * s;
+        let label_y = ey - 22.0 * s;
+        let tsw = 4.0 * s;
+        let label = marker_label("ENEMY", i, total);
+        let enemy_color = colors.enemy;
+        svg.push_str(&format!(r##"<g role="img"><title>{label}</title>"##));
+        // Diamond so enemy guns read differently from our round gun markers
+        svg.push_str(&format!(
+            r##"<polygon points="{ex},{} {},{ey} {ex},{} {},{ey}" fill="{enemy_color}" stroke="black" stroke-width="{sw}"/>"##,
+            ey - r,
+            ex + r,
+            ey + r,
+            ex - r
+        ));
+        svg.push_str(&format!(
+            r##"<text x="{ex}" y="{label_y}" fill="{enemy_color}" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text>"##
+        ));
+        if selected
+            == Some(SelectedMarker {
+                kind: MarkerKind::Enemy,
+                index: i,
+            })
+        {
+            build_selection_ring(svg, ex, ey, s);
+        }
+        svg.push_str("</g>");
+    }
+}
+
+/// Friendly FOBs (squares in the gun colour), observation bunkers (triangles
+/// in the spotter colour) and no-fire zones: a dashed white circle of the
+/// zone's radius (`no_fire_radii`, meters) around a crossed-out centre. Zones
+/// listed in `danger_close` are drawn in the target colour and called out.
+/// Drawn over the rest of the overlay, like [`build_custom_rings`].
+#[allow(clippy::too_many_arguments)]
+pub fn build_intel_markers(
+    fobs: &[(f64, f64)],
+    bunkers: &[(f64, f64)],
+    no_fire_zones: &[(f64, f64)],
+    no_fire_radii: &[f64],
+    danger_close: &[usize],
+    s: f64,
+    selected: Option<SelectedMarker>,
+    colors: &ThemeColors,
+    scale: MapScale,
+) -> String {
+    let sw = 3.0 * s;
+    let fs = 16.0 * s;
+    let tsw = 4.0 * s;
+    let mut svg = String::new();
+    let label = |svg: &mut String, x: f64, y: f64, color: &str, text: &str| {
+        svg.push_str(&format!(
+            r##"<text x="{x}" y="{y}" fill="{color}" font-size="{fs}" font-family="sans-serif" font-weight="700" text-anchor="middle" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{text}</text>"##
+        ));
+    };
+    let select = |svg: &mut String, kind: MarkerKind, index: usize, x: f64, y: f64| {
+        if selected == Some(SelectedMarker { kind, index }) {
+            build_selection_ring(svg, x, y, s);
+        }
+    };
+
+    let total = no_fire_zones.len();
+    for (i, &(nx, ny)) in no_fire_zones.iter().enumerate() {
+        let name = marker_label("NO FIRE", i, total);
+        let zone_r = scale.meters_to_px_distance(no_fire_radius(no_fire_radii, i));
+        let danger = danger_close.contains(&i);
+        let (class, color, fill) = if danger {
+            (
+                "no-fire-zone danger-close",
+                colors.target,
+                colors.accuracy_fill,
+            )
+        } else {
+            ("no-fire-zone", "white", "rgba(255,255,255,0.12)")
+        };
+        let r = 10.0 * s;
+        let d = r * std::f64::consts::FRAC_1_SQRT_2;
+        let dash = 8.0 * s;
+        svg.push_str(&format!(
+            r##"<g role="img" class="{class}"><title>{name}</title>"##
+        ));
+        svg.push_str(&format!(
+            r##"<circle cx="{nx}" cy="{ny}" r="{zone_r}" fill="{fill}" stroke="{color}" stroke-width="{sw}" stroke-dasharray="{dash} {dash}"/>"##
+        ));
+        // A "no entry" sign marks the centre so it can be picked and dragged
+        svg.push_str(&format!(
+            r##"<circle cx="{nx}" cy="{ny}" r="{r}" fill="rgba(0,0,0,0.6)" stroke="{color}" stroke-width="{sw}"/><line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{color}" stroke-width="{sw}"/>"##,
+            nx - d,
+            ny - d,
+            nx + d,
+            ny + d
+        ));
+        label(&mut svg, nx, ny - zone_r.max(r) - 6.0 * s, color, &name);
+        if danger {
+            label(&mut svg, nx, ny + zone_r.max(r) + fs, color, "DANGER CLOSE");
+        }
+        select(&mut svg, MarkerKind::NoFire, i, nx, ny);
+        svg.push_str("</g>");
+    }
+
+    let total = fobs.len();
+    for (i, &(fx, fy)) in fobs.iter().enumerate() {
+        let name = marker_label("FOB", i, total);
+        let half = 11.0 * s;
+        let color = colors.gun;
+        svg.push_str(&format!(r##"<g role="img"><title>{name}</title>"##));
+        svg.push_str(&format!(
+            r##"<rect x="{}" y="{}" width="{}" height="{}" fill="{color}" stroke="white" stroke-width="{sw}"/>"##,
+            fx - half,
+            fy - half,
+            2.0 * half,
+            2.0 * half
+        ));
+        label(&mut svg, fx, fy - 20.0 * s, color, &name);
+        select(&mut svg, MarkerKind::Fob, i, fx, fy);
+        svg.push_str("</g>");
+    }
+
+    let total = bunkers.len();
+    for (i, &(bx, by)) in bunkers.iter().enumerate() {
+        let name = marker_label("BUNKER", i, total);
+        let r = 13.0 * s;
+        let color = colors.spotter;
+        svg.push_str(&format!(r##"<g role="img"><title>{name}</title>"##));
+        svg.push_str(&format!(
+            r##"<polygon points="{bx},{} {},{} {},{}" fill="{color}" stroke="white" stroke-width="{sw}"/>"##,
+            by - r,
+            bx + r,
+            by + r * 0.75,
+            bx - r,
+            by + r * 0.75
+        ));
+        label(&mut svg, bx, by - 20.0 * s, colors.spotter_label, &name);
+        select(&mut svg, MarkerKind::Bunker, i, bx, by);
+        svg.push_str("</g>");
+    }
+    svg
+}
+
+/// Emit an animated dashed selection ring around a marker.
+/// Selection rings for every marker in a multi-selection. `s` is the
+/// [`marker_scale`] the markers were drawn with.
+#[allow(clippy::too_many_arguments)]
+pub fn build_group_selection(
+    group: &[SelectedMarker],
+    guns: &[(f64, f64)],
+    targets: &[(f64, f64)],
+    spotters: &[(f64, f64)],
+    enemies: &[(f64, f64)],
+    fobs: &[(f64, f64)],
+    bunkers: &[(f64, f64)],
+    no_fire_zones: &[(f64, f64)],
+    s: f64,
+) -> String {
+    let mut svg = String::new();
+    for marker in group {
+        let list = match marker.kind {
+            MarkerKind::Gun => guns,
+            MarkerKind::Target => targets,
+            MarkerKind::Spotter => spotters,
+            MarkerKind::Enemy => enemies,
+            MarkerKind::Fob => fobs,
+            MarkerKind::Bunker => bunkers,
+            MarkerKind::NoFire => no_fire_zones,
+        };
+        if let Some(&(x, y)) = list.get(marker.index) {
+            build_selection_ring(&mut svg, x, y, s);
+        }
+    }
+    svg
+}
+
+fn build_selection_ring(svg: &mut String, cx: f64, cy: f64, s: f64) {
+    let r = 24.0 * s;
+    let sw = 3.0 * s;
+    let da1 = 6.0 * s;
+    let da2 = 4.0 * s;
+    svg.push_str(&format!(
+        r##"<circle cx="{cx}" cy="{cy}" r="{r}" fill="none" stroke="white" stroke-width="{sw}" stroke-dasharray="{da1} {da2}" opacity="0.9"><animate attributeName="opacity" values="0.5;1;0.5" dur="1.2s" repeatCount="indefinite"/></circle>"##
+    ));
+}
+
+/// Compass rose centered on a gun: a tick every 10°, longer ones with their
+/// bearing every 30°, and the cardinal points, so an azimuth can be checked
+/// against the map by eye.
+fn build_compass_rose(svg: &mut String, cx: f64, cy: f64, s: f64) {
+    let r = 70.0 * s;
+    let sw = 1.5 * s;
+    let fs = 11.0 * s;
+    let tsw = 3.0 * s;
+    let point = |bearing: f64, dist: f64| {
+        let rad = bearing.to_radians();
+        (cx + dist * rad.sin(), cy - dist * rad.cos())
+    };
+    svg.push_str(&format!(
+        r##"<g class="compass-rose" opacity="0.8"><circle cx="{cx}" cy="{cy}" r="{r}" fill="none" stroke="white" stroke-width="{sw}"/>"##
+    ));
+    for deg in (0..360).step_by(10) {
+        let major = deg % 30 == 0;
+        let (x1, y1) = point(deg as f64, r);
+        let (x2, y2) = point(deg as f64, if major { r - 10.0 * s } else { r - 5.0 * s });
+        svg.push_str(&format!(
+            r##"<line class="compass-tick" x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="white" stroke-width="{sw}"/>"##
+        ));
+        if !major {
+            continue;
+        }
+        let (label, size) = match deg {
+            0 => ("N".to_string(), fs * 1.3),
+            90 => ("E".to_string(), fs * 1.3),
+            180 => ("S".to_string(), fs * 1.3),
+            270 => ("W".to_string(), fs * 1.3),
+            _ => (deg.to_string(), fs),
+        };
+        let (x, y) = point(deg as f64, r + 10.0 * s);
+        svg.push_str(&format!(
+            r##"<text x="{x}" y="{y}" fill="white" font-size="{size}" font-family="sans-serif" font-weight="700" text-anchor="middle" dominant-baseline="central" stroke="rgba(0,0,0,0.7)" stroke-width="{tsw}" paint-order="stroke">{label}</text>"##
+        ));
+    }
+    svg.push_str("</g>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NO_FIRE_RADIUS_M;
+
+    // --- build_firing_lines tests ---
+
+    #[test]
+    fn test_firing_lines_with_explicit_pairings() {
+        let guns = vec![(100.0, 200.0), (300.0, 400.0)];
+        let targets = vec![(150.0, 250.0), (350.0, 450.0)];
+        // Gun 0 → Target 1, Gun 1 → Target 0
+        let pairings = vec![Some(1), Some(0)];
+        let mut svg = String::new();
+        build_firing_lines(&mut svg, &guns, &targets, &pairings, 1.0, &MARKER_COLORS);
+        // Should draw line from gun 0 to target 1
+        assert!(svg.contains(r#"x1="100""#));
+        assert!(svg.contains(r#"y1="200""#));
+        assert!(svg.contains(r#"x2="350""#));
+        assert!(svg.contains(r#"y2="450""#));
+        // Should draw line from gun 1 to target 0
+        assert!(svg.contains(r#"x1="300""#));
+        assert!(svg.contains(r#"y1="400""#));
+        assert!(svg.contains(r#"x2="150""#));
+        assert!(svg.contains(r#"y2="250""#));
+    }
+
+    #[test]
+    fn test_firing_lines_unpaired_gun_draws_nothing() {
+        let guns = vec![(100.0, 200.0)];
+        let targets = vec![(150.0, 250.0)];
+        let pairings = vec![None]; // Gun 0 unpaired
+        let mut svg = String::new();
+        build_firing_lines(&mut svg, &guns, &targets, &pairings, 1.0, &MARKER_COLORS);
+        assert!(
+            svg.is_empty(),
+            "Unpaired gun should not produce a firing line"
+        );
+    }
+
+    #[test]
+    fn test_firing_lines_invalid_target_index_draws_nothing() {
+        let guns = vec![(100.0, 200.0)];
+        let targets = vec![(150.0, 250.0)];
+        let pairings = vec![Some(5)]; // Out-of-bounds target index
+        let mut svg = String::new();
+        build_firing_lines(&mut svg, &guns, &targets, &pairings, 1.0, &MARKER_COLORS);
+        assert!(
+            svg.is_empty(),
+            "Invalid target index should not produce a firing line"
+        );
+    }
+
+    #[test]
+    fn test_firing_lines_multiple_guns_same_target() {
+        let guns = vec![(100.0, 200.0), (300.0, 400.0)];
+        let targets = vec![(500.0, 600.0)];
+        // Both guns target the same target
+        let pairings = vec![Some(0), Some(0)];
+        let mut svg = String::new();
+        build_firing_lines(&mut svg, &guns, &targets, &pairings, 1.0, &MARKER_COLORS);
+        // Count the number of line elements — should be 2
+        let line_count = svg.matches("<line").count();
+        assert_eq!(
+            line_count, 2,
+            "Two guns pointing at same target should produce two lines"
+        );
+    }
+
+    // --- build_accuracy_circles tests ---
+
+    #[test]
+    fn test_accuracy_circles_with_pairings() {
+        let guns = vec![(100.0, 200.0)];
+        let targets = vec![(150.0, 250.0), (350.0, 450.0)];
+        let pairings = vec![Some(1)]; // Gun 0 → Target 1
+        let accuracy = vec![Some(ShellSpread::Circle(10.0))];
+        let mut svg = String::new();
+        build_accuracy_circles(
+            &mut svg,
+            &guns,
+            &targets,
+            &pairings,
+            &accuracy,
+            &[],
+            1.0,
+            &MARKER_COLORS,
+        );
+        // Circle should be at target 1's position
+        assert!(svg.contains(r#"cx="350""#));
+        assert!(svg.contains(r#"cy="450""#));
+        // Should NOT be at target 0's position
+        assert!(!svg.contains(r#"cx="150""#));
+    }
+
+    #[test]
+    fn test_accuracy_circles_unpaired_gun_draws_nothing() {
+        let guns = vec![(100.0, 200.0)];
+        let targets = vec![(150.0, 250.0)];
+        let pairings = vec![None];
+        let accuracy = vec![Some(ShellSpread::Circle(10.0))];
+        let mut svg = String::new();
+        build_accuracy_circles(
+            &mut svg,
+            &guns,
+            &targets,
+            &pairings,
+            &accuracy,
+            &[],
+            1.0,
+            &MARKER_COLORS,
+        );
+        assert!(svg.is_empty());
+    }
+
+    // --- clustering tests ---
+
+    #[test]
+    fn test_cluster_positions_groups_nearby() {
+        let positions = vec![(100.0, 100.0), (105.0, 100.0), (500.0, 500.0)];
+        let clusters = cluster_positions(&positions, 20.0);
+        assert_eq!(clusters, vec![vec![0, 1], vec![2]]);
+    }
+
+    #[test]
+    fn test_cluster_positions_empty() {
+        assert!(cluster_positions(&[], 20.0).is_empty());
+    }
+
+    #[test]
+    fn test_cluster_center_is_mean() {
+        let positions = vec![(100.0, 100.0), (110.0, 120.0)];
+        let (cx, cy) = cluster_center(&positions, &[0, 1]);
+        assert!((cx - 105.0).abs() < 1e-9);
+        assert!((cy - 110.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_target_clusters_disabled_above_threshold() {
+        let targets = vec![(100.0, 100.0), (101.0, 100.0)];
+        assert!(target_clusters(&targets, CLUSTER_ZOOM_THRESHOLD, 960.0, None).is_empty());
+        assert_eq!(
+            target_clusters(&targets, 1.0, 960.0, None),
+            vec![vec![0, 1]]
+        );
+    }
+
+    #[test]
+    fn test_measure_distance_and_bearing() {
+        // Due east by 100 m
+        let a = MapScale::STANDARD.meters_to_px(1000.0, 1000.0);
+        let b = MapScale::STANDARD.meters_to_px(1100.0, 1000.0);
+        let (distance, bearing) = measure(a, b, MapScale::STANDARD);
+        assert!((distance - 100.0).abs() < 1e-6);
+        assert!((bearing - 90.0).abs() < 1e-6);
+        // Back the other way is due west
+        let (_, back) = measure(b, a, MapScale::STANDARD);
+        assert!((back - 270.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_measure_label_pads_and_wraps_bearing() {
+        assert_eq!(measure_label(312.4, 47.2), "312 m  047\u{00b0}");
+        assert_eq!(measure_label(50.0, 359.7), "50 m  000\u{00b0}");
+    }
+
+    #[test]
+    fn test_build_measure_line_labels_midpoint() {
+        let a = MapScale::STANDARD.meters_to_px(0.0, 200.0);
+        let b = MapScale::STANDARD.meters_to_px(0.0, 0.0);
+        let svg = build_measure_line(a, b, 1.0, MapScale::STANDARD);
+        assert!(svg.contains("<title>200 m  000\u{00b0}</title>"));
+        assert_eq!(svg.matches("<circle").count(), 2);
+    }
+
+    #[test]
+    fn test_build_heatmap() {
+        let cells = [
+            HeatCell {
+                col: 6,
+                row: 8,
+                count: 10,
+            },
+            HeatCell {
+                col: 0,
+                row: 0,
+                count: 5,
+            },
+        ];
+        let scale = MapScale::STANDARD;
+        let svg = build_heatmap(&cells, "#5ab882", scale);
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains("<title>G9: 10</title>"));
+        assert!(svg.contains("<title>A1: 5</title>"));
+        // The busiest cell is the most opaque
+        assert!(svg.contains(r#"fill-opacity="0.55""#));
+        assert!(svg.contains(r#"fill-opacity="0.33""#));
+        assert!(svg.contains(&format!(r#"x="{}""#, scale.grid_col_px(6))));
+        assert!(build_heatmap(&[], "#5ab882", scale).is_empty());
+    }
+
+    #[test]
+    fn test_build_structure_icons() {
+        let icons = [
+            StructureIcon {
+                kind: StructureKind::TownHall,
+                team: Some(Faction::Warden),
+                pos: (100.0, 200.0),
+                location: Some("Callahan's Gate".to_string()),
+            },
+            StructureIcon {
+                kind: StructureKind::RelicBase,
+                team: None,
+                pos: (300.0, 400.0),
+                location: None,
+            },
+        ];
+        let svg = build_structure_icons(&icons, 1.0);
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains("<title>Town hall, Callahan&apos;s Gate (Warden)</title>"));
+        assert!(svg.contains("<title>Relic base</title>"));
+        assert!(svg.contains(r##"fill="#2d5fa8""##));
+        assert!(svg.contains(">T</text>"));
+        assert!(build_structure_icons(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_nearest_enemy_structure() {
+        let structure = |team, pos| StructureIcon {
+            kind: StructureKind::TownHall,
+            team,
+            pos,
+            location: Some("Abandoned Ward".to_string()),
+        };
+        let icons = [
+            structure(Some(Faction::Colonial), (100.0, 100.0)),
+            structure(Some(Faction::Warden), (120.0, 100.0)),
+            structure(None, (105.0, 100.0)),
+            structure(Some(Faction::Warden), (140.0, 100.0)),
+        ];
+        // Own and unclaimed structures are skipped even when closer
+        assert_eq!(
+            nearest_enemy_structure(&icons, (104.0, 100.0), 50.0, Faction::Colonial),
+            Some(1)
+        );
+        assert_eq!(
+            nearest_enemy_structure(&icons, (135.0, 100.0), 50.0, Faction::Colonial),
+            Some(3)
+        );
+        assert_eq!(
+            nearest_enemy_structure(&icons, (104.0, 100.0), 50.0, Faction::Warden),
+            Some(0)
+        );
+        assert_eq!(
+            nearest_enemy_structure(&icons, (300.0, 100.0), 50.0, Faction::Colonial),
+            None
+        );
+        assert_eq!(structure_label(&icons[0]), "Town hall, Abandoned Ward");
+    }
+
+    #[test]
+    fn test_build_displacement_routes_numbers_stops() {
+        let guns = [(10.0, 10.0), (50.0, 50.0), (90.0, 90.0)];
+        let alternates = vec![vec![(20.0, 10.0), (30.0, 10.0)], vec![]];
+        let svg = build_displacement_routes(&guns, &alternates, None, "#5ab882", 1.0);
+        // Only gun 1 has a route; gun 3 has no entry at all
+        assert_eq!(svg.matches("<polyline").count(), 1);
+        assert!(svg.contains(r#"points="10,10 20,10 30,10""#));
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains(">2</text>"));
+        assert!(svg.contains(">3</text>"));
+    }
+
+    #[test]
+    fn test_build_displacement_routes_dims_other_guns() {
+        let guns = [(10.0, 10.0), (50.0, 50.0)];
+        let alternates = vec![vec![(20.0, 10.0)], vec![(60.0, 50.0)]];
+        let svg = build_displacement_routes(&guns, &alternates, Some(1), "#5ab882", 1.0);
+        assert_eq!(svg.matches(r#"opacity="0.45""#).count(), 1);
+        assert_eq!(svg.matches(r#"opacity="0.9""#).count(), 1);
+    }
+
+    #[test]
+    fn test_build_plan_comparison_draws_ghosts() {
+        let scale = MapScale::STANDARD;
+        let at = |x: f64, y: f64| Position { x, y };
+        let changes = [
+            PlanChange {
+                kind: MarkerKind::Gun,
+                index: 0,
+                change: MarkerChange::Moved {
+                    from: at(100.0, 100.0),
+                    to: at(130.0, 140.0),
+                },
+            },
+            PlanChange {
+                kind: MarkerKind::Target,
+                index: 1,
+                change: MarkerChange::Removed(at(500.0, 500.0)),
+            },
+            PlanChange {
+                kind: MarkerKind::Spotter,
+                index: 0,
+                change: MarkerChange::Added(at(300.0, 300.0)),
+            },
+        ];
+        let svg = build_plan_comparison(&changes, &MARKER_COLORS, 1.0, scale);
+        assert_eq!(svg.matches("compare-ghost").count(), 2);
+        assert!(svg.contains(">50 m</text>"));
+        let (x, y) = scale.meters_to_px(500.0, 500.0);
+        assert!(svg.contains(&format!(
+            r#"class="compare-ghost compare-removed" cx="{x}" cy="{y}""#
+        )));
+        assert!(svg.contains(r#"class="compare-added" cx="#));
+        assert!(svg.contains(MARKER_COLORS.spotter));
+        assert!(build_plan_comparison(&[], &MARKER_COLORS, 1.0, scale).is_empty());
+    }
+
+    #[test]
+    fn test_build_custom_rings_follows_markers() {
+        let scale = MapScale::STANDARD;
+        let ring = |anchor: RingAnchor, index: usize, label: &str| CustomRing {
+            anchor,
+            index,
+            radius: 250.0,
+            label: label.to_string(),
+        };
+        let rings = [
+            ring(RingAnchor::Spotter, 0, ""),
+            ring(RingAnchor::Gun, 0, "Intel <range>"),
+            ring(RingAnchor::Target, 3, "Gone"),
+        ];
+        let svg = build_custom_rings(&rings, &[(10.0, 10.0)], &[], &[(50.0, 60.0)], 1.0, scale);
+        // The ring around a missing target is skipped
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains(r#"cx="50" cy="60""#));
+        assert!(svg.contains(&format!(r#"r="{}""#, scale.meters_to_px_distance(250.0))));
+        assert!(svg.contains(">250 m</text>"));
+        assert!(svg.contains(">Intel &lt;range&gt;</text>"));
+        assert!(!svg.contains("Gone"));
+    }
+
+    #[test]
+    fn test_build_intel_markers_draws_each_kind() {
+        let scale = MapScale::STANDARD;
+        let selected = Some(SelectedMarker {
+            kind: MarkerKind::Bunker,
+            index: 0,
+        });
+        let svg = build_intel_markers(
+            &[(10.0, 10.0), (40.0, 40.0)],
+            &[(100.0, 100.0)],
+            &[(500.0, 500.0)],
+            &[],
+            &[],
+            1.0,
+            selected,
+            &MARKER_COLORS,
+            scale,
+        );
+        assert_eq!(svg.matches("<rect").count(), 2);
+        assert!(svg.contains(">FOB 2</text>"));
+        assert_eq!(svg.matches("<polygon").count(), 1);
+        assert!(svg.contains(">BUNKER</text>"));
+        // The no-fire zone is drawn at its real size
+        assert!(svg.contains(&format!(
+            r#"cx="500" cy="500" r="{}""#,
+            scale.meters_to_px_distance(NO_FIRE_RADIUS_M)
+        )));
+        assert!(svg.contains(">NO FIRE</text>"));
+        assert!(!svg.contains("danger-close"));
+        assert_eq!(svg.matches("<animate").count(), 1);
+        assert!(
+            build_intel_markers(&[], &[], &[], &[], &[], 1.0, None, &MARKER_COLORS, scale)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_no_fire_zones_draw_their_radius_and_danger_close() {
+        let scale = MapScale::STANDARD;
+        let zones = [(100.0, 100.0), (500.0, 500.0)];
+        let svg = build_intel_markers(
+            &[],
+            &[],
+            &zones,
+            &[120.0],
+            &[1],
+            1.0,
+            None,
+            &MARKER_COLORS,
+            scale,
+        );
+        assert!(svg.contains(&format!(
+            r#"cx="100" cy="100" r="{}""#,
+            scale.meters_to_px_distance(120.0)
+        )));
+        // The second zone has no radius of its own
+        assert!(svg.contains(&format!(
+            r#"cx="500" cy="500" r="{}""#,
+            scale.meters_to_px_distance(NO_FIRE_RADIUS_M)
+        )));
+        assert_eq!(svg.matches("danger-close").count(), 1);
+        assert!(svg.contains(">DANGER CLOSE</text>"));
+    }
+
+    #[test]
+    fn test_no_fire_draft_labels_radius() {
+        let scale = MapScale::STANDARD;
+        let edge = (100.0 + scale.meters_to_px_distance(80.0), 100.0);
+        let svg = build_no_fire_draft((100.0, 100.0), edge, 1.0, scale);
+        assert!(svg.contains(r#"class="no-fire-draft""#));
+        assert!(svg.contains(">80 m</text>"));
+    }
+
+    #[test]
+    fn test_dispersion_pattern_stays_inside_circle() {
+        let points = dispersion_pattern((100.0, 100.0), 20.0, 8);
+        assert_eq!(points.len(), 8);
+        for (x, y) in &points {
+            assert!(((x - 100.0).powi(2) + (y - 100.0).powi(2)).sqrt() < 20.0);
+        }
+        assert_eq!(points, dispersion_pattern((100.0, 100.0), 20.0, 8));
+    }
+
+    #[test]
+    fn test_salvo_draws_dispersion_only_for_multiple_shells() {
+        let render = |salvo: &[u32]| {
+            let mut svg = String::new();
+            build_accuracy_circles(
+                &mut svg,
+                &[(0.0, 0.0)],
+                &[(300.0, 300.0)],
+                &[Some(0)],
+                &[Some(ShellSpread::Circle(25.0))],
+                salvo,
+                1.0,
+                &MARKER_COLORS,
+            );
+            svg
+        };
+        assert!(!render(&[]).contains("dispersion"));
+        assert!(!render(&[1]).contains("dispersion"));
+        let svg = render(&[6]);
+        assert!(svg.contains(r#"<g class="dispersion">"#));
+        assert_eq!(svg.matches("<circle").count(), 7);
+    }
+
+    #[test]
+    fn test_area_fire_draws_impact_zone_along_line_of_fire() {
+        let zone = ShellSpread::Zone {
+            length: 40.0,
+            width: 20.0,
+            azimuth: 90.0,
+        };
+        let mut svg = String::new();
+        build_accuracy_circles(
+            &mut svg,
+            &[(0.0, 300.0)],
+            &[(300.0, 300.0)],
+            &[Some(0)],
+            &[Some(zone)],
+            &[6],
+            1.0,
+            &MARKER_COLORS,
+        );
+        assert!(svg.contains(
+            r#"<ellipse class="impact-zone" cx="300" cy="300" rx="10" ry="20" transform="rotate(90 300 300)""#
+        ));
+        // Fired due east: the salvo strings out east-west
+        let points = zone.dispersion((300.0, 300.0), 6);
+        assert_eq!(points.len(), 6);
+        for (x, y) in &points {
+            assert!(((x - 300.0) / 20.0).powi(2) + ((y - 300.0) / 10.0).powi(2) < 1.0);
+        }
+        assert!(points.iter().any(|(x, _)| (x - 300.0).abs() > 10.0));
+    }
+
+    #[test]
+    fn test_build_group_selection_rings_each_member() {
+        let group = [
+            SelectedMarker {
+                kind: MarkerKind::Gun,
+                index: 0,
+            },
+            SelectedMarker {
+                kind: MarkerKind::Spotter,
+                index: 1,
+            },
+            // Stale index: skipped
+            SelectedMarker {
+                kind: MarkerKind::Target,
+                index: 5,
+            },
+        ];
+        let svg = build_group_selection(
+            &group,
+            &[(10.0, 20.0)],
+            &[],
+            &[(0.0, 0.0), (300.0, 400.0)],
+            &[],
+            &[],
+            &[],
+            &[],
+            1.0,
+        );
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains(r#"cx="10" cy="20""#));
+        assert!(svg.contains(r#"cx="300" cy="400""#));
+    }
+
+    #[test]
+    fn test_target_clusters_skips_selected_target() {
+        let targets = vec![(100.0, 100.0), (101.0, 100.0), (102.0, 100.0)];
+        let selected = Some(SelectedMarker {
+            kind: MarkerKind::Target,
+            index: 0,
+        });
+        assert_eq!(
+            target_clusters(&targets, 1.0, 960.0, selected),
+            vec![vec![1, 2]]
+        );
+    }
+
+    #[test]
+    fn test_clustered_targets_render_as_badge() {
+        let targets = vec![(100.0, 100.0), (102.0, 100.0)];
+        let svg = build_svg_content(
+            &[],
+            &targets,
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &MarkerLabels::default(),
+            &[],
+            1.0,
+            false,
+            GridWindow::ALL,
+            960.0,
+            None,
+            None,
+            None,
+            false,
+            &MARKER_COLORS,
+            MapScale::STANDARD,
+        );
+        assert!(svg.contains("2 targets"));
+        assert!(!svg.contains("TARGET 1"));
+    }
+
+    #[test]
+    fn test_high_contrast_outlines_and_enlarges_markers() {
+        let render = |high_contrast| {
+            build_svg_content(
+                &[(500.0, 500.0)],
+                &[],
+                &[],
+                &[],
+                &[None],
+                &[],
+                &[None],
+                &[],
+                &[],
+                &MarkerLabels::default(),
+                &[],
+                1.0,
+                false,
+                GridWindow::ALL,
+                960.0,
+                None,
+                None,
+                None,
+                high_contrast,
+                &MARKER_COLORS,
+                MapScale::STANDARD,
+            )
+        };
+        let normal = render(false);
+        let contrast = render(true);
+        assert!(!normal.contains("hc-outline"));
+        assert!(contrast.contains(r#"<filter id="hc-outline""#));
+        assert!(contrast.contains(r#"<g filter="url(#hc-outline)">"#));
+        assert!(normal.contains(r#"r="12""#));
+        assert!(contrast.contains(&format!(r#"r="{}""#, 12.0 * HIGH_CONTRAST_SCALE)));
+    }
+
+    // --- counter-battery tests ---
+
+    fn weapon_with_range(min_range: f64, max_range: f64) -> Weapon {
+        Weapon {
+            faction: Faction::Colonial,
+            display_name: "Enemy Gun".to_string(),
+            min_range,
+            max_range,
+            acc_radius: [10.0, 20.0],
+            wind_drift: [5.0, 10.0],
+            flight_time: [0.0, 0.0],
+            ammo: Vec::new(),
+            deployment: Default::default(),
+            area_fire: false,
+        }
+    }
+
+    #[test]
+    fn test_threatened_guns_only_inside_ring() {
+        let w = weapon_with_range(100.0, 300.0);
+        let gun = (1000.0, 800.0);
+        let scale = MapScale::STANDARD;
+        let far = (1000.0 + scale.meters_to_px_distance(200.0), 800.0);
+        let too_close = (1000.0 + scale.meters_to_px_distance(50.0), 800.0);
+        assert_eq!(threatened_guns(&[gun], &[far], &w, scale), vec![true]);
+        assert_eq!(
+            threatened_guns(&[gun], &[too_close], &w, scale),
+            vec![false]
+        );
+        assert_eq!(threatened_guns(&[gun], &[], &w, scale), vec![false]);
+    }
+
+    #[test]
+    fn test_danger_zones_drawn_per_gun() {
+        let w = weapon_with_range(100.0, 300.0);
+        let mut svg = String::new();
+        build_danger_zones(
+            &mut svg,
+            &[(500.0, 500.0), (900.0, 900.0)],
+            &[],
+            &w,
+            1.0,
+            &MARKER_COLORS,
+            MapScale::STANDARD,
+        );
+        assert_eq!(svg.matches("danger-zone").count(), 2);
+        assert!(svg.contains(MARKER_COLORS.danger_fill));
+    }
+
+    // --- keypad grid tests ---
+
+    #[test]
+    fn test_keypad_grid_when_zoomed_in_or_always() {
+        let render = |zoom, always_keypad| {
+            build_svg_content(
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &MarkerLabels::default(),
+                &[],
+                zoom,
+                always_keypad,
+                GridWindow::ALL,
+                960.0,
+                None,
+                None,
+                None,
+                false,
+                &MARKER_COLORS,
+                MapScale::STANDARD,
+            )
+        };
+        let keypad = "rgba(255,255,255,0.08)";
+        assert!(!render(1.0, false).contains(keypad));
+        assert!(render(3.0, false).contains(keypad));
+        assert!(render(1.0, true).contains(keypad));
+    }
+
+    #[test]
+    fn test_grid_window_around_pads_and_clamps() {
+        let scale = MapScale::STANDARD;
+        let (cell_w, cell_h) = (scale.grid_col_px(1), scale.grid_row_px(1));
+        // Inside cell C3: one cell either side
+        let mid = (cell_w * 2.5, cell_h * 2.5);
+        let view = GridWindow::around(mid, mid, scale);
+        assert_eq!(view.cols, (1, 4));
+        assert_eq!(view.rows, (1, 4));
+        let all = GridWindow::around((0.0, 0.0), (scale.width_px, scale.height_px), scale);
+        assert_eq!(all, GridWindow::ALL);
+    }
+
+    #[test]
+    fn test_keypad_labels_only_in_view() {
+        let render = |view| {
+            build_svg_content(
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &[],
+                &MarkerLabels::default(),
+                &[],
+                4.0,
+                false,
+                view,
+                960.0,
+                None,
+                None,
+                None,
+                false,
+                &MARKER_COLORS,
+                MapScale::STANDARD,
+            )
+        };
+        let label = "rgba(255,255,255,0.2)";
+        let all = render(GridWindow::ALL);
+        assert_eq!(
+            all.matches(label).count(),
+            grid::GRID_COLS * grid::GRID_ROWS * 9
+        );
+        let scale = MapScale::STANDARD;
+        let mid = (scale.grid_col_px(5) + 1.0, scale.grid_row_px(5) + 1.0);
+        let culled = render(GridWindow::around(mid, mid, scale));
+        assert_eq!(culled.matches(label).count(), 3 * 3 * 9);
+        assert!(culled.len() * 5 < all.len());
+    }
+
+    // --- compass rose tests ---
+
+    #[test]
+    fn test_compass_rose_only_around_selected_gun() {
+        let render = |selected| {
+            build_svg_content(
+                &[(500.0, 500.0)],
+                &[(500.0, 400.0)],
+                &[],
+                &[],
+                &[None],
+                &[],
+                &[Some(0)],
+                &[],
+                &[],
+                &MarkerLabels::default(),
+                &[],
+                1.0,
+                false,
+                GridWindow::ALL,
+                960.0,
+                selected,
+                None,
+                None,
+                false,
+                &MARKER_COLORS,
+                MapScale::STANDARD,
+            )
+        };
+        let gun = render(Some(SelectedMarker {
+            kind: MarkerKind::Gun,
+            index: 0,
+        }));
+        assert!(gun.contains(r#"<g class="compass-rose""#));
+        assert_eq!(gun.matches("compass-tick").count(), 36);
+        for label in [">N<", ">E<", ">S<", ">W<", ">30<", ">330<"] {
+            assert!(gun.contains(label), "{label}");
+        }
+        assert!(!gun.contains(">90<"));
+        // North tick runs straight up from the top of the ring
+        assert!(gun.contains(r#"x1="500" y1="430""#));
+
+        let target = render(Some(SelectedMarker {
+            kind: MarkerKind::Target,
+            index: 0,
+        }));
+        assert!(!target.contains("compass-rose"));
+        assert!(!render(None).contains("compass-rose"));
+    }
+
+    // --- wind tests ---
+
+    #[test]
+    fn test_wind_landing_drifts_downwind() {
+        let w = weapon_with_range(0.0, 300.0);
+        let scale = MapScale::STANDARD;
+        let east = WindInput {
+            direction: 90.0,
+            strength: 5,
+        };
+        let (x, y) = wind_landing((500.0, 500.0), (500.0, 400.0), &w, &east, scale);
+        assert!(x > 500.0);
+        assert!((y - 400.0).abs() < 1e-9);
+        let drift_m = (x - 500.0) * scale.meters_per_px_x();
+        let dist_m = 100.0 * scale.meters_per_px_y();
+        assert!((drift_m - calc::wind_drift_at_range(&w, dist_m)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wind_overlay_needs_strength() {
+        let w = weapon_with_range(0.0, 300.0);
+        let render = |strength| {
+            build_svg_content(
+                &[(500.0, 500.0)],
+                &[(500.0, 400.0)],
+                &[],
+                &[],
+                &[Some(&w)],
+                &[],
+                &[Some(0)],
+                &[],
+                &[],
+                &MarkerLabels::default(),
+                &[],
+                1.0,
+                false,
+                GridWindow::ALL,
+                960.0,
+                None,
+                None,
+                Some(&WindInput {
+                    direction: 270.0,
+                    strength,
+                }),
+                false,
+                &MARKER_COLORS,
+                MapScale::STANDARD,
+            )
+        };
+        let windy = render(3);
+        assert!(windy.contains("<title>Wind 270°, strength 3</title>"));
+        assert!(windy.contains("rotate(270)"));
+        assert_eq!(windy.matches(r#"class="wind-pip" cx"#).count(), 5);
+        assert_eq!(windy.matches(r#"fill="white" stroke="white""#).count(), 3);
+        assert_eq!(windy.matches("wind-drift").count(), 1);
+
+        let calm = render(0);
+        assert!(!calm.contains("wind-indicator"));
+        assert!(!calm.contains("wind-drift"));
+    }
+
+    #[test]
+    fn test_hidden_rings_skip_their_gun() {
+        let w = weapon_with_range(100.0, 300.0);
+        let mut svg = String::new();
+        build_range_circles(
+            &mut svg,
+            &[(500.0, 500.0), (900.0, 900.0)],
+            &[Some(&w), Some(&w)],
+            &[true],
+            1.0,
+            &MARKER_COLORS,
+            MapScale::STANDARD,
+        );
+        assert_eq!(svg.matches("<circle").count(), 2);
+        assert!(svg.contains(r#"cx="900""#));
+    }
+
+    // --- palette tests ---
+
+    #[test]
+    fn test_palettes_are_distinct() {
+        let palettes = [Palette::Warden, Palette::Colonial, Palette::Colorblind];
+        let markers = |p: Palette| {
+            let c = p.colors();
+            [c.gun, c.target, c.spotter, c.enemy]
+        };
+        for (i, &a) in palettes.iter().enumerate() {
+            let colors = markers(a);
+            for (j, x) in colors.iter().enumerate() {
+                assert!(!colors[j + 1..].contains(x), "{a:?} repeats {x}");
+            }
+            for &b in &palettes[i + 1..] {
+                assert_ne!(colors, markers(b), "{a:?} and {b:?}");
+            }
+        }
+        assert_eq!(MARKER_COLORS.gun, Palette::default().colors().gun);
+    }
+
+    #[test]
+    fn test_overlay_draws_in_the_given_palette() {
+        let w = weapon_with_range(100.0, 300.0);
+        let mut svg = String::new();
+        build_range_circles(
+            &mut svg,
+            &[(500.0, 500.0)],
+            &[Some(&w)],
+            &[],
+            1.0,
+            &COLORBLIND_COLORS,
+            MapScale::STANDARD,
+        );
+        assert!(svg.contains(COLORBLIND_COLORS.gun));
+        assert!(svg.contains(COLORBLIND_COLORS.max_range_fill));
+        assert!(!svg.contains(WARDEN_COLORS.gun));
+    }
+
+    // --- marker_label tests ---
+
+    #[test]
+    fn test_marker_label_single() {
+        assert_eq!(marker_label("GUN", 0, 1), "GUN");
+    }
+
+    #[test]
+    fn test_marker_label_multiple() {
+        assert_eq!(marker_label("GUN", 0, 3), "GUN 1");
+        assert_eq!(marker_label("GUN", 2, 3), "GUN 3");
+    }
+
+    #[test]
+    fn test_marker_labels_set_and_remove() {
+        let mut labels = MarkerLabels::default();
+        labels.set(MarkerKind::Target, 2, "  Bunker base east ");
+        assert_eq!(labels.targets, vec!["", "", "Bunker base east"]);
+        assert_eq!(labels.get(MarkerKind::Target, 0), None);
+        assert_eq!(labels.get(MarkerKind::Target, 2), Some("Bunker base east"));
+
+        labels.remove(MarkerKind::Target, 0);
+        assert_eq!(labels.get(MarkerKind::Target, 1), Some("Bunker base east"));
+        labels.remove(MarkerKind::Target, 5);
+        assert_eq!(labels.targets.len(), 2);
+
+        // Blank names past the end don't grow the list; enemies can't be named
+        labels.set(MarkerKind::Gun, 3, " ");
+        labels.set(MarkerKind::Enemy, 0, "Their 120");
+        assert!(labels.guns.is_empty());
+        assert_eq!(labels.get(MarkerKind::Enemy, 0), None);
+
+        labels.set(MarkerKind::Spotter, 0, &"x".repeat(100));
+        assert_eq!(labels.spotters[0].len(), MAX_LABEL_LEN);
+    }
+
+    #[test]
+    fn test_named_markers_replace_generated_labels() {
+        let labels = MarkerLabels {
+            guns: vec![String::new(), "Alpha <gun>".to_string()],
+            ..Default::default()
+        };
+        let svg = build_svg_content(
+            &[(100.0, 100.0), (500.0, 500.0)],
+            &[],
+            &[],
+            &[],
+            &[None, None],
+            &[],
+            &[None, None],
+            &[],
+            &[],
+            &labels,
+            &[],
+            1.0,
+            false,
+            GridWindow::ALL,
+            960.0,
+            None,
+            None,
+            None,
+            false,
+            &MARKER_COLORS,
+            MapScale::STANDARD,
+        );
+        assert!(svg.contains(">GUN 1<"));
+        assert!(svg.contains(">Alpha &lt;gun&gt;<"));
+        assert!(!svg.contains("GUN 2"));
+    }
+
+    #[test]
+    fn test_target_status_marks_fired_and_destroyed_targets() {
+        let targets = vec![(100.0, 100.0), (500.0, 500.0), (900.0, 900.0)];
+        let svg = build_svg_content(
+            &[],
+            &targets,
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &MarkerLabels::default(),
+            &[TargetStatus::Destroyed, TargetStatus::FiredUpon],
+            4.0,
+            false,
+            GridWindow::ALL,
+            960.0,
+            None,
+            None,
+            None,
+            false,
+            &MARKER_COLORS,
+            MapScale::STANDARD,
+        );
+        assert!(svg.contains(r#"class="target-destroyed""#));
+        assert!(svg.contains("<title>TARGET 1 (destroyed)</title>"));
+        assert!(svg.contains(r#"class="target-fired""#));
+        assert!(svg.contains("stroke-dasharray"));
+        assert!(svg.contains("<title>TARGET 3</title>"));
+    }
+}
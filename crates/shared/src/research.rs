@@ -0,0 +1,180 @@
+//! Accuracy research: where rounds actually landed compared with where the
+//! firing solution said they would, pooled per weapon so the `acc_radius` and
+//! `wind_drift` figures in `weapons.json` can be checked against real shots.
+//!
+//! Offsets are measured from the target along the gun's line of fire, so they
+//! don't depend on where the observer stood.
+
+use serde::{Deserialize, Serialize};
+
+use crate::calc;
+use crate::models::{Position, Weapon};
+
+/// Largest impact offset accepted along either axis, in meters. Anything
+/// further out is a misread or a different shell.
+pub const MAX_IMPACT_OFFSET: f64 = 300.0;
+
+/// Wind strength the `wind_drift` figures are given for.
+const FULL_WIND_STRENGTH: f64 = 5.0;
+
+/// One round: the firing data it was fired with and where it landed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImpactObservation {
+    pub weapon_slug: String,
+    /// Azimuth the gun fired at, in degrees.
+    pub azimuth: f64,
+    /// Distance the gun fired at, in meters.
+    pub distance: f64,
+    /// Direction the wind pushed shells towards, if there was wind.
+    pub wind_direction: Option<f64>,
+    /// Wind strength, 0-5.
+    pub wind_strength: u8,
+    /// Wind drift the firing data already allowed for, in meters.
+    pub compensated_drift: f64,
+    /// Meters the round landed beyond the target (negative: short).
+    pub long: f64,
+    /// Meters the round landed right of the target (negative: left).
+    pub right: f64,
+}
+
+impl ImpactObservation {
+    /// Straight-line miss distance.
+    pub fn miss(&self) -> f64 {
+        self.long.hypot(self.right)
+    }
+
+    /// How far the round drifted with the wind, counting the drift the firing
+    /// data allowed for; `None` without wind.
+    pub fn wind_drift(&self) -> Option<f64> {
+        let direction = self.wind_direction.filter(|_| self.wind_strength > 0)?;
+        let origin = Position { x: 0.0, y: 0.0 };
+        let offset = calc::offset_by_bearing(
+            calc::offset_by_bearing(origin, self.long, self.azimuth),
+            self.right,
+            self.azimuth + 90.0,
+        );
+        let push = calc::offset_by_bearing(origin, 1.0, direction);
+        let downwind = offset.x * push.x + offset.y * push.y;
+        Some(self.compensated_drift + downwind)
+    }
+}
+
+/// Observations of one weapon pooled together, next to the figures the
+/// planner currently uses at the same distance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImpactSummary {
+    pub samples: usize,
+    pub mean_distance: f64,
+    /// Mean straight-line miss; compare with the accuracy radius.
+    pub mean_miss: f64,
+    /// Mean offset along the line of fire; a steady bias suggests a range error.
+    pub mean_long: f64,
+    pub mean_right: f64,
+    /// Accuracy radius `weapons.json` gives at `mean_distance`.
+    pub predicted_accuracy_radius: f64,
+    /// Observations made in wind.
+    pub wind_samples: usize,
+    /// Mean drift scaled to full-strength wind, comparable with
+    /// `predicted_wind_drift`; `None` without windy samples.
+    pub observed_wind_drift: Option<f64>,
+    /// Full-strength drift `weapons.json` gives at `mean_distance`.
+    pub predicted_wind_drift: f64,
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, n) = values.fold((0.0, 0usize), |(sum, n), v| (sum + v, n + 1));
+    (n > 0).then(|| sum / n as f64)
+}
+
+/// Pool `observations` of `weapon`; `None` when there are none.
+pub fn summarize(weapon: &Weapon, observations: &[ImpactObservation]) -> Option<ImpactSummary> {
+    let mean_distance = mean(observations.iter().map(|o| o.distance))?;
+    let windy: Vec<f64> = observations
+        .iter()
+        .filter_map(|o| {
+            let drift = o.wind_drift()?;
+            Some(drift * FULL_WIND_STRENGTH / o.wind_strength.min(5) as f64)
+        })
+        .collect();
+    Some(ImpactSummary {
+        samples: observations.len(),
+        mean_distance,
+        mean_miss: mean(observations.iter().map(ImpactObservation::miss)).unwrap_or(0.0),
+        mean_long: mean(observations.iter().map(|o| o.long)).unwrap_or(0.0),
+        mean_right: mean(observations.iter().map(|o| o.right)).unwrap_or(0.0),
+        predicted_accuracy_radius: calc::accuracy_radius(weapon, mean_distance),
+        wind_samples: windy.len(),
+        observed_wind_drift: mean(windy.into_iter()),
+        predicted_wind_drift: calc::wind_drift_at_range(weapon, mean_distance),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Faction;
+
+    fn weapon() -> Weapon {
+        Weapon {
+            faction: Faction::Warden,
+            display_name: "Test Mortar".to_string(),
+            min_range: 100.0,
+            max_range: 300.0,
+            acc_radius: [20.0, 40.0],
+            wind_drift: [10.0, 30.0],
+            flight_time: [0.0, 0.0],
+            ammo: vec![],
+            deployment: Default::default(),
+            area_fire: false,
+        }
+    }
+
+    fn observation(long: f64, right: f64) -> ImpactObservation {
+        ImpactObservation {
+            weapon_slug: "test-mortar".to_string(),
+            azimuth: 0.0,
+            distance: 200.0,
+            wind_direction: None,
+            wind_strength: 0,
+            compensated_drift: 0.0,
+            long,
+            right,
+        }
+    }
+
+    #[test]
+    fn test_wind_drift_projects_onto_the_wind() {
+        // Firing north with the wind pushing east: landing right is downwind
+        let mut obs = observation(5.0, 12.0);
+        assert_eq!(obs.wind_drift(), None);
+        obs.wind_direction = Some(90.0);
+        obs.wind_strength = 3;
+        assert!((obs.wind_drift().unwrap() - 12.0).abs() < 1e-9);
+        // Drift the firing data allowed for counts too
+        obs.compensated_drift = 6.0;
+        assert!((obs.wind_drift().unwrap() - 18.0).abs() < 1e-9);
+        // Landing upwind of the target means the allowance was too generous
+        obs.right = -4.0;
+        assert!((obs.wind_drift().unwrap() - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_summarize() {
+        let mut windy = observation(0.0, 6.0);
+        windy.wind_direction = Some(90.0);
+        windy.wind_strength = 2;
+        let observations = [observation(3.0, 4.0), observation(-6.0, -8.0), windy];
+        let summary = summarize(&weapon(), &observations).unwrap();
+        assert_eq!(summary.samples, 3);
+        assert!((summary.mean_distance - 200.0).abs() < 1e-9);
+        assert!((summary.mean_miss - 7.0).abs() < 1e-9);
+        assert!((summary.mean_long - -1.0).abs() < 1e-9);
+        assert!((summary.predicted_accuracy_radius - 30.0).abs() < 1e-9);
+        assert_eq!(summary.wind_samples, 1);
+        // 6 m at strength 2 is 15 m at full strength
+        assert!((summary.observed_wind_drift.unwrap() - 15.0).abs() < 1e-9);
+        assert!((summary.predicted_wind_drift - 20.0).abs() < 1e-9);
+
+        assert_eq!(summarize(&weapon(), &[]), None);
+    }
+}
@@ -0,0 +1,84 @@
+//! Short plan links that can be read out over voice comms, like
+//! `brave-falcon-42`.
+//!
+//! The server makes a slug for each new plan from random bytes; the frontend
+//! only needs to tell a slug from a plan ID.
+
+pub const ADJECTIVES: [&str; 64] = [
+    "amber", "bold", "brave", "brisk", "calm", "clever", "cold", "crimson", "dark", "deep",
+    "dusty", "eager", "early", "fast", "fierce", "firm", "frosty", "gold", "grand", "green",
+    "grey", "grim", "happy", "heavy", "high", "hidden", "iron", "jolly", "keen", "late", "lively",
+    "loud", "lucky", "mighty", "muddy", "noble", "odd", "pale", "proud", "quick", "quiet", "rapid",
+    "red", "rough", "royal", "rusty", "salty", "sharp", "silent", "silver", "slow", "sly", "smoky",
+    "solid", "steady", "steel", "stern", "stormy", "swift", "tall", "tough", "wild", "windy",
+    "young",
+];
+
+pub const NOUNS: [&str; 64] = [
+    "anchor", "anvil", "arrow", "badger", "bear", "beacon", "bison", "boar", "bridge", "bunker",
+    "canyon", "castle", "cobra", "comet", "coyote", "crane", "crow", "dragon", "eagle", "ember",
+    "falcon", "ferret", "fox", "gator", "hammer", "harbor", "hawk", "heron", "hill", "hornet",
+    "jackal", "lance", "lion", "lynx", "marsh", "moose", "otter", "owl", "panther", "pike",
+    "raven", "ridge", "river", "rook", "saber", "shark", "shield", "spear", "stag", "storm",
+    "thunder", "tiger", "tower", "trench", "viper", "walrus", "wolf", "wasp", "badland", "bastion",
+    "cliff", "delta", "forge", "mortar",
+];
+
+/// A slug from random bytes: an adjective, a noun and a number from 10 to 99.
+pub fn slug_from_bytes(bytes: [u8; 4]) -> String {
+    let adjective = ADJECTIVES[bytes[0] as usize % ADJECTIVES.len()];
+    let noun = NOUNS[bytes[1] as usize % NOUNS.len()];
+    let number = 10 + u16::from_le_bytes([bytes[2], bytes[3]]) % 90;
+    format!("{adjective}-{noun}-{number}")
+}
+
+/// Whether `text` is shaped like a slug rather than a plan ID.
+pub fn is_slug(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('-').collect();
+    match parts[..] {
+        [adjective, noun, number] => {
+            ADJECTIVES.contains(&adjective)
+                && NOUNS.contains(&noun)
+                && number.len() == 2
+                && number.bytes().all(|b| b.is_ascii_digit())
+                && !number.starts_with('0')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slug_from_bytes() {
+        assert_eq!(slug_from_bytes([2, 20, 32, 0]), "brave-falcon-42");
+        // Every byte value lands on a word and a two-digit number
+        for b in 0..=255u8 {
+            assert!(is_slug(&slug_from_bytes([b, b, b, b])));
+        }
+        assert!(is_slug(&slug_from_bytes([255, 255, 255, 255])));
+    }
+
+    #[test]
+    fn test_words_are_unique_and_plain() {
+        for words in [&ADJECTIVES[..], &NOUNS[..]] {
+            for (i, w) in words.iter().enumerate() {
+                assert!(w.bytes().all(|b| b.is_ascii_lowercase()), "{w}");
+                assert!(!words[i + 1..].contains(w), "{w} twice");
+            }
+        }
+    }
+
+    #[test]
+    fn test_is_slug() {
+        assert!(is_slug("brave-falcon-42"));
+        assert!(!is_slug("550e8400-e29b-41d4-a716-446655440000"));
+        assert!(!is_slug("brave-falcon"));
+        assert!(!is_slug("brave-falcon-7"));
+        assert!(!is_slug("brave-falcon-07"));
+        assert!(!is_slug("Brave-falcon-42"));
+        assert!(!is_slug("brave-nothing-42"));
+    }
+}
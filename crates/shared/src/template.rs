@@ -0,0 +1,164 @@
+//! Battery templates: reusable gun layouts ("3×120mm in a triangle, 50 m
+//! apart") that can be stamped onto a map in one go.
+//!
+//! Offsets are in meters from the battery's centre, with `y` growing south
+//! like map positions, so a template fits any map image.
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::Position;
+
+/// Most guns a template may hold.
+pub const MAX_TEMPLATE_GUNS: usize = 12;
+
+/// One gun of a template: its weapon and where it sits from the centre.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TemplateGun {
+    pub weapon_id: String,
+    /// Meters from the battery's centre.
+    pub offset: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatteryTemplate {
+    pub name: String,
+    pub guns: Vec<TemplateGun>,
+}
+
+/// Shape of a generated battery layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Formation {
+    /// Guns side by side, west to east.
+    Line,
+    /// Guns on the corners of a regular polygon (a triangle for three),
+    /// the first one to the north.
+    Ring,
+}
+
+impl BatteryTemplate {
+    /// A template of guns placed on the map (meters), centred on their average position.
+    pub fn from_guns(name: &str, guns: &[(String, Position)]) -> Self {
+        let n = guns.len().max(1) as f64;
+        let cx = guns.iter().map(|(_, p)| p.x).sum::<f64>() / n;
+        let cy = guns.iter().map(|(_, p)| p.y).sum::<f64>() / n;
+        BatteryTemplate {
+            name: name.to_string(),
+            guns: guns
+                .iter()
+                .map(|(weapon_id, p)| TemplateGun {
+                    weapon_id: weapon_id.clone(),
+                    offset: Position {
+                        x: p.x - cx,
+                        y: p.y - cy,
+                    },
+                })
+                .collect(),
+        }
+    }
+
+    /// `count` guns of one weapon in `formation`, neighbours `spacing` meters apart.
+    pub fn formation(
+        name: &str,
+        weapon_id: &str,
+        count: usize,
+        spacing: f64,
+        formation: Formation,
+    ) -> Self {
+        let offsets: Vec<Position> = match formation {
+            // Two guns make a line whatever the shape
+            Formation::Ring if count > 2 => {
+                let step = std::f64::consts::TAU / count as f64;
+                let radius = spacing / (2.0 * (step / 2.0).sin());
+                (0..count)
+                    .map(|i| {
+                        let angle = i as f64 * step;
+                        Position {
+                            x: radius * angle.sin(),
+                            y: -radius * angle.cos(),
+                        }
+                    })
+                    .collect()
+            }
+            _ => {
+                let half = (count.max(1) - 1) as f64 / 2.0;
+                (0..count)
+                    .map(|i| Position {
+                        x: (i as f64 - half) * spacing,
+                        y: 0.0,
+                    })
+                    .collect()
+            }
+        };
+        BatteryTemplate {
+            name: name.to_string(),
+            guns: offsets
+                .into_iter()
+                .map(|offset| TemplateGun {
+                    weapon_id: weapon_id.to_string(),
+                    offset,
+                })
+                .collect(),
+        }
+    }
+
+    /// Weapons and map positions (meters) of the guns with the centre at `center`.
+    pub fn stamp(&self, center: Position) -> Vec<(String, Position)> {
+        self.guns
+            .iter()
+            .map(|g| {
+                (
+                    g.weapon_id.clone(),
+                    Position {
+                        x: center.x + g.offset.x,
+                        y: center.y + g.offset.y,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(a: Position, b: Position) -> f64 {
+        (a.x - b.x).hypot(a.y - b.y)
+    }
+
+    #[test]
+    fn test_ring_formation_spacing() {
+        let t = BatteryTemplate::formation("Triangle", "120mm", 3, 50.0, Formation::Ring);
+        assert_eq!(t.guns.len(), 3);
+        assert!(t.guns.iter().all(|g| g.weapon_id == "120mm"));
+        let p: Vec<Position> = t.guns.iter().map(|g| g.offset).collect();
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            assert!((distance(p[a], p[b]) - 50.0).abs() < 1e-9);
+        }
+        // First gun due north of the centre
+        assert!(p[0].x.abs() < 1e-9 && p[0].y < 0.0);
+    }
+
+    #[test]
+    fn test_line_formation_is_centred() {
+        let t = BatteryTemplate::formation("Line", "mortar", 4, 20.0, Formation::Line);
+        let xs: Vec<f64> = t.guns.iter().map(|g| g.offset.x).collect();
+        assert_eq!(xs, vec![-30.0, -10.0, 10.0, 30.0]);
+        // Two guns in a ring are just a line
+        let pair = BatteryTemplate::formation("Pair", "mortar", 2, 20.0, Formation::Ring);
+        assert_eq!(pair.guns[0].offset, Position { x: -10.0, y: 0.0 });
+    }
+
+    #[test]
+    fn test_from_guns_and_stamp_round_trip() {
+        let guns = vec![
+            ("a".to_string(), Position { x: 100.0, y: 200.0 }),
+            ("b".to_string(), Position { x: 140.0, y: 260.0 }),
+        ];
+        let t = BatteryTemplate::from_guns("Pair", &guns);
+        assert_eq!(t.guns[0].offset, Position { x: -20.0, y: -30.0 });
+        assert_eq!(t.stamp(Position { x: 120.0, y: 230.0 }), guns);
+        let moved = t.stamp(Position { x: 500.0, y: 500.0 });
+        assert_eq!(moved[1], ("b".to_string(), Position { x: 520.0, y: 530.0 }));
+    }
+}
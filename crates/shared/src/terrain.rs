@@ -0,0 +1,290 @@
+//! Where guns and spotters can stand: the hex outline, and optional per-map
+//! masks of water and impassable ground.
+//!
+//! The same checks run in the planner as markers are placed and on the server
+//! for saved plans. They only ever warn: masks are coarse, and a gun on a
+//! shoreline cell may well be on dry land.
+
+use crate::grid::{MAP_HEIGHT_M, MAP_WIDTH_M};
+use crate::models::Position;
+use crate::overlay::MarkerKind;
+
+/// What covers one cell of a [`TerrainMask`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Terrain {
+    Land,
+    Water,
+    Impassable,
+}
+
+impl Terrain {
+    fn from_char(c: char) -> Option<Terrain> {
+        match c {
+            '.' => Some(Terrain::Land),
+            '~' => Some(Terrain::Water),
+            '#' => Some(Terrain::Impassable),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Terrain::Land => '.',
+            Terrain::Water => '~',
+            Terrain::Impassable => '#',
+        }
+    }
+}
+
+/// Coarse bitmap of a map's terrain, kept in `assets/masks/<fileName>.txt`.
+///
+/// One line per row of cells from north to south and one character per cell
+/// from west to east: `.` land, `~` water, `#` impassable. The cells split
+/// the whole region evenly, so any resolution works.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TerrainMask {
+    cols: usize,
+    cells: Vec<Terrain>,
+}
+
+impl TerrainMask {
+    /// Read a mask from its text form; blank lines are skipped.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        Self::from_rows(text.lines().map(str::trim).filter(|l| !l.is_empty()))
+    }
+
+    /// Build a mask from its rows, north first.
+    pub fn from_rows<'a>(rows: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut cols = 0;
+        let mut cells = Vec::new();
+        for (i, row) in rows.into_iter().enumerate() {
+            let before = cells.len();
+            for c in row.chars() {
+                let terrain = Terrain::from_char(c).ok_or_else(|| {
+                    format!("row {}: unknown terrain {c:?} (use '.', '~' or '#')", i + 1)
+                })?;
+                cells.push(terrain);
+            }
+            let width = cells.len() - before;
+            if i == 0 {
+                cols = width;
+            } else if width != cols {
+                return Err(format!(
+                    "row {}: {width} cells, but the first row has {cols}",
+                    i + 1
+                ));
+            }
+        }
+        if cells.is_empty() {
+            return Err("mask has no cells".to_string());
+        }
+        Ok(TerrainMask { cols, cells })
+    }
+
+    /// The mask's rows in their text form, north first.
+    pub fn rows(&self) -> Vec<String> {
+        self.cells
+            .chunks(self.cols)
+            .map(|row| row.iter().map(|t| t.to_char()).collect())
+            .collect()
+    }
+
+    /// Terrain of the cell holding `pos` (meters); positions off the region
+    /// take the nearest edge cell.
+    pub fn at(&self, pos: Position) -> Terrain {
+        let rows = self.cells.len() / self.cols;
+        let cell = |v: f64, size: f64, count: usize| {
+            ((v / size * count as f64).floor().max(0.0) as usize).min(count - 1)
+        };
+        let col = cell(pos.x, MAP_WIDTH_M, self.cols);
+        let row = cell(pos.y, MAP_HEIGHT_M, rows);
+        self.cells[row * self.cols + col]
+    }
+}
+
+/// Whether `pos` (meters) lies inside the region's hexagon. The hex is
+/// flat-topped and touches all four sides of the map image.
+pub fn in_hex(pos: Position) -> bool {
+    if !(0.0..=MAP_HEIGHT_M).contains(&pos.y) {
+        return false;
+    }
+    let half_height = MAP_HEIGHT_M / 2.0;
+    let inset = MAP_WIDTH_M / 4.0 * (pos.y - half_height).abs() / half_height;
+    pos.x >= inset && pos.x <= MAP_WIDTH_M - inset
+}
+
+/// Why a marker can't really stand where it was put.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerrainIssue {
+    /// Outside the hex, in the blank corners of the map image.
+    OffMap,
+    Water,
+    Impassable,
+}
+
+impl std::fmt::Display for TerrainIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TerrainIssue::OffMap => write!(f, "outside the hex"),
+            TerrainIssue::Water => write!(f, "in water"),
+            TerrainIssue::Impassable => write!(f, "on impassable terrain"),
+        }
+    }
+}
+
+/// What's wrong with standing at `pos`, if anything. Without a mask only the
+/// hex outline is checked.
+pub fn terrain_issue(pos: Position, mask: Option<&TerrainMask>) -> Option<TerrainIssue> {
+    if !in_hex(pos) {
+        return Some(TerrainIssue::OffMap);
+    }
+    match mask?.at(pos) {
+        Terrain::Land => None,
+        Terrain::Water => Some(TerrainIssue::Water),
+        Terrain::Impassable => Some(TerrainIssue::Impassable),
+    }
+}
+
+/// A gun or spotter standing somewhere it can't be.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TerrainWarning {
+    pub marker: MarkerKind,
+    /// Index into the plan's guns or spotters.
+    pub index: usize,
+    pub issue: TerrainIssue,
+}
+
+impl TerrainWarning {
+    /// "Gun 2 is in water", given the marker's `label`.
+    pub fn message(&self, label: &str) -> String {
+        format!("{label} is {}", self.issue)
+    }
+}
+
+/// Warnings for every gun and spotter on bad ground, guns first. Targets may
+/// be anywhere: ships and bridges are fair game.
+pub fn placement_warnings(
+    guns: &[Position],
+    spotters: &[Position],
+    mask: Option<&TerrainMask>,
+) -> Vec<TerrainWarning> {
+    let check = |marker: MarkerKind, positions: &[Position]| {
+        positions
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &pos)| {
+                let issue = terrain_issue(pos, mask)?;
+                Some(TerrainWarning {
+                    marker,
+                    index,
+                    issue,
+                })
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut warnings = check(MarkerKind::Gun, guns);
+    warnings.extend(check(MarkerKind::Spotter, spotters));
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CENTER: Position = Position {
+        x: MAP_WIDTH_M / 2.0,
+        y: MAP_HEIGHT_M / 2.0,
+    };
+
+    #[test]
+    fn test_in_hex() {
+        assert!(in_hex(CENTER));
+        // West and east points of the hex sit on the image edge
+        assert!(in_hex(Position {
+            x: 0.0,
+            y: MAP_HEIGHT_M / 2.0
+        }));
+        assert!(in_hex(Position {
+            x: MAP_WIDTH_M / 2.0,
+            y: 0.0
+        }));
+        // The image's corners are off the map
+        assert!(!in_hex(Position { x: 10.0, y: 10.0 }));
+        assert!(!in_hex(Position {
+            x: MAP_WIDTH_M - 10.0,
+            y: MAP_HEIGHT_M - 10.0
+        }));
+        assert!(!in_hex(Position {
+            x: CENTER.x,
+            y: -1.0
+        }));
+    }
+
+    #[test]
+    fn test_mask_parse_and_lookup() {
+        let mask = TerrainMask::parse("\n..~~\n.#~~\n").unwrap();
+        assert_eq!(mask.rows(), vec!["..~~", ".#~~"]);
+        assert_eq!(mask.at(Position { x: 10.0, y: 10.0 }), Terrain::Land);
+        assert_eq!(
+            mask.at(Position {
+                x: MAP_WIDTH_M - 1.0,
+                y: 10.0
+            }),
+            Terrain::Water
+        );
+        // Second column, southern row
+        let impassable = Position {
+            x: MAP_WIDTH_M * 0.3,
+            y: MAP_HEIGHT_M * 0.75,
+        };
+        assert_eq!(mask.at(impassable), Terrain::Impassable);
+        // Off the region clamps to the edge
+        assert_eq!(
+            mask.at(Position {
+                x: MAP_WIDTH_M + 50.0,
+                y: -50.0
+            }),
+            Terrain::Water
+        );
+
+        assert!(TerrainMask::parse("..\n...")
+            .unwrap_err()
+            .contains("row 2: 3 cells"));
+        assert!(TerrainMask::parse("..x").unwrap_err().contains("'x'"));
+        assert!(TerrainMask::parse("").is_err());
+    }
+
+    #[test]
+    fn test_placement_warnings() {
+        let mask = TerrainMask::parse("..~~\n..~~").unwrap();
+        let water = Position {
+            x: MAP_WIDTH_M * 0.6,
+            y: CENTER.y,
+        };
+        let land = Position {
+            x: MAP_WIDTH_M * 0.4,
+            y: CENTER.y,
+        };
+        let corner = Position { x: 5.0, y: 5.0 };
+
+        let warnings = placement_warnings(&[land, water], &[corner], Some(&mask));
+        assert_eq!(
+            warnings,
+            vec![
+                TerrainWarning {
+                    marker: MarkerKind::Gun,
+                    index: 1,
+                    issue: TerrainIssue::Water,
+                },
+                TerrainWarning {
+                    marker: MarkerKind::Spotter,
+                    index: 0,
+                    issue: TerrainIssue::OffMap,
+                },
+            ]
+        );
+        // Without a mask only the hex outline counts
+        assert_eq!(placement_warnings(&[land, water], &[], None), vec![]);
+        assert_eq!(warnings[0].message("Gun 2"), "Gun 2 is in water");
+    }
+}
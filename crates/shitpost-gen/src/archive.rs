@@ -0,0 +1,233 @@
+//! Local archive of each war's placement stats, so a report can compare the
+//! war it covers with the ones before it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Stats;
+
+/// Archive file used when `--archive` isn't given.
+pub const DEFAULT_ARCHIVE_PATH: &str = "war-archive.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WeaponCount {
+    pub display_name: String,
+    pub faction: String,
+    pub count: u64,
+}
+
+/// One war's stats as fetched when it was archived.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WarSnapshot {
+    pub war: u64,
+    /// Unix seconds.
+    pub archived_at: u64,
+    /// War API winner (`WARDENS`, `COLONIALS` or `NONE`), when known.
+    pub winner: Option<String>,
+    pub warden_guns: u64,
+    pub colonial_guns: u64,
+    pub total_guns: u64,
+    pub targets: u64,
+    pub spotters: u64,
+    pub weapons: Vec<WeaponCount>,
+}
+
+impl WarSnapshot {
+    pub fn new(war: u64, winner: Option<String>, stats: &Stats) -> Self {
+        let archived_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        WarSnapshot {
+            war,
+            archived_at,
+            winner,
+            warden_guns: stats.gun_placement_totals.warden,
+            colonial_guns: stats.gun_placement_totals.colonial,
+            total_guns: stats.gun_placement_totals.total,
+            targets: stats.marker_placements.targets,
+            spotters: stats.marker_placements.spotters,
+            weapons: stats
+                .gun_placements
+                .iter()
+                .map(|wp| WeaponCount {
+                    display_name: wp.display_name.clone(),
+                    faction: wp.faction.clone(),
+                    count: wp.count,
+                })
+                .collect(),
+        }
+    }
+
+    fn weapon_count(&self, display_name: &str) -> u64 {
+        self.weapons
+            .iter()
+            .find(|w| w.display_name == display_name)
+            .map_or(0, |w| w.count)
+    }
+}
+
+/// Every archived war, oldest first.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Archive {
+    pub wars: Vec<WarSnapshot>,
+}
+
+impl Archive {
+    /// The archive at `path`; empty when the file doesn't exist yet.
+    pub fn load(path: &str) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json)
+                .map_err(|e| format!("Failed to parse archive {path}: {e}")),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Archive::default()),
+            Err(e) => Err(format!("Failed to read archive {path}: {e}")),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).unwrap();
+        std::fs::write(path, json + "\n")
+            .map_err(|e| format!("Failed to write archive {path}: {e}"))
+    }
+
+    /// Add `snapshot`, replacing any earlier snapshot of the same war.
+    pub fn record(&mut self, snapshot: WarSnapshot) {
+        self.wars.retain(|w| w.war != snapshot.war);
+        self.wars.push(snapshot);
+        self.wars.sort_by_key(|w| w.war);
+    }
+
+    /// The snapshot of `war` itself, from the last time it was recorded.
+    pub fn snapshot(&self, war: u64) -> Option<&WarSnapshot> {
+        self.wars.iter().find(|w| w.war == war)
+    }
+
+    /// The latest archived war before `war`.
+    pub fn previous(&self, war: u64) -> Option<&WarSnapshot> {
+        self.wars.iter().rev().find(|w| w.war < war)
+    }
+}
+
+/// "30% more than War 116 (650 vs 500)".
+fn change(now: u64, before: u64, war: u64) -> String {
+    if now == before {
+        return format!("the same as War {war} ({now})");
+    }
+    if before == 0 {
+        return format!("{now}, up from none in War {war}");
+    }
+    let percent = (now as f64 - before as f64) / before as f64 * 100.0;
+    let direction = if percent > 0.0 { "more" } else { "fewer" };
+    format!(
+        "{:.0}% {direction} than War {war} ({now} vs {before})",
+        percent.abs()
+    )
+}
+
+/// The current war's stats against the latest archived war before it.
+pub fn format_comparison(current: &WarSnapshot, previous: &WarSnapshot) -> String {
+    let war = previous.war;
+    let mut out = String::new();
+    out.push_str(&format!("=== Compared With War {war} ===\n"));
+    if let Some(winner) = &previous.winner {
+        out.push_str(&format!("  War {war} Winner: {winner}\n"));
+    }
+    let lines = [
+        (
+            "Warden gun placements",
+            current.warden_guns,
+            previous.warden_guns,
+        ),
+        (
+            "Colonial gun placements",
+            current.colonial_guns,
+            previous.colonial_guns,
+        ),
+        (
+            "Total gun placements",
+            current.total_guns,
+            previous.total_guns,
+        ),
+        ("Targets placed", current.targets, previous.targets),
+        ("Spotters placed", current.spotters, previous.spotters),
+    ];
+    for (label, now, before) in lines {
+        out.push_str(&format!("  {label}: {}\n", change(now, before, war)));
+    }
+    out.push_str(&biggest_moves(current, previous));
+    out.push('\n');
+    out
+}
+
+/// "+150 since the last snapshot (650, was 500)".
+fn progress(now: u64, before: u64) -> String {
+    let delta = now as i64 - before as i64;
+    if delta == 0 {
+        return format!("no change since the last snapshot ({now})");
+    }
+    format!("{delta:+} since the last snapshot ({now}, was {before})")
+}
+
+/// How the current war moved on since an earlier snapshot of the same war.
+pub fn format_progress(current: &WarSnapshot, previous: &WarSnapshot) -> String {
+    let days = current.archived_at.saturating_sub(previous.archived_at) / 86400;
+    let mut out = String::new();
+    out.push_str("=== Progress Since Last Snapshot ===\n");
+    out.push_str(&format!("  Last Snapshot: {days} days ago\n"));
+    let lines = [
+        (
+            "Warden gun placements",
+            current.warden_guns,
+            previous.warden_guns,
+        ),
+        (
+            "Colonial gun placements",
+            current.colonial_guns,
+            previous.colonial_guns,
+        ),
+        (
+            "Total gun placements",
+            current.total_guns,
+            previous.total_guns,
+        ),
+        ("Targets placed", current.targets, previous.targets),
+        ("Spotters placed", current.spotters, previous.spotters),
+    ];
+    for (label, now, before) in lines {
+        out.push_str(&format!("  {label}: {}\n", progress(now, before)));
+    }
+    out.push_str(&biggest_moves(current, previous));
+    out.push('\n');
+    out
+}
+
+/// The weapons whose use moved the most either way between two snapshots.
+fn biggest_moves(current: &WarSnapshot, previous: &WarSnapshot) -> String {
+    let mut out = String::new();
+    let mut deltas: Vec<(&str, i64)> = current
+        .weapons
+        .iter()
+        .map(|w| (w.display_name.as_str(), w.count as i64))
+        .chain(
+            previous
+                .weapons
+                .iter()
+                .filter(|w| {
+                    !current
+                        .weapons
+                        .iter()
+                        .any(|c| c.display_name == w.display_name)
+                })
+                .map(|w| (w.display_name.as_str(), 0)),
+        )
+        .map(|(name, now)| (name, now - previous.weapon_count(name) as i64))
+        .filter(|&(_, delta)| delta != 0)
+        .collect();
+    deltas.sort_by_key(|&(_, delta)| delta);
+    if let Some(&(name, delta)) = deltas.last().filter(|d| d.1 > 0) {
+        out.push_str(&format!("  Biggest rise: {name} (+{delta})\n"));
+    }
+    if let Some(&(name, delta)) = deltas.first().filter(|d| d.1 < 0) {
+        out.push_str(&format!("  Biggest drop: {name} ({delta})\n"));
+    }
+    out
+}
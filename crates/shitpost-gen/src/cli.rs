@@ -0,0 +1,313 @@
+//! Command-line interface.
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::archive::DEFAULT_ARCHIVE_PATH;
+use crate::llm::Provider;
+use crate::{Mode, Persona};
+
+const DEFAULT_GRAPHQL_URL: &str = "https://arty.dp42.dev/graphql";
+const DEFAULT_SUBREDDIT: &str = "foxholegame";
+
+/// Generate Foxhole end-of-war reports from artillery planner stats.
+#[derive(Parser, Debug)]
+#[command(name = "shitpost-gen", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the report prompt for piping into `claude -p`, or with --call
+    /// generate the report and print it.
+    Report {
+        #[command(flatten)]
+        report: ReportArgs,
+        /// Send the prompt to an API and print only the finished post.
+        #[arg(long)]
+        call: bool,
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+    /// Print the war data and placement stats a report would be written from.
+    Stats {
+        #[command(flatten)]
+        source: SourceArgs,
+        #[command(flatten)]
+        war: WarArgs,
+    },
+    /// Generate the report through an API and publish it.
+    Post {
+        /// Where to publish the report.
+        #[arg(long, value_enum)]
+        to: PostTarget,
+        /// Discord webhook to post to.
+        #[arg(long, required_if_eq("to", "discord"))]
+        webhook: Option<String>,
+        /// Subreddit to submit to.
+        #[arg(long, default_value = DEFAULT_SUBREDDIT)]
+        subreddit: String,
+        /// Print the requests that would be sent instead of sending them.
+        #[arg(long)]
+        dry_run: bool,
+        #[command(flatten)]
+        report: ReportArgs,
+        #[command(flatten)]
+        llm: LlmArgs,
+    },
+    /// Store a war's placement stats in the local war archive.
+    Archive {
+        #[command(flatten)]
+        source: SourceArgs,
+        /// War to archive (default: the current war).
+        #[arg(long)]
+        war: Option<u64>,
+        #[arg(long, default_value = DEFAULT_ARCHIVE_PATH)]
+        archive: String,
+    },
+}
+
+/// Where stats and war state come from.
+#[derive(Args, Debug)]
+pub struct SourceArgs {
+    /// Artillery planner GraphQL endpoint.
+    #[arg(long, default_value = DEFAULT_GRAPHQL_URL)]
+    pub url: String,
+    /// War API shard to read the current war from.
+    #[arg(long, value_enum, default_value_t = Shard::Live3)]
+    pub shard: Shard,
+}
+
+/// Which war's placements to use, and what to compare them with.
+#[derive(Args, Debug)]
+pub struct WarArgs {
+    /// Use placements across all wars instead of only the current one.
+    #[arg(long)]
+    pub lifetime: bool,
+    /// War archive to compare a single war against.
+    #[arg(long, default_value = DEFAULT_ARCHIVE_PATH)]
+    pub archive: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Your Foxhole in-game name.
+    #[arg(long)]
+    pub name: String,
+    /// Your clan tag.
+    #[arg(long)]
+    pub clan: String,
+    /// Side the report gloats for.
+    #[arg(long, value_enum, default_value_t = Persona::Warden)]
+    pub faction: Persona,
+    /// Kind of post to write.
+    #[arg(long, value_enum, default_value_t = Mode::EndOfWar)]
+    pub mode: Mode,
+    #[command(flatten)]
+    pub source: SourceArgs,
+    #[command(flatten)]
+    pub war: WarArgs,
+}
+
+/// API settings for generating the post; the key is read from the provider's
+/// environment variable.
+#[derive(Args, Debug)]
+pub struct LlmArgs {
+    /// API format to speak.
+    #[arg(long, value_enum, default_value_t = Provider::Anthropic)]
+    pub provider: Provider,
+    /// Model to generate with (default: the provider's default model).
+    #[arg(long)]
+    pub model: Option<String>,
+    /// API base URL, without the `/v1/...` path (default: the provider's public API).
+    #[arg(long)]
+    pub api_url: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Shard {
+    #[value(name = "live-1")]
+    Live1,
+    #[value(name = "live-2")]
+    Live2,
+    #[value(name = "live-3")]
+    Live3,
+}
+
+impl Shard {
+    pub fn war_api_base(self) -> &'static str {
+        match self {
+            Shard::Live1 => "https://war-service-live.foxholeservices.com/api",
+            Shard::Live2 => "https://war-service-live-2.foxholeservices.com/api",
+            Shard::Live3 => "https://war-service-live-3.foxholeservices.com/api",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum PostTarget {
+    Discord,
+    Reddit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("shitpost-gen").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_report_defaults() {
+        let cli = parse(&["report", "--name", "Sgt", "--clan", "ABC"]).unwrap();
+        let Command::Report { report, call, llm } = cli.command else {
+            panic!("expected the report command");
+        };
+        assert_eq!(report.name, "Sgt");
+        assert_eq!(report.clan, "ABC");
+        assert_eq!(report.faction, Persona::Warden);
+        assert_eq!(report.mode, Mode::EndOfWar);
+        assert_eq!(report.source.url, DEFAULT_GRAPHQL_URL);
+        assert_eq!(report.source.shard, Shard::Live3);
+        assert!(!report.war.lifetime);
+        assert_eq!(report.war.archive, DEFAULT_ARCHIVE_PATH);
+        assert!(!call);
+        assert_eq!(llm.provider, Provider::Anthropic);
+        assert_eq!(llm.model, None);
+    }
+
+    #[test]
+    fn test_report_options() {
+        let cli = parse(&[
+            "report",
+            "--faction",
+            "colonial",
+            "--clan",
+            "ABC",
+            "--name",
+            "Sgt",
+            "--shard",
+            "live-2",
+            "--lifetime",
+            "--mode",
+            "sitrep",
+            "--call",
+            "--provider",
+            "openai",
+            "--model",
+            "local",
+        ])
+        .unwrap();
+        let Command::Report { report, call, llm } = cli.command else {
+            panic!("expected the report command");
+        };
+        assert_eq!(report.faction, Persona::Colonial);
+        assert_eq!(report.source.shard, Shard::Live2);
+        assert!(report.war.lifetime);
+        assert_eq!(report.mode, Mode::Sitrep);
+        assert!(call);
+        assert_eq!(llm.provider, Provider::OpenAi);
+        assert_eq!(llm.model.as_deref(), Some("local"));
+    }
+
+    #[test]
+    fn test_bad_arguments_are_rejected() {
+        use clap::error::ErrorKind;
+        let kind = |args: &[&str]| parse(args).unwrap_err().kind();
+        // Missing required flags
+        assert_eq!(
+            kind(&["report", "--name", "Sgt"]),
+            ErrorKind::MissingRequiredArgument
+        );
+        // Typos are errors rather than silently ignored
+        assert_eq!(
+            kind(&["report", "--name", "Sgt", "--clan", "ABC", "--lifetme"]),
+            ErrorKind::UnknownArgument
+        );
+        assert_eq!(
+            kind(&[
+                "report",
+                "--name",
+                "Sgt",
+                "--clan",
+                "ABC",
+                "--faction",
+                "orc"
+            ]),
+            ErrorKind::InvalidValue
+        );
+        assert_eq!(
+            kind(&["report", "--name", "Sgt", "--clan", "ABC", "--shard", "live-9"]),
+            ErrorKind::InvalidValue
+        );
+        // A flag without its value
+        assert_eq!(
+            kind(&["report", "--name", "--clan", "ABC"]),
+            ErrorKind::InvalidValue
+        );
+        assert_eq!(kind(&["publish"]), ErrorKind::InvalidSubcommand);
+        assert_eq!(
+            kind(&["archive", "--war", "latest"]),
+            ErrorKind::ValueValidation
+        );
+    }
+
+    #[test]
+    fn test_post_needs_a_webhook_for_discord() {
+        let base = ["post", "--name", "Sgt", "--clan", "ABC", "--to"];
+        let discord: Vec<&str> = base.iter().copied().chain(["discord"]).collect();
+        assert_eq!(
+            parse(&discord).unwrap_err().kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+
+        let with_hook: Vec<&str> = discord
+            .iter()
+            .copied()
+            .chain(["--webhook", "https://example.com/hook", "--dry-run"])
+            .collect();
+        let Command::Post {
+            to,
+            webhook,
+            dry_run,
+            ..
+        } = parse(&with_hook).unwrap().command
+        else {
+            panic!("expected the post command");
+        };
+        assert_eq!(to, PostTarget::Discord);
+        assert_eq!(webhook.as_deref(), Some("https://example.com/hook"));
+        assert!(dry_run);
+
+        let reddit: Vec<&str> = base.iter().copied().chain(["reddit"]).collect();
+        let Command::Post { to, subreddit, .. } = parse(&reddit).unwrap().command else {
+            panic!("expected the post command");
+        };
+        assert_eq!(to, PostTarget::Reddit);
+        assert_eq!(subreddit, DEFAULT_SUBREDDIT);
+    }
+
+    #[test]
+    fn test_archive_and_stats() {
+        let cli = parse(&["archive", "--war", "116", "--archive", "wars.json"]).unwrap();
+        let Command::Archive { war, archive, .. } = cli.command else {
+            panic!("expected the archive command");
+        };
+        assert_eq!(war, Some(116));
+        assert_eq!(archive, "wars.json");
+
+        let cli = parse(&["stats", "--lifetime"]).unwrap();
+        let Command::Stats { war, .. } = cli.command else {
+            panic!("expected the stats command");
+        };
+        assert!(war.lifetime);
+    }
+}
@@ -0,0 +1,175 @@
+//! Generating the post: send the prompt to a chat completion API ourselves
+//! instead of piping it into a locally installed CLI.
+
+use std::time::Duration;
+
+use clap::ValueEnum;
+use serde_json::json;
+
+/// Attempts per request before giving up on transient failures.
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Longest a single attempt may take; posts take a while to write.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Upper bound on the post's length, in tokens.
+const MAX_TOKENS: u32 = 1024;
+
+/// Which wire format the endpoint speaks.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Provider {
+    /// Anthropic Messages API (`/v1/messages`).
+    Anthropic,
+    /// OpenAI Chat Completions API (`/v1/chat/completions`), also spoken by
+    /// most self-hosted and proxy endpoints.
+    #[value(name = "openai")]
+    OpenAi,
+}
+
+impl Provider {
+    /// Environment variable the API key is read from.
+    pub fn key_var(self) -> &'static str {
+        match self {
+            Provider::Anthropic => "ANTHROPIC_API_KEY",
+            Provider::OpenAi => "OPENAI_API_KEY",
+        }
+    }
+
+    pub fn default_base_url(self) -> &'static str {
+        match self {
+            Provider::Anthropic => "https://api.anthropic.com",
+            Provider::OpenAi => "https://api.openai.com",
+        }
+    }
+
+    pub fn default_model(self) -> &'static str {
+        match self {
+            Provider::Anthropic => "claude-sonnet-4-5",
+            Provider::OpenAi => "gpt-4o",
+        }
+    }
+}
+
+/// Where and how to send the prompt.
+pub struct Endpoint {
+    pub provider: Provider,
+    /// Base URL without the `/v1/...` path.
+    pub base_url: String,
+    pub api_key: String,
+    pub model: String,
+}
+
+impl Endpoint {
+    fn request(
+        &self,
+        client: &reqwest::blocking::Client,
+        system: &str,
+        user: &str,
+    ) -> reqwest::blocking::RequestBuilder {
+        let base = self.base_url.trim_end_matches('/');
+        match self.provider {
+            Provider::Anthropic => client
+                .post(format!("{base}/v1/messages"))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
+                .json(&json!({
+                    "model": self.model,
+                    "max_tokens": MAX_TOKENS,
+                    "system": system,
+                    "messages": [{ "role": "user", "content": user }],
+                })),
+            Provider::OpenAi => client
+                .post(format!("{base}/v1/chat/completions"))
+                .bearer_auth(&self.api_key)
+                .json(&json!({
+                    "model": self.model,
+                    "max_tokens": MAX_TOKENS,
+                    "messages": [
+                        { "role": "system", "content": system },
+                        { "role": "user", "content": user },
+                    ],
+                })),
+        }
+    }
+
+    /// The generated text in a successful response body.
+    fn extract_text(&self, body: &serde_json::Value) -> Option<String> {
+        let text = match self.provider {
+            Provider::Anthropic => body["content"]
+                .as_array()?
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect::<String>(),
+            Provider::OpenAi => body["choices"][0]["message"]["content"]
+                .as_str()?
+                .to_string(),
+        };
+        let text = text.trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+}
+
+/// Rate limits, overload and server errors are worth another try.
+fn is_transient(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+/// Wait before attempt `attempt + 1`: the server's `Retry-After` when it sent
+/// one, otherwise 2, 4, 8... seconds.
+fn backoff(attempt: u32, retry_after: Option<u64>) -> Duration {
+    Duration::from_secs(retry_after.unwrap_or(2u64.pow(attempt)).min(60))
+}
+
+/// Send `system` and `user` to the endpoint and return the finished post,
+/// retrying transient failures.
+pub fn generate(endpoint: &Endpoint, system: &str, user: &str) -> Result<String, String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))?;
+
+    let mut attempt = 1;
+    loop {
+        eprintln!(
+            "Generating post with {} (attempt {attempt}/{MAX_ATTEMPTS})...",
+            endpoint.model
+        );
+        let (error, retry_after) = match endpoint.request(&client, system, user).send() {
+            Ok(resp) if resp.status().is_success() => {
+                let body: serde_json::Value = resp
+                    .json()
+                    .map_err(|e| format!("Failed to parse API response: {e}"))?;
+                return endpoint
+                    .extract_text(&body)
+                    .ok_or_else(|| "API response contained no text".to_string());
+            }
+            Ok(resp) => {
+                let status = resp.status();
+                let retry_after = resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.parse().ok());
+                let body = resp.text().unwrap_or_default();
+                let error = format!("API returned {status}: {}", body.trim());
+                if !is_transient(status) {
+                    return Err(error);
+                }
+                (error, retry_after)
+            }
+            Err(e) if e.is_timeout() || e.is_connect() || e.is_request() => {
+                (format!("Request failed: {e}"), None)
+            }
+            Err(e) => return Err(format!("Request failed: {e}")),
+        };
+
+        if attempt >= MAX_ATTEMPTS {
+            return Err(error);
+        }
+        let wait = backoff(attempt, retry_after);
+        eprintln!("Warning: {error}; retrying in {}s", wait.as_secs());
+        std::thread::sleep(wait);
+        attempt += 1;
+    }
+}
@@ -1,7 +1,12 @@
+use clap::{Parser, ValueEnum};
 use serde::Deserialize;
 
-const DEFAULT_GRAPHQL_URL: &str = "https://arty.dp42.dev/graphql";
-const DEFAULT_WAR_API_BASE: &str = "https://war-service-live-3.foxholeservices.com/api";
+use cli::{Cli, Command, LlmArgs, PostTarget, ReportArgs, SourceArgs, WarArgs};
+
+mod archive;
+mod cli;
+mod llm;
+mod post;
 
 // --- GraphQL response types ---
 
@@ -18,6 +23,7 @@ struct GraphQlData {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Stats {
+    war: Option<u64>,
     gun_placements: Vec<WeaponPlacementStat>,
     gun_placement_totals: FactionPlacementStats,
     marker_placements: MarkerPlacementStats,
@@ -56,29 +62,120 @@ struct WarState {
     required_victory_towns: u64,
 }
 
-const SYSTEM_PROMPT: &str = r##"You are a Warden intelligence officer writing an end-of-war report for the Foxhole subreddit. The war is over. You are reviewing the artillery data and war statistics collected during the war and presenting your findings. Your tone is smug, triumphant, and dripping with barely-contained contempt for the Colonial faction.
+/// Side the report is written for, picked with `--faction`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Persona {
+    Warden,
+    Colonial,
+    Neutral,
+}
 
-Guidelines:
-- You are ALWAYS pro-Warden. Wardens are disciplined, precise, and tactically superior.
-- This is an END OF WAR report. Frame everything as a retrospective on the war that just concluded.
-- Reference the WAR NUMBER prominently — e.g. "War 117 Debrief" or "After-Action Report: WC117".
+impl Persona {
+    /// How the faction shows up in the War API's `winner` field.
+    fn war_api_name(self) -> Option<&'static str> {
+        match self {
+            Persona::Warden => Some("WARDENS"),
+            Persona::Colonial => Some("COLONIALS"),
+            Persona::Neutral => None,
+        }
+    }
+
+    fn system_prompt(self, mode: Mode) -> String {
+        let (role, tone, stance) = match self {
+            Persona::Warden => ("a Warden intelligence officer", WARDEN_TONE, WARDEN_STANCE),
+            Persona::Colonial => (
+                "a Colonial intelligence officer",
+                COLONIAL_TONE,
+                COLONIAL_STANCE,
+            ),
+            Persona::Neutral => (
+                "an impartial war correspondent",
+                NEUTRAL_TONE,
+                NEUTRAL_STANCE,
+            ),
+        };
+        let (task, mode_guidelines) = match mode {
+            Mode::EndOfWar => (END_OF_WAR_TASK, END_OF_WAR_GUIDELINES),
+            Mode::Sitrep => (SITREP_TASK, SITREP_GUIDELINES),
+        };
+        let sign_off = match self {
+            Persona::Warden => {
+                "Sign off with a Warden motto, salute, or ominous warning to the Colonials."
+            }
+            Persona::Colonial => {
+                "Sign off with a Colonial motto, salute, or ominous warning to the Wardens."
+            }
+            Persona::Neutral => match mode {
+                Mode::EndOfWar => "Sign off with a wry closing line about the next war.",
+                Mode::Sitrep => "Sign off with a wry closing line about the week ahead.",
+            },
+        };
+        format!(
+            "You are {role} {task} {tone}\n\nGuidelines:\n{stance}\n{mode_guidelines}\n{COMMON_GUIDELINES}\n\n{FORMAT}\n- {sign_off}"
+        )
+    }
+}
+
+/// What kind of post to write, picked with `--mode`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    /// A retrospective on the war that just ended.
+    EndOfWar,
+    /// A mid-war update on what changed since the last snapshot.
+    Sitrep,
+}
+
+const WARDEN_TONE: &str = "Your tone is smug, triumphant, and dripping with barely-contained contempt for the Colonial faction.";
+
+const WARDEN_STANCE: &str = "- You are ALWAYS pro-Warden. Wardens are disciplined, precise, and tactically superior.
 - If a winner is declared, use it: if Wardens won, gloat mercilessly. If Colonials won, downplay it — they got lucky, they zerg-rushed, or the real victory was in the artillery stats.
-- Use the war duration (days or start/end times) to set the scene — was it a quick blitz or a brutal slog?
-- If the winner is NONE, the war may still be ongoing or in resistance phase — adapt accordingly.
 - If Warden gun placements outnumber Colonial ones: brag about superior coordination, doctrine, and the fruits of rigorous training.
 - If Colonial gun placements outnumber Warden ones: mock them relentlessly. More guns placed means they need extra help aiming. They compensate for lack of skill with volume.
 - Weave target and spotter placement stats in as evidence — high spotter counts show Warden recon excellence, or Colonial desperation to find something to shoot at.
-- Note: target markers are shared between all guns in a plan and are NOT faction-specific. They represent total targets placed across all plans regardless of faction.
+- ALWAYS reference and link to the artillery planning tool by URL. Plug it shamelessly — it's Warden-approved technology.";
+
+const COLONIAL_TONE: &str = "Your tone is haughty, imperious, and dripping with barely-contained disdain for the Warden faction.";
+
+const COLONIAL_STANCE: &str = "- You are ALWAYS pro-Colonial. Colonials are cunning, adaptable, and strategically superior.
+- If a winner is declared, use it: if Colonials won, gloat mercilessly. If Wardens won, downplay it — they got lucky, they turtled behind concrete, or the real victory was in the artillery stats.
+- If Colonial gun placements outnumber Warden ones: brag about industrial might, relentless logistics, and the fruits of a well-oiled war machine.
+- If Warden gun placements outnumber Colonial ones: mock them relentlessly. More guns placed means they need extra help aiming. They compensate for lack of skill with volume.
+- Weave target and spotter placement stats in as evidence — high spotter counts show Colonial recon excellence, or Warden desperation to find something to shoot at.
+- ALWAYS reference and link to the artillery planning tool by URL. Plug it shamelessly — it's Colonial-approved technology.";
+
+const NEUTRAL_TONE: &str = "Your tone is dry, even-handed, and quietly amused by both factions.";
+
+const NEUTRAL_STANCE: &str = "- You favour NEITHER side. Give Wardens and Colonials equal credit and equal ribbing.
+- If a winner is declared, report it fairly: congratulate the winners without fawning and credit the losers' best moments.
+- Compare Warden and Colonial gun placements like a sports commentator — who fielded more guns, which weapons each side leaned on, and what that says about how the war was fought.
+- Weave target and spotter placement stats in as colour — how much spotting and targeting went into the war overall.
+- ALWAYS reference and link to the artillery planning tool by URL. Recommend it to both sides.";
+
+const END_OF_WAR_TASK: &str = "writing an end-of-war report for the Foxhole subreddit. The war is over. You are reviewing the artillery data and war statistics collected during the war and presenting your findings.";
+
+const END_OF_WAR_GUIDELINES: &str = r##"- This is an END OF WAR report. Frame everything as a retrospective on the war that just concluded.
+- Reference the WAR NUMBER prominently — e.g. "War 117 Debrief" or "After-Action Report: WC117".
+- Use the war duration (days or start/end times) to set the scene — was it a quick blitz or a brutal slog?
+- If the winner is NONE, the war may still be ongoing or in resistance phase — adapt accordingly.
+- If a comparison with an earlier war is provided, work the changes in as proof of your narrative — e.g. "30% more Warden gun placements than War 116"."##;
+
+const SITREP_TASK: &str = "writing a weekly situation report for the Foxhole subreddit. The war is still being fought. You are reviewing the artillery data collected so far and what has changed since the last report.";
+
+const SITREP_GUIDELINES: &str = r##"- This is a MID-WAR SITREP. The war is NOT over — never declare a winner or write it as a retrospective.
+- Reference the WAR NUMBER and how long the war has run so far — e.g. "WC117 Day 12 Sitrep" or "Intelligence Update: War 117, Week 2".
+- If progress since the last snapshot is provided, lead with it: which side placed more guns this week, which weapons surged or went quiet, and what that says about momentum.
+- If there is no earlier snapshot, treat this as the first sitrep of the war and set the stakes for the weeks ahead."##;
+
+const COMMON_GUIDELINES: &str = r##"- Note: target markers are shared between all guns in a plan and are NOT faction-specific. They represent total targets placed across all plans regardless of faction.
 - Be creative — use military jargon, backhanded compliments, dramatic flair, and dry wit. Each post should feel unique.
 - NEVER use emojis. This is a serious intelligence document.
-- ALWAYS reference and link to the artillery planning tool by URL. The URL will be provided with the stats. Plug it shamelessly — it's Warden-approved technology.
-- The report author's in-game name and clan tag will be provided. Work them into the sign-off or byline naturally — e.g. "Filed by [name], [clan]" or attribute the report to them as the commanding officer / intelligence analyst.
+- The URL of the artillery planning tool will be provided with the stats.
+- The report author's in-game name and clan tag will be provided. Work them into the sign-off or byline naturally — e.g. "Filed by [name], [clan]" or attribute the report to them as the commanding officer / intelligence analyst."##;
 
-Format:
+const FORMAT: &str = r##"Format:
 - Start with a dramatic Reddit post title on its own line, prefixed with "# " (markdown h1). Include the war number in the title.
 - Write the body in Reddit markdown format.
-- Keep it around 200 words — tight and punchy.
-- Sign off with a Warden motto, salute, or ominous warning to the Colonials."##;
+- Keep it around 200 words — tight and punchy."##;
 
 fn fetch_war_state(client: &reqwest::blocking::Client, api_base: &str) -> Option<WarState> {
     let url = format!("{api_base}/worldconquest/war");
@@ -133,6 +230,11 @@ fn format_war_state(war: &WarState) -> String {
 fn format_stats(stats: &Stats) -> String {
     let mut out = String::new();
 
+    match stats.war {
+        Some(war) => out.push_str(&format!("Placement stats for war {war} only.\n\n")),
+        None => out.push_str("Placement stats across all wars.\n\n"),
+    }
+
     out.push_str("=== Gun Placement Totals ===\n");
     out.push_str(&format!(
         "  Warden:   {}\n",
@@ -163,49 +265,51 @@ fn format_stats(stats: &Stats) -> String {
     out
 }
 
-fn get_arg(flag: &str) -> Option<String> {
-    std::env::args().skip_while(|a| a != flag).nth(1)
-}
-
-fn main() {
-    let player_name = get_arg("--name").unwrap_or_else(|| {
-        eprintln!("Error: --name <in-game-name> is required");
-        eprintln!("Usage: cargo run -p shitpost-gen -- --name YourName --clan YourClan [--shard live-1|live-2|live-3] | claude -p");
-        std::process::exit(1);
-    });
-
-    let clan = get_arg("--clan").unwrap_or_else(|| {
-        eprintln!("Error: --clan <clan-tag> is required");
-        eprintln!("Usage: cargo run -p shitpost-gen -- --name YourName --clan YourClan [--shard live-1|live-2|live-3] | claude -p");
-        std::process::exit(1);
-    });
-
-    let graphql_url = get_arg("--url").unwrap_or_else(|| DEFAULT_GRAPHQL_URL.to_string());
-
-    let war_api_base = match get_arg("--shard").as_deref() {
-        Some("live-2") => "https://war-service-live-2.foxholeservices.com/api".to_string(),
-        Some("live-3") => "https://war-service-live-3.foxholeservices.com/api".to_string(),
-        Some("live-1") | None => DEFAULT_WAR_API_BASE.to_string(),
-        Some(other) => {
-            eprintln!("Unknown shard: {other}. Use live-1, live-2, or live-3.");
-            std::process::exit(1);
-        }
+/// The war from the persona's side: whether its faction won and whether it
+/// out-placed the other one. Empty for the neutral persona.
+fn format_standing(
+    persona: Persona,
+    war: Option<&WarState>,
+    totals: &FactionPlacementStats,
+) -> String {
+    let Some(faction) = persona.war_api_name() else {
+        return String::new();
     };
+    let (ours, theirs) = match persona {
+        Persona::Colonial => (totals.colonial, totals.warden),
+        _ => (totals.warden, totals.colonial),
+    };
+    let mut out = String::new();
+    out.push_str("=== From Your Side ===\n");
+    out.push_str(&format!("  Reporting for: {faction}\n"));
+    match war.map(|w| w.winner.as_str()) {
+        Some("NONE") | None => out.push_str("  Outcome: undecided\n"),
+        Some(winner) if winner == faction => out.push_str("  Outcome: VICTORY\n"),
+        Some(_) => out.push_str("  Outcome: defeat\n"),
+    }
+    let placements = match ours.cmp(&theirs) {
+        std::cmp::Ordering::Greater => format!("ahead by {}", ours - theirs),
+        std::cmp::Ordering::Less => format!("behind by {}", theirs - ours),
+        std::cmp::Ordering::Equal => "level".to_string(),
+    };
+    out.push_str(&format!("  Gun placements: {placements}\n\n"));
+    out
+}
 
-    let client = reqwest::blocking::Client::new();
-
-    // Fetch war state from the Foxhole War API
-    let war_state = fetch_war_state(&client, &war_api_base);
-
-    // Fetch stats from GraphQL
+/// Placement stats for `war`, or across all wars; exits when they can't be fetched.
+fn fetch_stats(client: &reqwest::blocking::Client, graphql_url: &str, war: Option<u64>) -> Stats {
     let query = serde_json::json!({
-        "query": "{ stats { gunPlacements { displayName faction count } gunPlacementTotals { colonial warden total } markerPlacements { targets spotters } } }"
+        "query": "query($war: Int) { stats(war: $war) { war gunPlacements { displayName faction count } gunPlacementTotals { colonial warden total } markerPlacements { targets spotters } } }",
+        "variables": { "war": war },
     });
 
-    eprintln!("Fetching stats from {graphql_url}...");
+    match war {
+        Some(war) => eprintln!("Fetching war {war} stats from {graphql_url}..."),
+        None => eprintln!("Fetching lifetime stats from {graphql_url}..."),
+    }
 
     let stats_resp = client
-        .post(&graphql_url)
+        .post(graphql_url)
         .json(&query)
         .send()
         .unwrap_or_else(|e| {
@@ -218,25 +322,246 @@ fn main() {
         std::process::exit(1);
     });
 
-    // Build the data summary
-    let mut data_summary = String::new();
+    stats_body.data.stats
+}
+
+/// The war state, placement stats and comparisons a report is written from;
+/// with a persona, also how the war went for its side.
+fn data_summary(
+    client: &reqwest::blocking::Client,
+    source: &SourceArgs,
+    war_args: &WarArgs,
+    persona: Option<Persona>,
+    mode: Mode,
+) -> String {
+    // Fetch war state from the Foxhole War API
+    let war_state = fetch_war_state(client, source.shard.war_api_base());
+
+    // Fetch stats from GraphQL, for the current war unless asked for lifetime totals
+    let war = if war_args.lifetime {
+        None
+    } else {
+        war_state.as_ref().map(|w| w.war_number)
+    };
+    let stats = fetch_stats(client, &source.url, war);
 
+    let mut summary = String::new();
     if let Some(ref war) = war_state {
-        data_summary.push_str(&format_war_state(war));
+        summary.push_str(&format_war_state(war));
     }
+    if let Some(persona) = persona {
+        summary.push_str(&format_standing(
+            persona,
+            war_state.as_ref(),
+            &stats.gun_placement_totals,
+        ));
+    }
+    summary.push_str(&format_stats(&stats));
 
-    data_summary.push_str(&format_stats(&stats_body.data.stats));
+    let Some(war) = stats.war else {
+        return summary;
+    };
+    let mut archive = match archive::Archive::load(&war_args.archive) {
+        Ok(archive) => archive,
+        Err(e) => {
+            eprintln!("Warning: {e}");
+            return summary;
+        }
+    };
+    match mode {
+        // Compare a single war with the latest archived war before it
+        Mode::EndOfWar => {
+            if let Some(previous) = archive.previous(war) {
+                let current = archive::WarSnapshot::new(war, None, &stats);
+                summary.push('\n');
+                summary.push_str(&archive::format_comparison(&current, previous));
+            }
+        }
+        // Show what changed since the last sitrep, then snapshot the war for the next one
+        Mode::Sitrep => {
+            let winner = war_state.map(|w| w.winner);
+            let current = archive::WarSnapshot::new(war, winner, &stats);
+            if let Some(previous) = archive.snapshot(war) {
+                summary.push('\n');
+                summary.push_str(&archive::format_progress(&current, previous));
+            }
+            archive.record(current);
+            match archive.save(&war_args.archive) {
+                Ok(()) => eprintln!("Archived war {war} in {}", war_args.archive),
+                Err(e) => eprintln!("Warning: {e}"),
+            }
+        }
+    }
+    summary
+}
+
+/// The system prompt and the request with the report's data.
+fn build_prompt(client: &reqwest::blocking::Client, report: &ReportArgs) -> (String, String) {
+    if report.mode == Mode::Sitrep && report.war.lifetime {
+        eprintln!("Error: a sitrep covers the current war only; drop --lifetime");
+        std::process::exit(1);
+    }
+    let summary = data_summary(
+        client,
+        &report.source,
+        &report.war,
+        Some(report.faction),
+        report.mode,
+    );
 
     // Derive the site URL from the GraphQL endpoint
-    let site_url = graphql_url.strip_suffix("/graphql").unwrap_or(&graphql_url);
-
-    // Output prompt for piping into `claude -p`
-    println!(
-        "{SYSTEM_PROMPT}\n\n---\n\n\
-         The artillery planning tool URL is: {site_url}\n\
-         Report author in-game name: {player_name}\n\
-         Report author clan: [{clan}]\n\n\
+    let graphql_url = &report.source.url;
+    let site_url = graphql_url.strip_suffix("/graphql").unwrap_or(graphql_url);
+
+    let request = format!(
+        "The artillery planning tool URL is: {site_url}\n\
+         Report author in-game name: {}\n\
+         Report author clan: [{}]\n\n\
          Here are the war data and artillery stats. \
-         Write a Foxhole subreddit end-of-war report based on these numbers:\n\n{data_summary}"
+         Write a Foxhole subreddit {} based on these numbers:\n\n{summary}",
+        report.name,
+        report.clan,
+        match report.mode {
+            Mode::EndOfWar => "end-of-war report",
+            Mode::Sitrep => "mid-war sitrep",
+        }
     );
+    (report.faction.system_prompt(report.mode), request)
+}
+
+/// The API to generate with; exits when its key isn't set.
+fn endpoint(args: &LlmArgs) -> llm::Endpoint {
+    let provider = args.provider;
+    let api_key = std::env::var(provider.key_var()).unwrap_or_else(|_| {
+        eprintln!(
+            "Error: generating a post needs an API key in {}",
+            provider.key_var()
+        );
+        std::process::exit(1);
+    });
+    llm::Endpoint {
+        provider,
+        base_url: args
+            .api_url
+            .clone()
+            .unwrap_or_else(|| provider.default_base_url().to_string()),
+        api_key,
+        model: args
+            .model
+            .clone()
+            .unwrap_or_else(|| provider.default_model().to_string()),
+    }
+}
+
+fn generate(endpoint: &llm::Endpoint, system_prompt: &str, request: &str) -> String {
+    llm::generate(endpoint, system_prompt, request).unwrap_or_else(|e| {
+        eprintln!("Failed to generate post: {e}");
+        std::process::exit(1);
+    })
+}
+
+/// `report` subcommand: print the prompt, or with `--call` the finished post.
+fn report_command(report: ReportArgs, call: bool, llm: LlmArgs) {
+    // Check the API settings before fetching anything
+    let endpoint = call.then(|| endpoint(&llm));
+    let client = reqwest::blocking::Client::new();
+    let (system_prompt, request) = build_prompt(&client, &report);
+    match endpoint {
+        Some(endpoint) => println!("{}", generate(&endpoint, &system_prompt, &request)),
+        // Output prompt for piping into `claude -p`
+        None => println!("{system_prompt}\n\n---\n\n{request}"),
+    }
+}
+
+/// `post` subcommand: generate the post and publish it.
+fn post_command(
+    to: PostTarget,
+    webhook: Option<String>,
+    subreddit: String,
+    dry_run: bool,
+    report: ReportArgs,
+    llm: LlmArgs,
+) {
+    let sink = match to {
+        PostTarget::Discord => post::Sink::Discord {
+            // Required with --to discord
+            webhook: webhook.unwrap_or_default(),
+        },
+        PostTarget::Reddit => post::Sink::Reddit {
+            subreddit,
+            // A dry run never signs in
+            credentials: (!dry_run).then(|| {
+                post::RedditCredentials::from_env().unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                })
+            }),
+        },
+    };
+    let endpoint = endpoint(&llm);
+    let client = reqwest::blocking::Client::new();
+    let (system_prompt, request) = build_prompt(&client, &report);
+    let post = generate(&endpoint, &system_prompt, &request);
+    if !dry_run {
+        println!("{post}");
+    }
+    if let Err(e) = post::publish(&client, &sink, &post, dry_run) {
+        eprintln!("Failed to publish post: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// `archive` subcommand: store a war's stats in the archive for later reports.
+fn archive_command(source: SourceArgs, war: Option<u64>, path: String) {
+    let client = reqwest::blocking::Client::new();
+    let war_state = fetch_war_state(&client, source.shard.war_api_base());
+
+    let war = war
+        .or(war_state.as_ref().map(|w| w.war_number))
+        .unwrap_or_else(|| {
+            eprintln!("Error: couldn't read the current war from the War API; pass --war <number>");
+            std::process::exit(1);
+        });
+    let winner = war_state.filter(|w| w.war_number == war).map(|w| w.winner);
+    let stats = fetch_stats(&client, &source.url, Some(war));
+
+    let mut archive = archive::Archive::load(&path).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    });
+    archive.record(archive::WarSnapshot::new(war, winner, &stats));
+    if let Err(e) = archive.save(&path) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    eprintln!(
+        "Archived war {war} in {path} ({} wars archived)",
+        archive.wars.len()
+    );
+}
+
+fn main() {
+    match Cli::parse().command {
+        Command::Report { report, call, llm } => report_command(report, call, llm),
+        Command::Stats { source, war } => {
+            let client = reqwest::blocking::Client::new();
+            print!(
+                "{}",
+                data_summary(&client, &source, &war, None, Mode::EndOfWar)
+            );
+        }
+        Command::Post {
+            to,
+            webhook,
+            subreddit,
+            dry_run,
+            report,
+            llm,
+        } => post_command(to, webhook, subreddit, dry_run, report, llm),
+        Command::Archive {
+            source,
+            war,
+            archive,
+        } => archive_command(source, war, archive),
+    }
 }
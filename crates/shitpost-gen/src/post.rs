@@ -0,0 +1,211 @@
+//! `post` sinks: publish the finished post to a Discord webhook or a
+//! subreddit instead of copying it over by hand.
+
+use serde_json::json;
+
+/// Longest message a Discord webhook accepts, in characters.
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+
+/// Longest title Reddit accepts, in characters.
+const REDDIT_TITLE_LIMIT: usize = 300;
+
+const REDDIT_TOKEN_URL: &str = "https://www.reddit.com/api/v1/access_token";
+const REDDIT_SUBMIT_URL: &str = "https://oauth.reddit.com/api/submit";
+
+/// A Reddit "script" app and the account it posts as.
+pub struct RedditCredentials {
+    client_id: String,
+    client_secret: String,
+    username: String,
+    password: String,
+}
+
+impl RedditCredentials {
+    /// Read from `REDDIT_CLIENT_ID`, `REDDIT_CLIENT_SECRET`, `REDDIT_USERNAME`
+    /// and `REDDIT_PASSWORD`.
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| format!("posting to Reddit needs {name} to be set"))
+        };
+        Ok(RedditCredentials {
+            client_id: var("REDDIT_CLIENT_ID")?,
+            client_secret: var("REDDIT_CLIENT_SECRET")?,
+            username: var("REDDIT_USERNAME")?,
+            password: var("REDDIT_PASSWORD")?,
+        })
+    }
+}
+
+/// Where the post goes.
+pub enum Sink {
+    Discord {
+        webhook: String,
+    },
+    Reddit {
+        subreddit: String,
+        /// `None` only for a dry run.
+        credentials: Option<RedditCredentials>,
+    },
+}
+
+/// The post's `# ` title line and the body after it. Posts without one get a
+/// generic title.
+fn split_title(post: &str) -> (String, String) {
+    let post = post.trim();
+    match post.split_once('\n') {
+        Some((first, rest)) if first.starts_with("# ") => {
+            (first[2..].trim().to_string(), rest.trim().to_string())
+        }
+        None if post.starts_with("# ") => (post[2..].trim().to_string(), String::new()),
+        _ => ("End-of-war report".to_string(), post.to_string()),
+    }
+}
+
+/// `post` cut into webhook-sized messages, breaking between lines where it can.
+fn discord_messages(post: &str) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut current = String::new();
+    for line in post.trim().lines() {
+        let mut line = line;
+        // A single line too long for one message is hard-wrapped
+        while line.chars().count() > DISCORD_MESSAGE_LIMIT {
+            if !current.is_empty() {
+                messages.push(std::mem::take(&mut current));
+            }
+            let cut = line
+                .char_indices()
+                .nth(DISCORD_MESSAGE_LIMIT)
+                .map_or(line.len(), |(i, _)| i);
+            messages.push(line[..cut].to_string());
+            line = &line[cut..];
+        }
+        let needed = current.chars().count() + line.chars().count() + 1;
+        if !current.is_empty() && needed > DISCORD_MESSAGE_LIMIT {
+            messages.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.trim().is_empty() {
+        messages.push(current);
+    }
+    messages
+}
+
+/// Publish `post`, or with `dry_run` print what would be sent instead.
+pub fn publish(
+    client: &reqwest::blocking::Client,
+    sink: &Sink,
+    post: &str,
+    dry_run: bool,
+) -> Result<(), String> {
+    match sink {
+        Sink::Discord { webhook } => {
+            for content in discord_messages(post) {
+                let payload = json!({ "content": content });
+                if dry_run {
+                    println!("POST {webhook}");
+                    println!("{}\n", serde_json::to_string_pretty(&payload).unwrap());
+                    continue;
+                }
+                let resp = client
+                    .post(webhook)
+                    .json(&payload)
+                    .send()
+                    .map_err(|e| format!("Failed to post to Discord: {e}"))?;
+                if !resp.status().is_success() {
+                    let status = resp.status();
+                    let body = resp.text().unwrap_or_default();
+                    return Err(format!("Discord returned {status}: {}", body.trim()));
+                }
+            }
+            if !dry_run {
+                eprintln!("Posted to Discord.");
+            }
+            Ok(())
+        }
+        Sink::Reddit {
+            subreddit,
+            credentials,
+        } => {
+            let (title, text) = split_title(post);
+            let title: String = title.chars().take(REDDIT_TITLE_LIMIT).collect();
+            let form = [
+                ("sr", subreddit.as_str()),
+                ("kind", "self"),
+                ("title", title.as_str()),
+                ("text", text.as_str()),
+                ("api_type", "json"),
+            ];
+            let credentials = match (credentials, dry_run) {
+                (_, true) => {
+                    println!("POST {REDDIT_SUBMIT_URL}");
+                    for (field, value) in form {
+                        println!("{field}: {value}");
+                    }
+                    return Ok(());
+                }
+                (Some(credentials), false) => credentials,
+                (None, false) => return Err("Reddit credentials are missing".to_string()),
+            };
+            submit_to_reddit(client, credentials, &form)
+        }
+    }
+}
+
+fn submit_to_reddit(
+    client: &reqwest::blocking::Client,
+    credentials: &RedditCredentials,
+    form: &[(&str, &str)],
+) -> Result<(), String> {
+    // Reddit rejects API calls without a descriptive user agent
+    let user_agent = format!(
+        "shitpost-gen/{} (by /u/{})",
+        env!("CARGO_PKG_VERSION"),
+        credentials.username
+    );
+
+    eprintln!("Signing in to Reddit as /u/{}...", credentials.username);
+    let token: serde_json::Value = client
+        .post(REDDIT_TOKEN_URL)
+        .header(reqwest::header::USER_AGENT, &user_agent)
+        .basic_auth(&credentials.client_id, Some(&credentials.client_secret))
+        .form(&[
+            ("grant_type", "password"),
+            ("username", credentials.username.as_str()),
+            ("password", credentials.password.as_str()),
+        ])
+        .send()
+        .and_then(|resp| resp.error_for_status())
+        .and_then(|resp| resp.json())
+        .map_err(|e| format!("Failed to sign in to Reddit: {e}"))?;
+    let access_token = token["access_token"].as_str().ok_or_else(|| {
+        format!(
+            "Reddit sign-in failed: {}",
+            token["error"]
+                .as_str()
+                .unwrap_or("no access token returned")
+        )
+    })?;
+
+    eprintln!("Submitting to Reddit...");
+    let resp: serde_json::Value = client
+        .post(REDDIT_SUBMIT_URL)
+        .header(reqwest::header::USER_AGENT, &user_agent)
+        .bearer_auth(access_token)
+        .form(form)
+        .send()
+        .and_then(|resp| resp.error_for_status())
+        .and_then(|resp| resp.json())
+        .map_err(|e| format!("Failed to submit to Reddit: {e}"))?;
+    if let Some(errors) = resp["json"]["errors"].as_array().filter(|e| !e.is_empty()) {
+        return Err(format!("Reddit refused the post: {}", json!(errors)));
+    }
+    match resp["json"]["data"]["url"].as_str() {
+        Some(url) => eprintln!("Posted to Reddit: {url}"),
+        None => eprintln!("Posted to Reddit."),
+    }
+    Ok(())
+}
@@ -25,10 +25,57 @@ test.describe("Foxhole Artillery Planner", () => {
     );
 
     const buttons = page.locator(".placement-mode button");
-    await expect(buttons).toHaveCount(3);
+    await expect(buttons).toHaveCount(9);
     await expect(buttons.nth(0)).toHaveText("Gun");
     await expect(buttons.nth(1)).toHaveText("Target");
     await expect(buttons.nth(2)).toHaveText("Spotter");
+    await expect(buttons.nth(3)).toHaveText("Enemy");
+    await expect(buttons.nth(4)).toHaveText("FOB");
+    await expect(buttons.nth(5)).toHaveText("Bunker");
+    await expect(buttons.nth(6)).toHaveText("No-fire");
+    await expect(buttons.nth(7)).toHaveText("Measure");
+    await expect(buttons.nth(8)).toHaveText("Displace");
+  });
+
+  test("pinned maps sort first and the last-used map is remembered", async ({
+    page,
+  }) => {
+    const mapSelect = page.locator('select[aria-label="Select map"]');
+    const options = mapSelect.locator("option");
+    const lastValue = await options.last().getAttribute("value");
+
+    await mapSelect.selectOption(lastValue!);
+    await page.locator('button[aria-label="Pin map"]').click();
+    await expect(page.locator('button[aria-label="Unpin map"]')).toBeVisible();
+    await expect(options.first()).toHaveAttribute("value", lastValue!);
+    await expect(options.first()).toContainText("\u2605");
+
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(mapSelect).toHaveValue(lastValue!);
+    await expect(options.first()).toHaveAttribute("value", lastValue!);
+  });
+
+  test("saved map view is restored after reload", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const mapInner = page.locator(".map-inner");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    await page.mouse.move(box!.x + box!.width / 2, box!.y + box!.height / 2);
+    await page.mouse.wheel(0, -300);
+    await page.waitForTimeout(200);
+
+    await page.locator(".save-view-btn").click();
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await page.waitForTimeout(200);
+
+    const transform = await mapInner.evaluate(
+      (el) => getComputedStyle(el).transform,
+    );
+    const scaleMatch = transform.match(/matrix\(([^,]+)/);
+    expect(scaleMatch).not.toBeNull();
+    expect(parseFloat(scaleMatch![1])).toBeGreaterThan(1.0);
   });
 
   test("sidebar panels render", async ({ page }) => {
@@ -277,6 +324,60 @@ test.describe("Foxhole Artillery Planner", () => {
     await expect(spotterLabel).toBeVisible({ timeout: 5000 });
   });
 
+  test("intel modes place FOB, bunker and no-fire markers", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    const modes: [string, number][] = [
+      ["FOB", 0.3],
+      ["Bunker", 0.5],
+      ["No-fire", 0.7],
+    ];
+    for (const [mode, x] of modes) {
+      await page.locator(".placement-mode button", { hasText: mode }).click();
+      await mapContainer.click({ position: { x: box!.width * x, y: box!.height * 0.4 } });
+    }
+    // A no-fire zone is finished by its second click; the same spot keeps the default radius
+    await mapContainer.click({ position: { x: box!.width * 0.7, y: box!.height * 0.4 } });
+
+    const svg = page.locator(".map-container svg");
+    await expect(svg.locator('text:text("FOB")')).toBeVisible({ timeout: 5000 });
+    await expect(svg.locator('text:text("BUNKER")')).toBeVisible();
+    await expect(svg.locator(".no-fire-zone")).toHaveCount(1);
+  });
+
+  test("a no-fire zone over the target flags the solution danger close", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const firstWeapon = weaponSelect.locator("optgroup option").first();
+    await weaponSelect.selectOption((await firstWeapon.getAttribute("value"))!);
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.4, y: box!.height * 0.5 } });
+    await mapContainer.click({ position: { x: box!.width * 0.6, y: box!.height * 0.5 } });
+
+    const solutionPanel = page.locator('.panel:has(h3:text("Firing Solution"))');
+    await expect(solutionPanel.locator(".solution")).toBeVisible({ timeout: 10_000 });
+    await expect(solutionPanel.locator(".danger-close-warning")).toHaveCount(0);
+
+    // Centre just past the target, edge out beyond it
+    await page.locator(".placement-mode button", { hasText: "No-fire" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.62, y: box!.height * 0.5 } });
+    await mapContainer.click({ position: { x: box!.width * 0.68, y: box!.height * 0.5 } });
+
+    await expect(solutionPanel.locator(".danger-close-warning")).toContainText(
+      "DANGER CLOSE",
+    );
+    await expect(page.locator(".map-container svg .no-fire-zone.danger-close")).toHaveCount(1);
+  });
+
   test("placing gun and target without weapon shows no firing solution", async ({
     page,
   }) => {
@@ -305,6 +406,38 @@ test.describe("Foxhole Artillery Planner", () => {
     await expect(solutionPanel.locator(".solution")).not.toBeVisible();
   });
 
+  test("weapon options describe crew and emplacement", async ({ page }) => {
+    // The shipped weapon data has no crew or mount figures, so give two
+    // weapons some
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("weapons(profile: LIVE)")) {
+        const response = await route.fetch();
+        const body = await response.json();
+        for (const weapon of body.data.weapons) {
+          if (weapon.slug === "cremari-mortar") {
+            Object.assign(weapon, { crew: 1, mount: "PORTABLE" });
+          } else if (weapon.slug === "storm-cannon") {
+            Object.assign(weapon, { crew: 1, mount: "EMPLACED" });
+          }
+        }
+        await route.fulfill({ response, json: body });
+      } else {
+        await route.continue();
+      }
+    });
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const mortar = weaponSelect.locator("option", { hasText: "Cremari Mortar" });
+    await expect(mortar.first()).toHaveAttribute("title", "Portable, crew 1");
+    const stormCannon = weaponSelect.locator("option", { hasText: "Storm Cannon" });
+    await expect(stormCannon.first()).toHaveAttribute("title", /^Emplaced/);
+  });
+
   test("weapon + gun + target placement shows firing solution", async ({
     page,
   }) => {
@@ -351,6 +484,481 @@ test.describe("Foxhole Artillery Planner", () => {
     await expect(statusLabel).toBeVisible();
   });
 
+  test("a run of nudges recalculates the firing solution once", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    const cx = box!.x + box!.width / 2;
+    const cy = box!.y + box!.height / 2;
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const firstWeapon = weaponSelect.locator("optgroup option").first();
+    await weaponSelect.selectOption((await firstWeapon.getAttribute("value"))!);
+
+    // Gun then target (placing a gun switches to Target mode and auto-pairs)
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await page.mouse.click(cx - 60, cy);
+    await page.mouse.click(cx + 60, cy - 40);
+    const solutionPanel = page.locator(
+      '.panel:has(h3:text("Firing Solution"))',
+    );
+    await expect(solutionPanel.locator(".solution")).toBeVisible({
+      timeout: 10_000,
+    });
+    const distance = solutionPanel.locator(".solution").first();
+    const before = await distance.textContent();
+
+    let calculations = 0;
+    page.on("request", (request) => {
+      if (request.postData()?.includes("calculate(")) {
+        calculations += 1;
+      }
+    });
+
+    // Box-select the gun and nudge it several times in quick succession
+    await page.keyboard.down("Shift");
+    await page.mouse.move(cx - 90, cy - 20);
+    await page.mouse.down();
+    await page.mouse.move(cx - 30, cy + 20, { steps: 4 });
+    await page.mouse.up();
+    await page.keyboard.up("Shift");
+    for (let i = 0; i < 6; i++) {
+      await page.keyboard.press("ArrowLeft");
+    }
+
+    await expect(distance).not.toHaveText(before!, { timeout: 5000 });
+    await page.waitForTimeout(500);
+    expect(calculations).toBe(1);
+  });
+
+  test("salvo size draws a dispersion pattern and salvo hit chance", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const firstWeapon = weaponSelect.locator("optgroup option").first();
+    await weaponSelect.selectOption((await firstWeapon.getAttribute("value"))!);
+
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+    await mapContainer.click({ position: { x: box!.width * 0.4, y: box!.height * 0.5 } });
+    await mapContainer.click({ position: { x: box!.width * 0.6, y: box!.height * 0.5 } });
+
+    const solutionPanel = page.locator(
+      '.panel:has(h3:text("Firing Solution"))',
+    );
+    await expect(solutionPanel.locator('text="Hit Chance"')).toBeVisible({
+      timeout: 10_000,
+    });
+
+    // Select the gun and give it a 4-shell salvo
+    await page.locator(".marker-item:has(.gun-coord)").click();
+    await solutionPanel
+      .locator('input[aria-label="Shells per salvo for gun 1"]')
+      .fill("4");
+    await solutionPanel
+      .locator('input[aria-label="Shells per salvo for gun 1"]')
+      .press("Enter");
+
+    await expect(page.locator(".map-container svg .dispersion circle")).toHaveCount(4);
+    await expect(solutionPanel).toContainText(/in 4\)/);
+  });
+
+  test("rocket artillery shows an impact zone instead of a circle", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const rockets = weaponSelect.locator("option", { hasText: "Retiarius" });
+    await weaponSelect.selectOption((await rockets.first().getAttribute("value"))!);
+
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+    await mapContainer.click({ position: { x: box!.width * 0.4, y: box!.height * 0.5 } });
+    await mapContainer.click({ position: { x: box!.width * 0.6, y: box!.height * 0.5 } });
+
+    const solutionPanel = page.locator(
+      '.panel:has(h3:text("Firing Solution"))',
+    );
+    await expect(solutionPanel.locator('text="Impact Zone"')).toBeVisible({
+      timeout: 10_000,
+    });
+    await expect(page.locator(".map-container svg ellipse.impact-zone")).toHaveCount(1);
+  });
+
+  test("fire mission export buttons appear once a solution exists", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const firstWeapon = weaponSelect.locator("optgroup option").first();
+    await weaponSelect.selectOption((await firstWeapon.getAttribute("value"))!);
+
+    const exportRow = page.locator(".export-row");
+    await expect(exportRow).toHaveCount(0);
+
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await page
+      .locator(".placement-mode button", { hasText: "Target" })
+      .click();
+    await mapContainer.click({ position: { x: box!.width * 0.7, y: box!.height * 0.3 } });
+
+    await expect(exportRow).toBeVisible({ timeout: 10_000 });
+    await expect(exportRow.locator("button", { hasText: "Copy CSV" })).toBeVisible();
+    await expect(
+      exportRow.locator("button", { hasText: "Copy for Discord" }),
+    ).toBeVisible();
+    await expect(exportRow.locator("button", { hasText: "Copy all" })).toBeVisible();
+
+    // Per-gun copy button carries the chat line in its title
+    const copyGun = page.locator(".copy-solution-btn");
+    await expect(copyGun).toHaveCount(1);
+    await expect(copyGun).toHaveAttribute("title", /AZ \d+\.\d° \/ \d+ m/);
+  });
+
+  test("spotter correction gives a corrected solution", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const firstWeapon = weaponSelect.locator("optgroup option").first();
+    await weaponSelect.selectOption((await firstWeapon.getAttribute("value"))!);
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await page.locator(".placement-mode button", { hasText: "Target" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.7, y: box!.height * 0.3 } });
+    await page.locator(".placement-mode button", { hasText: "Spotter" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.6, y: box!.height * 0.6 } });
+
+    const correction = page.locator(".observer-correction");
+    await expect(correction).toHaveCount(0);
+    await page.locator(".gun-coord").first().click();
+    await expect(correction).toBeVisible();
+
+    await correction
+      .locator('input[aria-label="Splash long (m), negative for short"]')
+      .fill("30");
+    const result = correction.locator(".correction-result");
+    await expect(result).toContainText(/Corrected: AZ \d+\.\d°/);
+    const first = await result.textContent();
+
+    // Applying stacks the correction and clears the report
+    await correction.locator("button", { hasText: "Apply" }).click();
+    await expect(
+      correction.locator('input[aria-label="Splash long (m), negative for short"]'),
+    ).toHaveValue("");
+    await expect(result).toHaveText(first!);
+    await correction.locator("button", { hasText: "Reset" }).click();
+    await expect(result).not.toHaveText(first!);
+  });
+
+  test("shot log records rounds per gun", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const firstWeapon = weaponSelect.locator("optgroup option").first();
+    await weaponSelect.selectOption((await firstWeapon.getAttribute("value"))!);
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await page.locator(".placement-mode button", { hasText: "Target" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.7, y: box!.height * 0.3 } });
+
+    const log = page.locator(".shot-log");
+    await expect(log).toBeVisible({ timeout: 10_000 });
+    await log.locator("summary").click();
+    await expect(log.locator("summary")).toHaveText("Shot log (0)");
+
+    const observed = log.locator('input[aria-label^="Observed result for"]');
+    await observed.fill("20 m short");
+    await log.locator("button", { hasText: "Log shot" }).click();
+    await expect(observed).toHaveValue("");
+    await expect(log.locator("summary")).toHaveText("Shot log (1)");
+    await expect(log.locator("li").first()).toContainText(
+      /AZ \d+\.\d° \/ \d+ m → .* — 20 m short/,
+    );
+
+    // Undo takes the logged shot back out
+    await page.keyboard.press("Control+z");
+    await expect(log.locator("summary")).toHaveText("Shot log (0)");
+  });
+
+  test("registration points keep a gun's stored solutions", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const firstWeapon = weaponSelect.locator("optgroup option").first();
+    await weaponSelect.selectOption((await firstWeapon.getAttribute("value"))!);
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await page.locator(".placement-mode button", { hasText: "Target" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.7, y: box!.height * 0.3 } });
+
+    const points = page.locator(".registrations");
+    await expect(points).toBeVisible({ timeout: 10_000 });
+    const name = points.locator('input[aria-label^="Registration point name"]');
+    await expect(name).toHaveAttribute("placeholder", "TRP-1");
+    await name.fill("TRP-1 bridge");
+    await points.locator("button", { hasText: "Register target" }).click();
+
+    const entry = points.locator("li");
+    await expect(entry).toHaveCount(1);
+    await expect(entry.locator(".registration-name")).toHaveText("TRP-1 bridge");
+    await expect(entry.locator(".registration-solution")).toHaveText(
+      /AZ \d+\.\d° \/ \d+ m/,
+    );
+    await expect(name).toHaveAttribute("placeholder", "TRP-2");
+
+    await entry.locator('button[title="Remove registration point"]').click();
+    await expect(entry).toHaveCount(0);
+  });
+
+  test("ammo selector switches a gun's shell", async ({ page }) => {
+    // The shipped weapon data lists no shells, so give the mortar one
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("weapons(profile: LIVE)")) {
+        const response = await route.fetch();
+        const body = await response.json();
+        const mortar = body.data.weapons.find(
+          (w: { slug: string }) => w.slug === "cremari-mortar",
+        );
+        mortar.ammo = [
+          {
+            name: "Shrapnel",
+            minRange: mortar.minRange,
+            maxRange: mortar.maxRange,
+            accRadiusMin: 7.5,
+            accRadiusMax: 14,
+            windDriftMin: mortar.windDriftMin,
+            windDriftMax: mortar.windDriftMax,
+            flightTimeMin: 0,
+            flightTimeMax: 0,
+          },
+        ];
+        await route.fulfill({ response, json: body });
+      } else {
+        await route.continue();
+      }
+    });
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    await weaponSelect.selectOption("cremari-mortar");
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await page.locator(".gun-coord").first().click();
+
+    const ammo = page.locator('select[aria-label="Ammo for gun 1"]');
+    await expect(ammo).toBeVisible({ timeout: 10_000 });
+    await expect(ammo).toHaveValue("");
+    await ammo.selectOption("Shrapnel");
+    await expect(page.getByText("Cremari Mortar (Shrapnel)")).toBeVisible();
+
+    // A weapon without special shells has no ammo selector
+    const gunWeapon = page.locator('select[aria-label="Weapon for gun 1"]');
+    await gunWeapon.selectOption("storm-cannon");
+    await expect(ammo).toHaveCount(0);
+  });
+
+  test("logistics panel counts crates for the barrage", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    await weaponSelect.selectOption("storm-cannon");
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await page.locator(".placement-mode button", { hasText: "Target" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.7, y: box!.height * 0.3 } });
+
+    const panel = page.locator(".logistics");
+    await panel.locator('input[aria-label="Shells for Target"]').fill("12");
+    const row = panel.locator(".logistics-table tbody tr");
+    await expect(row).toHaveCount(1);
+    await expect(row.locator("td").first()).toHaveText("300mm");
+    await expect(row.locator("td").nth(2)).toHaveText("3");
+    await expect(panel.locator(".logistics-total")).toContainText(
+      "3 crates · 1 × truck · 1 × flatbed",
+    );
+  });
+
+  test("range rings panel hides gun rings and draws custom rings", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    await weaponSelect.selectOption("storm-cannon");
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await page.locator(".placement-mode button", { hasText: "Spotter" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.6, y: box!.height * 0.4 } });
+
+    const circles = page.locator(".map-container svg circle");
+    const withRings = await circles.count();
+    const panel = page.locator(".range-rings");
+    await panel.getByLabel("Min/max rings for Gun").uncheck();
+    await expect(circles).toHaveCount(withRings - 2);
+
+    await panel.locator('select[aria-label="Ring around"]').selectOption("spotter-0");
+    await panel.locator('input[aria-label="Ring radius (m)"]').fill("250");
+    await panel.locator('input[aria-label="Ring label"]').fill("Intel");
+    await panel.locator("button", { hasText: "Add ring" }).click();
+    const ring = page.locator(".map-container svg .custom-ring");
+    await expect(ring).toHaveCount(1);
+    await expect(ring.locator("text")).toHaveText("Intel");
+    await expect(panel.locator(".custom-rings li")).toContainText("Intel around Spotter");
+
+    await panel.locator('button[aria-label="Remove ring Intel around Spotter"]').click();
+    await expect(ring).toHaveCount(0);
+  });
+
+  test("unsaved session is offered back after reload", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width / 2, y: box!.height / 2 } });
+    // Let the autosave run
+    await page.waitForTimeout(1500);
+
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    const banner = page.locator(".session-banner");
+    await expect(banner).toContainText("Restore your unsaved session (1 gun)?");
+    await banner.locator("button", { hasText: "Restore" }).click();
+    await expect(banner).toHaveCount(0);
+    await expect(
+      page.locator('.map-container svg text:text("GUN")'),
+    ).toBeVisible({ timeout: 5000 });
+  });
+
+  test("stateless link carries the plan without the server", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width / 2, y: box!.height / 2 } });
+    await page.locator('input[aria-label="Plan name"]').fill("Op Courier");
+    await page.locator("button", { hasText: "Copy stateless link" }).click();
+    const link = await page
+      .locator('input[aria-label="Stateless plan URL"]')
+      .inputValue();
+    expect(link).toMatch(/\/#plan=[A-Za-z0-9_-]+$/);
+
+    // Opens with the backend gone; maps come from the offline cache
+    await page.route("**/graphql", (route) => route.abort());
+    await page.goto("about:blank");
+    await page.goto(link);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(page.locator('input[aria-label="Plan name"]')).toHaveValue(
+      "Op Courier",
+    );
+    await expect(
+      page.locator('.map-container svg text:text("GUN")'),
+    ).toBeVisible({ timeout: 5000 });
+    await expect(page.locator(".session-banner")).toHaveCount(0);
+    expect(page.url()).not.toContain("#plan=");
+  });
+
+  test("web app manifest makes the planner installable", async ({ page }) => {
+    const href = await page
+      .locator('link[rel="manifest"]')
+      .getAttribute("href");
+    expect(href).toBe("/manifest.webmanifest");
+    const resp = await page.request.get(href!);
+    expect(resp.ok()).toBe(true);
+    const manifest = await resp.json();
+    expect(manifest.name).toBe("Foxhole Artillery Planner");
+    expect(manifest.display).toBe("standalone");
+    expect((await page.request.get("/sw.js")).ok()).toBe(true);
+  });
+
+  test("firing solution is worked out locally when offline", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    await weaponSelect.selectOption("storm-cannon");
+
+    // Calculations can't reach the server from here on
+    await page.route("**/graphql", (route) => route.abort());
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await page.locator(".placement-mode button", { hasText: "Target" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.7, y: box!.height * 0.3 } });
+
+    const solutionPanel = page.locator(
+      '.panel:has(h3:text("Firing Solution"))',
+    );
+    await expect(solutionPanel.locator(".solution")).toBeVisible({
+      timeout: 10_000,
+    });
+    await expect(solutionPanel.locator('text="Azimuth"')).toBeVisible();
+  });
+
   test("weapon selection shows range circles on map", async ({ page }) => {
     const mapContainer = page.locator(".map-container");
     const box = await mapContainer.boundingBox();
@@ -381,6 +989,24 @@ test.describe("Foxhole Artillery Planner", () => {
     expect(count).toBeGreaterThanOrEqual(3);
   });
 
+  test("selected gun shows a compass rose", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width / 2, y: box!.height / 2 } });
+
+    const rose = page.locator(".map-container svg .compass-rose");
+    await page.locator(".gun-coord").first().click();
+    await expect(rose).toHaveCount(1);
+    await expect(rose.locator(".compass-tick")).toHaveCount(36);
+    await expect(rose.locator("text", { hasText: /^N$/ })).toHaveCount(1);
+
+    await page.keyboard.press("Escape");
+    await expect(rose).toHaveCount(0);
+  });
+
   test("firing line renders between gun and target", async ({ page }) => {
     const mapContainer = page.locator(".map-container");
     const box = await mapContainer.boundingBox();
@@ -420,6 +1046,175 @@ test.describe("Foxhole Artillery Planner", () => {
     await expect(northBtn).not.toHaveClass(/active/);
   });
 
+  test("wind shows as an arrow in the map corner", async ({ page }) => {
+    const windPanel = page.locator('.panel:has(h3:text("Wind"))');
+    const indicator = page.locator(".map-container svg .wind-indicator");
+    await windPanel.locator('button[aria-label="Wind direction NE"]').click();
+    await windPanel.locator('input[type="range"]').fill("3");
+    await expect(indicator.locator("title")).toHaveText("Wind 45°, strength 3");
+    await expect(indicator.locator('.wind-pip[fill="white"]')).toHaveCount(3);
+
+    // Calm wind draws nothing
+    await windPanel.locator('input[type="range"]').fill("0");
+    await expect(indicator).toHaveCount(0);
+  });
+
+  test("wind is pre-filled from the latest report and can be shared", async ({
+    page,
+  }) => {
+    let reported: Record<string, unknown> | null = null;
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("windReport(")) {
+        await route.fulfill({
+          status: 200,
+          contentType: "application/json",
+          body: JSON.stringify({
+            data: {
+              windReport: {
+                direction: 45,
+                strength: 3,
+                reportedAt: "2026-10-18T12:00:00+00:00",
+              },
+            },
+          }),
+        });
+      } else if (postData && postData.includes("reportWind(")) {
+        reported = JSON.parse(postData).variables.wind;
+        await route.fulfill({
+          status: 200,
+          contentType: "application/json",
+          body: JSON.stringify({
+            data: {
+              reportWind: {
+                direction: 90,
+                strength: 3,
+                reportedAt: "2026-10-18T12:05:00+00:00",
+              },
+            },
+          }),
+        });
+      } else {
+        await route.continue();
+      }
+    });
+    await page.reload();
+
+    const windPanel = page.locator('.panel:has(h3:text("Wind"))');
+    await expect(
+      windPanel.locator('button[aria-label="Wind direction NE"]'),
+    ).toHaveClass(/active/, { timeout: 5000 });
+    await expect(windPanel.locator(".strength-row .value")).toHaveText("3");
+    await expect(windPanel.locator(".wind-report")).toHaveText(
+      "Latest report: NE, strength 3",
+    );
+
+    await windPanel.locator('button[aria-label="Wind direction E"]').click();
+    await windPanel.locator("button", { hasText: "Share wind" }).click();
+    await expect(windPanel.locator(".wind-report")).toHaveText(
+      "Latest report: E, strength 3",
+    );
+    expect(reported).toEqual({ direction: 90, strength: 3 });
+  });
+
+  test("battery template is saved and stamped onto the map", async ({
+    page,
+  }) => {
+    await page.evaluate(() => localStorage.removeItem("battery_templates"));
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const firstWeapon = weaponSelect.locator("optgroup option").first();
+    await weaponSelect.selectOption((await firstWeapon.getAttribute("value"))!);
+
+    const panel = page.locator('.panel:has(h3:text("Battery templates"))');
+    await panel.locator('input[aria-label="Template name"]').fill("Triangle");
+    await panel.locator('input[aria-label="Guns"]').fill("3");
+    await panel.locator('input[aria-label="Spacing (m)"]').fill("50");
+    await panel.locator("button", { hasText: "Add formation" }).click();
+    await expect(panel.locator(".template-name")).toHaveText("Triangle (3)");
+
+    // Kept across reloads
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(panel.locator(".template-name")).toHaveText("Triangle (3)");
+
+    await panel.locator(".template-row button", { hasText: "Stamp" }).click();
+    await expect(panel.locator(".template-hint")).toContainText("Triangle");
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    await mapContainer.click({
+      position: { x: box!.width * 0.5, y: box!.height * 0.5 },
+    });
+    const guns = page.locator('.map-container svg text:text("GUN")');
+    await expect(guns).toHaveCount(3);
+    await expect(panel.locator(".template-hint")).toHaveCount(0);
+
+    // One undo takes the whole battery off again
+    await page.locator(".toolbar-actions .toolbar-btn").nth(0).click();
+    await expect(guns).toHaveCount(0);
+  });
+
+  test("grid reference entry places markers", async ({ page }) => {
+    const panel = page.locator('.panel:has(h3:text("Firing Solution"))');
+    const gridInput = panel.locator('input[aria-label="Grid reference"]');
+    const go = panel.locator(".grid-entry button", { hasText: "Go" });
+
+    await gridInput.fill("Z99");
+    await go.click();
+    await expect(panel.locator(".grid-entry-error")).toContainText("G9k3");
+
+    await gridInput.fill("G9k3");
+    await go.click();
+    const svg = page.locator(".map-container svg");
+    await expect(svg.locator('text:text("GUN")')).toHaveCount(1);
+
+    await panel
+      .locator('select[aria-label="Grid reference action"]')
+      .selectOption("target");
+    await gridInput.fill("H10k7");
+    await gridInput.press("Enter");
+    await expect(svg.locator('text:text("TARGET")')).toHaveCount(1);
+    await expect(panel.locator(".grid-entry-error")).toHaveCount(0);
+  });
+
+  test("range and bearing places a gun from the spotter", async ({ page }) => {
+    const panel = page.locator('.panel:has(h3:text("Range & bearing"))');
+    await expect(panel.locator(".range-bearing-hint")).toContainText(
+      "Place a spotter",
+    );
+
+    await page.locator(".placement-mode button", { hasText: "Spotter" }).click();
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    await mapContainer.click({
+      position: { x: box!.width * 0.5, y: box!.height * 0.5 },
+    });
+    await expect(panel.locator('select[aria-label="Measure from"]')).toHaveValue(
+      "spotter-0",
+    );
+
+    // Off-map distances are refused
+    await panel.locator('input[aria-label="Distance (m)"]').fill("100000");
+    await panel.locator('input[aria-label="Azimuth (°)"]').fill("40");
+    await panel.locator("button", { hasText: "Place" }).click();
+    await expect(panel.locator(".range-bearing-error")).toContainText(
+      "off the map",
+    );
+
+    await panel.locator('input[aria-label="Distance (m)"]').fill("125");
+    await expect(panel.locator(".range-bearing-hint")).toBeVisible();
+    await panel.locator("button", { hasText: "Place" }).click();
+    await expect(panel.locator(".range-bearing-error")).toHaveCount(0);
+    await expect(
+      page.locator('.map-container svg text:text("GUN")'),
+    ).toHaveCount(1);
+  });
+
   test("plan panel has name input and save button", async ({ page }) => {
     const planPanel = page.locator('.panel:has(h3:text("Plan"))');
     await expect(planPanel).toBeVisible();
@@ -542,40 +1337,179 @@ test.describe("Foxhole Artillery Planner", () => {
     await page.mouse.up();
     await page.waitForTimeout(200);
 
-    const afterDrag = await mapInner.evaluate(
-      (el) => getComputedStyle(el).transform,
-    );
-    // Transform should have changed (panned)
-    expect(afterDrag).not.toBe(beforeDrag);
+    const afterDrag = await mapInner.evaluate(
+      (el) => getComputedStyle(el).transform,
+    );
+    // Transform should have changed (panned)
+    expect(afterDrag).not.toBe(beforeDrag);
+  });
+
+  test("click without drag still places markers correctly", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    await expect(mapContainer).toBeVisible();
+
+    // Zoom in first
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    await page.mouse.move(box!.x + box!.width / 2, box!.y + box!.height / 2);
+    await page.mouse.wheel(0, -300);
+    await page.waitForTimeout(200);
+
+    // Click (no drag) to place gun marker
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+    const updatedBox = await mapContainer.boundingBox();
+    await page.mouse.click(
+      updatedBox!.x + updatedBox!.width / 2,
+      updatedBox!.y + updatedBox!.height / 2,
+    );
+
+    // Gun marker should appear
+    const svg = page.locator(".map-container svg");
+    const gunMarker = svg.locator('text:text("GUN")');
+    await expect(gunMarker).toBeVisible({ timeout: 5000 });
+  });
+
+  test("dragging a marker moves it in one undo step", async ({ page }) => {
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    const startX = box!.x + box!.width / 2;
+    const startY = box!.y + box!.height / 2;
+    await page.mouse.click(startX, startY);
+
+    const gunTag = page.locator(".coord-tag.gun-tag");
+    await expect(gunTag).toBeVisible({ timeout: 5000 });
+    const before = await gunTag.textContent();
+    const mapInner = page.locator(".map-inner");
+    const transformBefore = await mapInner.evaluate(
+      (el) => getComputedStyle(el).transform,
+    );
+
+    // Press on the gun and drag it: the marker moves, the map doesn't pan
+    await page.mouse.move(startX, startY);
+    await page.mouse.down();
+    await page.mouse.move(startX + 120, startY + 80, { steps: 8 });
+    await page.mouse.up();
+
+    await expect(gunTag).not.toHaveText(before!);
+    expect(
+      await mapInner.evaluate((el) => getComputedStyle(el).transform),
+    ).toBe(transformBefore);
+    await expect(
+      page.locator('.map-container svg text:text("GUN")'),
+    ).toHaveCount(1);
+
+    // A single undo puts it back where the drag started
+    await page.keyboard.press("Control+z");
+    await expect(gunTag).toHaveText(before!);
+  });
+
+  test("box-selected markers nudge together in one undo step", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    const cx = box!.x + box!.width / 2;
+    const cy = box!.y + box!.height / 2;
+
+    // Two guns side by side (placing a gun switches to Target mode)
+    const gunButton = page.locator(".placement-mode button", { hasText: "Gun" });
+    await gunButton.click();
+    await page.mouse.click(cx - 40, cy);
+    await gunButton.click();
+    await page.mouse.click(cx + 40, cy);
+    const gunTags = page.locator(".coord-tag.gun-tag");
+    await expect(gunTags).toHaveCount(2);
+    const before = await gunTags.allTextContents();
+
+    // Shift+drag a box around both of them
+    await page.keyboard.down("Shift");
+    await page.mouse.move(cx - 80, cy - 40);
+    await page.mouse.down();
+    await page.mouse.move(cx + 80, cy + 40, { steps: 6 });
+    await expect(page.locator(".box-select")).toBeVisible();
+    await page.mouse.up();
+    await page.keyboard.up("Shift");
+    await expect(page.locator(".box-select")).toHaveCount(0);
+
+    // Shift+ArrowUp moves the pair 50 m north
+    await page.keyboard.press("Shift+ArrowUp");
+    await expect(gunTags.nth(0)).not.toHaveText(before[0]);
+    await expect(gunTags.nth(1)).not.toHaveText(before[1]);
+
+    await page.keyboard.press("Control+z");
+    await expect(gunTags.nth(0)).toHaveText(before[0]);
+    await expect(gunTags.nth(1)).toHaveText(before[1]);
+  });
+
+  test("measure mode shows distance and bearing without placing markers", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    const cx = box!.x + box!.width / 2;
+    const cy = box!.y + box!.height / 2;
+
+    await page.keyboard.press("m");
+    const measureButton = page.locator(".placement-mode button", {
+      hasText: "Measure",
+    });
+    await expect(measureButton).toHaveClass(/active-measure/);
+
+    await page.mouse.click(cx - 60, cy);
+    await page.mouse.click(cx + 60, cy);
+    const label = page.locator(".measure-line text");
+    await expect(label).toHaveCount(1);
+    await expect(label).toContainText(/\d+ m\s+090°/);
+    await expect(page.locator(".coord-tag")).toHaveCount(0);
+
+    // Switching modes clears the line
+    await page.keyboard.press("g");
+    await expect(page.locator(".measure-line")).toHaveCount(0);
   });
 
-  test("click without drag still places markers correctly", async ({
+  test("displace mode adds alternate positions to a gun's route", async ({
     page,
   }) => {
     const mapContainer = page.locator(".map-container");
-    await expect(mapContainer).toBeVisible();
-
-    // Zoom in first
     const box = await mapContainer.boundingBox();
     expect(box).not.toBeNull();
-    await page.mouse.move(box!.x + box!.width / 2, box!.y + box!.height / 2);
-    await page.mouse.wheel(0, -300);
-    await page.waitForTimeout(200);
+    const cx = box!.x + box!.width / 2;
+    const cy = box!.y + box!.height / 2;
+
+    // Place a gun and a target
+    await page.mouse.click(cx, cy);
+    await page.mouse.click(cx + 40, cy);
+
+    await page.keyboard.press("d");
+    await expect(
+      page.locator(".placement-mode button", { hasText: "Displace" }),
+    ).toHaveClass(/active-displace/);
+
+    // Pick the gun, then add two alternate positions
+    await page.mouse.click(cx, cy);
+    await page.mouse.click(cx, cy + 60);
+    await page.mouse.click(cx - 60, cy + 60);
+
+    await expect(page.locator(".displacement-route")).toHaveCount(1);
+    await expect(page.locator(".displacement-step")).toHaveCount(2);
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(1);
 
-    // Click (no drag) to place gun marker
     await page
-      .locator(".placement-mode button", { hasText: "Gun" })
+      .locator(".marker-item", { has: page.locator(".displacement-step") })
+      .first()
+      .locator('button[title="Remove position"]')
       .click();
-    const updatedBox = await mapContainer.boundingBox();
-    await page.mouse.click(
-      updatedBox!.x + updatedBox!.width / 2,
-      updatedBox!.y + updatedBox!.height / 2,
-    );
-
-    // Gun marker should appear
-    const svg = page.locator(".map-container svg");
-    const gunMarker = svg.locator('text:text("GUN")');
-    await expect(gunMarker).toBeVisible({ timeout: 5000 });
+    await expect(page.locator(".displacement-step")).toHaveCount(1);
   });
 
   test("double-click resets zoom to 1.0", async ({ page }) => {
@@ -659,11 +1593,12 @@ test.describe("Foxhole Artillery Planner", () => {
     const svg = page.locator(".map-container svg");
     await expect(svg.locator('text:text("GUN")')).toBeVisible({ timeout: 5000 });
 
-    // Right-click near the same spot to remove it
+    // Right-click near the same spot opens the context menu; choose Remove
     await mapContainer.click({
       position: { x: cx, y: cy },
       button: "right",
     });
+    await page.locator(".context-menu button", { hasText: "Remove gun" }).click();
 
     // Gun marker should be gone
     await expect(svg.locator('text:text("GUN")')).not.toBeVisible({ timeout: 5000 });
@@ -688,11 +1623,12 @@ test.describe("Foxhole Artillery Planner", () => {
     // Switch back to Gun mode so right-click checks guns first
     await gunBtn.click();
 
-    // Right-click near the first gun to remove it
+    // Right-click near the first gun and remove it from the context menu
     await mapContainer.click({
       position: { x: box!.width * 0.3, y: box!.height * 0.5 },
       button: "right",
     });
+    await page.locator(".context-menu button", { hasText: "Remove gun" }).click();
 
     // After removal, only one gun remains — label goes back to "GUN" (no number)
     await expect(svg.locator('text:text("GUN")')).toBeVisible({ timeout: 5000 });
@@ -700,6 +1636,353 @@ test.describe("Foxhole Artillery Planner", () => {
     await expect(svg.locator('text:text("GUN 2")')).not.toBeVisible();
   });
 
+  test("right-click removes instantly when preference is enabled", async ({ page }) => {
+    await page.locator(".checkbox-row", { hasText: "Right-click removes" }).click();
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const cx = box!.width * 0.5;
+    const cy = box!.height * 0.5;
+    await mapContainer.click({ position: { x: cx, y: cy } });
+
+    const svg = page.locator(".map-container svg");
+    await expect(svg.locator('text:text("GUN")')).toBeVisible({ timeout: 5000 });
+
+    await mapContainer.click({
+      position: { x: cx, y: cy },
+      button: "right",
+    });
+
+    await expect(page.locator(".context-menu")).toHaveCount(0);
+    await expect(svg.locator('text:text("GUN")')).not.toBeVisible({ timeout: 5000 });
+  });
+
+  test("high-contrast preference outlines markers", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    await mapContainer.click({ position: { x: box!.width * 0.5, y: box!.height * 0.5 } });
+
+    const svg = page.locator(".map-container svg");
+    await expect(svg.locator('text:text("GUN")')).toBeVisible({ timeout: 5000 });
+    await expect(svg.locator("filter#hc-outline")).toHaveCount(0);
+
+    await page.locator(".checkbox-row", { hasText: "High-contrast" }).click();
+    await expect(mapContainer).toHaveClass(/high-contrast/);
+    await expect(svg.locator("filter#hc-outline")).toHaveCount(1);
+  });
+
+  test("settings drawer switches units and keeps the placement mode", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    await page.locator(".settings-open-btn").click();
+    const drawer = page.locator('.settings-drawer[aria-label="Settings"]');
+    await expect(drawer).toBeVisible();
+    await drawer.locator('select[aria-label="Azimuth unit"]').selectOption("mils");
+    await drawer.locator('select[aria-label="Distance rounding"]').selectOption("10");
+    await drawer.locator(".checkbox-row", { hasText: "Switch between gun and target" }).click();
+    await page.keyboard.press("Escape");
+    await expect(drawer).toHaveCount(0);
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const weaponValue = await weaponSelect
+      .locator("optgroup option")
+      .first()
+      .getAttribute("value");
+    await weaponSelect.selectOption(weaponValue!);
+
+    // With auto-switching off, the planner stays in gun mode after a gun
+    const gunBtn = page.locator(".placement-mode button", { hasText: "Gun" });
+    await gunBtn.click();
+    await mapContainer.click({ position: { x: box!.width * 0.3, y: box!.height * 0.5 } });
+    await expect(gunBtn).toHaveClass(/active/);
+
+    await page.locator(".placement-mode button", { hasText: "Target" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.6, y: box!.height * 0.4 } });
+    const solution = page.locator('.panel:has(h3:text("Firing Solution")) .solution');
+    await expect(solution).toContainText("mil", { timeout: 10_000 });
+    await expect(solution).toContainText(/\d*0m/);
+
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await page.locator(".settings-open-btn").click();
+    await expect(page.locator('select[aria-label="Azimuth unit"]')).toHaveValue("mils");
+  });
+
+  test("research mode reports where a round landed", async ({ page }) => {
+    let submitted: any = null;
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("submitImpactObservation")) {
+        submitted = JSON.parse(postData).variables.input;
+        await route.fulfill({
+          status: 200,
+          contentType: "application/json",
+          body: JSON.stringify({ data: { submitImpactObservation: true } }),
+        });
+        return;
+      }
+      await route.continue();
+    });
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    await page.locator(".settings-open-btn").click();
+    await page.locator(".checkbox-row", { hasText: "Accuracy research mode" }).click();
+    await page.keyboard.press("Escape");
+
+    const weaponSelect = page
+      .locator('.panel:has(h3:text("Active Weapon")) select')
+      .first();
+    const weaponValue = await weaponSelect
+      .locator("optgroup option")
+      .first()
+      .getAttribute("value");
+    await weaponSelect.selectOption(weaponValue!);
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.5, y: box!.height * 0.5 } });
+    await page.locator(".placement-mode button", { hasText: "Target" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.52, y: box!.height * 0.5 } });
+
+    const report = page.locator(".impact-report");
+    await expect(report).toBeVisible({ timeout: 10_000 });
+    await report.locator("summary").click();
+    await report.locator('input[aria-label^="Impact long"]').fill("-12");
+    await report.locator('input[aria-label^="Impact right"]').fill("4");
+    await report.locator("button", { hasText: "Submit" }).click();
+    await expect(report).toContainText("Impact reported");
+    expect(submitted.weaponId).toBe(weaponValue);
+    expect(submitted.long).toBe(-12);
+    expect(submitted.right).toBe(4);
+  });
+
+  test("live structures preference draws War API structures", async ({ page }) => {
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("mapStructures")) {
+        await route.fulfill({
+          status: 200,
+          contentType: "application/json",
+          body: JSON.stringify({
+            data: {
+              mapStructures: {
+                structures: [
+                  {
+                    kind: "TOWN_HALL",
+                    team: "COLONIAL",
+                    position: { x: 1092, y: 945 },
+                    location: "Test Town",
+                  },
+                ],
+              },
+            },
+          }),
+        });
+      } else {
+        await route.continue();
+      }
+    });
+
+    const svg = page.locator(".map-container svg");
+    await expect(svg.locator("g.map-structure")).toHaveCount(0);
+
+    await page.locator(".checkbox-row", { hasText: "Show live town halls" }).click();
+    const structure = svg.locator("g.map-structure");
+    await expect(structure).toHaveCount(1, { timeout: 5000 });
+    await expect(structure.locator("title")).toHaveText(
+      "Town hall, Test Town (Colonial)",
+    );
+
+    await page.locator(".checkbox-row", { hasText: "Show live town halls" }).click();
+    await expect(svg.locator("g.map-structure")).toHaveCount(0);
+  });
+
+  test("placing a gun in water warns from the map's terrain mask", async ({ page }) => {
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("terrainMask")) {
+        await route.fulfill({
+          status: 200,
+          contentType: "application/json",
+          body: JSON.stringify({ data: { terrainMask: ["~~~~", "~~~~"] } }),
+        });
+      } else {
+        await route.continue();
+      }
+    });
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+
+    const banner = page.locator(".terrain-banner");
+    await expect(banner).toHaveCount(0);
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+    await mapContainer.click({ position: { x: box!.width * 0.5, y: box!.height * 0.5 } });
+
+    await expect(banner).toContainText("Gun 1 is in water", { timeout: 5000 });
+  });
+
+  test("heatmap preference shades popular grid cells", async ({ page }) => {
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("placementHeatmap")) {
+        await route.fulfill({
+          status: 200,
+          contentType: "application/json",
+          body: JSON.stringify({
+            data: {
+              placementHeatmap: {
+                guns: [
+                  { col: 6, row: 8, count: 12 },
+                  { col: 7, row: 8, count: 3 },
+                ],
+                targets: [{ col: 2, row: 2, count: 1 }],
+              },
+            },
+          }),
+        });
+      } else {
+        await route.continue();
+      }
+    });
+
+    const svg = page.locator(".map-container svg");
+    const heatmap = page.getByLabel("Community heatmap");
+    await heatmap.selectOption("guns");
+    await expect(svg.locator("rect.heat-cell")).toHaveCount(2, { timeout: 5000 });
+    await expect(svg.locator("rect.heat-cell title").first()).toHaveText("G9: 12");
+
+    await heatmap.selectOption("targets");
+    await expect(svg.locator("rect.heat-cell")).toHaveCount(1);
+    await expect(svg.locator("rect.heat-cell title")).toHaveText("C3: 1");
+
+    await heatmap.selectOption("off");
+    await expect(svg.locator("rect.heat-cell")).toHaveCount(0);
+  });
+
+  test("targets placed near an enemy structure snap onto it", async ({ page }) => {
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("mapStructures")) {
+        await route.fulfill({
+          status: 200,
+          contentType: "application/json",
+          body: JSON.stringify({
+            data: {
+              mapStructures: {
+                structures: [
+                  {
+                    kind: "TOWN_HALL",
+                    team: "COLONIAL",
+                    position: { x: 1092, y: 945 },
+                    location: "Test Town",
+                  },
+                ],
+              },
+            },
+          }),
+        });
+      } else {
+        await route.continue();
+      }
+    });
+
+    await page.locator(".checkbox-row", { hasText: "Show live town halls" }).click();
+    const svg = page.locator(".map-container svg");
+    const structure = svg.locator("g.map-structure");
+    await expect(structure).toHaveCount(1, { timeout: 5000 });
+
+    // Wardens by default, so the Colonial town hall is an enemy structure
+    const box = await structure.boundingBox();
+    expect(box).not.toBeNull();
+    await page
+      .locator(".placement-mode button", { hasText: "Target" })
+      .click();
+    await page.mouse.click(box!.x + box!.width / 2 + 6, box!.y + box!.height / 2 + 6);
+
+    await expect(
+      svg.locator("text", { hasText: "Town hall, Test Town" }),
+    ).toBeVisible({ timeout: 5000 });
+  });
+
+  test("context menu duplicates a marker", async ({ page }) => {
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const cx = box!.width * 0.5;
+    const cy = box!.height * 0.5;
+    await mapContainer.click({ position: { x: cx, y: cy } });
+
+    await mapContainer.click({
+      position: { x: cx, y: cy },
+      button: "right",
+    });
+    await page.locator(".context-menu button", { hasText: "Duplicate gun" }).click();
+
+    const svg = page.locator(".map-container svg");
+    await expect(svg.locator('text:text("GUN 2")')).toBeVisible({ timeout: 5000 });
+  });
+
+  test("targets can be marked destroyed", async ({ page }) => {
+    await page
+      .locator(".placement-mode button", { hasText: "Target" })
+      .click();
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    const cx = box!.width * 0.5;
+    const cy = box!.height * 0.5;
+    await mapContainer.click({ position: { x: cx, y: cy } });
+    await expect(page.locator(".target-progress")).toHaveText(
+      "0/1 targets destroyed (0%)",
+    );
+
+    // Right-click menu moves the target on to "fired upon"
+    await mapContainer.click({
+      position: { x: cx, y: cy },
+      button: "right",
+    });
+    await page
+      .locator(".context-menu button", { hasText: "Mark fired upon" })
+      .click();
+    const statusBtn = page.locator(".target-status-btn");
+    await expect(statusBtn).toHaveText("Fired upon");
+
+    // The firing panel button finishes it off
+    await statusBtn.click();
+    await expect(statusBtn).toHaveText("Destroyed");
+    await expect(page.locator(".coord-info.target-destroyed")).toHaveCount(1);
+    await expect(
+      page.locator(".map-container svg g.target-destroyed"),
+    ).toHaveCount(1);
+    await expect(page.locator(".target-progress")).toHaveText(
+      "1/1 targets destroyed (100%)",
+    );
+  });
+
   test("map bottom is reachable by panning at zoom 1", async ({ page }) => {
     // Regression: clamp_pan used to assume content height == container height,
     // preventing downward panning when the map image (width:100%, height:auto)
@@ -1096,17 +2379,59 @@ test.describe("Placement tracking", () => {
     expect(box).not.toBeNull();
     await mapContainer.click({ position: { x: box!.width / 2, y: box!.height / 2 } });
 
-    // Wait for the fire-and-forget tracking call to complete
+    // Wait for the fire-and-forget tracking call to complete
+    await page.waitForTimeout(1000);
+
+    // Query stats via GraphQL
+    const resp = await page.request.post("/graphql", {
+      data: {
+        query: `{ stats { markerPlacements { spotters } } }`,
+      },
+    });
+    const json = await resp.json();
+    expect(json.data.stats.markerPlacements.spotters).toBeGreaterThanOrEqual(1);
+  });
+
+  test("placements are counted per map", async ({ page }) => {
+    await page.locator(".placement-mode button", { hasText: "Target" }).click();
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    await mapContainer.click({ position: { x: box!.width / 2, y: box!.height / 2 } });
+
+    // Wait for the fire-and-forget tracking call to complete
+    await page.waitForTimeout(1000);
+
+    const resp = await page.request.post("/graphql", {
+      data: {
+        query: `{ stats { placementsByMap { mapId displayName targets } } }`,
+      },
+    });
+    const json = await resp.json();
+    const maps = json.data.stats.placementsByMap;
+    expect(maps.length).toBeGreaterThanOrEqual(1);
+    expect(maps.some((m: { targets: number }) => m.targets >= 1)).toBe(true);
+  });
+
+  test("repeat placements by one tab count once in deduped stats", async ({ page }) => {
+    const query = `{ stats { markerPlacements { spotters } dedupedPlacements { spotters } } }`;
+    const before = (await (await page.request.post("/graphql", { data: { query } })).json()).data
+      .stats;
+
+    await page.locator(".placement-mode button", { hasText: "Spotter" }).click();
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    await mapContainer.click({ position: { x: box!.width / 2, y: box!.height / 2 } });
+    await mapContainer.click({ position: { x: box!.width / 3, y: box!.height / 3 } });
+
+    // Wait for the fire-and-forget tracking calls to complete
     await page.waitForTimeout(1000);
 
-    // Query stats via GraphQL
-    const resp = await page.request.post("/graphql", {
-      data: {
-        query: `{ stats { markerPlacements { spotters } } }`,
-      },
-    });
-    const json = await resp.json();
-    expect(json.data.stats.markerPlacements.spotters).toBeGreaterThanOrEqual(1);
+    const after = (await (await page.request.post("/graphql", { data: { query } })).json()).data
+      .stats;
+    expect(after.markerPlacements.spotters - before.markerPlacements.spotters).toBe(2);
+    expect(after.dedupedPlacements.spotters - before.dedupedPlacements.spotters).toBe(1);
   });
 });
 
@@ -1248,7 +2573,33 @@ test.describe("Theme toggle", () => {
     const accent = await app.evaluate((el) =>
       getComputedStyle(el).getPropertyValue("--accent").trim(),
     );
-    expect(accent).toBe("#c43030");
+    expect(accent).toBe("#d0452b");
+  });
+
+  test("theme preferences recolor the app and map and persist", async ({ page }) => {
+    await page.evaluate(() => localStorage.removeItem("theme"));
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({ position: { x: box!.width * 0.5, y: box!.height * 0.5 } });
+
+    const svg = page.locator(".map-container svg");
+    await expect(svg.locator('circle[fill="#5ab882"]')).toHaveCount(1);
+    await page.locator(".header .faction-toggle button", { hasText: "Colonial" }).click();
+    await expect(svg.locator('circle[fill="#8fb84a"]')).toHaveCount(1);
+
+    await page.locator(".checkbox-row", { hasText: "Colorblind-safe colors" }).click();
+    await expect(svg.locator('circle[fill="#56b4e9"]')).toHaveCount(1);
+    await page.locator('select[aria-label="Theme"]').selectOption("light");
+    const app = page.locator(".app");
+    await expect(app).toHaveClass(/light/);
+    await expect(app).toHaveClass(/colorblind/);
+
+    await page.reload();
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(app).toHaveClass(/colonial light colorblind/);
+    await expect(page.locator('select[aria-label="Theme"]')).toHaveValue("light");
   });
 
   test("clicking Warden switches back to Warden theme", async ({ page }) => {
@@ -1342,6 +2693,94 @@ test.describe("Plan save and load", () => {
     await page.waitForSelector(".app", { timeout: 15_000 });
   });
 
+  test("plan size grows as markers are added", async ({ page }) => {
+    const planPanel = page.locator('.panel:has(h3:text("Plan"))');
+    const size = planPanel.locator(".plan-size");
+    await expect(size).toContainText("Size:");
+    const before = await size.textContent();
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+    await page
+      .locator(".placement-mode button", { hasText: "Spotter" })
+      .click();
+    for (let i = 0; i < 5; i++) {
+      await mapContainer.click({
+        position: { x: box!.width * (0.3 + i * 0.1), y: box!.height * 0.5 },
+      });
+    }
+    await expect(size).not.toHaveText(before!);
+    await expect(size).not.toHaveClass(/too-large/);
+  });
+
+  test("custom map image is calibrated and saved with the plan", async ({
+    page,
+  }) => {
+    const imageUrl = "https://maps.example.com/deadlands-rustard.png";
+    await page.route(imageUrl, (route) =>
+      route.fulfill({ status: 200, contentType: "image/png", body: "" }),
+    );
+    let sentCalibration: Record<string, unknown> | null = null;
+    await page.route("**/graphql", async (route) => {
+      const postData = route.request().postData();
+      if (postData && postData.includes("createPlan(")) {
+        sentCalibration = JSON.parse(postData).variables.input.calibration;
+      }
+      await route.continue();
+    });
+
+    const panel = page.locator('.panel:has(h3:text("Custom map image"))');
+    await panel.locator('input[aria-label="Custom map image URL"]').fill(imageUrl);
+    await panel.locator('input[aria-label="Image width"]').fill("1024");
+    await panel.locator('input[aria-label="Image height"]').fill("888");
+    await panel.locator('input[aria-label="Point 1 image x"]').fill("90");
+    await panel.locator('input[aria-label="Point 1 image y"]').fill("80");
+    await panel.locator('input[aria-label="Point 1 grid reference"]').fill("B2k5");
+    await panel.locator('input[aria-label="Point 2 image x"]').fill("900");
+    await panel.locator('input[aria-label="Point 2 image y"]').fill("80");
+    await panel.locator('input[aria-label="Point 2 grid reference"]').fill("N2k5");
+
+    // Both points on one row can't set the vertical scale
+    await panel.locator("button", { hasText: "Apply" }).click();
+    await expect(panel.locator(".calibration-error")).toContainText("apart");
+
+    await panel.locator('input[aria-label="Point 2 image y"]').fill("780");
+    await panel.locator('input[aria-label="Point 2 grid reference"]').fill("N12k5");
+    await panel.locator("button", { hasText: "Apply" }).click();
+    await expect(panel.locator(".calibration-error")).toHaveCount(0);
+    await expect(page.locator(".map-container img")).toHaveAttribute(
+      "src",
+      imageUrl,
+    );
+
+    const planPanel = page.locator('.panel:has(h3:text("Plan"))');
+    await planPanel.locator("button", { hasText: "Save & Share" }).click();
+    const planUrlInput = planPanel.locator(".plan-url input[readonly]");
+    await expect(planUrlInput).toBeVisible({ timeout: 10_000 });
+    expect(sentCalibration).toMatchObject({ imageUrl, width: 1024, height: 888 });
+
+    // The calibration comes back with the plan
+    await page.goto(new URL(await planUrlInput.inputValue()).pathname);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(page.locator(".map-container img")).toHaveAttribute(
+      "src",
+      imageUrl,
+      { timeout: 10_000 },
+    );
+    const loaded = page.locator('.panel:has(h3:text("Custom map image"))');
+    await expect(
+      loaded.locator('input[aria-label="Point 2 grid reference"]'),
+    ).toHaveValue("N12k5");
+
+    // Back to the stock map
+    await loaded.locator("button", { hasText: "Use stock map" }).click();
+    await expect(page.locator(".map-container img")).toHaveAttribute(
+      "src",
+      /\/static\/images\/maps\//,
+    );
+  });
+
   test("saving a plan and loading it preserves markers", async ({ page }) => {
     const mapContainer = page.locator(".map-container");
     const box = await mapContainer.boundingBox();
@@ -1407,6 +2846,368 @@ test.describe("Plan save and load", () => {
     await expect(page.locator(".coord-tag.gun-tag")).toBeVisible();
     await expect(page.locator(".coord-tag.target-tag")).toBeVisible();
   });
+
+  test("named markers show their names and keep them when saved", async ({
+    page,
+  }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    expect(box).not.toBeNull();
+
+    await page
+      .locator(".placement-mode button", { hasText: "Gun" })
+      .click();
+    await mapContainer.click({
+      position: { x: box!.width * 0.3, y: box!.height * 0.4 },
+    });
+
+    // Select the gun from the firing solution panel and name it
+    await page.locator(".marker-item:has(.gun-coord)").click();
+    const labelInput = page.locator(".marker-label-input");
+    await expect(labelInput).toHaveAttribute("placeholder", "Name this gun");
+    await labelInput.fill("Alpha gun");
+    await labelInput.press("Enter");
+
+    const svg = page.locator(".map-container svg");
+    await expect(svg.locator('text:text("Alpha gun")')).toBeVisible({ timeout: 5000 });
+    await expect(svg.locator('text:text-is("GUN")')).toHaveCount(0);
+    await expect(page.locator(".gun-coord")).toContainText("Alpha gun:");
+
+    const planPanel = page.locator('.panel:has(h3:text("Plan"))');
+    await planPanel.locator("button", { hasText: "Save & Share" }).click();
+    const planUrlInput = planPanel.locator(".plan-url input[readonly]");
+    await expect(planUrlInput).toBeVisible({ timeout: 10_000 });
+    const urlPath = new URL(await planUrlInput.inputValue()).pathname;
+
+    await page.goto(urlPath);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(
+      page.locator('.map-container svg text:text("Alpha gun")'),
+    ).toBeVisible({ timeout: 10_000 });
+  });
+
+  test("second editor of a plan is read-only until taking over", async ({
+    page,
+    browser,
+  }) => {
+    const resp = await page.request.post("/graphql", {
+      data: {
+        query: `mutation { createPlan(input: { name: "Lock test", mapId: "acrithia", weaponIds: [] }) { id editToken } }`,
+      },
+    });
+    const { id, editToken } = (await resp.json()).data.createPlan;
+    // Both tabs belong to the plan's creator, so both hold the edit token
+    const withToken = ([id, token]: string[]) =>
+      localStorage.setItem(`edit_token:${id}`, token);
+    await page.addInitScript(withToken, [id, editToken]);
+
+    await page.goto(`/plan/${id}`);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(page.locator(".lock-banner")).toHaveCount(0);
+
+    // A second browser context has its own session, so it finds the plan locked
+    const other = await browser.newContext();
+    await other.addInitScript(withToken, [id, editToken]);
+    const otherPage = await other.newPage();
+    await otherPage.goto(`/plan/${id}`);
+    await otherPage.waitForSelector(".app", { timeout: 15_000 });
+    const banner = otherPage.locator(".lock-banner");
+    await expect(banner).toContainText("being edited by someone else", {
+      timeout: 10_000,
+    });
+
+    // Clicks don't place markers while read-only
+    const mapContainer = otherPage.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    await mapContainer.click({
+      position: { x: box!.width * 0.5, y: box!.height * 0.5 },
+    });
+    const svg = otherPage.locator(".map-container svg");
+    await expect(svg.locator('text:text("GUN")')).toHaveCount(0);
+
+    await banner.locator("button", { hasText: "Take over" }).click();
+    await expect(banner).toHaveCount(0, { timeout: 10_000 });
+    await mapContainer.click({
+      position: { x: box!.width * 0.5, y: box!.height * 0.5 },
+    });
+    await expect(svg.locator('text:text("GUN")')).toBeVisible({ timeout: 5000 });
+    await other.close();
+  });
+
+  test("someone else's plan is saved as a copy", async ({ page }) => {
+    const resp = await page.request.post("/graphql", {
+      data: {
+        query: `mutation { createPlan(input: { name: "Not mine", mapId: "acrithia", weaponIds: [] }) { id } }`,
+      },
+    });
+    const id = (await resp.json()).data.createPlan.id;
+
+    // No edit token in this browser
+    await page.goto(`/plan/${id}`);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(page.locator(".plan-copy-note")).toBeVisible({
+      timeout: 10_000,
+    });
+    await expect(page.locator(".lock-banner")).toHaveCount(0);
+
+    await page.click('button:has-text("Save & Share")');
+    const url = page.locator(".plan-url input");
+    await expect(url).toHaveValue(/\/plan\/[0-9a-f-]+$/, { timeout: 10_000 });
+    expect(await url.inputValue()).not.toContain(id);
+    await expect(page.locator(".plan-copy-note")).toHaveCount(0);
+
+    // The copy is ours: saving again updates it in place
+    const copyUrl = await url.inputValue();
+    await page.click('button:has-text("Save & Share")');
+    await expect(page.locator(".save-error")).toHaveCount(0);
+    await expect(url).toHaveValue(copyUrl);
+  });
+
+  test("saving over a plan someone else updated offers a merge", async ({
+    page,
+  }) => {
+    const input = `{ name: "Merge test", mapId: "acrithia", weaponIds: [] }`;
+    const resp = await page.request.post("/graphql", {
+      data: { query: `mutation { createPlan(input: ${input}) { id editToken } }` },
+    });
+    const { id, editToken } = (await resp.json()).data.createPlan;
+    await page.addInitScript(
+      ([id, token]) => localStorage.setItem(`edit_token:${id}`, token),
+      [id, editToken],
+    );
+
+    await page.goto(`/plan/${id}`);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+
+    // Someone else saves first, moving the plan past the version we loaded
+    await page.request.post("/graphql", {
+      data: {
+        query: `mutation { updatePlan(id: "${id}", editToken: "${editToken}", baseVersion: 0, input: { name: "Theirs", mapId: "acrithia", weaponIds: [], targetPositions: [{ x: 900, y: 900 }] }) { plan { version } } }`,
+      },
+    });
+
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    await mapContainer.click({
+      position: { x: box!.width * 0.5, y: box!.height * 0.5 },
+    });
+    await page.click('button:has-text("Save & Share")');
+
+    const dialog = page.locator(".merge-dialog");
+    await expect(dialog).toBeVisible({ timeout: 10_000 });
+    await expect(dialog).toContainText("Targets: 0 only yours, 1 only theirs");
+    await dialog.locator("button", { hasText: "Merge markers" }).click();
+    await expect(dialog).toHaveCount(0);
+    await expect(page.locator(".plan-url input")).toHaveValue(
+      new RegExp(`/plan/${id}$`),
+      { timeout: 10_000 },
+    );
+
+    const saved = await page.request.post("/graphql", {
+      data: {
+        query: `{ plan(id: "${id}") { version gunPositions { x } targetPositions { x } } }`,
+      },
+    });
+    const plan = (await saved.json()).data.plan;
+    expect(plan.version).toBe(2);
+    expect(plan.gunPositions).toHaveLength(1);
+    expect(plan.targetPositions).toHaveLength(1);
+  });
+
+  test("forks someone else's plan into a new one", async ({ page }) => {
+    const resp = await page.request.post("/graphql", {
+      data: {
+        query: `mutation { createPlan(input: { name: "Bridge defense", mapId: "acrithia", weaponIds: [], targetPositions: [{ x: 900, y: 900 }] }) { id } }`,
+      },
+    });
+    const id = (await resp.json()).data.createPlan.id;
+
+    await page.goto(`/plan/${id}`);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(page.locator(".plan-copy-note")).toBeVisible({
+      timeout: 10_000,
+    });
+
+    await page.click('button:has-text("Fork")');
+    await page.waitForURL((url) => !url.pathname.endsWith(id), {
+      timeout: 10_000,
+    });
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(page.locator('input[aria-label="Plan name"]')).toHaveValue(
+      "Bridge defense (copy)",
+      { timeout: 10_000 },
+    );
+    // The copy is ours, and it kept the original's target
+    await expect(page.locator(".plan-copy-note")).toHaveCount(0);
+    await expect(page.locator(".coord-tag.target-tag")).toHaveCount(1);
+  });
+
+  test("restores an earlier version from the history", async ({ page }) => {
+    const input = `{ name: "History test", mapId: "acrithia", weaponIds: [] }`;
+    const resp = await page.request.post("/graphql", {
+      data: { query: `mutation { createPlan(input: ${input}) { id editToken } }` },
+    });
+    const { id, editToken } = (await resp.json()).data.createPlan;
+    await page.addInitScript(
+      ([id, token]) => localStorage.setItem(`edit_token:${id}`, token),
+      [id, editToken],
+    );
+
+    await page.goto(`/plan/${id}`);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+
+    // Save once with a gun so version 0 (empty) goes into the history
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    await mapContainer.click({
+      position: { x: box!.width * 0.5, y: box!.height * 0.5 },
+    });
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(1);
+    await page.click('button:has-text("Save & Share")');
+    await expect(page.locator(".plan-url input")).toBeVisible({
+      timeout: 10_000,
+    });
+
+    await page.click('button:has-text("History")');
+    const entries = page.locator(".plan-history-entry");
+    await expect(entries).toHaveCount(1, { timeout: 10_000 });
+    await expect(entries.first()).toContainText("v0");
+    await entries.first().locator("button", { hasText: "Restore" }).click();
+
+    // The empty version comes back as the newest one
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(0, {
+      timeout: 10_000,
+    });
+    await expect(entries).toHaveCount(2, { timeout: 10_000 });
+    const saved = await page.request.post("/graphql", {
+      data: { query: `{ plan(id: "${id}") { version gunPositions { x } } }` },
+    });
+    const plan = (await saved.json()).data.plan;
+    expect(plan.version).toBe(2);
+    expect(plan.gunPositions).toHaveLength(0);
+  });
+
+  test("short link opens the plan under its id", async ({ page }) => {
+    const resp = await page.request.post("/graphql", {
+      data: {
+        query: `mutation { createPlan(input: { name: "Voice comms", mapId: "acrithia", weaponIds: [] }) { id slug } }`,
+      },
+    });
+    const { id, slug } = (await resp.json()).data.createPlan;
+    expect(slug).toMatch(/^[a-z]+-[a-z]+-[1-9][0-9]$/);
+
+    await page.goto(`/plan/${slug}`);
+    await page.waitForURL((url) => url.pathname === `/plan/${id}`, {
+      timeout: 15_000,
+    });
+    await expect(page.locator('input[aria-label="Plan name"]')).toHaveValue(
+      "Voice comms",
+      { timeout: 10_000 },
+    );
+    const image = await page.request.get(`/plan/${slug}/image.svg`);
+    expect(image.status()).toBe(200);
+  });
+
+  test("read-only link shows the plan without editing controls", async ({
+    page,
+  }) => {
+    const resp = await page.request.post("/graphql", {
+      data: {
+        query: `mutation { createPlan(input: { name: "Final fire plan", mapId: "acrithia", weaponIds: [], gunPositions: [{ x: 800, y: 800 }] }) { id } }`,
+      },
+    });
+    const id = (await resp.json()).data.createPlan.id;
+
+    await page.goto(`/plan/${id}?readonly=1`);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(1, {
+      timeout: 10_000,
+    });
+    await expect(page.locator(".view-only-banner")).toBeVisible();
+    await expect(
+      page.locator(".placement-mode button", { hasText: "Gun" }),
+    ).toHaveCount(0);
+    await expect(page.locator("button", { hasText: "Save & Share" })).toHaveCount(
+      0,
+    );
+    await expect(page.locator('input[aria-label="Plan name"]')).toHaveAttribute(
+      "readonly",
+      "",
+    );
+
+    // Clicking the map doesn't place anything
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    await mapContainer.click({
+      position: { x: box!.width * 0.6, y: box!.height * 0.6 },
+    });
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(1);
+  });
+
+  test("plan tabs keep their own markers", async ({ page }) => {
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({
+      position: { x: box!.width * 0.5, y: box!.height * 0.5 },
+    });
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(1);
+
+    // A new tab starts empty
+    await page.locator(".plan-tab-add").click();
+    await expect(page.locator(".plan-tab")).toHaveCount(2);
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(0);
+    await page.locator('input[aria-label="Plan name"]').fill("Next hex");
+    await expect(page.locator(".plan-tab.active")).toContainText("Next hex");
+
+    // Switching back brings the first plan's gun back
+    await page.locator(".plan-tab-select").first().click();
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(1);
+    await expect(page.locator(".plan-tab").nth(1)).toContainText("Next hex");
+
+    // Closing the empty tab leaves the first one
+    await page.locator(".plan-tab-close").nth(1).click();
+    await expect(page.locator(".plan-tab")).toHaveCount(1);
+    await expect(page.locator(".plan-tabs-save")).toHaveCount(0);
+  });
+
+  test("compares the map with another plan", async ({ page }) => {
+    const resp = await page.request.post("/graphql", {
+      data: {
+        query: `mutation { createPlan(input: { name: "20:00 plan", mapId: "acrithia", weaponIds: [], gunPositions: [{ x: 800, y: 800 }] }) { id } }`,
+      },
+    });
+    const id = (await resp.json()).data.createPlan.id;
+
+    await page.goto(`/plan/${id}`);
+    await page.waitForSelector(".app", { timeout: 15_000 });
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(1, {
+      timeout: 10_000,
+    });
+    // Add a second gun to the loaded plan
+    const mapContainer = page.locator(".map-container");
+    const box = await mapContainer.boundingBox();
+    await page.locator(".placement-mode button", { hasText: "Gun" }).click();
+    await mapContainer.click({
+      position: { x: box!.width * 0.6, y: box!.height * 0.6 },
+    });
+    await expect(page.locator(".coord-tag.gun-tag")).toHaveCount(2);
+
+    await page
+      .locator('input[aria-label="Plan to compare with"]')
+      .fill(`http://localhost/plan/${id}`);
+    await page.locator(".plan-compare-row button", { hasText: "Compare" }).click();
+    const panel = page.locator(".plan-compare");
+    await expect(panel).toContainText('Changes since "20:00 plan"', {
+      timeout: 10_000,
+    });
+    await expect(panel.locator("li")).toHaveText(["Gun 2 added"]);
+    await expect(page.locator(".map-container svg .compare-added")).toHaveCount(1);
+
+    await panel.locator('button[aria-label="Stop comparing"]').click();
+    await expect(panel).toHaveCount(0);
+    await expect(page.locator(".map-container svg .compare-added")).toHaveCount(0);
+  });
 });
 
 test.describe("Error handling", () => {
@@ -1497,9 +3298,52 @@ test.describe("Responsive layout", () => {
       await page.setViewportSize({ width: 375, height: 667 });
     });
 
-    test("sidebar is hidden by default", async ({ page }) => {
+    test("panel sheet is closed by default", async ({ page }) => {
+      const sidebar = page.locator(".sidebar");
+      await expect(sidebar).not.toHaveClass(/open/);
+      await expect(page.locator(".sheet-handle")).toBeInViewport();
+      await expect(sidebar.locator(".panel").first()).not.toBeInViewport();
+    });
+
+    test("sheet handle opens the panels and swipes them full height", async ({
+      page,
+    }) => {
       const sidebar = page.locator(".sidebar");
-      await expect(sidebar).not.toBeInViewport();
+      const handle = page.locator(".sheet-handle");
+      await handle.click();
+      await expect(sidebar).toHaveClass(/open/);
+      await expect(sidebar.locator(".panel").first()).toBeInViewport();
+      const half = (await sidebar.boundingBox())!.height;
+
+      // Swipe the handle up
+      const box = (await handle.boundingBox())!;
+      const x = box.x + box.width / 2;
+      const y = box.y + box.height / 2;
+      await handle.dispatchEvent("touchstart", {
+        touches: [{ identifier: 0, clientX: x, clientY: y }],
+      });
+      await handle.dispatchEvent("touchend", {
+        changedTouches: [{ identifier: 0, clientX: x, clientY: y - 120 }],
+      });
+      await expect(sidebar).toHaveClass(/full/);
+      await expect
+        .poll(async () => (await sidebar.boundingBox())!.height)
+        .toBeGreaterThan(half);
+
+      await page.locator(".sidebar-toggle").click();
+      await expect(sidebar).not.toHaveClass(/open/);
+    });
+
+    test("placement modes float over the map", async ({ page }) => {
+      const toolbar = page.locator(".placement-mode");
+      const position = await toolbar.evaluate(
+        (el) => getComputedStyle(el).position,
+      );
+      expect(position).toBe("fixed");
+      const bar = (await toolbar.boundingBox())!;
+      const map = (await page.locator(".map-container").boundingBox())!;
+      expect(bar.y).toBeGreaterThan(map.y);
+      expect(bar.y + bar.height).toBeLessThanOrEqual(map.y + map.height);
     });
 
     test("hamburger button is visible", async ({ page }) => {
@@ -1699,7 +3543,7 @@ test.describe("Responsive layout", () => {
 
       // Every placement mode button should be fully within the viewport
       const placementBtns = page.locator(".placement-mode button");
-      for (let i = 0; i < 3; i++) {
+      for (let i = 0; i < 5; i++) {
         const box = await placementBtns.nth(i).boundingBox();
         expect(box).toBeTruthy();
         expect(box!.x).toBeGreaterThanOrEqual(0);
@@ -1716,4 +3560,40 @@ test.describe("Responsive layout", () => {
       }
     });
   });
+
+  test("stats page charts gun placements", async ({ page }) => {
+    await page.request.post("/graphql", {
+      data: {
+        query: `mutation { trackGunPlacement(weaponSlug: "cremari-mortar") }`,
+      },
+    });
+
+    await page.goto("/stats");
+    await expect(page.locator(".header h1")).toHaveText("Planner Statistics");
+    await expect(page.locator(".admin-stats")).toContainText("Saved plans");
+    await expect(
+      page.locator(".stat-row").filter({ hasText: "Cremari Mortar" }),
+    ).toBeVisible();
+
+    // The war picker always offers every war combined
+    await expect(
+      page.getByLabel("War").locator("option").first(),
+    ).toHaveText("All wars");
+  });
+
+  test("admin page asks for a token and rejects a wrong one", async ({
+    page,
+  }) => {
+    await page.goto("/admin");
+    await expect(page.locator(".header h1")).toHaveText("Instance Admin");
+
+    const tokenInput = page.getByLabel("Admin token");
+    await tokenInput.fill("definitely-not-the-token");
+    await page.getByRole("button", { name: "Log in" }).click();
+
+    // Either the token is wrong or no ADMIN_TOKEN is configured; both keep us logged out
+    await expect(page.getByRole("alert")).toContainText(/admin/i);
+    await expect(tokenInput).toBeVisible();
+    await expect(page.locator(".admin-stats")).toHaveCount(0);
+  });
 });